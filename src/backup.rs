@@ -0,0 +1,288 @@
+//! Backup and restore of a whole tools directory
+//!
+//! A backup is a tar.gz of TOOLS_DIR: every tool directory (with its
+//! version history), a snapshot of the memory database (taken with `VACUUM
+//! INTO`, so it is consistent while the server writes), service
+//! definitions, plus a `skillz-backup.json` metadata file. Left out are
+//! what can be rebuilt or isn't portable: the tools' `env/` directories
+//! (venvs, node_modules, gems), the WASM build cache, `exports/` and
+//! service volume `backups/`.
+//!
+//! A restore unpacks next to TOOLS_DIR and swaps the directories, so a
+//! failed restore leaves the current tools untouched. The replaced
+//! directory is kept as `<TOOLS_DIR>.before-restore-<time>`. Script
+//! dependencies are reinstalled and WASM tools recompiled from `src.rs`.
+//! Stop the server before restoring.
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::builder::BUILD_CACHE_DIR;
+use crate::exporter::EXPORTS_DIR;
+use crate::memory::{self, MEMORY_DB_FILE};
+use crate::registry::{ToolRegistry, ToolType};
+use crate::runtime;
+use crate::services::BACKUPS_DIR;
+
+/// Version of the backup layout, bumped on incompatible changes
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+/// Name of the metadata file at the root of a backup
+pub const BACKUP_METADATA_FILE: &str = "skillz-backup.json";
+
+/// Top-level entries of TOOLS_DIR left out of backups
+const SKIPPED_TOP_LEVEL: &[&str] = &[
+    BUILD_CACHE_DIR,
+    EXPORTS_DIR,
+    BACKUPS_DIR,
+    BACKUP_METADATA_FILE,
+];
+
+/// Metadata stored in every backup
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupMetadata {
+    pub format_version: u32,
+    pub skillz_version: String,
+    pub created_at: String,
+    /// Names of the tools in the backup
+    pub tools: Vec<String>,
+}
+
+/// What a restore did besides swapping the directories
+#[derive(Debug, Default)]
+pub struct RestoreSummary {
+    pub metadata: Option<BackupMetadata>,
+    /// Where the replaced tools directory was moved, if there was one
+    pub previous_dir: Option<PathBuf>,
+    /// WASM tools recompiled from src.rs
+    pub recompiled: Vec<String>,
+    /// Script tools whose dependencies were reinstalled
+    pub reinstalled: Vec<String>,
+    /// "tool: what went wrong" for tools that need attention
+    pub warnings: Vec<String>,
+}
+
+/// Backup and restore of TOOLS_DIR
+pub struct Backup;
+
+impl Backup {
+    /// Write a backup of the registry's tools directory to `out`, returning
+    /// its metadata
+    pub fn create(registry: &ToolRegistry, out: &Path) -> Result<BackupMetadata> {
+        let storage_dir = registry.storage_dir();
+        let mut tools: Vec<String> = registry
+            .list_tools()
+            .iter()
+            .map(|t| t.name().to_string())
+            .collect();
+        tools.sort();
+        let metadata = BackupMetadata {
+            format_version: BACKUP_FORMAT_VERSION,
+            skillz_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: crate::registry::chrono_now(),
+            tools,
+        };
+
+        if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        // Written to a temporary file first so a failed backup doesn't
+        // replace a good one
+        let partial = out.with_extension("partial");
+        let file = fs::File::create(&partial)
+            .with_context(|| format!("Failed to create {}", partial.display()))?;
+        let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        archive.follow_symlinks(false);
+
+        let json = serde_json::to_vec_pretty(&metadata)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(json.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_cksum();
+        archive.append_data(&mut header, BACKUP_METADATA_FILE, json.as_slice())?;
+
+        let skip = [out.to_path_buf(), partial.clone()];
+        if storage_dir.is_dir() {
+            append_dir(&mut archive, storage_dir, Path::new(""), &skip)?;
+        }
+        let db_path = storage_dir.join(MEMORY_DB_FILE);
+        if db_path.is_file() {
+            let snapshot_dir = tempfile::tempdir()?;
+            let snapshot = snapshot_dir.path().join(MEMORY_DB_FILE);
+            memory::snapshot_database(&db_path, &snapshot)?;
+            archive.append_path_with_name(&snapshot, MEMORY_DB_FILE)?;
+        }
+        archive.into_inner()?.finish()?;
+        fs::rename(&partial, out).with_context(|| format!("Failed to write {}", out.display()))?;
+        Ok(metadata)
+    }
+
+    /// Replace the registry's tools directory with the contents of the
+    /// backup at `archive`, then reload the registry. With `rebuild`, WASM
+    /// tools are recompiled from their src.rs and script dependencies
+    /// reinstalled.
+    pub fn restore(
+        registry: &ToolRegistry,
+        archive: &Path,
+        rebuild: bool,
+    ) -> Result<RestoreSummary> {
+        let storage_dir = registry.storage_dir().clone();
+        let parent = storage_dir
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        fs::create_dir_all(parent)?;
+        let dir_name = storage_dir
+            .file_name()
+            .context("The tools directory has no name")?
+            .to_string_lossy()
+            .to_string();
+
+        // Unpack next to the tools directory so the swap is a rename
+        let staging = tempfile::Builder::new()
+            .prefix(&format!(".{}.restore-", dir_name))
+            .tempdir_in(parent)?;
+        let file = fs::File::open(archive)
+            .with_context(|| format!("Failed to open {}", archive.display()))?;
+        tar::Archive::new(GzDecoder::new(file))
+            .unpack(staging.path())
+            .with_context(|| format!("Failed to unpack {}", archive.display()))?;
+
+        let metadata_path = staging.path().join(BACKUP_METADATA_FILE);
+        let metadata: BackupMetadata = serde_json::from_slice(
+            &fs::read(&metadata_path)
+                .with_context(|| format!("{} is not a skillz backup", archive.display()))?,
+        )
+        .with_context(|| format!("Invalid {} in {}", BACKUP_METADATA_FILE, archive.display()))?;
+        if metadata.format_version == 0 || metadata.format_version > BACKUP_FORMAT_VERSION {
+            anyhow::bail!(
+                "Unsupported backup format version {} (this skillz supports up to {})",
+                metadata.format_version,
+                BACKUP_FORMAT_VERSION
+            );
+        }
+        fs::remove_file(&metadata_path)?;
+
+        let mut summary = RestoreSummary::default();
+        if storage_dir.exists() {
+            let secs = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let previous = parent.join(format!("{}.before-restore-{}", dir_name, secs));
+            fs::rename(&storage_dir, &previous)
+                .with_context(|| format!("Failed to move {} aside", storage_dir.display()))?;
+            summary.previous_dir = Some(previous);
+        }
+        let staged = staging.keep();
+        if let Err(e) = fs::rename(&staged, &storage_dir) {
+            if let Some(previous) = &summary.previous_dir {
+                let _ = fs::rename(previous, &storage_dir);
+            }
+            let _ = fs::remove_dir_all(&staged);
+            return Err(e).with_context(|| format!("Failed to restore {}", storage_dir.display()));
+        }
+        summary.metadata = Some(metadata);
+
+        registry.reload();
+        for error in registry.load_errors() {
+            summary
+                .warnings
+                .push(format!("{}: {}", error.dir, error.message));
+        }
+        if rebuild {
+            rebuild_tools(registry, &mut summary);
+        }
+        Ok(summary)
+    }
+}
+
+/// Recompile WASM tools and reinstall script dependencies after a restore
+fn rebuild_tools(registry: &ToolRegistry, summary: &mut RestoreSummary) {
+    let mut tools = registry.list_tools();
+    tools.sort_by(|a, b| a.name().cmp(b.name()));
+    for tool in tools {
+        let name = tool.name().to_string();
+        match tool.tool_type() {
+            ToolType::Wasm if tool.tool_dir.join("src.rs").exists() => {
+                match registry.rebuild_wasm_module(&name) {
+                    Ok(()) => summary.recompiled.push(name),
+                    Err(e) => summary.warnings.push(format!(
+                        "{}: recompiling failed, keeping the backed-up module ({:#})",
+                        name, e
+                    )),
+                }
+            }
+            ToolType::Script if !tool.manifest.dependencies.is_empty() => {
+                let env_path = registry.tool_env_path(&name);
+                match runtime::install_tool_deps(
+                    &env_path,
+                    tool.manifest.interpreter.as_deref(),
+                    &tool.manifest.dependencies,
+                ) {
+                    Ok(result) if result.success => match registry.mark_deps_installed(&name) {
+                        Ok(()) => summary.reinstalled.push(name),
+                        Err(e) => summary.warnings.push(format!("{}: {:#}", name, e)),
+                    },
+                    Ok(result) => summary.warnings.push(format!(
+                        "{}: dependency installation failed: {}",
+                        name, result.message
+                    )),
+                    Err(e) => summary
+                        .warnings
+                        .push(format!("{}: dependency installation failed: {:#}", name, e)),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Add the contents of `dir` (at `prefix` in the archive), leaving out the
+/// skipped top-level entries, tools' `env/` directories, the memory
+/// database and its WAL files (archived as a snapshot instead) and the
+/// paths in `skip`
+fn append_dir<W: std::io::Write>(
+    archive: &mut tar::Builder<W>,
+    dir: &Path,
+    prefix: &Path,
+    skip: &[PathBuf],
+) -> Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .flatten()
+        .collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    let depth = prefix.components().count();
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        let skipped = match depth {
+            0 => SKIPPED_TOP_LEVEL.contains(&name.as_str()) || name.starts_with(MEMORY_DB_FILE),
+            // A tool's dependency environment
+            1 => name == "env",
+            _ => false,
+        };
+        if skipped || skip.iter().any(|s| s == &path) {
+            continue;
+        }
+
+        let archive_path = prefix.join(&name);
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            archive.append_dir(&archive_path, &path)?;
+            append_dir(archive, &path, &archive_path, skip)?;
+        } else {
+            archive
+                .append_path_with_name(&path, &archive_path)
+                .with_context(|| format!("Failed to add {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,10 @@
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use tempfile::TempDir;
 
@@ -11,6 +15,8 @@ pub struct WasmDependency {
     pub version: String,
     /// Optional features to enable
     pub features: Vec<String>,
+    /// Whether the crate's default features are enabled
+    pub default_features: bool,
 }
 
 impl WasmDependency {
@@ -20,6 +26,7 @@ impl WasmDependency {
             name: name.to_string(),
             version: version.to_string(),
             features: vec![],
+            default_features: true,
         }
     }
 
@@ -29,7 +36,8 @@ impl WasmDependency {
         self
     }
 
-    /// Parse from string format: "name@version" or "name@version[feat1,feat2]"
+    /// Parse from string format: "name@version" or "name@version[feat1,feat2]".
+    /// The pseudo-feature `no-default-features` turns off the crate's defaults.
     pub fn parse(s: &str) -> Option<Self> {
         let s = s.trim();
         if s.is_empty() {
@@ -53,6 +61,12 @@ impl WasmDependency {
             (s, vec![])
         };
 
+        let default_features = !features.iter().any(|f| f == NO_DEFAULT_FEATURES);
+        let features = features
+            .into_iter()
+            .filter(|f| f != NO_DEFAULT_FEATURES)
+            .collect();
+
         // Parse name@version or just name
         if let Some(at_pos) = main_part.find('@') {
             let name = main_part[..at_pos].trim();
@@ -61,6 +75,7 @@ impl WasmDependency {
                 name: name.to_string(),
                 version: version.to_string(),
                 features,
+                default_features,
             })
         } else {
             // Default to latest version
@@ -68,27 +83,465 @@ impl WasmDependency {
                 name: main_part.to_string(),
                 version: "*".to_string(),
                 features,
+                default_features,
             })
         }
     }
 
     /// Convert to Cargo.toml dependency line
     pub fn to_toml_line(&self) -> String {
-        if self.features.is_empty() {
-            format!("{} = \"{}\"", self.name, self.version)
-        } else {
-            format!(
-                "{} = {{ version = \"{}\", features = [{}] }}",
-                self.name,
-                self.version,
+        if self.features.is_empty() && self.default_features {
+            return format!("{} = \"{}\"", self.name, self.version);
+        }
+
+        let mut fields = vec![format!("version = \"{}\"", self.version)];
+        if !self.default_features {
+            fields.push("default-features = false".to_string());
+        }
+        if !self.features.is_empty() {
+            fields.push(format!(
+                "features = [{}]",
                 self.features
                     .iter()
                     .map(|f| format!("\"{}\"", f))
                     .collect::<Vec<_>>()
                     .join(", ")
+            ));
+        }
+        format!("{} = {{ {} }}", self.name, fields.join(", "))
+    }
+}
+
+/// Pseudo-feature in a dependency spec that disables default features
+pub const NO_DEFAULT_FEATURES: &str = "no-default-features";
+
+/// Build settings threaded into a tool's generated Cargo.toml
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildOptions {
+    /// Disable default features of every dependency
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_default_features: bool,
+    /// `[profile.release] opt-level`: 0, 1, 2, 3, "s" or "z"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opt_level: Option<String>,
+    /// `[profile.release] lto`: true, false, "thin", "fat" or "off"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lto: Option<String>,
+}
+
+impl BuildOptions {
+    /// Parse a `{"no_default_features", "opt_level", "lto"}` object.
+    /// Numbers and booleans are accepted for opt_level and lto.
+    pub fn from_value(mut value: serde_json::Value) -> Result<Self> {
+        for field in ["opt_level", "lto"] {
+            if let Some(v) = value.get_mut(field) {
+                if v.is_number() || v.is_boolean() {
+                    *v = serde_json::Value::String(v.to_string());
+                }
+            }
+        }
+        let options: Self = serde_json::from_value(value).map_err(|e| {
+            anyhow::anyhow!(
+                "Invalid build_options (expected {{no_default_features, opt_level, lto}}): {}",
+                e
             )
+        })?;
+
+        if let Some(level) = &options.opt_level {
+            if !matches!(level.as_str(), "0" | "1" | "2" | "3" | "s" | "z") {
+                anyhow::bail!(
+                    "Invalid build_options.opt_level '{}': use 0, 1, 2, 3, \"s\" or \"z\"",
+                    level
+                );
+            }
+        }
+        if let Some(lto) = &options.lto {
+            if !matches!(lto.as_str(), "true" | "false" | "thin" | "fat" | "off") {
+                anyhow::bail!(
+                    "Invalid build_options.lto '{}': use true, false, \"thin\", \"fat\" or \"off\"",
+                    lto
+                );
+            }
+        }
+        Ok(options)
+    }
+
+    /// Whether these are the defaults (nothing to add to Cargo.toml)
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The `[profile.release]` section for these options, if any
+    pub fn profile_toml(&self) -> Option<String> {
+        if self.opt_level.is_none() && self.lto.is_none() {
+            return None;
+        }
+        let mut section = "[profile.release]\n".to_string();
+        if let Some(level) = &self.opt_level {
+            match level.as_str() {
+                "s" | "z" => section.push_str(&format!("opt-level = \"{}\"\n", level)),
+                _ => section.push_str(&format!("opt-level = {}\n", level)),
+            }
+        }
+        if let Some(lto) = &self.lto {
+            match lto.as_str() {
+                "true" | "false" => section.push_str(&format!("lto = {}\n", lto)),
+                _ => section.push_str(&format!("lto = \"{}\"\n", lto)),
+            }
+        }
+        Some(section)
+    }
+
+    /// Apply the options to parsed dependencies
+    fn apply_to(&self, dependencies: &[WasmDependency]) -> Vec<WasmDependency> {
+        dependencies
+            .iter()
+            .cloned()
+            .map(|mut dep| {
+                if self.no_default_features {
+                    dep.default_features = false;
+                }
+                dep
+            })
+            .collect()
+    }
+}
+
+/// A single diagnostic emitted by rustc while compiling a tool
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CompileDiagnostic {
+    /// "error", "warning", "note", ...
+    pub level: String,
+    pub message: String,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+    /// Source text covered by the primary span
+    pub span: Option<String>,
+}
+
+impl fmt::Display for CompileDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(col)) => {
+                write!(f, "{} [{}:{}]: {}", self.level, line, col, self.message)?
+            }
+            (Some(line), None) => write!(f, "{} [{}]: {}", self.level, line, self.message)?,
+            _ => write!(f, "{}: {}", self.level, self.message)?,
+        }
+        if let Some(span) = &self.span {
+            write!(f, "\n    | {}", span.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Compilation failure with parsed diagnostics.
+///
+/// `diagnostics` is empty when cargo's JSON output could not be parsed, in
+/// which case `raw` holds the unprocessed compiler output.
+#[derive(Debug, Clone)]
+pub struct CompileError {
+    pub diagnostics: Vec<CompileDiagnostic>,
+    pub raw: String,
+}
+
+impl CompileError {
+    /// Diagnostics with level "error" (including internal compiler errors)
+    pub fn errors(&self) -> Vec<&CompileDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.level.starts_with("error"))
+            .collect()
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors = self.errors();
+        if errors.is_empty() {
+            return write!(f, "Cargo build failed:\n{}", self.raw);
+        }
+        write!(f, "Cargo build failed with {} error(s):", errors.len())?;
+        for diag in errors {
+            write!(f, "\n{}", diag)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Parse the `compiler-message` entries from `cargo --message-format=json` output.
+///
+/// Lines that are not JSON (or not compiler messages) are ignored. With
+/// `manifest_path` (the tool project's Cargo.toml), only diagnostics for the
+/// tool's own package are kept, so dependency warnings don't drown out the
+/// errors the author cares about.
+pub fn parse_diagnostics(output: &str, manifest_path: Option<&Path>) -> Vec<CompileDiagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for line in output.lines() {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if value.get("reason").and_then(|r| r.as_str()) != Some("compiler-message") {
+            continue;
+        }
+        let package_manifest = value.get("manifest_path").and_then(|m| m.as_str());
+        if let (Some(ours), Some(theirs)) = (manifest_path, package_manifest) {
+            if Path::new(theirs) != ours {
+                continue;
+            }
+        }
+        let Some(message) = value.get("message") else {
+            continue;
+        };
+
+        let level = message
+            .get("level")
+            .and_then(|l| l.as_str())
+            .unwrap_or("error")
+            .to_string();
+        let text = message
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        // Skip the trailing "aborting due to N previous errors" summary
+        if text.starts_with("aborting due to") {
+            continue;
+        }
+
+        let primary = message
+            .get("spans")
+            .and_then(|s| s.as_array())
+            .and_then(|spans| {
+                spans
+                    .iter()
+                    .find(|s| s.get("is_primary").and_then(|p| p.as_bool()) == Some(true))
+                    .or_else(|| spans.first())
+            });
+
+        let (line, column, span) = match primary {
+            Some(span) => (
+                span.get("line_start").and_then(|l| l.as_u64()),
+                span.get("column_start").and_then(|c| c.as_u64()),
+                span.get("text")
+                    .and_then(|t| t.as_array())
+                    .and_then(|t| t.first())
+                    .and_then(|t| t.get("text"))
+                    .and_then(|t| t.as_str())
+                    .map(|t| t.to_string()),
+            ),
+            None => (None, None, None),
+        };
+
+        diagnostics.push(CompileDiagnostic {
+            level,
+            message: text,
+            line,
+            column,
+            span,
+        });
+    }
+
+    diagnostics
+}
+
+/// Compilation target for all WASM tools
+pub const WASM_TARGET: &str = "wasm32-wasip1";
+
+/// Set to `1` to let skillz run `rustup target add` when the WASM target is missing
+pub const AUTO_INSTALL_TARGET_ENV: &str = "SKILLZ_AUTO_INSTALL_TARGET";
+
+/// Set once the WASM target is known to be installed, so rustup is asked only once
+static WASM_TARGET_INSTALLED: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+
+/// How to fix a missing WASM target
+pub fn missing_target_message() -> String {
+    format!(
+        "The {} target is not installed. Run: rustup target add {}",
+        WASM_TARGET, WASM_TARGET
+    )
+}
+
+/// Whether cargo/rustc output says the compilation target isn't installed
+pub fn is_missing_target_error(output: &str) -> bool {
+    output.contains("target may not be installed")
+        || output.contains(&format!("rustup target add {}", WASM_TARGET))
+}
+
+/// Whether `rustup target list --installed` output lists the WASM target
+fn lists_wasm_target(installed: &str) -> bool {
+    installed.lines().any(|line| line.trim() == WASM_TARGET)
+}
+
+/// Directory name (under TOOLS_DIR) holding cached build artifacts
+pub const BUILD_CACHE_DIR: &str = ".build_cache";
+
+/// Result of a (possibly cached) compilation
+#[derive(Debug, Clone)]
+pub struct CompileOutput {
+    pub wasm_path: PathBuf,
+    /// True when the module was served from the build cache
+    pub cached: bool,
+    /// Size change from wasm-opt, if it ran on this build
+    pub optimization: Option<WasmOptReport>,
+}
+
+/// Module size before and after running `wasm-opt`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WasmOptReport {
+    pub before: u64,
+    pub after: u64,
+}
+
+impl WasmOptReport {
+    /// Human-readable summary, e.g. "1.9 MB → 812.4 KB (-58%)"
+    pub fn summary(&self) -> String {
+        let saved = if self.before > 0 {
+            100.0 - (self.after as f64 / self.before as f64 * 100.0)
+        } else {
+            0.0
+        };
+        format!(
+            "{} → {} (-{:.0}%)",
+            format_size(self.before),
+            format_size(self.after),
+            saved
+        )
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    let b = bytes as f64;
+    if b >= MB {
+        format!("{:.1} MB", b / MB)
+    } else if b >= KB {
+        format!("{:.1} KB", b / KB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// Marker line starting each file of a multi-file source bundle
+const FILE_MARKER: &str = "// @skillz-file ";
+
+/// Source code of a WASM tool: either the contents of `src/main.rs`, or
+/// several project files keyed by relative path (e.g. `src/main.rs`,
+/// `src/util.rs`, `src/lib.rs`).
+///
+/// Multi-file sources are stored as a single text bundle (see [`ToolSource::to_text`])
+/// so they can be saved as `src.rs`, diffed, and recompiled like single-file tools.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolSource {
+    Single(String),
+    Files(BTreeMap<String, String>),
+}
+
+impl ToolSource {
+    /// Build a multi-file source, validating paths. `src/main.rs` is required.
+    pub fn from_files(files: BTreeMap<String, String>) -> Result<Self> {
+        for path in files.keys() {
+            validate_source_path(path)?;
+        }
+        if !files.contains_key("src/main.rs") {
+            anyhow::bail!("Multi-file sources must include src/main.rs");
+        }
+        Ok(Self::Files(files))
+    }
+
+    /// Parse saved source text: a bundle produced by `to_text`, or plain `src/main.rs` code
+    pub fn parse(text: &str) -> Result<Self> {
+        if !text.starts_with(FILE_MARKER) {
+            return Ok(Self::Single(text.to_string()));
+        }
+
+        let mut files = BTreeMap::new();
+        let mut current: Option<(String, String)> = None;
+        for line in text.split_inclusive('\n') {
+            if let Some(path) = line.strip_prefix(FILE_MARKER) {
+                if let Some((path, contents)) = current.take() {
+                    files.insert(path, contents);
+                }
+                current = Some((path.trim().to_string(), String::new()));
+            } else if let Some((_, contents)) = current.as_mut() {
+                contents.push_str(line);
+            }
+        }
+        if let Some((path, contents)) = current {
+            files.insert(path, contents);
+        }
+        Self::from_files(files)
+    }
+
+    /// Text form: plain code for a single file, otherwise each file preceded by a marker line
+    pub fn to_text(&self) -> String {
+        match self {
+            Self::Single(code) => code.clone(),
+            Self::Files(files) => {
+                let mut text = String::new();
+                for (path, contents) in files {
+                    text.push_str(FILE_MARKER);
+                    text.push_str(path);
+                    text.push('\n');
+                    text.push_str(contents);
+                    if !contents.ends_with('\n') {
+                        text.push('\n');
+                    }
+                }
+                text
+            }
+        }
+    }
+
+    /// Write the source files into a cargo project
+    pub fn write_to(&self, project_path: &Path) -> Result<()> {
+        match self {
+            Self::Single(code) => {
+                fs::write(project_path.join("src/main.rs"), code)
+                    .context("Failed to write source code")?;
+            }
+            Self::Files(files) => {
+                for (path, contents) in files {
+                    let file_path = project_path.join(path);
+                    if let Some(parent) = file_path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&file_path, contents)
+                        .with_context(|| format!("Failed to write {}", path))?;
+                }
+            }
         }
+        Ok(())
+    }
+}
+
+/// Source files must stay inside the project and leave the manifest to the builder
+fn validate_source_path(path: &str) -> Result<()> {
+    use std::path::Component;
+
+    let relative = Path::new(path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+    if path.is_empty() || !relative || path.contains('\n') {
+        anyhow::bail!(
+            "Invalid source path '{}': must be a relative path inside the project",
+            path
+        );
+    }
+    if matches!(path, "Cargo.toml" | "Cargo.lock") || path.starts_with("target/") {
+        anyhow::bail!(
+            "Invalid source path '{}': Cargo files are generated (declare crates via dependencies)",
+            path
+        );
     }
+    Ok(())
 }
 
 pub struct Builder;
@@ -105,18 +558,72 @@ impl Builder {
         "cargo".to_string()
     }
 
+    /// Make sure the WASM target is installed before building.
+    ///
+    /// Without rustup (e.g. a distro toolchain) nothing can be checked here;
+    /// a missing target is then caught from the build output instead. If
+    /// [`AUTO_INSTALL_TARGET_ENV`] is `1`, a missing target is installed.
+    pub fn ensure_wasm_target() -> Result<()> {
+        use std::sync::atomic::Ordering;
+
+        if WASM_TARGET_INSTALLED.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let Ok(output) = Command::new("rustup")
+            .args(["target", "list", "--installed"])
+            .output()
+        else {
+            return Ok(());
+        };
+        if !output.status.success() {
+            // rustup couldn't tell; leave it to the build output
+            return Ok(());
+        }
+        if lists_wasm_target(&String::from_utf8_lossy(&output.stdout)) {
+            WASM_TARGET_INSTALLED.store(true, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        if std::env::var(AUTO_INSTALL_TARGET_ENV).as_deref() != Ok("1") {
+            anyhow::bail!(
+                "{} (or set {}=1 to install it automatically)",
+                missing_target_message(),
+                AUTO_INSTALL_TARGET_ENV
+            );
+        }
+
+        eprintln!("📦 Installing the {} target...", WASM_TARGET);
+        let output = Command::new("rustup")
+            .args(["target", "add", WASM_TARGET])
+            .output()
+            .context("Failed to run rustup target add")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to install the {} target: {}",
+                WASM_TARGET,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        WASM_TARGET_INSTALLED.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
     /// Compile a WASM tool with optional dependencies
     #[allow(dead_code)]
     pub fn compile_tool(name: &str, code: &str) -> Result<PathBuf> {
-        Self::compile_tool_with_deps(name, code, &[])
+        Self::compile_tool_with_deps(name, code, &[], &BuildOptions::default())
     }
 
-    /// Compile a WASM tool with dependencies
+    /// Compile a WASM tool with dependencies. `code` is `src/main.rs` or a
+    /// multi-file bundle from [`ToolSource::to_text`].
     pub fn compile_tool_with_deps(
         name: &str,
         code: &str,
         dependencies: &[WasmDependency],
+        options: &BuildOptions,
     ) -> Result<PathBuf> {
+        Self::ensure_wasm_target()?;
+
         let temp_dir = TempDir::new()?;
         let package_name = name.replace([' ', '-'], "_").to_lowercase();
         let project_path = temp_dir.path().join(&package_name);
@@ -135,12 +642,11 @@ impl Builder {
             anyhow::bail!("Failed to create cargo project: {}", stderr);
         }
 
-        // Write source code
-        let src_path = project_path.join("src/main.rs");
-        fs::write(&src_path, code).context("Failed to write source code")?;
+        // Write source code (a single main.rs or a multi-file bundle)
+        ToolSource::parse(code)?.write_to(&project_path)?;
 
-        // If there are dependencies, update Cargo.toml
-        if !dependencies.is_empty() {
+        // If there are dependencies or build options, update Cargo.toml
+        if !dependencies.is_empty() || !options.is_default() {
             let cargo_toml_path = project_path.join("Cargo.toml");
             let mut cargo_toml = fs::read_to_string(&cargo_toml_path)?;
 
@@ -150,11 +656,16 @@ impl Builder {
             }
 
             // Add each dependency
-            for dep in dependencies {
+            for dep in options.apply_to(dependencies) {
                 cargo_toml.push_str(&dep.to_toml_line());
                 cargo_toml.push('\n');
             }
 
+            if let Some(profile) = options.profile_toml() {
+                cargo_toml.push('\n');
+                cargo_toml.push_str(&profile);
+            }
+
             fs::write(&cargo_toml_path, cargo_toml)?;
         }
 
@@ -164,18 +675,34 @@ impl Builder {
             .arg("build")
             .arg("--release")
             .arg("--target")
-            .arg("wasm32-wasip1")
+            .arg(WASM_TARGET)
+            .arg("--message-format=json")
             .output()
             .context("Failed to run cargo build")?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
             let stdout = String::from_utf8_lossy(&output.stdout);
-            anyhow::bail!("Cargo build failed:\n{}\n{}", stderr, stdout);
+            if is_missing_target_error(&stderr) || is_missing_target_error(&stdout) {
+                anyhow::bail!(missing_target_message());
+            }
+            // Cargo reports the manifest by its real path (e.g. /private/var on macOS)
+            let manifest = project_path.join("Cargo.toml");
+            let manifest = fs::canonicalize(&manifest).unwrap_or(manifest);
+            let diagnostics = parse_diagnostics(&stdout, Some(&manifest));
+            let raw = if diagnostics.iter().any(|d| d.level.starts_with("error")) {
+                stderr.to_string()
+            } else {
+                // Nothing useful in the JSON stream; fall back to everything we have
+                format!("{}\n{}", stderr, stdout)
+            };
+            return Err(CompileError { diagnostics, raw }.into());
         }
 
         let wasm_path = project_path
-            .join("target/wasm32-wasip1/release")
+            .join("target")
+            .join(WASM_TARGET)
+            .join("release")
             .join(format!("{}.wasm", package_name));
 
         if !wasm_path.exists() {
@@ -189,8 +716,111 @@ impl Builder {
         Ok(output_path)
     }
 
+    /// Compute the build cache key for a piece of code, its dependencies and
+    /// build options. Dependencies are sorted so their declaration order doesn't matter.
+    pub fn cache_key(
+        code: &str,
+        dependencies: &[WasmDependency],
+        options: &BuildOptions,
+    ) -> String {
+        let mut deps: Vec<String> = options
+            .apply_to(dependencies)
+            .iter()
+            .map(|d| d.to_toml_line())
+            .collect();
+        deps.sort();
+
+        let mut hasher = Sha256::new();
+        hasher.update(WASM_TARGET.as_bytes());
+        hasher.update([0]);
+        hasher.update(code.as_bytes());
+        hasher.update([0]);
+        hasher.update(deps.join("\n").as_bytes());
+        if let Some(profile) = options.profile_toml() {
+            hasher.update([0]);
+            hasher.update(profile.as_bytes());
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Compile a WASM tool, reusing a previously built module from `cache_dir`
+    /// when the same code, dependencies and options were compiled before.
+    pub fn compile_tool_cached(
+        name: &str,
+        code: &str,
+        dependencies: &[WasmDependency],
+        options: &BuildOptions,
+        cache_dir: &Path,
+    ) -> Result<CompileOutput> {
+        let package_name = name.replace([' ', '-'], "_").to_lowercase();
+        let output_path = std::env::temp_dir().join(format!("{}.wasm", package_name));
+        let cached_path = cache_dir.join(format!(
+            "{}.wasm",
+            Self::cache_key(code, dependencies, options)
+        ));
+
+        if cached_path.exists() && fs::copy(&cached_path, &output_path).is_ok() {
+            return Ok(CompileOutput {
+                wasm_path: output_path,
+                cached: true,
+                optimization: None,
+            });
+        }
+
+        let wasm_path = Self::compile_tool_with_deps(name, code, dependencies, options)?;
+        let optimization = Self::optimize_wasm(&wasm_path);
+
+        // Caching is best-effort: a failure here must not fail the build.
+        // Write to a temp file first so readers never see a partial module.
+        if fs::create_dir_all(cache_dir).is_ok() {
+            let tmp_path = cached_path.with_extension("wasm.tmp");
+            if fs::copy(&wasm_path, &tmp_path).is_ok() {
+                let _ = fs::rename(&tmp_path, &cached_path);
+            }
+        }
+
+        Ok(CompileOutput {
+            wasm_path,
+            cached: false,
+            optimization,
+        })
+    }
+
+    /// Shrink a module in place with `wasm-opt -Oz` (binaryen).
+    ///
+    /// Returns `None` without touching the file if wasm-opt isn't installed,
+    /// fails, or doesn't make the module any smaller.
+    pub fn optimize_wasm(wasm_path: &Path) -> Option<WasmOptReport> {
+        let before = fs::metadata(wasm_path).ok()?.len();
+        let optimized_path = wasm_path.with_extension("opt.wasm");
+
+        let status = Command::new("wasm-opt")
+            .arg("-Oz")
+            .arg(wasm_path)
+            .arg("-o")
+            .arg(&optimized_path)
+            .output()
+            .ok()?
+            .status;
+
+        let after = fs::metadata(&optimized_path).map(|m| m.len()).unwrap_or(0);
+        if !status.success() || after == 0 || after >= before {
+            let _ = fs::remove_file(&optimized_path);
+            return None;
+        }
+
+        fs::rename(&optimized_path, wasm_path).ok()?;
+        Some(WasmOptReport { before, after })
+    }
+
     /// Parse dependency strings into WasmDependency objects
-    /// Format: "name@version" or "name@version[feat1,feat2]" or just "name"
+    /// Format: "name@version" or "name@version[feat1,feat2]" or just "name";
+    /// add `no-default-features` to the brackets to disable default features
     pub fn parse_dependencies(deps: &[String]) -> Vec<WasmDependency> {
         deps.iter()
             .filter_map(|s| WasmDependency::parse(s))
@@ -240,4 +870,269 @@ mod tests {
             "serde = { version = \"1.0\", features = [\"derive\"] }"
         );
     }
+
+    #[test]
+    fn test_cache_key_ignores_dependency_order() {
+        let a = WasmDependency::new("serde", "1.0");
+        let b = WasmDependency::new("regex", "1.10");
+        let opts = BuildOptions::default();
+        let key1 = Builder::cache_key("fn main() {}", &[a.clone(), b.clone()], &opts);
+        let key2 = Builder::cache_key("fn main() {}", &[b, a.clone()], &opts);
+        assert_eq!(key1, key2);
+        assert_eq!(key1.len(), 64);
+
+        let plain = Builder::cache_key("fn main() {}", std::slice::from_ref(&a), &opts);
+        assert_ne!(
+            plain,
+            Builder::cache_key("fn main() { }", std::slice::from_ref(&a), &opts)
+        );
+        assert_ne!(
+            plain,
+            Builder::cache_key(
+                "fn main() {}",
+                &[a.clone().with_features(vec!["derive".to_string()])],
+                &opts
+            )
+        );
+
+        let size_opts = BuildOptions {
+            opt_level: Some("z".to_string()),
+            ..Default::default()
+        };
+        assert_ne!(
+            plain,
+            Builder::cache_key("fn main() {}", std::slice::from_ref(&a), &size_opts)
+        );
+    }
+
+    #[test]
+    fn test_parse_dependency_no_default_features() {
+        let dep = WasmDependency::parse("serde@1.0[no-default-features,derive]").unwrap();
+        assert!(!dep.default_features);
+        assert_eq!(dep.features, vec!["derive"]);
+        assert_eq!(
+            dep.to_toml_line(),
+            "serde = { version = \"1.0\", default-features = false, features = [\"derive\"] }"
+        );
+        assert!(WasmDependency::parse("serde@1.0").unwrap().default_features);
+    }
+
+    #[test]
+    fn test_build_options_profile() {
+        let opts = BuildOptions::from_value(serde_json::json!({
+            "opt_level": "z",
+            "lto": true
+        }))
+        .unwrap();
+        assert_eq!(
+            opts.profile_toml().unwrap(),
+            "[profile.release]\nopt-level = \"z\"\nlto = true\n"
+        );
+
+        let opts =
+            BuildOptions::from_value(serde_json::json!({"opt_level": 3, "lto": "thin"})).unwrap();
+        assert_eq!(
+            opts.profile_toml().unwrap(),
+            "[profile.release]\nopt-level = 3\nlto = \"thin\"\n"
+        );
+
+        assert!(BuildOptions::default().profile_toml().is_none());
+        assert!(BuildOptions::from_value(serde_json::json!({"opt_level": 4})).is_err());
+        assert!(BuildOptions::from_value(serde_json::json!({"lto": "max"})).is_err());
+        assert!(BuildOptions::from_value(serde_json::json!({"strip": true})).is_err());
+    }
+
+    #[test]
+    fn test_build_options_no_default_features_applies_to_all() {
+        let opts = BuildOptions {
+            no_default_features: true,
+            ..Default::default()
+        };
+        let deps = opts.apply_to(&Builder::parse_dependencies(&[
+            "serde@1.0[derive]".to_string(),
+            "regex".to_string(),
+        ]));
+        assert!(deps.iter().all(|d| !d.default_features));
+    }
+
+    #[test]
+    fn test_tool_source_bundle_round_trip() {
+        let files: BTreeMap<String, String> = [
+            ("src/main.rs", "mod util;\nfn main() { util::run(); }\n"),
+            ("src/util.rs", "pub fn run() {}"),
+        ]
+        .into_iter()
+        .map(|(p, c)| (p.to_string(), c.to_string()))
+        .collect();
+        let source = ToolSource::from_files(files).unwrap();
+
+        let text = source.to_text();
+        assert!(text.starts_with("// @skillz-file src/main.rs\n"));
+        match ToolSource::parse(&text).unwrap() {
+            ToolSource::Files(parsed) => {
+                assert_eq!(
+                    parsed["src/main.rs"],
+                    "mod util;\nfn main() { util::run(); }\n"
+                );
+                // A trailing newline is added to files that lack one
+                assert_eq!(parsed["src/util.rs"], "pub fn run() {}\n");
+            }
+            other => panic!("expected files, got {:?}", other),
+        }
+
+        // Plain code stays a single main.rs
+        assert_eq!(
+            ToolSource::parse("fn main() {}").unwrap(),
+            ToolSource::Single("fn main() {}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tool_source_rejects_bad_paths() {
+        let files = |path: &str| -> BTreeMap<String, String> {
+            [("src/main.rs", "fn main() {}"), (path, "")]
+                .into_iter()
+                .map(|(p, c)| (p.to_string(), c.to_string()))
+                .collect()
+        };
+        assert!(ToolSource::from_files(files("src/util.rs")).is_ok());
+        for bad in [
+            "../evil.rs",
+            "/etc/passwd",
+            "src/../../x.rs",
+            "Cargo.toml",
+            "",
+        ] {
+            assert!(ToolSource::from_files(files(bad)).is_err(), "{}", bad);
+        }
+
+        let no_main: BTreeMap<String, String> = [("src/lib.rs".to_string(), String::new())].into();
+        assert!(ToolSource::from_files(no_main).is_err());
+    }
+
+    #[test]
+    fn test_tool_source_write_to() {
+        let project = TempDir::new().unwrap();
+        fs::create_dir_all(project.path().join("src")).unwrap();
+        let files: BTreeMap<String, String> = [
+            ("src/main.rs".to_string(), "fn main() {}".to_string()),
+            ("src/nested/mod.rs".to_string(), "".to_string()),
+        ]
+        .into();
+        ToolSource::from_files(files)
+            .unwrap()
+            .write_to(project.path())
+            .unwrap();
+        assert!(project.path().join("src/nested/mod.rs").is_file());
+    }
+
+    #[test]
+    fn test_detects_missing_target() {
+        let stderr = "error[E0463]: can't find crate for `std`\n  |\n  = note: the `wasm32-wasip1` target may not be installed\n  = help: consider downloading the target with `rustup target add wasm32-wasip1`";
+        assert!(is_missing_target_error(stderr));
+        assert!(!is_missing_target_error(
+            "error[E0425]: cannot find value `x` in this scope"
+        ));
+        assert_eq!(
+            missing_target_message(),
+            "The wasm32-wasip1 target is not installed. Run: rustup target add wasm32-wasip1"
+        );
+
+        assert!(lists_wasm_target(
+            "wasm32-wasip1\nx86_64-unknown-linux-gnu\n"
+        ));
+        assert!(!lists_wasm_target(
+            "wasm32-wasip2\nx86_64-unknown-linux-gnu\n"
+        ));
+    }
+
+    #[test]
+    fn test_compile_tool_cached_hit() {
+        let cache_dir = TempDir::new().unwrap();
+        let code = "fn main() {}";
+        let opts = BuildOptions::default();
+        let key = Builder::cache_key(code, &[], &opts);
+        fs::write(cache_dir.path().join(format!("{}.wasm", key)), b"\0asm").unwrap();
+
+        let output =
+            Builder::compile_tool_cached("cache_hit_tool", code, &[], &opts, cache_dir.path())
+                .unwrap();
+        assert!(output.cached);
+        assert_eq!(fs::read(&output.wasm_path).unwrap(), b"\0asm");
+        let _ = fs::remove_file(output.wasm_path);
+    }
+
+    #[test]
+    fn test_wasm_opt_report_summary() {
+        let report = WasmOptReport {
+            before: 2 * 1024 * 1024,
+            after: 512 * 1024,
+        };
+        assert_eq!(report.summary(), "2.0 MB → 512.0 KB (-75%)");
+    }
+
+    #[test]
+    fn test_optimize_wasm_skips_invalid_module() {
+        // Not a real module: wasm-opt (if installed) rejects it, and without
+        // wasm-opt there is nothing to do. Either way the file is untouched.
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("broken.wasm");
+        fs::write(&path, b"not wasm").unwrap();
+
+        assert!(Builder::optimize_wasm(&path).is_none());
+        assert_eq!(fs::read(&path).unwrap(), b"not wasm");
+        assert!(!dir.path().join("broken.opt.wasm").exists());
+    }
+
+    #[test]
+    fn test_parse_diagnostics() {
+        let output = concat!(
+            r#"{"reason":"compiler-artifact","package_id":"x"}"#,
+            "\n",
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"cannot find value `x` in this scope","spans":[{"is_primary":true,"line_start":3,"column_start":5,"text":[{"text":"    x + 1"}]}]}}"#,
+            "\n",
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"aborting due to 1 previous error","spans":[]}}"#,
+            "\n",
+            "not json at all\n",
+        );
+        let diags = parse_diagnostics(output, None);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].level, "error");
+        assert_eq!(diags[0].line, Some(3));
+        assert_eq!(diags[0].column, Some(5));
+        assert_eq!(diags[0].span.as_deref(), Some("    x + 1"));
+    }
+
+    #[test]
+    fn test_parse_diagnostics_keeps_the_tools_package() {
+        let output = concat!(
+            r#"{"reason":"compiler-message","manifest_path":"/home/u/.cargo/registry/src/dep-1.0.0/Cargo.toml","message":{"level":"warning","message":"unused import","spans":[]}}"#,
+            "\n",
+            r#"{"reason":"compiler-message","manifest_path":"/tmp/build/tool/Cargo.toml","message":{"level":"error","message":"mismatched types","spans":[]}}"#,
+            "\n",
+        );
+        let diags = parse_diagnostics(output, Some(Path::new("/tmp/build/tool/Cargo.toml")));
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].message, "mismatched types");
+        assert_eq!(parse_diagnostics(output, None).len(), 2);
+    }
+
+    #[test]
+    fn test_compile_error_falls_back_to_raw() {
+        let err = CompileError {
+            diagnostics: vec![],
+            raw: "linker exploded".to_string(),
+        };
+        assert!(err.to_string().contains("linker exploded"));
+
+        let err = CompileError {
+            diagnostics: parse_diagnostics(
+                r#"{"reason":"compiler-message","message":{"level":"error","message":"expected `;`","spans":[]}}"#,
+                None,
+            ),
+            raw: String::new(),
+        };
+        assert_eq!(err.errors().len(), 1);
+        assert!(err.to_string().contains("error: expected `;`"));
+    }
 }
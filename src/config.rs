@@ -6,6 +6,108 @@ use std::path::PathBuf;
 pub struct SkillzConfig {
     #[serde(default)]
     pub servers: HashMap<String, ServerConfig>,
+    #[serde(default)]
+    pub versions: VersionsConfig,
+    #[serde(default)]
+    pub execution: ExecutionConfig,
+    #[serde(default)]
+    pub memory: MemoryConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub services: ServicesConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ServicesConfig {
+    /// Stop (not remove) services no tool has used for this many seconds
+    /// (0 = never, the default). Services defined with keep_alive are
+    /// left running.
+    #[serde(default)]
+    pub idle_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LoggingConfig {
+    /// Minimum level of logs forwarded to the MCP client ("debug", "info",
+    /// "warning", "error"...; default: info). `--log-level` overrides it.
+    #[serde(default)]
+    pub level: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryConfig {
+    /// Seconds between sweeps of expired memory entries (0 = only on startup)
+    #[serde(default = "default_cleanup_interval_secs")]
+    pub cleanup_interval_secs: u64,
+    /// Shell command that reads text on stdin and prints its embedding as a
+    /// JSON array; enables semantic search
+    #[serde(default)]
+    pub embedding_command: Option<String>,
+    /// Ask the MCP client's model for embeddings via sampling
+    /// (used only when no embedding_command is set)
+    #[serde(default)]
+    pub embed_with_sampling: bool,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            cleanup_interval_secs: default_cleanup_interval_secs(),
+            embedding_command: None,
+            embed_with_sampling: false,
+        }
+    }
+}
+
+fn default_cleanup_interval_secs() -> u64 {
+    crate::memory::DEFAULT_CLEANUP_INTERVAL_SECS
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionConfig {
+    /// Maximum number of tools running at once (0 = unlimited, default: CPU count)
+    #[serde(default = "default_max_concurrent_tools")]
+    pub max_concurrent_tools: usize,
+    /// Seconds to memoize results of idempotentHint tools (0 = disabled)
+    #[serde(default = "default_result_cache_ttl_secs")]
+    pub result_cache_ttl_secs: u64,
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_tools: default_max_concurrent_tools(),
+            result_cache_ttl_secs: default_result_cache_ttl_secs(),
+        }
+    }
+}
+
+fn default_result_cache_ttl_secs() -> u64 {
+    crate::memory::DEFAULT_RESULT_CACHE_TTL_SECS
+}
+
+fn default_max_concurrent_tools() -> usize {
+    crate::runtime::default_max_concurrency()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionsConfig {
+    /// Number of version backups kept per tool (0 = keep all)
+    #[serde(default = "default_max_versions")]
+    pub max_versions: usize,
+}
+
+impl Default for VersionsConfig {
+    fn default() -> Self {
+        Self {
+            max_versions: default_max_versions(),
+        }
+    }
+}
+
+fn default_max_versions() -> usize {
+    crate::registry::DEFAULT_MAX_VERSIONS
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
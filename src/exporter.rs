@@ -0,0 +1,452 @@
+//! Tool exporter - Package a tool as a portable .skillz archive (tar.gz)
+//!
+//! A package contains only what is needed to run the tool elsewhere:
+//! `manifest.json`, the script (plus its dependency lockfile, if any) or
+//! `src.rs` + compiled module, and a `skillz-package.json` metadata file.
+//! Virtual environments (`env/`), version backups (`versions/`) and
+//! environment variable values are left out.
+//!
+//! Tools can also be shared as GitHub gists ([`GistSharer`]), which
+//! `import_tool` reads back with `gist:ID`.
+
+use anyhow::{Context, Result};
+use base64::Engine as _;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::registry::{ToolConfig, ToolManifest, ToolRegistry, ToolType};
+use crate::runtime;
+
+/// Version of the package layout, bumped on incompatible changes
+pub const PACKAGE_FORMAT_VERSION: u32 = 1;
+/// File extension of exported packages
+pub const PACKAGE_EXTENSION: &str = "skillz";
+/// Directory under TOOLS_DIR where packages are written
+pub const EXPORTS_DIR: &str = "exports";
+/// Name of the metadata file inside a package
+pub const METADATA_FILE: &str = "skillz-package.json";
+/// Largest package returned inline as base64
+pub const INLINE_LIMIT: usize = 64 * 1024;
+
+/// Placeholder for environment variable values stripped from exported manifests
+const REDACTED: &str = "";
+
+/// Metadata stored in every package
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PackageMetadata {
+    pub format_version: u32,
+    pub skillz_version: String,
+    pub tool_name: String,
+    pub tool_type: ToolType,
+    pub tool_version: String,
+    /// Script dependencies (pip/npm/gem) to install on import
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// Rust crate dependencies of a WASM tool
+    #[serde(default)]
+    pub wasm_dependencies: Vec<String>,
+    /// Environment variables the tool expects; values are not exported
+    #[serde(default)]
+    pub required_env: Vec<String>,
+}
+
+/// Tool exporter
+pub struct Exporter;
+
+impl Exporter {
+    /// Build the tar.gz package for a tool
+    pub fn package(registry: &ToolRegistry, name: &str) -> Result<Vec<u8>> {
+        let tool = registry
+            .get_tool(name)
+            .with_context(|| format!("Tool '{}' not found", name))?;
+
+        let (manifest, required_env) = export_manifest(&tool.manifest);
+        let metadata = PackageMetadata {
+            format_version: PACKAGE_FORMAT_VERSION,
+            skillz_version: env!("CARGO_PKG_VERSION").to_string(),
+            tool_name: manifest.name.clone(),
+            tool_type: manifest.tool_type.clone(),
+            tool_version: manifest.version.clone(),
+            dependencies: manifest.dependencies.clone(),
+            wasm_dependencies: manifest.wasm_dependencies.clone(),
+            required_env,
+        };
+
+        let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        append_bytes(
+            &mut archive,
+            name,
+            "manifest.json",
+            serde_json::to_string_pretty(&manifest)?.as_bytes(),
+        )?;
+        append_bytes(
+            &mut archive,
+            name,
+            METADATA_FILE,
+            serde_json::to_string_pretty(&metadata)?.as_bytes(),
+        )?;
+        for path in tool_files(&tool) {
+            let file_name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .context("Invalid tool file name")?;
+            let bytes = fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+            append_bytes(&mut archive, name, file_name, &bytes)?;
+        }
+
+        Ok(archive.into_inner()?.finish()?)
+    }
+
+    /// Write the package for a tool to `dest_dir/<name>-<version>.skillz`
+    pub fn export_to_dir(registry: &ToolRegistry, name: &str, dest_dir: &Path) -> Result<PathBuf> {
+        let bytes = Self::package(registry, name)?;
+        let version = registry
+            .get_tool(name)
+            .map(|t| t.manifest.version)
+            .unwrap_or_default();
+
+        fs::create_dir_all(dest_dir)?;
+        let path = dest_dir.join(format!("{}-{}.{}", name, version, PACKAGE_EXTENSION));
+        fs::write(&path, bytes)?;
+        Ok(path)
+    }
+
+    /// Base64-encode a package for returning inline
+    pub fn encode(bytes: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    }
+}
+
+/// Copy of the manifest safe to share: environment variable values are
+/// removed (a script tool's become `${KEY}`, read from the environment of
+/// whoever runs it; bare `${VAR}` references are kept), as is this
+/// installation's bookkeeping (where the tool was imported from or shared
+/// to). Returns the manifest and the names of the stripped variables.
+fn export_manifest(manifest: &ToolManifest) -> (ToolManifest, Vec<String>) {
+    let mut manifest = manifest.clone();
+    manifest.disabled = false;
+    manifest.imported_from = None;
+    manifest.shared_gist = None;
+
+    let mut required_env = Vec::new();
+    if let Some(server) = manifest.mcp_server.as_mut() {
+        for (key, value) in server.env.iter_mut() {
+            required_env.push(key.clone());
+            *value = REDACTED.to_string();
+        }
+    }
+    for (key, value) in manifest.env.iter_mut() {
+        if !is_env_reference(value) {
+            required_env.push(key.clone());
+            *value = format!("${{{}}}", key);
+        }
+    }
+    required_env.sort();
+    required_env.dedup();
+    (manifest, required_env)
+}
+
+/// Whether a value is a single `${VAR}` reference, resolved from the
+/// environment of whoever runs the tool rather than holding a value itself
+fn is_env_reference(value: &str) -> bool {
+    value
+        .strip_prefix("${")
+        .and_then(|rest| rest.strip_suffix('}'))
+        .is_some_and(|name| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// Files (besides the manifest) that make up a tool
+fn tool_files(tool: &ToolConfig) -> Vec<PathBuf> {
+    let candidates = match tool.tool_type() {
+        ToolType::Script => vec![
+            tool.script_path.clone(),
+            tool.tool_dir.join(runtime::PYTHON_LOCKFILE),
+            tool.tool_dir.join(runtime::NODE_LOCKFILE),
+        ],
+        ToolType::Wasm => vec![tool.wasm_path.clone(), tool.tool_dir.join("src.rs")],
+        ToolType::Pipeline | ToolType::Mcp => Vec::new(),
+    };
+    candidates.into_iter().filter(|p| p.is_file()).collect()
+}
+
+/// Base URL of the GitHub API
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// Seconds to wait for a GitHub API response
+const GITHUB_API_TIMEOUT_SECS: u64 = 30;
+
+/// A tool shared as a gist
+#[derive(Debug, Clone)]
+pub struct SharedGist {
+    pub id: String,
+    /// Page of the gist on GitHub
+    pub url: String,
+    /// Whether an existing gist was updated rather than a new one created
+    pub updated: bool,
+    /// Names of the files in the gist
+    pub files: Vec<String>,
+    /// Environment variables the tool expects; their values weren't shared
+    pub required_env: Vec<String>,
+}
+
+/// Files of a tool as shared in a gist (name -> contents): the shareable
+/// manifest plus the script (and lockfile) or `src.rs`. Gists hold text
+/// only, so a WASM tool's compiled module is left out; importers rebuild it
+/// from `src.rs`. Also returns the names of the stripped variables.
+pub fn gist_files(tool: &ToolConfig) -> Result<(BTreeMap<String, String>, Vec<String>)> {
+    let (manifest, required_env) = export_manifest(&tool.manifest);
+    let mut files = BTreeMap::new();
+    files.insert(
+        "manifest.json".to_string(),
+        serde_json::to_string_pretty(&manifest)?,
+    );
+    for path in tool_files(tool) {
+        if path == tool.wasm_path {
+            continue;
+        }
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .context("Invalid tool file name")?;
+        let contents = String::from_utf8(fs::read(&path)?)
+            .with_context(|| format!("{} isn't text, so it can't go in a gist", file_name))?;
+        files.insert(file_name.to_string(), contents);
+    }
+    Ok((files, required_env))
+}
+
+/// Publishes tools as GitHub gists with a token that has the `gist` scope
+pub struct GistSharer {
+    api_url: String,
+    token: String,
+}
+
+impl GistSharer {
+    pub fn new(token: String) -> Self {
+        Self {
+            api_url: GITHUB_API_URL.to_string(),
+            token,
+        }
+    }
+
+    /// Talk to another GitHub API endpoint (GitHub Enterprise, or a test server)
+    #[allow(dead_code)]
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Publish a tool's [`gist_files`]. The gist recorded in its manifest
+    /// is updated in place (dropping files the tool no longer has) unless
+    /// `new_gist`; otherwise a new gist is created, secret unless `public`,
+    /// and recorded for next time.
+    pub async fn share(
+        &self,
+        registry: &ToolRegistry,
+        name: &str,
+        public: bool,
+        new_gist: bool,
+    ) -> Result<SharedGist> {
+        let tool = registry
+            .get_tool(name)
+            .with_context(|| format!("Tool '{}' not found", name))?;
+        let (files, required_env) = gist_files(&tool)?;
+
+        let mut payload_files: serde_json::Map<String, serde_json::Value> = files
+            .iter()
+            .map(|(name, content)| (name.clone(), serde_json::json!({ "content": content })))
+            .collect();
+        let description = format!(
+            "{} - {} (skillz {:?} tool v{})",
+            tool.name(),
+            tool.description(),
+            tool.tool_type(),
+            tool.manifest.version
+        );
+
+        let existing = tool.manifest.shared_gist.clone().filter(|_| !new_gist);
+        let response = match &existing {
+            Some(id) => {
+                // Files left over from an earlier share are deleted by nulling them
+                let current = self
+                    .request(reqwest::Method::GET, &format!("/gists/{}", id), None)
+                    .await
+                    .map_err(|e| gist_not_found_hint(e, id))?;
+                if let Some(old_files) = current.get("files").and_then(|f| f.as_object()) {
+                    for old in old_files.keys() {
+                        if !files.contains_key(old) {
+                            payload_files.insert(old.clone(), serde_json::Value::Null);
+                        }
+                    }
+                }
+                let body = serde_json::json!({
+                    "description": description,
+                    "files": payload_files,
+                });
+                self.request(
+                    reqwest::Method::PATCH,
+                    &format!("/gists/{}", id),
+                    Some(body),
+                )
+                .await
+                .map_err(|e| gist_not_found_hint(e, id))?
+            }
+            None => {
+                let body = serde_json::json!({
+                    "description": description,
+                    "public": public,
+                    "files": payload_files,
+                });
+                self.request(reqwest::Method::POST, "/gists", Some(body))
+                    .await?
+            }
+        };
+
+        let id = response
+            .get("id")
+            .and_then(|v| v.as_str())
+            .context("GitHub API response has no gist id")?
+            .to_string();
+        let url = response
+            .get("html_url")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("https://gist.github.com/{}", id));
+
+        if tool.manifest.shared_gist.as_deref() != Some(id.as_str()) {
+            let mut manifest = tool.manifest.clone();
+            manifest.shared_gist = Some(id.clone());
+            registry.update_manifest(name, manifest)?;
+        }
+
+        Ok(SharedGist {
+            id,
+            url,
+            updated: existing.is_some(),
+            files: files.into_keys().collect(),
+            required_env,
+        })
+    }
+
+    /// Send a GitHub API request, turning error statuses into errors
+    async fn request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(GITHUB_API_TIMEOUT_SECS))
+            .build()?;
+        let mut request = client
+            .request(method, format!("{}{}", self.api_url, path))
+            .header("Accept", "application/vnd.github+json")
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("User-Agent", concat!("skillz/", env!("CARGO_PKG_VERSION")));
+        if let Some(body) = body {
+            request = request
+                .header("Content-Type", "application/json")
+                .body(serde_json::to_vec(&body)?);
+        }
+        let response = request.send().await.context("GitHub API request failed")?;
+
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        match status.as_u16() {
+            200..=299 => serde_json::from_str(&text).context("Failed to parse GitHub API response"),
+            401 | 403 => anyhow::bail!(
+                "Authentication failed (HTTP {}): the token was rejected or lacks the gist scope",
+                status.as_u16()
+            ),
+            404 => anyhow::bail!("Not found (HTTP 404)"),
+            code => {
+                let message = serde_json::from_str::<serde_json::Value>(&text)
+                    .ok()
+                    .and_then(|v| {
+                        v.get("message")
+                            .and_then(|m| m.as_str())
+                            .map(str::to_string)
+                    })
+                    .unwrap_or(text);
+                anyhow::bail!("GitHub API error (HTTP {}): {}", code, message)
+            }
+        }
+    }
+}
+
+/// Explain a 404 for the gist a tool was shared to
+fn gist_not_found_hint(error: anyhow::Error, id: &str) -> anyhow::Error {
+    if error.to_string().contains("HTTP 404") {
+        anyhow::anyhow!(
+            "Gist {} that this tool was shared to wasn't found (deleted, or owned by another account); share it again with new_gist=true",
+            id
+        )
+    } else {
+        error
+    }
+}
+
+/// Add a file to the archive under `<tool>/<file>`
+fn append_bytes<W: std::io::Write>(
+    archive: &mut tar::Builder<W>,
+    tool_name: &str,
+    file_name: &str,
+    bytes: &[u8],
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(0);
+    header.set_cksum();
+    archive.append_data(&mut header, format!("{}/{}", tool_name, file_name), bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::McpServerConfig;
+
+    #[test]
+    fn test_export_manifest_strips_env_values() {
+        let mut manifest =
+            ToolManifest::new("remote".to_string(), "Remote".to_string(), ToolType::Mcp);
+        manifest.mcp_server = Some(McpServerConfig {
+            command: "npx".to_string(),
+            args: vec![],
+            env: [("API_KEY".to_string(), "secret".to_string())].into(),
+        });
+
+        let (exported, required_env) = export_manifest(&manifest);
+        assert_eq!(required_env, vec!["API_KEY"]);
+        assert_eq!(exported.mcp_server.unwrap().env["API_KEY"], "");
+        // The original is untouched
+        assert_eq!(manifest.mcp_server.unwrap().env["API_KEY"], "secret");
+    }
+
+    #[test]
+    fn test_export_manifest_strips_script_env_values() {
+        let mut manifest =
+            ToolManifest::new("fetch".to_string(), "Fetch".to_string(), ToolType::Script);
+        manifest.env = [
+            ("API_KEY".to_string(), "sk-live-123".to_string()),
+            ("DATA_DIR".to_string(), "${HOME}/data".to_string()),
+            ("TOKEN".to_string(), "${FETCH_TOKEN}".to_string()),
+        ]
+        .into();
+
+        let (exported, required_env) = export_manifest(&manifest);
+        assert_eq!(required_env, vec!["API_KEY", "DATA_DIR"]);
+        assert_eq!(exported.env["API_KEY"], "${API_KEY}");
+        assert_eq!(exported.env["DATA_DIR"], "${DATA_DIR}");
+        // A bare reference holds no value of its own
+        assert_eq!(exported.env["TOKEN"], "${FETCH_TOKEN}");
+        assert_eq!(manifest.env["API_KEY"], "sk-live-123");
+    }
+}
@@ -3,14 +3,19 @@
 //! This crate provides a Model Context Protocol (MCP) server that allows
 //! dynamic tool creation using WebAssembly and script-based tools.
 
+pub mod backup;
 pub mod builder;
 pub mod client;
 pub mod config;
+pub mod exporter;
 pub mod importer;
+pub mod logging;
 pub mod memory;
 pub mod pipeline;
 pub mod prompts;
 pub mod registry;
 pub mod runtime;
+pub mod schedule;
 pub mod services;
+pub mod tool_index;
 pub mod watcher;
@@ -0,0 +1,268 @@
+//! Log level filtering for messages forwarded to the MCP client.
+//!
+//! Script and server logs go out as `notifications/message` only when they
+//! are at or above the current threshold: `--log-level`, else `[logging]
+//! level` in skillz.toml, changed by the client with `logging/setLevel`.
+//!
+//! Some clients send `setLevel` right after the `initialize` response,
+//! before their `initialized` notification. rmcp treats anything but that
+//! notification as a failed handshake, so [`DeferEarlyRequests`] holds such
+//! messages back and delivers them once the client is initialized.
+
+use rmcp::model::{ClientJsonRpcMessage, ClientNotification, ClientRequest, LoggingLevel};
+use rmcp::service::{RoleServer, RxJsonRpcMessage, TxJsonRpcMessage};
+use rmcp::transport::{IntoTransport, Transport};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+/// Level used when neither `--log-level` nor the config sets one
+pub const DEFAULT_LOG_LEVEL: LoggingLevel = LoggingLevel::Info;
+
+const LEVELS: [LoggingLevel; 8] = [
+    LoggingLevel::Debug,
+    LoggingLevel::Info,
+    LoggingLevel::Notice,
+    LoggingLevel::Warning,
+    LoggingLevel::Error,
+    LoggingLevel::Critical,
+    LoggingLevel::Alert,
+    LoggingLevel::Emergency,
+];
+
+/// Parse a level name as MCP spells it ("debug" ... "emergency"), also
+/// accepting "warn"
+pub fn parse_level(name: &str) -> Option<LoggingLevel> {
+    let name = name.trim().to_lowercase();
+    match name.as_str() {
+        "warn" => Some(LoggingLevel::Warning),
+        _ => LEVELS.into_iter().find(|level| level_name(*level) == name),
+    }
+}
+
+/// The MCP name of a level
+pub fn level_name(level: LoggingLevel) -> &'static str {
+    match level {
+        LoggingLevel::Debug => "debug",
+        LoggingLevel::Info => "info",
+        LoggingLevel::Notice => "notice",
+        LoggingLevel::Warning => "warning",
+        LoggingLevel::Error => "error",
+        LoggingLevel::Critical => "critical",
+        LoggingLevel::Alert => "alert",
+        LoggingLevel::Emergency => "emergency",
+    }
+}
+
+/// The minimum level forwarded to the client, shared by the log handlers
+/// and the `setLevel` handler
+#[derive(Clone)]
+pub struct LogLevelFilter(Arc<AtomicU8>);
+
+impl LogLevelFilter {
+    pub fn new(level: LoggingLevel) -> Self {
+        Self(Arc::new(AtomicU8::new(level as u8)))
+    }
+
+    #[allow(dead_code)]
+    pub fn level(&self) -> LoggingLevel {
+        LEVELS[self.0.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn set(&self, level: LoggingLevel) {
+        self.0.store(level as u8, Ordering::Relaxed);
+    }
+
+    /// Whether a message at `level` should be forwarded
+    pub fn allows(&self, level: LoggingLevel) -> bool {
+        level as u8 >= self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for LogLevelFilter {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOG_LEVEL)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Handshake {
+    /// Waiting for the `initialize` request
+    Initialize,
+    /// Initialize answered, waiting for the `initialized` notification
+    Initialized,
+    Done,
+}
+
+/// A server transport that holds back messages a client sends between the
+/// `initialize` request and its `initialized` notification, and delivers
+/// them right after the notification
+pub struct DeferEarlyRequests<T> {
+    inner: T,
+    handshake: Handshake,
+    held: VecDeque<RxJsonRpcMessage<RoleServer>>,
+}
+
+impl<T: Transport<RoleServer>> DeferEarlyRequests<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            handshake: Handshake::Initialize,
+            held: VecDeque::new(),
+        }
+    }
+}
+
+/// Wrap anything rmcp can serve over (e.g. `stdio()`) in [`DeferEarlyRequests`]
+pub fn defer_early_requests<E, A>(
+    transport: impl IntoTransport<RoleServer, E, A>,
+) -> DeferEarlyRequests<impl Transport<RoleServer, Error = E>>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    DeferEarlyRequests::new(transport.into_transport())
+}
+
+impl<T: Transport<RoleServer>> Transport<RoleServer> for DeferEarlyRequests<T> {
+    type Error = T::Error;
+
+    fn send(
+        &mut self,
+        item: TxJsonRpcMessage<RoleServer>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static {
+        self.inner.send(item)
+    }
+
+    async fn receive(&mut self) -> Option<RxJsonRpcMessage<RoleServer>> {
+        loop {
+            if self.handshake == Handshake::Done {
+                if let Some(message) = self.held.pop_front() {
+                    return Some(message);
+                }
+                return self.inner.receive().await;
+            }
+
+            let message = self.inner.receive().await?;
+            match (self.handshake, &message) {
+                (Handshake::Initialize, ClientJsonRpcMessage::Request(request))
+                    if matches!(request.request, ClientRequest::InitializeRequest(_)) =>
+                {
+                    self.handshake = Handshake::Initialized;
+                    return Some(message);
+                }
+                (Handshake::Initialized, ClientJsonRpcMessage::Notification(notification))
+                    if matches!(
+                        notification.notification,
+                        ClientNotification::InitializedNotification(_)
+                    ) =>
+                {
+                    self.handshake = Handshake::Done;
+                    return Some(message);
+                }
+                (Handshake::Initialized, _) => self.held.push_back(message),
+                // Before initialize, rmcp reports whatever came instead
+                (_, _) => return Some(message),
+            }
+        }
+    }
+
+    fn close(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.inner.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("debug"), Some(LoggingLevel::Debug));
+        assert_eq!(parse_level(" WARN "), Some(LoggingLevel::Warning));
+        assert_eq!(parse_level("warning"), Some(LoggingLevel::Warning));
+        assert_eq!(parse_level("emergency"), Some(LoggingLevel::Emergency));
+        assert_eq!(parse_level("verbose"), None);
+        for level in LEVELS {
+            assert_eq!(parse_level(level_name(level)), Some(level));
+        }
+    }
+
+    #[test]
+    fn test_filter_threshold() {
+        let filter = LogLevelFilter::default();
+        assert!(!filter.allows(LoggingLevel::Debug));
+        assert!(filter.allows(LoggingLevel::Info));
+        assert!(filter.allows(LoggingLevel::Error));
+
+        // Clones share the threshold
+        filter.clone().set(LoggingLevel::Error);
+        assert_eq!(filter.level(), LoggingLevel::Error);
+        assert!(!filter.allows(LoggingLevel::Warning));
+        assert!(filter.allows(LoggingLevel::Critical));
+    }
+
+    /// A transport replaying client messages from JSON
+    struct Replay(VecDeque<ClientJsonRpcMessage>);
+
+    impl Transport<RoleServer> for Replay {
+        type Error = std::io::Error;
+
+        fn send(
+            &mut self,
+            _item: TxJsonRpcMessage<RoleServer>,
+        ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static {
+            std::future::ready(Ok(()))
+        }
+
+        fn receive(&mut self) -> impl Future<Output = Option<ClientJsonRpcMessage>> + Send {
+            std::future::ready(self.0.pop_front())
+        }
+
+        fn close(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+            std::future::ready(Ok(()))
+        }
+    }
+
+    fn method(message: &ClientJsonRpcMessage) -> String {
+        serde_json::to_value(message).unwrap()["method"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_defers_requests_until_initialized() {
+        let messages = [
+            serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+                "protocolVersion": "2025-03-26",
+                "capabilities": {},
+                "clientInfo": {"name": "early", "version": "1.0"}
+            }}),
+            serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "logging/setLevel", "params": {"level": "debug"}}),
+            serde_json::json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+            serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "tools/list"}),
+        ];
+        let replay = Replay(
+            messages
+                .into_iter()
+                .map(|m| serde_json::from_value(m).unwrap())
+                .collect(),
+        );
+
+        let mut transport = DeferEarlyRequests::new(replay);
+        let mut received = Vec::new();
+        while let Some(message) = transport.receive().await {
+            received.push(method(&message));
+        }
+        assert_eq!(
+            received,
+            [
+                "initialize",
+                "notifications/initialized",
+                "logging/setLevel",
+                "tools/list"
+            ]
+        );
+    }
+}
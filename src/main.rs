@@ -1,31 +1,37 @@
+mod backup;
 mod builder;
 mod client;
 mod config;
+mod exporter;
 mod importer;
+mod logging;
 mod memory;
 mod pipeline;
 mod prompts;
 mod registry;
 mod runtime;
+mod schedule;
 mod services;
+mod tool_index;
 mod watcher;
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
 use registry::ToolType;
 use rmcp::schemars::JsonSchema;
 use rmcp::{
     handler::server::{router::tool::ToolRouter, wrapper::Parameters},
     model::{
-        AnnotateAble, CancelledNotificationParam, GetPromptRequestParam, GetPromptResult,
-        ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, LoggingLevel,
-        LoggingMessageNotificationParam, PaginatedRequestParam, ProgressNotificationParam,
-        RawResource, RawResourceTemplate, ReadResourceRequestParam, ReadResourceResult,
-        ResourceContents, ResourceUpdatedNotificationParam, ServerCapabilities, ServerInfo,
+        AnnotateAble, CallToolRequestParam, CallToolResult, CancelledNotificationParam,
+        GetPromptRequestParam, GetPromptResult, ListPromptsResult, ListResourceTemplatesResult,
+        ListResourcesResult, ListToolsResult, LoggingLevel, LoggingMessageNotificationParam,
+        PaginatedRequestParam, ProgressNotificationParam, RawResource, RawResourceTemplate,
+        ReadResourceRequestParam, ReadResourceResult, ResourceContents,
+        ResourceUpdatedNotificationParam, ServerCapabilities, ServerInfo, SetLevelRequestParam,
         SubscribeRequestParam, UnsubscribeRequestParam,
     },
     service::{NotificationContext, RequestContext},
-    tool, tool_handler, tool_router,
+    tool, tool_router,
     transport::stdio,
     ErrorData as McpError, RoleServer, ServerHandler, ServiceExt,
 };
@@ -54,6 +60,17 @@ EXAMPLES:
 
   # Custom tools directory
   TOOLS_DIR=/my/tools skillz
+
+  # Export a tool as a portable package
+  skillz tools export my_tool
+
+  # List the tools in TOOLS_DIR, or show one, without starting the server
+  skillz list
+  skillz info my_tool
+
+  # Run a tool once and print its JSON result
+  skillz run my_tool --args '{"text": "hi"}'
+  echo '{"text": "hi"}' | skillz run my_tool --args-file -
 "#)]
 struct Cli {
     /// Transport mode: stdio or http
@@ -71,6 +88,122 @@ struct Cli {
     /// Enable hot reload - watch tools directory for changes
     #[arg(long, default_value = "false")]
     hot_reload: bool,
+
+    /// Minimum level of logs sent to the client: debug, info, notice,
+    /// warning, error, critical, alert or emergency (default: [logging]
+    /// level in skillz.toml, else info)
+    #[arg(long)]
+    log_level: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// List the registered tools and exit (fails if a tool couldn't be loaded)
+    List {
+        /// Only show tools with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Grouping: type (default) or tag
+        #[arg(long)]
+        group_by: Option<String>,
+    },
+    /// Show a registered tool and exit
+    Info {
+        /// Name of the tool
+        name: String,
+    },
+    /// Run a WASM or script tool once, print its JSON result and exit
+    Run {
+        /// Name of the tool
+        name: String,
+        /// Arguments as a JSON object, e.g. '{"text": "hi"}'
+        #[arg(long)]
+        args: Option<String>,
+        /// Read the JSON arguments from this file ("-" for stdin)
+        #[arg(long)]
+        args_file: Option<std::path::PathBuf>,
+    },
+    /// Manage tools without starting the server
+    Tools {
+        #[command(subcommand)]
+        action: ToolsCommand,
+    },
+    /// Back up the whole tools directory (tools, versions, memory, services)
+    /// to a tar.gz archive
+    Backup {
+        /// Archive to write, e.g. skillz-backup.tar.gz
+        output: std::path::PathBuf,
+    },
+    /// Replace the tools directory with a backup; stop the server first.
+    /// The current directory is kept as <TOOLS_DIR>.before-restore-<time>
+    Restore {
+        /// Archive written by skillz backup
+        archive: std::path::PathBuf,
+        /// Don't recompile WASM tools or reinstall script dependencies
+        #[arg(long)]
+        no_rebuild: bool,
+    },
+    /// Back up and restore tool memory
+    Memory {
+        #[command(subcommand)]
+        action: MemoryCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum MemoryCommand {
+    /// Export memory entries to a JSON file
+    Export {
+        /// Only export this tool's memory (default: all tools)
+        #[arg(long)]
+        tool: Option<String>,
+        /// Output file (default: TOOLS_DIR/exports/memory-<tool|all>-<time>.json)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Import memory entries from a JSON file written by export
+    Import {
+        /// Export file to read
+        file: std::path::PathBuf,
+        /// What to do with existing keys: overwrite, skip or error
+        #[arg(long, default_value = "error")]
+        strategy: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ToolsCommand {
+    /// Export a tool as a portable .skillz package
+    Export {
+        /// Name of the tool to export
+        name: String,
+        /// Output file (default: TOOLS_DIR/exports/<name>-<version>.skillz)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Import a tool from a .skillz package (path or URL), git repo, gist,
+    /// local directory or raw file URL; repos and directories may hold several
+    Import {
+        /// Package path/URL, git URL, gist:ID, directory or raw file URL
+        source: String,
+        /// Replace the tool if it already exists
+        #[arg(long)]
+        overwrite: bool,
+        /// Import only these tools from a repo or directory (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        select: Vec<String>,
+    },
+    /// Update an imported tool from the source it was imported from
+    Update {
+        /// Name of the imported tool
+        name: String,
+        /// Replace the tool even if it was changed since it was imported
+        #[arg(long)]
+        force: bool,
+    },
 }
 
 use rmcp::service::Peer;
@@ -94,6 +227,9 @@ type SharedClientCaps = Arc<RwLock<McpClientCapabilities>>;
 /// Subscribed resource URIs (for resource update notifications)
 type SharedSubscriptions = Arc<RwLock<std::collections::HashSet<String>>>;
 
+/// Imports inspected with import_tool's inspect, by token, awaiting confirm_import
+type SharedInspections = Arc<RwLock<std::collections::HashMap<String, importer::Inspection>>>;
+
 #[derive(Clone)]
 struct AppState {
     registry: registry::ToolRegistry,
@@ -106,12 +242,16 @@ struct AppState {
     client_caps: SharedClientCaps,
     /// Subscribed resource URIs
     subscriptions: SharedSubscriptions,
+    /// Quarantined imports awaiting confirm_import
+    pending_imports: SharedInspections,
     /// Manager for external MCP clients
     client_manager: Arc<client::McpClientManager>,
     /// Prompt registry for built-in prompts
     prompt_registry: prompts::PromptRegistry,
     /// Service registry for Docker services
     service_registry: services::ServiceRegistry,
+    /// Minimum level of logs forwarded to the client
+    log_level: logging::LogLevelFilter,
 }
 
 #[derive(Deserialize, Serialize, JsonSchema)]
@@ -119,7 +259,10 @@ struct AppState {
 /// Unified tool building/management
 struct BuildToolArgs {
     name: String,
-    code: String,
+    /// Rust source: a string (the contents of src/main.rs), or an object mapping
+    /// relative paths to contents for multi-file projects.
+    /// Example: {"src/main.rs": "mod util; fn main() { ... }", "src/util.rs": "pub fn ..."}
+    code: serde_json::Value,
     description: String,
     /// JSON Schema describing the tool's input arguments
     input_schema: Option<serde_json::Value>,
@@ -131,9 +274,28 @@ struct BuildToolArgs {
     /// Rust crate dependencies for WASM tools
     /// Format: "name@version" or "name@version[feat1,feat2]" or just "name"
     /// Example: ["serde@1.0[derive]", "regex@1.10", "anyhow"]
+    /// Add "no-default-features" inside the brackets to disable a crate's default features
     dependencies: Option<Vec<String>>,
+    /// Build settings for the generated Cargo.toml, kept for recompiles
+    /// Example: {"no_default_features": true, "opt_level": "z", "lto": true}
+    build_options: Option<serde_json::Value>,
+    /// Compute budget per call in fuel units (about one per WASM instruction); a call
+    /// that uses it up fails with "WASM fuel exhausted". Unlimited when unset
+    wasm_fuel: Option<u64>,
     /// Allow overwriting existing tools
     overwrite: Option<bool>,
+    /// Tags for organization (e.g., ["text", "utils"]). Kept from the previous version if omitted on update
+    tags: Option<Vec<String>>,
+    /// Version bump when updating an existing tool: 'major', 'minor', or 'patch' (default)
+    version_bump: Option<String>,
+    /// Explicit version for this release (e.g. "2.0.0"); must be newer than the current one. Overrides version_bump
+    version: Option<String>,
+    /// Limit how often the tool may be called, e.g. {"max": 10, "per_secs": 60}.
+    /// Useful for tools with openWorldHint that hit external APIs
+    rate_limit: Option<serde_json::Value>,
+    /// Only check that the code compiles: report diagnostics or the module
+    /// size, without registering the tool or touching its versions
+    dry_run: Option<bool>,
 }
 
 /// Register a script tool
@@ -164,12 +326,33 @@ struct RegisterScriptArgs {
     annotations: Option<serde_json::Value>,
     /// Allow overwriting existing tools
     overwrite: Option<bool>,
-    /// Dependencies to install (pip packages for Python, npm packages for Node.js)
-    /// Example: ["requests", "pandas"] for Python, ["axios", "lodash"] for Node.js
+    /// Dependencies to install (pip packages for Python, npm packages for Node.js, gems for Ruby)
+    /// Example: ["requests", "pandas"] for Python, ["axios", "lodash"] for Node.js, ["httparty"] for Ruby
     dependencies: Option<Vec<String>>,
     /// Docker services this tool requires. The services must be defined and running.
     /// Example: ["postgres", "redis"] - tool will receive POSTGRES_HOST, REDIS_PORT, etc.
     requires_services: Option<Vec<String>>,
+    /// Tags for organization (e.g., ["text", "utils"]). Kept from the previous version if omitted on update
+    tags: Option<Vec<String>>,
+    /// Version bump when updating an existing tool: 'major', 'minor', or 'patch' (default)
+    version_bump: Option<String>,
+    /// Explicit version for this release (e.g. "2.0.0"); must be newer than the current one. Overrides version_bump
+    version: Option<String>,
+    /// Limit how often the tool may be called, e.g. {"max": 10, "per_secs": 60}.
+    /// Useful for tools with openWorldHint that hit external APIs
+    rate_limit: Option<serde_json::Value>,
+    /// How the script receives arguments: 'stdin' (default, JSON-RPC request) or
+    /// 'argv' (each argument as a --key value flag; stdout is returned as the result)
+    arg_mode: Option<String>,
+    /// Collect every JSON-RPC result line the script prints into an array (NDJSON),
+    /// instead of keeping only the last one. Useful for streaming/aggregating tools
+    ndjson: Option<bool>,
+    /// Directory the script runs in, relative to the first workspace root (or absolute).
+    /// Useful for tools wrapping project-specific CLIs
+    working_dir: Option<String>,
+    /// Extra environment variables for the script, e.g. {"PATH": "/opt/cli/bin:${PATH}"};
+    /// ${VAR} expands from the server's environment
+    env: Option<std::collections::HashMap<String, String>>,
 }
 
 #[derive(Deserialize, Serialize, JsonSchema)]
@@ -179,6 +362,62 @@ struct DeleteToolArgs {
     tool_name: String,
 }
 
+/// Fetch a tool's code for editing
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct GetToolSourceArgs {
+    /// Name of the tool
+    tool_name: String,
+}
+
+/// Manage an existing tool (rename, copy, recompile, tag, untag)
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct ManageToolArgs {
+    /// Action: 'rename', 'copy', 'recompile', 'tag', or 'untag'
+    action: String,
+    /// Name of the existing tool
+    tool_name: String,
+    /// New name for the tool or the copy (required for rename/copy)
+    new_name: Option<String>,
+    /// For 'rename': also point pipelines using the old name at the new one (default: true)
+    update_pipelines: Option<bool>,
+    /// Tags to add or remove (required for tag/untag)
+    tags: Option<Vec<String>>,
+}
+
+/// Show per-tool usage statistics
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct ToolStatsArgs {
+    /// Sort by: 'calls' (default), 'last_used', 'failures', 'duration', or 'name'
+    sort_by: Option<String>,
+    /// Only show statistics for this tool
+    tool_name: Option<String>,
+}
+
+/// Query the execution audit log
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct HistoryArgs {
+    /// Only show executions of this tool
+    tool_name: Option<String>,
+    /// Only show successful (true) or failed (false) executions
+    success: Option<bool>,
+    /// Maximum number of executions to return, newest first (default: 20)
+    limit: Option<usize>,
+}
+
+/// List registered tools
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct ListToolsArgs {
+    /// Only show tools with this tag
+    tag: Option<String>,
+    /// Grouping: 'type' (default) or 'tag'
+    group_by: Option<String>,
+}
+
 #[derive(Deserialize, Serialize, JsonSchema)]
 #[schemars(crate = "rmcp::schemars")]
 enum VersionAction {
@@ -188,17 +427,27 @@ enum VersionAction {
     Rollback,
     #[serde(rename = "info")]
     Info,
+    #[serde(rename = "diff")]
+    Diff,
+    #[serde(rename = "prune")]
+    Prune,
 }
 
 #[derive(Deserialize, Serialize, JsonSchema)]
 #[schemars(crate = "rmcp::schemars")]
 struct VersionArgs {
-    /// Action: 'list', 'rollback', 'info'
+    /// Action: 'list', 'rollback', 'info', 'diff', 'prune'
     action: VersionAction,
     /// Tool name
     tool_name: Option<String>,
     /// Version to rollback to (for 'rollback' action)
     version: Option<String>,
+    /// Older version to compare (for 'diff' action)
+    from: Option<String>,
+    /// Newer version to compare (for 'diff' action, default: current)
+    to: Option<String>,
+    /// Number of backups to keep (for 'prune' action, default: configured retention)
+    keep: Option<usize>,
 }
 
 #[derive(Deserialize, Serialize, JsonSchema)]
@@ -206,6 +455,8 @@ struct VersionArgs {
 struct CallToolArgs {
     tool_name: String,
     arguments: Option<std::collections::HashMap<String, serde_json::Value>>,
+    /// Skip memoized results of idempotentHint tools and run the tool again
+    no_cache: Option<bool>,
 }
 
 /// Code execution mode - compose multiple tools via code
@@ -228,11 +479,82 @@ struct ExecuteCodeArgs {
 #[schemars(crate = "rmcp::schemars")]
 struct ImportToolArgs {
     /// Source to import from. Supported formats:
-    /// - Git: "https://github.com/user/repo" or "https://github.com/user/repo#branch"
+    /// - Git: "https://github.com/user/repo", pinned with "#<branch|tag|commit>"
+    ///   (e.g. "https://github.com/user/repo#v1.2.0")
     /// - Gist: "gist:GIST_ID" or "https://gist.github.com/user/GIST_ID"
+    /// - Package: "/path/to/tool-1.0.0.skillz" or "https://example.com/tool.skillz"
+    /// - Local directory: "file:///path/to/tool_dir" or "/path/to/tool_dir"
+    /// - Raw files: "https://example.com/tool/manifest.json" or ".../tool/main.py"
+    /// - Tool index: "index:weather_fetch" (see search_index), checksum-verified
+    ///
+    /// A repository or directory may hold several tools, one per directory
+    /// with a manifest.json (e.g. tools/<name>/manifest.json); all are imported.
     source: String,
     /// Allow overwriting if tool already exists
     overwrite: Option<bool>,
+    /// Import only these tools from a repository or directory with several
+    select: Option<Vec<String>>,
+    /// Existing tools that may be overwritten (without overwriting the rest)
+    overwrite_tools: Option<Vec<String>>,
+    /// Token for a private git repository or gist, overriding SKILLZ_GIT_TOKEN.
+    /// Used for this import only: never stored, logged or echoed back.
+    auth_token: Option<String>,
+    /// Fetch into quarantine and report the manifest, dependencies, code and
+    /// red flags without registering anything; confirm_import registers it
+    inspect: Option<bool>,
+}
+
+/// Search the community tool index
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct SearchIndexArgs {
+    /// Words to find in tool names, descriptions and tags (empty lists every tool)
+    query: String,
+}
+
+/// Register an import inspected with import_tool's inspect
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct ConfirmImportArgs {
+    /// Token from the inspection report
+    token: String,
+}
+
+/// Update an imported tool from the source it was imported from
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct UpdateToolArgs {
+    /// Name of the imported tool
+    tool_name: String,
+    /// Replace the tool even if it was changed since it was imported
+    /// (the current version is backed up first)
+    force: Option<bool>,
+    /// Token for a private git repository or gist, overriding SKILLZ_GIT_TOKEN.
+    /// Used for this update only: never stored, logged or echoed back.
+    auth_token: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct ExportToolArgs {
+    /// Name of the tool to export
+    tool_name: String,
+    /// Return the package as base64 instead of writing a file (only for packages up to 64 KB)
+    inline: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct ShareToolArgs {
+    /// Name of the tool to share
+    tool_name: String,
+    /// GitHub token with the gist scope, overriding SKILLZ_GIT_TOKEN.
+    /// Used for this request only: never stored, logged or echoed back.
+    auth_token: Option<String>,
+    /// Create a public gist instead of a secret one (new gists only)
+    public: Option<bool>,
+    /// Create a new gist even if the tool was shared before
+    new_gist: Option<bool>,
 }
 
 /// A step in a pipeline
@@ -243,12 +565,45 @@ struct PipelineStepArg {
     name: Option<String>,
     /// Tool to execute
     tool: String,
-    /// Arguments to pass. Use $input.field, $prev.field, or $step_name.field for dynamic values
+    /// Arguments to pass. Use $input.field, $prev.field, or $step_name.field for dynamic values;
+    /// start a string with $$ for a literal leading "$" (e.g. "$$5.00")
     args: Option<serde_json::Value>,
     /// Continue pipeline even if this step fails (default: false)
     continue_on_error: Option<bool>,
-    /// Condition to check before running (e.g., "$prev.success == true")
+    /// Condition to check before running (e.g., "$prev.success == true").
+    /// A script that exits non-zero reports "exit_code", so "$prev.exit_code" is truthy after a crash
     condition: Option<String>,
+    /// Give up on an attempt after this many seconds
+    timeout_secs: Option<u64>,
+    /// Retry a failed or timed-out step this many more times (default: 0, max: 10)
+    retries: Option<u32>,
+    /// Wait between attempts, in milliseconds (default: 0, max: 60000)
+    retry_delay_ms: Option<u64>,
+    /// Run the tool once per element of this array, e.g. "$fetch.items". In args, $item is the
+    /// element and $index its position; the step's output is the array of all results
+    foreach: Option<String>,
+    /// For foreach steps: how many elements run at once (default: 1)
+    max_concurrency: Option<usize>,
+    /// Reuse the step's output when it runs again with the same tool and resolved
+    /// args within ttl_seconds, e.g. { ttl_seconds: 3600 } for an expensive fetch.
+    /// Clear with pipeline(action: "clear_cache")
+    cache: Option<StepCacheArg>,
+}
+
+/// Output caching for a pipeline step
+#[derive(Clone, Copy, Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct StepCacheArg {
+    /// How long a cached output stays fresh, in seconds
+    ttl_seconds: u64,
+}
+
+impl From<StepCacheArg> for registry::StepCache {
+    fn from(arg: StepCacheArg) -> Self {
+        Self {
+            ttl_seconds: arg.ttl_seconds,
+        }
+    }
 }
 
 /// Register an external MCP server
@@ -286,20 +641,46 @@ struct HealthCheckArg {
     timeout: Option<String>,
 }
 
+/// Changes to a service preset
+#[derive(Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct ServiceOverridesArg {
+    /// Image to run instead of the preset's (e.g. "postgres:15")
+    image: Option<String>,
+    /// Port mappings replacing the preset's
+    ports: Option<Vec<String>>,
+    /// Environment variables, merged over the preset's (e.g. {"POSTGRES_PASSWORD": "dev"})
+    env: Option<std::collections::HashMap<String, String>>,
+    /// Volume mounts replacing the preset's
+    volumes: Option<Vec<String>>,
+    /// Health check replacing the preset's
+    healthcheck: Option<HealthCheckArg>,
+    /// Service description
+    description: Option<String>,
+}
+
 /// Manage Docker services for tools
 #[derive(Deserialize, Serialize, JsonSchema)]
 #[schemars(crate = "rmcp::schemars")]
 struct ServicesArgs {
-    /// Action: 'define', 'list', 'start', 'stop', 'remove', 'logs', 'status', 'prune'
+    /// Action: 'define', 'presets', 'import_compose', 'list', 'start', 'stop', 'remove', 'logs', 'status', 'backup', 'restore', 'run_once', 'prune'
     action: String,
-    /// Service name (required for define/start/stop/remove/logs/status)
+    /// Service name (required for define/start/stop/remove/logs/status/backup/restore)
     name: Option<String>,
-    /// Docker image (e.g., "postgres:15", "redis:alpine") - required for define
+    /// Docker image (e.g., "postgres:15", "redis:alpine") - required for define without a preset and for run_once
     image: Option<String>,
+    /// define: start from a built-in preset (postgres, redis, mysql, mongodb, minio, rabbitmq; see action 'presets')
+    preset: Option<String>,
+    /// define with a preset: changes to the preset's image, ports, env, volumes, healthcheck or description
+    overrides: Option<ServiceOverridesArg>,
     /// Port mappings: ["5432"] for random host port, or ["5432:5432"] for fixed
     ports: Option<Vec<String>>,
     /// Environment variables for the container
     env: Option<std::collections::HashMap<String, String>>,
+    /// run_once: command and arguments for the container (default: the image's own)
+    command: Option<Vec<String>>,
+    /// run_once: kill the container after this many seconds (default 60, max 600)
+    timeout_secs: Option<u64>,
     /// Volume mounts: ["data:/var/lib/data", "/host/path:/container/path"]
     volumes: Option<Vec<String>>,
     /// Health check configuration
@@ -308,46 +689,190 @@ struct ServicesArgs {
     description: Option<String>,
     /// Allow overwriting existing service definition
     overwrite: Option<bool>,
+    /// define: never stop the service for being idle ([services] idle_timeout_secs)
+    keep_alive: Option<bool>,
+    /// define: seconds start waits for the health check to pass (default: the healthcheck's retries × interval)
+    startup_timeout_secs: Option<u32>,
     /// Number of log lines to tail (for logs action)
     tail: Option<u32>,
+    /// logs: only lines since a timestamp or a duration like "10m" (docker's --since)
+    since: Option<String>,
+    /// logs: keep streaming new lines as log notifications tagged with the service name
+    follow: Option<bool>,
+    /// logs with follow: how long to stream in seconds (default 30, max 600); cancelling the request stops earlier
+    duration_secs: Option<u64>,
     /// Also remove volumes when removing service
     remove_volumes: Option<bool>,
     /// Also remove volumes when pruning (for prune action)
     include_volumes: Option<bool>,
+    /// backup: directory for the archive (default: TOOLS_DIR/backups)
+    output_dir: Option<String>,
+    /// restore: path of a backup archive made by the backup action
+    archive: Option<String>,
+    /// restore: replace the contents of volumes that already hold data
+    force: Option<bool>,
+    /// import_compose: path to a docker-compose file, or its YAML content
+    path_or_content: Option<String>,
+    /// import_compose: prefix for the imported service names (default: the compose project name or the file's directory)
+    prefix: Option<String>,
+}
+
+/// Show or change the script sandbox
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct SandboxArgs {
+    /// Action: 'get' (default) or 'set'
+    action: Option<String>,
+    /// Sandbox mode: 'none', 'bubblewrap' (or 'bwrap'), 'firejail' or 'nsjail'
+    mode: Option<String>,
+    /// Allow network access inside the sandbox
+    allow_network: Option<bool>,
+    /// Extra directories scripts may read (replaces the current list)
+    read_paths: Option<Vec<String>>,
+    /// Directories scripts may write (replaces the current list)
+    write_paths: Option<Vec<String>>,
+    /// Memory limit in MB (0 = unlimited)
+    memory_limit_mb: Option<u64>,
+    /// CPU time limit in seconds (0 = unlimited)
+    time_limit_secs: Option<u64>,
 }
 
 /// Unified pipeline management
 #[derive(Deserialize, Serialize, JsonSchema)]
 #[schemars(crate = "rmcp::schemars")]
 struct PipelineArgs {
-    /// Action: 'create', 'list', 'delete', 'run'
+    /// Action: 'create', 'list', 'show', 'update', 'run', 'validate', 'diagram',
+    /// 'schedule', 'unschedule', 'runs', 'clear_cache', 'delete'
     action: String,
-    /// Pipeline name (required for create/delete/run)
+    /// Pipeline name (required for all actions except list)
     name: Option<String>,
-    /// Description of what the pipeline does (for create)
+    /// Description of what the pipeline does (for create/update)
     description: Option<String>,
-    /// Steps to execute in order (for create)
+    /// Steps to execute in order (for create; replaces all steps on update)
     steps: Option<Vec<PipelineStepArg>>,
-    /// Tags for organization (for create/list filter)
+    /// Steps to run when a failed step ends the pipeline, so not for failures
+    /// continued past (for create/update).
+    /// They can read $error.step, $error.message and earlier named steps.
+    on_error: Option<Vec<PipelineStepArg>>,
+    /// Tags for organization (for create/update)
     tags: Option<Vec<String>>,
+    /// Don't send per-step progress notifications or forward step logs when
+    /// the pipeline runs (for create/update)
+    quiet: Option<bool>,
+    /// When to run the pipeline automatically while the server is up (for
+    /// create/schedule): "every 30s", "every 5m", "every 1h", "every 1d" or a
+    /// cron expression like "0 * * * *" (UTC)
+    schedule: Option<String>,
     /// Filter by tag (for list)
     tag: Option<String>,
+    /// Input object bound to $input (for run)
+    input: Option<serde_json::Value>,
+    /// Stop at the first failed step (for run, default true). When false every
+    /// step runs as if it set continue_on_error.
+    stop_on_error: Option<bool>,
+    /// Show step outputs in full instead of truncated (for run, markdown format)
+    verbose: Option<bool>,
+    /// Result format (for run): 'markdown' (default) or 'json' (the structured
+    /// run with every step result)
+    format: Option<String>,
+    /// Bypass memoized step results (for run)
+    no_cache: Option<bool>,
+    /// Save each step's resolved args and output to memory under
+    /// __pipeline_debug__, keyed by a run id shown in the result (for run)
+    debug: Option<bool>,
+    /// Dry-run the steps before creating and refuse if they have problems (for create)
+    validate: Option<bool>,
+    /// Example input the dry run checks $input references against (for validate/create)
+    sample_input: Option<serde_json::Value>,
+    /// Example output per step name, used by the dry run instead of the tool's
+    /// output schema (for validate/create)
+    sample_outputs: Option<std::collections::HashMap<String, serde_json::Value>>,
+}
+
+/// Built-in tools that pipeline steps may call besides registered tools.
+/// Each needs a case in `call_builtin_tool`.
+const PIPELINE_BUILTIN_TOOLS: &[&str] = &[
+    "build_tool",
+    "register_script",
+    "import_tool",
+    "list_tools",
+    "execute_code",
+    "delete_tool",
+    "memory",
+    pipeline::TRANSFORM_TOOL,
+];
+
+/// Execution settings a pipeline passes down to its steps
+#[derive(Clone, Copy)]
+struct PipelineCall<'a> {
+    ct: &'a tokio_util::sync::CancellationToken,
+    /// Reuse memoized results of idempotentHint tools
+    use_cache: bool,
+    /// Running pipelines, outermost first, ending with the current one
+    chain: &'a [String],
+    /// Step notifications for the client (None leaves them to the runtime)
+    progress: Option<&'a pipeline::StepProgress>,
+    /// Run id to checkpoint the pipeline's steps under in memory (debug runs;
+    /// nested pipelines aren't checkpointed step by step)
+    debug_run: Option<&'a str>,
 }
 
 // ==================== Memory Args ====================
 
+/// Page size of the memory tool's list action when no limit is given
+const DEFAULT_MEMORY_LIST_LIMIT: usize = 100;
+
 /// Unified memory management - combines get, set, list, clear, stats
 #[derive(Deserialize, Serialize, JsonSchema)]
 #[schemars(crate = "rmcp::schemars")]
 struct MemoryArgs {
-    /// Action: 'store', 'get', 'update', 'delete', 'list'
+    /// Action: 'store', 'get', 'exists', 'delete', 'list', 'query', 'stats', 'bulk_store', 'bulk_update', 'bulk_get', 'increment', 'append', 'semantic_search', 'export', 'import'
     action: String,
-    /// Tool name (namespace for isolation)
+    /// Tool name (namespace for isolation); '*' exports every tool
     tool_name: String,
-    /// Key to retrieve or store (required for get/store/delete)
+    /// Key to retrieve or store (required for get/exists/store/delete)
     key: Option<String>,
-    /// Value to store (any JSON value) - required for store action
+    /// Value to store (any JSON value) - required for store action; the item to add for append.
+    /// For import: an export document ({tool: {key: {value, created_at, expires_at}}})
     value: Option<serde_json::Value>,
+    /// Expire the stored entry after this many seconds (store only; omit or 0 = never expires)
+    ttl_seconds: Option<u64>,
+    /// Entries for bulk_store/bulk_update, written in one transaction (all or nothing)
+    /// Example: [{"key": "a", "value": 1}, {"key": "b", "value": {"x": 2}, "ttl_seconds": 60}]
+    entries: Option<Vec<MemoryEntryArgs>>,
+    /// Keys to fetch with bulk_get
+    keys: Option<Vec<String>>,
+    /// list: only keys starting with this prefix (e.g. "user:")
+    prefix: Option<String>,
+    /// list/query: maximum keys returned (default: 100)
+    limit: Option<usize>,
+    /// list: cursor returned by the previous page
+    cursor: Option<String>,
+    /// list: include values (large values are truncated, with their size)
+    include_values: Option<bool>,
+    /// increment: amount to add to the integer value (default: 1, may be negative)
+    delta: Option<i64>,
+    /// append: keep only the newest this many items
+    max_length: Option<usize>,
+    /// semantic_search: text to find similar entries for.
+    /// query: substring to look for in the stored JSON values
+    query: Option<String>,
+    /// semantic_search: number of results (default: 5)
+    top_k: Option<usize>,
+    /// import: JSON file written by export (instead of an inline value)
+    path: Option<String>,
+    /// import: what to do with existing keys - 'overwrite', 'skip' or 'error' (default; nothing is imported)
+    merge_strategy: Option<String>,
+}
+
+/// One entry of a bulk memory write
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+struct MemoryEntryArgs {
+    key: String,
+    value: serde_json::Value,
+    /// Expire after this many seconds (omit or 0 = never expires)
+    ttl_seconds: Option<u64>,
 }
 
 impl AppState {
@@ -366,20 +891,20 @@ impl AppState {
         // Initialize service registry
         let service_registry = services::ServiceRegistry::new(&storage_dir);
 
-        // Set up logging handler that forwards to MCP peer
+        // Set up logging handler that forwards to MCP peer, dropping
+        // messages below the log level
+        let log_level = logging::LogLevelFilter::default();
         let peer_for_logging = peer.clone();
+        let level_for_logging = log_level.clone();
         let logging_handler: runtime::LoggingHandler = Arc::new(move |level, message, data| {
             let peer = peer_for_logging.clone();
+            let mcp_level = logging::parse_level(&level).unwrap_or(LoggingLevel::Info);
+            let forward = level_for_logging.allows(mcp_level);
             Box::pin(async move {
+                if !forward {
+                    return;
+                }
                 if let Some(ref p) = *peer.read().await {
-                    let mcp_level = match level.to_lowercase().as_str() {
-                        "debug" => LoggingLevel::Debug,
-                        "info" => LoggingLevel::Info,
-                        "warning" | "warn" => LoggingLevel::Warning,
-                        "error" => LoggingLevel::Error,
-                        _ => LoggingLevel::Info,
-                    };
-
                     let log_data = data.unwrap_or_else(|| serde_json::json!({"message": message}));
                     let _ = p
                         .notify_logging_message(LoggingMessageNotificationParam {
@@ -522,6 +1047,22 @@ impl AppState {
                         description: Some(tool.description().to_string()),
                         mime_type: Some("text/markdown".to_string()),
                     });
+                    if tool.source_path().is_some() {
+                        resources.push(runtime::ResourceInfo {
+                            uri: format!("skillz://tools/{}/source", tool.name()),
+                            name: format!("{} source", tool.name()),
+                            description: Some(format!("Source code of {}", tool.name())),
+                            mime_type: Some(tool.source_mime_type().to_string()),
+                        });
+                    }
+                    if *tool.tool_type() == ToolType::Pipeline {
+                        resources.push(runtime::ResourceInfo {
+                            uri: format!("skillz://pipelines/{}/diagram", tool.name()),
+                            name: format!("{} diagram", tool.name()),
+                            description: Some(format!("Mermaid flowchart of {}", tool.name())),
+                            mime_type: Some(MERMAID_MIME_TYPE.to_string()),
+                        });
+                    }
                 }
 
                 Ok(resources)
@@ -529,16 +1070,36 @@ impl AppState {
         });
 
         let registry_for_read = registry.clone();
+        let memory_for_read = memory.clone();
         let resource_read_handler: runtime::ResourceReadHandler = Arc::new(move |uri| {
             let reg = registry_for_read.clone();
+            let mem = memory_for_read.clone();
             Box::pin(async move {
+                if let Some(tool_name) = parse_tool_source_uri(&uri) {
+                    let (text, mime_type) = read_tool_source(&reg, tool_name)?;
+                    return Ok(runtime::ResourceContent {
+                        uri: uri.clone(),
+                        mime_type: Some(mime_type),
+                        text: Some(text),
+                        blob: None,
+                    });
+                }
+                if let Some(name) = parse_pipeline_diagram_uri(&uri) {
+                    return Ok(runtime::ResourceContent {
+                        uri: uri.clone(),
+                        mime_type: Some(MERMAID_MIME_TYPE.to_string()),
+                        text: Some(read_pipeline_diagram(&reg, name)?),
+                        blob: None,
+                    });
+                }
                 let content = match uri.as_str() {
                     "skillz://guide" => get_guide_content_static(),
                     "skillz://examples" => get_examples_content_static(),
                     "skillz://protocol" => get_protocol_content_static(),
                     _ if uri.starts_with("skillz://tools/") => {
                         let tool_name = uri.strip_prefix("skillz://tools/").unwrap();
-                        get_tool_info_static(&reg, tool_name)
+                        let usage = mem.tool_usage(tool_name).await.ok().flatten();
+                        get_tool_info_static(&reg, tool_name, usage.as_ref())
                     }
                     _ => return Err(anyhow::anyhow!("Resource not found: {}", uri)),
                 };
@@ -554,8 +1115,10 @@ impl AppState {
 
         // Set up stream handler - forward stream chunks (for progressive output)
         let peer_for_stream = peer.clone();
+        let level_for_stream = log_level.clone();
         let stream_handler: runtime::StreamHandler = Arc::new(move |chunk| {
             let peer = peer_for_stream.clone();
+            let forward = level_for_stream.allows(LoggingLevel::Info);
             Box::pin(async move {
                 // Log the chunk for debugging
                 eprintln!(
@@ -565,6 +1128,9 @@ impl AppState {
                 );
 
                 // Forward as a log message for now (could use custom notification in future)
+                if !forward {
+                    return;
+                }
                 if let Some(ref p) = *peer.read().await {
                     let _ = p
                         .notify_logging_message(LoggingMessageNotificationParam {
@@ -584,25 +1150,37 @@ impl AppState {
 
         // Set up tool call handler - allow tools to call other tools
         let registry_for_tool_call = registry.clone();
-        let runtime_for_tool_call = runtime.clone();
-        let tool_call_handler: runtime::ToolCallHandler = Arc::new(move |name, arguments| {
-            let reg = registry_for_tool_call.clone();
-            let rt = runtime_for_tool_call.clone();
-            Box::pin(async move {
-                let tool = reg
-                    .get_tool(&name)
-                    .ok_or_else(|| anyhow::anyhow!("Tool '{}' not found", name))?;
+        // The calling tool already holds an execution slot
+        let runtime_for_tool_call = runtime.clone().without_concurrency_limit();
+        let memory_for_tool_call = memory.clone();
+        let tool_call_handler: runtime::ToolCallHandler =
+            Arc::new(move |caller, name, arguments| {
+                let reg = registry_for_tool_call.clone();
+                let rt = runtime_for_tool_call.clone();
+                let mem = memory_for_tool_call.clone();
+                Box::pin(async move {
+                    let tool = reg
+                        .get_tool(&name)
+                        .ok_or_else(|| anyhow::anyhow!("Tool '{}' not found", name))?;
+                    if let Some(caller) = reg.get_tool(&caller) {
+                        caller.check_may_call(&tool)?;
+                    }
 
-                let args = arguments.unwrap_or(serde_json::json!({}));
+                    let args = arguments.unwrap_or(serde_json::json!({}));
 
-                // Use spawn_blocking for sync operations
-                let result = tokio::task::spawn_blocking(move || rt.call_tool(&tool, args))
-                    .await
-                    .map_err(|e| anyhow::anyhow!("Task join error: {}", e))??;
+                    // Use spawn_blocking for sync operations
+                    let start = std::time::Instant::now();
+                    let result = tokio::task::spawn_blocking(move || rt.call_tool(&tool, args))
+                        .await
+                        .map_err(|e| anyhow::anyhow!("Task join error: {}", e))?;
 
-                Ok(result)
-            })
-        });
+                    let _ = mem
+                        .record_tool_call(&name, start.elapsed().as_millis() as u64, result.is_ok())
+                        .await;
+
+                    result
+                })
+            });
 
         // Configure runtime with handlers
         runtime = runtime
@@ -622,12 +1200,27 @@ impl AppState {
             peer,
             client_caps: Arc::new(RwLock::new(McpClientCapabilities::default())),
             subscriptions: Arc::new(RwLock::new(std::collections::HashSet::new())),
+            pending_imports: Arc::new(RwLock::new(std::collections::HashMap::new())),
             client_manager,
             prompt_registry: prompts::PromptRegistry::new_with_defaults(),
             service_registry,
+            log_level,
         }
     }
 
+    /// Forward logs at `level` and above to the client (until it sends
+    /// `logging/setLevel`)
+    fn with_log_level(self, level: LoggingLevel) -> Self {
+        self.log_level.set(level);
+        self
+    }
+
+    /// Stop services no tool has used for `secs` seconds (0 = never)
+    fn with_service_idle_timeout(mut self, secs: u64) -> Self {
+        self.service_registry = self.service_registry.with_idle_timeout(secs);
+        self
+    }
+
     /// Update the peer reference (called when we get a context)
     async fn update_peer(&self, new_peer: Peer<RoleServer>) {
         let mut peer = self.peer.write().await;
@@ -644,39 +1237,268 @@ impl AppState {
     async fn get_client_caps(&self) -> McpClientCapabilities {
         self.client_caps.read().await.clone()
     }
-}
 
-#[tool_router]
-impl AppState {
-    // ==================== WASM TOOLS (Rust) ====================
+    /// Env vars for the services a tool `requires_services`, or the guidance
+    /// message when one isn't defined or running. Docker is queried (and, with
+    /// auto-start, services started) on a blocking thread.
+    async fn required_service_env(
+        &self,
+        tool: &registry::ToolConfig,
+    ) -> Result<std::collections::HashMap<String, String>, String> {
+        let required = tool.manifest.requires_services.clone();
+        if required.is_empty() {
+            return Ok(std::collections::HashMap::new());
+        }
+        let services = self.service_registry.clone();
+        tokio::task::spawn_blocking(move || services.check_required_services(&required))
+            .await
+            .unwrap_or_else(|e| Err(format!("❌ Service check failed: {}", e)))
+    }
 
-    #[tool(
-        description = "Compile and register a new WASM tool from Rust code. Supports Rust crate dependencies! Set overwrite=true to update existing tools."
-    )]
-    async fn build_tool(&self, Parameters(args): Parameters<BuildToolArgs>) -> String {
+    /// import_tool, asking for confirmation through `peer` when inspecting
+    /// for a client that supports elicitation. Fails with the reply when
+    /// nothing could be imported
+    async fn run_import(
+        &self,
+        args: ImportToolArgs,
+        peer: Option<&Peer<RoleServer>>,
+    ) -> Result<String> {
+        let importer = importer::Importer::new(self.registry.storage_dir().to_path_buf())
+            .with_auth_token(args.auth_token.clone());
+        eprintln!("Importing tool from: {}", importer.redact(&args.source));
+
+        let filter = importer::ImportFilter {
+            select: args.select.clone(),
+            overwrite: args.overwrite.unwrap_or(false),
+            overwrite_tools: args.overwrite_tools.clone().unwrap_or_default(),
+        };
+
+        if args.inspect.unwrap_or(false) {
+            let inspection = match importer.inspect(&args.source, &self.registry, &filter) {
+                Ok(inspection) => inspection,
+                Err(e) => {
+                    anyhow::bail!(
+                        "❌ **Inspection Failed**\n\n**Source:** {}\n**Error:** {}",
+                        importer.redact(&args.source),
+                        importer.redact(&format!("{:#}", e))
+                    )
+                }
+            };
+            let report = importer.redact(&format_inspection(&inspection));
+            let elicitation = self.get_client_caps().await.elicitation;
+            if let Some(peer) = peer.filter(|_| elicitation) {
+                match self.confirm_import_by_elicitation(peer, &inspection).await {
+                    Some(true) => {
+                        let outcome = importer.confirm(&inspection, &self.registry);
+                        self.registry.reload();
+                        return match outcome {
+                            Ok(summary) => {
+                                Ok(format!("{}\n\n{}", report, format_import_summary(&summary)))
+                            }
+                            Err(e) => anyhow::bail!(
+                                "{}\n\n❌ **Import Failed**\n\n**Error:** {:#}",
+                                report,
+                                e
+                            ),
+                        };
+                    }
+                    Some(false) => {
+                        return Ok(format!(
+                            "{}\n\n🚫 Import declined; nothing was registered.",
+                            report
+                        ))
+                    }
+                    None => {}
+                }
+            }
+            let token = inspection.token.clone();
+            self.pending_imports
+                .write()
+                .await
+                .insert(token.clone(), inspection);
+            return Ok(format!(
+                "{}\n\n🔒 Nothing was registered. To register exactly what was inspected, call `confirm_import(token: \"{}\")`.",
+                report, token
+            ));
+        }
+
+        match importer.import_all(&args.source, &self.registry, &filter) {
+            Ok(summary) => {
+                // Reload registry to pick up the new tools immediately
+                self.registry.reload();
+
+                let single = summary.imported.len() == 1
+                    && summary.skipped.is_empty()
+                    && summary.failed.is_empty();
+                if !single {
+                    return Ok(format_import_summary(&summary));
+                }
+                let result = &summary.imported[0];
+                Ok(format!(
+                    "✅ **Tool Imported Successfully**\n\n\
+                    - **Name:** {}\n\
+                    - **Type:** {:?}\n\
+                    - **Source:** {}\n\n\
+                    {}\n\n\
+                    🎉 Tool is ready to use! Call it with `call_tool(tool_name: \"{}\")`",
+                    result.tool_name,
+                    result.tool_type,
+                    result.source,
+                    result.message,
+                    result.tool_name
+                ))
+            }
+            Err(e) => {
+                anyhow::bail!(
+                    "❌ **Import Failed**\n\n\
+                    **Source:** {}\n\
+                    **Error:** {}\n\n\
+                    **Supported formats:**\n\
+                    - Git: `https://github.com/user/repo`, pinned with `#<branch|tag|commit>`\n\
+                    - Gist: `gist:GIST_ID` or `https://gist.github.com/user/GIST_ID`\n\
+                    - Package: `/path/to/tool.skillz` or `https://example.com/tool.skillz`\n\
+                    - Local directory: `file:///path/to/tool_dir` or `/path/to/tool_dir`\n\
+                    - Raw files: `https://example.com/tool/manifest.json` or `.../tool/main.py`\n\
+                    - Tool index: `index:NAME` (find names with `search_index`)",
+                    importer.redact(&args.source),
+                    importer.redact(&format!("{:#}", e))
+                )
+            }
+        }
+    }
+
+    /// Ask the user whether to register an inspected import: Some(true) to
+    /// register, Some(false) when declined, None when they couldn't be asked
+    /// (or dismissed the prompt) and the inspection should stay pending
+    async fn confirm_import_by_elicitation(
+        &self,
+        peer: &Peer<RoleServer>,
+        inspection: &importer::Inspection,
+    ) -> Option<bool> {
+        let flags: usize = inspection.tools.iter().map(|t| t.red_flags.len()).sum();
+        let tools: Vec<String> = inspection
+            .tools
+            .iter()
+            .map(|t| format!("{} ({:?})", t.manifest.name, t.manifest.tool_type))
+            .collect();
+        let message = format!(
+            "Register {} from {}? The static scan found {} red flag(s); see the inspection report for details.",
+            tools.join(", "),
+            inspection.source,
+            flags
+        );
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "confirm": {
+                    "type": "boolean",
+                    "description": "Register the inspected tools"
+                }
+            },
+            "required": ["confirm"]
+        });
+        let schema = serde_json::from_value(schema).ok()?;
+        let requested_schema = rmcp::model::ElicitationSchema::from_json_schema(schema).ok()?;
+        let result = peer
+            .create_elicitation(rmcp::model::CreateElicitationRequestParam {
+                message,
+                requested_schema,
+            })
+            .await
+            .ok()?;
+        match result.action {
+            rmcp::model::ElicitationAction::Accept => Some(
+                result
+                    .content
+                    .and_then(|c| c.get("confirm").and_then(|v| v.as_bool()))
+                    .unwrap_or(false),
+            ),
+            rmcp::model::ElicitationAction::Decline => Some(false),
+            rmcp::model::ElicitationAction::Cancel => None,
+        }
+    }
+    /// build_tool, failing with the reply when nothing was built or registered
+    async fn run_build_tool(&self, args: BuildToolArgs) -> Result<String> {
         eprintln!("Building WASM tool: {}", args.name);
 
+        if let Err(e) = registry::validate_tool_name(&args.name) {
+            anyhow::bail!("❌ {}", e);
+        }
+
         // Check if tool exists
-        if self.registry.get_tool(&args.name).is_some() && !args.overwrite.unwrap_or(false) {
-            return format!(
+        let dry_run = args.dry_run.unwrap_or(false);
+        let exists = self.registry.get_tool(&args.name).is_some();
+        if exists && !args.overwrite.unwrap_or(false) && !dry_run {
+            anyhow::bail!(
                 "Error: Tool '{}' already exists. Use overwrite=true to update it.",
                 args.name
             );
         }
 
+        let code = match parse_tool_code(&args.code) {
+            Ok(code) => code,
+            Err(e) => anyhow::bail!(e),
+        };
+        let bump = match parse_version_bump(args.version_bump.as_deref()) {
+            Ok(b) => b,
+            Err(e) => anyhow::bail!(e),
+        };
+        if let Some(version) = &args.version {
+            if let Err(e) = self.registry.validate_explicit_version(&args.name, version) {
+                anyhow::bail!("❌ {}", e);
+            }
+        }
+        let rate_limit = match args.rate_limit.map(registry::RateLimit::from_value) {
+            Some(Ok(limit)) => Some(limit),
+            Some(Err(e)) => anyhow::bail!("❌ {}", e),
+            None => None,
+        };
+        let build_options = match args.build_options.map(builder::BuildOptions::from_value) {
+            Some(Ok(options)) => Some(options).filter(|o| !o.is_default()),
+            Some(Err(e)) => anyhow::bail!("❌ {}", e),
+            None => None,
+        };
+
         // Parse dependencies
         let deps = args.dependencies.clone().unwrap_or_default();
         let wasm_deps = builder::Builder::parse_dependencies(&deps);
 
-        // Compile with dependencies
-        let wasm_bytes =
-            match builder::Builder::compile_tool_with_deps(&args.name, &args.code, &wasm_deps) {
-                Ok(path) => match std::fs::read(&path) {
-                    Ok(bytes) => bytes,
-                    Err(e) => return format!("Error reading compiled WASM: {}", e),
-                },
-                Err(e) => return format!("Compilation error: {}", e),
-            };
+        // Compile with dependencies (served from the build cache when unchanged)
+        let cache_dir = self.registry.storage_dir().join(builder::BUILD_CACHE_DIR);
+        let (wasm_bytes, cached, optimization) = match builder::Builder::compile_tool_cached(
+            &args.name,
+            &code,
+            &wasm_deps,
+            &build_options.clone().unwrap_or_default(),
+            &cache_dir,
+        ) {
+            Ok(output) => match std::fs::read(&output.wasm_path) {
+                Ok(bytes) => (bytes, output.cached, output.optimization),
+                Err(e) => anyhow::bail!("Error reading compiled WASM: {}", e),
+            },
+            Err(e) => anyhow::bail!(format_compile_error(&e)),
+        };
+
+        if dry_run {
+            let mut output = format!(
+                "✅ Dry run: '{}' compiles ({} module). Nothing was registered.",
+                args.name,
+                builder::format_size(wasm_bytes.len() as u64)
+            );
+            if cached {
+                output.push_str("\n⚡ Reused cached build (source unchanged)");
+            }
+            if let Some(report) = optimization {
+                output.push_str(&format!("\n🗜️ wasm-opt: {}", report.summary()));
+            }
+            if exists && !args.overwrite.unwrap_or(false) {
+                output.push_str(&format!(
+                    "\n💡 Tool '{}' already exists; build it with overwrite=true to update it.",
+                    args.name
+                ));
+            }
+            return Ok(output);
+        }
 
         // Build manifest
         let mut manifest = registry::ToolManifest::new(
@@ -691,66 +1513,100 @@ impl AppState {
         manifest.output_schema = args.output_schema.map(registry::ToolSchema::from_value);
         manifest.annotations = args.annotations.map(registry::ToolAnnotations::from_value);
         manifest.wasm_dependencies = deps.clone();
+        manifest.build_options = build_options;
+        manifest.wasm_fuel = args.wasm_fuel;
+        manifest.tags = self.resolve_tags(&args.name, args.tags);
+        if let Some(version) = args.version {
+            manifest.version = version;
+        }
+        manifest.rate_limit = rate_limit;
 
         // Also save the source code so the tool can be recompiled
-        match self
-            .registry
-            .register_wasm_tool(manifest, &wasm_bytes, &args.code)
-        {
-            Ok(config) => {
-                let tool_dir = config.tool_dir.display();
-                let deps_msg = if deps.is_empty() {
-                    String::new()
-                } else {
-                    format!("\n📦 Dependencies: {:?}", deps)
-                };
-                if args.overwrite.unwrap_or(false) {
-                    format!(
-                        "🦀 WASM Tool '{}' updated successfully\n\nDirectory: {}{}",
-                        args.name, tool_dir, deps_msg
-                    )
-                } else {
-                    format!(
-                        "🦀 WASM Tool '{}' built and registered\n\nDirectory: {}{}",
-                        args.name, tool_dir, deps_msg
-                    )
+        Ok(
+            match self
+                .registry
+                .register_wasm_tool_with_bump(manifest, &wasm_bytes, &code, bump)
+            {
+                Ok(config) => {
+                    let tool_dir = config.tool_dir.display();
+                    let mut deps_msg = if deps.is_empty() {
+                        String::new()
+                    } else {
+                        format!("\n📦 Dependencies: {:?}", deps)
+                    };
+                    if cached {
+                        deps_msg.push_str("\n⚡ Reused cached build (source unchanged)");
+                    }
+                    if let Some(report) = optimization {
+                        deps_msg.push_str(&format!("\n🗜️ wasm-opt: {}", report.summary()));
+                    }
+                    if args.overwrite.unwrap_or(false) {
+                        format!(
+                            "🦀 WASM Tool '{}' updated to v{}\n\nDirectory: {}{}",
+                            args.name, config.manifest.version, tool_dir, deps_msg
+                        )
+                    } else {
+                        format!(
+                            "🦀 WASM Tool '{}' built and registered\n\nDirectory: {}{}",
+                            args.name, tool_dir, deps_msg
+                        )
+                    }
                 }
-            }
-            Err(e) => format!("Registration error: {}", e),
-        }
+                Err(e) => anyhow::bail!("Registration error: {}", e),
+            },
+        )
     }
 
-    // ==================== SCRIPT TOOLS (Any Language) ====================
-
-    #[tool(
-        description = r#"Register a script tool in any language (Python, Node.js, Ruby, Bash, etc.). Scripts communicate via JSON-RPC 2.0 over stdin/stdout. CRITICAL: Use sys.stdin.readline() NOT sys.stdin.read() in Python scripts - read() blocks forever! Always call sys.stdout.flush() after printing.
-
-Bidirectional Features (scripts can REQUEST from host):
-- **Elicitation**: Request user input via {"jsonrpc":"2.0","method":"elicitation/create","params":{"message":"prompt","requestedSchema":{"type":"object","properties":{"field":{"type":"string"}}}},"id":1}
-- **Sampling**: Request LLM completion via {"jsonrpc":"2.0","method":"sampling/createMessage","params":{"messages":[{"role":"user","content":{"type":"text","text":"prompt"}}],"maxTokens":100},"id":1}
-- **Memory**: Store/retrieve state via memory/set, memory/get, memory/list
-- **Logging**: Send logs via {"jsonrpc":"2.0","method":"logging/message","params":{"level":"info","message":"text"}}
-- **Progress**: Report progress via {"jsonrpc":"2.0","method":"progress/update","params":{"current":1,"total":10,"message":"step"}}
-
-Note: Check context.capabilities before using elicitation/sampling - not all clients support them."#
-    )]
-    async fn register_script(&self, Parameters(args): Parameters<RegisterScriptArgs>) -> String {
+    /// register_script, failing with the reply when nothing was registered
+    async fn run_register_script(&self, args: RegisterScriptArgs) -> Result<String> {
         eprintln!("Registering script tool: {}", args.name);
 
+        if let Err(e) = registry::validate_tool_name(&args.name) {
+            anyhow::bail!("❌ {}", e);
+        }
+
         // Check if tool exists
         if self.registry.get_tool(&args.name).is_some() && !args.overwrite.unwrap_or(false) {
-            return format!(
+            anyhow::bail!(
                 "Error: Tool '{}' already exists. Use overwrite=true to update it.",
                 args.name
             );
         }
 
+        let bump = match parse_version_bump(args.version_bump.as_deref()) {
+            Ok(b) => b,
+            Err(e) => anyhow::bail!(e),
+        };
+        if let Some(version) = &args.version {
+            if let Err(e) = self.registry.validate_explicit_version(&args.name, version) {
+                anyhow::bail!("❌ {}", e);
+            }
+        }
+        let rate_limit = match args.rate_limit.map(registry::RateLimit::from_value) {
+            Some(Ok(limit)) => Some(limit),
+            Some(Err(e)) => anyhow::bail!("❌ {}", e),
+            None => None,
+        };
+        let arg_mode = match args.arg_mode.as_deref().map(registry::ArgMode::parse) {
+            Some(Some(mode)) => mode,
+            Some(None) => anyhow::bail!("❌ arg_mode must be 'stdin' or 'argv'"),
+            None => registry::ArgMode::default(),
+        };
+        let env = args.env.unwrap_or_default();
+        if let Err(e) = registry::validate_env_names(&env) {
+            anyhow::bail!("❌ {}", e);
+        }
+
         // Build manifest
         let mut manifest = registry::ToolManifest::new(
             args.name.clone(),
             args.description.clone(),
             ToolType::Script,
         );
+        manifest.arg_mode = arg_mode;
+        manifest.ndjson = args.ndjson.unwrap_or(false);
+        manifest.working_dir = args.working_dir.filter(|dir| !dir.trim().is_empty());
+        manifest.env = env;
         manifest.interpreter = args.interpreter.clone();
         manifest.input_schema = args
             .input_schema
@@ -760,23 +1616,38 @@ Note: Check context.capabilities before using elicitation/sampling - not all cli
         manifest.annotations = args.annotations.map(registry::ToolAnnotations::from_value);
         manifest.dependencies = args.dependencies.clone().unwrap_or_default();
         manifest.requires_services = args.requires_services.clone().unwrap_or_default();
+        manifest.tags = self.resolve_tags(&args.name, args.tags);
+        if let Some(version) = args.version {
+            manifest.version = version;
+        }
+        manifest.rate_limit = rate_limit;
 
         // Register the tool (this creates the directory and saves the script)
-        let config = match self.registry.register_tool(manifest, args.code.as_bytes()) {
-            Ok(c) => c,
-            Err(e) => return format!("Registration error: {}", e),
-        };
+        let config =
+            match self
+                .registry
+                .register_tool_with_bump(manifest, args.code.as_bytes(), bump)
+            {
+                Ok(c) => c,
+                Err(e) => anyhow::bail!("Registration error: {}", e),
+            };
 
         // Handle dependencies
         let mut deps_message = String::new();
         if !config.manifest.dependencies.is_empty() {
             let tool_env_path = self.registry.tool_env_path(&args.name);
 
-            match runtime::install_tool_deps(
-                &tool_env_path,
-                args.interpreter.as_deref(),
-                &config.manifest.dependencies,
-            ) {
+            // Installs can take minutes; run off the async executor and stream progress
+            let runtime = self.runtime.clone();
+            let interpreter = args.interpreter.clone();
+            let dependencies = config.manifest.dependencies.clone();
+            let install = tokio::task::spawn_blocking(move || {
+                runtime.install_deps(&tool_env_path, interpreter.as_deref(), &dependencies)
+            })
+            .await
+            .unwrap_or_else(|e| Err(anyhow::anyhow!("Install task failed: {}", e)));
+
+            match install {
                 Ok(result) => {
                     if result.success {
                         let _ = self.registry.mark_deps_installed(&args.name);
@@ -801,218 +1672,1126 @@ Note: Check context.capabilities before using elicitation/sampling - not all cli
             .unwrap_or_default();
 
         let tool_dir = config.tool_dir.display();
-        if args.overwrite.unwrap_or(false) {
+        Ok(if args.overwrite.unwrap_or(false) {
             format!(
-                "📜 Script Tool '{}'{} updated successfully\n\nDirectory: {}{}",
-                args.name, interpreter_info, tool_dir, deps_message
+                "📜 Script Tool '{}'{} updated to v{}\n\nDirectory: {}{}",
+                args.name, interpreter_info, config.manifest.version, tool_dir, deps_message
             )
         } else {
             format!(
                 "📜 Script Tool '{}'{} registered\n\nDirectory: {}{}",
                 args.name, interpreter_info, tool_dir, deps_message
             )
-        }
+        })
     }
 
-    #[tool(description = "Delete a registered tool and clean up its files")]
-    async fn delete_tool(&self, Parameters(args): Parameters<DeleteToolArgs>) -> String {
+    /// delete_tool, failing with the reply when nothing was deleted
+    async fn run_delete_tool(&self, args: DeleteToolArgs) -> Result<String> {
         match self.registry.delete_tool(&args.tool_name) {
-            Ok(true) => format!("🗑️ Tool '{}' deleted successfully", args.tool_name),
-            Ok(false) => format!("Tool '{}' not found", args.tool_name),
-            Err(e) => format!("Error deleting tool: {}", e),
+            Ok(true) => Ok(format!("🗑️ Tool '{}' deleted successfully", args.tool_name)),
+            Ok(false) => anyhow::bail!("Tool '{}' not found", args.tool_name),
+            Err(e) => anyhow::bail!("Error deleting tool: {}", e),
         }
     }
 
-    // ==================== VERSIONING ====================
+    /// execute_code, failing with the reply when the code didn't run cleanly
+    async fn run_execute_code(&self, args: ExecuteCodeArgs) -> Result<String> {
+        let language = args.language.as_deref().unwrap_or("python");
+        let _timeout = args.timeout.unwrap_or(30); // TODO: Implement timeout
 
-    #[tool(
-        description = r#"Manage tool versions. Actions: 'list' (show versions), 'rollback' (restore version), 'info' (current version).
+        // Get available tools
+        let available_tools: Vec<_> = if let Some(ref tool_names) = args.tools {
+            self.registry
+                .list_tools()
+                .into_iter()
+                .filter(|t| tool_names.contains(&t.name().to_string()))
+                .collect()
+        } else {
+            self.registry.list_tools()
+        };
 
-Tools are automatically versioned:
-- Updates auto-backup current version
-- Version auto-increments on update
-- Rollback restores any previous version
+        // Generate tool API stubs
+        let tool_stubs = self.generate_tool_stubs(&available_tools, language);
 
-Example: `version(action: "rollback", tool_name: "my_tool", version: "1.0.0")`"#
-    )]
-    async fn version(&self, Parameters(args): Parameters<VersionArgs>) -> String {
-        match args.action {
-            VersionAction::List => {
-                let name = match args.tool_name {
-                    Some(n) => n,
-                    None => return "❌ tool_name is required for 'list' action".to_string(),
-                };
-                match self.registry.list_versions(&name) {
-                    Ok(versions) if versions.is_empty() => {
-                        format!("No versions found for '{}'", name)
-                    }
-                    Ok(versions) => {
-                        let mut output = format!("## 📦 Versions of '{}'\n\n", name);
-                        for v in versions {
-                            output.push_str(&format!("- {}\n", v));
-                        }
-                        output
-                    }
-                    Err(e) => format!("❌ Error listing versions: {}", e),
-                }
+        // Create the execution script
+        let script = match language {
+            "python" | "python3" => {
+                self.wrap_python_code(&args.code, &tool_stubs, &available_tools)
             }
-            VersionAction::Rollback => {
-                let name = match args.tool_name {
-                    Some(n) => n,
-                    None => return "❌ tool_name is required for 'rollback' action".to_string(),
-                };
-                let version = match args.version {
-                    Some(v) => v,
-                    None => return "❌ version is required for 'rollback' action".to_string(),
-                };
-                match self.registry.rollback(&name, &version) {
-                    Ok(msg) => format!("✅ {}", msg),
-                    Err(e) => format!("❌ Rollback failed: {}", e),
-                }
+            "javascript" | "js" | "node" => {
+                self.wrap_javascript_code(&args.code, &tool_stubs, &available_tools)
             }
-            VersionAction::Info => {
-                let name = match args.tool_name {
-                    Some(n) => n,
-                    None => return "❌ tool_name is required for 'info' action".to_string(),
-                };
-                match self.registry.get_tool(&name) {
-                    Some(tool) => {
-                        let versions = self.registry.list_versions(&name).unwrap_or_default();
-                        format!(
-                            "## 📦 {} v{}\n\n\
-                            - **Type:** {:?}\n\
-                            - **Created:** {}\n\
-                            - **Updated:** {}\n\
-                            - **Available versions:** {}\n",
-                            name,
-                            tool.manifest.version,
-                            tool.manifest.tool_type,
-                            tool.manifest.created_at.as_deref().unwrap_or("unknown"),
-                            tool.manifest.updated_at.as_deref().unwrap_or("unknown"),
-                            versions.len()
-                        )
-                    }
-                    None => format!("Tool '{}' not found", name),
-                }
+            _ => {
+                anyhow::bail!(
+                    "❌ Unsupported language: {}. Use 'python' or 'javascript'.",
+                    language
+                )
             }
-        }
-    }
-
-    // ==================== TOOL EXECUTION ====================
-
-    #[tool(
-        description = "Call a registered tool (WASM or Script). For script tools, arguments are passed via JSON-RPC 2.0."
-    )]
-    #[doc = "NOTE: This tool can ONLY call tools registered within Skillz, not tools from other MCP servers."]
-    async fn call_tool(&self, Parameters(args): Parameters<CallToolArgs>) -> String {
-        eprintln!("Calling tool: {}", args.tool_name);
+        };
 
-        let tool = match self.registry.get_tool(&args.tool_name) {
-            Some(t) => t,
-            None => return format!("Error: Tool '{}' not found", args.tool_name),
+        // Execute in sandbox
+        let interpreter = match language {
+            "python" | "python3" => "python3",
+            "javascript" | "js" | "node" => "node",
+            _ => "python3",
         };
 
-        // Check required services and get service environment variables
-        let required_services = tool.manifest.requires_services.clone();
-        let service_env_vars = if !required_services.is_empty() {
-            match self
-                .service_registry
-                .check_required_services(&required_services)
-            {
-                Ok(vars) => vars,
-                Err(e) => return e,
-            }
-        } else {
-            std::collections::HashMap::new()
+        // Create temp file
+        let ext = match language {
+            "python" | "python3" => "py",
+            "javascript" | "js" | "node" => "js",
+            _ => "py",
         };
 
-        let tool_args = args
-            .arguments
-            .map(|args| serde_json::json!(args))
-            .unwrap_or(serde_json::json!({}));
+        let temp_dir = std::env::temp_dir();
+        let script_path = temp_dir.join(format!("skillz_exec_{}.{}", std::process::id(), ext));
 
-        // Handle pipeline tools specially
-        if *tool.tool_type() == ToolType::Pipeline {
-            return self.execute_pipeline(&tool, tool_args).await;
+        if let Err(e) = std::fs::write(&script_path, &script) {
+            anyhow::bail!("❌ Failed to create execution script: {}", e);
         }
 
-        let tool_config = tool.clone();
-        let mut runtime = self.runtime.clone();
+        // Execute with timeout
+        let output = std::process::Command::new(interpreter)
+            .arg(&script_path)
+            .output();
 
-        // Inject service environment variables
-        for (key, value) in service_env_vars {
-            runtime.set_env_var(key, value);
-        }
+        // Cleanup
+        let _ = std::fs::remove_file(&script_path);
 
-        // Update runtime with actual MCP client capabilities
-        let mcp_caps = self.get_client_caps().await;
-        runtime.update_capabilities(runtime::ClientCapabilities {
-            sampling: mcp_caps.sampling,
-            elicitation: mcp_caps.elicitation,
-            memory: true,    // Memory is always available (server-side)
-            resources: true, // Resources are always available (server-side)
-        });
+        match output {
+            Ok(result) => {
+                let stdout = String::from_utf8_lossy(&result.stdout);
+                let stderr = String::from_utf8_lossy(&result.stderr);
 
-        // Use spawn_blocking for sync operations
-        match tokio::task::spawn_blocking(move || runtime.call_tool(&tool_config, tool_args)).await
-        {
-            Ok(Ok(result)) => result.to_string(),
-            Ok(Err(e)) => format!("Error executing tool: {}", e),
-            Err(e) => format!("Task join error: {}", e),
+                if result.status.success() {
+                    Ok(if stderr.is_empty() {
+                        format!("✅ **Execution Result**\n\n```\n{}\n```", stdout.trim())
+                    } else {
+                        format!(
+                            "✅ **Execution Result**\n\n```\n{}\n```\n\n**Logs:**\n```\n{}\n```",
+                            stdout.trim(),
+                            stderr.trim()
+                        )
+                    })
+                } else {
+                    anyhow::bail!(
+                        "❌ **Execution Failed**\n\n**Error:**\n```\n{}\n```\n\n**Output:**\n```\n{}\n```",
+                        stderr.trim(),
+                        stdout.trim()
+                    )
+                }
+            }
+            Err(e) => anyhow::bail!("❌ Failed to execute: {}", e),
         }
     }
 
-    /// Execute a pipeline tool
-    async fn execute_pipeline(
-        &self,
-        tool: &registry::ToolConfig,
-        input: serde_json::Value,
-    ) -> String {
-        let start_time = std::time::Instant::now();
-        let steps = tool.pipeline_steps();
-
-        let mut step_results: std::collections::HashMap<String, serde_json::Value> =
-            std::collections::HashMap::new();
-        let mut prev_output: Option<serde_json::Value> = None;
-        let mut results: Vec<pipeline::StepResult> = Vec::new();
-        let mut pipeline_success = true;
-
-        for (i, step) in steps.iter().enumerate() {
-            let step_start = std::time::Instant::now();
-
-            // Check condition
-            if let Some(ref condition) = step.condition {
-                match pipeline::PipelineExecutor::evaluate_condition(
-                    condition,
-                    &input,
-                    &step_results,
-                    prev_output.as_ref(),
-                ) {
-                    Ok(true) => {}
-                    Ok(false) => {
-                        results.push(pipeline::StepResult {
-                            step_index: i,
-                            step_name: step.name.clone(),
-                            tool: step.tool.clone(),
-                            success: true,
-                            output: serde_json::json!({"skipped": true, "reason": "condition not met"}),
-                            error: None,
-                            duration_ms: 0,
-                        });
-                        continue;
+    /// memory, failing with the reply when the action failed
+    async fn run_memory(&self, args: MemoryArgs) -> Result<String> {
+        Ok(match args.action.as_str() {
+            "get" => {
+                let key = match &args.key {
+                    Some(k) => k,
+                    None => anyhow::bail!("Error: 'key' is required for get action"),
+                };
+                match self.memory.get(&args.tool_name, key).await {
+                    Ok(Some(value)) => serde_json::to_string_pretty(&value).unwrap_or_else(|_| "null".to_string()),
+                    Ok(None) => "null".to_string(),
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                }
+            }
+            "exists" => {
+                let key = match &args.key {
+                    Some(k) => k,
+                    None => anyhow::bail!("Error: 'key' is required for exists action"),
+                };
+                match self.memory.exists(&args.tool_name, key).await {
+                    Ok(found) => found.to_string(),
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                }
+            }
+            "query" => {
+                let needle = match &args.query {
+                    Some(q) if !q.is_empty() => q,
+                    _ => anyhow::bail!("Error: 'query' is required for query action"),
+                };
+                let limit = args.limit.unwrap_or(DEFAULT_MEMORY_LIST_LIMIT).max(1);
+                match self.memory.query_values(&args.tool_name, needle, Some(limit)).await {
+                    Ok(keys) if keys.is_empty() => {
+                        format!("No values for tool '{}' contain '{}'", args.tool_name, needle)
                     }
-                    Err(e) => {
-                        results.push(pipeline::StepResult {
-                            step_index: i,
-                            step_name: step.name.clone(),
+                    Ok(keys) => format!(
+                        "Keys for '{}' with values containing '{}': {}",
+                        args.tool_name,
+                        needle,
+                        keys.join(", ")
+                    ),
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                }
+            }
+            "store" | "set" => {
+                let key = match &args.key {
+                    Some(k) => k,
+                    None => anyhow::bail!("Error: 'key' is required for store action"),
+                };
+                let value = match &args.value {
+                    Some(v) => v.clone(),
+                    None => anyhow::bail!("Error: 'value' is required for store action"),
+                };
+                match self
+                    .memory
+                    .set_with_ttl(&args.tool_name, key, value, args.ttl_seconds)
+                    .await
+                {
+                    Ok(()) => match args.ttl_seconds.filter(|&t| t > 0) {
+                        Some(ttl) => format!(
+                            "✅ Stored '{}' for tool '{}' (expires in {}s)",
+                            key, args.tool_name, ttl
+                        ),
+                        None => format!("✅ Stored '{}' for tool '{}'", key, args.tool_name),
+                    },
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                }
+            }
+            "delete" | "clear" => {
+                if let Some(key) = &args.key {
+                    // Delete specific key
+                    match self.memory.delete(&args.tool_name, key).await {
+                        Ok(true) => format!("🗑️ Deleted '{}' from tool '{}'", key, args.tool_name),
+                        Ok(false) => format!("Key '{}' not found for tool '{}'", key, args.tool_name),
+                        Err(e) => anyhow::bail!("Error: {}", e),
+                    }
+                } else {
+                    // Clear all keys for tool
+                    match self.memory.clear(&args.tool_name).await {
+                        Ok(count) => format!("🗑️ Cleared {} entries for tool '{}'", count, args.tool_name),
+                        Err(e) => anyhow::bail!("Error: {}", e),
+                    }
+                }
+            }
+            "list" => {
+                let offset = match args.cursor.as_deref().map(str::parse::<usize>) {
+                    None => 0,
+                    Some(Ok(offset)) => offset,
+                    Some(Err(_)) => anyhow::bail!("Error: invalid cursor '{}'", args.cursor.unwrap_or_default()),
+                };
+                let include_values = args.include_values.unwrap_or(false);
+                match self
+                    .memory
+                    .list(
+                        &args.tool_name,
+                        args.prefix.as_deref(),
+                        Some(args.limit.unwrap_or(DEFAULT_MEMORY_LIST_LIMIT).max(1)),
+                        offset,
+                        include_values,
+                    )
+                    .await
+                {
+                    Ok(page) if page.entries.is_empty() => {
+                        format!("No memory stored for tool '{}'", args.tool_name)
+                    }
+                    Ok(page) => {
+                        let mut output = if include_values {
+                            let mut output = format!("Entries for '{}':\n", args.tool_name);
+                            for entry in &page.entries {
+                                let value = entry.value.as_ref().map(|v| v.to_string()).unwrap_or_default();
+                                if entry.truncated {
+                                    output.push_str(&format!(
+                                        "  - {}: {} (truncated, {} bytes)\n",
+                                        entry.key, value, entry.size_bytes
+                                    ));
+                                } else {
+                                    output.push_str(&format!("  - {}: {}\n", entry.key, value));
+                                }
+                            }
+                            output
+                        } else {
+                            let keys: Vec<&str> = page.entries.iter().map(|e| e.key.as_str()).collect();
+                            format!("Keys for '{}': {}", args.tool_name, keys.join(", "))
+                        };
+                        if let Some(next) = page.next_offset {
+                            output.push_str(&format!("\n➡️ More entries: pass cursor=\"{}\"", next));
+                        }
+                        output
+                    }
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                }
+            }
+            "stats" => {
+                match self.memory.stats().await {
+                    Ok(stats) => format!(
+                        "📊 Memory Stats:\n  - Total entries: {}\n  - Tools with memory: {}\n  - Expiring within {}h: {}\n  - Schema version: {}",
+                        stats.total_entries,
+                        stats.total_tools,
+                        memory::EXPIRING_SOON_SECS / 3600,
+                        stats.expiring_soon,
+                        stats.schema_version
+                    ),
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                }
+            }
+            "bulk_store" | "bulk_update" => {
+                let entries: Vec<memory::BulkEntry> = match args.entries {
+                    Some(entries) if !entries.is_empty() => entries
+                        .into_iter()
+                        .map(|e| memory::BulkEntry {
+                            key: e.key,
+                            value: e.value,
+                            ttl_secs: e.ttl_seconds,
+                        })
+                        .collect(),
+                    _ => anyhow::bail!("Error: 'entries' is required for {} action", args.action),
+                };
+                let mode = if args.action == "bulk_update" {
+                    memory::BulkMode::Update
+                } else {
+                    memory::BulkMode::Store
+                };
+                match self.memory.set_many(&args.tool_name, &entries, mode).await {
+                    Ok(statuses) => {
+                        let mut output = format!(
+                            "✅ Wrote {} entries for tool '{}'\n",
+                            statuses.len(),
+                            args.tool_name
+                        );
+                        for (key, status) in &statuses {
+                            output.push_str(&format!("  - {}: {}\n", key, status.as_str()));
+                        }
+                        output
+                    }
+                    Err(e) => anyhow::bail!("❌ {} rolled back, nothing was written: {:#}", args.action, e),
+                }
+            }
+            "bulk_get" => {
+                let keys = match &args.keys {
+                    Some(keys) if !keys.is_empty() => keys,
+                    _ => anyhow::bail!("Error: 'keys' is required for bulk_get action"),
+                };
+                match self.memory.get_many(&args.tool_name, keys).await {
+                    Ok(values) => {
+                        let map: serde_json::Map<String, serde_json::Value> = values
+                            .into_iter()
+                            .map(|(k, v)| (k, v.unwrap_or(serde_json::Value::Null)))
+                            .collect();
+                        serde_json::to_string_pretty(&map).unwrap_or_else(|_| "{}".to_string())
+                    }
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                }
+            }
+            "increment" => {
+                let key = match &args.key {
+                    Some(k) => k,
+                    None => anyhow::bail!("Error: 'key' is required for increment action"),
+                };
+                match self.memory.increment(&args.tool_name, key, args.delta.unwrap_or(1)).await {
+                    Ok(value) => format!("✅ '{}' is now {}", key, value),
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                }
+            }
+            "append" => {
+                let key = match &args.key {
+                    Some(k) => k,
+                    None => anyhow::bail!("Error: 'key' is required for append action"),
+                };
+                let item = match args.value {
+                    Some(v) => v,
+                    None => anyhow::bail!("Error: 'value' is required for append action"),
+                };
+                match self.memory.append(&args.tool_name, key, item, args.max_length).await {
+                    Ok(len) => format!("✅ Appended to '{}' ({} items)", key, len),
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                }
+            }
+            "semantic_search" => {
+                let query = match &args.query {
+                    Some(q) if !q.trim().is_empty() => q,
+                    _ => anyhow::bail!("Error: 'query' is required for semantic_search action"),
+                };
+                let top_k = args.top_k.unwrap_or(memory::DEFAULT_SEARCH_TOP_K).max(1);
+                match self.memory.semantic_search(&args.tool_name, query, top_k).await {
+                    Ok(results) => {
+                        let mut output = String::new();
+                        if results.mode == memory::SearchMode::FullText {
+                            output.push_str("ℹ️ No embedder configured (set [memory] embedding_command or embed_with_sampling in skillz.toml); using full-text search\n");
+                        }
+                        if results.hits.is_empty() {
+                            output.push_str(&format!("No matches for '{}' in tool '{}'", query, args.tool_name));
+                            return Ok(output);
+                        }
+                        output.push_str(&format!("🔎 Top {} matches for '{}':\n", results.hits.len(), query));
+                        for hit in &results.hits {
+                            output.push_str(&format!("  - {} ({:.3}): {}\n", hit.key, hit.score, hit.value));
+                        }
+                        output
+                    }
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                }
+            }
+            "export" => {
+                let tool = (args.tool_name != "*").then_some(args.tool_name.as_str());
+                let export = match self.memory.export(tool).await {
+                    Ok(export) => export,
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                };
+                let json = serde_json::to_string_pretty(&export).unwrap_or_else(|_| "{}".to_string());
+                if json.len() <= memory::EXPORT_INLINE_LIMIT {
+                    return Ok(json);
+                }
+                match write_memory_export(self.registry.storage_dir(), tool, &export) {
+                    Ok(path) => format!(
+                        "📦 Memory export ({}) written to {}\nRestore with memory(action: \"import\", path: \"{}\")",
+                        builder::format_size(json.len() as u64),
+                        path.display(),
+                        path.display()
+                    ),
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                }
+            }
+            "import" => {
+                let strategy = match memory::MergeStrategy::parse(args.merge_strategy.as_deref().unwrap_or("error")) {
+                    Ok(strategy) => strategy,
+                    Err(e) => anyhow::bail!("Error: {}", e),
+                };
+                let data = match (args.value, &args.path) {
+                    (Some(value), _) => value,
+                    (None, Some(path)) => match read_memory_export(std::path::Path::new(path)) {
+                        Ok(data) => data,
+                        Err(e) => anyhow::bail!("Error: {}", e),
+                    },
+                    (None, None) => anyhow::bail!("Error: 'value' or 'path' is required for import action"),
+                };
+                match self.memory.import(&data, strategy).await {
+                    Ok(summary) => format!(
+                        "✅ Imported {} entries ({} existing kept)",
+                        summary.imported, summary.skipped
+                    ),
+                    Err(e) => anyhow::bail!("❌ Import rolled back, nothing was written: {}", e),
+                }
+            }
+            _ => anyhow::bail!(
+                "Unknown action: '{}'. Use: store, get, exists, delete, list, query, stats, bulk_store, bulk_update, bulk_get, increment, append, semantic_search, export, import",
+                args.action
+            ),
+        })
+    }
+}
+
+#[tool_router]
+impl AppState {
+    // ==================== WASM TOOLS (Rust) ====================
+
+    #[tool(
+        description = "Compile and register a new WASM tool from Rust code. Supports Rust crate dependencies and multi-file projects (pass code as a map of path -> contents)! Set overwrite=true to update existing tools, or dry_run=true to only check that the code compiles."
+    )]
+    async fn build_tool(&self, Parameters(args): Parameters<BuildToolArgs>) -> String {
+        self.run_build_tool(args)
+            .await
+            .unwrap_or_else(|e| e.to_string())
+    }
+
+    // ==================== SCRIPT TOOLS (Any Language) ====================
+
+    #[tool(
+        description = r#"Register a script tool in any language (Python, Node.js, Ruby, Bash, etc.). Scripts communicate via JSON-RPC 2.0 over stdin/stdout. CRITICAL: Use sys.stdin.readline() NOT sys.stdin.read() in Python scripts - read() blocks forever! Always call sys.stdout.flush() after printing.
+
+Bidirectional Features (scripts can REQUEST from host):
+- **Elicitation**: Request user input via {"jsonrpc":"2.0","method":"elicitation/create","params":{"message":"prompt","requestedSchema":{"type":"object","properties":{"field":{"type":"string"}}}},"id":1}
+- **Sampling**: Request LLM completion via {"jsonrpc":"2.0","method":"sampling/createMessage","params":{"messages":[{"role":"user","content":{"type":"text","text":"prompt"}}],"maxTokens":100},"id":1}
+- **Memory**: Store/retrieve state via memory/set, memory/get, memory/list; atomic counters and lists via memory/increment, memory/append
+- **Logging**: Send logs via {"jsonrpc":"2.0","method":"logging/message","params":{"level":"info","message":"text"}}
+- **Progress**: Report progress via {"jsonrpc":"2.0","method":"progress/update","params":{"current":1,"total":10,"message":"step"}}
+
+Note: Check context.capabilities before using elicitation/sampling - not all clients support them."#
+    )]
+    async fn register_script(&self, Parameters(args): Parameters<RegisterScriptArgs>) -> String {
+        self.run_register_script(args)
+            .await
+            .unwrap_or_else(|e| e.to_string())
+    }
+
+    #[tool(description = "Delete a registered tool and clean up its files")]
+    async fn delete_tool(&self, Parameters(args): Parameters<DeleteToolArgs>) -> String {
+        self.run_delete_tool(args)
+            .await
+            .unwrap_or_else(|e| e.to_string())
+    }
+
+    #[tool(
+        description = "Get a tool's code for editing: src.rs of a WASM tool, the script of a script tool, or the steps of a pipeline. Also returns the arguments (dependencies, schemas, ...) that rebuild it with build_tool, register_script or pipeline update, so an edited copy can be sent straight back."
+    )]
+    async fn get_tool_source(&self, Parameters(args): Parameters<GetToolSourceArgs>) -> String {
+        match self.registry.get_tool(&args.tool_name) {
+            Some(tool) => format_tool_source(&tool),
+            None => format!("❌ Tool '{}' not found", args.tool_name),
+        }
+    }
+
+    #[tool(
+        description = r#"Manage an existing tool. Actions: 'rename' (move tool, keeping versions and memory), 'copy' (duplicate under a new name with fresh version history and a copy of its memory), 'recompile' (rebuild a WASM tool from its saved source, e.g. after a toolchain update), 'tag' / 'untag' (add or remove tags).
+
+Pipelines that reference a renamed tool are updated automatically unless update_pipelines is false.
+
+Examples:
+- `manage_tool(action: "rename", tool_name: "tmp_tool", new_name: "word_counter")`
+- `manage_tool(action: "tag", tool_name: "word_counter", tags: ["text"])`"#
+    )]
+    async fn manage_tool(&self, Parameters(args): Parameters<ManageToolArgs>) -> String {
+        match args.action.as_str() {
+            "recompile" => match self.registry.recompile_wasm_tool(&args.tool_name) {
+                Ok(config) => format!(
+                    "🦀 WASM Tool '{}' recompiled from saved source (now v{})",
+                    args.tool_name, config.manifest.version
+                ),
+                Err(e) => format!("❌ Recompile failed: {}", format_compile_error(&e)),
+            },
+            "tag" | "untag" => {
+                let tags = match args.tags {
+                    Some(t) if !t.is_empty() => t,
+                    _ => return format!("❌ tags is required for '{}' action", args.action),
+                };
+                let result = if args.action == "tag" {
+                    self.registry.add_tags(&args.tool_name, &tags)
+                } else {
+                    self.registry.remove_tags(&args.tool_name, &tags)
+                };
+                match result {
+                    Ok(current) if current.is_empty() => {
+                        format!("🏷️ '{}' has no tags", args.tool_name)
+                    }
+                    Ok(current) => {
+                        format!("🏷️ '{}' tags: {}", args.tool_name, current.join(", "))
+                    }
+                    Err(e) => format!("❌ Failed to update tags: {}", e),
+                }
+            }
+            "rename" => {
+                let Some(new_name) = args.new_name else {
+                    return "❌ new_name is required for 'rename' action".to_string();
+                };
+                if let Err(e) = self.registry.rename_tool(&args.tool_name, &new_name) {
+                    return format!("❌ Rename failed: {}", e);
+                }
+
+                let mut output = format!("✅ Renamed '{}' to '{}'", args.tool_name, new_name);
+
+                match self.memory.rename_tool(&args.tool_name, &new_name).await {
+                    Ok(0) => {}
+                    Ok(n) => output.push_str(&format!("\n🧠 Migrated {} memory entries", n)),
+                    Err(e) => output.push_str(&format!("\n⚠️ Memory migration failed: {}", e)),
+                }
+
+                if args.update_pipelines.unwrap_or(true) {
+                    match self
+                        .registry
+                        .replace_pipeline_references(&args.tool_name, &new_name)
+                    {
+                        Ok(updated) if !updated.is_empty() => output
+                            .push_str(&format!("\n🔗 Updated pipelines: {}", updated.join(", "))),
+                        Ok(_) => {}
+                        Err(e) => {
+                            output.push_str(&format!("\n⚠️ Failed to update pipelines: {}", e))
+                        }
+                    }
+                }
+
+                // Anything still pointing at the old name needs manual attention
+                let stale = self.registry.pipelines_referencing(&args.tool_name);
+                if !stale.is_empty() {
+                    output.push_str(&format!(
+                        "\n⚠️ Pipelines still referencing '{}': {}",
+                        args.tool_name,
+                        stale.join(", ")
+                    ));
+                }
+
+                output
+            }
+            "copy" => {
+                let Some(new_name) = args.new_name else {
+                    return "❌ new_name is required for 'copy' action".to_string();
+                };
+                if let Err(e) = self.registry.copy_tool(&args.tool_name, &new_name) {
+                    return format!("❌ Copy failed: {}", e);
+                }
+
+                let mut output = format!("✅ Copied '{}' to '{}'", args.tool_name, new_name);
+                match self.memory.copy_tool(&args.tool_name, &new_name).await {
+                    Ok(0) => {}
+                    Ok(n) => output.push_str(&format!("\n🧠 Copied {} memory entries", n)),
+                    Err(e) => output.push_str(&format!("\n⚠️ Memory copy failed: {}", e)),
+                }
+                output
+            }
+            other => format!(
+                "❌ Unknown action '{}'. Valid actions: rename, copy, recompile, tag, untag",
+                other
+            ),
+        }
+    }
+
+    // ==================== VERSIONING ====================
+
+    #[tool(
+        description = r#"Manage tool versions. Actions: 'list' (show versions), 'rollback' (restore version), 'info' (current version), 'diff' (compare two versions), 'prune' (delete old backups).
+
+Tools are automatically versioned:
+- Updates auto-backup current version
+- Version auto-increments on update (patch by default; pass version_bump: "major"/"minor" or an explicit version to build_tool or register_script)
+- Rollback restores any previous version
+- Only the newest backups are kept (10 by default, `[versions] max_versions` in skillz.toml)
+
+Example: `version(action: "rollback", tool_name: "my_tool", version: "1.0.0")`
+Example: `version(action: "diff", tool_name: "my_tool", from: "1.0.0", to: "current")`"#
+    )]
+    async fn version(&self, Parameters(args): Parameters<VersionArgs>) -> String {
+        match args.action {
+            VersionAction::List => {
+                let name = match args.tool_name {
+                    Some(n) => n,
+                    None => return "❌ tool_name is required for 'list' action".to_string(),
+                };
+                match self.registry.list_versions(&name) {
+                    Ok(versions) if versions.is_empty() => {
+                        format!("No versions found for '{}'", name)
+                    }
+                    Ok(versions) => {
+                        let mut output = format!("## 📦 Versions of '{}'\n\n", name);
+                        for v in versions {
+                            output.push_str(&format!("- {}\n", v));
+                        }
+                        output
+                    }
+                    Err(e) => format!("❌ Error listing versions: {}", e),
+                }
+            }
+            VersionAction::Rollback => {
+                let name = match args.tool_name {
+                    Some(n) => n,
+                    None => return "❌ tool_name is required for 'rollback' action".to_string(),
+                };
+                let version = match args.version {
+                    Some(v) => v,
+                    None => return "❌ version is required for 'rollback' action".to_string(),
+                };
+                match self.registry.rollback(&name, &version) {
+                    Ok(msg) => format!("✅ {}", msg),
+                    Err(e) => format!("❌ Rollback failed: {}", e),
+                }
+            }
+            VersionAction::Info => {
+                let name = match args.tool_name {
+                    Some(n) => n,
+                    None => return "❌ tool_name is required for 'info' action".to_string(),
+                };
+                match self.registry.get_tool(&name) {
+                    Some(tool) => {
+                        let versions = self.registry.list_versions(&name).unwrap_or_default();
+                        format!(
+                            "## 📦 {} v{}\n\n\
+                            - **Type:** {:?}\n\
+                            - **Created:** {}\n\
+                            - **Updated:** {}\n\
+                            - **Available versions:** {}\n",
+                            name,
+                            tool.manifest.version,
+                            tool.manifest.tool_type,
+                            tool.manifest.created_at.as_deref().unwrap_or("unknown"),
+                            tool.manifest.updated_at.as_deref().unwrap_or("unknown"),
+                            versions.len()
+                        )
+                    }
+                    None => format!("Tool '{}' not found", name),
+                }
+            }
+            VersionAction::Diff => {
+                let name = match args.tool_name {
+                    Some(n) => n,
+                    None => return "❌ tool_name is required for 'diff' action".to_string(),
+                };
+                let from = match args.from {
+                    Some(v) => v,
+                    None => return "❌ from is required for 'diff' action".to_string(),
+                };
+                let to = args.to.unwrap_or_else(|| "current".to_string());
+                match self.registry.diff_versions(&name, &from, &to) {
+                    Ok(diff) => format_version_diff(&name, &diff),
+                    Err(e) => format!("❌ Diff failed: {}", e),
+                }
+            }
+            VersionAction::Prune => {
+                let name = match args.tool_name {
+                    Some(n) => n,
+                    None => return "❌ tool_name is required for 'prune' action".to_string(),
+                };
+                let keep = args.keep.unwrap_or(match self.registry.max_versions() {
+                    0 => registry::DEFAULT_MAX_VERSIONS,
+                    n => n,
+                });
+                match self.registry.prune_versions(&name, keep) {
+                    Ok(removed) if removed.is_empty() => {
+                        format!("✅ Nothing to prune for '{}' (keeping {})", name, keep)
+                    }
+                    Ok(removed) => format!(
+                        "🧹 Removed {} old version(s) of '{}': {}",
+                        removed.len(),
+                        name,
+                        removed.join(", ")
+                    ),
+                    Err(e) => format!("❌ Prune failed: {}", e),
+                }
+            }
+        }
+    }
+
+    // ==================== TOOL EXECUTION ====================
+
+    #[tool(
+        description = "Call a registered tool (WASM or Script). For script tools, arguments are passed via JSON-RPC 2.0. Results of tools annotated idempotentHint are memoized briefly; pass no_cache=true to force a fresh run."
+    )]
+    #[doc = "NOTE: This tool can ONLY call tools registered within Skillz, not tools from other MCP servers."]
+    async fn call_tool(
+        &self,
+        Parameters(args): Parameters<CallToolArgs>,
+        ctx: RequestContext<RoleServer>,
+    ) -> String {
+        eprintln!("Calling tool: {}", args.tool_name);
+
+        let tool = match self.registry.get_tool(&args.tool_name) {
+            Some(t) => t,
+            None => return format!("Error: Tool '{}' not found", args.tool_name),
+        };
+
+        // Check required services and get service environment variables
+        let service_env_vars = match self.required_service_env(&tool).await {
+            Ok(vars) => vars,
+            Err(e) => return e,
+        };
+
+        let tool_args = args
+            .arguments
+            .map(|args| serde_json::json!(args))
+            .unwrap_or(serde_json::json!({}));
+
+        let use_cache = !args.no_cache.unwrap_or(false);
+
+        // Handle pipeline tools specially
+        if *tool.tool_type() == ToolType::Pipeline {
+            let call = PipelineCall {
+                ct: &ctx.ct,
+                use_cache,
+                chain: &[tool.name().to_string()],
+                progress: None,
+                debug_run: None,
+            };
+            return self
+                .execute_pipeline(&tool, tool_args, true, call, progress_token(&ctx))
+                .await
+                .to_markdown(false);
+        }
+
+        if use_cache {
+            let start = std::time::Instant::now();
+            if let Some(cached) = self.cached_result(&tool, &tool_args).await {
+                self.record_usage(&args.tool_name, &tool_args, start, true)
+                    .await;
+                return cached.to_string();
+            }
+        }
+
+        let tool_config = tool.clone();
+        let mut runtime = self.runtime.clone().with_cancellation(ctx.ct.clone());
+
+        // Inject service environment variables
+        for (key, value) in service_env_vars {
+            runtime.set_env_var(key, value);
+        }
+
+        // Update runtime with actual MCP client capabilities
+        let mcp_caps = self.get_client_caps().await;
+        runtime.update_capabilities(runtime::ClientCapabilities {
+            sampling: mcp_caps.sampling,
+            elicitation: mcp_caps.elicitation,
+            memory: true,    // Memory is always available (server-side)
+            resources: true, // Resources are always available (server-side)
+        });
+
+        // Use spawn_blocking for sync operations
+        let start = std::time::Instant::now();
+        let call_args = tool_args.clone();
+        let result =
+            tokio::task::spawn_blocking(move || runtime.call_tool(&tool_config, call_args)).await;
+        self.record_usage(
+            &args.tool_name,
+            &tool_args,
+            start,
+            matches!(result, Ok(Ok(_))),
+        )
+        .await;
+
+        if let Ok(Ok(ref output)) = result {
+            self.store_result(&tool, &tool_args, output).await;
+        }
+
+        match result {
+            Ok(Ok(result)) => result.to_string(),
+            Ok(Err(e)) if e.is::<runtime::Cancelled>() => {
+                format!("⏹️ Tool '{}' cancelled", args.tool_name)
+            }
+            Ok(Err(e)) if e.is::<runtime::RateLimited>() => format!("⏳ {}", e),
+            Ok(Err(e)) => format!("Error executing tool: {}", e),
+            Err(e) => format!("Task join error: {}", e),
+        }
+    }
+
+    /// Run a step's pipeline tool with the step's args as its $input,
+    /// refusing cycles and too-deep nesting. Returns a boxed future because
+    /// pipelines run pipelines, so the futures are recursive.
+    fn run_nested_pipeline<'a>(
+        &'a self,
+        step: &'a registry::PipelineStep,
+        child: &'a registry::ToolConfig,
+        args: serde_json::Value,
+        call: PipelineCall<'a>,
+    ) -> futures::future::BoxFuture<'a, pipeline::StepAttempts> {
+        Box::pin(async move {
+            let chain = match pipeline::enter_pipeline(call.chain, child.name()) {
+                Ok(chain) => chain,
+                Err(e) => {
+                    return pipeline::StepAttempts {
+                        result: Err(e),
+                        attempts: 1,
+                        attempt_errors: Vec::new(),
+                        cached: false,
+                    }
+                }
+            };
+            pipeline::run_with_retries(step, call.ct, |token| {
+                let args = args.clone();
+                let chain = &chain;
+                async move {
+                    let call = PipelineCall {
+                        ct: &token,
+                        chain,
+                        progress: None,
+                        debug_run: None,
+                        ..call
+                    };
+                    self.execute_pipeline(child, args, true, call, None)
+                        .await
+                        .into_result()
+                }
+            })
+            .await
+        })
+    }
+
+    /// Run a pipeline step's tool, or reuse its output if the step has a
+    /// `cache` block and ran with the same args within the TTL. Outputs are
+    /// stored even when `use_cache` is off, which only skips the lookup.
+    async fn run_step_tool(
+        &self,
+        step: &registry::PipelineStep,
+        step_tool: Option<&registry::ToolConfig>,
+        step_runtime: &runtime::ToolRuntime,
+        args: serde_json::Value,
+        call: PipelineCall<'_>,
+    ) -> pipeline::StepAttempts {
+        let (Some(cache), Some(pipeline_name)) = (step.cache, call.chain.last()) else {
+            return self
+                .run_step_tool_uncached(step, step_tool, step_runtime, args, call)
+                .await;
+        };
+        let version = step_tool.map_or("builtin", |t| t.manifest.version.as_str());
+        let key = memory::pipeline_cache_key(pipeline_name, &step.tool, version, &args);
+
+        if call.use_cache {
+            if let Ok(Some(output)) = self
+                .memory
+                .get(memory::PIPELINE_CACHE_NAMESPACE, &key)
+                .await
+            {
+                eprintln!("💾 Cached output for step '{}'", step.tool);
+                return pipeline::StepAttempts {
+                    result: Ok(output),
+                    attempts: 1,
+                    attempt_errors: Vec::new(),
+                    cached: true,
+                };
+            }
+        }
+
+        let outcome = self
+            .run_step_tool_uncached(step, step_tool, step_runtime, args, call)
+            .await;
+        if let Ok(ref output) = outcome.result {
+            if let Err(e) = self
+                .memory
+                .set_with_ttl(
+                    memory::PIPELINE_CACHE_NAMESPACE,
+                    &key,
+                    output.clone(),
+                    Some(cache.ttl_seconds),
+                )
+                .await
+            {
+                eprintln!("Failed to cache output of step '{}': {}", step.tool, e);
+            }
+        }
+        outcome
+    }
+
+    /// Call a pipeline step's tool with the step's timeout and retry policy,
+    /// reusing and storing memoized results when `use_cache` is set
+    async fn run_step_tool_uncached(
+        &self,
+        step: &registry::PipelineStep,
+        step_tool: Option<&registry::ToolConfig>,
+        step_runtime: &runtime::ToolRuntime,
+        args: serde_json::Value,
+        call: PipelineCall<'_>,
+    ) -> pipeline::StepAttempts {
+        if step.tool == pipeline::TRANSFORM_TOOL {
+            return pipeline::StepAttempts {
+                result: pipeline::run_transform(&args),
+                attempts: 1,
+                attempt_errors: Vec::new(),
+                cached: false,
+            };
+        }
+        if let Some(child) = step_tool.filter(|t| *t.tool_type() == ToolType::Pipeline) {
+            return self.run_nested_pipeline(step, child, args, call).await;
+        }
+        if step_tool.is_none() && PIPELINE_BUILTIN_TOOLS.contains(&step.tool.as_str()) {
+            let start = std::time::Instant::now();
+            let outcome = pipeline::run_with_retries(step, call.ct, |_| {
+                self.call_builtin_tool(&step.tool, args.clone())
+            })
+            .await;
+            self.record_usage(&step.tool, &args, start, outcome.result.is_ok())
+                .await;
+            return outcome;
+        }
+
+        let start = std::time::Instant::now();
+        let cached = match (step_tool, call.use_cache) {
+            (Some(t), true) => self.cached_result(t, &args).await,
+            _ => None,
+        };
+        let outcome = match cached {
+            Some(output) => pipeline::StepAttempts {
+                result: Ok(output),
+                attempts: 1,
+                attempt_errors: Vec::new(),
+                cached: true,
+            },
+            None => {
+                let outcome = pipeline::run_with_retries(step, call.ct, |token| {
+                    let runtime = step_runtime.clone().with_cancellation(token);
+                    let args = args.clone();
+                    async move {
+                        runtime
+                            .call_tool_by_name(&step.tool, Some(args), &self.registry)
+                            .await
+                    }
+                })
+                .await;
+                if let (Ok(output), Some(t)) = (&outcome.result, step_tool) {
+                    self.store_result(t, &args, output).await;
+                }
+                outcome
+            }
+        };
+        self.record_usage(&step.tool, &args, start, outcome.result.is_ok())
+            .await;
+        outcome
+    }
+
+    /// Run a built-in tool for a pipeline step. The tool's reply becomes the
+    /// step output, parsed as JSON when it is JSON (e.g. `memory` get); when
+    /// the tool fails, the step fails with its reply.
+    async fn call_builtin_tool(
+        &self,
+        name: &str,
+        args: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        fn parse<T: serde::de::DeserializeOwned>(name: &str, args: serde_json::Value) -> Result<T> {
+            serde_json::from_value(args).with_context(|| format!("Invalid arguments for {}", name))
+        }
+
+        let text = match name {
+            "build_tool" => self.run_build_tool(parse(name, args)?).await?,
+            "register_script" => self.run_register_script(parse(name, args)?).await?,
+            "import_tool" => self.run_import(parse(name, args)?, None).await?,
+            "list_tools" => self.list_tools(Parameters(parse(name, args)?)).await,
+            "execute_code" => self.run_execute_code(parse(name, args)?).await?,
+            "delete_tool" => self.run_delete_tool(parse(name, args)?).await?,
+            "memory" => self.run_memory(parse(name, args)?).await?,
+            _ => anyhow::bail!("'{}' can't be called from a pipeline", name),
+        };
+        Ok(serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text)))
+    }
+
+    /// Check that pipeline step args reference known tools and convert them
+    /// to validated registry steps. `label` names the steps in errors.
+    fn pipeline_steps_from_args(
+        &self,
+        steps: &[PipelineStepArg],
+        label: &str,
+    ) -> Result<Vec<registry::PipelineStep>, String> {
+        // Validate steps reference existing tools
+        for (i, step) in steps.iter().enumerate() {
+            if self.registry.get_tool(&step.tool).is_none()
+                && !PIPELINE_BUILTIN_TOOLS.contains(&step.tool.as_str())
+            {
+                let hint = match step.tool.as_str() {
+                    "call_tool" | "pipeline" => {
+                        "Use the tool or pipeline name as the step's tool instead."
+                    }
+                    _ => "Create or import it first.",
+                };
+                return Err(format!(
+                    "❌ {} {} references unknown tool '{}'. {}",
+                    label,
+                    i + 1,
+                    step.tool,
+                    hint
+                ));
+            }
+        }
+
+        // Convert to registry PipelineStep
+        let reg_steps: Vec<registry::PipelineStep> = steps
+            .iter()
+            .map(|s| registry::PipelineStep {
+                name: s.name.clone(),
+                tool: s.tool.clone(),
+                args: s.args.clone().unwrap_or(serde_json::json!({})),
+                continue_on_error: s.continue_on_error.unwrap_or(false),
+                condition: s.condition.clone(),
+                timeout_secs: s.timeout_secs,
+                retries: s.retries.unwrap_or(0),
+                retry_delay_ms: s.retry_delay_ms.unwrap_or(0),
+                foreach: s.foreach.clone(),
+                max_concurrency: s.max_concurrency,
+                cache: s.cache.map(Into::into),
+            })
+            .collect();
+        for (i, step) in reg_steps.iter().enumerate() {
+            if let Err(e) = step.validate() {
+                return Err(format!("❌ {} {}: {}", label, i + 1, e));
+            }
+        }
+        Ok(reg_steps)
+    }
+
+    /// Dry-run pipeline steps against the registered tools' output schemas
+    fn dry_run_pipeline(
+        &self,
+        steps: &[registry::PipelineStep],
+        on_error: &[registry::PipelineStep],
+        sample_input: Option<&serde_json::Value>,
+        sample_outputs: Option<&std::collections::HashMap<String, serde_json::Value>>,
+    ) -> Vec<String> {
+        pipeline::dry_run(
+            steps,
+            on_error,
+            sample_input,
+            sample_outputs.unwrap_or(&Default::default()),
+            |tool| match self.registry.get_tool(tool) {
+                Some(t) => Ok(t
+                    .output_schema()
+                    .and_then(|schema| serde_json::to_value(schema).ok())),
+                None if PIPELINE_BUILTIN_TOOLS.contains(&tool) => Ok(None),
+                None => Err(anyhow::anyhow!("unknown tool '{}'", tool)),
+            },
+        )
+    }
+
+    /// Run `steps` in order, recording named outputs in `step_results`.
+    /// Returns the step results, the index of the failed result that ended
+    /// the run (None if every step succeeded or was continued past) and
+    /// whether the run was cancelled. For debug runs,
+    /// each step is checkpointed to memory under `section` as it finishes.
+    async fn run_pipeline_steps(
+        &self,
+        steps: &[registry::PipelineStep],
+        input: &serde_json::Value,
+        step_results: &mut std::collections::HashMap<String, serde_json::Value>,
+        prev_output: &mut Option<serde_json::Value>,
+        call: PipelineCall<'_>,
+        section: &str,
+    ) -> (Vec<pipeline::StepResult>, Option<usize>, bool) {
+        let mut results: Vec<pipeline::StepResult> = Vec::new();
+        let mut failed_at = None;
+        let mut cancelled = false;
+        // Results already reported as finished to the client
+        let mut reported = 0;
+        // Resolved args by step index, and results already checkpointed (debug runs)
+        let mut resolved_args = std::collections::HashMap::new();
+        let mut checkpointed = 0;
+
+        for (i, step) in steps.iter().enumerate() {
+            let step_start = std::time::Instant::now();
+
+            if let Some(progress) = call.progress {
+                for result in &results[reported..] {
+                    progress.step_finished(result).await;
+                }
+                reported = results.len();
+            }
+            if let Some(run_id) = call.debug_run {
+                self.checkpoint_steps(run_id, section, &results[checkpointed..], &resolved_args)
+                    .await;
+                checkpointed = results.len();
+            }
+
+            if call.ct.is_cancelled() {
+                cancelled = true;
+                break;
+            }
+
+            if let Some(progress) = call.progress {
+                progress.step_started(i, step).await;
+            }
+
+            // Check condition
+            if let Some(ref condition) = step.condition {
+                match pipeline::PipelineExecutor::evaluate_condition(
+                    condition,
+                    input,
+                    step_results,
+                    prev_output.as_ref(),
+                ) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        results.push(pipeline::StepResult {
+                            step_index: i,
+                            step_name: step.name.clone(),
+                            tool: step.tool.clone(),
+                            success: true,
+                            output: serde_json::json!({"skipped": true, "reason": "condition not met"}),
+                            error: None,
+                            duration_ms: 0,
+                            attempts: 0,
+                            attempt_errors: Vec::new(),
+                            cached: false,
+                        });
+                        continue;
+                    }
+                    Err(e) => {
+                        results.push(pipeline::StepResult {
+                            step_index: i,
+                            step_name: step.name.clone(),
                             tool: step.tool.clone(),
                             success: false,
                             output: serde_json::json!(null),
                             error: Some(format!("Condition evaluation failed: {}", e)),
                             duration_ms: step_start.elapsed().as_millis() as u64,
+                            attempts: 0,
+                            attempt_errors: Vec::new(),
+                            cached: false,
                         });
                         if !step.continue_on_error {
-                            pipeline_success = false;
+                            failed_at = Some(results.len() - 1);
                             break;
                         }
                         continue;
@@ -1020,14 +2799,48 @@ Example: `version(action: "rollback", tool_name: "my_tool", version: "1.0.0")`"#
                 }
             }
 
-            // Resolve arguments
-            let resolved_args = match pipeline::PipelineExecutor::resolve_args(
-                &step.args,
-                &input,
-                &step_results,
-                prev_output.as_ref(),
-            ) {
-                Ok(args) => args,
+            // Resolve arguments, once per element for foreach steps
+            let step_args = pipeline::PipelineExecutor::step_args(step);
+            let arg_sets = match &step.foreach {
+                None => pipeline::PipelineExecutor::resolve_args(
+                    &step_args,
+                    input,
+                    step_results,
+                    prev_output.as_ref(),
+                )
+                .map(|args| vec![args]),
+                Some(expr) => pipeline::PipelineExecutor::resolve_foreach(
+                    expr,
+                    input,
+                    step_results,
+                    prev_output.as_ref(),
+                )
+                .and_then(|items| {
+                    items
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, item)| {
+                            pipeline::PipelineExecutor::resolve_args(
+                                &step_args,
+                                input,
+                                &pipeline::PipelineExecutor::item_scope(step_results, index, item),
+                                prev_output.as_ref(),
+                            )
+                        })
+                        .collect()
+                }),
+            };
+            let arg_sets = match arg_sets {
+                Ok(arg_sets) => {
+                    if call.debug_run.is_some() {
+                        let args = match step.foreach {
+                            None => arg_sets[0].clone(),
+                            Some(_) => serde_json::Value::Array(arg_sets.clone()),
+                        };
+                        resolved_args.insert(i, args);
+                    }
+                    arg_sets
+                }
                 Err(e) => {
                     results.push(pipeline::StepResult {
                         step_index: i,
@@ -1037,9 +2850,12 @@ Example: `version(action: "rollback", tool_name: "my_tool", version: "1.0.0")`"#
                         output: serde_json::json!(null),
                         error: Some(format!("Failed to resolve arguments: {}", e)),
                         duration_ms: step_start.elapsed().as_millis() as u64,
+                        attempts: 0,
+                        attempt_errors: Vec::new(),
+                        cached: false,
                     });
                     if !step.continue_on_error {
-                        pipeline_success = false;
+                        failed_at = Some(results.len() - 1);
                         break;
                     }
                     continue;
@@ -1050,12 +2866,8 @@ Example: `version(action: "rollback", tool_name: "my_tool", version: "1.0.0")`"#
             // First, check service dependencies and get env vars
             let step_tool = self.registry.get_tool(&step.tool);
             let service_env_vars = if let Some(ref tool_config) = step_tool {
-                let required_services = &tool_config.manifest.requires_services;
-                if !required_services.is_empty() {
-                    match self
-                        .service_registry
-                        .check_required_services(required_services)
-                    {
+                if !tool_config.manifest.requires_services.is_empty() {
+                    match self.required_service_env(tool_config).await {
                         Ok(vars) => vars,
                         Err(e) => {
                             results.push(pipeline::StepResult {
@@ -1066,9 +2878,12 @@ Example: `version(action: "rollback", tool_name: "my_tool", version: "1.0.0")`"#
                                 output: serde_json::json!(null),
                                 error: Some(e),
                                 duration_ms: step_start.elapsed().as_millis() as u64,
+                                attempts: 0,
+                                attempt_errors: Vec::new(),
+                                cached: false,
                             });
                             if !step.continue_on_error {
-                                pipeline_success = false;
+                                failed_at = Some(results.len() - 1);
                                 break;
                             }
                             continue;
@@ -1086,10 +2901,24 @@ Example: `version(action: "rollback", tool_name: "my_tool", version: "1.0.0")`"#
             for (key, value) in service_env_vars {
                 step_runtime.set_env_var(key, value);
             }
+            if let Some(progress) = call.progress {
+                step_runtime.set_logging_handler(progress.step_logger(i, step));
+            }
 
-            let result = step_runtime
-                .call_tool_by_name(&step.tool, Some(resolved_args), &self.registry)
-                .await;
+            let concurrency = step.max_concurrency.unwrap_or(1);
+            let mut outcomes = pipeline::run_foreach(arg_sets, concurrency, |(_, args)| {
+                self.run_step_tool(step, step_tool.as_ref(), &step_runtime, args, call)
+            })
+            .await;
+            let pipeline::StepAttempts {
+                result,
+                attempts,
+                attempt_errors,
+                cached,
+            } = match step.foreach {
+                None => outcomes.remove(0),
+                Some(_) => pipeline::collect_foreach(outcomes),
+            };
             let duration_ms = step_start.elapsed().as_millis() as u64;
 
             let (success, output, error) = match result {
@@ -1097,7 +2926,10 @@ Example: `version(action: "rollback", tool_name: "my_tool", version: "1.0.0")`"#
                     // Result is already a Value, no parsing needed
                     (true, output_value, None)
                 }
-                Err(e) => (false, serde_json::json!(null), Some(e.to_string())),
+                Err(e) => {
+                    cancelled |= e.is::<runtime::Cancelled>();
+                    (false, serde_json::json!(null), Some(e.to_string()))
+                }
             };
 
             let step_result = pipeline::StepResult {
@@ -1108,186 +2940,304 @@ Example: `version(action: "rollback", tool_name: "my_tool", version: "1.0.0")`"#
                 output: output.clone(),
                 error,
                 duration_ms,
+                attempts,
+                attempt_errors,
+                cached,
             };
 
             if let Some(ref name) = step.name {
                 step_results.insert(name.clone(), output.clone());
             }
-            prev_output = Some(output);
+            *prev_output = Some(output);
 
             let step_failed = !step_result.success;
             results.push(step_result);
 
+            if cancelled {
+                break;
+            }
             if step_failed && !step.continue_on_error {
-                pipeline_success = false;
+                failed_at = Some(results.len() - 1);
                 break;
             }
         }
 
-        let total_duration_ms = start_time.elapsed().as_millis() as u64;
-
-        // Format result
-        let mut output = format!(
-            "## {} Pipeline '{}' {}\n\n**Duration:** {}ms\n\n### Steps:\n\n",
-            if pipeline_success { "✅" } else { "❌" },
-            tool.name(),
-            if pipeline_success {
-                "Completed"
-            } else {
-                "Failed"
-            },
-            total_duration_ms
-        );
+        if let Some(progress) = call.progress {
+            for result in &results[reported..] {
+                progress.step_finished(result).await;
+            }
+        }
+        if let Some(run_id) = call.debug_run {
+            self.checkpoint_steps(run_id, section, &results[checkpointed..], &resolved_args)
+                .await;
+        }
 
-        for result in &results {
-            let status = if result.success { "✅" } else { "❌" };
-            let default_name = format!("step_{}", result.step_index + 1);
-            let name = result.step_name.as_deref().unwrap_or(&default_name);
-            output.push_str(&format!(
-                "**{} {}** ({}) - {}ms\n",
-                status, name, result.tool, result.duration_ms
-            ));
+        (results, failed_at, cancelled)
+    }
 
-            if let Some(ref err) = result.error {
-                output.push_str(&format!("  Error: {}\n", err));
-            } else {
-                let output_str = serde_json::to_string_pretty(&result.output).unwrap_or_default();
-                if output_str.len() > 200 {
-                    output.push_str(&format!("  Output: {}...\n", &output_str[..200]));
-                } else {
-                    output.push_str(&format!("  Output: {}\n", output_str));
-                }
+    /// Save step results of a debug run to memory, each with the args the
+    /// step's tool was called with (null if they never resolved; an array
+    /// for foreach steps)
+    async fn checkpoint_steps(
+        &self,
+        run_id: &str,
+        section: &str,
+        results: &[pipeline::StepResult],
+        resolved_args: &std::collections::HashMap<usize, serde_json::Value>,
+    ) {
+        for result in results {
+            let mut checkpoint = serde_json::to_value(result).unwrap_or_default();
+            if let Some(fields) = checkpoint.as_object_mut() {
+                let args = resolved_args.get(&result.step_index).cloned();
+                fields.insert("args".to_string(), args.unwrap_or_default());
+            }
+            let key = memory::pipeline_debug_key(run_id, section, result.step_index);
+            if let Err(e) = self
+                .memory
+                .set_with_ttl(
+                    memory::PIPELINE_DEBUG_NAMESPACE,
+                    &key,
+                    checkpoint,
+                    Some(memory::PIPELINE_DEBUG_TTL_SECS),
+                )
+                .await
+            {
+                eprintln!("Failed to save debug checkpoint {}: {}", key, e);
             }
-            output.push('\n');
         }
+    }
 
-        if let Some(last) = results.last() {
-            output.push_str(&format!(
-                "### Final Result:\n```json\n{}\n```",
-                serde_json::to_string_pretty(&last.output).unwrap_or_default()
-            ));
-        }
+    /// Execute a pipeline tool. Stops between (or during) steps once `call.ct` is cancelled.
+    /// With `stop_on_error` unset, failed steps never end the run.
+    /// `call.chain` lists the running pipelines, outermost first, ending with this one.
+    /// Steps calling idempotentHint tools reuse memoized results when `call.use_cache` is set.
+    /// The outermost pipeline reports each step to the client under `progress_token`
+    /// unless its manifest is `quiet`.
+    async fn execute_pipeline(
+        &self,
+        tool: &registry::ToolConfig,
+        input: serde_json::Value,
+        stop_on_error: bool,
+        call: PipelineCall<'_>,
+        progress_token: Option<String>,
+    ) -> pipeline::PipelineRun {
+        let start_time = std::time::Instant::now();
 
-        output
+        // Without stop_on_error every step behaves as if it set continue_on_error
+        let steps: std::borrow::Cow<[registry::PipelineStep]> = if stop_on_error {
+            std::borrow::Cow::Borrowed(tool.pipeline_steps())
+        } else {
+            tool.pipeline_steps()
+                .iter()
+                .cloned()
+                .map(|step| registry::PipelineStep {
+                    continue_on_error: true,
+                    ..step
+                })
+                .collect()
+        };
+
+        // A quiet pipeline reports through no handlers, which also mutes step logs
+        let progress = if tool.manifest.quiet {
+            Some(pipeline::StepProgress::default())
+        } else if call.chain.len() == 1 {
+            Some(pipeline::StepProgress::new(
+                self.runtime.progress_handler(),
+                self.runtime.logging_handler(),
+                progress_token,
+                steps.len(),
+            ))
+        } else {
+            None
+        };
+
+        let mut step_results: std::collections::HashMap<String, serde_json::Value> =
+            std::collections::HashMap::new();
+        let mut prev_output: Option<serde_json::Value> = None;
+        let (results, failed_at, cancelled) = self
+            .run_pipeline_steps(
+                &steps,
+                &input,
+                &mut step_results,
+                &mut prev_output,
+                PipelineCall {
+                    progress: progress.as_ref(),
+                    ..call
+                },
+                "step",
+            )
+            .await;
+        let pipeline_success = failed_at.is_none() && !cancelled;
+
+        // Run the on_error steps for the failure that ended the run
+        let on_error_results = match failed_at {
+            Some(failed)
+                if !pipeline_success && !cancelled && !tool.on_error_steps().is_empty() =>
+            {
+                step_results.insert(
+                    "error".to_string(),
+                    pipeline::PipelineExecutor::error_info(&results[failed]),
+                );
+                let (handler_results, _, _) = self
+                    .run_pipeline_steps(
+                        tool.on_error_steps(),
+                        &input,
+                        &mut step_results,
+                        &mut prev_output,
+                        PipelineCall {
+                            progress: None,
+                            ..call
+                        },
+                        "on_error",
+                    )
+                    .await;
+                handler_results
+            }
+            _ => Vec::new(),
+        };
+
+        self.record_usage(tool.name(), &input, start_time, pipeline_success)
+            .await;
+
+        pipeline::PipelineRun {
+            pipeline: tool.name().to_string(),
+            success: pipeline_success,
+            cancelled,
+            duration_ms: start_time.elapsed().as_millis() as u64,
+            steps: results,
+            on_error: on_error_results,
+            debug_run: call.debug_run.map(str::to_string),
+        }
     }
 
     // ==================== TOOL LISTING ====================
 
-    #[tool(description = "List all available tools (both WASM and Script tools)")]
-    async fn list_tools(&self) -> String {
-        let tools = self.registry.list_tools();
-        if tools.is_empty() {
-            return "No tools registered yet.\n\n• Use `build_tool` to create Rust/WASM tools\n• Use `register_script` to create tools in any language".to_string();
-        }
+    #[tool(
+        description = "Show usage statistics per tool: call count, failures, average/total duration and last use. Registered tools that were never called are included so unused tools are easy to spot."
+    )]
+    async fn tool_stats(&self, Parameters(args): Parameters<ToolStatsArgs>) -> String {
+        let recorded = match self.memory.all_tool_usage().await {
+            Ok(s) => s,
+            Err(e) => return format!("❌ Failed to read usage statistics: {}", e),
+        };
 
-        let wasm_tools: Vec<_> = tools
-            .iter()
-            .filter(|t| *t.tool_type() == ToolType::Wasm)
-            .collect();
-        let script_tools: Vec<_> = tools
-            .iter()
-            .filter(|t| *t.tool_type() == ToolType::Script)
-            .collect();
-        let pipeline_tools: Vec<_> = tools
-            .iter()
-            .filter(|t| *t.tool_type() == ToolType::Pipeline)
-            .collect();
-        let external_tools: Vec<_> = tools
+        let mut by_name: std::collections::HashMap<String, memory::ToolUsageStats> =
+            recorded.into_iter().map(|s| (s.tool.clone(), s)).collect();
+        let mut stats: Vec<memory::ToolUsageStats> = self
+            .registry
+            .list_tools()
             .iter()
-            .filter(|t| *t.tool_type() == ToolType::Mcp)
+            .map(|t| {
+                by_name
+                    .remove(t.name())
+                    .unwrap_or_else(|| memory::ToolUsageStats {
+                        tool: t.name().to_string(),
+                        ..Default::default()
+                    })
+            })
             .collect();
-        let mcp_servers: Vec<_> = tools.iter().filter(|t| t.mcp_server().is_some()).collect();
-
-        let mut output = format!("📦 Available Tools ({} total)\n\n", tools.len());
-
-        if !mcp_servers.is_empty() {
-            output.push_str(&format!(
-                "### 🌐 External MCP Servers ({})\n\n",
-                mcp_servers.len()
-            ));
-            for tool in &mcp_servers {
-                output.push_str(&format!("• **{}** - {}\n", tool.name(), tool.description()));
-            }
-            output.push('\n');
-        }
 
-        if !wasm_tools.is_empty() {
-            output.push_str(&format!("### 🦀 WASM Tools ({})\n\n", wasm_tools.len()));
-            for tool in wasm_tools {
-                output.push_str(&format!("• **{}** - {}\n", tool.name(), tool.description()));
+        if let Some(ref name) = args.tool_name {
+            stats.retain(|s| &s.tool == name);
+            if stats.is_empty() {
+                return format!("Tool '{}' not found", name);
             }
-            output.push('\n');
         }
 
-        if !script_tools.is_empty() {
-            output.push_str(&format!("### 📜 Script Tools ({})\n\n", script_tools.len()));
-            for tool in script_tools {
-                let interpreter = tool.interpreter().unwrap_or("executable");
-                output.push_str(&format!(
-                    "• **{}** [{}] - {}\n",
-                    tool.name(),
-                    interpreter,
-                    tool.description()
-                ));
+        match args.sort_by.as_deref().unwrap_or("calls") {
+            "calls" => stats.sort_by(|a, b| b.calls.cmp(&a.calls).then(a.tool.cmp(&b.tool))),
+            // "YYYY-MM-DD HH:MM:SS" sorts chronologically as a string; never-used last
+            "last_used" => {
+                stats.sort_by(|a, b| b.last_called.cmp(&a.last_called).then(a.tool.cmp(&b.tool)))
+            }
+            "failures" => {
+                stats.sort_by(|a, b| b.failures.cmp(&a.failures).then(a.tool.cmp(&b.tool)))
+            }
+            "duration" => stats.sort_by(|a, b| {
+                b.avg_duration_ms()
+                    .cmp(&a.avg_duration_ms())
+                    .then(a.tool.cmp(&b.tool))
+            }),
+            "name" => stats.sort_by(|a, b| a.tool.cmp(&b.tool)),
+            other => {
+                return format!(
+                    "❌ Unknown sort_by '{}'. Use calls, last_used, failures, duration, or name",
+                    other
+                )
             }
-            output.push('\n');
         }
 
-        if !pipeline_tools.is_empty() {
+        let mut output = format!("## 📊 Tool Usage ({} tools)\n\n", stats.len());
+        output.push_str("| Tool | Calls | Failures | Avg ms | Total ms | Last used (UTC) |\n");
+        output.push_str("|------|------:|---------:|-------:|---------:|-----------------|\n");
+        for s in &stats {
             output.push_str(&format!(
-                "### ⛓️ Pipeline Tools ({})\n\n",
-                pipeline_tools.len()
+                "| {} | {} | {} | {} | {} | {} |\n",
+                s.tool,
+                s.calls,
+                s.failures,
+                s.avg_duration_ms(),
+                s.total_duration_ms,
+                s.last_called.as_deref().unwrap_or("never")
             ));
-            for tool in pipeline_tools {
-                output.push_str(&format!("• **{}** - {}\n", tool.name(), tool.description()));
-            }
-            output.push('\n');
         }
 
-        if !external_tools.is_empty() {
-            // Group external tools by namespace
-            let mut by_namespace: std::collections::HashMap<String, Vec<_>> =
-                std::collections::HashMap::new();
-            for tool in &external_tools {
-                if tool.mcp_server().is_none() {
-                    let ns = tool
-                        .namespace
-                        .clone()
-                        .unwrap_or_else(|| "unknown".to_string());
-                    by_namespace.entry(ns).or_default().push(tool);
-                }
-            }
+        let unused = stats.iter().filter(|s| s.calls == 0).count();
+        if unused > 0 {
+            output.push_str(&format!("\n💡 {} tool(s) have never been called.", unused));
+        }
 
-            if !by_namespace.is_empty() {
-                output.push_str(&format!(
-                    "### 🔗 Proxied Tools ({})\n\n",
-                    external_tools.len() - mcp_servers.len()
-                ));
-                for (namespace, ns_tools) in by_namespace.iter() {
-                    output.push_str(&format!("**[{}]** ({} tools)\n", namespace, ns_tools.len()));
-                    for tool in ns_tools {
-                        // Strip the namespace prefix for cleaner display
-                        let short_name = tool
-                            .name()
-                            .strip_prefix(&format!("{}_", namespace))
-                            .unwrap_or(tool.name());
-                        output.push_str(&format!(
-                            "  • `{}` - {}\n",
-                            short_name,
-                            tool.description()
-                                .strip_prefix(&format!("[{}] ", namespace))
-                                .unwrap_or(tool.description())
-                        ));
-                    }
-                }
-                output.push('\n');
-            }
+        output
+    }
+
+    #[tool(
+        description = "Show recent tool executions from the audit log (newest first): tool, outcome, duration, timestamp and arguments hash. Filter by tool_name and success. Pipelines are logged as a whole and per step."
+    )]
+    async fn history(&self, Parameters(args): Parameters<HistoryArgs>) -> String {
+        let executions = match self
+            .memory
+            .executions(
+                args.tool_name.as_deref(),
+                args.success,
+                args.limit.unwrap_or(20),
+            )
+            .await
+        {
+            Ok(e) => e,
+            Err(e) => return format!("❌ Failed to read execution history: {}", e),
+        };
+
+        if executions.is_empty() {
+            return "No executions recorded yet.".to_string();
         }
 
-        output.push_str("\n💡 Use `call_tool(tool_name: \"...\")` to execute any tool.");
+        let mut output = format!("## 📜 Execution History ({} entries)\n\n", executions.len());
+        output.push_str("| Time (UTC) | Tool | Result | Duration ms | Args hash |\n");
+        output.push_str("|------------|------|--------|------------:|-----------|\n");
+        for e in &executions {
+            output.push_str(&format!(
+                "| {} | {} | {} | {} | `{}` |\n",
+                e.executed_at,
+                e.tool_name,
+                if e.success { "✅" } else { "❌" },
+                e.duration_ms,
+                &e.args_hash[..12.min(e.args_hash.len())]
+            ));
+        }
         output
     }
 
+    #[tool(
+        description = "List all available tools (both WASM and Script tools). Filter with tag, or use group_by: \"tag\" to group by tag."
+    )]
+    async fn list_tools(&self, Parameters(args): Parameters<ListToolsArgs>) -> String {
+        format_tool_list(
+            &self.registry,
+            args.tag.as_deref(),
+            args.group_by.as_deref(),
+        )
+    }
+
     // ==================== CODE EXECUTION MODE ====================
 
     #[tool(
@@ -1295,141 +3245,220 @@ Example: `version(action: "rollback", tool_name: "my_tool", version: "1.0.0")`"#
     )]
     #[doc = "NOTE: This tool can ONLY access tools registered within Skillz, not tools from other MCP servers."]
     async fn execute_code(&self, Parameters(args): Parameters<ExecuteCodeArgs>) -> String {
-        let language = args.language.as_deref().unwrap_or("python");
-        let _timeout = args.timeout.unwrap_or(30); // TODO: Implement timeout
+        self.run_execute_code(args)
+            .await
+            .unwrap_or_else(|e| e.to_string())
+    }
 
-        // Get available tools
-        let available_tools: Vec<_> = if let Some(ref tool_names) = args.tools {
-            self.registry
-                .list_tools()
-                .into_iter()
-                .filter(|t| tool_names.contains(&t.name().to_string()))
-                .collect()
-        } else {
-            self.registry.list_tools()
-        };
+    // ==================== TOOL IMPORT ====================
 
-        // Generate tool API stubs
-        let tool_stubs = self.generate_tool_stubs(&available_tools, language);
+    #[tool(
+        description = "Import a tool from an external source (git repository, GitHub gist, .skillz package, local directory or raw file URL). Supports: git URLs (https://github.com/user/repo), refs pinning a branch, tag or full commit hash (url#v1.2.0; repositories are cloned shallowly and the resolved commit is recorded), gists (gist:ID or https://gist.github.com/user/ID), .skillz packages from export_tool (local path or URL), copies of local tool directories (file:///path or an absolute path, outside TOOLS_DIR), and raw manifest.json or script URLs (the other files are fetched from the same URL directory). Dependencies are installed on import for packages, directories and raw URLs. Private repositories and gists use SKILLZ_GIT_TOKEN or auth_token, which is never stored or echoed. With inspect=true nothing is registered: the source is fetched into quarantine and its manifest, dependencies, code and red flags are reported, to be registered with confirm_import (or after a confirmation prompt when the client supports elicitation)."
+    )]
+    async fn import_tool(
+        &self,
+        Parameters(args): Parameters<ImportToolArgs>,
+        ctx: RequestContext<RoleServer>,
+    ) -> String {
+        self.run_import(args, Some(&ctx.peer))
+            .await
+            .unwrap_or_else(|e| e.to_string())
+    }
 
-        // Create the execution script
-        let script = match language {
-            "python" | "python3" => {
-                self.wrap_python_code(&args.code, &tool_stubs, &available_tools)
-            }
-            "javascript" | "js" | "node" => {
-                self.wrap_javascript_code(&args.code, &tool_stubs, &available_tools)
-            }
-            _ => {
-                return format!(
-                    "❌ Unsupported language: {}. Use 'python' or 'javascript'.",
-                    language
-                )
-            }
+    #[tool(
+        description = "Search the community tool index (SKILLZ_TOOL_INDEX) by name, description and tags. Install a result with import_tool(source: \"index:NAME\"), which verifies the tool against the index's checksum before registering it."
+    )]
+    async fn search_index(&self, Parameters(args): Parameters<SearchIndexArgs>) -> String {
+        let location = tool_index::index_location();
+        let index = match tokio::task::spawn_blocking({
+            let location = location.clone();
+            move || tool_index::ToolIndex::load(&location)
+        })
+        .await
+        {
+            Ok(Ok(index)) => index,
+            Ok(Err(e)) => return format!("❌ {:#}", e),
+            Err(e) => return format!("❌ Failed to load the tool index: {}", e),
         };
+        let found = index.search(&args.query);
+        if found.is_empty() {
+            return format!(
+                "🔎 No tools in the tool index ({}) match '{}'",
+                location, args.query
+            );
+        }
 
-        // Execute in sandbox
-        let interpreter = match language {
-            "python" | "python3" => "python3",
-            "javascript" | "js" | "node" => "node",
-            _ => "python3",
-        };
+        let mut output = format!(
+            "🔎 **{} tool(s)** in the tool index ({}) matching '{}'\n\n",
+            found.len(),
+            location,
+            args.query
+        );
+        for entry in found {
+            let version = entry
+                .version
+                .as_ref()
+                .map(|v| format!(" v{}", v))
+                .unwrap_or_default();
+            let installed = if self.registry.get_tool(&entry.name).is_some() {
+                " (installed)"
+            } else {
+                ""
+            };
+            output.push_str(&format!(
+                "• **{}**{}{} - {}\n",
+                entry.name, version, installed, entry.description
+            ));
+            if !entry.tags.is_empty() {
+                output.push_str(&format!("  Tags: {}\n", entry.tags.join(", ")));
+            }
+            output.push_str(&format!(
+                "  Source: {}\n  Install: `import_tool(source: \"index:{}\")`\n",
+                entry.source, entry.name
+            ));
+        }
+        output.trim_end().to_string()
+    }
 
-        // Create temp file
-        let ext = match language {
-            "python" | "python3" => "py",
-            "javascript" | "js" | "node" => "js",
-            _ => "py",
+    #[tool(
+        description = "Register the tools of an import inspected with import_tool(inspect=true), from the quarantined copy that was inspected. Existing tools are only replaced when the import asked for overwrite or overwrite_tools."
+    )]
+    async fn confirm_import(&self, Parameters(args): Parameters<ConfirmImportArgs>) -> String {
+        let Some(inspection) = self.pending_imports.write().await.remove(&args.token) else {
+            return format!(
+                "❌ No pending import with token '{}'. Inspect it again with import_tool(inspect=true); pending imports don't survive a restart.",
+                args.token
+            );
         };
-
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join(format!("skillz_exec_{}.{}", std::process::id(), ext));
-
-        if let Err(e) = std::fs::write(&script_path, &script) {
-            return format!("❌ Failed to create execution script: {}", e);
+        let importer = importer::Importer::new(self.registry.storage_dir().to_path_buf());
+        match importer.confirm(&inspection, &self.registry) {
+            Ok(summary) => {
+                self.registry.reload();
+                format_import_summary(&summary)
+            }
+            Err(e) => format!(
+                "❌ **Import Failed**\n\n**Source:** {}\n**Error:** {:#}",
+                inspection.source, e
+            ),
         }
+    }
 
-        // Execute with timeout
-        let output = std::process::Command::new(interpreter)
-            .arg(&script_path)
-            .output();
-
-        // Cleanup
-        let _ = std::fs::remove_file(&script_path);
-
-        match output {
+    #[tool(
+        description = "Update an imported tool from the source recorded when it was imported (git repository, gist, package, directory or raw URL). Shows what changed and re-registers it, backing up the current version first (bumping the patch version if the source didn't raise it). Refuses if the tool was changed locally since import unless force=true."
+    )]
+    async fn update_tool(&self, Parameters(args): Parameters<UpdateToolArgs>) -> String {
+        let importer = importer::Importer::new(self.registry.storage_dir().to_path_buf())
+            .with_auth_token(args.auth_token.clone());
+        match importer.update(&args.tool_name, &self.registry, args.force.unwrap_or(false)) {
             Ok(result) => {
-                let stdout = String::from_utf8_lossy(&result.stdout);
-                let stderr = String::from_utf8_lossy(&result.stderr);
-
-                if result.status.success() {
-                    if stderr.is_empty() {
-                        format!("✅ **Execution Result**\n\n```\n{}\n```", stdout.trim())
-                    } else {
-                        format!(
-                            "✅ **Execution Result**\n\n```\n{}\n```\n\n**Logs:**\n```\n{}\n```",
-                            stdout.trim(),
-                            stderr.trim()
-                        )
-                    }
-                } else {
-                    format!(
-                        "❌ **Execution Failed**\n\n**Error:**\n```\n{}\n```\n\n**Output:**\n```\n{}\n```",
-                        stderr.trim(),
-                        stdout.trim()
-                    )
-                }
+                self.registry.reload();
+                format_update_result(&result)
             }
-            Err(e) => format!("❌ Failed to execute: {}", e),
+            Err(e) => format!(
+                "❌ **Update Failed** for '{}'\n\n**Error:** {}",
+                args.tool_name,
+                importer.redact(&format!("{:#}", e))
+            ),
         }
     }
 
-    // ==================== TOOL IMPORT ====================
-
     #[tool(
-        description = "Import a tool from an external source (git repository or GitHub gist). Supports: git URLs (https://github.com/user/repo), branch specifiers (url#branch), and gists (gist:ID or https://gist.github.com/user/ID)."
+        description = "Export a tool as a portable .skillz package (tar.gz with manifest, code, and metadata; no env/, versions/, or secret values). Writes to TOOLS_DIR/exports/ or, with inline=true, returns base64 for small tools."
     )]
-    async fn import_tool(&self, Parameters(args): Parameters<ImportToolArgs>) -> String {
-        eprintln!("Importing tool from: {}", args.source);
+    async fn export_tool(&self, Parameters(args): Parameters<ExportToolArgs>) -> String {
+        if args.inline.unwrap_or(false) {
+            return match exporter::Exporter::package(&self.registry, &args.tool_name) {
+                Ok(bytes) if bytes.len() > exporter::INLINE_LIMIT => format!(
+                    "❌ Package for '{}' is {} (inline limit {}). Export without inline to write a file.",
+                    args.tool_name,
+                    builder::format_size(bytes.len() as u64),
+                    builder::format_size(exporter::INLINE_LIMIT as u64)
+                ),
+                Ok(bytes) => format!(
+                    "📦 **Exported '{}'** ({})\n\nBase64 `.{}` package:\n```\n{}\n```",
+                    args.tool_name,
+                    builder::format_size(bytes.len() as u64),
+                    exporter::PACKAGE_EXTENSION,
+                    exporter::Exporter::encode(&bytes)
+                ),
+                Err(e) => format!("❌ Export failed: {}", e),
+            };
+        }
 
-        let importer = importer::Importer::new(self.registry.storage_dir().to_path_buf());
+        let exports_dir = self.registry.storage_dir().join(exporter::EXPORTS_DIR);
+        match exporter::Exporter::export_to_dir(&self.registry, &args.tool_name, &exports_dir) {
+            Ok(path) => format!(
+                "📦 **Exported '{}'**\n\n- **Package:** {}",
+                args.tool_name,
+                path.display()
+            ),
+            Err(e) => format!("❌ Export failed: {}", e),
+        }
+    }
 
-        match importer.import(
-            &args.source,
-            &self.registry,
-            args.overwrite.unwrap_or(false),
-        ) {
-            Ok(result) => {
-                // Reload registry to pick up the new tool immediately
-                self.registry.reload();
+    #[tool(
+        description = "Share a tool as a GitHub gist: manifest.json (environment variable values stripped) plus the script or src.rs. Needs a token with the gist scope (SKILLZ_GIT_TOKEN or auth_token). Sharing the same tool again updates its gist. Returns the gist URL and the import_tool call others run to install it."
+    )]
+    async fn share_tool(&self, Parameters(args): Parameters<ShareToolArgs>) -> String {
+        let token = args
+            .auth_token
+            .clone()
+            .or_else(|| std::env::var(importer::GIT_TOKEN_ENV).ok())
+            .filter(|token| !token.trim().is_empty());
+        let Some(token) = token else {
+            return format!(
+                "❌ Sharing needs a GitHub token with the gist scope: set {} or pass auth_token",
+                importer::GIT_TOKEN_ENV
+            );
+        };
 
-                format!(
-                    "✅ **Tool Imported Successfully**\n\n\
-                    - **Name:** {}\n\
-                    - **Type:** {:?}\n\
-                    - **Source:** {}\n\n\
-                    {}\n\n\
-                    🎉 Tool is ready to use! Call it with `call_tool(tool_name: \"{}\")`",
-                    result.tool_name,
-                    result.tool_type,
-                    result.source,
-                    result.message,
-                    result.tool_name
-                )
-            }
-            Err(e) => {
-                format!(
-                    "❌ **Import Failed**\n\n\
-                    **Source:** {}\n\
-                    **Error:** {}\n\n\
-                    **Supported formats:**\n\
-                    - Git: `https://github.com/user/repo` or `https://github.com/user/repo#branch`\n\
-                    - Gist: `gist:GIST_ID` or `https://gist.github.com/user/GIST_ID`",
-                    args.source,
-                    e
-                )
-            }
+        let redact = |text: String| text.replace(&token, "***");
+        let sharer = exporter::GistSharer::new(token.clone());
+        let shared = match sharer
+            .share(
+                &self.registry,
+                &args.tool_name,
+                args.public.unwrap_or(false),
+                args.new_gist.unwrap_or(false),
+            )
+            .await
+        {
+            Ok(shared) => shared,
+            Err(e) => return format!("❌ Share failed: {}", redact(format!("{:#}", e))),
+        };
+
+        let mut output = format!(
+            "🔗 **{} '{}'**\n\n\
+            - **Gist:** {}\n\
+            - **Files:** {}\n\n\
+            To install it elsewhere:\n```\nimport_tool(source: \"gist:{}\")\n```",
+            if shared.updated {
+                "Updated the gist for"
+            } else {
+                "Shared"
+            },
+            args.tool_name,
+            shared.url,
+            shared.files.join(", "),
+            shared.id
+        );
+        if self
+            .registry
+            .get_tool(&args.tool_name)
+            .is_some_and(|t| *t.tool_type() == ToolType::Wasm)
+        {
+            output.push_str(&format!(
+                "\nThe compiled module isn't in the gist; after importing, build it with `manage_tool(action: \"recompile\", tool_name: \"{}\")`.",
+                args.tool_name
+            ));
+        }
+        if !shared.required_env.is_empty() {
+            output.push_str(&format!(
+                "\n\nRequired environment variables (values not shared): {}",
+                shared.required_env.join(", ")
+            ));
         }
+        output
     }
 
     // ==================== EXTERNAL MCP SERVERS ====================
@@ -1594,11 +3623,14 @@ import_mcp(name: "github", command: "uvx", args: ["mcp-server-github"])"#
     // ==================== DOCKER SERVICES ====================
 
     #[tool(
-        description = r#"Manage Docker services for tools. Define, start, stop, and manage containers that tools depend on.
+        description = r#"Manage Docker services for tools. Define, start, stop, and manage containers that tools depend on. Runs on Docker, or Podman when Docker isn't installed (SKILLZ_CONTAINER_ENGINE picks one).
 
-Actions: 'define', 'list', 'start', 'stop', 'remove', 'logs', 'status', 'prune'
+Actions: 'define', 'presets', 'import_compose', 'list', 'start', 'stop', 'remove', 'logs', 'status', 'backup', 'restore', 'run_once', 'prune'
 
-Define a reusable service:
+Common dependencies have presets (postgres, redis, mysql, mongodb, minio, rabbitmq) with healthchecks and volumes set up:
+services(action: "define", preset: "postgres", name: "postgres", overrides: { env: { "POSTGRES_PASSWORD": "dev" } })
+
+Or define a reusable service yourself:
 services(
   action: "define",
   name: "postgres",
@@ -1609,60 +3641,212 @@ services(
   healthcheck: { cmd: "pg_isready -U postgres", interval: "2s", retries: 15 }
 )
 
-Then in your script tool, use requires_services: ["postgres"] to ensure it's running.
-The tool will receive POSTGRES_HOST, POSTGRES_PORT environment variables.
+Then in your script tool, use requires_services: ["postgres"] to ensure it's running (set SKILLZ_AUTO_START_SERVICES=1 to start stopped services automatically).
+The tool will receive POSTGRES_HOST, POSTGRES_PORT environment variables (presets add more, like POSTGRES_URL).
 
 Other actions:
+- presets: List the built-in presets and the env vars they export
 - list: Show all defined services and their status
 - start: Start a service (services(action: "start", name: "postgres"))
 - stop: Stop a service (keeps container for restart)
 - remove: Remove service definition and container (remove_volumes: true to also delete volumes)
-- logs: View logs (tail: 50 for last 50 lines)
+- logs: View logs (tail: 50 for last 50 lines, since: "10m"); with follow: true, new lines are streamed as log notifications for duration_secs (default 30) or until the request is cancelled
 - status: Check if service is running/healthy
-- prune: Remove stopped containers (include_volumes: true for unused volumes)"#
+- backup: Archive the service's named volumes to TOOLS_DIR/backups/<name>-<time>.tar.gz (output_dir to change), e.g. before remove_volumes or prune
+- restore: Restore a backup (archive: path) into the service's volumes; the service must be stopped, and volumes holding data are only replaced with force: true
+- run_once: Run a command in a throwaway container and return its output, for containerized CLIs without defining a service (services(action: "run_once", image: "dpokidov/imagemagick", command: ["-version"], env: {...})); it joins the skillz_services network, is removed when done and killed after timeout_secs (default 60, max 600)
+- prune: Remove stopped containers (include_volumes: true for unused volumes)
+- import_compose: Define services from a docker-compose file (services(action: "import_compose", path_or_content: "./docker-compose.yml")); names become <prefix>_<service>, and anything that can't be translated (build sections, depends_on ordering) is reported"#
     )]
-    async fn services(&self, Parameters(args): Parameters<ServicesArgs>) -> String {
+    async fn services(
+        &self,
+        Parameters(args): Parameters<ServicesArgs>,
+        ctx: RequestContext<RoleServer>,
+    ) -> String {
         match args.action.as_str() {
             "define" => {
                 let name = match &args.name {
                     Some(n) => n.clone(),
                     None => return "❌ Error: 'name' is required for define action".to_string(),
                 };
-                let image = match &args.image {
-                    Some(i) => i.clone(),
-                    None => return "❌ Error: 'image' is required for define action".to_string(),
-                };
-
-                let healthcheck = args.healthcheck.map(|hc| services::HealthCheck {
+                let to_healthcheck = |hc: HealthCheckArg| services::HealthCheck {
                     cmd: hc.cmd,
                     interval: hc.interval.unwrap_or_else(|| "2s".to_string()),
                     retries: hc.retries.unwrap_or(15),
                     timeout: hc.timeout.unwrap_or_else(|| "5s".to_string()),
-                });
+                };
 
-                let def = services::ServiceDefinition {
-                    name: name.clone(),
-                    image,
-                    ports: args.ports.unwrap_or_default(),
-                    env: args.env.unwrap_or_default(),
-                    volumes: args.volumes.unwrap_or_default(),
-                    healthcheck,
-                    description: args.description,
-                    network: "skillz_services".to_string(),
+                let mut def = if let Some(preset_name) = &args.preset {
+                    let Some(preset) = services::service_preset(preset_name) else {
+                        let names: Vec<&str> =
+                            services::SERVICE_PRESETS.iter().map(|p| p.name).collect();
+                        return format!(
+                            "❌ Unknown preset '{}'. Available presets: {}",
+                            preset_name,
+                            names.join(", ")
+                        );
+                    };
+                    if args.image.is_some()
+                        || args.ports.is_some()
+                        || args.env.is_some()
+                        || args.volumes.is_some()
+                        || args.healthcheck.is_some()
+                        || args.description.is_some()
+                    {
+                        return "❌ Error: with a preset, pass changes in 'overrides' (e.g. overrides: {env: {\"POSTGRES_PASSWORD\": \"dev\"}})".to_string();
+                    }
+                    let overrides = args.overrides.unwrap_or_default();
+                    let mut def = preset.render(&name);
+                    if let Some(image) = overrides.image {
+                        def.image = image;
+                    }
+                    if let Some(ports) = overrides.ports {
+                        def.ports = ports;
+                    }
+                    def.env.extend(overrides.env.unwrap_or_default());
+                    if let Some(volumes) = overrides.volumes {
+                        def.volumes = volumes;
+                    }
+                    if let Some(hc) = overrides.healthcheck {
+                        def.healthcheck = Some(to_healthcheck(hc));
+                    }
+                    if let Some(description) = overrides.description {
+                        def.description = Some(description);
+                    }
+                    def
+                } else {
+                    if args.overrides.is_some() {
+                        return "❌ Error: 'overrides' only applies with a 'preset'".to_string();
+                    }
+                    let image = match &args.image {
+                        Some(i) => i.clone(),
+                        None => {
+                            return "❌ Error: 'image' (or 'preset') is required for define action"
+                                .to_string()
+                        }
+                    };
+                    services::ServiceDefinition {
+                        name: name.clone(),
+                        image,
+                        ports: args.ports.unwrap_or_default(),
+                        env: args.env.unwrap_or_default(),
+                        volumes: args.volumes.unwrap_or_default(),
+                        healthcheck: args.healthcheck.map(to_healthcheck),
+                        description: args.description,
+                        network: "skillz_services".to_string(),
+                        command: Vec::new(),
+                        exports: std::collections::HashMap::new(),
+                        keep_alive: false,
+                        startup_timeout_secs: None,
+                    }
                 };
+                def.keep_alive = args.keep_alive.unwrap_or(false);
+                def.startup_timeout_secs = args.startup_timeout_secs;
 
                 match self.service_registry.define(def, args.overwrite.unwrap_or(false)) {
                     Ok(msg) => format!("✅ {}\n\n💡 Start with: services(action: \"start\", name: \"{}\")", msg, name),
                     Err(e) => format!("❌ {}", e),
                 }
             }
+            "presets" => {
+                let mut output = String::from("📦 **Service Presets**\n\n");
+                for preset in services::SERVICE_PRESETS {
+                    output.push_str(&format!(
+                        "• **{}** - {} (`{}`)\n  Exports: {}\n",
+                        preset.name,
+                        preset.description,
+                        preset.image,
+                        preset.exported_vars("<NAME>").join(", ")
+                    ));
+                }
+                output.push_str("\n💡 Define one with: services(action: \"define\", preset: \"postgres\", name: \"mydb\", overrides: {env: {\"POSTGRES_PASSWORD\": \"dev\"}})");
+                output
+            }
+            "import_compose" => {
+                let source = match &args.path_or_content {
+                    Some(s) => s.clone(),
+                    None => {
+                        return "❌ Error: 'path_or_content' is required for import_compose action"
+                            .to_string()
+                    }
+                };
+                let path = std::path::Path::new(&source);
+                let from_file = !source.contains('\n') && path.is_file();
+                let (content, base_dir) = if from_file {
+                    match std::fs::read_to_string(path) {
+                        Ok(content) => (
+                            content,
+                            path.canonicalize().ok().and_then(|p| p.parent().map(|d| d.to_path_buf())),
+                        ),
+                        Err(e) => return format!("❌ Failed to read {}: {}", source, e),
+                    }
+                } else {
+                    (source.clone(), None)
+                };
+                let prefix = args.prefix.clone().unwrap_or_else(|| {
+                    services::compose_project_name(&content)
+                        .or_else(|| {
+                            base_dir
+                                .as_ref()
+                                .and_then(|d| d.file_name())
+                                .map(|n| n.to_string_lossy().to_string())
+                        })
+                        .unwrap_or_else(|| "compose".to_string())
+                });
+
+                let import = match services::parse_compose(&content, &prefix, base_dir.as_deref()) {
+                    Ok(import) => import,
+                    Err(e) => return format!("❌ {}", e),
+                };
+
+                let mut defined = Vec::new();
+                let mut failed = Vec::new();
+                for def in import.definitions {
+                    let name = def.name.clone();
+                    match self.service_registry.define(def, args.overwrite.unwrap_or(false)) {
+                        Ok(_) => defined.push(name),
+                        Err(e) => failed.push(format!("{}: {}", name, e)),
+                    }
+                }
+
+                let mut output = if defined.is_empty() {
+                    "❌ No services imported\n".to_string()
+                } else {
+                    format!("✅ Imported {} service(s) from docker-compose:\n", defined.len())
+                };
+                for name in &defined {
+                    output.push_str(&format!("  • {}\n", name));
+                }
+                if !failed.is_empty() {
+                    output.push_str("\n❌ Not defined:\n");
+                    for f in &failed {
+                        output.push_str(&format!("  • {}\n", f));
+                    }
+                }
+                if !import.warnings.is_empty() {
+                    output.push_str("\n⚠️ Not translated:\n");
+                    for w in &import.warnings {
+                        output.push_str(&format!("  • {}\n", w));
+                    }
+                }
+                if let Some(first) = defined.first() {
+                    output.push_str(&format!(
+                        "\n💡 Start with: services(action: \"start\", name: \"{}\")",
+                        first
+                    ));
+                }
+                output
+            }
             "list" => {
                 match self.service_registry.list() {
                     Ok(statuses) => {
                         if statuses.is_empty() {
                             "📦 No services defined yet.\n\n💡 Define one with:\nservices(action: \"define\", name: \"redis\", image: \"redis:alpine\", ports: [\"6379\"])".to_string()
                         } else {
-                            let mut output = String::from("📦 **Defined Services**\n\n");
+                            let mut output = format!(
+                                "📦 **Defined Services** ({})\n\n",
+                                self.service_registry.engine().name()
+                            );
                             for status in statuses {
                                 let icon = match status.status.as_str() {
                                     "running" => "🟢",
@@ -1670,13 +3854,15 @@ Other actions:
                                     "not_created" => "⚪",
                                     _ => "🟡",
                                 };
-                                let health = status.health.map(|h| format!(" ({})", h)).unwrap_or_default();
+                                let health = status.health.clone().map(|h| format!(" ({})", h)).unwrap_or_default();
                                 let ports = if status.ports.is_empty() {
                                     String::new()
                                 } else {
                                     format!(" → {}", status.ports.iter().map(|(c, h)| format!("{}:{}", h, c)).collect::<Vec<_>>().join(", "))
                                 };
-                                output.push_str(&format!("{} **{}**: {}{}{}\n", icon, status.name, status.status, health, ports));
+                                let usage = format_service_usage(&status);
+                                let usage = if usage.is_empty() { usage } else { format!(" — {}", usage) };
+                                output.push_str(&format!("{} **{}**: {}{}{}{}\n", icon, status.name, status.status, health, ports, usage));
                             }
                             output
                         }
@@ -1727,8 +3913,69 @@ Other actions:
                     Some(n) => n.clone(),
                     None => return "❌ Error: 'name' is required for logs action".to_string(),
                 };
-                match self.service_registry.logs(&name, args.tail) {
-                    Ok(logs) => format!("📜 **Logs for '{}':**\n```\n{}\n```", name, logs),
+                if !args.follow.unwrap_or(false) {
+                    return match self.service_registry.logs(&name, args.tail, args.since.as_deref()) {
+                        Ok(logs) => format!("📜 **Logs for '{}':**\n```\n{}\n```", name, logs),
+                        Err(e) => format!("❌ {}", e),
+                    };
+                }
+
+                let secs = args
+                    .duration_secs
+                    .unwrap_or(services::DEFAULT_FOLLOW_SECS)
+                    .clamp(1, services::MAX_FOLLOW_SECS);
+                let handler = self.runtime.logging_handler();
+                let mut recent = std::collections::VecDeque::new();
+                let result = self
+                    .service_registry
+                    .follow_logs(
+                        &name,
+                        args.tail,
+                        args.since.as_deref(),
+                        std::time::Duration::from_secs(secs),
+                        &ctx.ct,
+                        |stream, line| {
+                            if recent.len() == 20 {
+                                recent.pop_front();
+                            }
+                            recent.push_back(line.clone());
+                            let handler = handler.clone();
+                            let name = name.clone();
+                            async move {
+                                if let Some(handler) = handler {
+                                    let data = serde_json::json!({
+                                        "service": name,
+                                        "stream": stream.name(),
+                                        "line": line,
+                                    });
+                                    handler("info".to_string(), format!("[{}] {}", name, line), Some(data))
+                                        .await;
+                                }
+                            }
+                        },
+                    )
+                    .await;
+                match result {
+                    Ok((count, end)) => {
+                        let stopped = match end {
+                            services::FollowEnd::Elapsed => format!("after {}s", secs),
+                            services::FollowEnd::Cancelled => "when cancelled".to_string(),
+                            services::FollowEnd::Exited => "when the log stream ended".to_string(),
+                        };
+                        let mut out = format!(
+                            "📜 Followed logs for '{}', stopped {}: {} line(s) sent as log notifications",
+                            name, stopped, count
+                        );
+                        if !recent.is_empty() {
+                            let lines: Vec<String> = recent.into_iter().collect();
+                            out.push_str(&format!(
+                                "\n\n**Last {} line(s):**\n```\n{}\n```",
+                                lines.len(),
+                                lines.join("\n")
+                            ));
+                        }
+                        out
+                    }
                     Err(e) => format!("❌ {}", e),
                 }
             }
@@ -1738,7 +3985,9 @@ Other actions:
                     None => return "❌ Error: 'name' is required for status action".to_string(),
                 };
                 match self.service_registry.get_status(&name) {
-                    Ok(status) => {
+                    Ok(mut status) => {
+                        self.service_registry.add_usage(std::slice::from_mut(&mut status));
+                        let usage = format_service_usage(&status);
                         let icon = match status.status.as_str() {
                             "running" => "🟢",
                             "exited" | "stopped" => "🔴",
@@ -1753,7 +4002,113 @@ Other actions:
                             format!("\n📡 Ports: {}", status.ports.iter().map(|(c, h)| format!("localhost:{}→{}", h, c)).collect::<Vec<_>>().join(", "))
                         };
                         let uptime = status.uptime.map(|u| format!("\n⏱️ Started: {}", u)).unwrap_or_default();
-                        format!("{} **{}**: {}{}{}{}{}", icon, name, status.status, container, health, ports, uptime)
+                        let usage = if usage.is_empty() { usage } else { format!("\n📊 {}", usage) };
+                        format!("{} **{}**: {}{}{}{}{}{}", icon, name, status.status, container, health, ports, uptime, usage)
+                    }
+                    Err(e) => format!("❌ {}", e),
+                }
+            }
+            "backup" => {
+                let name = match &args.name {
+                    Some(n) => n.clone(),
+                    None => return "❌ Error: 'name' is required for backup action".to_string(),
+                };
+                let registry = self.service_registry.clone();
+                let output_dir = args.output_dir.map(std::path::PathBuf::from);
+                let service = name.clone();
+                let backup = tokio::task::spawn_blocking(move || {
+                    registry.backup_volumes(&service, output_dir.as_deref())
+                })
+                .await
+                .unwrap_or_else(|e| Err(format!("Backup task failed: {}", e)));
+                match backup {
+                    Ok(backup) => {
+                        let warning = if backup.was_running {
+                            "\n⚠️ The service was running; stop it first for a consistent copy"
+                        } else {
+                            ""
+                        };
+                        format!(
+                            "💾 Backed up {} to {} ({}){}\n\n💡 Restore with: services(action: \"restore\", name: \"{}\", archive: \"{}\")",
+                            backup.volumes.join(", "),
+                            backup.path.display(),
+                            builder::format_size(backup.size),
+                            warning,
+                            name,
+                            backup.path.display()
+                        )
+                    }
+                    Err(e) => format!("❌ {}", e),
+                }
+            }
+            "restore" => {
+                let (name, archive) = match (&args.name, &args.archive) {
+                    (Some(n), Some(a)) => (n.clone(), std::path::PathBuf::from(a)),
+                    _ => return "❌ Error: 'name' and 'archive' are required for restore action".to_string(),
+                };
+                let registry = self.service_registry.clone();
+                let force = args.force.unwrap_or(false);
+                let service = name.clone();
+                let restore = tokio::task::spawn_blocking(move || {
+                    registry.restore_volumes(&service, &archive, force)
+                })
+                .await
+                .unwrap_or_else(|e| Err(format!("Restore task failed: {}", e)));
+                match restore {
+                    Ok(restore) => {
+                        let overwritten = if restore.overwritten.is_empty() {
+                            String::new()
+                        } else {
+                            format!("\n♻️ Replaced existing data in {}", restore.overwritten.join(", "))
+                        };
+                        format!(
+                            "📥 Restored {} for '{}' from a {} backup{}\n\n💡 Start with: services(action: \"start\", name: \"{}\")",
+                            restore.volumes.join(", "),
+                            name,
+                            builder::format_size(restore.size),
+                            overwritten,
+                            name
+                        )
+                    }
+                    Err(e) => format!("❌ {}", e),
+                }
+            }
+            "run_once" => {
+                let Some(image) = &args.image else {
+                    return "❌ Error: 'image' is required for run_once action".to_string();
+                };
+                let command = args.command.unwrap_or_default();
+                let secs = args
+                    .timeout_secs
+                    .unwrap_or(services::DEFAULT_RUN_ONCE_SECS)
+                    .clamp(1, services::MAX_RUN_ONCE_SECS);
+                let run = self
+                    .service_registry
+                    .run_once(
+                        image,
+                        &command,
+                        &args.env.unwrap_or_default(),
+                        std::time::Duration::from_secs(secs),
+                        &ctx.ct,
+                    )
+                    .await;
+                match run {
+                    Ok(output) => {
+                        let icon = if output.exit_code == Some(0) { "✅" } else { "❌" };
+                        let code = output
+                            .exit_code
+                            .map_or("killed by a signal".to_string(), |c| format!("exit code {}", c));
+                        let mut out =
+                            format!("{} Ran {} as {} ({})", icon, image, output.container, code);
+                        if !command.is_empty() {
+                            out.push_str(&format!(": {}", command.join(" ")));
+                        }
+                        for (label, text) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+                            if !text.trim().is_empty() {
+                                out.push_str(&format!("\n\n**{}:**\n```\n{}\n```", label, text.trim_end()));
+                            }
+                        }
+                        out
                     }
                     Err(e) => format!("❌ {}", e),
                 }
@@ -1764,7 +4119,47 @@ Other actions:
                     Err(e) => format!("❌ {}", e),
                 }
             }
-            _ => format!("❌ Unknown action: '{}'. Use: define, list, start, stop, remove, logs, status, prune", args.action),
+            _ => format!("❌ Unknown action: '{}'. Use: define, presets, import_compose, list, start, stop, remove, logs, status, backup, restore, run_once, prune", args.action),
+        }
+    }
+
+    // ==================== SANDBOX ====================
+
+    #[tool(
+        description = "Show or change how script tools are sandboxed, for this session without a restart. action 'get' (default) returns the effective config. action 'set' changes only the fields given: mode ('none', 'bubblewrap', 'firejail' or 'nsjail'), allow_network, read_paths, write_paths, memory_limit_mb and time_limit_secs. A mode whose sandbox program isn't installed is rejected and the config left unchanged. Changes apply from the next tool call and last until the server restarts (SKILLZ_SANDBOX and SKILLZ_SANDBOX_NETWORK set the startup config)."
+    )]
+    async fn sandbox(&self, Parameters(args): Parameters<SandboxArgs>) -> String {
+        match args.action.as_deref().unwrap_or("get") {
+            "get" => format_sandbox_config("🔒 **Sandbox**", &self.runtime.sandbox_config()),
+            "set" => {
+                let mut config = self.runtime.sandbox_config();
+                if let Some(mode) = &args.mode {
+                    config.mode = match runtime::SandboxMode::parse(mode) {
+                        Ok(mode) => mode,
+                        Err(e) => return format!("❌ {}", e),
+                    };
+                }
+                if let Some(allow_network) = args.allow_network {
+                    config.allow_network = allow_network;
+                }
+                if let Some(paths) = args.read_paths {
+                    config.read_paths = paths.into_iter().map(std::path::PathBuf::from).collect();
+                }
+                if let Some(paths) = args.write_paths {
+                    config.write_paths = paths.into_iter().map(std::path::PathBuf::from).collect();
+                }
+                if let Some(limit) = args.memory_limit_mb {
+                    config.memory_limit_mb = limit;
+                }
+                if let Some(limit) = args.time_limit_secs {
+                    config.time_limit_secs = limit;
+                }
+                match self.runtime.set_sandbox_config(config) {
+                    Ok(config) => format_sandbox_config("✅ **Sandbox updated**", &config),
+                    Err(e) => format!("❌ {}", e),
+                }
+            }
+            other => format!("❌ Unknown action: '{}'. Use: get, set", other),
         }
     }
 
@@ -1773,23 +4168,51 @@ Other actions:
     #[tool(
         description = r#"Create and manage pipeline tools. Pipelines chain tools together with outputs available to subsequent steps.
 
-Actions: 'create', 'list', 'delete'
+Actions: 'create', 'list', 'show', 'update', 'run', 'validate', 'diagram', 'schedule', 'unschedule', 'runs', 'clear_cache', 'delete'
+
+'show' returns the pipeline's steps as JSON. 'diagram' draws them as a Mermaid flowchart (also the skillz://pipelines/{name}/diagram resource). 'update' replaces any of steps, on_error, description, tags and quiet, keeping the rest; the previous definition is saved as a version for rollback.
+
+'validate' is a dry run that executes nothing: it checks tools exist, $input/$prev/$step references resolve against sample_input and the tools' output schemas (or sample_outputs per step name), foreach values are arrays and conditions parse and can be true. create with validate: true refuses pipelines that fail it.
+
+'run' executes a pipeline with `input` bound to $input. Options: stop_on_error (default true; false runs every step), verbose (untruncated outputs), format: "json" (structured result with every step), no_cache, debug (checkpoint each step's resolved args and output to memory under __pipeline_debug__ for a day; the result names the run id).
 
-NOTE: Pipelines can ONLY use Skillz's own registered tools, not tools from other MCP servers.
+A step with cache: { ttl_seconds } reuses its output when it runs again with the same tool and resolved args, e.g. after a later step failed; the step result is marked cached. no_cache forces fresh runs and 'clear_cache' drops a pipeline's cached outputs.
+
+'schedule' runs the pipeline automatically while the server is up, with an empty input: schedule is "every 30s", "every 5m", "every 1h", "every 1d" or a cron expression ("0 * * * *", UTC). create accepts schedule too. 'unschedule' stops it and 'runs' lists the last scheduled runs with their outcome. Scheduled runs are logged to the client and appear in history.
+
+While a pipeline runs, the client gets a progress notification before and after each step and the steps' logs tagged with the step name. Create the pipeline with quiet: true to turn both off.
+
+NOTE: Pipelines can ONLY use Skillz's own registered tools, not tools from other MCP servers. Steps may also call the built-ins memory, list_tools, import_tool, build_tool, register_script, execute_code and delete_tool, e.g. a last step { tool: "memory", args: { action: "store", tool_name: "reports", key: "latest", value: "$prev" } }.
 
 Variable syntax (for create):
 - $input.field - Access pipeline input
 - $prev - Previous step's entire output
 - $prev.field - Access field from previous step
 - $step_name.field - Access field from a named step
+- $$ - A literal "$" at the start of a string, e.g. "$$5.00" passes "$5.00" and "$$end" passes "$end"
+
+Flaky steps can set timeout_secs, retries and retry_delay_ms; continue_on_error only applies once all attempts fail.
+
+To run a tool on every element of a list, set foreach: "$fetch.items" and use $item (element) and $index in args; the step outputs the array of results. max_concurrency runs several elements at once.
+
+A step's tool can be another pipeline: it gets the step's args as $input and its final result becomes the step's output. Cycles (A → B → A) and nesting deeper than 8 pipelines fail the step.
+
+The built-in "transform" step reshapes data without a script: args { expr, input } apply a jq-like expression (paths like .items[0].name, .[], |, map(f), select(f), {name, id: .user_id}, [...], comparisons, and/or, length, keys, not) to input, which defaults to $prev.
+
+on_error lists steps to run when a step fails without continue_on_error; they can use $error.step, $error.message and outputs of earlier named steps. The pipeline still reports the original failure.
 
 Example:
 pipeline(action: "create", name: "my_pipeline", steps: [
-    { name: "fetch", tool: "http_get", args: { url: "$input.url" } },
-    { tool: "analyze", args: { text: "$fetch.body" } }
+    { name: "fetch", tool: "http_get", args: { url: "$input.url" }, timeout_secs: 30, retries: 2, retry_delay_ms: 500 },
+    { tool: "analyze", args: { text: "$fetch.body" } },
+    { tool: "summarize", foreach: "$prev.sections", args: { text: "$item" }, max_concurrency: 4 }
 ])"#
     )]
-    async fn pipeline(&self, Parameters(args): Parameters<PipelineArgs>) -> String {
+    async fn pipeline(
+        &self,
+        Parameters(args): Parameters<PipelineArgs>,
+        ctx: RequestContext<RoleServer>,
+    ) -> String {
         match args.action.as_str() {
             "create" => {
                 let name = match &args.name {
@@ -1803,6 +4226,10 @@ pipeline(action: "create", name: "my_pipeline", steps: [
 
                 eprintln!("Creating pipeline: {}", name);
 
+                if let Err(e) = registry::validate_tool_name(&name) {
+                    return format!("❌ {}", e);
+                }
+
                 // Check if tool already exists
                 if self.registry.get_tool(&name).is_some() {
                     return format!(
@@ -1811,51 +4238,48 @@ pipeline(action: "create", name: "my_pipeline", steps: [
                     );
                 }
 
-                // Validate steps reference existing tools
-                for (i, step) in steps.iter().enumerate() {
-                    if self.registry.get_tool(&step.tool).is_none() {
-                        let built_in_tools = [
-                            "build_tool",
-                            "register_script",
-                            "create_skill",
-                            "import_tool",
-                            "call_tool",
-                            "list_tools",
-                            "complete",
-                            "execute_code",
-                            "install_deps",
-                            "delete_tool",
-                            "test_validate",
-                            "pipeline",
-                            "memory",
-                        ];
-                        if !built_in_tools.contains(&step.tool.as_str()) {
-                            return format!(
-                                "❌ Step {} references unknown tool '{}'. Create or import it first.",
-                                i + 1, step.tool
-                            );
-                        }
+                let reg_steps = match self.pipeline_steps_from_args(&steps, "Step") {
+                    Ok(steps) => steps,
+                    Err(e) => return e,
+                };
+                let on_error = match &args.on_error {
+                    Some(steps) => match self.pipeline_steps_from_args(steps, "on_error step") {
+                        Ok(steps) => steps,
+                        Err(e) => return e,
+                    },
+                    None => Vec::new(),
+                };
+
+                if args.validate.unwrap_or(false) {
+                    let issues = self.dry_run_pipeline(
+                        &reg_steps,
+                        &on_error,
+                        args.sample_input.as_ref(),
+                        args.sample_outputs.as_ref(),
+                    );
+                    if !issues.is_empty() {
+                        return format!(
+                            "❌ Pipeline '{}' not created:\n\n{}",
+                            name,
+                            format_validation_issues(&issues)
+                        );
                     }
                 }
 
-                // Convert to registry PipelineStep
-                let reg_steps: Vec<registry::PipelineStep> = steps
-                    .iter()
-                    .map(|s| registry::PipelineStep {
-                        name: s.name.clone(),
-                        tool: s.tool.clone(),
-                        args: s.args.clone().unwrap_or(serde_json::json!({})),
-                        continue_on_error: s.continue_on_error.unwrap_or(false),
-                        condition: s.condition.clone(),
-                    })
-                    .collect();
-
                 let mut manifest = registry::ToolManifest::new_pipeline(
                     name.clone(),
                     args.description.unwrap_or_default(),
                     reg_steps,
                 );
                 manifest.tags = args.tags.unwrap_or_default();
+                manifest.on_error = on_error;
+                manifest.quiet = args.quiet.unwrap_or(false);
+                if let Some(expr) = args.schedule {
+                    if let Err(e) = schedule::Schedule::parse(&expr) {
+                        return format!("❌ {:#}", e);
+                    }
+                    manifest.schedule = Some(expr.trim().to_string());
+                }
 
                 match self.registry.register_tool(manifest, &[]) {
                     Ok(_) => format!(
@@ -1888,7 +4312,7 @@ pipeline(action: "create", name: "my_pipeline", steps: [
                 let mut output = format!("## 📋 Pipelines ({})\n\n", filtered.len());
                 for p in filtered {
                     output.push_str(&format!(
-                        "### {}\n- **Description:** {}\n- **Steps:** {}\n- **Tags:** {}\n\n",
+                        "### {}\n- **Description:** {}\n- **Steps:** {}\n- **Tags:** {}\n{}\n",
                         p.name(),
                         if p.description().is_empty() {
                             "(none)"
@@ -1900,6 +4324,10 @@ pipeline(action: "create", name: "my_pipeline", steps: [
                             "(none)".to_string()
                         } else {
                             p.manifest.tags.join(", ")
+                        },
+                        match p.manifest.schedule {
+                            Some(ref expr) => format!("- **Schedule:** {}\n", expr),
+                            None => String::new(),
                         }
                     ));
                 }
@@ -1926,88 +4354,448 @@ pipeline(action: "create", name: "my_pipeline", steps: [
                     Err(e) => format!("❌ Failed to delete pipeline: {}", e),
                 }
             }
-            _ => format!(
-                "Unknown action: '{}'. Use: create, list, delete",
-                args.action
-            ),
-        }
-    }
+            "update" => {
+                let name = match &args.name {
+                    Some(n) => n.clone(),
+                    None => return "Error: 'name' is required for update action".to_string(),
+                };
+                let previous = match self.registry.get_tool(&name) {
+                    Some(tool) if *tool.tool_type() == ToolType::Pipeline => tool.manifest.version,
+                    Some(_) => {
+                        return format!("⚠️ '{}' is not a pipeline. Use build_tool or register_script to update it.", name)
+                    }
+                    None => return format!("⚠️ Pipeline '{}' not found", name),
+                };
 
-    // ==================== MEMORY / PERSISTENT STATE ====================
+                let mut update = registry::PipelineUpdate {
+                    description: args.description,
+                    tags: args.tags,
+                    quiet: args.quiet,
+                    ..Default::default()
+                };
+                if let Some(ref steps) = args.steps {
+                    match self.pipeline_steps_from_args(steps, "Step") {
+                        Ok(steps) => update.steps = Some(steps),
+                        Err(e) => return e,
+                    }
+                }
+                if let Some(ref steps) = args.on_error {
+                    match self.pipeline_steps_from_args(steps, "on_error step") {
+                        Ok(steps) => update.on_error = Some(steps),
+                        Err(e) => return e,
+                    }
+                }
 
-    #[tool(
-        description = r#"Manage knowledge entries. Actions: 'store' (save new), 'get' (by ID), 'update' (modify), 'delete' (remove), 'list' (browse), 'bulk_store' (create multiple), 'bulk_update' (update multiple). For bulk operations, use 'entries' array. Store any text, code, or notes for later retrieval."#
-    )]
-    async fn memory(&self, Parameters(args): Parameters<MemoryArgs>) -> String {
-        match args.action.as_str() {
-            "get" => {
-                let key = match &args.key {
-                    Some(k) => k,
-                    None => return "Error: 'key' is required for get action".to_string(),
+                match self.registry.update_pipeline(&name, update) {
+                    Ok(tool) => format!(
+                        "✅ **Pipeline '{}' Updated** to v{}\n\nUndo with `version(action: \"rollback\", tool_name: \"{}\", version: \"{}\")`",
+                        name, tool.manifest.version, name, previous
+                    ),
+                    Err(e) => format!("❌ Failed to update pipeline: {}", e),
+                }
+            }
+            "run" => {
+                let name = match &args.name {
+                    Some(n) => n,
+                    None => return "Error: 'name' is required for run action".to_string(),
                 };
-                match self.memory.get(&args.tool_name, key).await {
-                    Ok(Some(value)) => serde_json::to_string_pretty(&value).unwrap_or_else(|_| "null".to_string()),
-                    Ok(None) => "null".to_string(),
-                    Err(e) => format!("Error: {}", e),
+                let tool = match self.registry.get_tool(name) {
+                    Some(tool) if *tool.tool_type() == ToolType::Pipeline => tool,
+                    Some(_) => {
+                        return format!("⚠️ '{}' is not a pipeline. Use call_tool to run it.", name)
+                    }
+                    None => return format!("⚠️ Pipeline '{}' not found", name),
+                };
+                let input = args.input.unwrap_or_else(|| serde_json::json!({}));
+                if !input.is_object() {
+                    return "Error: 'input' must be an object".to_string();
+                }
+                let json = match args.format.as_deref().unwrap_or("markdown") {
+                    "markdown" => false,
+                    "json" => true,
+                    other => return format!("❌ Unknown format '{}'. Use markdown or json", other),
+                };
+
+                let debug_run = args.debug.unwrap_or(false).then(|| {
+                    format!(
+                        "{}-{}",
+                        tool.name(),
+                        chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ")
+                    )
+                });
+                let call = PipelineCall {
+                    ct: &ctx.ct,
+                    use_cache: !args.no_cache.unwrap_or(false),
+                    chain: &[tool.name().to_string()],
+                    progress: None,
+                    debug_run: debug_run.as_deref(),
+                };
+                let run = self
+                    .execute_pipeline(
+                        &tool,
+                        input,
+                        args.stop_on_error.unwrap_or(true),
+                        call,
+                        progress_token(&ctx),
+                    )
+                    .await;
+                if json {
+                    serde_json::to_string_pretty(&run).unwrap_or_default()
+                } else {
+                    run.to_markdown(args.verbose.unwrap_or(false))
                 }
             }
-            "store" | "set" => {
-                let key = match &args.key {
-                    Some(k) => k,
-                    None => return "Error: 'key' is required for store action".to_string(),
+            "validate" => {
+                let name = match &args.name {
+                    Some(n) => n,
+                    None => return "Error: 'name' is required for validate action".to_string(),
                 };
-                let value = match &args.value {
-                    Some(v) => v.clone(),
-                    None => return "Error: 'value' is required for store action".to_string(),
+                let tool = match self.registry.get_tool(name) {
+                    Some(tool) if *tool.tool_type() == ToolType::Pipeline => tool,
+                    Some(_) => return format!("⚠️ '{}' is not a pipeline", name),
+                    None => return format!("⚠️ Pipeline '{}' not found", name),
                 };
-                match self.memory.set(&args.tool_name, key, value).await {
-                    Ok(()) => format!("✅ Stored '{}' for tool '{}'", key, args.tool_name),
-                    Err(e) => format!("Error: {}", e),
+                let issues = self.dry_run_pipeline(
+                    tool.pipeline_steps(),
+                    tool.on_error_steps(),
+                    args.sample_input.as_ref(),
+                    args.sample_outputs.as_ref(),
+                );
+                if issues.is_empty() {
+                    format!(
+                        "✅ Pipeline '{}' is valid: every step and reference resolves",
+                        name
+                    )
+                } else {
+                    format!(
+                        "❌ Pipeline '{}' has {} problem(s):\n\n{}",
+                        name,
+                        issues.len(),
+                        format_validation_issues(&issues)
+                    )
                 }
             }
-            "delete" | "clear" => {
-                if let Some(key) = &args.key {
-                    // Delete specific key
-                    match self.memory.delete(&args.tool_name, key).await {
-                        Ok(true) => format!("🗑️ Deleted '{}' from tool '{}'", key, args.tool_name),
-                        Ok(false) => format!("Key '{}' not found for tool '{}'", key, args.tool_name),
-                        Err(e) => format!("Error: {}", e),
+            "show" => {
+                let name = match &args.name {
+                    Some(n) => n,
+                    None => return "Error: 'name' is required for show action".to_string(),
+                };
+                let tool = match self.registry.get_tool(name) {
+                    Some(tool) if *tool.tool_type() == ToolType::Pipeline => tool,
+                    Some(_) => return format!("⚠️ '{}' is not a pipeline", name),
+                    None => return format!("⚠️ Pipeline '{}' not found", name),
+                };
+                let mut definition = serde_json::json!({
+                    "name": tool.name(),
+                    "description": tool.description(),
+                    "version": tool.manifest.version,
+                    "tags": tool.tags(),
+                    "steps": tool.pipeline_steps(),
+                });
+                if !tool.on_error_steps().is_empty() {
+                    definition["on_error"] = serde_json::json!(tool.on_error_steps());
+                }
+                if let Some(ref expr) = tool.manifest.schedule {
+                    definition["schedule"] = serde_json::json!(expr);
+                }
+                format!(
+                    "## ⛓️ Pipeline '{}'\n\nEdit and resubmit with `pipeline(action: \"update\", ...)`\n\n```json\n{}\n```",
+                    name,
+                    serde_json::to_string_pretty(&definition).unwrap_or_default()
+                )
+            }
+            "diagram" => {
+                let name = match &args.name {
+                    Some(n) => n,
+                    None => return "Error: 'name' is required for diagram action".to_string(),
+                };
+                match read_pipeline_diagram(&self.registry, name) {
+                    Ok(diagram) => format!(
+                        "## ⛓️ Pipeline '{}' Diagram\n\nAlso available as the `skillz://pipelines/{}/diagram` resource.\n\n```mermaid\n{}```",
+                        name, name, diagram
+                    ),
+                    Err(e) => format!("⚠️ {}", e),
+                }
+            }
+            "schedule" => {
+                let name = match &args.name {
+                    Some(n) => n,
+                    None => return "Error: 'name' is required for schedule action".to_string(),
+                };
+                let expr = match args.schedule {
+                    Some(expr) => expr,
+                    None => {
+                        return "Error: 'schedule' is required for schedule action (e.g. \"every 5m\" or \"0 * * * *\")".to_string()
                     }
-                } else {
-                    // Clear all keys for tool
-                    match self.memory.clear(&args.tool_name).await {
-                        Ok(count) => format!("🗑️ Cleared {} entries for tool '{}'", count, args.tool_name),
-                        Err(e) => format!("Error: {}", e),
+                };
+                match self.registry.set_schedule(name, Some(expr)) {
+                    Ok(tool) => {
+                        let expr = tool.manifest.schedule.unwrap_or_default();
+                        let next = schedule::Schedule::parse(&expr)
+                            .ok()
+                            .and_then(|s| s.next_after(chrono::Utc::now()))
+                            .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                            .unwrap_or_else(|| "never".to_string());
+                        format!(
+                            "⏰ Pipeline '{}' scheduled: {}\n\nNext run: {}. See results with `pipeline(action: \"runs\", name: \"{}\")`",
+                            name, expr, next, name
+                        )
                     }
+                    Err(e) => format!("❌ {:#}", e),
                 }
             }
-            "list" => {
-                match self.memory.list_keys(&args.tool_name).await {
-                    Ok(keys) => {
-                        if keys.is_empty() {
-                            format!("No memory stored for tool '{}'", args.tool_name)
-                        } else {
-                            format!("Keys for '{}': {}", args.tool_name, keys.join(", "))
-                        }
+            "unschedule" => {
+                let name = match &args.name {
+                    Some(n) => n,
+                    None => return "Error: 'name' is required for unschedule action".to_string(),
+                };
+                match self.registry.get_tool(name) {
+                    Some(tool) if tool.manifest.schedule.is_none() => {
+                        return format!("⚠️ Pipeline '{}' has no schedule", name)
                     }
-                    Err(e) => format!("Error: {}", e),
+                    _ => {}
+                }
+                match self.registry.set_schedule(name, None) {
+                    Ok(_) => format!("✅ Pipeline '{}' unscheduled", name),
+                    Err(e) => format!("❌ {}", e),
                 }
             }
-            "stats" => {
-                match self.memory.stats().await {
-                    Ok(stats) => format!(
-                        "📊 Memory Stats:\n  - Total entries: {}\n  - Tools with memory: {}\n  - Schema version: {}",
-                        stats.total_entries, stats.total_tools, stats.schema_version
+            "clear_cache" => {
+                let name = match &args.name {
+                    Some(n) => n,
+                    None => return "Error: 'name' is required for clear_cache action".to_string(),
+                };
+                match self.registry.get_tool(name) {
+                    Some(tool) if *tool.tool_type() == ToolType::Pipeline => {}
+                    Some(_) => return format!("⚠️ '{}' is not a pipeline", name),
+                    None => return format!("⚠️ Pipeline '{}' not found", name),
+                }
+                match self
+                    .memory
+                    .delete_prefix(memory::PIPELINE_CACHE_NAMESPACE, &format!("{}/", name))
+                    .await
+                {
+                    Ok(count) => format!(
+                        "🗑️ Cleared {} cached step output(s) of pipeline '{}'",
+                        count, name
                     ),
-                    Err(e) => format!("Error: {}", e),
+                    Err(e) => format!("❌ Failed to clear cache: {}", e),
+                }
+            }
+            "runs" => {
+                let name = match &args.name {
+                    Some(n) => n,
+                    None => return "Error: 'name' is required for runs action".to_string(),
+                };
+                let tool = match self.registry.get_tool(name) {
+                    Some(tool) if *tool.tool_type() == ToolType::Pipeline => tool,
+                    Some(_) => return format!("⚠️ '{}' is not a pipeline", name),
+                    None => return format!("⚠️ Pipeline '{}' not found", name),
+                };
+                let runs: Vec<schedule::ScheduledRun> = match self
+                    .memory
+                    .get(schedule::SCHEDULED_RUNS_NAMESPACE, name)
+                    .await
+                {
+                    Ok(Some(value)) => serde_json::from_value(value).unwrap_or_default(),
+                    Ok(None) => Vec::new(),
+                    Err(e) => return format!("❌ Failed to read scheduled runs: {}", e),
+                };
+                format_scheduled_runs(name, tool.manifest.schedule.as_deref(), &runs)
+            }
+            _ => format!(
+                "Unknown action: '{}'. Use: create, list, show, update, run, validate, diagram, schedule, unschedule, runs, clear_cache, delete",
+                args.action
+            ),
+        }
+    }
+
+    // ==================== MEMORY / PERSISTENT STATE ====================
+
+    #[tool(
+        description = r#"Manage knowledge entries. Actions: 'store' (save new), 'get' (by ID), 'exists' (true/false without reading the value), 'update' (modify), 'delete' (remove), 'list' (browse), 'query' (keys whose stored JSON value contains the 'query' substring), 'stats' (counts, incl. entries expiring soon), 'bulk_store' (create multiple), 'bulk_update' (update existing, multiple), 'bulk_get' (fetch several 'keys'), 'increment' (atomically add 'delta' to a counter), 'append' (atomically add 'value' to a list, trimmed to max_length), 'semantic_search' (entries most similar to 'query', top_k results; full-text search if no embedder is configured), 'export' (backup as JSON; tool_name '*' for all tools; large exports go to TOOLS_DIR/exports/), 'import' (restore an export from 'value' or 'path' with merge_strategy). For bulk writes, use 'entries' array; they are all-or-nothing. Pass ttl_seconds with 'store' to make an entry expire. Store a JSON Schema under the key '_schema' to have every other write for that tool_name validated against it. Store any text, code, or notes for later retrieval."#
+    )]
+    async fn memory(&self, Parameters(args): Parameters<MemoryArgs>) -> String {
+        self.run_memory(args)
+            .await
+            .unwrap_or_else(|e| e.to_string())
+    }
+}
+
+impl AppState {
+    /// Run scheduled pipelines when they are due, for as long as the server
+    /// is up. A pipeline still running from its last turn is skipped.
+    fn spawn_scheduler(&self) -> tokio::task::JoinHandle<()> {
+        let state = self.clone();
+        tokio::spawn(async move {
+            let mut scheduler = schedule::Scheduler::default();
+            let running: Arc<std::sync::Mutex<std::collections::HashSet<String>>> = Arc::default();
+            let mut tick = tokio::time::interval(schedule::SCHEDULER_TICK);
+            loop {
+                tick.tick().await;
+                let scheduled = state.registry.scheduled_pipelines();
+                for name in scheduler.due(&scheduled, chrono::Utc::now()) {
+                    if !running.lock().unwrap().insert(name.clone()) {
+                        eprintln!("⏰ Skipping scheduled run of '{}': still running", name);
+                        continue;
+                    }
+                    let state = state.clone();
+                    let running = running.clone();
+                    tokio::spawn(async move {
+                        state.run_scheduled_pipeline(&name).await;
+                        running.lock().unwrap().remove(&name);
+                    });
+                }
+            }
+        })
+    }
+
+    /// Run a pipeline for its schedule, log the outcome to the client and keep
+    /// a summary in memory for `pipeline(action: "runs")`
+    async fn run_scheduled_pipeline(&self, name: &str) {
+        let tool = match self.registry.get_tool(name) {
+            Some(tool) if *tool.tool_type() == ToolType::Pipeline => tool,
+            _ => return,
+        };
+        let log = |level: &'static str, message: String| {
+            let handler = self.runtime.logging_handler();
+            async move {
+                eprintln!("⏰ {}", message);
+                if let Some(handler) = handler {
+                    let data = serde_json::json!({"message": message, "pipeline": name});
+                    handler(level.to_string(), message, Some(data)).await;
                 }
             }
-            _ => format!("Unknown action: '{}'. Use: store, get, delete, list, stats", args.action),
+        };
+        log(
+            "info",
+            format!("Scheduled run of pipeline '{}' started", name),
+        )
+        .await;
+
+        let started_at = chrono::Utc::now();
+        let ct = tokio_util::sync::CancellationToken::new();
+        let call = PipelineCall {
+            ct: &ct,
+            use_cache: true,
+            chain: &[name.to_string()],
+            progress: None,
+            debug_run: None,
+        };
+        let run = self
+            .execute_pipeline(&tool, serde_json::json!({}), true, call, None)
+            .await;
+        let record = schedule::ScheduledRun::new(started_at, &run);
+
+        if let Err(e) = self
+            .memory
+            .append(
+                schedule::SCHEDULED_RUNS_NAMESPACE,
+                name,
+                serde_json::to_value(&record).unwrap_or_default(),
+                Some(schedule::MAX_SCHEDULED_RUNS),
+            )
+            .await
+        {
+            eprintln!("Failed to record scheduled run of {}: {}", name, e);
+        }
+        let (level, outcome) = if record.success {
+            ("info", "succeeded")
+        } else {
+            ("error", "failed")
+        };
+        log(
+            level,
+            format!(
+                "Scheduled run of pipeline '{}' {} in {}ms: {}",
+                name, outcome, record.duration_ms, record.summary
+            ),
+        )
+        .await;
+    }
+
+    /// Record a tool execution in the usage statistics (best-effort)
+    async fn record_usage(
+        &self,
+        tool_name: &str,
+        args: &serde_json::Value,
+        start: std::time::Instant,
+        success: bool,
+    ) {
+        let duration_ms = start.elapsed().as_millis() as u64;
+        if let Err(e) = self
+            .memory
+            .record_tool_call(tool_name, duration_ms, success)
+            .await
+        {
+            eprintln!("Failed to record usage for {}: {}", tool_name, e);
+        }
+
+        // The audit log is append-only and never read on the hot path, so write it in the background
+        let memory = self.memory.clone();
+        let tool_name = tool_name.to_string();
+        let args_hash = memory::args_hash(args);
+        tokio::spawn(async move {
+            if let Err(e) = memory
+                .record_execution(&tool_name, &args_hash, success, duration_ms)
+                .await
+            {
+                eprintln!("Failed to record execution of {}: {}", tool_name, e);
+            }
+        });
+    }
+
+    /// A memoized result for a cacheable tool called with these arguments
+    async fn cached_result(
+        &self,
+        tool: &registry::ToolConfig,
+        args: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        if !tool.is_cacheable() {
+            return None;
+        }
+        let cached = self
+            .memory
+            .cached_result(tool.name(), &tool.manifest.version, args)
+            .await
+            .ok()
+            .flatten()?;
+        eprintln!("💾 Cached result for {}", tool.name());
+        Some(cached)
+    }
+
+    /// Memoize the result of a cacheable tool (best-effort). Failed runs
+    /// aren't kept, so the next call tries again.
+    async fn store_result(
+        &self,
+        tool: &registry::ToolConfig,
+        args: &serde_json::Value,
+        result: &serde_json::Value,
+    ) {
+        if !tool.is_cacheable() || runtime::is_failed_run(result) {
+            return;
+        }
+        if let Err(e) = self
+            .memory
+            .cache_result(tool.name(), &tool.manifest.version, args, result)
+            .await
+        {
+            eprintln!("Failed to cache result for {}: {}", tool.name(), e);
+        }
+    }
+
+    /// Tags for a (re-)registered tool: explicit tags win, otherwise keep the existing ones
+    fn resolve_tags(&self, name: &str, tags: Option<Vec<String>>) -> Vec<String> {
+        match tags {
+            Some(tags) => registry::normalize_tags(&tags),
+            None => self
+                .registry
+                .get_tool(name)
+                .map(|t| t.manifest.tags)
+                .unwrap_or_default(),
         }
     }
-}
 
-impl AppState {
     fn generate_tool_stubs(&self, tools: &[registry::ToolConfig], language: &str) -> String {
         let mut stubs = String::new();
 
@@ -2112,8 +4900,28 @@ function _call_tool(name, args) {{
     }
 }
 
-#[tool_handler(router = self.tool_router)]
+// Written out rather than generated by #[tool_handler] so list_tools can paginate
 impl ServerHandler for AppState {
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let tcc = rmcp::handler::server::tool::ToolCallContext::new(self, request, context);
+        self.tool_router.call(tcc).await
+    }
+
+    async fn list_tools(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        _ctx: RequestContext<RoleServer>,
+    ) -> std::result::Result<ListToolsResult, McpError> {
+        let mut tools = self.tool_router.list_all();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        let (tools, next_cursor) = paginate_request(tools, request)?;
+        Ok(ListToolsResult { tools, next_cursor })
+    }
+
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             instructions: Some("Skillz - Build and execute custom tools at runtime. Supports WASM (Rust) and Script tools (Python, Node.js, Ruby, etc.) via JSON-RPC 2.0. NOTE: Skillz tools can ONLY call other Skillz tools, not tools from other MCP servers. CRITICAL: For Python scripts, use sys.stdin.readline() NOT sys.stdin.read() - read() blocks forever! Always call sys.stdout.flush() after printing.".into()),
@@ -2125,9 +4933,9 @@ impl ServerHandler for AppState {
                 .enable_resources_subscribe()
                 .enable_prompts()
                 .enable_prompts_list_changed()
-                // Note: logging disabled temporarily due to Cursor sending setLevel before initialized
-                // which violates MCP spec (setLevel should come AFTER initialized notification)
-                // .enable_logging()
+                // Clients that send setLevel before initialized (e.g. Cursor) are
+                // served through logging::DeferEarlyRequests
+                .enable_logging()
                 .build(),
             ..Default::default()
         }
@@ -2166,19 +4974,24 @@ impl ServerHandler for AppState {
         notification: CancelledNotificationParam,
         _ctx: NotificationContext<RoleServer>,
     ) {
-        // Log cancellation request
-        // Note: Full cancellation support would require tracking running processes
-        // by request_id and killing them. For now, we just acknowledge the request.
+        // rmcp cancels the request's token (RequestContext::ct) before this
+        // hook runs; call_tool hands that token to the runtime, which kills
+        // the script process or traps the WASM module.
         eprintln!(
             "⚠️ Cancellation requested for request_id: {:?} (reason: {:?})",
             notification.request_id, notification.reason
         );
+    }
 
-        // TODO: Implement process tracking and killing for script tools
-        // This would require:
-        // 1. Storing child process handles in a map keyed by request_id
-        // 2. Looking up the process here and calling .kill()
-        // 3. Coordinating with the async tool execution to handle the cancellation
+    /// Change the minimum level of logs forwarded to the client
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        _ctx: RequestContext<RoleServer>,
+    ) -> std::result::Result<(), McpError> {
+        eprintln!("📝 Log level set to {}", logging::level_name(request.level));
+        self.log_level.set(request.level);
+        Ok(())
     }
 
     /// Subscribe to resource updates
@@ -2224,7 +5037,7 @@ impl ServerHandler for AppState {
 
     async fn list_resources(
         &self,
-        _request: Option<PaginatedRequestParam>,
+        request: Option<PaginatedRequestParam>,
         _ctx: RequestContext<RoleServer>,
     ) -> std::result::Result<ListResourcesResult, McpError> {
         let mut resources = vec![
@@ -2245,8 +5058,10 @@ impl ServerHandler for AppState {
             .no_annotation(),
         ];
 
-        // Add dynamic resources for each built tool
-        for tool in self.registry.list_tools() {
+        // Add dynamic resources for each built tool, by name so pages are stable
+        let mut tools = self.registry.list_tools();
+        tools.sort_by(|a, b| a.name().cmp(b.name()));
+        for tool in tools {
             let type_emoji = match tool.tool_type() {
                 ToolType::Wasm => "🦀",
                 ToolType::Script => "📜",
@@ -2260,11 +5075,28 @@ impl ServerHandler for AppState {
                 )
                 .no_annotation(),
             );
+            if tool.source_path().is_some() {
+                let mut source = RawResource::new(
+                    format!("skillz://tools/{}/source", tool.name()),
+                    format!("{} {} - source code", type_emoji, tool.name()),
+                );
+                source.mime_type = Some(tool.source_mime_type().to_string());
+                resources.push(source.no_annotation());
+            }
+            if *tool.tool_type() == ToolType::Pipeline {
+                let mut diagram = RawResource::new(
+                    format!("skillz://pipelines/{}/diagram", tool.name()),
+                    format!("{} {} - Mermaid diagram", type_emoji, tool.name()),
+                );
+                diagram.mime_type = Some(MERMAID_MIME_TYPE.to_string());
+                resources.push(diagram.no_annotation());
+            }
         }
 
+        let (resources, next_cursor) = paginate_request(resources, request)?;
         Ok(ListResourcesResult {
             resources,
-            next_cursor: None,
+            next_cursor,
         })
     }
 
@@ -2284,6 +5116,28 @@ impl ServerHandler for AppState {
             ),
             mime_type: Some("text/markdown".to_string()),
         }
+        .no_annotation(),
+        RawResourceTemplate {
+            uri_template: "skillz://tools/{tool_name}/source".to_string(),
+            name: "Tool Source".to_string(),
+            title: Some("Tool Source".to_string()),
+            description: Some(
+                "Raw source code of a WASM or script tool, e.g. to review an imported tool before running it."
+                    .to_string(),
+            ),
+            mime_type: None,
+        }
+        .no_annotation(),
+        RawResourceTemplate {
+            uri_template: "skillz://pipelines/{pipeline_name}/diagram".to_string(),
+            name: "Pipeline Diagram".to_string(),
+            title: Some("Pipeline Diagram".to_string()),
+            description: Some(
+                "A pipeline's steps as a Mermaid flowchart, with data dependencies and conditional steps."
+                    .to_string(),
+            ),
+            mime_type: Some(MERMAID_MIME_TYPE.to_string()),
+        }
         .no_annotation()];
 
         Ok(ListResourceTemplatesResult {
@@ -2297,13 +5151,41 @@ impl ServerHandler for AppState {
         ReadResourceRequestParam { uri }: ReadResourceRequestParam,
         _ctx: RequestContext<RoleServer>,
     ) -> std::result::Result<ReadResourceResult, McpError> {
+        if let Some(tool_name) = parse_tool_source_uri(&uri) {
+            let (text, mime_type) = read_tool_source(&self.registry, tool_name).map_err(|e| {
+                McpError::resource_not_found(e.to_string(), Some(serde_json::json!({ "uri": uri })))
+            })?;
+            return Ok(ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri,
+                    mime_type: Some(mime_type),
+                    text,
+                    meta: None,
+                }],
+            });
+        }
+        if let Some(name) = parse_pipeline_diagram_uri(&uri) {
+            let text = read_pipeline_diagram(&self.registry, name).map_err(|e| {
+                McpError::resource_not_found(e.to_string(), Some(serde_json::json!({ "uri": uri })))
+            })?;
+            return Ok(ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri,
+                    mime_type: Some(MERMAID_MIME_TYPE.to_string()),
+                    text,
+                    meta: None,
+                }],
+            });
+        }
+
         let content = match uri.as_str() {
             "skillz://guide" => self.get_guide_content(),
             "skillz://examples" => self.get_examples_content(),
             "skillz://protocol" => self.get_protocol_content(),
             _ if uri.starts_with("skillz://tools/") => {
                 let tool_name = uri.strip_prefix("skillz://tools/").unwrap();
-                self.get_tool_info(tool_name)
+                let usage = self.memory.tool_usage(tool_name).await.ok().flatten();
+                self.get_tool_info(tool_name, usage.as_ref())
             }
             _ => {
                 return Err(McpError::resource_not_found(
@@ -2431,8 +5313,31 @@ memory_stats()  // Total entries, tools, schema version
 "##,
         );
 
-        // Add dynamic registered tools section
-        if !wasm_tools.is_empty() || !script_tools.is_empty() {
+        // Add dynamic registered tools section, grouped by tag when tools are tagged
+        let local_tools: Vec<_> = tools
+            .iter()
+            .filter(|t| matches!(t.tool_type(), ToolType::Wasm | ToolType::Script))
+            .cloned()
+            .collect();
+        if local_tools.iter().any(|t| !t.tags().is_empty()) {
+            let (groups, untagged) = registry::group_by_tag(&local_tools);
+            guide.push_str("## 📦 Registered Tools\n\n");
+            for (tag, group) in groups
+                .iter()
+                .map(|(tag, group)| (tag.as_str(), group))
+                .chain(std::iter::once(("untagged", &untagged)))
+            {
+                if group.is_empty() {
+                    continue;
+                }
+                guide.push_str(&format!("### 🏷️ {}\n\n", tag));
+                for tool in group {
+                    guide.push_str(&format!("- **{}** - {}\n", tool.name(), tool.description()));
+                }
+                guide.push('\n');
+            }
+            guide.push_str("---\n\n");
+        } else if !wasm_tools.is_empty() || !script_tools.is_empty() {
             guide.push_str("## 📦 Registered Tools\n\n");
 
             if !wasm_tools.is_empty() {
@@ -2493,6 +5398,8 @@ memory_stats()  // Total entries, tools, schema version
 - `skillz://examples` - Code examples for WASM and Script tools
 - `skillz://protocol` - JSON-RPC 2.0 protocol documentation
 - `skillz://tools/{name}` - Individual tool documentation
+- `skillz://tools/{name}/source` - Source code of a WASM or script tool
+- `skillz://pipelines/{name}/diagram` - Mermaid flowchart of a pipeline
 "##,
         );
 
@@ -2738,10 +5645,19 @@ Supports optional TTL for caching use cases.
 
 ### Set with TTL (Caching)
 ```json
-{"jsonrpc": "2.0", "method": "memory/set", "params": {"key": "cached", "value": "data", "ttl": 60}, "id": 11}
+{"jsonrpc": "2.0", "method": "memory/set", "params": {"key": "cached", "value": "data", "ttl_seconds": 60}, "id": 11}
 ```
-- `ttl` - Time-to-live in seconds (optional, omit for permanent storage)
-- After TTL expires, `memory/get` returns null
+- `ttl_seconds` - Time-to-live in seconds (optional, omit for permanent storage; `ttl` is also accepted)
+- After TTL expires, `memory/get` returns null and `memory/list` no longer includes the key
+- Expired entries are deleted on startup and periodically (`[memory] cleanup_interval_secs`)
+
+### Validate Values with a Schema (Opt-in)
+```json
+{"jsonrpc": "2.0", "method": "memory/set", "params": {"key": "_schema", "value": {"type": "object", "required": ["retries"]}}, "id": 11}
+```
+- Once the tool has a `_schema` entry, every other write (`memory/set`, `memory/increment`, `memory/append`, bulk stores) must match that JSON Schema
+- A non-conforming write is rejected and nothing is stored: `{"result": {"error": "Value for 'config' doesn't match the memory schema of 'my_tool': ..."}}`
+- Delete `_schema` to turn validation off again
 
 ### List All Keys
 ```json
@@ -2751,6 +5667,7 @@ Supports optional TTL for caching use cases.
 ```json
 {"jsonrpc": "2.0", "result": {"keys": ["counter", "config", "history"]}, "id": 12}
 ```
+- Optional params: `prefix` (only keys starting with it), `limit` + `cursor` (paging; the response has `next_cursor` when more keys follow), `include_values` (adds `entries` with `key`, `value`, `size_bytes`; values over 256 bytes are truncated and marked `truncated`)
 
 ### Delete a Key
 ```json
@@ -2761,6 +5678,28 @@ Supports optional TTL for caching use cases.
 {"jsonrpc": "2.0", "result": {"deleted": true}, "id": 13}
 ```
 
+### Increment a Counter (Atomic)
+```json
+{"jsonrpc": "2.0", "method": "memory/increment", "params": {"key": "counter", "delta": 1}, "id": 14}
+```
+**Response:**
+```json
+{"jsonrpc": "2.0", "result": {"value": 44}, "id": 14}
+```
+- `delta` defaults to 1 and may be negative; a missing key starts at 0
+- Safe when the tool runs several times in parallel, unlike `memory/get` + `memory/set`
+
+### Append to a List (Atomic)
+```json
+{"jsonrpc": "2.0", "method": "memory/append", "params": {"key": "history", "item": {"q": "hi"}, "max_length": 100}, "id": 15}
+```
+**Response:**
+```json
+{"jsonrpc": "2.0", "result": {"length": 12}, "id": 15}
+```
+- A missing key starts a new list; appending to a non-array value is an error
+- `max_length` (optional) drops the oldest items so at most that many remain
+
 ### Python Helper Functions
 ```python
 def memory_get(key):
@@ -2774,399 +5713,1195 @@ def memory_set(key, value):
     print(json.dumps(req), flush=True)
     resp = json.loads(sys.stdin.readline())
     return resp.get("result", {}).get("success", False)
+
+def memory_increment(key, delta=1):
+    req = {"jsonrpc": "2.0", "method": "memory/increment", "params": {"key": key, "delta": delta}, "id": 14}
+    print(json.dumps(req), flush=True)
+    resp = json.loads(sys.stdin.readline())
+    return resp.get("result", {}).get("value")
+
+def memory_append(key, item, max_length=None):
+    params = {"key": key, "item": item}
+    if max_length is not None:
+        params["max_length"] = max_length
+    req = {"jsonrpc": "2.0", "method": "memory/append", "params": params, "id": 15}
+    print(json.dumps(req), flush=True)
+    resp = json.loads(sys.stdin.readline())
+    return resp.get("result", {}).get("length")
+```
+
+---
+
+## 📦 Resources (List and Read Server Resources)
+
+Access Skillz resources from your script tool.
+**Check `context.capabilities.resources` before using!**
+
+### List Available Resources
+```json
+{"jsonrpc": "2.0", "method": "resources/list", "params": {}, "id": 20}
+```
+**Response:**
+```json
+{
+    "jsonrpc": "2.0",
+    "result": {
+        "resources": [
+            {"uri": "skillz://guide", "name": "Skillz Guide", "description": "Complete usage guide"},
+            {"uri": "skillz://examples", "name": "Skillz Examples", "description": "Code snippets"},
+            {"uri": "skillz://protocol", "name": "JSON-RPC 2.0 Protocol", "description": "How to communicate"},
+            {"uri": "skillz://tools/my_tool", "name": "my_tool", "description": "Tool description"}
+        ]
+    },
+    "id": 20
+}
+```
+
+### Read Resource Content
+```json
+{"jsonrpc": "2.0", "method": "resources/read", "params": {"uri": "skillz://guide"}, "id": 21}
+```
+**Response:**
+```json
+{
+    "jsonrpc": "2.0",
+    "result": {
+        "contents": [
+            {"uri": "skillz://guide", "mime_type": "text/markdown", "text": "# 🚀 Skillz Guide..."}
+        ]
+    },
+    "id": 21
+}
+```
+
+### Read Workspace Files
+`skillz://files/{path}` reads a file inside the workspace roots (`path` relative to a root, or absolute). Text files come back as `text`; images and other binary files as base64 `blob`, both with a `mime_type`. Paths outside the roots and files over 10 MB are refused.
+```json
+{"jsonrpc": "2.0", "method": "resources/read", "params": {"uri": "skillz://files/assets/logo.png"}, "id": 22}
+```
+**Response:**
+```json
+{"jsonrpc": "2.0", "result": {"contents": [{"uri": "skillz://files/assets/logo.png", "mime_type": "image/png", "blob": "iVBORw0KGgo..."}]}, "id": 22}
+```
+
+### Python Helper Functions
+```python
+def resources_list():
+    req = {"jsonrpc": "2.0", "method": "resources/list", "params": {}, "id": 20}
+    print(json.dumps(req), flush=True)
+    resp = json.loads(sys.stdin.readline())
+    return resp.get("result", {}).get("resources", [])
+
+def resources_read(uri):
+    req = {"jsonrpc": "2.0", "method": "resources/read", "params": {"uri": uri}, "id": 21}
+    print(json.dumps(req), flush=True)
+    resp = json.loads(sys.stdin.readline())
+    contents = resp.get("result", {}).get("contents", [])
+    return contents[0].get("text") if contents else None
+```
+
+---
+
+## 🔗 Tools Calling Tools (`tools/call`)
+
+Call other registered tools from within your script tool.
+Enables dynamic composition and meta-tools.
+
+### Call Another Tool
+```json
+{"jsonrpc": "2.0", "method": "tools/call", "params": {"name": "json_formatter", "arguments": {"data": "..."}}, "id": 30}
+```
+**Response:**
+```json
+{"jsonrpc": "2.0", "result": {"output": "<tool's result>"}, "id": 30}
 ```
 
+### Python Helper
+```python
+def call_tool(name, arguments=None):
+    params = {"name": name}
+    if arguments:
+        params["arguments"] = arguments
+    req = {"jsonrpc": "2.0", "method": "tools/call", "params": params, "id": 30}
+    print(json.dumps(req), flush=True)
+    resp = json.loads(sys.stdin.readline())
+    return resp.get("result", {}).get("output")
+```
+
+---
+
+## 📡 Streaming Output (`stream/chunk`)
+
+Send partial results progressively for long-running operations.
+Stream chunks are notifications (no response needed).
+
+### Send Stream Chunk
+```json
+{"jsonrpc": "2.0", "method": "stream/chunk", "params": {"data": "partial result...", "index": 0, "is_final": false}}
+{"jsonrpc": "2.0", "method": "stream/chunk", "params": {"data": "more data...", "index": 1, "is_final": true}}
+```
+
+### Parameters
+- `data` - The chunk content (any JSON value)
+- `index` - Optional chunk number for ordering
+- `is_final` - Whether this is the last chunk before final result
+
+### Python Helper
+```python
+def stream_chunk(data, index=None, is_final=False):
+    params = {"data": data, "is_final": is_final}
+    if index is not None:
+        params["index"] = index
+    print(json.dumps({"jsonrpc": "2.0", "method": "stream/chunk", "params": params}), flush=True)
+```
+
+---
+
+## 🔔 MCP Server Capabilities
+
+Skillz implements full MCP protocol support with these server capabilities:
+
+### Tools
+- `tools/list` - List all registered tools
+- `tools/call` - Execute a tool
+- `notifications/tools/list_changed` - Emitted when tools change (hot reload)
+
+### Resources  
+- `resources/list` - List available resources
+- `resources/read` - Read resource content
+- `resources/templates/list` - URI templates with completion
+- `resources/subscribe` - Subscribe to resource updates
+- `resources/unsubscribe` - Unsubscribe from updates
+- `notifications/resources/list_changed` - Emitted when resource list changes
+- `notifications/resources/updated` - Emitted when subscribed resource changes
+
+### Prompts
+- `prompts/list` - List available prompts
+- `prompts/get` - Get prompt details
+- `notifications/prompts/list_changed` - Emitted when prompts change
+
+### Utilities
+- `logging/setLevel` - Set logging level
+- Progress notifications with `_meta.progressToken` support
+- Cancellation handler (`notifications/cancelled`)
+
 ---
 
-## 📦 Resources (List and Read Server Resources)
+## 🎤 Elicitation (User Input)
 
-Access Skillz resources from your script tool.
-**Check `context.capabilities.resources` before using!**
+Request structured input from the user during tool execution.
+**Check `context.capabilities.elicitation` before using!**
 
-### List Available Resources
+### Request User Input
 ```json
-{"jsonrpc": "2.0", "method": "resources/list", "params": {}, "id": 20}
+{
+    "jsonrpc": "2.0",
+    "method": "elicitation/create",
+    "params": {
+        "message": "Please provide your email address",
+        "requestedSchema": {
+            "type": "object",
+            "properties": {
+                "email": {"type": "string", "format": "email"},
+                "name": {"type": "string"}
+            },
+            "required": ["email"]
+        }
+    },
+    "id": 100
+}
 ```
-**Response:**
+
+### Response from User
 ```json
 {
     "jsonrpc": "2.0",
     "result": {
-        "resources": [
-            {"uri": "skillz://guide", "name": "Skillz Guide", "description": "Complete usage guide"},
-            {"uri": "skillz://examples", "name": "Skillz Examples", "description": "Code snippets"},
-            {"uri": "skillz://protocol", "name": "JSON-RPC 2.0 Protocol", "description": "How to communicate"},
-            {"uri": "skillz://tools/my_tool", "name": "my_tool", "description": "Tool description"}
-        ]
+        "action": "accept",
+        "content": {"email": "user@example.com", "name": "John"}
     },
-    "id": 20
+    "id": 100
 }
 ```
 
-### Read Resource Content
+**Actions:** `accept` (user provided input), `decline` (user refused), `cancel` (user dismissed)
+
+---
+
+## 🤖 Sampling (LLM Requests)
+
+Request LLM completions during tool execution.
+**Check `context.capabilities.sampling` before using!**
+
+### Request LLM Completion
 ```json
-{"jsonrpc": "2.0", "method": "resources/read", "params": {"uri": "skillz://guide"}, "id": 21}
+{
+    "jsonrpc": "2.0",
+    "method": "sampling/createMessage",
+    "params": {
+        "messages": [
+            {"role": "user", "content": {"type": "text", "text": "Summarize this code..."}}
+        ],
+        "maxTokens": 1000,
+        "temperature": 0.7
+    },
+    "id": 101
+}
 ```
-**Response:**
+
+### Response from LLM
 ```json
 {
     "jsonrpc": "2.0",
     "result": {
-        "contents": [
-            {"uri": "skillz://guide", "mime_type": "text/markdown", "text": "# 🚀 Skillz Guide..."}
-        ]
+        "message": {
+            "role": "assistant",
+            "content": {"type": "text", "text": "This code does..."}
+        },
+        "model": "claude-3",
+        "stopReason": "endTurn"
     },
-    "id": 21
+    "id": 101
 }
 ```
 
-### Python Helper Functions
+---
+
+## Final Response
+
+### Success
+```json
+{"jsonrpc": "2.0", "result": {"your": "data"}, "id": 1}
+```
+
+### Error
+```json
+{"jsonrpc": "2.0", "error": {"code": -32000, "message": "Error description", "data": {}}, "id": 1}
+```
+
+---
+
+## Complete Python Example with All Features
+
 ```python
+#!/usr/bin/env python3
+import json
+import sys
+
+def log(level, message):
+    print(json.dumps({"jsonrpc": "2.0", "method": "log", 
+        "params": {"level": level, "message": message}}), flush=True)
+
 def resources_list():
+    """List available resources"""
     req = {"jsonrpc": "2.0", "method": "resources/list", "params": {}, "id": 20}
     print(json.dumps(req), flush=True)
     resp = json.loads(sys.stdin.readline())
     return resp.get("result", {}).get("resources", [])
 
 def resources_read(uri):
+    """Read resource content"""
     req = {"jsonrpc": "2.0", "method": "resources/read", "params": {"uri": uri}, "id": 21}
     print(json.dumps(req), flush=True)
     resp = json.loads(sys.stdin.readline())
     contents = resp.get("result", {}).get("contents", [])
     return contents[0].get("text") if contents else None
-```
-
----
 
-## 🔗 Tools Calling Tools (`tools/call`)
+def elicit(message, schema):
+    """Request user input (check capabilities first!)"""
+    req = {"jsonrpc": "2.0", "method": "elicitation/create", 
+           "params": {"message": message, "requestedSchema": schema}, "id": 100}
+    print(json.dumps(req), flush=True)
+    return json.loads(sys.stdin.readline())
 
-Call other registered tools from within your script tool.
-Enables dynamic composition and meta-tools.
+def sample(prompt, max_tokens=500):
+    """Request LLM completion (check capabilities first!)"""
+    req = {"jsonrpc": "2.0", "method": "sampling/createMessage",
+           "params": {"messages": [{"role": "user", "content": {"type": "text", "text": prompt}}],
+                      "maxTokens": max_tokens}, "id": 101}
+    print(json.dumps(req), flush=True)
+    return json.loads(sys.stdin.readline())
 
-### Call Another Tool
-```json
-{"jsonrpc": "2.0", "method": "tools/call", "params": {"name": "json_formatter", "arguments": {"data": "..."}}, "id": 30}
-```
-**Response:**
-```json
-{"jsonrpc": "2.0", "result": {"output": "<tool's result>"}, "id": 30}
-```
+def main():
+    # IMPORTANT: Use readline() not read() - read() blocks for EOF!
+    request = json.loads(sys.stdin.readline())
+    context = request.get("params", {}).get("context", {})
+    caps = context.get("capabilities", {})
+    
+    log("info", f"Capabilities: {caps}")
+    
+    # Example: List and read resources (always available)
+    if caps.get("resources"):
+        resources = resources_list()
+        log("info", f"Available resources: {len(resources)}")
+        # guide = resources_read("skillz://guide")
+    
+    # Example: Request user input if supported
+    if caps.get("elicitation"):
+        log("info", "Elicitation available!")
+        # resp = elicit("What's your name?", {"type": "object", "properties": {"name": {"type": "string"}}})
+    
+    # Example: Request LLM completion if supported  
+    if caps.get("sampling"):
+        log("info", "Sampling available!")
+        # resp = sample("Hello, how are you?")
+    
+    result = {"message": "Done!", "capabilities_available": caps}
+    print(json.dumps({"jsonrpc": "2.0", "result": result, "id": request.get("id")}))
 
-### Python Helper
-```python
-def call_tool(name, arguments=None):
-    params = {"name": name}
-    if arguments:
-        params["arguments"] = arguments
-    req = {"jsonrpc": "2.0", "method": "tools/call", "params": params, "id": 30}
-    print(json.dumps(req), flush=True)
-    resp = json.loads(sys.stdin.readline())
-    return resp.get("result", {}).get("output")
+if __name__ == "__main__":
+    main()
 ```
 
 ---
 
-## 📡 Streaming Output (`stream/chunk`)
-
-Send partial results progressively for long-running operations.
-Stream chunks are notifications (no response needed).
-
-### Send Stream Chunk
-```json
-{"jsonrpc": "2.0", "method": "stream/chunk", "params": {"data": "partial result...", "index": 0, "is_final": false}}
-{"jsonrpc": "2.0", "method": "stream/chunk", "params": {"data": "more data...", "index": 1, "is_final": true}}
-```
-
-### Parameters
-- `data` - The chunk content (any JSON value)
-- `index` - Optional chunk number for ordering
-- `is_final` - Whether this is the last chunk before final result
+## ⚠️ Critical Tips
 
-### Python Helper
+### Use `readline()` NOT `read()`!
 ```python
-def stream_chunk(data, index=None, is_final=False):
-    params = {"data": data, "is_final": is_final}
-    if index is not None:
-        params["index"] = index
-    print(json.dumps({"jsonrpc": "2.0", "method": "stream/chunk", "params": params}), flush=True)
+# ✅ CORRECT - Returns immediately
+request = json.loads(sys.stdin.readline())
+
+# ❌ WRONG - Blocks forever waiting for EOF!
+request = json.loads(sys.stdin.read())
 ```
 
----
+### Other Tips
+- Check `context.capabilities` before using features
+- `memory` and `resources` are always available (server-side)
+- `elicitation` and `sampling` depend on MCP client support
+- Use `flush=True` to ensure output is sent immediately
+- All bidirectional methods require an `id` field for responses
+- Log to stderr for debug output that won't interfere with JSON-RPC
+- Always include `id` from original request in final response
+"##.to_string()
+    }
 
-## 🔔 MCP Server Capabilities
+    fn get_tool_info(&self, tool_name: &str, usage: Option<&memory::ToolUsageStats>) -> String {
+        match self.registry.get_tool(tool_name) {
+            Some(tool) => {
+                let (type_name, type_emoji, path_info) = match tool.tool_type() {
+                    ToolType::Wasm => (
+                        "WASM",
+                        "🦀",
+                        format!("Directory: {}", tool.tool_dir.display()),
+                    ),
+                    ToolType::Script => {
+                        let interp = tool.interpreter().unwrap_or("executable");
+                        (
+                            "Script",
+                            "📜",
+                            format!(
+                                "Directory: {}\nInterpreter: {}",
+                                tool.tool_dir.display(),
+                                interp
+                            ),
+                        )
+                    }
+                    ToolType::Pipeline => (
+                        "Pipeline",
+                        "⛓️",
+                        format!(
+                            "Directory: {}\nSteps: {}",
+                            tool.tool_dir.display(),
+                            tool.pipeline_steps().len()
+                        ),
+                    ),
+                    ToolType::Mcp => (
+                        "MCP",
+                        "🌐",
+                        format!(
+                            "Server ID: {}",
+                            tool.server_id.as_deref().unwrap_or("unknown")
+                        ),
+                    ),
+                };
+                let version_info = format!(
+                    "- **Version:** {}\n{}{}",
+                    tool.manifest.version,
+                    format_usage_line(usage),
+                    format_origin_lines(&tool.manifest)
+                );
+                format!(
+                    "# {} {} Tool: {}\n\n## Description\n{}\n\n## Details\n- **Type:** {}\n- **Name:** {}\n{}- {}\n- **Status:** ✅ Ready to use\n\n## Usage\n```\ncall_tool(tool_name: \"{}\")\n```\n",
+                    type_emoji, type_name, tool.name(),
+                    tool.description(),
+                    type_name, tool.name(), version_info, path_info,
+                    tool.name()
+                )
+            }
+            None => format!(
+                "# ❌ Tool Not Found\n\nNo tool named '{}' exists.",
+                tool_name
+            ),
+        }
+    }
+}
 
-Skillz implements full MCP protocol support with these server capabilities:
+// ==================== Build Helpers ====================
 
-### Tools
-- `tools/list` - List all registered tools
-- `tools/call` - Execute a tool
-- `notifications/tools/list_changed` - Emitted when tools change (hot reload)
+/// Render a version diff as markdown: manifest field changes, then the source diff
+fn format_version_diff(name: &str, diff: &registry::VersionDiff) -> String {
+    let mut output = format!("## 🔍 {}: v{} → v{}\n\n", name, diff.from, diff.to);
 
-### Resources  
-- `resources/list` - List available resources
-- `resources/read` - Read resource content
-- `resources/templates/list` - URI templates with completion
-- `resources/subscribe` - Subscribe to resource updates
-- `resources/unsubscribe` - Unsubscribe from updates
-- `notifications/resources/list_changed` - Emitted when resource list changes
-- `notifications/resources/updated` - Emitted when subscribed resource changes
+    output.push_str("### Manifest\n\n");
+    if diff.manifest_changes.is_empty() {
+        output.push_str("_No manifest changes_\n");
+    }
+    let show = |v: &Option<serde_json::Value>| match v {
+        Some(v) => format!("`{}`", v),
+        None => "_(unset)_".to_string(),
+    };
+    for change in &diff.manifest_changes {
+        output.push_str(&format!(
+            "- **{}:** {} → {}\n",
+            change.field,
+            show(&change.from),
+            show(&change.to)
+        ));
+    }
 
-### Prompts
-- `prompts/list` - List available prompts
-- `prompts/get` - Get prompt details
-- `notifications/prompts/list_changed` - Emitted when prompts change
+    if let Some((old, new)) = &diff.module_change {
+        output.push_str(
+            "\n### Module\n\n_Source not stored for both versions; comparing compiled modules_\n\n",
+        );
+        output.push_str(&format!(
+            "- **Size:** {} → {}\n",
+            builder::format_size(old.size),
+            builder::format_size(new.size)
+        ));
+        if old.sha256 == new.sha256 {
+            output.push_str(&format!(
+                "- **SHA-256:** `{}` (unchanged)\n",
+                &old.sha256[..12]
+            ));
+        } else {
+            output.push_str(&format!(
+                "- **SHA-256:** `{}` → `{}`\n",
+                &old.sha256[..12],
+                &new.sha256[..12]
+            ));
+        }
+        return output;
+    }
 
-### Utilities
-- `logging/setLevel` - Set logging level
-- Progress notifications with `_meta.progressToken` support
-- Cancellation handler (`notifications/cancelled`)
+    output.push_str("\n### Source\n\n");
+    match diff.source_diff.as_deref() {
+        None => output.push_str("_No source stored for these versions_\n"),
+        Some("") => output.push_str("_Source unchanged_\n"),
+        Some(d) => output.push_str(&format!("```diff\n{}```\n", d)),
+    }
+    output
+}
 
----
+/// Parse the optional `version_bump` argument of build_tool/register_script
+fn parse_version_bump(value: Option<&str>) -> Result<registry::VersionBump, String> {
+    match value {
+        None => Ok(registry::VersionBump::default()),
+        Some(v) => registry::VersionBump::parse(v).ok_or_else(|| {
+            format!(
+                "❌ Invalid version_bump '{}'. Use 'major', 'minor', or 'patch'",
+                v
+            )
+        }),
+    }
+}
 
-## 🎤 Elicitation (User Input)
+/// Source text for build_tool's `code`: plain main.rs code, or a multi-file bundle
+fn parse_tool_code(code: &serde_json::Value) -> Result<String, String> {
+    match code {
+        serde_json::Value::String(code) => Ok(code.clone()),
+        serde_json::Value::Object(files) => {
+            let files = files
+                .iter()
+                .map(|(path, contents)| match contents.as_str() {
+                    Some(c) => Ok((path.clone(), c.to_string())),
+                    None => Err(format!("❌ Contents of '{}' must be a string", path)),
+                })
+                .collect::<Result<_, _>>()?;
+            builder::ToolSource::from_files(files)
+                .map(|source| source.to_text())
+                .map_err(|e| format!("❌ {}", e))
+        }
+        _ => Err("❌ 'code' must be a string or an object of path -> contents".to_string()),
+    }
+}
 
-Request structured input from the user during tool execution.
-**Check `context.capabilities.elicitation` before using!**
+/// Maximum number of compiler errors shown in a build_tool response
+const MAX_REPORTED_COMPILE_ERRORS: usize = 5;
+
+/// Render a build failure, preferring structured rustc diagnostics over raw stderr
+/// Write a memory export to TOOLS_DIR/exports/memory-<tool|all>-<unix time>.json
+fn write_memory_export(
+    storage_dir: &std::path::Path,
+    tool: Option<&str>,
+    export: &serde_json::Value,
+) -> Result<std::path::PathBuf> {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let dir = storage_dir.join(exporter::EXPORTS_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("memory-{}-{}.json", tool.unwrap_or("all"), secs));
+    write_json_file(&path, export)?;
+    Ok(path)
+}
 
-### Request User Input
-```json
-{
-    "jsonrpc": "2.0",
-    "method": "elicitation/create",
-    "params": {
-        "message": "Please provide your email address",
-        "requestedSchema": {
-            "type": "object",
-            "properties": {
-                "email": {"type": "string", "format": "email"},
-                "name": {"type": "string"}
-            },
-            "required": ["email"]
-        }
-    },
-    "id": 100
+/// Stream JSON to a file without building the whole string in memory
+fn write_json_file(path: &std::path::Path, value: &serde_json::Value) -> Result<()> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, value)?;
+    std::io::Write::flush(&mut writer)?;
+    Ok(())
 }
-```
 
-### Response from User
-```json
-{
-    "jsonrpc": "2.0",
-    "result": {
-        "action": "accept",
-        "content": {"email": "user@example.com", "name": "John"}
-    },
-    "id": 100
+/// Read a memory export written by `memory export`
+fn read_memory_export(path: &std::path::Path) -> Result<serde_json::Value> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open memory export {}", path.display()))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("Invalid memory export {}", path.display()))
 }
-```
 
-**Actions:** `accept` (user provided input), `decline` (user refused), `cancel` (user dismissed)
+fn format_compile_error(err: &anyhow::Error) -> String {
+    let Some(compile_err) = err.downcast_ref::<builder::CompileError>() else {
+        return format!("Compilation error: {}", err);
+    };
 
----
+    let errors = compile_err.errors();
+    if errors.is_empty() {
+        return format!("Compilation error: {}", compile_err.raw);
+    }
 
-## 🤖 Sampling (LLM Requests)
+    let mut out = format!("❌ Compilation failed with {} error(s)\n", errors.len());
+    for diag in errors.iter().take(MAX_REPORTED_COMPILE_ERRORS) {
+        out.push_str(&format!("\n{}", diag));
+    }
+    if errors.len() > MAX_REPORTED_COMPILE_ERRORS {
+        out.push_str(&format!(
+            "\n\n... and {} more",
+            errors.len() - MAX_REPORTED_COMPILE_ERRORS
+        ));
+    }
+    out
+}
 
-Request LLM completions during tool execution.
-**Check `context.capabilities.sampling` before using!**
+/// The progress token the client sent in the request's `_meta`, if any
+fn progress_token(ctx: &RequestContext<RoleServer>) -> Option<String> {
+    ctx.meta
+        .get_progress_token()
+        .map(|token| token.0.to_string())
+}
 
-### Request LLM Completion
-```json
-{
-    "jsonrpc": "2.0",
-    "method": "sampling/createMessage",
-    "params": {
-        "messages": [
-            {"role": "user", "content": {"type": "text", "text": "Summarize this code..."}}
-        ],
-        "maxTokens": 1000,
-        "temperature": 0.7
-    },
-    "id": 101
+/// One page of an MCP list response, following the request's cursor
+fn paginate_request<T>(
+    items: Vec<T>,
+    request: Option<PaginatedRequestParam>,
+) -> std::result::Result<(Vec<T>, Option<String>), McpError> {
+    let cursor = request.and_then(|r| r.cursor);
+    registry::paginate(items, cursor.as_deref(), registry::LIST_PAGE_SIZE)
+        .map_err(|e| McpError::invalid_params(e.to_string(), None))
 }
-```
 
-### Response from LLM
-```json
-{
-    "jsonrpc": "2.0",
-    "result": {
-        "message": {
-            "role": "assistant",
-            "content": {"type": "text", "text": "This code does..."}
-        },
-        "model": "claude-3",
-        "stopReason": "endTurn"
-    },
-    "id": 101
+/// import_tool reply for a repository or directory with several tools:
+/// what was imported, skipped and failed, and why
+/// Render the outcome of update_tool: the diff when anything changed
+fn format_update_result(result: &importer::UpdateResult) -> String {
+    let commit = result
+        .commit
+        .as_deref()
+        .map(|c| format!(" (commit {})", &c[..c.len().min(12)]))
+        .unwrap_or_default();
+    let Some(diff) = &result.diff else {
+        return format!(
+            "✅ **{}** is up to date with {}{} (v{})",
+            result.tool_name, result.source, commit, result.to_version
+        );
+    };
+    let mut output = format!(
+        "✅ **Updated {}** from {}{}: v{} → v{}\n\n\
+        📦 v{} was backed up; `version(action: \"rollback\", tool_name: \"{}\", version: \"{}\")` restores it.\n\n",
+        result.tool_name,
+        result.source,
+        commit,
+        result.from_version,
+        result.to_version,
+        result.from_version,
+        result.tool_name,
+        result.from_version
+    );
+    output.push_str(&format_version_diff(&result.tool_name, diff));
+    if !result.message.is_empty() {
+        output.push_str(&format!("\n{}\n", result.message));
+    }
+    output
 }
-```
 
----
+/// The list_tools listing (also `skillz list`): tools grouped by type, or
+/// by tag with `group_by` "tag", optionally only those tagged `tag`
+fn format_tool_list(
+    registry: &registry::ToolRegistry,
+    tag: Option<&str>,
+    group_by: Option<&str>,
+) -> String {
+    let mut tools = registry.list_tools();
+    let load_errors = format_load_errors(&registry.load_errors());
+    if tools.is_empty() {
+        return format!("{}No tools registered yet.\n\n• Use `build_tool` to create Rust/WASM tools\n• Use `register_script` to create tools in any language", load_errors);
+    }
 
-## Final Response
+    if let Some(tag) = tag {
+        tools.retain(|t| t.has_tag(tag));
+        if tools.is_empty() {
+            return format!("No tools tagged '{}'.", tag);
+        }
+    }
 
-### Success
-```json
-{"jsonrpc": "2.0", "result": {"your": "data"}, "id": 1}
-```
+    if group_by == Some("tag") {
+        let (groups, untagged) = registry::group_by_tag(&tools);
+        let mut output = format!("📦 Available Tools ({} total)\n\n", tools.len());
+        for (tag, group) in &groups {
+            output.push_str(&format!("### 🏷️ {} ({})\n\n", tag, group.len()));
+            for tool in group {
+                output.push_str(&format!("• **{}** - {}\n", tool.name(), tool.description()));
+            }
+            output.push('\n');
+        }
+        if !untagged.is_empty() {
+            output.push_str(&format!("### Untagged ({})\n\n", untagged.len()));
+            for tool in &untagged {
+                output.push_str(&format!("• **{}** - {}\n", tool.name(), tool.description()));
+            }
+            output.push('\n');
+        }
+        output.push_str(&load_errors);
+        output.push_str("\n💡 Use `call_tool(tool_name: \"...\")` to execute any tool.");
+        return output;
+    }
 
-### Error
-```json
-{"jsonrpc": "2.0", "error": {"code": -32000, "message": "Error description", "data": {}}, "id": 1}
-```
+    let wasm_tools: Vec<_> = tools
+        .iter()
+        .filter(|t| *t.tool_type() == ToolType::Wasm)
+        .collect();
+    let script_tools: Vec<_> = tools
+        .iter()
+        .filter(|t| *t.tool_type() == ToolType::Script)
+        .collect();
+    let pipeline_tools: Vec<_> = tools
+        .iter()
+        .filter(|t| *t.tool_type() == ToolType::Pipeline)
+        .collect();
+    let external_tools: Vec<_> = tools
+        .iter()
+        .filter(|t| *t.tool_type() == ToolType::Mcp)
+        .collect();
+    let mcp_servers: Vec<_> = tools.iter().filter(|t| t.mcp_server().is_some()).collect();
+
+    let mut output = format!("📦 Available Tools ({} total)\n\n", tools.len());
+
+    if !mcp_servers.is_empty() {
+        output.push_str(&format!(
+            "### 🌐 External MCP Servers ({})\n\n",
+            mcp_servers.len()
+        ));
+        for tool in &mcp_servers {
+            output.push_str(&format!("• **{}** - {}\n", tool.name(), tool.description()));
+        }
+        output.push('\n');
+    }
 
----
+    if !wasm_tools.is_empty() {
+        output.push_str(&format!("### 🦀 WASM Tools ({})\n\n", wasm_tools.len()));
+        for tool in wasm_tools {
+            output.push_str(&format!(
+                "• **{}** - {}{}\n",
+                tool.name(),
+                tool.description(),
+                import_note(registry, tool)
+            ));
+        }
+        output.push('\n');
+    }
 
-## Complete Python Example with All Features
+    if !script_tools.is_empty() {
+        output.push_str(&format!("### 📜 Script Tools ({})\n\n", script_tools.len()));
+        for tool in script_tools {
+            let interpreter = tool.interpreter().unwrap_or("executable");
+            output.push_str(&format!(
+                "• **{}** [{}] - {}{}\n",
+                tool.name(),
+                interpreter,
+                tool.description(),
+                import_note(registry, tool)
+            ));
+        }
+        output.push('\n');
+    }
 
-```python
-#!/usr/bin/env python3
-import json
-import sys
+    if !pipeline_tools.is_empty() {
+        output.push_str(&format!(
+            "### ⛓️ Pipeline Tools ({})\n\n",
+            pipeline_tools.len()
+        ));
+        for tool in pipeline_tools {
+            output.push_str(&format!(
+                "• **{}** - {}{}\n",
+                tool.name(),
+                tool.description(),
+                import_note(registry, tool)
+            ));
+        }
+        output.push('\n');
+    }
 
-def log(level, message):
-    print(json.dumps({"jsonrpc": "2.0", "method": "log", 
-        "params": {"level": level, "message": message}}), flush=True)
+    if !external_tools.is_empty() {
+        // Group external tools by namespace
+        let mut by_namespace: std::collections::HashMap<String, Vec<_>> =
+            std::collections::HashMap::new();
+        for tool in &external_tools {
+            if tool.mcp_server().is_none() {
+                let ns = tool
+                    .namespace
+                    .clone()
+                    .unwrap_or_else(|| "unknown".to_string());
+                by_namespace.entry(ns).or_default().push(tool);
+            }
+        }
 
-def resources_list():
-    """List available resources"""
-    req = {"jsonrpc": "2.0", "method": "resources/list", "params": {}, "id": 20}
-    print(json.dumps(req), flush=True)
-    resp = json.loads(sys.stdin.readline())
-    return resp.get("result", {}).get("resources", [])
+        if !by_namespace.is_empty() {
+            output.push_str(&format!(
+                "### 🔗 Proxied Tools ({})\n\n",
+                external_tools.len() - mcp_servers.len()
+            ));
+            for (namespace, ns_tools) in by_namespace.iter() {
+                output.push_str(&format!("**[{}]** ({} tools)\n", namespace, ns_tools.len()));
+                for tool in ns_tools {
+                    // Strip the namespace prefix for cleaner display
+                    let short_name = tool
+                        .name()
+                        .strip_prefix(&format!("{}_", namespace))
+                        .unwrap_or(tool.name());
+                    output.push_str(&format!(
+                        "  • `{}` - {}\n",
+                        short_name,
+                        tool.description()
+                            .strip_prefix(&format!("[{}] ", namespace))
+                            .unwrap_or(tool.description())
+                    ));
+                }
+            }
+            output.push('\n');
+        }
+    }
 
-def resources_read(uri):
-    """Read resource content"""
-    req = {"jsonrpc": "2.0", "method": "resources/read", "params": {"uri": uri}, "id": 21}
-    print(json.dumps(req), flush=True)
-    resp = json.loads(sys.stdin.readline())
-    contents = resp.get("result", {}).get("contents", [])
-    return contents[0].get("text") if contents else None
+    output.push_str(&load_errors);
+    output.push_str("\n💡 Use `call_tool(tool_name: \"...\")` to execute any tool.");
+    output
+}
 
-def elicit(message, schema):
-    """Request user input (check capabilities first!)"""
-    req = {"jsonrpc": "2.0", "method": "elicitation/create", 
-           "params": {"message": message, "requestedSchema": schema}, "id": 100}
-    print(json.dumps(req), flush=True)
-    return json.loads(sys.stdin.readline())
+/// How list_tools marks an imported tool: its source, and whether
+/// update_tool can check it for updates
+fn import_note(registry: &registry::ToolRegistry, tool: &registry::ToolConfig) -> String {
+    let Some(origin) = &tool.manifest.imported_from else {
+        return String::new();
+    };
+    let status = if !origin.is_reachable() {
+        "source no longer available"
+    } else if registry.modified_since_import(tool.name()) == Some(true) {
+        "modified locally; update_tool needs force"
+    } else {
+        "update_tool can check for updates"
+    };
+    format!(" _(📥 imported from {}; {})_", origin.source, status)
+}
+
+fn format_import_summary(summary: &importer::ImportSummary) -> String {
+    let found = summary.imported.len() + summary.skipped.len() + summary.failed.len();
+    let status = if summary.imported.is_empty() {
+        "❌"
+    } else if summary.failed.is_empty() {
+        "✅"
+    } else {
+        "⚠️"
+    };
+    let mut output = format!(
+        "{} **Imported {} of {} tool(s)** from {}\n\n",
+        status,
+        summary.imported.len(),
+        found,
+        summary.source
+    );
+    if !summary.imported.is_empty() {
+        output.push_str("### Imported\n\n");
+        for result in &summary.imported {
+            output.push_str(&format!(
+                "• **{}** ({:?}) - {}\n",
+                result.tool_name,
+                result.tool_type,
+                result.message.replace('\n', "; ")
+            ));
+        }
+        output.push('\n');
+    }
+    for (title, tools) in [("Skipped", &summary.skipped), ("Failed", &summary.failed)] {
+        if tools.is_empty() {
+            continue;
+        }
+        output.push_str(&format!("### {}\n\n", title));
+        for tool in tools {
+            output.push_str(&format!("• **{}** - {}\n", tool.name, tool.reason));
+        }
+        output.push('\n');
+    }
+    if !summary.skipped.is_empty() {
+        output.push_str(
+            "💡 Pass `overwrite_tools: [\"name\"]` (or `overwrite: true`) to replace existing tools, or `select` to choose which to import.",
+        );
+    }
+    output.trim_end().to_string()
+}
+
+/// Report of an import inspected in quarantine: each tool's manifest,
+/// files, red flags and the start of its code
+fn format_inspection(inspection: &importer::Inspection) -> String {
+    const MAX_FLAGS: usize = 20;
+    let mut output = format!(
+        "🔍 **Inspected {} tool(s)** from {}",
+        inspection.tools.len(),
+        inspection.source
+    );
+    output.push_str("\n\n");
+
+    for tool in &inspection.tools {
+        let m = &tool.manifest;
+        output.push_str(&format!(
+            "### 📦 {} v{} ({:?})\n\n{}\n\n",
+            m.name, m.version, m.tool_type, m.description
+        ));
+        if let Some(interpreter) = &m.interpreter {
+            output.push_str(&format!("- **Interpreter:** {}\n", interpreter));
+        }
+        if !m.dependencies.is_empty() {
+            output.push_str(&format!(
+                "- **Dependencies:** {}\n",
+                m.dependencies.join(", ")
+            ));
+        }
+        if !m.wasm_dependencies.is_empty() {
+            output.push_str(&format!(
+                "- **Crates:** {}\n",
+                m.wasm_dependencies.join(", ")
+            ));
+        }
+        if let Some(annotations) = m
+            .annotations
+            .as_ref()
+            .and_then(|a| serde_json::to_value(a).ok())
+            .and_then(|a| a.as_object().cloned())
+            .filter(|a| !a.is_empty())
+        {
+            let hints: Vec<String> = annotations
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect();
+            output.push_str(&format!("- **Annotations:** {}\n", hints.join(", ")));
+        }
+        if !m.pipeline_steps.is_empty() {
+            let steps: Vec<&str> = m.pipeline_steps.iter().map(|s| s.tool.as_str()).collect();
+            output.push_str(&format!("- **Steps:** {}\n", steps.join(" → ")));
+        }
+        output.push_str(&format!("- **Checksum:** sha256:{}\n", tool.content_hash));
+        let files: Vec<String> = tool
+            .files
+            .iter()
+            .map(|(name, size)| format!("{} ({})", name, builder::format_size(*size)))
+            .collect();
+        output.push_str(&format!("- **Files:** {}\n", files.join(", ")));
+        if tool.exists {
+            output.push_str(if inspection.overwrites(&m.name) {
+                "- ⚠️ Already registered: confirming replaces it (the current version is backed up)\n"
+            } else {
+                "- ⚠️ Already registered: confirming skips it (import with overwrite to replace it)\n"
+            });
+        }
+
+        if tool.red_flags.is_empty() {
+            output.push_str("\n✅ No red flags found by the static scan\n");
+        } else {
+            output.push_str(&format!("\n**🚩 Red flags ({}):**\n", tool.red_flags.len()));
+            for flag in tool.red_flags.iter().take(MAX_FLAGS) {
+                let location = if flag.line == 0 {
+                    flag.file.clone()
+                } else {
+                    format!("{}:{}", flag.file, flag.line)
+                };
+                output.push_str(&format!(
+                    "- {} ({}): `{}`\n",
+                    flag.category, location, flag.text
+                ));
+            }
+            if tool.red_flags.len() > MAX_FLAGS {
+                output.push_str(&format!(
+                    "- ... and {} more\n",
+                    tool.red_flags.len() - MAX_FLAGS
+                ));
+            }
+        }
 
-def sample(prompt, max_tokens=500):
-    """Request LLM completion (check capabilities first!)"""
-    req = {"jsonrpc": "2.0", "method": "sampling/createMessage",
-           "params": {"messages": [{"role": "user", "content": {"type": "text", "text": prompt}}],
-                      "maxTokens": max_tokens}, "id": 101}
-    print(json.dumps(req), flush=True)
-    return json.loads(sys.stdin.readline())
+        if let Some(code_file) = &tool.code_file {
+            let language = match code_file.rsplit('.').next() {
+                Some("rs") => "rust",
+                Some("py") => "python",
+                Some("js") => "javascript",
+                Some("rb") => "ruby",
+                Some("sh") => "bash",
+                Some("pl") => "perl",
+                Some("php") => "php",
+                _ => "",
+            };
+            let shown = tool.code_lines.min(importer::PREVIEW_LINES);
+            output.push_str(&format!(
+                "\n**Code** ({}, {} of {} lines):\n\n```{}\n{}\n```\n",
+                code_file, shown, tool.code_lines, language, tool.code_preview
+            ));
+        }
+        output.push('\n');
+    }
 
-def main():
-    # IMPORTANT: Use readline() not read() - read() blocks for EOF!
-    request = json.loads(sys.stdin.readline())
-    context = request.get("params", {}).get("context", {})
-    caps = context.get("capabilities", {})
-    
-    log("info", f"Capabilities: {caps}")
-    
-    # Example: List and read resources (always available)
-    if caps.get("resources"):
-        resources = resources_list()
-        log("info", f"Available resources: {len(resources)}")
-        # guide = resources_read("skillz://guide")
-    
-    # Example: Request user input if supported
-    if caps.get("elicitation"):
-        log("info", "Elicitation available!")
-        # resp = elicit("What's your name?", {"type": "object", "properties": {"name": {"type": "string"}}})
-    
-    # Example: Request LLM completion if supported  
-    if caps.get("sampling"):
-        log("info", "Sampling available!")
-        # resp = sample("Hello, how are you?")
-    
-    result = {"message": "Done!", "capabilities_available": caps}
-    print(json.dumps({"jsonrpc": "2.0", "result": result, "id": request.get("id")}))
+    if !inspection.failed.is_empty() {
+        output.push_str("### Can't be imported\n\n");
+        for tool in &inspection.failed {
+            output.push_str(&format!("• **{}** - {}\n", tool.name, tool.reason));
+        }
+    }
+    output.trim_end().to_string()
+}
 
-if __name__ == "__main__":
-    main()
-```
+/// list_tools section naming the tool directories whose manifest failed to
+/// load, or an empty string when every manifest loaded
+fn format_load_errors(errors: &[registry::LoadError]) -> String {
+    if errors.is_empty() {
+        return String::new();
+    }
+    let mut output = format!("### ⚠️ Skipped Tool Directories ({})\n\n", errors.len());
+    for error in errors {
+        output.push_str(&format!("• `{}` - {}\n", error.dir, error.message));
+    }
+    output.push_str(
+        "\nFix the manifest.json (its name must match the directory) to load the tool on the next start, or right away with --hot-reload.\n\n",
+    );
+    output
+}
 
----
+/// Bullet list of pipeline dry-run problems
+fn format_validation_issues(issues: &[String]) -> String {
+    issues
+        .iter()
+        .map(|issue| format!("- {}", issue))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-## ⚠️ Critical Tips
+/// Tool name of a `skillz://tools/{name}/source` URI
+fn parse_tool_source_uri(uri: &str) -> Option<&str> {
+    uri.strip_prefix("skillz://tools/")?.strip_suffix("/source")
+}
 
-### Use `readline()` NOT `read()`!
-```python
-# ✅ CORRECT - Returns immediately
-request = json.loads(sys.stdin.readline())
+/// MIME type of pipeline diagram resources
+const MERMAID_MIME_TYPE: &str = "text/vnd.mermaid";
+
+/// Scheduled runs of a pipeline, most recent first
+fn format_scheduled_runs(
+    name: &str,
+    schedule: Option<&str>,
+    runs: &[schedule::ScheduledRun],
+) -> String {
+    let mut output = format!(
+        "## ⏰ Scheduled Runs of '{}'\n\n**Schedule:** {}\n\n",
+        name,
+        schedule.unwrap_or("(none)")
+    );
+    if runs.is_empty() {
+        output.push_str("No scheduled runs yet.");
+        return output;
+    }
+    for run in runs.iter().rev() {
+        output.push_str(&format!(
+            "- {} {} ({}ms): {}\n",
+            if run.success { "✅" } else { "❌" },
+            run.started_at,
+            run.duration_ms,
+            run.summary
+        ));
+    }
+    output
+}
 
-# ❌ WRONG - Blocks forever waiting for EOF!
-request = json.loads(sys.stdin.read())
-```
+/// Pipeline name of a `skillz://pipelines/{name}/diagram` URI
+fn parse_pipeline_diagram_uri(uri: &str) -> Option<&str> {
+    uri.strip_prefix("skillz://pipelines/")?
+        .strip_suffix("/diagram")
+}
 
-### Other Tips
-- Check `context.capabilities` before using features
-- `memory` and `resources` are always available (server-side)
-- `elicitation` and `sampling` depend on MCP client support
-- Use `flush=True` to ensure output is sent immediately
-- All bidirectional methods require an `id` field for responses
-- Log to stderr for debug output that won't interfere with JSON-RPC
-- Always include `id` from original request in final response
-"##.to_string()
+/// Mermaid flowchart of a pipeline, for its diagram resource
+fn read_pipeline_diagram(registry: &registry::ToolRegistry, name: &str) -> Result<String> {
+    match registry.get_tool(name) {
+        Some(tool) if *tool.tool_type() == ToolType::Pipeline => Ok(pipeline::to_mermaid(
+            tool.pipeline_steps(),
+            tool.on_error_steps(),
+        )),
+        Some(_) => anyhow::bail!("'{}' is not a pipeline", name),
+        None => anyhow::bail!("Pipeline not found: {}", name),
     }
+}
 
-    fn get_tool_info(&self, tool_name: &str) -> String {
-        match self.registry.get_tool(tool_name) {
-            Some(tool) => {
-                let (type_name, type_emoji, path_info) = match tool.tool_type() {
-                    ToolType::Wasm => (
-                        "WASM",
-                        "🦀",
-                        format!("Directory: {}", tool.tool_dir.display()),
-                    ),
-                    ToolType::Script => {
-                        let interp = tool.interpreter().unwrap_or("executable");
-                        (
-                            "Script",
-                            "📜",
-                            format!(
-                                "Directory: {}\nInterpreter: {}",
-                                tool.tool_dir.display(),
-                                interp
-                            ),
-                        )
-                    }
-                    ToolType::Pipeline => (
-                        "Pipeline",
-                        "⛓️",
-                        format!(
-                            "Directory: {}\nSteps: {}",
-                            tool.tool_dir.display(),
-                            tool.pipeline_steps().len()
-                        ),
-                    ),
-                    ToolType::Mcp => (
-                        "MCP",
-                        "🌐",
-                        format!(
-                            "Server ID: {}",
-                            tool.server_id.as_deref().unwrap_or("unknown")
-                        ),
-                    ),
+/// get_tool_source's reply: the code in a fenced block, then the arguments
+/// that rebuild the tool once it's edited
+fn format_tool_source(tool: &registry::ToolConfig) -> String {
+    let Some(rebuild_args) = tool.rebuild_args() else {
+        return format!(
+            "❌ '{}' is a tool of an external MCP server; its code isn't stored here",
+            tool.name()
+        );
+    };
+    let mut output = format!(
+        "## 📄 Source of '{}' ({:?}, v{})\n\n",
+        tool.name(),
+        tool.tool_type(),
+        tool.manifest.version
+    );
+    let rebuild_with = match tool.tool_type() {
+        ToolType::Wasm => "`build_tool` with these arguments plus `code`",
+        ToolType::Script => "`register_script` with these arguments plus `code`",
+        _ => "`pipeline` with these arguments (steps are replaced as a whole)",
+    };
+
+    if *tool.tool_type() != ToolType::Pipeline {
+        match (tool.source_path(), tool.read_source()) {
+            (Some(path), Ok(source)) => {
+                let language = match tool.source_mime_type() {
+                    "text/x-rust" => "rust",
+                    "text/x-python" => "python",
+                    "text/javascript" => "javascript",
+                    "text/x-ruby" => "ruby",
+                    "text/x-shellscript" => "bash",
+                    "text/x-perl" => "perl",
+                    "text/x-php" => "php",
+                    _ => "",
                 };
-                let version_info = format!("- **Version:** {}\n", tool.manifest.version);
-                format!(
-                    "# {} {} Tool: {}\n\n## Description\n{}\n\n## Details\n- **Type:** {}\n- **Name:** {}\n{}- {}\n- **Status:** ✅ Ready to use\n\n## Usage\n```\ncall_tool(tool_name: \"{}\")\n```\n",
-                    type_emoji, type_name, tool.name(),
-                    tool.description(),
-                    type_name, tool.name(), version_info, path_info,
-                    tool.name()
-                )
+                output.push_str(&format!(
+                    "**File:** {}\n\n```{}\n{}{}```\n\n",
+                    path.display(),
+                    language,
+                    source,
+                    if source.ends_with('\n') { "" } else { "\n" }
+                ));
             }
-            None => format!(
-                "# ❌ Tool Not Found\n\nNo tool named '{}' exists.",
-                tool_name
+            _ => output.push_str(
+                "_No source is stored for this tool (only the compiled module); rebuilding needs the code from elsewhere._\n\n",
             ),
         }
     }
+
+    output.push_str(&format!(
+        "### ♻️ Rebuild\n\nAfter editing, call {}:\n\n```json\n{}\n```",
+        rebuild_with,
+        serde_json::to_string_pretty(&rebuild_args).unwrap_or_default()
+    ));
+    output
+}
+
+/// Source code and MIME type of a tool, for its source resource
+fn read_tool_source(registry: &registry::ToolRegistry, name: &str) -> Result<(String, String)> {
+    let tool = registry
+        .get_tool(name)
+        .ok_or_else(|| anyhow::anyhow!("Tool not found: {}", name))?;
+    Ok((tool.read_source()?, tool.source_mime_type().to_string()))
+}
+
+/// "Last used" line for tool documentation resources
+fn format_usage_line(usage: Option<&memory::ToolUsageStats>) -> String {
+    match usage {
+        Some(u) => format!(
+            "- **Last used:** {} UTC ({} calls, {} failed)\n",
+            u.last_called.as_deref().unwrap_or("unknown"),
+            u.calls,
+            u.failures
+        ),
+        None => "- **Last used:** never\n".to_string(),
+    }
+}
+
+/// "Imported from" and resolved commit lines for the tool info resource,
+/// empty for tools that weren't imported
+fn format_origin_lines(manifest: &registry::ToolManifest) -> String {
+    let Some(origin) = &manifest.imported_from else {
+        return String::new();
+    };
+    let mut lines = format!(
+        "- **Imported from:** {} ({})\n",
+        origin.source, origin.imported_at
+    );
+    if let Some(commit) = &origin.commit {
+        lines.push_str(&format!("- **Commit:** {}\n", commit));
+    }
+    lines
+}
+
+/// Render the effective sandbox config under `title`
+/// CPU, memory and last use of a service, e.g. "CPU 0.15%, memory 24MiB /
+/// 7.7GiB, last used 5m ago"; empty when none is known
+fn format_service_usage(status: &services::ServiceStatus) -> String {
+    let mut parts = Vec::new();
+    if let Some(cpu) = &status.cpu {
+        parts.push(format!("CPU {}", cpu));
+    }
+    if let Some(memory) = &status.memory {
+        parts.push(format!("memory {}", memory));
+    }
+    if let Some(used) = status.last_used {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        parts.push(format!(
+            "last used {} ago",
+            services::format_age(now.saturating_sub(used))
+        ));
+    }
+    parts.join(", ")
+}
+
+fn format_sandbox_config(title: &str, config: &runtime::SandboxConfig) -> String {
+    let paths = |paths: &[std::path::PathBuf]| {
+        if paths.is_empty() {
+            "none".to_string()
+        } else {
+            paths
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+    };
+    let limit = |value: u64, unit: &str| {
+        if value == 0 {
+            "unlimited".to_string()
+        } else {
+            format!("{} {}", value, unit)
+        }
+    };
+    format!(
+        "{}\n\n- **Mode:** {:?}\n- **Network:** {}\n- **Read paths:** {}\n- **Write paths:** {}\n- **Memory limit:** {}\n- **Time limit:** {}\n",
+        title,
+        config.mode,
+        if config.allow_network { "allowed" } else { "blocked" },
+        paths(&config.read_paths),
+        paths(&config.write_paths),
+        limit(config.memory_limit_mb, "MB"),
+        limit(config.time_limit_secs, "s"),
+    )
 }
 
 // ==================== Static Resource Content Helpers ====================
@@ -3234,6 +6969,32 @@ fn main() {
 }
 ```
 
+### HTTP Requests
+WASM tools have no network of their own; the host makes requests for them
+(only when skillz runs with `SKILLZ_SANDBOX_NETWORK=1`).
+```rust
+#[link(wasm_import_module = "skillz")]
+unsafe extern "C" {
+    fn skillz_http_fetch(req_ptr: *const u8, req_len: usize) -> i32;
+    fn skillz_http_response(buf_ptr: *mut u8, buf_len: usize) -> i32;
+}
+
+/// `request` is JSON: {"url": "...", "method": "GET", "headers": {...}, "body": "..."}
+/// Returns JSON: {"status": 200, "headers": {...}, "body": "..."} or {"error": "..."}
+fn http_fetch(request: &str) -> String {
+    unsafe {
+        let len = skillz_http_fetch(request.as_ptr(), request.len());
+        let mut buf = vec![0u8; len as usize];
+        skillz_http_response(buf.as_mut_ptr(), buf.len());
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+}
+
+fn main() {
+    println!("{}", http_fetch(r#"{"url": "https://api.github.com/zen", "headers": {"user-agent": "skillz"}}"#));
+}
+```
+
 ---
 
 ## 📜 Script Tools (Any Language)
@@ -3317,6 +7078,8 @@ fn get_protocol_content_static() -> String {
 {"jsonrpc": "2.0", "method": "memory/set", "params": {"key": "counter", "value": 42}, "id": 11}
 {"jsonrpc": "2.0", "method": "memory/list", "params": {}, "id": 12}
 {"jsonrpc": "2.0", "method": "memory/delete", "params": {"key": "counter"}, "id": 13}
+{"jsonrpc": "2.0", "method": "memory/increment", "params": {"key": "counter", "delta": 1}, "id": 14}
+{"jsonrpc": "2.0", "method": "memory/append", "params": {"key": "history", "item": "x", "max_length": 100}, "id": 15}
 ```
 
 ### Resources (NEW!)
@@ -3340,6 +7103,8 @@ fn get_protocol_content_static() -> String {
 ]}, "id": 21}
 ```
 
+Workspace files: `skillz://files/{path}` (inside the roots) returns `text`, or base64 `blob` for binary files.
+
 ### Elicitation (User Input)
 ```json
 {"jsonrpc": "2.0", "method": "elicitation/create", "params": {"message": "...", "requestedSchema": {}}, "id": 100}
@@ -3366,7 +7131,11 @@ request = json.loads(sys.stdin.read())
 "##.to_string()
 }
 
-fn get_tool_info_static(registry: &registry::ToolRegistry, tool_name: &str) -> String {
+fn get_tool_info_static(
+    registry: &registry::ToolRegistry,
+    tool_name: &str,
+    usage: Option<&memory::ToolUsageStats>,
+) -> String {
     match registry.get_tool(tool_name) {
         Some(tool) => {
             let (type_name, type_emoji) = match tool.tool_type() {
@@ -3376,10 +7145,12 @@ fn get_tool_info_static(registry: &registry::ToolRegistry, tool_name: &str) -> S
                 ToolType::Mcp => ("MCP", "🌐"),
             };
             format!(
-                "# {} {} Tool: {}\n\n## Description\n{}\n\n## Details\n- **Type:** {}\n- **Version:** {}\n- **Status:** ✅ Ready to use\n\n## Usage\n```\ncall_tool(tool_name: \"{}\")\n```\n",
+                "# {} {} Tool: {}\n\n## Description\n{}\n\n## Details\n- **Type:** {}\n- **Version:** {}\n{}{}- **Status:** ✅ Ready to use\n\n## Usage\n```\ncall_tool(tool_name: \"{}\")\n```\n",
                 type_emoji, type_name, tool.name(),
                 tool.description(),
                 type_name, tool.manifest.version,
+                format_usage_line(usage),
+                format_origin_lines(&tool.manifest),
                 tool.name()
             )
         }
@@ -3390,6 +7161,280 @@ fn get_tool_info_static(registry: &registry::ToolRegistry, tool_name: &str) -> S
     }
 }
 
+/// Load skillz.toml from the current directory (defaults if missing or invalid)
+fn load_config() -> Result<config::SkillzConfig> {
+    let config_path = std::env::current_dir()?.join("skillz.toml");
+    Ok(config::SkillzConfig::load(config_path).unwrap_or_default())
+}
+
+/// The tool registry, memory store and runtime (with memory support) for
+/// `storage_dir`, set up as `config` says; shared by the server and `skillz run`
+async fn open_tools(
+    storage_dir: &std::path::Path,
+    config: &config::SkillzConfig,
+) -> Result<(registry::ToolRegistry, memory::Memory, runtime::ToolRuntime)> {
+    let registry = registry::ToolRegistry::new(storage_dir.to_path_buf())
+        .with_max_versions(config.versions.max_versions);
+    let mut memory = memory::Memory::new(storage_dir)
+        .await?
+        .with_result_cache_ttl(config.execution.result_cache_ttl_secs);
+    if let Some(command) = config.memory.embedding_command.clone() {
+        memory = memory.with_embedder(memory::command_embedder(command));
+    }
+
+    let runtime = runtime::ToolRuntime::new()?
+        .with_memory(memory.clone())
+        .with_max_concurrency(config.execution.max_concurrent_tools);
+    Ok((registry, memory, runtime))
+}
+
+/// Read `skillz run` arguments: `--args` JSON, or the JSON in `--args-file`
+/// ("-" for stdin), or an empty object
+fn read_run_args(
+    args: Option<String>,
+    args_file: Option<std::path::PathBuf>,
+) -> Result<serde_json::Value> {
+    let text = match (args, args_file) {
+        (Some(_), Some(_)) => anyhow::bail!("Pass either --args or --args-file, not both"),
+        (Some(args), None) => args,
+        (None, Some(path)) if path.as_os_str() == "-" => {
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)
+                .context("Failed to read arguments from stdin")?;
+            text
+        }
+        (None, Some(path)) => std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read arguments from {}", path.display()))?,
+        (None, None) => return Ok(serde_json::json!({})),
+    };
+    let value: serde_json::Value =
+        serde_json::from_str(&text).context("Tool arguments must be JSON")?;
+    if !value.is_object() {
+        anyhow::bail!("Tool arguments must be a JSON object, got: {}", value);
+    }
+    Ok(value)
+}
+
+/// Run a CLI subcommand instead of starting the server
+async fn run_command(command: Commands, storage_dir: &std::path::Path) -> Result<()> {
+    let registry = registry::ToolRegistry::new(storage_dir.to_path_buf());
+
+    match command {
+        Commands::List { tag, group_by } => {
+            println!(
+                "{}",
+                format_tool_list(&registry, tag.as_deref(), group_by.as_deref())
+            );
+            let errors = registry.load_errors();
+            if !errors.is_empty() {
+                anyhow::bail!("{} tool(s) failed to load", errors.len());
+            }
+        }
+        Commands::Info { name } => {
+            if registry.get_tool(&name).is_none() {
+                anyhow::bail!("No tool named '{}' in {}", name, storage_dir.display());
+            }
+            // Read usage only from an existing memory database; don't create one
+            let usage = if storage_dir.join(memory::MEMORY_DB_FILE).exists() {
+                memory::Memory::new(storage_dir)
+                    .await?
+                    .tool_usage(&name)
+                    .await?
+            } else {
+                None
+            };
+            println!("{}", get_tool_info_static(&registry, &name, usage.as_ref()));
+        }
+        Commands::Run {
+            name,
+            args,
+            args_file,
+        } => {
+            let arguments = read_run_args(args, args_file)?;
+            let (registry, _memory, mut runtime) = open_tools(storage_dir, &load_config()?).await?;
+            let tool = registry.get_tool(&name).with_context(|| {
+                format!("No tool named '{}' in {}", name, storage_dir.display())
+            })?;
+            if matches!(tool.tool_type(), ToolType::Pipeline | ToolType::Mcp) {
+                anyhow::bail!(
+                    "'{}' is a {:?} tool; skillz run only runs WASM and script tools (call it through the server)",
+                    name,
+                    tool.tool_type()
+                );
+            }
+            if !tool.manifest.requires_services.is_empty() {
+                let env = services::ServiceRegistry::new(storage_dir)
+                    .check_required_services(&tool.manifest.requires_services)
+                    .map_err(anyhow::Error::msg)?;
+                for (key, value) in env {
+                    runtime.set_env_var(key, value);
+                }
+            }
+            let result =
+                tokio::task::spawn_blocking(move || runtime.call_tool(&tool, arguments)).await??;
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        Commands::Backup { output } => {
+            let metadata = backup::Backup::create(&registry, &output)?;
+            println!(
+                "Backed up {} tool(s) from {} to {}",
+                metadata.tools.len(),
+                storage_dir.display(),
+                output.display()
+            );
+        }
+        Commands::Restore {
+            archive,
+            no_rebuild,
+        } => {
+            let summary = backup::Backup::restore(&registry, &archive, !no_rebuild)?;
+            if let Some(metadata) = &summary.metadata {
+                println!(
+                    "Restored {} tool(s) from the backup of {} into {}",
+                    metadata.tools.len(),
+                    metadata.created_at,
+                    storage_dir.display()
+                );
+            }
+            if let Some(previous) = &summary.previous_dir {
+                println!("Previous tools directory kept at {}", previous.display());
+            }
+            if !summary.recompiled.is_empty() {
+                println!("Recompiled: {}", summary.recompiled.join(", "));
+            }
+            if !summary.reinstalled.is_empty() {
+                println!(
+                    "Reinstalled dependencies: {}",
+                    summary.reinstalled.join(", ")
+                );
+            }
+            for warning in &summary.warnings {
+                eprintln!("⚠️ {}", warning);
+            }
+        }
+        Commands::Tools {
+            action: ToolsCommand::Export { name, output },
+        } => {
+            let path = match output {
+                Some(path) => {
+                    std::fs::write(&path, exporter::Exporter::package(&registry, &name)?)?;
+                    path
+                }
+                None => exporter::Exporter::export_to_dir(
+                    &registry,
+                    &name,
+                    &storage_dir.join(exporter::EXPORTS_DIR),
+                )?,
+            };
+            println!("{}", path.display());
+        }
+        Commands::Tools {
+            action:
+                ToolsCommand::Import {
+                    source,
+                    overwrite,
+                    select,
+                },
+        } => {
+            let importer = importer::Importer::new(storage_dir.to_path_buf());
+            let filter = importer::ImportFilter {
+                select: (!select.is_empty()).then_some(select),
+                overwrite,
+                overwrite_tools: Vec::new(),
+            };
+            let summary = importer.import_all(&source, &registry, &filter)?;
+            for result in &summary.imported {
+                println!("{}\n{}", result.tool_name, result.message);
+            }
+            for tool in summary.skipped.iter().chain(&summary.failed) {
+                eprintln!("⚠️ {}: {}", tool.name, tool.reason);
+            }
+            if summary.imported.is_empty() {
+                anyhow::bail!("No tools were imported from {}", summary.source);
+            }
+        }
+        Commands::Tools {
+            action: ToolsCommand::Update { name, force },
+        } => {
+            let importer = importer::Importer::new(storage_dir.to_path_buf());
+            let result = importer.update(&name, &registry, force)?;
+            if result.diff.is_none() {
+                println!("{} is up to date (v{})", name, result.to_version);
+            } else {
+                println!(
+                    "{}: v{} -> v{}",
+                    name, result.from_version, result.to_version
+                );
+            }
+            if !result.message.is_empty() {
+                println!("{}", result.message);
+            }
+        }
+        Commands::Memory {
+            action: MemoryCommand::Export { tool, output },
+        } => {
+            let memory = memory::Memory::new(storage_dir).await?;
+            let export = memory.export(tool.as_deref()).await?;
+            let path = match output {
+                Some(path) => {
+                    write_json_file(&path, &export)?;
+                    path
+                }
+                None => write_memory_export(storage_dir, tool.as_deref(), &export)?,
+            };
+            println!("{}", path.display());
+        }
+        Commands::Memory {
+            action: MemoryCommand::Import { file, strategy },
+        } => {
+            let strategy = memory::MergeStrategy::parse(&strategy)?;
+            let memory = memory::Memory::new(storage_dir).await?;
+            let summary = memory.import(&read_memory_export(&file)?, strategy).await?;
+            println!(
+                "Imported {} entries ({} existing kept)",
+                summary.imported, summary.skipped
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Plain HTTP routes served next to the MCP transport: `GET /health` for
+/// load balancers and `GET /tools` to list tools without speaking MCP
+fn rest_routes(registry: registry::ToolRegistry) -> axum::Router {
+    use axum::{extract::State, routing::get, Json};
+
+    async fn health() -> Json<serde_json::Value> {
+        Json(serde_json::json!({
+            "status": "ok",
+            "version": env!("CARGO_PKG_VERSION"),
+        }))
+    }
+
+    async fn tools(State(registry): State<registry::ToolRegistry>) -> Json<serde_json::Value> {
+        let mut tools = registry.list_tools();
+        tools.sort_by(|a, b| a.name().cmp(b.name()));
+        let tools: Vec<_> = tools
+            .iter()
+            .map(|t| {
+                serde_json::json!({
+                    "name": t.name(),
+                    "description": t.manifest.description,
+                    "type": t.tool_type(),
+                    "version": t.manifest.version,
+                    "tags": t.manifest.tags,
+                })
+            })
+            .collect();
+        Json(serde_json::json!({ "tools": tools }))
+    }
+
+    axum::Router::new()
+        .route("/health", get(health))
+        .route("/tools", get(tools))
+        .with_state(registry)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -3403,20 +7448,18 @@ async fn main() -> Result<()> {
     let storage_dir = std::path::PathBuf::from(tools_dir);
     std::fs::create_dir_all(&storage_dir)?;
 
-    eprintln!("Tools directory: {}", storage_dir.display());
+    if let Some(command) = cli.command {
+        return run_command(command, &storage_dir).await;
+    }
 
-    let registry = registry::ToolRegistry::new(storage_dir.clone());
-    let memory = memory::Memory::new(&storage_dir).await?;
+    eprintln!("Tools directory: {}", storage_dir.display());
 
-    // Create runtime with memory support
-    let runtime = runtime::ToolRuntime::new()?.with_memory(memory.clone());
+    let config = load_config()?;
+    let (registry, memory, runtime) = open_tools(&storage_dir, &config).await?;
+    memory.spawn_cleanup(config.memory.cleanup_interval_secs);
 
     eprintln!("Memory database initialized (with runtime integration)");
 
-    // Load config
-    let config_path = std::env::current_dir()?.join("skillz.toml");
-    let config = config::SkillzConfig::load(config_path).unwrap_or_default();
-
     // Initialize client manager
     let client_manager = Arc::new(client::McpClientManager::new());
 
@@ -3587,13 +7630,34 @@ async fn main() -> Result<()> {
         false
     }
 
+    let log_level = match cli.log_level.as_ref().or(config.logging.level.as_ref()) {
+        Some(name) => logging::parse_level(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown log level '{}' (use debug, info, notice, warning, error, critical, alert or emergency)",
+                name
+            )
+        })?,
+        None => logging::DEFAULT_LOG_LEVEL,
+    };
     let state = AppState::new(
         registry,
         runtime,
         memory,
         client_manager,
         storage_dir.clone(),
-    );
+    )
+    .with_log_level(log_level)
+    .with_service_idle_timeout(config.services.idle_timeout_secs);
+    if config.memory.embed_with_sampling && config.memory.embedding_command.is_none() {
+        if let Some(sampling) = state.runtime.sampling_handler() {
+            state
+                .memory
+                .set_embedder(memory::sampling_embedder(sampling));
+        }
+    }
+
+    let _scheduler = state.spawn_scheduler();
+    let _idle_services = state.service_registry.spawn_idle_stop();
 
     // Start hot reload if enabled
     let _hot_reload = if cli.hot_reload {
@@ -3602,6 +7666,7 @@ async fn main() -> Result<()> {
                 let registry_clone = state.registry.clone();
                 let peer_for_hot_reload = state.peer.clone();
                 let subscriptions_for_hot_reload = state.subscriptions.clone();
+                let services_for_hot_reload = state.service_registry.clone();
                 // Spawn task to handle reload events
                 tokio::spawn(async move {
                     while let Some(event) = hr.next_event().await {
@@ -3657,6 +7722,13 @@ async fn main() -> Result<()> {
                                     let _ = p.notify_resource_list_changed().await;
                                 }
                             }
+                            watcher::WatchEvent::ServiceChanged(name) => {
+                                eprintln!(
+                                    "🐳 Hot reload: service {} changed, reloading definitions",
+                                    name
+                                );
+                                services_for_hot_reload.load_definitions();
+                            }
                             watcher::WatchEvent::Error(e) => {
                                 eprintln!("⚠️ Hot reload error: {}", e);
                             }
@@ -3680,7 +7752,11 @@ async fn main() -> Result<()> {
             if cli.hot_reload {
                 eprintln!("🔥 Hot reload enabled");
             }
-            state.serve(stdio()).await?.waiting().await?;
+            state
+                .serve(logging::defer_early_requests(stdio()))
+                .await?
+                .waiting()
+                .await?;
         }
         "http" | "sse" => {
             use rmcp::transport::sse_server::{SseServer, SseServerConfig};
@@ -3700,6 +7776,8 @@ async fn main() -> Result<()> {
             eprintln!("Skillz MCP started (HTTP/SSE transport)");
             eprintln!("  SSE endpoint: http://{}/sse", addr);
             eprintln!("  POST endpoint: http://{}/message", addr);
+            eprintln!("  Health check:  http://{}/health", addr);
+            eprintln!("  Tool list:     http://{}/tools", addr);
             if cli.hot_reload {
                 eprintln!("  🔥 Hot reload: enabled");
             }
@@ -3710,8 +7788,18 @@ async fn main() -> Result<()> {
                 addr
             );
 
-            // Start the SSE server (it handles the HTTP server internally)
-            let mut sse_server = SseServer::serve_with_config(config).await?;
+            // Serve the SSE transport together with the plain REST routes
+            let (mut sse_server, sse_router) = SseServer::new(config);
+            let app = sse_router.merge(rest_routes(state.registry.clone()));
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            let server_ct = ct.child_token();
+            tokio::spawn(async move {
+                let server = axum::serve(listener, app)
+                    .with_graceful_shutdown(async move { server_ct.cancelled().await });
+                if let Err(e) = server.await {
+                    eprintln!("HTTP server error: {}", e);
+                }
+            });
 
             // Accept and serve MCP connections
             loop {
@@ -3721,7 +7809,7 @@ async fn main() -> Result<()> {
                             let state_clone = state.clone();
                             tokio::spawn(async move {
                                 eprintln!("New SSE client connected");
-                                if let Err(e) = state_clone.serve(transport).await {
+                                if let Err(e) = state_clone.serve(logging::defer_early_requests(transport)).await {
                                     eprintln!("Client error: {}", e);
                                 }
                             });
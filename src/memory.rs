@@ -2,41 +2,111 @@
 //!
 //! Provides persistent memory for tools to store state between calls.
 //! Uses rusqlite with bundled SQLite for cross-platform compatibility.
+//!
+//! Entries can carry an embedding vector for semantic search. Vectors come
+//! from a configurable [`Embedder`] and are compared in Rust (the bundled
+//! SQLite has no vector functions); without an embedder, search falls back
+//! to full-text matching.
 
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection};
+use rusqlite::{
+    params, Connection, ErrorCode, OpenFlags, OptionalExtension, Transaction, TransactionBehavior,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::path::Path;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Semaphore, SemaphorePermit};
+
+/// File name of the memory database inside TOOLS_DIR
+pub const MEMORY_DB_FILE: &str = ".memory.db";
 
 /// Current schema version - increment when making breaking changes
-const SCHEMA_VERSION: i32 = 2;
+const SCHEMA_VERSION: i32 = 4;
+
+/// Reserved namespace holding per-tool usage statistics (one key per tool)
+pub const USAGE_NAMESPACE: &str = "_skillz_usage";
+
+/// Reserved namespace holding memoized results of idempotentHint tools
+pub const RESULT_CACHE_NAMESPACE: &str = "_skillz_cache";
+
+/// Reserved key holding an optional JSON Schema for a tool's namespace.
+/// Once set, every other value written for that tool must conform to it.
+pub const SCHEMA_KEY: &str = "_schema";
+
+/// Reserved namespace holding cached outputs of pipeline steps with a
+/// `cache` block, keyed by pipeline (see [`pipeline_cache_key`])
+pub const PIPELINE_CACHE_NAMESPACE: &str = "__pipeline_cache__";
+
+/// Reserved namespace holding step checkpoints of pipelines run with
+/// `debug: true`, keyed `<run_id>/step-001` (see [`pipeline_debug_key`])
+pub const PIPELINE_DEBUG_NAMESPACE: &str = "__pipeline_debug__";
+
+/// How long pipeline debug checkpoints are kept (one day)
+pub const PIPELINE_DEBUG_TTL_SECS: u64 = 86_400;
+
+/// Default lifetime of a memoized tool result
+pub const DEFAULT_RESULT_CACHE_TTL_SECS: u64 = 300;
+
+/// Default interval between sweeps of expired entries (10 minutes)
+pub const DEFAULT_CLEANUP_INTERVAL_SECS: u64 = 600;
+
+/// Values larger than this (serialized) are truncated in listings
+pub const LIST_VALUE_PREVIEW_BYTES: usize = 256;
+
+/// Entries expiring within this window are reported as "expiring soon" in stats
+pub const EXPIRING_SOON_SECS: u64 = 3600;
+
+/// Largest memory export returned inline; bigger ones are written to a file
+pub const EXPORT_INLINE_LIMIT: usize = 64 * 1024;
+
+/// Read-only connections kept open next to the writer, so that many reads
+/// can run at once
+pub const READ_POOL_SIZE: usize = 4;
+
+/// Default number of results returned by a semantic search
+pub const DEFAULT_SEARCH_TOP_K: usize = 5;
+
+/// Number of dimensions requested from the client model when embedding via sampling
+pub const SAMPLING_EMBEDDING_DIMS: usize = 32;
+
+/// Computes an embedding vector for a piece of text
+pub type Embedder =
+    Arc<dyn Fn(String) -> Pin<Box<dyn Future<Output = Result<Vec<f32>>> + Send>> + Send + Sync>;
 
 /// Memory store for tool state persistence
 #[derive(Clone)]
 pub struct Memory {
+    /// The one writer connection; holding its lock serializes writes
     conn: Arc<Mutex<Connection>>,
+    /// Read-only connections for queries, which WAL lets run alongside the writer
+    readers: Arc<ReaderPool>,
+    /// Lifetime of memoized tool results in seconds (0 disables the cache)
+    result_cache_ttl: u64,
+    /// Embedder used on store and search; shared so clones see it once set
+    embedder: Arc<std::sync::RwLock<Option<Embedder>>>,
+    /// Database file, reopened after a connection-level failure
+    db_path: PathBuf,
 }
 
 impl Memory {
     /// Create a new memory store
     pub async fn new(tools_dir: &Path) -> Result<Self> {
-        let db_path = tools_dir.join(".memory.db");
+        let db_path = tools_dir.join(MEMORY_DB_FILE);
 
         // Ensure parent directory exists
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        let conn = Connection::open(&db_path)
-            .with_context(|| format!("Failed to open memory database at {:?}", db_path))?;
-
-        // Enable WAL mode for better concurrency
-        conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;")?;
-
         let store = Self {
-            conn: Arc::new(Mutex::new(conn)),
+            conn: Arc::new(Mutex::new(open_writer(&db_path)?)),
+            readers: Arc::new(ReaderPool::open(&db_path, READ_POOL_SIZE)?),
+            result_cache_ttl: DEFAULT_RESULT_CACHE_TTL_SECS,
+            embedder: Arc::new(std::sync::RwLock::new(None)),
+            db_path,
         };
 
         // Run migrations
@@ -45,6 +115,93 @@ impl Memory {
         Ok(store)
     }
 
+    /// A read-only connection from the pool, waiting for one to be free
+    async fn reader(&self) -> Result<PooledReader<'_>> {
+        self.readers.acquire().await
+    }
+
+    /// Run `op` on a read-only connection. After a connection-level failure
+    /// (see [`is_connection_error`]) the connection is reopened and `op`
+    /// retried once before the error is returned.
+    async fn read<T>(&self, mut op: impl FnMut(&Connection) -> Result<T>) -> Result<T> {
+        let mut conn = self.reader().await?;
+        match op(&conn) {
+            Err(e) if is_connection_error(&e) => {
+                eprintln!("Memory database read failed ({:#}); reconnecting", e);
+                conn.reopen().map_err(|reopen| e.context(reopen))?;
+                op(&conn)
+            }
+            result => result,
+        }
+    }
+
+    /// Run `op` in an immediate transaction on the writer connection,
+    /// holding its lock. If the database file can't be opened or read
+    /// (see [`is_connection_error`]) the connection is reopened and `op`
+    /// retried once; the failed transaction was rolled back, so nothing is
+    /// applied twice or left half-done.
+    async fn write<T>(&self, mut op: impl FnMut(&Transaction) -> Result<T>) -> Result<T> {
+        let mut conn = self.conn.lock().await;
+        let mut run = |conn: &mut Connection| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let value = op(&tx)?;
+            tx.commit()?;
+            Ok(value)
+        };
+        match run(&mut conn) {
+            Err(e) if is_connection_error(&e) => {
+                eprintln!("Memory database write failed ({:#}); reconnecting", e);
+                *conn = open_writer(&self.db_path).map_err(|reopen| e.context(reopen))?;
+                run(&mut conn)
+            }
+            result => result,
+        }
+    }
+
+    /// Set how long memoized tool results live (0 disables the result cache)
+    pub fn with_result_cache_ttl(mut self, ttl_secs: u64) -> Self {
+        self.result_cache_ttl = ttl_secs;
+        self
+    }
+
+    /// Compute embeddings with this embedder when storing and searching
+    pub fn with_embedder(self, embedder: Embedder) -> Self {
+        self.set_embedder(embedder);
+        self
+    }
+
+    /// Install an embedder after construction (applies to all clones)
+    pub fn set_embedder(&self, embedder: Embedder) {
+        if let Ok(mut slot) = self.embedder.write() {
+            *slot = Some(embedder);
+        }
+    }
+
+    /// The configured embedder, if any
+    fn embedder(&self) -> Option<Embedder> {
+        self.embedder.read().ok().and_then(|slot| slot.clone())
+    }
+
+    /// Embed a value about to be stored. Internal namespaces are never
+    /// embedded, and failures are logged rather than failing the write
+    /// (the entry is embedded again on the next search).
+    async fn embed_value(&self, tool: &str, value: &Value) -> Option<Vec<f32>> {
+        if tool.starts_with("_skillz_")
+            || tool == PIPELINE_CACHE_NAMESPACE
+            || tool == PIPELINE_DEBUG_NAMESPACE
+        {
+            return None;
+        }
+        let embedder = self.embedder()?;
+        match embedder(embedding_text(value)).await {
+            Ok(vector) => Some(vector),
+            Err(e) => {
+                eprintln!("Failed to embed memory entry for {}: {}", tool, e);
+                None
+            }
+        }
+    }
+
     /// Run database migrations
     async fn migrate(&self) -> Result<()> {
         let conn = self.conn.lock().await;
@@ -76,6 +233,14 @@ impl Memory {
             Self::migrate_v2(&conn)?;
         }
 
+        if current_version < 3 {
+            Self::migrate_v3(&conn)?;
+        }
+
+        if current_version < 4 {
+            Self::migrate_v4(&conn)?;
+        }
+
         Ok(())
     }
 
@@ -138,30 +303,122 @@ impl Memory {
         Ok(())
     }
 
+    /// Migration v3: Audit log of tool executions
+    fn migrate_v3(conn: &Connection) -> Result<()> {
+        eprintln!("Running memory migration v3 (execution audit log)...");
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS executions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                tool_name TEXT NOT NULL,
+                args_hash TEXT NOT NULL,
+                success INTEGER NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                executed_at TEXT NOT NULL DEFAULT (datetime('now'))
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_executions_tool ON executions(tool_name, id)",
+            [],
+        )?;
+
+        // Record migration
+        conn.execute("INSERT INTO _migrations (version) VALUES (3)", [])?;
+
+        eprintln!("Memory migration v3 complete");
+        Ok(())
+    }
+
+    /// Migration v4: Embedding vectors for semantic search
+    fn migrate_v4(conn: &Connection) -> Result<()> {
+        eprintln!("Running memory migration v4 (embeddings)...");
+
+        // Little-endian f32 vector (NULL = not embedded yet)
+        conn.execute(
+            "ALTER TABLE memories ADD COLUMN embedding BLOB DEFAULT NULL",
+            [],
+        )?;
+
+        // Record migration
+        conn.execute("INSERT INTO _migrations (version) VALUES (4)", [])?;
+
+        eprintln!("Memory migration v4 complete");
+        Ok(())
+    }
+
     // ==================== Key-Value Operations ====================
 
     /// Get a value by key (returns None if expired)
     pub async fn get(&self, tool: &str, key: &str) -> Result<Option<Value>> {
-        let conn = self.conn.lock().await;
-        let result = conn.query_row(
-            "SELECT value FROM memories 
-             WHERE tool = ?1 AND key = ?2 
-             AND (expires_at IS NULL OR expires_at > datetime('now'))",
-            params![tool, key],
-            |row| row.get::<_, String>(0),
-        );
-
-        match result {
-            Ok(json_str) => {
-                let value: Value = serde_json::from_str(&json_str)?;
-                Ok(Some(value))
+        self.read(|conn| {
+            let result = conn.query_row(
+                "SELECT value FROM memories 
+                 WHERE tool = ?1 AND key = ?2 
+                 AND (expires_at IS NULL OR expires_at > datetime('now'))",
+                params![tool, key],
+                |row| row.get::<_, String>(0),
+            );
+
+            match result {
+                Ok(json_str) => {
+                    let value: Value = serde_json::from_str(&json_str)?;
+                    Ok(Some(value))
+                }
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(e.into()),
             }
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e.into()),
-        }
+        })
+        .await
+    }
+
+    /// Whether a live (unexpired) entry exists, without reading its value
+    pub async fn exists(&self, tool: &str, key: &str) -> Result<bool> {
+        self.read(|conn| {
+            let found = conn
+                .query_row(
+                    "SELECT 1 FROM memories
+                     WHERE tool = ?1 AND key = ?2
+                     AND (expires_at IS NULL OR expires_at > datetime('now'))
+                     LIMIT 1",
+                    params![tool, key],
+                    |_| Ok(()),
+                )
+                .optional()?;
+            Ok(found.is_some())
+        })
+        .await
+    }
+
+    /// Keys of live entries whose stored JSON contains `needle` (case-insensitive
+    /// for ASCII, as with SQL `LIKE`), ordered by key, at most `limit` of them
+    pub async fn query_values(
+        &self,
+        tool: &str,
+        needle: &str,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>> {
+        self.read(|conn| {
+            let pattern = format!("%{}%", escape_like(needle));
+            let limit = limit.map(|l| l as i64).unwrap_or(-1);
+            let mut stmt = conn.prepare(
+                "SELECT key FROM memories
+                 WHERE tool = ?1 AND value LIKE ?2 ESCAPE '\\'
+                 AND (expires_at IS NULL OR expires_at > datetime('now'))
+                 ORDER BY key
+                 LIMIT ?3",
+            )?;
+            let keys = stmt
+                .query_map(params![tool, pattern, limit], |row| row.get::<_, String>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            Ok(keys)
+        })
+        .await
     }
 
     /// Set a value (without TTL - never expires)
+    #[allow(dead_code)]
     pub async fn set(&self, tool: &str, key: &str, value: Value) -> Result<()> {
         self.set_with_ttl(tool, key, value, None).await
     }
@@ -174,203 +431,1596 @@ impl Memory {
         value: Value,
         ttl_secs: Option<u64>,
     ) -> Result<()> {
-        let conn = self.conn.lock().await;
-        let json_str = serde_json::to_string(&value)?;
+        let embedding = self.embed_value(tool, &value).await;
+        self.write(|conn| {
+            check_schema(conn, tool, key, &value)?;
+            upsert(conn, tool, key, &value, ttl_secs, embedding.as_deref())
+        })
+        .await
+    }
+
+    /// Get several keys at once; missing or expired keys map to None
+    pub async fn get_many(
+        &self,
+        tool: &str,
+        keys: &[String],
+    ) -> Result<Vec<(String, Option<Value>)>> {
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            values.push((key.clone(), self.get(tool, key).await?));
+        }
+        Ok(values)
+    }
 
-        // Calculate expiration time if TTL provided
-        let expires_at = ttl_secs
-            .filter(|&t| t > 0)
-            .map(|t| format!("+{} seconds", t));
+    /// Write several entries in one transaction: if any entry is invalid
+    /// (empty key, or a missing key in [`BulkMode::Update`]) nothing is written.
+    /// Within a batch a later entry for the same key wins.
+    pub async fn set_many(
+        &self,
+        tool: &str,
+        entries: &[BulkEntry],
+        mode: BulkMode,
+    ) -> Result<Vec<(String, BulkStatus)>> {
+        // Embed before taking the lock; the embedder may be slow
+        let mut embeddings = Vec::with_capacity(entries.len());
+        for entry in entries {
+            embeddings.push(self.embed_value(tool, &entry.value).await);
+        }
 
-        match expires_at {
-            Some(offset) => {
-                conn.execute(
-                    &format!(
-                        "INSERT INTO memories (tool, key, value, updated_at, expires_at)
-                         VALUES (?1, ?2, ?3, datetime('now'), datetime('now', '{}'))
-                         ON CONFLICT(tool, key) DO UPDATE SET
-                            value = excluded.value,
-                            updated_at = datetime('now'),
-                            expires_at = datetime('now', '{}')",
-                        offset, offset
-                    ),
-                    params![tool, key, json_str],
+        self.write(|tx| {
+            let mut statuses: Vec<(String, BulkStatus)> = Vec::with_capacity(entries.len());
+            for (i, (entry, embedding)) in entries.iter().zip(&embeddings).enumerate() {
+                if entry.key.is_empty() {
+                    anyhow::bail!("Entry {} has an empty key", i);
+                }
+
+                let exists: bool = tx.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM memories
+                     WHERE tool = ?1 AND key = ?2
+                     AND (expires_at IS NULL OR expires_at > datetime('now')))",
+                    params![tool, entry.key],
+                    |row| row.get(0),
                 )?;
-            }
-            None => {
-                conn.execute(
-                    "INSERT INTO memories (tool, key, value, updated_at, expires_at)
-                     VALUES (?1, ?2, ?3, datetime('now'), NULL)
-                     ON CONFLICT(tool, key) DO UPDATE SET
-                        value = excluded.value,
-                        updated_at = datetime('now'),
-                        expires_at = NULL",
-                    params![tool, key, json_str],
+                if mode == BulkMode::Update && !exists {
+                    anyhow::bail!("Entry {}: key '{}' does not exist", i, entry.key);
+                }
+                check_schema(tx, tool, &entry.key, &entry.value)
+                    .with_context(|| format!("Entry {}", i))?;
+
+                let mut status = if exists {
+                    BulkStatus::Updated
+                } else {
+                    BulkStatus::Created
+                };
+                // An earlier entry for the same key is overwritten by this one,
+                // which takes over its status relative to what was stored before
+                if let Some(earlier) = statuses
+                    .iter_mut()
+                    .find(|(k, s)| *k == entry.key && *s != BulkStatus::Superseded)
+                {
+                    status = earlier.1;
+                    earlier.1 = BulkStatus::Superseded;
+                }
+
+                upsert(
+                    tx,
+                    tool,
+                    &entry.key,
+                    &entry.value,
+                    entry.ttl_secs,
+                    embedding.as_deref(),
                 )?;
+                statuses.push((entry.key.clone(), status));
             }
-        }
-
-        Ok(())
+            Ok(statuses)
+        })
+        .await
     }
 
-    /// List all keys for a tool (excludes expired)
-    pub async fn list_keys(&self, tool: &str) -> Result<Vec<String>> {
-        let conn = self.conn.lock().await;
-        let mut stmt = conn.prepare(
-            "SELECT key FROM memories 
-             WHERE tool = ?1 
-             AND (expires_at IS NULL OR expires_at > datetime('now'))
-             ORDER BY key",
-        )?;
+    /// Atomically add `delta` to an integer entry and return the new value.
+    /// A missing or expired key counts as 0; an existing expiry is kept.
+    pub async fn increment(&self, tool: &str, key: &str, delta: i64) -> Result<i64> {
+        self.update_atomically(tool, key, |current| {
+            let current = match current {
+                None => 0,
+                Some(value) => value
+                    .as_i64()
+                    .with_context(|| format!("Value of '{}' is not an integer", key))?,
+            };
+            let next = current
+                .checked_add(delta)
+                .with_context(|| format!("Incrementing '{}' overflows", key))?;
+            Ok((Value::from(next), next))
+        })
+        .await
+    }
 
-        let keys = stmt
-            .query_map(params![tool], |row| row.get(0))?
-            .collect::<Result<Vec<String>, _>>()?;
+    /// Atomically append `item` to an array entry and return the new length.
+    /// A missing or expired key starts a new array; with `max_len` the oldest
+    /// items are dropped so at most that many remain.
+    pub async fn append(
+        &self,
+        tool: &str,
+        key: &str,
+        item: Value,
+        max_len: Option<usize>,
+    ) -> Result<usize> {
+        self.update_atomically(tool, key, |current| {
+            let mut items = match current {
+                None => Vec::new(),
+                Some(Value::Array(items)) => items,
+                Some(_) => anyhow::bail!("Value of '{}' is not an array", key),
+            };
+            items.push(item.clone());
+            if let Some(max_len) = max_len {
+                let excess = items.len().saturating_sub(max_len);
+                items.drain(..excess);
+            }
+            let len = items.len();
+            Ok((Value::Array(items), len))
+        })
+        .await
+    }
 
-        Ok(keys)
+    /// Read-modify-write an entry in one immediate transaction, so concurrent
+    /// updates (from this or another process) are serialized. The embedding
+    /// is cleared and recomputed lazily by the next semantic search.
+    async fn update_atomically<T>(
+        &self,
+        tool: &str,
+        key: &str,
+        mut update: impl FnMut(Option<Value>) -> Result<(Value, T)>,
+    ) -> Result<T> {
+        if key.is_empty() {
+            anyhow::bail!("Key must not be empty");
+        }
+        self.write(|tx| {
+            let current: Option<String> = tx
+                .query_row(
+                    "SELECT value FROM memories
+                 WHERE tool = ?1 AND key = ?2
+                 AND (expires_at IS NULL OR expires_at > datetime('now'))",
+                    params![tool, key],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let current = current
+                .map(|json| serde_json::from_str(&json))
+                .transpose()?;
+            let exists = current.is_some();
+
+            let (value, result) = update(current)?;
+            check_schema(tx, tool, key, &value)?;
+            if exists {
+                tx.execute(
+                    "UPDATE memories SET value = ?3, updated_at = datetime('now'), embedding = NULL
+                     WHERE tool = ?1 AND key = ?2",
+                    params![tool, key, serde_json::to_string(&value)?],
+                )?;
+            } else {
+                upsert(tx, tool, key, &value, None, None)?;
+            }
+            Ok(result)
+        })
+        .await
     }
 
-    /// Get all entries for a tool (excludes expired)
+    /// List all keys for a tool (excludes expired)
     #[allow(dead_code)]
-    pub async fn get_all(&self, tool: &str) -> Result<Vec<(String, Value)>> {
-        let conn = self.conn.lock().await;
-        let mut stmt = conn.prepare(
-            "SELECT key, value FROM memories 
-             WHERE tool = ?1 
-             AND (expires_at IS NULL OR expires_at > datetime('now'))
-             ORDER BY key",
-        )?;
+    pub async fn list_keys(&self, tool: &str) -> Result<Vec<String>> {
+        let page = self.list(tool, None, None, 0, false).await?;
+        Ok(page.entries.into_iter().map(|e| e.key).collect())
+    }
 
-        let entries = stmt
-            .query_map(params![tool], |row| {
-                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-            })?
-            .filter_map(|r| r.ok())
-            .filter_map(|(key, value_str)| {
-                serde_json::from_str(&value_str)
-                    .ok()
-                    .map(|value| (key, value))
+    /// List a page of live entries for a tool, ordered by key.
+    ///
+    /// `prefix` filters keys in SQL; `limit` (None = all) and `offset` page
+    /// through the result. With `include_values`, each entry carries its value,
+    /// truncated to [`LIST_VALUE_PREVIEW_BYTES`] when larger.
+    pub async fn list(
+        &self,
+        tool: &str,
+        prefix: Option<&str>,
+        limit: Option<usize>,
+        offset: usize,
+        include_values: bool,
+    ) -> Result<MemoryPage> {
+        self.read(|conn| {
+            let pattern = format!("{}%", escape_like(prefix.unwrap_or("")));
+            // Fetch one extra row to know whether another page follows
+            let fetch = limit.map(|l| l as i64 + 1).unwrap_or(-1);
+
+            let mut stmt = conn.prepare(
+                "SELECT key, value FROM memories
+                 WHERE tool = ?1 AND key LIKE ?2 ESCAPE '\\'
+                 AND (expires_at IS NULL OR expires_at > datetime('now'))
+                 ORDER BY key
+                 LIMIT ?3 OFFSET ?4",
+            )?;
+            let mut entries = stmt
+                .query_map(params![tool, pattern, fetch, offset as i64], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?
+                .map(|row| {
+                    let (key, raw) = row?;
+                    Ok(ListedEntry::new(key, &raw, include_values))
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let next_offset = match limit {
+                Some(limit) if entries.len() > limit => {
+                    entries.truncate(limit);
+                    Some(offset + limit)
+                }
+                _ => None,
+            };
+
+            Ok(MemoryPage {
+                entries,
+                next_offset,
             })
-            .collect();
+        })
+        .await
+    }
 
-        Ok(entries)
+    /// Get all entries for a tool (excludes expired)
+    pub async fn get_all(&self, tool: &str) -> Result<Vec<(String, Value)>> {
+        self.read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT key, value FROM memories 
+                 WHERE tool = ?1 
+                 AND (expires_at IS NULL OR expires_at > datetime('now'))
+                 ORDER BY key",
+            )?;
+
+            let entries = stmt
+                .query_map(params![tool], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?
+                .filter_map(|r| r.ok())
+                .filter_map(|(key, value_str)| {
+                    serde_json::from_str(&value_str)
+                        .ok()
+                        .map(|value| (key, value))
+                })
+                .collect();
+
+            Ok(entries)
+        })
+        .await
     }
 
     /// Clean up expired entries (garbage collection)
-    #[allow(dead_code)]
     pub async fn cleanup_expired(&self) -> Result<u64> {
-        let conn = self.conn.lock().await;
-        let rows = conn.execute(
-            "DELETE FROM memories WHERE expires_at IS NOT NULL AND expires_at <= datetime('now')",
-            [],
-        )?;
-        Ok(rows as u64)
+        self.write(|conn| {
+            let rows = conn.execute(
+                "DELETE FROM memories
+                 WHERE expires_at IS NOT NULL AND expires_at <= datetime('now')",
+                [],
+            )?;
+            Ok(rows as u64)
+        })
+        .await
+    }
+
+    /// Sweep expired entries now and then every `interval_secs` in the background
+    /// (0 = only sweep once, on startup)
+    pub fn spawn_cleanup(&self, interval_secs: u64) -> tokio::task::JoinHandle<()> {
+        let memory = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match memory.cleanup_expired().await {
+                    Ok(0) => {}
+                    Ok(n) => eprintln!("🧹 Removed {} expired memory entries", n),
+                    Err(e) => eprintln!("Failed to clean up expired memory entries: {}", e),
+                }
+                if interval_secs == 0 {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(interval_secs)).await;
+            }
+        })
     }
 
     /// Delete a key
     pub async fn delete(&self, tool: &str, key: &str) -> Result<bool> {
-        let conn = self.conn.lock().await;
-        let rows = conn.execute(
-            "DELETE FROM memories WHERE tool = ?1 AND key = ?2",
-            params![tool, key],
-        )?;
-        Ok(rows > 0)
+        self.write(|conn| {
+            let rows = conn.execute(
+                "DELETE FROM memories WHERE tool = ?1 AND key = ?2",
+                params![tool, key],
+            )?;
+            Ok(rows > 0)
+        })
+        .await
     }
 
     /// Clear all memory for a tool
     pub async fn clear(&self, tool: &str) -> Result<u64> {
-        let conn = self.conn.lock().await;
-        let rows = conn.execute("DELETE FROM memories WHERE tool = ?1", params![tool])?;
-        Ok(rows as u64)
+        self.write(|conn| {
+            let rows = conn.execute("DELETE FROM memories WHERE tool = ?1", params![tool])?;
+            Ok(rows as u64)
+        })
+        .await
+    }
+
+    /// Delete a tool's entries whose key starts with `prefix`
+    pub async fn delete_prefix(&self, tool: &str, prefix: &str) -> Result<u64> {
+        self.write(|conn| {
+            let rows = conn.execute(
+                "DELETE FROM memories WHERE tool = ?1 AND substr(key, 1, length(?2)) = ?2",
+                params![tool, prefix],
+            )?;
+            Ok(rows as u64)
+        })
+        .await
+    }
+
+    /// Move all entries from one tool namespace to another (used when renaming a tool).
+    /// Existing entries under `new_tool` with the same key are replaced.
+    /// Usage statistics follow the tool as well.
+    pub async fn rename_tool(&self, old_tool: &str, new_tool: &str) -> Result<u64> {
+        self.write(|conn| {
+            let rows = conn.execute(
+                "UPDATE OR REPLACE memories SET tool = ?2 WHERE tool = ?1",
+                params![old_tool, new_tool],
+            )?;
+            conn.execute(
+                "UPDATE OR REPLACE memories SET key = ?3 WHERE tool = ?1 AND key = ?2",
+                params![USAGE_NAMESPACE, old_tool, new_tool],
+            )?;
+            Ok(rows as u64)
+        })
+        .await
+    }
+
+    /// Copy all entries from one tool namespace to another (used when copying a tool)
+    pub async fn copy_tool(&self, source_tool: &str, new_tool: &str) -> Result<u64> {
+        self.write(|conn| {
+            let rows = conn.execute(
+                "INSERT OR REPLACE INTO memories
+                    (tool, key, value, created_at, updated_at, expires_at, embedding)
+                 SELECT ?2, key, value, created_at, updated_at, expires_at, embedding
+                 FROM memories WHERE tool = ?1",
+                params![source_tool, new_tool],
+            )?;
+            Ok(rows as u64)
+        })
+        .await
     }
 
     /// Clear all memory (all tools)
     #[allow(dead_code)]
     pub async fn clear_all(&self) -> Result<u64> {
-        let conn = self.conn.lock().await;
-        let rows = conn.execute("DELETE FROM memories", [])?;
-        Ok(rows as u64)
+        self.write(|conn| {
+            let rows = conn.execute("DELETE FROM memories", [])?;
+            Ok(rows as u64)
+        })
+        .await
     }
 
-    // ==================== Stats ====================
-
-    /// Get memory statistics
-    pub async fn stats(&self) -> Result<MemoryStats> {
-        let conn = self.conn.lock().await;
+    // ==================== Semantic Search ====================
 
-        let total_entries: i64 = conn
-            .query_row("SELECT COUNT(*) FROM memories", [], |row| row.get(0))
-            .unwrap_or(0);
+    /// Entries of a tool ranked by similarity to `query`, best first.
+    ///
+    /// With an embedder, entries are ranked by cosine similarity to the
+    /// query's embedding; entries stored before the embedder was configured
+    /// are embedded (and saved) on the way. Without one, entries are ranked
+    /// by the share of query terms they contain ([`SearchMode::FullText`]).
+    pub async fn semantic_search(
+        &self,
+        tool: &str,
+        query: &str,
+        top_k: usize,
+    ) -> Result<SearchResults> {
+        let Some(embedder) = self.embedder() else {
+            return self.full_text_search(tool, query, top_k).await;
+        };
+        let query_vector = embedder(query.to_string())
+            .await
+            .context("Failed to embed query")?;
+
+        let rows = self
+            .read(|conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT key, value, embedding FROM memories
+                     WHERE tool = ?1
+                     AND (expires_at IS NULL OR expires_at > datetime('now'))
+                     ORDER BY key",
+                )?;
+                let rows = stmt
+                    .query_map(params![tool], |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, Option<Vec<u8>>>(2)?,
+                        ))
+                    })?
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                Ok(rows)
+            })
+            .await?;
+
+        let mut hits = Vec::new();
+        let mut backfill = Vec::new();
+        for (key, raw, blob) in rows {
+            let Ok(value) = serde_json::from_str::<Value>(&raw) else {
+                continue;
+            };
+            let vector = match blob {
+                Some(blob) => decode_embedding(&blob),
+                None => {
+                    let vector = embedder(embedding_text(&value))
+                        .await
+                        .with_context(|| format!("Failed to embed '{}'", key))?;
+                    backfill.push((key.clone(), raw, encode_embedding(&vector)));
+                    vector
+                }
+            };
+            if let Some(score) = cosine_similarity(&query_vector, &vector) {
+                hits.push(SearchHit { key, value, score });
+            }
+        }
 
-        let total_tools: i64 = conn
-            .query_row("SELECT COUNT(DISTINCT tool) FROM memories", [], |row| {
-                row.get(0)
+        if !backfill.is_empty() {
+            self.write(|conn| {
+                for (key, raw, blob) in &backfill {
+                    // Skip entries rewritten while we were embedding
+                    conn.execute(
+                        "UPDATE memories SET embedding = ?4
+                         WHERE tool = ?1 AND key = ?2 AND value = ?3 AND embedding IS NULL",
+                        params![tool, key, raw, blob],
+                    )?;
+                }
+                Ok(())
             })
-            .unwrap_or(0);
+            .await?;
+        }
 
-        Ok(MemoryStats {
-            total_entries: total_entries as u64,
-            total_tools: total_tools as u64,
-            schema_version: SCHEMA_VERSION,
+        Ok(SearchResults {
+            mode: SearchMode::Semantic,
+            hits: rank(hits, top_k),
         })
     }
-}
 
-/// Memory statistics
-#[derive(Debug, Clone)]
-pub struct MemoryStats {
-    pub total_entries: u64,
-    pub total_tools: u64,
-    pub schema_version: i32,
-}
+    /// Rank entries by the share of (case-insensitive) query terms found in
+    /// their key or value
+    async fn full_text_search(
+        &self,
+        tool: &str,
+        query: &str,
+        top_k: usize,
+    ) -> Result<SearchResults> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let hits = self
+            .get_all(tool)
+            .await?
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let haystack = format!("{} {}", key, embedding_text(&value)).to_lowercase();
+                let matched = terms
+                    .iter()
+                    .filter(|t| haystack.contains(t.as_str()))
+                    .count();
+                (matched > 0).then(|| SearchHit {
+                    key,
+                    value,
+                    score: matched as f32 / terms.len() as f32,
+                })
+            })
+            .collect();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
+        Ok(SearchResults {
+            mode: SearchMode::FullText,
+            hits: rank(hits, top_k),
+        })
+    }
 
-    async fn create_test_memory() -> (Memory, TempDir) {
-        let temp_dir = TempDir::new().unwrap();
-        let memory = Memory::new(temp_dir.path()).await.unwrap();
-        (memory, temp_dir)
+    // ==================== Export / Import ====================
+
+    /// Snapshot live entries as `{tool: {key: {value, created_at, expires_at}}}`,
+    /// for one tool or all of them. Memoized results and embeddings are left
+    /// out; embeddings are recomputed on the next search.
+    pub async fn export(&self, tool: Option<&str>) -> Result<Value> {
+        self.read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT tool, key, value, created_at, expires_at FROM memories
+                 WHERE (?1 IS NULL OR tool = ?1) AND tool != ?2
+                 AND (expires_at IS NULL OR expires_at > datetime('now'))
+                 ORDER BY tool, key",
+            )?;
+            let rows = stmt.query_map(params![tool, RESULT_CACHE_NAMESPACE], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?;
+
+            let mut export = serde_json::Map::new();
+            for row in rows {
+                let (tool, key, raw, created_at, expires_at) = row?;
+                let entry = serde_json::json!({
+                    "value": serde_json::from_str::<Value>(&raw)?,
+                    "created_at": created_at,
+                    "expires_at": expires_at,
+                });
+                export
+                    .entry(tool)
+                    .or_insert_with(|| Value::Object(serde_json::Map::new()))
+                    .as_object_mut()
+                    .expect("tool entry is an object")
+                    .insert(key, entry);
+            }
+            Ok(Value::Object(export))
+        })
+        .await
     }
 
-    #[tokio::test]
-    async fn test_set_and_get() {
-        let (memory, _dir) = create_test_memory().await;
+    /// Restore entries from an [`export`](Self::export) document in one
+    /// transaction. `strategy` decides what happens when a live entry with the
+    /// same key exists; with [`MergeStrategy::Error`] nothing is written.
+    pub async fn import(&self, data: &Value, strategy: MergeStrategy) -> Result<ImportSummary> {
+        let tools = data
+            .as_object()
+            .context("Import data must be an object of {tool: {key: entry}}")?;
+
+        self.write(|tx| {
+            let mut summary = ImportSummary::default();
+
+            for (tool, entries) in tools {
+                let entries = entries
+                    .as_object()
+                    .with_context(|| format!("Entries of tool '{}' must be an object", tool))?;
+                for (key, entry) in entries {
+                    let value = entry
+                        .get("value")
+                        .with_context(|| format!("Entry '{}/{}' has no value", tool, key))?;
+                    let created_at = entry.get("created_at").and_then(Value::as_str);
+                    let expires_at = entry.get("expires_at").and_then(Value::as_str);
+
+                    let exists: bool = tx.query_row(
+                        "SELECT EXISTS(SELECT 1 FROM memories
+                     WHERE tool = ?1 AND key = ?2
+                     AND (expires_at IS NULL OR expires_at > datetime('now')))",
+                        params![tool, key],
+                        |row| row.get(0),
+                    )?;
+                    if exists {
+                        match strategy {
+                            MergeStrategy::Skip => {
+                                summary.skipped += 1;
+                                continue;
+                            }
+                            MergeStrategy::Error => {
+                                anyhow::bail!("Key '{}' already exists for tool '{}'", key, tool)
+                            }
+                            MergeStrategy::Overwrite => {}
+                        }
+                    }
+
+                    tx.execute(
+                        "INSERT INTO memories
+                            (tool, key, value, created_at, updated_at, expires_at)
+                         VALUES (?1, ?2, ?3, COALESCE(?4, datetime('now')), datetime('now'), ?5)
+                         ON CONFLICT(tool, key) DO UPDATE SET
+                            value = excluded.value,
+                            created_at = excluded.created_at,
+                            updated_at = datetime('now'),
+                            expires_at = excluded.expires_at,
+                            embedding = NULL",
+                        params![
+                            tool,
+                            key,
+                            serde_json::to_string(value)?,
+                            created_at,
+                            expires_at
+                        ],
+                    )?;
+                    summary.imported += 1;
+                }
+            }
+            Ok(summary)
+        })
+        .await
+    }
 
-        memory
-            .set("test_tool", "counter", serde_json::json!(42))
-            .await
-            .unwrap();
-        let value = memory.get("test_tool", "counter").await.unwrap();
+    // ==================== Usage Statistics ====================
 
-        assert_eq!(value, Some(serde_json::json!(42)));
+    /// Record one execution of a tool
+    pub async fn record_tool_call(
+        &self,
+        tool: &str,
+        duration_ms: u64,
+        success: bool,
+    ) -> Result<()> {
+        self.write(|conn| {
+            let existing: Option<String> = conn
+                .query_row(
+                    "SELECT value FROM memories WHERE tool = ?1 AND key = ?2",
+                    params![USAGE_NAMESPACE, tool],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let mut stats: ToolUsageStats = existing
+                .and_then(|v| serde_json::from_str(&v).ok())
+                .unwrap_or_default();
+
+            let now: String = conn.query_row("SELECT datetime('now')", [], |row| row.get(0))?;
+            stats.tool = tool.to_string();
+            stats.calls += 1;
+            if !success {
+                stats.failures += 1;
+            }
+            stats.total_duration_ms += duration_ms;
+            stats.last_called = Some(now);
+
+            conn.execute(
+                "INSERT INTO memories (tool, key, value, updated_at, expires_at)
+                 VALUES (?1, ?2, ?3, datetime('now'), NULL)
+                 ON CONFLICT(tool, key) DO UPDATE SET
+                    value = excluded.value,
+                    updated_at = datetime('now')",
+                params![USAGE_NAMESPACE, tool, serde_json::to_string(&stats)?],
+            )?;
+
+            Ok(())
+        })
+        .await
     }
 
-    #[tokio::test]
-    async fn test_get_nonexistent() {
-        let (memory, _dir) = create_test_memory().await;
-
-        let value = memory.get("test_tool", "nonexistent").await.unwrap();
-        assert_eq!(value, None);
+    /// Usage statistics for a single tool (None if it has never been called)
+    pub async fn tool_usage(&self, tool: &str) -> Result<Option<ToolUsageStats>> {
+        Ok(self
+            .get(USAGE_NAMESPACE, tool)
+            .await?
+            .and_then(|v| serde_json::from_value::<ToolUsageStats>(v).ok())
+            .map(|mut stats| {
+                stats.tool = tool.to_string();
+                stats
+            }))
     }
 
-    #[tokio::test]
-    async fn test_update() {
-        let (memory, _dir) = create_test_memory().await;
+    /// Usage statistics for every tool that has been called
+    pub async fn all_tool_usage(&self) -> Result<Vec<ToolUsageStats>> {
+        Ok(self
+            .get_all(USAGE_NAMESPACE)
+            .await?
+            .into_iter()
+            .filter_map(|(tool, value)| {
+                serde_json::from_value::<ToolUsageStats>(value)
+                    .ok()
+                    .map(|mut stats| {
+                        stats.tool = tool;
+                        stats
+                    })
+            })
+            .collect())
+    }
 
-        memory
-            .set("test_tool", "key", serde_json::json!(1))
-            .await
-            .unwrap();
-        memory
-            .set("test_tool", "key", serde_json::json!(2))
-            .await
-            .unwrap();
+    // ==================== Execution Audit Log ====================
 
-        let value = memory.get("test_tool", "key").await.unwrap();
-        assert_eq!(value, Some(serde_json::json!(2)));
+    /// Append one tool execution to the audit log
+    pub async fn record_execution(
+        &self,
+        tool: &str,
+        args_hash: &str,
+        success: bool,
+        duration_ms: u64,
+    ) -> Result<()> {
+        self.write(|conn| {
+            conn.execute(
+                "INSERT INTO executions (tool_name, args_hash, success, duration_ms)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![tool, args_hash, success, duration_ms as i64],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Most recent executions first, optionally filtered by tool and outcome
+    pub async fn executions(
+        &self,
+        tool: Option<&str>,
+        success: Option<bool>,
+        limit: usize,
+    ) -> Result<Vec<ExecutionRecord>> {
+        self.read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, tool_name, args_hash, success, duration_ms, executed_at
+                 FROM executions
+                 WHERE (?1 IS NULL OR tool_name = ?1) AND (?2 IS NULL OR success = ?2)
+                 ORDER BY id DESC
+                 LIMIT ?3",
+            )?;
+
+            let records = stmt
+                .query_map(params![tool, success, limit as i64], |row| {
+                    Ok(ExecutionRecord {
+                        id: row.get(0)?,
+                        tool_name: row.get(1)?,
+                        args_hash: row.get(2)?,
+                        success: row.get(3)?,
+                        duration_ms: row.get::<_, i64>(4)? as u64,
+                        executed_at: row.get(5)?,
+                    })
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+
+            Ok(records)
+        })
+        .await
+    }
+
+    // ==================== Result Cache ====================
+
+    /// A memoized result for this tool version and arguments, if still fresh
+    pub async fn cached_result(
+        &self,
+        tool: &str,
+        version: &str,
+        args: &Value,
+    ) -> Result<Option<Value>> {
+        if self.result_cache_ttl == 0 {
+            return Ok(None);
+        }
+        self.get(
+            RESULT_CACHE_NAMESPACE,
+            &result_cache_key(tool, version, args),
+        )
+        .await
+    }
+
+    /// Memoize a tool result for the configured TTL
+    pub async fn cache_result(
+        &self,
+        tool: &str,
+        version: &str,
+        args: &Value,
+        result: &Value,
+    ) -> Result<()> {
+        if self.result_cache_ttl == 0 {
+            return Ok(());
+        }
+        self.set_with_ttl(
+            RESULT_CACHE_NAMESPACE,
+            &result_cache_key(tool, version, args),
+            result.clone(),
+            Some(self.result_cache_ttl),
+        )
+        .await
+    }
+
+    // ==================== Stats ====================
+
+    /// Get memory statistics
+    pub async fn stats(&self) -> Result<MemoryStats> {
+        self.read(|conn| {
+            let total_entries: i64 = conn
+                .query_row("SELECT COUNT(*) FROM memories", [], |row| row.get(0))
+                .unwrap_or(0);
+
+            let total_tools: i64 = conn
+                .query_row("SELECT COUNT(DISTINCT tool) FROM memories", [], |row| {
+                    row.get(0)
+                })
+                .unwrap_or(0);
+
+            let expiring_soon: i64 = conn
+                .query_row(
+                    &format!(
+                        "SELECT COUNT(*) FROM memories
+                         WHERE expires_at > datetime('now')
+                         AND expires_at <= datetime('now', '+{} seconds')",
+                        EXPIRING_SOON_SECS
+                    ),
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap_or(0);
+
+            Ok(MemoryStats {
+                total_entries: total_entries as u64,
+                total_tools: total_tools as u64,
+                expiring_soon: expiring_soon as u64,
+                schema_version: SCHEMA_VERSION,
+            })
+        })
+        .await
+    }
+}
+
+/// Escape `%`, `_` and the escape character itself for a `LIKE ... ESCAPE '\\'` pattern
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Open the writer connection to the database at `db_path`
+fn open_writer(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open(db_path)
+        .with_context(|| format!("Failed to open memory database at {:?}", db_path))?;
+
+    // WAL lets the read-only connections query while a write is in progress
+    conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;")?;
+    Ok(conn)
+}
+
+/// Whether an error means the connection lost its database file (it can't
+/// be opened or read, e.g. after being replaced or unmounted), so that
+/// reopening it may help. A busy or locked database, a full disk or a
+/// read-only file stay that way after a reconnect, so they are reported.
+fn is_connection_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<rusqlite::Error>(),
+            Some(rusqlite::Error::SqliteFailure(e, _)) if matches!(
+                e.code,
+                ErrorCode::SystemIoFailure | ErrorCode::CannotOpen | ErrorCode::NotADatabase
+            )
+        )
+    })
+}
+
+/// A fixed set of read-only connections, handed out one caller at a time
+struct ReaderPool {
+    idle: std::sync::Mutex<Vec<Connection>>,
+    available: Semaphore,
+    db_path: PathBuf,
+}
+
+impl ReaderPool {
+    /// Open `size` read-only connections to the database at `db_path`
+    fn open(db_path: &Path, size: usize) -> Result<Self> {
+        let idle = (0..size)
+            .map(|_| open_reader(db_path))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            idle: std::sync::Mutex::new(idle),
+            available: Semaphore::new(size),
+            db_path: db_path.to_path_buf(),
+        })
+    }
+
+    async fn acquire(&self) -> Result<PooledReader<'_>> {
+        let permit = self.available.acquire().await?;
+        let conn = self
+            .idle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop()
+            .context("Memory read pool is empty")?;
+        Ok(PooledReader {
+            pool: self,
+            conn: Some(conn),
+            _permit: permit,
+        })
+    }
+}
+
+/// Write a consistent copy of the database at `db_path` to `dest` (which
+/// must not exist), including changes still in its write-ahead log, while
+/// the server may be writing to it
+pub fn snapshot_database(db_path: &Path, dest: &Path) -> Result<()> {
+    open_reader(db_path)?
+        .execute("VACUUM INTO ?1", params![dest.to_string_lossy()])
+        .with_context(|| format!("Failed to snapshot the memory database at {:?}", db_path))?;
+    Ok(())
+}
+
+/// Open a read-only connection to the database at `db_path`
+fn open_reader(db_path: &Path) -> Result<Connection> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY
+        | OpenFlags::SQLITE_OPEN_NO_MUTEX
+        | OpenFlags::SQLITE_OPEN_URI;
+    Connection::open_with_flags(db_path, flags).with_context(|| {
+        format!(
+            "Failed to open memory database at {:?} for reading",
+            db_path
+        )
+    })
+}
+
+/// A connection borrowed from the [`ReaderPool`], returned on drop
+struct PooledReader<'a> {
+    pool: &'a ReaderPool,
+    conn: Option<Connection>,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl PooledReader<'_> {
+    /// Replace the borrowed connection with a freshly opened one
+    fn reopen(&mut self) -> Result<()> {
+        self.conn = Some(open_reader(&self.pool.db_path)?);
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for PooledReader<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection is held until drop")
+    }
+}
+
+impl Drop for PooledReader<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool
+                .idle
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(conn);
+        }
+    }
+}
+
+/// Check a value about to be written against the tool's schema entry, if it
+/// has one. A new schema must itself be a valid JSON Schema.
+fn check_schema(conn: &Connection, tool: &str, key: &str, value: &Value) -> Result<()> {
+    if key == SCHEMA_KEY {
+        return jsonschema::validator_for(value)
+            .map(|_| ())
+            .map_err(|e| anyhow::anyhow!("Invalid memory schema for '{}': {}", tool, e));
+    }
+
+    let schema: Option<String> = conn
+        .query_row(
+            "SELECT value FROM memories
+             WHERE tool = ?1 AND key = ?2
+             AND (expires_at IS NULL OR expires_at > datetime('now'))",
+            params![tool, SCHEMA_KEY],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(schema) = schema else {
+        return Ok(());
+    };
+    let schema: Value = serde_json::from_str(&schema)?;
+    let validator = jsonschema::validator_for(&schema)
+        .map_err(|e| anyhow::anyhow!("Stored memory schema for '{}' is invalid: {}", tool, e))?;
+
+    let problems: Vec<String> = validator
+        .iter_errors(value)
+        .map(|e| match e.instance_path.to_string().as_str() {
+            "" => e.to_string(),
+            path => format!("{} (at {})", e, path),
+        })
+        .collect();
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        anyhow::bail!(
+            "Value for '{}' doesn't match the memory schema of '{}': {}",
+            key,
+            tool,
+            problems.join("; ")
+        )
+    }
+}
+
+/// Insert or replace an entry, setting or clearing its expiry. The
+/// embedding is replaced too, so a changed value never keeps a stale vector.
+fn upsert(
+    conn: &Connection,
+    tool: &str,
+    key: &str,
+    value: &Value,
+    ttl_secs: Option<u64>,
+    embedding: Option<&[f32]>,
+) -> Result<()> {
+    let json_str = serde_json::to_string(value)?;
+    let embedding = embedding.map(encode_embedding);
+
+    // Calculate expiration time if TTL provided
+    let expires_at = ttl_secs
+        .filter(|&t| t > 0)
+        .map(|t| format!("+{} seconds", t));
+
+    match expires_at {
+        Some(offset) => {
+            conn.execute(
+                &format!(
+                    "INSERT INTO memories (tool, key, value, updated_at, expires_at, embedding)
+                     VALUES (?1, ?2, ?3, datetime('now'), datetime('now', '{}'), ?4)
+                     ON CONFLICT(tool, key) DO UPDATE SET
+                        value = excluded.value,
+                        updated_at = datetime('now'),
+                        expires_at = datetime('now', '{}'),
+                        embedding = excluded.embedding",
+                    offset, offset
+                ),
+                params![tool, key, json_str, embedding],
+            )?;
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO memories (tool, key, value, updated_at, expires_at, embedding)
+                 VALUES (?1, ?2, ?3, datetime('now'), NULL, ?4)
+                 ON CONFLICT(tool, key) DO UPDATE SET
+                    value = excluded.value,
+                    updated_at = datetime('now'),
+                    expires_at = NULL,
+                    embedding = excluded.embedding",
+                params![tool, key, json_str, embedding],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// One page of [`Memory::list`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MemoryPage {
+    pub entries: Vec<ListedEntry>,
+    /// Offset of the next page, if there is one
+    pub next_offset: Option<usize>,
+}
+
+/// A key in a memory listing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ListedEntry {
+    pub key: String,
+    /// Size of the stored (JSON) value in bytes
+    pub size_bytes: usize,
+    /// The value, if requested; a string preview when `truncated`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl ListedEntry {
+    fn new(key: String, raw: &str, include_value: bool) -> Self {
+        let size_bytes = raw.len();
+        let truncated = include_value && size_bytes > LIST_VALUE_PREVIEW_BYTES;
+        let value = if !include_value {
+            None
+        } else if truncated {
+            let mut end = LIST_VALUE_PREVIEW_BYTES;
+            while !raw.is_char_boundary(end) {
+                end -= 1;
+            }
+            Some(Value::String(format!("{}…", &raw[..end])))
+        } else {
+            serde_json::from_str(raw).ok()
+        };
+        Self {
+            key,
+            size_bytes,
+            value,
+            truncated,
+        }
+    }
+}
+
+/// One entry of a bulk write
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkEntry {
+    pub key: String,
+    pub value: Value,
+    /// Expire after this many seconds (None/0 = never)
+    pub ttl_secs: Option<u64>,
+}
+
+/// How a bulk write treats keys that don't exist yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkMode {
+    /// Create or overwrite every entry
+    Store,
+    /// Only overwrite existing keys; a missing key fails the whole batch
+    Update,
+}
+
+/// Outcome of one entry in a bulk write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkStatus {
+    Created,
+    Updated,
+    /// Overwritten by a later entry with the same key in the same batch
+    Superseded,
+}
+
+impl BulkStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Superseded => "superseded",
+        }
+    }
+}
+
+/// What [`Memory::import`] does with keys that already exist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Replace the existing entry
+    Overwrite,
+    /// Keep the existing entry
+    Skip,
+    /// Abort the whole import
+    Error,
+}
+
+impl MergeStrategy {
+    /// Parse "overwrite", "skip" or "error"
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "overwrite" => Ok(Self::Overwrite),
+            "skip" => Ok(Self::Skip),
+            "error" => Ok(Self::Error),
+            other => anyhow::bail!(
+                "Invalid merge strategy '{}': use overwrite, skip or error",
+                other
+            ),
+        }
+    }
+}
+
+/// Outcome of [`Memory::import`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub imported: u64,
+    /// Existing keys kept by [`MergeStrategy::Skip`]
+    pub skipped: u64,
+}
+
+/// How [`Memory::semantic_search`] ranked its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    /// Cosine similarity of embeddings
+    Semantic,
+    /// No embedder configured: share of query terms matched
+    FullText,
+}
+
+/// Result of [`Memory::semantic_search`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchResults {
+    pub mode: SearchMode,
+    pub hits: Vec<SearchHit>,
+}
+
+/// One entry matched by a search
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub key: String,
+    pub value: Value,
+    /// Cosine similarity (-1..1) or share of query terms matched (0..1)
+    pub score: f32,
+}
+
+/// Best `top_k` hits, highest score first (ties by key)
+fn rank(mut hits: Vec<SearchHit>, top_k: usize) -> Vec<SearchHit> {
+    hits.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.key.cmp(&b.key))
+    });
+    hits.truncate(top_k);
+    hits
+}
+
+/// Text that represents a value for embedding: strings as-is, anything else as JSON
+fn embedding_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Serialize a vector as little-endian f32s
+fn encode_embedding(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode_embedding(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Cosine similarity, or None if the vectors differ in length or either is zero
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some(dot / (norm_a * norm_b))
+}
+
+/// Parse an embedder's output: a JSON array of numbers, an object with an
+/// `embedding` array, or text with the array somewhere inside it
+pub fn parse_embedding(output: &str) -> Result<Vec<f32>> {
+    let parsed: Option<Value> = serde_json::from_str(output.trim()).ok().or_else(|| {
+        let start = output.find('[')?;
+        let end = output.rfind(']')?;
+        serde_json::from_str(output.get(start..=end)?).ok()
+    });
+    let array = match parsed {
+        Some(Value::Array(items)) => items,
+        Some(Value::Object(mut map)) => match map.remove("embedding") {
+            Some(Value::Array(items)) => items,
+            _ => anyhow::bail!("Embedding output has no 'embedding' array"),
+        },
+        _ => anyhow::bail!("Embedding output is not a JSON array of numbers"),
+    };
+
+    let vector = array
+        .iter()
+        .map(|v| v.as_f64().map(|x| x as f32))
+        .collect::<Option<Vec<f32>>>()
+        .context("Embedding contains a non-numeric element")?;
+    if vector.is_empty() {
+        anyhow::bail!("Embedding is empty");
+    }
+    Ok(vector)
+}
+
+/// Embedder that runs a shell command with the text on stdin and reads the
+/// vector (see [`parse_embedding`]) from stdout
+pub fn command_embedder(command: String) -> Embedder {
+    Arc::new(move |text| {
+        let command = command.clone();
+        Box::pin(async move {
+            use tokio::io::AsyncWriteExt;
+
+            let mut child = tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(&command)
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .with_context(|| format!("Failed to run embedding command '{}'", command))?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(text.as_bytes()).await?;
+            }
+
+            let output = child.wait_with_output().await?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "Embedding command failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            parse_embedding(&String::from_utf8_lossy(&output.stdout))
+        })
+    })
+}
+
+/// Embedder that asks the MCP client's model for a
+/// [`SAMPLING_EMBEDDING_DIMS`]-dimensional vector. Coarser than a real
+/// embedding model, but needs no local setup.
+pub fn sampling_embedder(sampling: crate::runtime::SamplingHandler) -> Embedder {
+    Arc::new(move |text| {
+        let sampling = sampling.clone();
+        Box::pin(async move {
+            let params = serde_json::json!({
+                "messages": [{"role": "user", "content": {"type": "text", "text": text}}],
+                "systemPrompt": format!(
+                    "Return a semantic embedding of the user's text as a JSON array of exactly {} numbers between -1 and 1. Texts with similar meaning must get similar arrays. Reply with the array only.",
+                    SAMPLING_EMBEDDING_DIMS
+                ),
+                "temperature": 0.0,
+                "maxTokens": 512,
+            });
+            let result = sampling(params).await?;
+            if let Some(error) = result.get("error") {
+                anyhow::bail!("Sampling failed: {}", error);
+            }
+            let reply = result
+                .pointer("/content/text")
+                .and_then(Value::as_str)
+                .context("Sampling returned no text")?;
+
+            let vector = parse_embedding(reply)?;
+            if vector.len() != SAMPLING_EMBEDDING_DIMS {
+                anyhow::bail!(
+                    "Expected {} dimensions from sampling, got {}",
+                    SAMPLING_EMBEDDING_DIMS,
+                    vector.len()
+                );
+            }
+            Ok(vector)
+        })
+    })
+}
+
+/// Cache key for a tool call: `tool@version:` followed by the arguments hash.
+/// Including the version means updating a tool never serves stale results.
+pub fn result_cache_key(tool: &str, version: &str, args: &Value) -> String {
+    format!("{}@{}:{}", tool, version, args_hash(args))
+}
+
+/// Key of a cached pipeline step output: the pipeline, then the tool and its
+/// arguments as in [`result_cache_key`]
+pub fn pipeline_cache_key(pipeline: &str, tool: &str, version: &str, args: &Value) -> String {
+    format!("{}/{}", pipeline, result_cache_key(tool, version, args))
+}
+
+/// Key of a pipeline debug checkpoint: `<run_id>/step-001` for the first
+/// step, `<run_id>/on_error-001` for the first on_error step. Zero-padded so
+/// listing the run's keys returns them in order.
+pub fn pipeline_debug_key(run_id: &str, section: &str, step_index: usize) -> String {
+    format!("{}/{}-{:03}", run_id, section, step_index + 1)
+}
+
+/// SHA-256 of the arguments with object keys sorted, so key order doesn't matter
+pub fn args_hash(args: &Value) -> String {
+    use sha2::{Digest, Sha256};
+
+    fn canonicalize(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let sorted: std::collections::BTreeMap<&String, Value> =
+                    map.iter().map(|(k, v)| (k, canonicalize(v))).collect();
+                serde_json::to_value(sorted).unwrap_or(Value::Null)
+            }
+            Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+            other => other.clone(),
+        }
+    }
+
+    let canonical = canonicalize(args).to_string();
+    Sha256::digest(canonical.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// One entry of the execution audit log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    pub id: i64,
+    pub tool_name: String,
+    /// SHA-256 of the canonicalized arguments (see [`args_hash`])
+    pub args_hash: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    /// UTC timestamp ("YYYY-MM-DD HH:MM:SS")
+    pub executed_at: String,
+}
+
+/// Per-tool usage statistics
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolUsageStats {
+    #[serde(default)]
+    pub tool: String,
+    #[serde(default)]
+    pub calls: u64,
+    #[serde(default)]
+    pub failures: u64,
+    #[serde(default)]
+    pub total_duration_ms: u64,
+    /// UTC timestamp of the last call ("YYYY-MM-DD HH:MM:SS")
+    #[serde(default)]
+    pub last_called: Option<String>,
+}
+
+impl ToolUsageStats {
+    /// Average duration per call in milliseconds
+    pub fn avg_duration_ms(&self) -> u64 {
+        self.total_duration_ms.checked_div(self.calls).unwrap_or(0)
+    }
+}
+
+/// Memory statistics
+#[derive(Debug, Clone)]
+pub struct MemoryStats {
+    pub total_entries: u64,
+    pub total_tools: u64,
+    /// Live entries that expire within [`EXPIRING_SOON_SECS`]
+    pub expiring_soon: u64,
+    pub schema_version: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn create_test_memory() -> (Memory, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let memory = Memory::new(temp_dir.path()).await.unwrap();
+        (memory, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_set_and_get() {
+        let (memory, _dir) = create_test_memory().await;
+
+        memory
+            .set("test_tool", "counter", serde_json::json!(42))
+            .await
+            .unwrap();
+        let value = memory.get("test_tool", "counter").await.unwrap();
+
+        assert_eq!(value, Some(serde_json::json!(42)));
+    }
+
+    #[tokio::test]
+    async fn test_get_nonexistent() {
+        let (memory, _dir) = create_test_memory().await;
+
+        let value = memory.get("test_tool", "nonexistent").await.unwrap();
+        assert_eq!(value, None);
+    }
+
+    #[tokio::test]
+    async fn test_update() {
+        let (memory, _dir) = create_test_memory().await;
+
+        memory
+            .set("test_tool", "key", serde_json::json!(1))
+            .await
+            .unwrap();
+        memory
+            .set("test_tool", "key", serde_json::json!(2))
+            .await
+            .unwrap();
+
+        let value = memory.get("test_tool", "key").await.unwrap();
+        assert_eq!(value, Some(serde_json::json!(2)));
+    }
+
+    #[tokio::test]
+    async fn test_exists() {
+        let (memory, _dir) = create_test_memory().await;
+        memory
+            .set("tool1", "present", serde_json::json!(null))
+            .await
+            .unwrap();
+        memory
+            .set_with_ttl("tool1", "expired", serde_json::json!(1), Some(1))
+            .await
+            .unwrap();
+
+        assert!(memory.exists("tool1", "present").await.unwrap());
+        assert!(!memory.exists("tool1", "missing").await.unwrap());
+        assert!(!memory.exists("tool2", "present").await.unwrap());
+
+        tokio::time::sleep(std::time::Duration::from_millis(2100)).await;
+        assert!(!memory.exists("tool1", "expired").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_schema_validates_writes() {
+        let (memory, _dir) = create_test_memory().await;
+        // Without a schema anything goes
+        memory
+            .set("config", "loose", serde_json::json!("anything"))
+            .await
+            .unwrap();
+
+        let err = memory
+            .set("config", SCHEMA_KEY, serde_json::json!({"type": "nope"}))
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid memory schema for 'config'"));
+
+        memory
+            .set(
+                "config",
+                SCHEMA_KEY,
+                serde_json::json!({
+                    "type": "object",
+                    "properties": {"retries": {"type": "integer"}},
+                    "required": ["retries"]
+                }),
+            )
+            .await
+            .unwrap();
+        memory
+            .set("config", "good", serde_json::json!({"retries": 3}))
+            .await
+            .unwrap();
+
+        let err = memory
+            .set("config", "bad", serde_json::json!({"retries": "three"}))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with("Value for 'bad' doesn't match the memory schema of 'config'"),
+            "{}",
+            err
+        );
+        assert!(err.contains("/retries"), "{}", err);
+        assert_eq!(memory.get("config", "bad").await.unwrap(), None);
+
+        let entries = [BulkEntry {
+            key: "bulk".to_string(),
+            value: serde_json::json!({}),
+            ttl_secs: None,
+        }];
+        let err = memory
+            .set_many("config", &entries, BulkMode::Store)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("\"retries\" is a required property"));
+        assert!(memory.increment("config", "count", 1).await.is_err());
+
+        // Other tools are unaffected, and removing the schema lifts it
+        memory
+            .set("other", "bad", serde_json::json!("text"))
+            .await
+            .unwrap();
+        memory.delete("config", SCHEMA_KEY).await.unwrap();
+        memory
+            .set("config", "bad", serde_json::json!("text"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_values() {
+        let (memory, _dir) = create_test_memory().await;
+        memory
+            .set(
+                "users",
+                "u1",
+                serde_json::json!({"name": "Ada", "role": "admin"}),
+            )
+            .await
+            .unwrap();
+        memory
+            .set(
+                "users",
+                "u2",
+                serde_json::json!({"name": "Bob", "role": "dev"}),
+            )
+            .await
+            .unwrap();
+        memory
+            .set("users", "u3", serde_json::json!("100% ada_fan"))
+            .await
+            .unwrap();
+        memory
+            .set("other", "u1", serde_json::json!({"name": "Ada"}))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            memory.query_values("users", "ada", None).await.unwrap(),
+            vec!["u1", "u3"]
+        );
+        assert_eq!(
+            memory
+                .query_values("users", "\"role\":\"dev\"", None)
+                .await
+                .unwrap(),
+            vec!["u2"]
+        );
+        // LIKE wildcards in the needle match literally
+        assert_eq!(
+            memory.query_values("users", "0% ada_", None).await.unwrap(),
+            vec!["u3"]
+        );
+        assert!(memory
+            .query_values("users", "a_m", None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            memory.query_values("users", "a", Some(1)).await.unwrap(),
+            vec!["u1"]
+        );
     }
 
     #[tokio::test]
@@ -402,144 +2052,571 @@ mod tests {
             .set("test_tool", "key", serde_json::json!(1))
             .await
             .unwrap();
-        let deleted = memory.delete("test_tool", "key").await.unwrap();
-        assert!(deleted);
-
-        let value = memory.get("test_tool", "key").await.unwrap();
-        assert_eq!(value, None);
+        let deleted = memory.delete("test_tool", "key").await.unwrap();
+        assert!(deleted);
+
+        let value = memory.get("test_tool", "key").await.unwrap();
+        assert_eq!(value, None);
+    }
+
+    #[tokio::test]
+    async fn test_clear() {
+        let (memory, _dir) = create_test_memory().await;
+
+        memory
+            .set("test_tool", "key1", serde_json::json!(1))
+            .await
+            .unwrap();
+        memory
+            .set("test_tool", "key2", serde_json::json!(2))
+            .await
+            .unwrap();
+        memory
+            .set("other_tool", "key1", serde_json::json!(3))
+            .await
+            .unwrap();
+
+        let cleared = memory.clear("test_tool").await.unwrap();
+        assert_eq!(cleared, 2);
+
+        let keys = memory.list_keys("test_tool").await.unwrap();
+        assert!(keys.is_empty());
+
+        // Other tool's data should be intact
+        let value = memory.get("other_tool", "key1").await.unwrap();
+        assert_eq!(value, Some(serde_json::json!(3)));
+    }
+
+    #[tokio::test]
+    async fn test_complex_values() {
+        let (memory, _dir) = create_test_memory().await;
+
+        let complex = serde_json::json!({
+            "name": "Test",
+            "items": [1, 2, 3],
+            "nested": {
+                "deep": true
+            }
+        });
+
+        memory
+            .set("test_tool", "complex", complex.clone())
+            .await
+            .unwrap();
+        let value = memory.get("test_tool", "complex").await.unwrap();
+
+        assert_eq!(value, Some(complex));
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let (memory, _dir) = create_test_memory().await;
+
+        memory
+            .set("tool1", "key1", serde_json::json!(1))
+            .await
+            .unwrap();
+        memory
+            .set("tool1", "key2", serde_json::json!(2))
+            .await
+            .unwrap();
+        memory
+            .set("tool2", "key1", serde_json::json!(3))
+            .await
+            .unwrap();
+
+        let stats = memory.stats().await.unwrap();
+        assert_eq!(stats.total_entries, 3);
+        assert_eq!(stats.total_tools, 2);
+        assert_eq!(stats.schema_version, SCHEMA_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_isolation_between_tools() {
+        let (memory, _dir) = create_test_memory().await;
+
+        memory
+            .set("tool_a", "shared_key", serde_json::json!("A"))
+            .await
+            .unwrap();
+        memory
+            .set("tool_b", "shared_key", serde_json::json!("B"))
+            .await
+            .unwrap();
+
+        let value_a = memory.get("tool_a", "shared_key").await.unwrap();
+        let value_b = memory.get("tool_b", "shared_key").await.unwrap();
+
+        assert_eq!(value_a, Some(serde_json::json!("A")));
+        assert_eq!(value_b, Some(serde_json::json!("B")));
+    }
+
+    #[tokio::test]
+    async fn test_get_all() {
+        let (memory, _dir) = create_test_memory().await;
+
+        memory
+            .set("test_tool", "a", serde_json::json!(1))
+            .await
+            .unwrap();
+        memory
+            .set("test_tool", "b", serde_json::json!(2))
+            .await
+            .unwrap();
+
+        let all = memory.get_all("test_tool").await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0], ("a".to_string(), serde_json::json!(1)));
+        assert_eq!(all[1], ("b".to_string(), serde_json::json!(2)));
+    }
+
+    #[tokio::test]
+    async fn test_rename_tool_moves_entries() {
+        let (memory, _dir) = create_test_memory().await;
+
+        memory
+            .set("old_tool", "a", serde_json::json!(1))
+            .await
+            .unwrap();
+        memory
+            .set("old_tool", "b", serde_json::json!(2))
+            .await
+            .unwrap();
+        memory
+            .set("new_tool", "a", serde_json::json!("stale"))
+            .await
+            .unwrap();
+
+        let moved = memory.rename_tool("old_tool", "new_tool").await.unwrap();
+        assert_eq!(moved, 2);
+
+        assert!(memory.list_keys("old_tool").await.unwrap().is_empty());
+        assert_eq!(
+            memory.get("new_tool", "a").await.unwrap(),
+            Some(serde_json::json!(1))
+        );
+        assert_eq!(memory.list_keys("new_tool").await.unwrap(), vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_copy_tool_keeps_source() {
+        let (memory, _dir) = create_test_memory().await;
+
+        memory
+            .set("source", "key", serde_json::json!("value"))
+            .await
+            .unwrap();
+
+        let copied = memory.copy_tool("source", "copy").await.unwrap();
+        assert_eq!(copied, 1);
+        assert_eq!(
+            memory.get("source", "key").await.unwrap(),
+            Some(serde_json::json!("value"))
+        );
+        assert_eq!(
+            memory.get("copy", "key").await.unwrap(),
+            Some(serde_json::json!("value"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_tool_call() {
+        let (memory, _dir) = create_test_memory().await;
+
+        assert!(memory.tool_usage("counted").await.unwrap().is_none());
+
+        memory.record_tool_call("counted", 10, true).await.unwrap();
+        memory.record_tool_call("counted", 30, false).await.unwrap();
+        memory.record_tool_call("other", 5, true).await.unwrap();
+
+        let stats = memory.tool_usage("counted").await.unwrap().unwrap();
+        assert_eq!(stats.tool, "counted");
+        assert_eq!(stats.calls, 2);
+        assert_eq!(stats.failures, 1);
+        assert_eq!(stats.total_duration_ms, 40);
+        assert_eq!(stats.avg_duration_ms(), 20);
+        assert!(stats.last_called.is_some());
+
+        let all = memory.all_tool_usage().await.unwrap();
+        assert_eq!(all.len(), 2);
+
+        // Usage follows a renamed tool
+        memory.rename_tool("counted", "renamed").await.unwrap();
+        assert!(memory.tool_usage("counted").await.unwrap().is_none());
+        assert_eq!(
+            memory.tool_usage("renamed").await.unwrap().unwrap().calls,
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execution_audit_log() {
+        let (memory, _dir) = create_test_memory().await;
+
+        let hash = args_hash(&serde_json::json!({"a": 1, "b": 2}));
+        assert_eq!(hash, args_hash(&serde_json::json!({"b": 2, "a": 1})));
+
+        memory
+            .record_execution("alpha", &hash, true, 12)
+            .await
+            .unwrap();
+        memory
+            .record_execution("beta", &hash, false, 3)
+            .await
+            .unwrap();
+        memory
+            .record_execution("alpha", &hash, false, 7)
+            .await
+            .unwrap();
+
+        let all = memory.executions(None, None, 10).await.unwrap();
+        assert_eq!(all.len(), 3);
+        // Newest first
+        assert_eq!(all[0].tool_name, "alpha");
+        assert_eq!(all[0].duration_ms, 7);
+        assert_eq!(all[0].args_hash, hash);
+
+        let alpha = memory.executions(Some("alpha"), None, 10).await.unwrap();
+        assert_eq!(alpha.len(), 2);
+        let failed = memory.executions(None, Some(false), 10).await.unwrap();
+        assert_eq!(
+            failed
+                .iter()
+                .map(|e| e.tool_name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["alpha", "beta"]
+        );
+        let succeeded_alpha = memory
+            .executions(Some("alpha"), Some(true), 10)
+            .await
+            .unwrap();
+        assert_eq!(succeeded_alpha.len(), 1);
+        assert!(succeeded_alpha[0].success);
+
+        assert_eq!(memory.executions(None, None, 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_result_cache() {
+        let (memory, _dir) = create_test_memory().await;
+        let args = serde_json::json!({"a": 1, "b": {"y": 2, "x": 3}});
+        let reordered = serde_json::json!({"b": {"x": 3, "y": 2}, "a": 1});
+        let result = serde_json::json!({"answer": 42});
+
+        assert!(memory
+            .cached_result("calc", "1.0.0", &args)
+            .await
+            .unwrap()
+            .is_none());
+        memory
+            .cache_result("calc", "1.0.0", &args, &result)
+            .await
+            .unwrap();
+
+        // Key order doesn't matter, but the version and arguments do
+        assert_eq!(
+            memory
+                .cached_result("calc", "1.0.0", &reordered)
+                .await
+                .unwrap(),
+            Some(result)
+        );
+        assert!(memory
+            .cached_result("calc", "1.0.1", &args)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(memory
+            .cached_result("calc", "1.0.0", &serde_json::json!({"a": 2}))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_result_cache_disabled() {
+        let (memory, _dir) = create_test_memory().await;
+        let memory = memory.with_result_cache_ttl(0);
+        let args = serde_json::json!({});
+
+        memory
+            .cache_result("calc", "1.0.0", &args, &serde_json::json!(1))
+            .await
+            .unwrap();
+        assert!(memory
+            .cached_result("calc", "1.0.0", &args)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ttl_expiration() {
+        let (memory, _dir) = create_test_memory().await;
+
+        // Set with 1 second TTL
+        memory
+            .set_with_ttl("test_tool", "temp", serde_json::json!("expires"), Some(1))
+            .await
+            .unwrap();
+
+        // Should exist immediately
+        let value = memory.get("test_tool", "temp").await.unwrap();
+        assert!(value.is_some());
+
+        // Wait for expiration
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+
+        // Should be expired now, and gone from listings
+        let value = memory.get("test_tool", "temp").await.unwrap();
+        assert!(value.is_none());
+        assert!(memory.list_keys("test_tool").await.unwrap().is_empty());
+        assert!(memory.get_all("test_tool").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_prefix_and_paging() {
+        let (memory, _dir) = create_test_memory().await;
+        for i in 0..20 {
+            memory
+                .set("t", &format!("user:{:02}", i), serde_json::json!(i))
+                .await
+                .unwrap();
+        }
+        for i in 0..10 {
+            memory
+                .set("t", &format!("session:{:02}", i), serde_json::json!(i))
+                .await
+                .unwrap();
+        }
+        // LIKE wildcards in the prefix are literal
+        memory
+            .set("t", "user_x", serde_json::json!(0))
+            .await
+            .unwrap();
+
+        let all = memory.list("t", None, None, 0, false).await.unwrap();
+        assert_eq!(all.entries.len(), 31);
+        assert_eq!(all.next_offset, None);
+
+        let sessions = memory
+            .list("t", Some("session:"), None, 0, false)
+            .await
+            .unwrap();
+        assert_eq!(sessions.entries.len(), 10);
+        assert!(sessions.entries.iter().all(|e| e.value.is_none()));
+
+        // Page through the 20 user keys, 8 at a time
+        let mut keys = Vec::new();
+        let mut offset = 0;
+        let mut pages = 0;
+        loop {
+            let page = memory
+                .list("t", Some("user:"), Some(8), offset, true)
+                .await
+                .unwrap();
+            pages += 1;
+            keys.extend(page.entries.iter().map(|e| e.key.clone()));
+            match page.next_offset {
+                Some(next) => offset = next,
+                None => break,
+            }
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(keys.len(), 20);
+        assert_eq!(keys[0], "user:00");
+        assert_eq!(keys[19], "user:19");
+
+        let user_x = memory
+            .list("t", Some("user_"), None, 0, false)
+            .await
+            .unwrap();
+        assert_eq!(user_x.entries.len(), 1);
     }
 
     #[tokio::test]
-    async fn test_clear() {
+    async fn test_list_truncates_large_values() {
         let (memory, _dir) = create_test_memory().await;
-
-        memory
-            .set("test_tool", "key1", serde_json::json!(1))
-            .await
-            .unwrap();
+        let big = "x".repeat(1000);
         memory
-            .set("test_tool", "key2", serde_json::json!(2))
+            .set("t", "big", serde_json::json!(big))
             .await
             .unwrap();
         memory
-            .set("other_tool", "key1", serde_json::json!(3))
+            .set("t", "small", serde_json::json!({"a": 1}))
             .await
             .unwrap();
 
-        let cleared = memory.clear("test_tool").await.unwrap();
-        assert_eq!(cleared, 2);
+        let page = memory.list("t", None, None, 0, true).await.unwrap();
+        let big_entry = &page.entries[0];
+        assert_eq!(big_entry.size_bytes, 1002);
+        assert!(big_entry.truncated);
+        let preview = big_entry.value.as_ref().unwrap().as_str().unwrap();
+        assert!(preview.len() < 300 && preview.ends_with('…'));
 
-        let keys = memory.list_keys("test_tool").await.unwrap();
-        assert!(keys.is_empty());
+        let small = &page.entries[1];
+        assert!(!small.truncated);
+        assert_eq!(small.value, Some(serde_json::json!({"a": 1})));
+    }
 
-        // Other tool's data should be intact
-        let value = memory.get("other_tool", "key1").await.unwrap();
-        assert_eq!(value, Some(serde_json::json!(3)));
+    fn entry(key: &str, value: Value) -> BulkEntry {
+        BulkEntry {
+            key: key.to_string(),
+            value,
+            ttl_secs: None,
+        }
     }
 
     #[tokio::test]
-    async fn test_complex_values() {
+    async fn test_bulk_store_and_get() {
         let (memory, _dir) = create_test_memory().await;
 
-        let complex = serde_json::json!({
-            "name": "Test",
-            "items": [1, 2, 3],
-            "nested": {
-                "deep": true
-            }
-        });
+        let entries: Vec<BulkEntry> = (0..100)
+            .map(|i| entry(&format!("key{:03}", i), serde_json::json!(i)))
+            .collect();
+        let statuses = memory
+            .set_many("bulk", &entries, BulkMode::Store)
+            .await
+            .unwrap();
+        assert_eq!(statuses.len(), 100);
+        assert!(statuses.iter().all(|(_, s)| *s == BulkStatus::Created));
+        assert_eq!(memory.list_keys("bulk").await.unwrap().len(), 100);
 
-        memory
-            .set("test_tool", "complex", complex.clone())
+        let values = memory
+            .get_many("bulk", &["key042".to_string(), "missing".to_string()])
             .await
             .unwrap();
-        let value = memory.get("test_tool", "complex").await.unwrap();
+        assert_eq!(
+            values,
+            vec![
+                ("key042".to_string(), Some(serde_json::json!(42))),
+                ("missing".to_string(), None)
+            ]
+        );
 
-        assert_eq!(value, Some(complex));
+        // Storing again reports updates
+        let statuses = memory
+            .set_many("bulk", &entries[..2], BulkMode::Store)
+            .await
+            .unwrap();
+        assert!(statuses.iter().all(|(_, s)| *s == BulkStatus::Updated));
     }
 
     #[tokio::test]
-    async fn test_stats() {
+    async fn test_bulk_store_duplicate_keys() {
         let (memory, _dir) = create_test_memory().await;
 
-        memory
-            .set("tool1", "key1", serde_json::json!(1))
-            .await
-            .unwrap();
-        memory
-            .set("tool1", "key2", serde_json::json!(2))
-            .await
-            .unwrap();
-        memory
-            .set("tool2", "key1", serde_json::json!(3))
+        let statuses = memory
+            .set_many(
+                "bulk",
+                &[
+                    entry("a", serde_json::json!(1)),
+                    entry("b", serde_json::json!(2)),
+                    entry("a", serde_json::json!(3)),
+                ],
+                BulkMode::Store,
+            )
             .await
             .unwrap();
-
-        let stats = memory.stats().await.unwrap();
-        assert_eq!(stats.total_entries, 3);
-        assert_eq!(stats.total_tools, 2);
-        assert_eq!(stats.schema_version, SCHEMA_VERSION);
+        assert_eq!(
+            statuses,
+            vec![
+                ("a".to_string(), BulkStatus::Superseded),
+                ("b".to_string(), BulkStatus::Created),
+                ("a".to_string(), BulkStatus::Created),
+            ]
+        );
+        assert_eq!(
+            memory.get("bulk", "a").await.unwrap(),
+            Some(serde_json::json!(3))
+        );
     }
 
     #[tokio::test]
-    async fn test_isolation_between_tools() {
+    async fn test_bulk_write_rolls_back() {
         let (memory, _dir) = create_test_memory().await;
-
-        memory
-            .set("tool_a", "shared_key", serde_json::json!("A"))
-            .await
-            .unwrap();
         memory
-            .set("tool_b", "shared_key", serde_json::json!("B"))
+            .set("bulk", "existing", serde_json::json!(0))
             .await
             .unwrap();
 
-        let value_a = memory.get("tool_a", "shared_key").await.unwrap();
-        let value_b = memory.get("tool_b", "shared_key").await.unwrap();
+        // An empty key halfway through leaves nothing behind
+        let err = memory
+            .set_many(
+                "bulk",
+                &[
+                    entry("first", serde_json::json!(1)),
+                    entry("", serde_json::json!(2)),
+                    entry("third", serde_json::json!(3)),
+                ],
+                BulkMode::Store,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("empty key"));
+        assert_eq!(memory.list_keys("bulk").await.unwrap(), vec!["existing"]);
+
+        // bulk_update fails on a missing key, undoing the earlier update
+        let err = memory
+            .set_many(
+                "bulk",
+                &[
+                    entry("existing", serde_json::json!(1)),
+                    entry("missing", serde_json::json!(2)),
+                ],
+                BulkMode::Update,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("'missing' does not exist"));
+        assert_eq!(
+            memory.get("bulk", "existing").await.unwrap(),
+            Some(serde_json::json!(0))
+        );
 
-        assert_eq!(value_a, Some(serde_json::json!("A")));
-        assert_eq!(value_b, Some(serde_json::json!("B")));
+        let statuses = memory
+            .set_many(
+                "bulk",
+                &[entry("existing", serde_json::json!(5))],
+                BulkMode::Update,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            statuses,
+            vec![("existing".to_string(), BulkStatus::Updated)]
+        );
     }
 
     #[tokio::test]
-    async fn test_get_all() {
+    async fn test_stats_expiring_soon() {
         let (memory, _dir) = create_test_memory().await;
 
         memory
-            .set("test_tool", "a", serde_json::json!(1))
+            .set("t", "forever", serde_json::json!(1))
             .await
             .unwrap();
         memory
-            .set("test_tool", "b", serde_json::json!(2))
+            .set_with_ttl("t", "soon", serde_json::json!(2), Some(60))
             .await
             .unwrap();
-
-        let all = memory.get_all("test_tool").await.unwrap();
-        assert_eq!(all.len(), 2);
-        assert_eq!(all[0], ("a".to_string(), serde_json::json!(1)));
-        assert_eq!(all[1], ("b".to_string(), serde_json::json!(2)));
-    }
-
-    #[tokio::test]
-    async fn test_ttl_expiration() {
-        let (memory, _dir) = create_test_memory().await;
-
-        // Set with 1 second TTL
         memory
-            .set_with_ttl("test_tool", "temp", serde_json::json!("expires"), Some(1))
+            .set_with_ttl(
+                "t",
+                "later",
+                serde_json::json!(3),
+                Some(EXPIRING_SOON_SECS * 24),
+            )
             .await
             .unwrap();
 
-        // Should exist immediately
-        let value = memory.get("test_tool", "temp").await.unwrap();
-        assert!(value.is_some());
-
-        // Wait for expiration
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        let stats = memory.stats().await.unwrap();
+        assert_eq!(stats.total_entries, 3);
+        assert_eq!(stats.expiring_soon, 1);
 
-        // Should be expired now
-        let value = memory.get("test_tool", "temp").await.unwrap();
-        assert!(value.is_none());
+        // Overwriting without a TTL makes the entry permanent again
+        memory.set("t", "soon", serde_json::json!(4)).await.unwrap();
+        assert_eq!(memory.stats().await.unwrap().expiring_soon, 0);
     }
 
     #[tokio::test]
@@ -595,4 +2672,410 @@ mod tests {
         let value = memory.get("test_tool", "permanent").await.unwrap();
         assert!(value.is_some());
     }
+
+    #[tokio::test]
+    async fn test_spawn_cleanup_sweeps_on_start() {
+        let (memory, _dir) = create_test_memory().await;
+
+        memory
+            .set_with_ttl("test_tool", "temp", serde_json::json!(1), Some(1))
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+
+        memory.spawn_cleanup(0).await.unwrap();
+        assert_eq!(memory.stats().await.unwrap().total_entries, 0);
+    }
+
+    /// Embedder that maps known texts to fixed vectors and counts its calls
+    fn stub_embedder(calls: Arc<std::sync::atomic::AtomicUsize>) -> Embedder {
+        Arc::new(move |text| {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Box::pin(async move {
+                Ok(match text.as_str() {
+                    "cats are small felines" => vec![1.0, 0.1, 0.0],
+                    "dogs are loyal canines" => vec![0.1, 1.0, 0.0],
+                    "rust compiles to wasm" => vec![0.0, 0.1, 1.0],
+                    "kitten" => vec![0.9, 0.2, 0.0],
+                    "puppy" => vec![0.2, 0.9, 0.1],
+                    other => anyhow::bail!("unknown text: {}", other),
+                })
+            })
+        })
+    }
+
+    async fn store_animals(memory: &Memory) {
+        for (key, text) in [
+            ("cat", "cats are small felines"),
+            ("dog", "dogs are loyal canines"),
+            ("rust", "rust compiles to wasm"),
+        ] {
+            memory
+                .set("notes", key, serde_json::json!(text))
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search_orders_by_similarity() {
+        let (memory, _dir) = create_test_memory().await;
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let memory = memory.with_embedder(stub_embedder(calls.clone()));
+        store_animals(&memory).await;
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        let results = memory.semantic_search("notes", "kitten", 3).await.unwrap();
+        assert_eq!(results.mode, SearchMode::Semantic);
+        let keys: Vec<&str> = results.hits.iter().map(|h| h.key.as_str()).collect();
+        assert_eq!(keys, vec!["cat", "dog", "rust"]);
+        assert!(results.hits[0].score > results.hits[1].score);
+
+        let results = memory.semantic_search("notes", "puppy", 1).await.unwrap();
+        assert_eq!(results.hits.len(), 1);
+        assert_eq!(results.hits[0].key, "dog");
+        assert_eq!(
+            results.hits[0].value,
+            serde_json::json!("dogs are loyal canines")
+        );
+
+        // Stored embeddings are reused: only the two queries were embedded
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search_embeds_entries_stored_earlier() {
+        let (memory, _dir) = create_test_memory().await;
+        store_animals(&memory).await;
+
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        memory.set_embedder(stub_embedder(calls.clone()));
+        let results = memory.semantic_search("notes", "puppy", 1).await.unwrap();
+        assert_eq!(results.hits[0].key, "dog");
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+        // The backfilled vectors were saved
+        memory.semantic_search("notes", "kitten", 1).await.unwrap();
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_search_without_embedder_falls_back_to_full_text() {
+        let (memory, _dir) = create_test_memory().await;
+        store_animals(&memory).await;
+
+        let results = memory
+            .semantic_search("notes", "Loyal dogs", 5)
+            .await
+            .unwrap();
+        assert_eq!(results.mode, SearchMode::FullText);
+        assert_eq!(results.hits.len(), 1);
+        assert_eq!(results.hits[0].key, "dog");
+        assert_eq!(results.hits[0].score, 1.0);
+    }
+
+    #[test]
+    fn test_parse_embedding() {
+        assert_eq!(
+            parse_embedding("[1, 0.5, -2]").unwrap(),
+            vec![1.0, 0.5, -2.0]
+        );
+        assert_eq!(
+            parse_embedding(r#"{"embedding": [0.25]}"#).unwrap(),
+            vec![0.25]
+        );
+        assert_eq!(
+            parse_embedding("Here you go: [0.1, 0.2]").unwrap(),
+            vec![0.1, 0.2]
+        );
+        assert!(parse_embedding("[]").is_err());
+        assert!(parse_embedding(r#"["a"]"#).is_err());
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]), Some(1.0));
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), Some(0.0));
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), None);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), None);
+        let blob = encode_embedding(&[0.5, -1.5]);
+        assert_eq!(decode_embedding(&blob), vec![0.5, -1.5]);
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let (source, _source_dir) = create_test_memory().await;
+        source
+            .set("notes", "a", serde_json::json!({"x": 1}))
+            .await
+            .unwrap();
+        source
+            .set_with_ttl("notes", "b", serde_json::json!("temp"), Some(3600))
+            .await
+            .unwrap();
+        source
+            .set("counter", "n", serde_json::json!(7))
+            .await
+            .unwrap();
+        source
+            .cache_result(
+                "notes",
+                "1.0.0",
+                &serde_json::json!({}),
+                &serde_json::json!(1),
+            )
+            .await
+            .unwrap();
+
+        let export = source.export(None).await.unwrap();
+        assert_eq!(
+            export.as_object().unwrap().keys().collect::<Vec<_>>(),
+            vec!["counter", "notes"]
+        );
+        assert_eq!(export["notes"]["a"]["value"], serde_json::json!({"x": 1}));
+        assert!(export["notes"]["a"]["expires_at"].is_null());
+        assert!(export["notes"]["b"]["expires_at"].is_string());
+
+        let (target, _target_dir) = create_test_memory().await;
+        let summary = target.import(&export, MergeStrategy::Error).await.unwrap();
+        assert_eq!(summary.imported, 3);
+        assert_eq!(target.export(None).await.unwrap(), export);
+        assert_eq!(
+            target.get("counter", "n").await.unwrap(),
+            Some(serde_json::json!(7))
+        );
+
+        // Exporting a single tool
+        let notes = source.export(Some("notes")).await.unwrap();
+        assert_eq!(notes.as_object().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_merge_strategies() {
+        let (source, _source_dir) = create_test_memory().await;
+        source
+            .set("notes", "a", serde_json::json!("imported"))
+            .await
+            .unwrap();
+        source
+            .set("notes", "b", serde_json::json!("new"))
+            .await
+            .unwrap();
+        let export = source.export(None).await.unwrap();
+
+        let (target, _target_dir) = create_test_memory().await;
+        target
+            .set("notes", "a", serde_json::json!("local"))
+            .await
+            .unwrap();
+
+        // error: the conflict aborts the whole import
+        assert!(target.import(&export, MergeStrategy::Error).await.is_err());
+        assert_eq!(target.get("notes", "b").await.unwrap(), None);
+
+        // skip: existing keys are kept
+        let summary = target.import(&export, MergeStrategy::Skip).await.unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                imported: 1,
+                skipped: 1
+            }
+        );
+        assert_eq!(
+            target.get("notes", "a").await.unwrap(),
+            Some(serde_json::json!("local"))
+        );
+
+        // overwrite: existing keys are replaced
+        target
+            .import(&export, MergeStrategy::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(
+            target.get("notes", "a").await.unwrap(),
+            Some(serde_json::json!("imported"))
+        );
+
+        assert!(MergeStrategy::parse("replace").is_err());
+        assert!(target
+            .import(&serde_json::json!([1]), MergeStrategy::Skip)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_increments() {
+        let (memory, _dir) = create_test_memory().await;
+
+        let tasks: Vec<_> = (0..20)
+            .map(|_| {
+                let memory = memory.clone();
+                tokio::spawn(async move { memory.increment("counter", "hits", 1).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(
+            memory.get("counter", "hits").await.unwrap(),
+            Some(serde_json::json!(20))
+        );
+        assert_eq!(memory.increment("counter", "hits", -5).await.unwrap(), 15);
+
+        memory
+            .set("counter", "name", serde_json::json!("text"))
+            .await
+            .unwrap();
+        assert!(memory.increment("counter", "name", 1).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_get_and_set() {
+        let (memory, _dir) = create_test_memory().await;
+
+        let tasks: Vec<_> = (0..32)
+            .map(|i| {
+                let memory = memory.clone();
+                tokio::spawn(async move {
+                    let key = format!("key{}", i % 8);
+                    memory
+                        .set("shared", &key, serde_json::json!(i))
+                        .await
+                        .unwrap();
+                    memory.get("shared", &key).await.unwrap()
+                })
+            })
+            .collect();
+        for task in tasks {
+            // Another task may have overwritten the key, but it is never missing
+            assert!(task.await.unwrap().is_some());
+        }
+        assert_eq!(memory.list_keys("shared").await.unwrap().len(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_reads_do_not_wait_for_the_writer() {
+        let (memory, _dir) = create_test_memory().await;
+        memory
+            .set("tool", "key", serde_json::json!("value"))
+            .await
+            .unwrap();
+
+        let _writer = memory.conn.lock().await;
+        let value =
+            tokio::time::timeout(std::time::Duration::from_secs(5), memory.get("tool", "key"))
+                .await
+                .expect("read blocked behind the writer lock")
+                .unwrap();
+        assert_eq!(value, Some(serde_json::json!("value")));
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_after_connection_failure() {
+        let (memory, dir) = create_test_memory().await;
+        memory
+            .set("tool", "key", serde_json::json!("value"))
+            .await
+            .unwrap();
+
+        // Simulate dropped connections: point every connection at a file
+        // that isn't a database, so the next query on each fails
+        let bogus = dir.path().join("not-a-db");
+        std::fs::write(&bogus, vec![0x42u8; 4096]).unwrap();
+        *memory.conn.lock().await = Connection::open(&bogus).unwrap();
+        for conn in memory.readers.idle.lock().unwrap().iter_mut() {
+            *conn = open_reader(&bogus).unwrap();
+        }
+
+        memory
+            .set("tool", "other", serde_json::json!(2))
+            .await
+            .unwrap();
+        assert_eq!(
+            memory.get("tool", "key").await.unwrap(),
+            Some(serde_json::json!("value"))
+        );
+        assert_eq!(
+            memory.get("tool", "other").await.unwrap(),
+            Some(serde_json::json!(2))
+        );
+
+        // Query errors aren't retried or hidden
+        memory
+            .set("tool", SCHEMA_KEY, serde_json::json!({"type": "integer"}))
+            .await
+            .unwrap();
+        assert!(memory
+            .set("tool", "typed", serde_json::json!("text"))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_only_lost_databases_are_connection_errors() {
+        let failure = |code| -> anyhow::Error {
+            rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(code), None).into()
+        };
+        assert!(is_connection_error(&failure(rusqlite::ffi::SQLITE_IOERR)));
+        assert!(is_connection_error(
+            &failure(rusqlite::ffi::SQLITE_NOTADB).context("Failed to read")
+        ));
+        for code in [
+            rusqlite::ffi::SQLITE_BUSY,
+            rusqlite::ffi::SQLITE_LOCKED,
+            rusqlite::ffi::SQLITE_FULL,
+            rusqlite::ffi::SQLITE_READONLY,
+            rusqlite::ffi::SQLITE_PERM,
+        ] {
+            assert!(!is_connection_error(&failure(code)), "{}", code);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_is_rolled_back() {
+        let (memory, _dir) = create_test_memory().await;
+
+        let result: Result<()> = memory
+            .write(|conn| {
+                upsert(conn, "tool", "first", &serde_json::json!(1), None, None)?;
+                anyhow::bail!("second statement failed")
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(memory.get("tool", "first").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_append_trims_to_max_len() {
+        let (memory, _dir) = create_test_memory().await;
+
+        for i in 0..5 {
+            memory
+                .append("log", "events", serde_json::json!(i), Some(3))
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            memory.get("log", "events").await.unwrap(),
+            Some(serde_json::json!([2, 3, 4]))
+        );
+        assert_eq!(
+            memory
+                .append("log", "events", serde_json::json!(5), None)
+                .await
+                .unwrap(),
+            4
+        );
+
+        memory
+            .set("log", "scalar", serde_json::json!(1))
+            .await
+            .unwrap();
+        assert!(memory
+            .append("log", "scalar", serde_json::json!(2), None)
+            .await
+            .is_err());
+    }
 }
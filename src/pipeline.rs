@@ -1,8 +1,15 @@
 //! Pipeline execution - execute pipeline tools
 
 use anyhow::Result;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::registry::PipelineStep;
+use crate::runtime::{Cancelled, LoggingHandler, ProgressHandler};
 
 /// Result of a single step execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,14 +21,470 @@ pub struct StepResult {
     pub output: serde_json::Value,
     pub error: Option<String>,
     pub duration_ms: u64,
+    /// How many times the tool was called (0 if the step never ran it)
+    #[serde(default)]
+    pub attempts: u32,
+    /// Errors of the failed attempts before the last one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attempt_errors: Vec<String>,
+    /// The output was reused from an earlier run instead of calling the tool
+    #[serde(default, skip_serializing_if = "crate::registry::is_false")]
+    pub cached: bool,
+}
+
+/// Most pipelines that may be running inside each other at once
+pub const MAX_PIPELINE_DEPTH: usize = 8;
+
+/// Call chain for running pipeline `name` from a step of the last pipeline
+/// in `chain`. Fails if `name` is already running (a cycle) or the chain
+/// would exceed `MAX_PIPELINE_DEPTH`.
+pub fn enter_pipeline(chain: &[String], name: &str) -> Result<Vec<String>> {
+    let mut next = chain.to_vec();
+    next.push(name.to_string());
+    if chain.iter().any(|p| p == name) {
+        anyhow::bail!("Pipeline cycle detected: {}", next.join(" → "));
+    }
+    if next.len() > MAX_PIPELINE_DEPTH {
+        anyhow::bail!(
+            "Pipeline nesting exceeds the maximum depth of {}: {}",
+            MAX_PIPELINE_DEPTH,
+            next.join(" → ")
+        );
+    }
+    Ok(next)
+}
+
+/// Outputs longer than this are cut short in the markdown summary unless verbose
+const SUMMARY_OUTPUT_CHARS: usize = 200;
+
+/// The first `max_chars` characters of `text`, or None if it is no longer
+/// than that. Cuts on a char boundary, so multi-byte text never panics.
+pub fn truncate_chars(text: &str, max_chars: usize) -> Option<&str> {
+    text.char_indices()
+        .nth(max_chars)
+        .map(|(end, _)| &text[..end])
+}
+
+impl StepResult {
+    /// Markdown summary of the step: status, attempts and its output or error.
+    /// Outputs are truncated unless `verbose` is set.
+    pub fn to_markdown(&self, verbose: bool) -> String {
+        let mut out = String::new();
+        let status = if self.success { "✅" } else { "❌" };
+        let default_name = format!("step_{}", self.step_index + 1);
+        let name = self.step_name.as_deref().unwrap_or(&default_name);
+        out.push_str(&format!(
+            "**{} {}** ({}) - {}ms",
+            status, name, self.tool, self.duration_ms
+        ));
+        if self.attempts > 1 {
+            out.push_str(&format!(" after {} attempts", self.attempts));
+        }
+        if self.cached {
+            out.push_str(" (cached)");
+        }
+        out.push('\n');
+        for (attempt, err) in self.attempt_errors.iter().enumerate() {
+            out.push_str(&format!("  Attempt {} failed: {}\n", attempt + 1, err));
+        }
+
+        if let Some(ref err) = self.error {
+            out.push_str(&format!("  Error: {}\n", err));
+        } else {
+            let output_str = serde_json::to_string_pretty(&self.output).unwrap_or_default();
+            match truncate_chars(&output_str, SUMMARY_OUTPUT_CHARS) {
+                Some(cut) if !verbose => out.push_str(&format!("  Output: {}...\n", cut)),
+                _ => out.push_str(&format!("  Output: {}\n", output_str)),
+            }
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// Result of a whole pipeline run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineRun {
+    pub pipeline: String,
+    /// Every step succeeded or was continued past
+    pub success: bool,
+    pub cancelled: bool,
+    pub duration_ms: u64,
+    pub steps: Vec<StepResult>,
+    /// Results of the on_error steps, if a step failed and they ran
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_error: Vec<StepResult>,
+    /// Run id the steps were checkpointed under in memory, for debug runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug_run: Option<String>,
+}
+
+impl PipelineRun {
+    /// The run as a step result for a parent pipeline: the final output, or
+    /// an error naming the failed step
+    pub fn into_result(self) -> Result<serde_json::Value> {
+        if self.cancelled {
+            return Err(Cancelled.into());
+        }
+        if !self.success {
+            let failed = self.steps.iter().rev().find(|s| !s.success);
+            let (step, error) = match failed {
+                Some(s) => (
+                    s.step_name.clone().unwrap_or_else(|| s.tool.clone()),
+                    s.error.clone().unwrap_or_default(),
+                ),
+                None => (String::new(), String::new()),
+            };
+            anyhow::bail!(
+                "Pipeline '{}' failed at '{}': {}",
+                self.pipeline,
+                step,
+                error
+            );
+        }
+        Ok(self
+            .steps
+            .into_iter()
+            .last()
+            .map_or(serde_json::Value::Null, |s| s.output))
+    }
+
+    /// Output of the last step that ran
+    pub fn final_output(&self) -> Option<&serde_json::Value> {
+        self.steps.last().map(|s| &s.output)
+    }
+
+    /// Markdown report of the run; step outputs are truncated unless `verbose`
+    pub fn to_markdown(&self, verbose: bool) -> String {
+        let (status_emoji, status) = if self.cancelled {
+            ("⏹️", "Cancelled")
+        } else if self.success {
+            ("✅", "Completed")
+        } else {
+            ("❌", "Failed")
+        };
+        let mut output = format!(
+            "## {} Pipeline '{}' {}\n\n**Duration:** {}ms\n\n### Steps:\n\n",
+            status_emoji, self.pipeline, status, self.duration_ms
+        );
+
+        for result in &self.steps {
+            output.push_str(&result.to_markdown(verbose));
+        }
+
+        if !self.on_error.is_empty() {
+            output.push_str("### On Error:\n\n");
+            for result in &self.on_error {
+                output.push_str(&result.to_markdown(verbose));
+            }
+        }
+
+        if let Some(last) = self.final_output() {
+            output.push_str(&format!(
+                "### Final Result:\n```json\n{}\n```",
+                serde_json::to_string_pretty(last).unwrap_or_default()
+            ));
+        }
+
+        if let Some(ref run_id) = self.debug_run {
+            output.push_str(&format!(
+                "\n\n🐞 **Debug run:** `{}`. Each step's resolved args and output are in memory: \
+                `memory(action: \"list\", tool_name: \"{}\", prefix: \"{}/\", include_values: true)`",
+                run_id,
+                crate::memory::PIPELINE_DEBUG_NAMESPACE,
+                run_id
+            ));
+        }
+
+        output
+    }
+}
+
+/// Outcome of running a step with its timeout and retry policy
+pub struct StepAttempts {
+    /// Result of the last attempt
+    pub result: Result<serde_json::Value>,
+    pub attempts: u32,
+    /// Errors of the failed attempts before the last one
+    pub attempt_errors: Vec<String>,
+    /// The result came from a cache, not a tool call
+    pub cached: bool,
+}
+
+/// Reports a pipeline run to the client: a progress notification before and
+/// after every step, and the steps' logs tagged with the step that wrote them.
+/// Everything is tied to the caller's progress token.
+#[derive(Clone, Default)]
+pub struct StepProgress {
+    progress_handler: Option<ProgressHandler>,
+    logging_handler: Option<LoggingHandler>,
+    progress_token: Option<String>,
+    total: u64,
 }
 
+impl StepProgress {
+    /// Report a run of `total` steps through the given handlers
+    pub fn new(
+        progress_handler: Option<ProgressHandler>,
+        logging_handler: Option<LoggingHandler>,
+        progress_token: Option<String>,
+        total: usize,
+    ) -> Self {
+        Self {
+            progress_handler,
+            logging_handler,
+            progress_token,
+            total: total as u64,
+        }
+    }
+
+    /// How a step is named in notifications: "fetch (http_get)", or
+    /// "step 2 (http_get)" for an unnamed step
+    fn label(index: usize, name: Option<&str>, tool: &str) -> String {
+        match name {
+            Some(name) => format!("{} ({})", name, tool),
+            None => format!("step {} ({})", index + 1, tool),
+        }
+    }
+
+    async fn notify(&self, current: u64, message: String) {
+        if let Some(ref handler) = self.progress_handler {
+            handler(
+                current,
+                self.total,
+                Some(message),
+                self.progress_token.clone(),
+            )
+            .await;
+        }
+    }
+
+    /// Progress before step `index` runs: "running fetch (http_get)"
+    pub async fn step_started(&self, index: usize, step: &PipelineStep) {
+        let label = Self::label(index, step.name.as_deref(), &step.tool);
+        self.notify(index as u64, format!("running {}", label))
+            .await;
+    }
+
+    /// Progress once a step is done: "fetch (http_get) done", "... failed"
+    /// or "... skipped"
+    pub async fn step_finished(&self, result: &StepResult) {
+        let label = Self::label(result.step_index, result.step_name.as_deref(), &result.tool);
+        let status = if !result.success {
+            "failed"
+        } else if result.attempts == 0 {
+            "skipped"
+        } else if result.cached {
+            "done (cached)"
+        } else {
+            "done"
+        };
+        self.notify(
+            result.step_index as u64 + 1,
+            format!("{} {}", label, status),
+        )
+        .await;
+    }
+
+    /// Logging handler for step `index` that adds the step to every log
+    /// entry's data, or None if logs aren't forwarded
+    pub fn step_logger(&self, index: usize, step: &PipelineStep) -> Option<LoggingHandler> {
+        let handler = self.logging_handler.clone()?;
+        let label = Self::label(index, step.name.as_deref(), &step.tool);
+        Some(std::sync::Arc::new(move |level, message, data| {
+            let mut tagged = match data {
+                Some(serde_json::Value::Object(map)) => map,
+                Some(other) => {
+                    let mut map = serde_json::Map::new();
+                    map.insert("data".to_string(), other);
+                    map
+                }
+                None => serde_json::Map::new(),
+            };
+            tagged
+                .entry("message")
+                .or_insert_with(|| message.clone().into());
+            tagged.insert("step".to_string(), label.clone().into());
+            handler(level, message, Some(serde_json::Value::Object(tagged)))
+        }))
+    }
+}
+
+/// Name of a JSON value's type, for error messages
+fn json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "a boolean",
+        serde_json::Value::Number(_) => "a number",
+        serde_json::Value::String(_) => "a string",
+        serde_json::Value::Array(_) => "an array",
+        serde_json::Value::Object(_) => "an object",
+    }
+}
+
+/// Run `run` for every element with at most `max_concurrency` running at
+/// once, returning the outcomes in element order
+pub async fn run_foreach<F, Fut, T>(
+    items: Vec<serde_json::Value>,
+    max_concurrency: usize,
+    run: F,
+) -> Vec<T>
+where
+    F: FnMut((usize, serde_json::Value)) -> Fut,
+    Fut: Future<Output = T>,
+{
+    futures::stream::iter(items.into_iter().enumerate())
+        .map(run)
+        .buffered(max_concurrency.max(1))
+        .collect()
+        .await
+}
+
+/// Combine the per-element outcomes of a foreach step: the outputs in
+/// element order, or the first element's error. The step counts as cached
+/// when every element was.
+pub fn collect_foreach(outcomes: Vec<StepAttempts>) -> StepAttempts {
+    let cached = !outcomes.is_empty() && outcomes.iter().all(|o| o.cached);
+    let mut outputs = Vec::with_capacity(outcomes.len());
+    let mut attempts = 0;
+    let mut attempt_errors = Vec::new();
+    let mut error = None;
+    for (index, outcome) in outcomes.into_iter().enumerate() {
+        attempts += outcome.attempts;
+        attempt_errors.extend(
+            outcome
+                .attempt_errors
+                .into_iter()
+                .map(|e| format!("Item {}: {}", index, e)),
+        );
+        match outcome.result {
+            Ok(output) => outputs.push(output),
+            Err(e) if error.is_none() => {
+                error = Some(if e.is::<Cancelled>() {
+                    e
+                } else {
+                    anyhow::anyhow!("Item {}: {}", index, e)
+                });
+            }
+            Err(_) => {}
+        }
+    }
+    StepAttempts {
+        result: match error {
+            Some(e) => Err(e),
+            None => Ok(serde_json::Value::Array(outputs)),
+        },
+        attempts,
+        attempt_errors,
+        cached,
+    }
+}
+
+/// Error of a script that ran but exited non-zero (its output carries the exit code)
+fn crashed(output: &serde_json::Value) -> Option<String> {
+    match output.get("exit_code")? {
+        serde_json::Value::Number(code) if code.as_i64() == Some(0) => None,
+        serde_json::Value::Null => Some("Script was killed by a signal".to_string()),
+        code => Some(format!("Script exited with code {}", code)),
+    }
+}
+
+/// Run `attempt` until it succeeds, retrying up to `step.retries` times
+/// with `step.retry_delay_ms` between attempts. An error, a timeout and a
+/// script exiting non-zero all count as failed attempts; the last attempt's
+/// result is returned as-is. Each attempt gets a child
+/// of `ct` that is cancelled when it exceeds `step.timeout_secs`; once `ct`
+/// itself is cancelled no further attempts are made.
+pub async fn run_with_retries<F, Fut>(
+    step: &PipelineStep,
+    ct: &CancellationToken,
+    mut attempt: F,
+) -> StepAttempts
+where
+    F: FnMut(CancellationToken) -> Fut,
+    Fut: Future<Output = Result<serde_json::Value>>,
+{
+    let mut attempt_errors = Vec::new();
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let token = ct.child_token();
+        let call = attempt(token.clone());
+        let result = match step.timeout_secs {
+            Some(secs) => match tokio::time::timeout(Duration::from_secs(secs), call).await {
+                Ok(result) => result,
+                Err(_) => {
+                    // Stop the abandoned call
+                    token.cancel();
+                    Err(anyhow::anyhow!("Timed out after {}s", secs))
+                }
+            },
+            None => call.await,
+        };
+
+        let error = match &result {
+            Ok(output) => crashed(output),
+            Err(e) if e.is::<Cancelled>() => None,
+            Err(e) => Some(e.to_string()),
+        };
+        let Some(error) = error.filter(|_| attempts <= step.retries && !ct.is_cancelled()) else {
+            return StepAttempts {
+                result,
+                attempts,
+                attempt_errors,
+                cached: false,
+            };
+        };
+        eprintln!(
+            "🔁 Step '{}' attempt {} failed: {}",
+            step.tool, attempts, error
+        );
+        attempt_errors.push(error);
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(step.retry_delay_ms)) => {}
+            _ = ct.cancelled() => {}
+        }
+    }
+}
+
+/// A variable path names a field its source value doesn't have
+#[derive(Debug)]
+struct MissingField {
+    field: String,
+    source: String,
+}
+
+impl std::fmt::Display for MissingField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Field '{}' not found in {}", self.field, self.source)
+    }
+}
+
+impl std::error::Error for MissingField {}
+
 /// Pipeline executor - resolves variables and evaluates conditions
 pub struct PipelineExecutor;
 
 impl PipelineExecutor {
-    /// Resolve variable references in arguments
-    /// Supports: $input.field, $prev.field, $step_name.field, $prev (whole output)
+    /// A step's args before resolution. Transform steps without an `input`
+    /// read `$prev`.
+    pub fn step_args(step: &PipelineStep) -> std::borrow::Cow<'_, serde_json::Value> {
+        match &step.args {
+            serde_json::Value::Object(args)
+                if step.tool == TRANSFORM_TOOL && !args.contains_key("input") =>
+            {
+                let mut args = args.clone();
+                args.insert("input".to_string(), "$prev".into());
+                std::borrow::Cow::Owned(serde_json::Value::Object(args))
+            }
+            args => std::borrow::Cow::Borrowed(args),
+        }
+    }
+
+    /// Resolve variable references in arguments, at any depth of nested
+    /// objects and arrays.
+    /// Supports: $input.field, $prev.field, $step_name.field, $prev (whole output).
+    /// A string starting with `$$` is a literal with one `$` removed: "$$5.00" is "$5.00".
     pub fn resolve_args(
         args: &serde_json::Value,
         input: &serde_json::Value,
@@ -30,8 +493,10 @@ impl PipelineExecutor {
     ) -> Result<serde_json::Value> {
         match args {
             serde_json::Value::String(s) => {
-                // Check for variable reference
-                if s.starts_with('$') {
+                // Check for an escaped literal, then a variable reference
+                if s.starts_with("$$") {
+                    Ok(serde_json::Value::String(s[1..].to_string()))
+                } else if s.starts_with('$') {
                     Self::resolve_variable(s, input, step_results, prev_output)
                 } else {
                     Ok(serde_json::Value::String(s.clone()))
@@ -96,14 +561,54 @@ impl PipelineExecutor {
         // Navigate the path
         let mut current = source_value;
         for part in path {
-            current = current
-                .get(part)
-                .ok_or_else(|| anyhow::anyhow!("Field '{}' not found in {}", part, source))?;
+            current = current.get(part).ok_or_else(|| MissingField {
+                field: part.to_string(),
+                source: source.to_string(),
+            })?;
         }
 
         Ok(current.clone())
     }
 
+    /// Resolve a foreach expression (e.g. "$fetch.items") to the elements to iterate over
+    pub fn resolve_foreach(
+        expr: &str,
+        input: &serde_json::Value,
+        step_results: &HashMap<String, serde_json::Value>,
+        prev_output: Option<&serde_json::Value>,
+    ) -> Result<Vec<serde_json::Value>> {
+        match Self::resolve_variable(expr.trim(), input, step_results, prev_output)? {
+            serde_json::Value::Array(items) => Ok(items),
+            other => anyhow::bail!(
+                "foreach '{}' must resolve to an array, got {}",
+                expr,
+                json_type(&other)
+            ),
+        }
+    }
+
+    /// Step results extended with a foreach element as `$item` and its position as `$index`
+    pub fn item_scope(
+        step_results: &HashMap<String, serde_json::Value>,
+        index: usize,
+        item: serde_json::Value,
+    ) -> HashMap<String, serde_json::Value> {
+        let mut scope = step_results.clone();
+        scope.insert("item".to_string(), item);
+        scope.insert("index".to_string(), index.into());
+        scope
+    }
+
+    /// The `$error` value exposed to on_error steps: the failed step's name
+    /// (or tool, if unnamed), tool and error message
+    pub fn error_info(failed: &StepResult) -> serde_json::Value {
+        serde_json::json!({
+            "step": failed.step_name.as_deref().unwrap_or(&failed.tool),
+            "tool": failed.tool,
+            "message": failed.error.as_deref().unwrap_or_default(),
+        })
+    }
+
     /// Evaluate a simple condition
     /// Supports: $var == value, $var != value, $var (truthy check)
     pub fn evaluate_condition(
@@ -138,56 +643,1351 @@ impl PipelineExecutor {
 
             return Ok(left != right);
         }
-
-        // Truthy check
-        let value = Self::resolve_condition_value(condition, input, step_results, prev_output)?;
-        Ok(match value {
-            serde_json::Value::Bool(b) => b,
-            serde_json::Value::Null => false,
-            serde_json::Value::String(s) => !s.is_empty(),
-            serde_json::Value::Number(n) => n.as_f64().is_some_and(|f| f != 0.0),
-            serde_json::Value::Array(a) => !a.is_empty(),
-            serde_json::Value::Object(o) => !o.is_empty(),
-        })
+
+        // Truthy check
+        let value = Self::resolve_condition_value(condition, input, step_results, prev_output)?;
+        Ok(match value {
+            serde_json::Value::Bool(b) => b,
+            serde_json::Value::Null => false,
+            serde_json::Value::String(s) => !s.is_empty(),
+            serde_json::Value::Number(n) => n.as_f64().is_some_and(|f| f != 0.0),
+            serde_json::Value::Array(a) => !a.is_empty(),
+            serde_json::Value::Object(o) => !o.is_empty(),
+        })
+    }
+
+    fn resolve_condition_value(
+        value: &str,
+        input: &serde_json::Value,
+        step_results: &HashMap<String, serde_json::Value>,
+        prev_output: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        let value = value.trim();
+
+        // Variable reference; a missing field is null, so e.g. `$prev.exit_code`
+        // is falsy for outputs that don't carry one
+        if value.starts_with('$') {
+            return match Self::resolve_variable(value, input, step_results, prev_output) {
+                Err(e) if e.is::<MissingField>() => Ok(serde_json::Value::Null),
+                result => result,
+            };
+        }
+
+        // Boolean literals
+        if value == "true" {
+            return Ok(serde_json::Value::Bool(true));
+        }
+        if value == "false" {
+            return Ok(serde_json::Value::Bool(false));
+        }
+
+        // Null
+        if value == "null" {
+            return Ok(serde_json::Value::Null);
+        }
+
+        // Number
+        if let Ok(n) = value.parse::<i64>() {
+            return Ok(serde_json::Value::Number(n.into()));
+        }
+        if let Ok(n) = value.parse::<f64>() {
+            return Ok(serde_json::json!(n));
+        }
+
+        // String (with or without quotes)
+        let s = value.trim_matches('"').trim_matches('\'');
+        Ok(serde_json::Value::String(s.to_string()))
+    }
+}
+
+// ==================== DRY RUN ====================
+
+/// What a dry run knows about a value
+#[derive(Debug, Clone)]
+enum Shape {
+    /// A sample value (sample input or a user-supplied step output)
+    Sample(serde_json::Value),
+    /// A JSON schema (a tool's output schema)
+    Schema(serde_json::Value),
+    /// Nothing is known, so every path may exist
+    Unknown,
+}
+
+impl Shape {
+    /// Shape of `field`, or `None` if the value can't have it
+    fn field(&self, field: &str) -> Option<Shape> {
+        match self {
+            Shape::Sample(value) => value.get(field).cloned().map(Shape::Sample),
+            Shape::Schema(schema) => match schema.get("properties") {
+                Some(serde_json::Value::Object(props)) => match props.get(field) {
+                    Some(prop) => Some(Shape::Schema(prop.clone())),
+                    // Declared fields are exhaustive unless extra ones are allowed
+                    None => match schema.get("additionalProperties") {
+                        None | Some(serde_json::Value::Bool(false)) => None,
+                        Some(_) => Some(Shape::Unknown),
+                    },
+                },
+                _ => Some(Shape::Unknown),
+            },
+            Shape::Unknown => Some(Shape::Unknown),
+        }
+    }
+
+    /// Shape of one element, or an error if the value isn't an array
+    fn element(&self) -> std::result::Result<Shape, String> {
+        match self {
+            Shape::Sample(serde_json::Value::Array(items)) => {
+                Ok(items.first().cloned().map_or(Shape::Unknown, Shape::Sample))
+            }
+            Shape::Sample(other) => Err(json_type(other).to_string()),
+            Shape::Schema(schema) => match schema.get("type").and_then(|t| t.as_str()) {
+                Some("array") => Ok(schema
+                    .get("items")
+                    .cloned()
+                    .map_or(Shape::Unknown, Shape::Schema)),
+                Some(other) => Err(format!("of type {}", other)),
+                None => Ok(Shape::Unknown),
+            },
+            Shape::Unknown => Ok(Shape::Unknown),
+        }
+    }
+
+    /// Fields the value is known to have, for hints
+    fn known_fields(&self) -> Vec<String> {
+        let fields = match self {
+            Shape::Sample(serde_json::Value::Object(obj)) => Some(obj),
+            Shape::Schema(schema) => schema.get("properties").and_then(|p| p.as_object()),
+            _ => None,
+        };
+        fields
+            .map(|f| f.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Variables visible to a step during a dry run
+struct DryRunScope<'a> {
+    input: Shape,
+    prev: Option<Shape>,
+    steps: HashMap<String, Shape>,
+    /// Names of all steps, to tell "runs later" from "doesn't exist"
+    all_names: &'a [&'a str],
+}
+
+impl DryRunScope<'_> {
+    /// Shape of a `$source.path` reference. With `strict` unset a missing
+    /// field is fine (conditions treat it as null); unknown sources never are.
+    fn resolve(&self, reference: &str, strict: bool) -> std::result::Result<Shape, String> {
+        let var = reference.trim().trim_start_matches('$');
+        let mut parts = var.split('.');
+        let source = parts.next().unwrap_or_default();
+        let mut current = match source {
+            "input" => self.input.clone(),
+            "prev" => self
+                .prev
+                .clone()
+                .ok_or_else(|| format!("{}: there is no previous step", reference))?,
+            name => match self.steps.get(name) {
+                Some(shape) => shape.clone(),
+                None if self.all_names.contains(&name) => {
+                    return Err(format!(
+                        "{}: step '{}' hasn't run yet at this point",
+                        reference, name
+                    ))
+                }
+                None => return Err(format!("{}: unknown step '{}'", reference, name)),
+            },
+        };
+        for part in parts {
+            current = match current.field(part) {
+                Some(shape) => shape,
+                None if !strict => Shape::Unknown,
+                None => {
+                    let known = current.known_fields();
+                    let hint = if known.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" (available: {})", known.join(", "))
+                    };
+                    return Err(format!(
+                        "{}: '{}' isn't in the output of '{}'{}",
+                        reference, part, source, hint
+                    ));
+                }
+            };
+        }
+        Ok(current)
+    }
+
+    /// Check every `$` reference in a step's args
+    fn check_args(&self, args: &serde_json::Value, issues: &mut Vec<String>, label: &str) {
+        match args {
+            serde_json::Value::String(s) if s.starts_with('$') && !s.starts_with("$$") => {
+                if let Err(e) = self.resolve(s, true) {
+                    issues.push(format!("{}: {}", label, e));
+                }
+            }
+            serde_json::Value::Object(obj) => {
+                for value in obj.values() {
+                    self.check_args(value, issues, label);
+                }
+            }
+            serde_json::Value::Array(arr) => {
+                for value in arr {
+                    self.check_args(value, issues, label);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Operands of a condition, or an error if it doesn't parse
+fn condition_operands(condition: &str) -> std::result::Result<Vec<&str>, String> {
+    let condition = condition.trim();
+    for op in ["==", "!="] {
+        if condition.contains(op) {
+            let parts: Vec<&str> = condition.split(op).map(str::trim).collect();
+            if parts.len() != 2 || parts.iter().any(|p| p.is_empty()) {
+                return Err(format!("invalid condition '{}'", condition));
+            }
+            return Ok(parts);
+        }
+    }
+    if condition.is_empty() {
+        return Err("empty condition".to_string());
+    }
+    Ok(vec![condition])
+}
+
+/// Check a pipeline without running any tool: every step's tool exists,
+/// every `$input.*`, `$prev.*` and `$step.*` reference resolves, foreach
+/// expressions are arrays, conditions parse and can be true.
+///
+/// Step outputs are described by `sample_outputs` (by step name) or else by
+/// the tool's output schema from `output_schema`, which returns an error for
+/// unknown tools. References into outputs without either can't be checked.
+/// Returns the problems found; empty means the pipeline looks runnable.
+pub fn dry_run<F>(
+    steps: &[PipelineStep],
+    on_error: &[PipelineStep],
+    sample_input: Option<&serde_json::Value>,
+    sample_outputs: &HashMap<String, serde_json::Value>,
+    output_schema: F,
+) -> Vec<String>
+where
+    F: Fn(&str) -> Result<Option<serde_json::Value>>,
+{
+    let names: Vec<&str> = steps
+        .iter()
+        .chain(on_error)
+        .filter_map(|s| s.name.as_deref())
+        .collect();
+    let mut scope = DryRunScope {
+        input: sample_input.cloned().map_or(Shape::Unknown, Shape::Sample),
+        prev: None,
+        steps: HashMap::new(),
+        all_names: &names,
+    };
+    let mut issues = Vec::new();
+
+    for (i, step) in steps.iter().enumerate() {
+        let label = format!("Step {}", i + 1);
+        let output = dry_run_step(
+            step,
+            &label,
+            &scope,
+            sample_outputs,
+            &output_schema,
+            &mut issues,
+        );
+        if let Some(ref name) = step.name {
+            scope.steps.insert(name.clone(), output.clone());
+        }
+        scope.prev = Some(output);
+    }
+
+    // on_error steps may follow any failed step, so their $prev is unknown
+    scope.steps.insert(
+        "error".to_string(),
+        Shape::Sample(serde_json::json!({"step": "", "tool": "", "message": ""})),
+    );
+    for (i, step) in on_error.iter().enumerate() {
+        scope.prev = Some(Shape::Unknown);
+        let label = format!("on_error step {}", i + 1);
+        let output = dry_run_step(
+            step,
+            &label,
+            &scope,
+            sample_outputs,
+            &output_schema,
+            &mut issues,
+        );
+        if let Some(ref name) = step.name {
+            scope.steps.insert(name.clone(), output);
+        }
+    }
+
+    issues
+}
+
+/// Dry-run one step, returning the shape of its output
+fn dry_run_step<F>(
+    step: &PipelineStep,
+    label: &str,
+    scope: &DryRunScope,
+    sample_outputs: &HashMap<String, serde_json::Value>,
+    output_schema: &F,
+    issues: &mut Vec<String>,
+) -> Shape
+where
+    F: Fn(&str) -> Result<Option<serde_json::Value>>,
+{
+    let schema = match output_schema(&step.tool) {
+        Ok(schema) => schema,
+        Err(e) => {
+            issues.push(format!("{}: {}", label, e));
+            None
+        }
+    };
+
+    if let Some(ref condition) = step.condition {
+        match condition_operands(condition) {
+            Err(e) => issues.push(format!("{}: {}", label, e)),
+            Ok(operands) => {
+                for operand in operands.iter().filter(|o| o.starts_with('$')) {
+                    if let Err(e) = scope.resolve(operand, false) {
+                        issues.push(format!("{}: condition {}", label, e));
+                    }
+                }
+                let constant = operands.iter().all(|o| !o.starts_with('$'));
+                let empty = HashMap::new();
+                if constant
+                    && !PipelineExecutor::evaluate_condition(
+                        condition,
+                        &serde_json::Value::Null,
+                        &empty,
+                        None,
+                    )
+                    .unwrap_or(true)
+                {
+                    issues.push(format!(
+                        "{}: condition '{}' is never true, so the step is unreachable",
+                        label, condition
+                    ));
+                }
+            }
+        }
+    }
+
+    if step.tool == TRANSFORM_TOOL {
+        match step.args.get("expr").and_then(|e| e.as_str()) {
+            Some(expr) => {
+                if let Err(e) = Transform::parse(expr) {
+                    issues.push(format!("{}: {}", label, e));
+                }
+            }
+            None => issues.push(format!(
+                "{}: transform needs an 'expr' string argument",
+                label
+            )),
+        }
+    }
+
+    let args = PipelineExecutor::step_args(step);
+    match step.foreach {
+        None => scope.check_args(&args, issues, label),
+        Some(ref expr) => {
+            let element = match scope.resolve(expr, true) {
+                Ok(shape) => match shape.element() {
+                    Ok(element) => element,
+                    Err(kind) => {
+                        issues.push(format!(
+                            "{}: foreach {} is {}, not an array",
+                            label, expr, kind
+                        ));
+                        Shape::Unknown
+                    }
+                },
+                Err(e) => {
+                    issues.push(format!("{}: foreach {}", label, e));
+                    Shape::Unknown
+                }
+            };
+            let mut item_scope = DryRunScope {
+                input: scope.input.clone(),
+                prev: scope.prev.clone(),
+                steps: scope.steps.clone(),
+                all_names: scope.all_names,
+            };
+            item_scope.steps.insert("item".to_string(), element);
+            item_scope
+                .steps
+                .insert("index".to_string(), Shape::Sample(serde_json::json!(0)));
+            item_scope.check_args(&args, issues, label);
+        }
+    }
+
+    let sample = step.name.as_ref().and_then(|n| sample_outputs.get(n));
+    match (sample, schema) {
+        (Some(sample), _) => Shape::Sample(sample.clone()),
+        (None, Some(schema)) if step.foreach.is_some() => {
+            Shape::Schema(serde_json::json!({"type": "array", "items": schema}))
+        }
+        (None, Some(schema)) => Shape::Schema(schema),
+        (None, None) => Shape::Unknown,
+    }
+}
+
+// ==================== DIAGRAM ====================
+
+/// Render a pipeline as a Mermaid flowchart. Steps run top to bottom from
+/// `input`; extra labelled edges show `$input` and `$step_name` references
+/// to anything other than the step just before. Edges into a conditional
+/// step are dashed and labelled with the condition, continue_on_error steps
+/// are styled and explained in a legend, and on_error steps get a subgraph.
+pub fn to_mermaid(steps: &[PipelineStep], on_error: &[PipelineStep]) -> String {
+    let mut lines = vec![
+        "flowchart TD".to_string(),
+        "    input([\"input\"])".to_string(),
+    ];
+    let mut may_fail = Vec::new();
+
+    for (i, step) in steps.iter().enumerate() {
+        let id = format!("s{}", i + 1);
+        lines.push(format!("    {}[\"{}\"]", id, mermaid_label(i, step)));
+        if step.continue_on_error {
+            may_fail.push(id.clone());
+        }
+
+        let arrow = match &step.condition {
+            Some(condition) => format!("-.->|\"if {}\"|", mermaid_escape(condition)),
+            None => "-->".to_string(),
+        };
+        let prev = if i == 0 {
+            "input".to_string()
+        } else {
+            format!("s{}", i)
+        };
+        lines.push(format!("    {} {} {}", prev, arrow, id));
+
+        let data_arrow = if step.condition.is_some() {
+            "-.->"
+        } else {
+            "-->"
+        };
+        for source in step_references(step) {
+            let from = if source == "input" {
+                "input".to_string()
+            } else {
+                match steps[..i]
+                    .iter()
+                    .position(|s| s.name.as_deref() == Some(source))
+                {
+                    Some(j) => format!("s{}", j + 1),
+                    None => continue,
+                }
+            };
+            if from != prev {
+                lines.push(format!(
+                    "    {} {}|\"${}\"| {}",
+                    from, data_arrow, source, id
+                ));
+            }
+        }
+    }
+
+    if !on_error.is_empty() {
+        lines.push("    subgraph on_error [\"on_error\"]".to_string());
+        for (i, step) in on_error.iter().enumerate() {
+            lines.push(format!(
+                "        e{}[\"{}\"]",
+                i + 1,
+                mermaid_label(i, step)
+            ));
+            if i > 0 {
+                lines.push(format!("        e{} --> e{}", i, i + 1));
+            }
+            if step.continue_on_error {
+                may_fail.push(format!("e{}", i + 1));
+            }
+        }
+        lines.push("    end".to_string());
+    }
+
+    if !may_fail.is_empty() {
+        lines.push("    subgraph legend [\"Legend\"]".to_string());
+        lines.push("        legend_may_fail[\"continue_on_error\"]".to_string());
+        lines.push("    end".to_string());
+        lines.push("    classDef mayFail stroke-dasharray: 5 5".to_string());
+        may_fail.push("legend_may_fail".to_string());
+        lines.push(format!("    class {} mayFail", may_fail.join(",")));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Node text: name (or "step N") over the tool, plus any foreach
+fn mermaid_label(index: usize, step: &PipelineStep) -> String {
+    let name = match &step.name {
+        Some(name) => name.clone(),
+        None => format!("step {}", index + 1),
+    };
+    let mut label = format!("{}<br/>{}", name, step.tool);
+    if let Some(ref foreach) = step.foreach {
+        label.push_str(&format!("<br/>foreach {}", foreach));
+    }
+    mermaid_escape(&label)
+}
+
+/// Quotes would end a Mermaid label early
+fn mermaid_escape(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+/// Sources (`input` or step names) a step reads through its args, foreach
+/// and condition, in order of first use. `$prev`, `$item` and `$index`
+/// aren't included.
+fn step_references(step: &PipelineStep) -> Vec<&str> {
+    fn collect<'a>(value: &'a serde_json::Value, out: &mut Vec<&'a str>) {
+        match value {
+            serde_json::Value::String(s) if !s.starts_with("$$") => push_reference(s, out),
+            serde_json::Value::Object(obj) => obj.values().for_each(|v| collect(v, out)),
+            serde_json::Value::Array(arr) => arr.iter().for_each(|v| collect(v, out)),
+            _ => {}
+        }
+    }
+    fn push_reference<'a>(reference: &'a str, out: &mut Vec<&'a str>) {
+        let Some(var) = reference.strip_prefix('$') else {
+            return;
+        };
+        let source = var.split('.').next().unwrap_or_default();
+        if !matches!(source, "" | "prev" | "item" | "index") && !out.contains(&source) {
+            out.push(source);
+        }
+    }
+
+    let mut out = Vec::new();
+    collect(&step.args, &mut out);
+    if let Some(ref foreach) = step.foreach {
+        push_reference(foreach.trim(), &mut out);
+    }
+    if let Some(ref condition) = step.condition {
+        for operand in condition_operands(condition).unwrap_or_default() {
+            push_reference(operand, &mut out);
+        }
+    }
+    out
+}
+
+// ==================== TRANSFORM ====================
+
+/// Built-in step that reshapes data in-process with a jq-like expression:
+/// `{tool: "transform", args: {expr: ".items | map(.name)", input: "$fetch"}}`.
+/// `input` defaults to `$prev`. Only available inside pipelines.
+pub const TRANSFORM_TOOL: &str = "transform";
+
+/// Apply a transform step's resolved args: run `expr` on `input`. One result
+/// is returned as is, several are collected into an array, none gives null.
+pub fn run_transform(args: &serde_json::Value) -> Result<serde_json::Value> {
+    let expr = args
+        .get("expr")
+        .and_then(|e| e.as_str())
+        .ok_or_else(|| anyhow::anyhow!("transform needs an 'expr' string argument"))?;
+    let input = args.get("input").unwrap_or(&serde_json::Value::Null);
+    let mut outputs = Transform::parse(expr)?.apply(input)?;
+    Ok(match outputs.len() {
+        0 => serde_json::Value::Null,
+        1 => outputs.remove(0),
+        _ => serde_json::Value::Array(outputs),
+    })
+}
+
+/// A parsed transform expression, a subset of jq:
+///
+/// - paths: `.`, `.field`, `."key with spaces"`, `.[0]`, `.[-1]`, `.["key"]`, `.[]`, `?`
+/// - `a | b` pipes, `a, b` several results, `(...)` grouping
+/// - `[...]` collects results into an array, `{name, id: .user_id, "k": ...}` builds objects
+/// - `==`, `!=`, `<`, `<=`, `>`, `>=`, `and`, `or`, and literals
+/// - `map(f)`, `select(f)`, `length`, `keys`, `not`
+#[derive(Debug, Clone)]
+pub struct Transform {
+    source: String,
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    kind: NodeKind,
+    /// Byte range of the node in the expression, for error messages
+    span: (usize, usize),
+}
+
+#[derive(Debug, Clone)]
+enum NodeKind {
+    Identity,
+    Field(Box<Node>, String),
+    Index(Box<Node>, Box<Node>),
+    Iterate(Box<Node>),
+    Optional(Box<Node>),
+    Literal(serde_json::Value),
+    Array(Option<Box<Node>>),
+    Object(Vec<(String, Node)>),
+    Pipe(Box<Node>, Box<Node>),
+    Comma(Box<Node>, Box<Node>),
+    Compare(Box<Node>, CompareOp, Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Call(Builtin, Option<Box<Node>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Builtin {
+    Map,
+    Select,
+    Length,
+    Keys,
+    Not,
+}
+
+impl Builtin {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "map" => Some(Self::Map),
+            "select" => Some(Self::Select),
+            "length" => Some(Self::Length),
+            "keys" => Some(Self::Keys),
+            "not" => Some(Self::Not),
+            _ => None,
+        }
+    }
+
+    /// Whether the builtin takes a filter argument in parentheses
+    fn takes_arg(self) -> bool {
+        matches!(self, Self::Map | Self::Select)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    Field(String),
+    Ident(String),
+    Literal(serde_json::Value),
+    Op(CompareOp),
+    Punct(char),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Dot => "'.'".to_string(),
+            Token::Field(name) => format!("'.{}'", name),
+            Token::Ident(name) => format!("'{}'", name),
+            Token::Literal(value) => format!("'{}'", value),
+            Token::Op(op) => format!("'{}'", op.symbol()),
+            Token::Punct(c) => format!("'{}'", c),
+        }
+    }
+}
+
+impl CompareOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+}
+
+/// Split an expression into tokens with their byte ranges
+fn tokenize(source: &str) -> Result<Vec<(Token, usize, usize)>> {
+    let bytes = source.as_bytes();
+    let is_ident_start = |b: u8| b.is_ascii_alphabetic() || b == b'_';
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    // End of the JSON string literal starting at `start` (just past the quote)
+    let string_end = |start: usize| -> Result<usize> {
+        let mut i = start + 1;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 2,
+                b'"' => return Ok(i + 1),
+                _ => i += 1,
+            }
+        }
+        Err(parse_error(source, start, "unterminated string"))
+    };
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let b = bytes[i];
+        let token = match b {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                i += 1;
+                continue;
+            }
+            b'.' if i + 1 < bytes.len() && is_ident_start(bytes[i + 1]) => {
+                i += 1;
+                while i < bytes.len() && is_ident(bytes[i]) {
+                    i += 1;
+                }
+                Token::Field(source[start + 1..i].to_string())
+            }
+            b'.' if i + 1 < bytes.len() && bytes[i + 1] == b'"' => {
+                i = string_end(i + 1)?;
+                let name: String = serde_json::from_str(&source[start + 1..i])
+                    .map_err(|_| parse_error(source, start + 1, "invalid string"))?;
+                Token::Field(name)
+            }
+            b'.' => {
+                i += 1;
+                Token::Dot
+            }
+            b'"' => {
+                i = string_end(i)?;
+                let value: String = serde_json::from_str(&source[start..i])
+                    .map_err(|_| parse_error(source, start, "invalid string"))?;
+                Token::Literal(serde_json::Value::String(value))
+            }
+            b'0'..=b'9' | b'-' => {
+                i += 1;
+                while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                let number: serde_json::Number = source[start..i]
+                    .parse()
+                    .map_err(|_| parse_error(source, start, "invalid number"))?;
+                Token::Literal(serde_json::Value::Number(number))
+            }
+            b'=' | b'!' | b'<' | b'>' => {
+                let double = bytes.get(i + 1) == Some(&b'=');
+                i += if double { 2 } else { 1 };
+                Token::Op(match (b, double) {
+                    (b'=', true) => CompareOp::Eq,
+                    (b'!', true) => CompareOp::Ne,
+                    (b'<', false) => CompareOp::Lt,
+                    (b'<', true) => CompareOp::Le,
+                    (b'>', false) => CompareOp::Gt,
+                    (b'>', true) => CompareOp::Ge,
+                    _ => return Err(parse_error(source, start, "expected '==' or '!='")),
+                })
+            }
+            b'(' | b')' | b'[' | b']' | b'{' | b'}' | b'|' | b',' | b':' | b'?' => {
+                i += 1;
+                Token::Punct(b as char)
+            }
+            _ if is_ident_start(b) => {
+                while i < bytes.len() && is_ident(bytes[i]) {
+                    i += 1;
+                }
+                match &source[start..i] {
+                    "true" => Token::Literal(serde_json::Value::Bool(true)),
+                    "false" => Token::Literal(serde_json::Value::Bool(false)),
+                    "null" => Token::Literal(serde_json::Value::Null),
+                    ident => Token::Ident(ident.to_string()),
+                }
+            }
+            _ => {
+                let c = source[start..].chars().next().unwrap_or_default();
+                return Err(parse_error(
+                    source,
+                    start,
+                    &format!("unexpected character '{}'", c),
+                ));
+            }
+        };
+        tokens.push((token, start, i));
+    }
+    Ok(tokens)
+}
+
+/// Parse error pointing at byte offset `at` of the expression
+fn parse_error(source: &str, at: usize, message: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Invalid transform expression '{}': {} at column {}",
+        source,
+        message,
+        source[..at].chars().count() + 1
+    )
+}
+
+/// Recursive-descent parser over the token list
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, usize, usize)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _, _)| t)
+    }
+
+    /// Byte offset of the next token (the end of the expression if none)
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.source.len(), |(_, start, _)| *start)
+    }
+
+    fn next(&mut self) -> Option<(Token, usize, usize)> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> anyhow::Error {
+        let found = match self.peek() {
+            Some(token) => format!("found {}", token.describe()),
+            None => "reached the end".to_string(),
+        };
+        parse_error(
+            self.source,
+            self.offset(),
+            &format!("expected {}, {}", expected, found),
+        )
+    }
+
+    /// Consume `c`, returning the end of its span
+    fn expect(&mut self, c: char) -> Result<usize> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Punct(p), _, end)) if *p == c => {
+                let end = *end;
+                self.pos += 1;
+                Ok(end)
+            }
+            _ => Err(self.unexpected(&format!("'{}'", c))),
+        }
+    }
+
+    fn node(kind: NodeKind, start: usize, end: usize) -> Node {
+        Node {
+            kind,
+            span: (start, end),
+        }
+    }
+
+    fn binary(left: Node, right: Node, kind: fn(Box<Node>, Box<Node>) -> NodeKind) -> Node {
+        let span = (left.span.0, right.span.1);
+        Node {
+            kind: kind(Box::new(left), Box::new(right)),
+            span,
+        }
+    }
+
+    fn parse_pipe(&mut self) -> Result<Node> {
+        let left = self.parse_comma()?;
+        if self.eat(&Token::Punct('|')) {
+            let right = self.parse_pipe()?;
+            return Ok(Self::binary(left, right, NodeKind::Pipe));
+        }
+        Ok(left)
+    }
+
+    fn parse_comma(&mut self) -> Result<Node> {
+        let mut left = self.parse_or()?;
+        while self.eat(&Token::Punct(',')) {
+            let right = self.parse_or()?;
+            left = Self::binary(left, right, NodeKind::Comma);
+        }
+        Ok(left)
+    }
+
+    fn parse_or(&mut self) -> Result<Node> {
+        let mut left = self.parse_and()?;
+        while self.eat(&Token::Ident("or".to_string())) {
+            let right = self.parse_and()?;
+            left = Self::binary(left, right, NodeKind::Or);
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Node> {
+        let mut left = self.parse_compare()?;
+        while self.eat(&Token::Ident("and".to_string())) {
+            let right = self.parse_compare()?;
+            left = Self::binary(left, right, NodeKind::And);
+        }
+        Ok(left)
+    }
+
+    fn parse_compare(&mut self) -> Result<Node> {
+        let left = self.parse_postfix()?;
+        if let Some(Token::Op(op)) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            let right = self.parse_postfix()?;
+            let span = (left.span.0, right.span.1);
+            return Ok(Node {
+                kind: NodeKind::Compare(Box::new(left), op, Box::new(right)),
+                span,
+            });
+        }
+        Ok(left)
+    }
+
+    /// A term followed by any number of `.field`, `[...]`, `[]` and `?`
+    fn parse_postfix(&mut self) -> Result<Node> {
+        let mut node = self.parse_primary()?;
+        loop {
+            let start = node.span.0;
+            match self.peek() {
+                Some(Token::Field(name)) => {
+                    let name = name.clone();
+                    let (_, _, end) = self.next().expect("peeked");
+                    node = Self::node(NodeKind::Field(Box::new(node), name), start, end);
+                }
+                Some(Token::Punct('[')) => node = self.parse_brackets(node)?,
+                // jq also accepts `.a.[0]`
+                Some(Token::Dot)
+                    if matches!(
+                        self.tokens.get(self.pos + 1),
+                        Some((Token::Punct('['), _, _))
+                    ) =>
+                {
+                    self.pos += 1;
+                    node = self.parse_brackets(node)?;
+                }
+                Some(Token::Punct('?')) => {
+                    let (_, _, end) = self.next().expect("peeked");
+                    node = Self::node(NodeKind::Optional(Box::new(node)), start, end);
+                }
+                _ => return Ok(node),
+            }
+        }
+    }
+
+    /// `[]` or `[index]` applied to `target`
+    fn parse_brackets(&mut self, target: Node) -> Result<Node> {
+        let start = target.span.0;
+        self.expect('[')?;
+        if self.peek() == Some(&Token::Punct(']')) {
+            let end = self.expect(']')?;
+            return Ok(Self::node(NodeKind::Iterate(Box::new(target)), start, end));
+        }
+        let index = self.parse_pipe()?;
+        let end = self.expect(']')?;
+        Ok(Self::node(
+            NodeKind::Index(Box::new(target), Box::new(index)),
+            start,
+            end,
+        ))
+    }
+
+    fn parse_primary(&mut self) -> Result<Node> {
+        let Some((token, start, end)) = self.tokens.get(self.pos).cloned() else {
+            return Err(self.unexpected("an expression"));
+        };
+        match token {
+            Token::Dot => {
+                self.pos += 1;
+                Ok(Self::node(NodeKind::Identity, start, end))
+            }
+            Token::Field(name) => {
+                self.pos += 1;
+                let identity = Self::node(NodeKind::Identity, start, start + 1);
+                Ok(Self::node(
+                    NodeKind::Field(Box::new(identity), name),
+                    start,
+                    end,
+                ))
+            }
+            Token::Literal(value) => {
+                self.pos += 1;
+                Ok(Self::node(NodeKind::Literal(value), start, end))
+            }
+            Token::Punct('(') => {
+                self.pos += 1;
+                let mut inner = self.parse_pipe()?;
+                inner.span = (start, self.expect(')')?);
+                Ok(inner)
+            }
+            Token::Punct('[') => {
+                self.pos += 1;
+                if self.peek() == Some(&Token::Punct(']')) {
+                    let end = self.expect(']')?;
+                    return Ok(Self::node(NodeKind::Array(None), start, end));
+                }
+                let inner = self.parse_pipe()?;
+                let end = self.expect(']')?;
+                Ok(Self::node(
+                    NodeKind::Array(Some(Box::new(inner))),
+                    start,
+                    end,
+                ))
+            }
+            Token::Punct('{') => {
+                self.pos += 1;
+                self.parse_object(start)
+            }
+            Token::Ident(name) => {
+                let Some(builtin) = Builtin::parse(&name) else {
+                    return Err(parse_error(
+                        self.source,
+                        start,
+                        &format!(
+                            "unknown function '{}' (supported: map, select, length, keys, not)",
+                            name
+                        ),
+                    ));
+                };
+                self.pos += 1;
+                if !builtin.takes_arg() {
+                    return Ok(Self::node(NodeKind::Call(builtin, None), start, end));
+                }
+                self.expect('(')?;
+                let arg = self.parse_pipe()?;
+                let end = self.expect(')')?;
+                Ok(Self::node(
+                    NodeKind::Call(builtin, Some(Box::new(arg))),
+                    start,
+                    end,
+                ))
+            }
+            _ => Err(self.unexpected("an expression")),
+        }
+    }
+
+    /// Object construction after the opening brace
+    fn parse_object(&mut self, start: usize) -> Result<Node> {
+        let mut fields = Vec::new();
+        if self.peek() == Some(&Token::Punct('}')) {
+            let end = self.expect('}')?;
+            return Ok(Self::node(NodeKind::Object(fields), start, end));
+        }
+        loop {
+            let (key, key_start, key_end) = match self.next() {
+                Some((Token::Ident(key), s, e)) => (key, s, e),
+                Some((Token::Literal(serde_json::Value::String(key)), s, e)) => (key, s, e),
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.unexpected("an object key"));
+                }
+            };
+            let value = if self.eat(&Token::Punct(':')) {
+                self.parse_or()?
+            } else {
+                // `{name}` is short for `{name: .name}`
+                let identity = Self::node(NodeKind::Identity, key_start, key_start);
+                Self::node(
+                    NodeKind::Field(Box::new(identity), key.clone()),
+                    key_start,
+                    key_end,
+                )
+            };
+            fields.push((key, value));
+            if self.eat(&Token::Punct(',')) {
+                continue;
+            }
+            let end = self.expect('}')?;
+            return Ok(Self::node(NodeKind::Object(fields), start, end));
+        }
+    }
+}
+
+/// jq truthiness: everything but false and null
+fn jq_truthy(value: &serde_json::Value) -> bool {
+    !matches!(
+        value,
+        serde_json::Value::Null | serde_json::Value::Bool(false)
+    )
+}
+
+/// jq ordering: null < false < true < numbers < strings < arrays < objects
+fn jq_order(a: &serde_json::Value, b: &serde_json::Value) -> std::cmp::Ordering {
+    use serde_json::Value;
+    use std::cmp::Ordering;
+
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+            x.partial_cmp(&y).unwrap_or(Ordering::Equal)
+        }
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| jq_order(x, y))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| x.len().cmp(&y.len())),
+        (Value::Object(x), Value::Object(y)) => {
+            let mut x_keys: Vec<&String> = x.keys().collect();
+            let mut y_keys: Vec<&String> = y.keys().collect();
+            x_keys.sort();
+            y_keys.sort();
+            x_keys.cmp(&y_keys).then_with(|| {
+                x_keys
+                    .iter()
+                    .map(|k| jq_order(&x[*k], &y[*k]))
+                    .find(|o| o.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+impl Transform {
+    /// Parse an expression, reporting the column of any syntax error
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            source,
+            tokens,
+            pos: 0,
+        };
+        let root = parser.parse_pipe()?;
+        if parser.peek().is_some() {
+            return Err(parser.unexpected("the end of the expression"));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    /// Run the expression on `input`, returning every result it produces
+    pub fn apply(&self, input: &serde_json::Value) -> Result<Vec<serde_json::Value>> {
+        self.eval(&self.root, input)
+    }
+
+    /// Runtime error naming the part of the expression that failed
+    fn fail(&self, node: &Node, message: String) -> anyhow::Error {
+        let part = self.source[node.span.0..node.span.1].trim();
+        anyhow::anyhow!("Transform failed at '{}': {}", part, message)
+    }
+
+    fn eval(&self, node: &Node, input: &serde_json::Value) -> Result<Vec<serde_json::Value>> {
+        use serde_json::Value;
+
+        match &node.kind {
+            NodeKind::Identity => Ok(vec![input.clone()]),
+            NodeKind::Literal(value) => Ok(vec![value.clone()]),
+            NodeKind::Field(target, name) => self
+                .eval(target, input)?
+                .into_iter()
+                .map(|value| match value {
+                    Value::Object(mut map) => Ok(map.remove(name).unwrap_or(Value::Null)),
+                    Value::Null => Ok(Value::Null),
+                    other => Err(self.fail(
+                        node,
+                        format!("cannot index {} with \"{}\"", json_type(&other), name),
+                    )),
+                })
+                .collect(),
+            NodeKind::Index(target, index) => {
+                let mut out = Vec::new();
+                for value in self.eval(target, input)? {
+                    for key in self.eval(index, input)? {
+                        out.push(self.index(node, &value, &key)?);
+                    }
+                }
+                Ok(out)
+            }
+            NodeKind::Iterate(target) => {
+                let mut out = Vec::new();
+                for value in self.eval(target, input)? {
+                    match value {
+                        Value::Array(items) => out.extend(items),
+                        Value::Object(map) => out.extend(map.into_iter().map(|(_, v)| v)),
+                        other => {
+                            return Err(self
+                                .fail(node, format!("cannot iterate over {}", json_type(&other))))
+                        }
+                    }
+                }
+                Ok(out)
+            }
+            NodeKind::Optional(inner) => Ok(self.eval(inner, input).unwrap_or_default()),
+            NodeKind::Array(None) => Ok(vec![Value::Array(Vec::new())]),
+            NodeKind::Array(Some(inner)) => Ok(vec![Value::Array(self.eval(inner, input)?)]),
+            NodeKind::Object(fields) => {
+                // Fields with several results multiply the objects, as in jq
+                let mut objects = vec![serde_json::Map::new()];
+                for (key, value) in fields {
+                    let values = self.eval(value, input)?;
+                    objects = objects
+                        .into_iter()
+                        .flat_map(|object| {
+                            values.iter().map(move |v| {
+                                let mut object = object.clone();
+                                object.insert(key.clone(), v.clone());
+                                object
+                            })
+                        })
+                        .collect();
+                }
+                Ok(objects.into_iter().map(Value::Object).collect())
+            }
+            NodeKind::Pipe(left, right) => {
+                let mut out = Vec::new();
+                for value in self.eval(left, input)? {
+                    out.extend(self.eval(right, &value)?);
+                }
+                Ok(out)
+            }
+            NodeKind::Comma(left, right) => {
+                let mut out = self.eval(left, input)?;
+                out.extend(self.eval(right, input)?);
+                Ok(out)
+            }
+            NodeKind::Compare(left, op, right) => {
+                let rights = self.eval(right, input)?;
+                let mut out = Vec::new();
+                for l in self.eval(left, input)? {
+                    for r in &rights {
+                        let ordering = jq_order(&l, r);
+                        out.push(Value::Bool(match op {
+                            CompareOp::Eq => ordering.is_eq(),
+                            CompareOp::Ne => ordering.is_ne(),
+                            CompareOp::Lt => ordering.is_lt(),
+                            CompareOp::Le => ordering.is_le(),
+                            CompareOp::Gt => ordering.is_gt(),
+                            CompareOp::Ge => ordering.is_ge(),
+                        }));
+                    }
+                }
+                Ok(out)
+            }
+            NodeKind::And(left, right) | NodeKind::Or(left, right) => {
+                let is_and = matches!(node.kind, NodeKind::And(..));
+                let mut out = Vec::new();
+                for l in self.eval(left, input)? {
+                    // `false and x` and `true or x` don't look at x
+                    if jq_truthy(&l) != is_and {
+                        out.push(Value::Bool(!is_and));
+                        continue;
+                    }
+                    for r in self.eval(right, input)? {
+                        out.push(Value::Bool(jq_truthy(&r)));
+                    }
+                }
+                Ok(out)
+            }
+            NodeKind::Call(builtin, arg) => self.call(node, *builtin, arg.as_deref(), input),
+        }
     }
 
-    fn resolve_condition_value(
-        value: &str,
-        input: &serde_json::Value,
-        step_results: &HashMap<String, serde_json::Value>,
-        prev_output: Option<&serde_json::Value>,
+    /// `value[key]`: array by integer (negative counts from the end), object by string
+    fn index(
+        &self,
+        node: &Node,
+        value: &serde_json::Value,
+        key: &serde_json::Value,
     ) -> Result<serde_json::Value> {
-        let value = value.trim();
-
-        // Variable reference
-        if value.starts_with('$') {
-            return Self::resolve_variable(value, input, step_results, prev_output);
-        }
+        use serde_json::Value;
 
-        // Boolean literals
-        if value == "true" {
-            return Ok(serde_json::Value::Bool(true));
-        }
-        if value == "false" {
-            return Ok(serde_json::Value::Bool(false));
+        match (value, key) {
+            (Value::Null, _) => Ok(Value::Null),
+            (Value::Object(map), Value::String(k)) => {
+                Ok(map.get(k).cloned().unwrap_or(Value::Null))
+            }
+            (Value::Array(items), Value::Number(n)) => {
+                let i = n.as_i64().ok_or_else(|| {
+                    self.fail(node, format!("array index {} isn't an integer", n))
+                })?;
+                let i = if i < 0 { items.len() as i64 + i } else { i };
+                Ok(usize::try_from(i)
+                    .ok()
+                    .and_then(|i| items.get(i))
+                    .cloned()
+                    .unwrap_or(Value::Null))
+            }
+            (value, key) => Err(self.fail(
+                node,
+                format!("cannot index {} with {}", json_type(value), json_type(key)),
+            )),
         }
+    }
 
-        // Null
-        if value == "null" {
-            return Ok(serde_json::Value::Null);
-        }
+    fn call(
+        &self,
+        node: &Node,
+        builtin: Builtin,
+        arg: Option<&Node>,
+        input: &serde_json::Value,
+    ) -> Result<Vec<serde_json::Value>> {
+        use serde_json::Value;
 
-        // Number
-        if let Ok(n) = value.parse::<i64>() {
-            return Ok(serde_json::Value::Number(n.into()));
-        }
-        if let Ok(n) = value.parse::<f64>() {
-            return Ok(serde_json::json!(n));
+        match (builtin, arg) {
+            (Builtin::Map, Some(f)) => match input {
+                Value::Array(items) => {
+                    let mut out = Vec::new();
+                    for item in items {
+                        out.extend(self.eval(f, item)?);
+                    }
+                    Ok(vec![Value::Array(out)])
+                }
+                other => Err(self.fail(
+                    node,
+                    format!("map needs an array, got {}", json_type(other)),
+                )),
+            },
+            (Builtin::Select, Some(f)) => Ok(self
+                .eval(f, input)?
+                .iter()
+                .filter(|v| jq_truthy(v))
+                .map(|_| input.clone())
+                .collect()),
+            (Builtin::Length, _) => match input {
+                Value::Null => Ok(vec![Value::from(0)]),
+                Value::String(s) => Ok(vec![Value::from(s.chars().count())]),
+                Value::Array(items) => Ok(vec![Value::from(items.len())]),
+                Value::Object(map) => Ok(vec![Value::from(map.len())]),
+                Value::Number(n) => Ok(vec![Value::from(n.as_f64().unwrap_or(0.0).abs())]),
+                Value::Bool(_) => Err(self.fail(node, "a boolean has no length".to_string())),
+            },
+            (Builtin::Keys, _) => match input {
+                Value::Object(map) => {
+                    let mut keys: Vec<&String> = map.keys().collect();
+                    keys.sort();
+                    Ok(vec![Value::from(
+                        keys.into_iter().cloned().collect::<Vec<_>>(),
+                    )])
+                }
+                Value::Array(items) => Ok(vec![Value::from((0..items.len()).collect::<Vec<_>>())]),
+                other => Err(self.fail(node, format!("{} has no keys", json_type(other)))),
+            },
+            (Builtin::Not, _) => Ok(vec![Value::Bool(!jq_truthy(input))]),
+            (_, None) => Err(self.fail(node, "missing argument".to_string())),
         }
-
-        // String (with or without quotes)
-        let s = value.trim_matches('"').trim_matches('\'');
-        Ok(serde_json::Value::String(s.to_string()))
     }
 }
 
@@ -231,6 +2031,36 @@ mod tests {
         assert_eq!(resolved, serde_json::json!("http://example.com"));
     }
 
+    #[test]
+    fn test_resolve_args_escaped_dollar() {
+        let input = serde_json::json!({"price": 5});
+        let args = serde_json::json!({
+            "label": "$$5.00",
+            "pattern": ["$$end", "$$$x", "a$b"],
+            "price": "$input.price"
+        });
+
+        let resolved =
+            PipelineExecutor::resolve_args(&args, &input, &HashMap::new(), None).unwrap();
+        assert_eq!(
+            resolved,
+            serde_json::json!({
+                "label": "$5.00",
+                "pattern": ["$end", "$$x", "a$b"],
+                "price": 5
+            })
+        );
+
+        // Unescaped, the same strings are references and fail to resolve
+        assert!(PipelineExecutor::resolve_args(
+            &serde_json::json!("$5.00"),
+            &input,
+            &HashMap::new(),
+            None
+        )
+        .is_err());
+    }
+
     #[test]
     fn test_resolve_args_object() {
         let input = serde_json::json!({"text": "hello world"});
@@ -279,6 +2109,30 @@ mod tests {
         assert!(result);
     }
 
+    #[test]
+    fn test_evaluate_condition_missing_field_is_null() {
+        let input = serde_json::json!({});
+        let step_results = HashMap::new();
+        let prev = serde_json::json!({"result": "ok"});
+
+        let missing = |condition| {
+            PipelineExecutor::evaluate_condition(condition, &input, &step_results, Some(&prev))
+                .unwrap()
+        };
+        assert!(!missing("$prev.exit_code"));
+        assert!(missing("$prev.exit_code == null"));
+        assert!(
+            PipelineExecutor::evaluate_condition("$other.x", &input, &step_results, None).is_err()
+        );
+        assert!(PipelineExecutor::resolve_args(
+            &serde_json::json!("$prev.exit_code"),
+            &input,
+            &step_results,
+            Some(&prev)
+        )
+        .is_err());
+    }
+
     #[test]
     fn test_resolve_deeply_nested_variable() {
         let input = serde_json::json!({});
@@ -341,6 +2195,47 @@ mod tests {
         assert_eq!(resolved["static_value"], "unchanged");
     }
 
+    #[test]
+    fn test_resolve_args_deeply_nested() {
+        let input = serde_json::json!({"field": "status", "limit": 10});
+        let mut step_results = HashMap::new();
+        step_results.insert("fetch".to_string(), serde_json::json!({"ids": [1, 2]}));
+        let prev = serde_json::json!({"count": 3});
+
+        let args = serde_json::json!({
+            "filters": [
+                {"field": "$input.field", "values": ["$fetch.ids", "open"]},
+                {"field": "$input.field", "not": {"in": ["$prev.count", "$$literal"]}}
+            ],
+            "page": {"size": {"max": "$input.limit"}},
+            "matrix": [[["$prev.count"]], []],
+            "n": 5,
+            "flag": true,
+            "none": null
+        });
+
+        let resolved =
+            PipelineExecutor::resolve_args(&args, &input, &step_results, Some(&prev)).unwrap();
+        assert_eq!(
+            resolved,
+            serde_json::json!({
+                "filters": [
+                    {"field": "status", "values": [[1, 2], "open"]},
+                    {"field": "status", "not": {"in": [3, "$literal"]}}
+                ],
+                "page": {"size": {"max": 10}},
+                "matrix": [[[3]], []],
+                "n": 5,
+                "flag": true,
+                "none": null
+            })
+        );
+
+        // A bad reference deep inside still fails the step
+        let args = serde_json::json!({"filters": [{"field": "$input.missing"}]});
+        assert!(PipelineExecutor::resolve_args(&args, &input, &step_results, Some(&prev)).is_err());
+    }
+
     #[test]
     fn test_resolve_args_array_with_variables() {
         let input = serde_json::json!({"items": ["x", "y", "z"]});
@@ -388,4 +2283,553 @@ mod tests {
         .unwrap();
         assert!(result);
     }
+
+    #[test]
+    fn test_resolve_foreach() {
+        let input = serde_json::json!({"items": [1, 2], "name": "x"});
+        let step_results = HashMap::new();
+
+        let items =
+            PipelineExecutor::resolve_foreach("$input.items", &input, &step_results, None).unwrap();
+        assert_eq!(items, vec![serde_json::json!(1), serde_json::json!(2)]);
+
+        let err = PipelineExecutor::resolve_foreach("$input.name", &input, &step_results, None)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "foreach '$input.name' must resolve to an array, got a string"
+        );
+
+        let scope = PipelineExecutor::item_scope(&step_results, 1, serde_json::json!({"id": 7}));
+        let args = PipelineExecutor::resolve_args(
+            &serde_json::json!({"id": "$item.id", "position": "$index"}),
+            &input,
+            &scope,
+            None,
+        )
+        .unwrap();
+        assert_eq!(args, serde_json::json!({"id": 7, "position": 1}));
+    }
+
+    #[test]
+    fn test_collect_foreach() {
+        let outcome = |result: Result<serde_json::Value>| StepAttempts {
+            result,
+            attempts: 1,
+            attempt_errors: Vec::new(),
+            cached: false,
+        };
+
+        let empty = collect_foreach(Vec::new());
+        assert_eq!(empty.result.unwrap(), serde_json::json!([]));
+        assert_eq!(empty.attempts, 0);
+
+        let failed = collect_foreach(vec![
+            outcome(Ok(serde_json::json!(1))),
+            outcome(Err(anyhow::anyhow!("boom"))),
+        ]);
+        assert_eq!(failed.result.unwrap_err().to_string(), "Item 1: boom");
+        assert_eq!(failed.attempts, 2);
+    }
+
+    fn step_result(index: usize, output: serde_json::Value, error: Option<&str>) -> StepResult {
+        StepResult {
+            step_index: index,
+            step_name: None,
+            tool: "t".to_string(),
+            success: error.is_none(),
+            output,
+            error: error.map(str::to_string),
+            duration_ms: 1,
+            attempts: 1,
+            attempt_errors: Vec::new(),
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn test_run_markdown_truncates_unless_verbose() {
+        let long = "é".repeat(300);
+        let run = PipelineRun {
+            pipeline: "p".to_string(),
+            success: false,
+            cancelled: false,
+            duration_ms: 5,
+            steps: vec![
+                step_result(0, serde_json::json!(long), None),
+                step_result(1, serde_json::json!(null), Some("boom")),
+            ],
+            on_error: vec![step_result(0, serde_json::json!("handled"), None)],
+            debug_run: None,
+        };
+
+        let summary = run.to_markdown(false);
+        assert!(summary.starts_with("## ❌ Pipeline 'p' Failed"));
+        assert!(summary.contains("**✅ step_1** (t)"));
+        assert!(summary.contains("  Error: boom"));
+        assert!(summary.contains("### On Error:"));
+        assert!(!summary.contains(&long));
+        assert!(summary.contains("..."));
+
+        assert!(run.to_markdown(true).contains(&long));
+    }
+
+    #[test]
+    fn test_truncate_chars_respects_char_boundaries() {
+        assert_eq!(truncate_chars("héllo", 2), Some("hé"));
+        assert_eq!(truncate_chars("héllo", 5), None);
+        assert_eq!(truncate_chars("", 0), None);
+        assert_eq!(truncate_chars("🎉🎉🎉", 1), Some("🎉"));
+    }
+
+    /// Regression: a step returning emoji around the cut used to panic
+    #[test]
+    fn test_step_markdown_truncates_emoji_output() {
+        let emoji = "🎉".repeat(300);
+        let result = step_result(0, serde_json::json!(emoji), None);
+
+        let summary = result.to_markdown(false);
+        // The JSON string's opening quote counts as the first character
+        let expected = format!("  Output: \"{}...", "🎉".repeat(SUMMARY_OUTPUT_CHARS - 1));
+        assert!(summary.contains(&expected));
+        assert!(!summary.contains(&emoji));
+        assert!(result.to_markdown(true).contains(&emoji));
+    }
+
+    fn dry_step(name: Option<&str>, tool: &str, args: serde_json::Value) -> PipelineStep {
+        PipelineStep {
+            name: name.map(str::to_string),
+            tool: tool.to_string(),
+            args,
+            continue_on_error: false,
+            condition: None,
+            timeout_secs: None,
+            retries: 0,
+            retry_delay_ms: 0,
+            foreach: None,
+            max_concurrency: None,
+            cache: None,
+        }
+    }
+
+    /// "fetch" declares {status, body: {items: [{id}]}}; "emit" has no schema
+    fn schemas(tool: &str) -> Result<Option<serde_json::Value>> {
+        match tool {
+            "fetch" => Ok(Some(serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "status": {"type": "integer"},
+                    "body": {
+                        "type": "object",
+                        "properties": {
+                            "items": {
+                                "type": "array",
+                                "items": {"type": "object", "properties": {"id": {"type": "string"}}}
+                            }
+                        }
+                    }
+                }
+            }))),
+            "emit" => Ok(None),
+            other => anyhow::bail!("unknown tool '{}'", other),
+        }
+    }
+
+    fn check(steps: &[PipelineStep], input: Option<&serde_json::Value>) -> Vec<String> {
+        dry_run(steps, &[], input, &HashMap::new(), schemas)
+    }
+
+    #[test]
+    fn test_dry_run_accepts_schema_paths() {
+        let mut each = dry_step(Some("each"), "emit", serde_json::json!({"id": "$item.id"}));
+        each.foreach = Some("$f.body.items".to_string());
+        let steps = vec![
+            dry_step(Some("f"), "fetch", serde_json::json!({"url": "$input.url"})),
+            dry_step(None, "emit", serde_json::json!({"code": "$prev.status"})),
+            each,
+        ];
+        let input = serde_json::json!({"url": "https://example.com"});
+        assert!(check(&steps, Some(&input)).is_empty());
+    }
+
+    #[test]
+    fn test_dry_run_reports_unresolved_references() {
+        let steps = vec![
+            dry_step(
+                Some("f"),
+                "fetch",
+                serde_json::json!({"url": "$input.link"}),
+            ),
+            dry_step(None, "emit", serde_json::json!({"text": "$f.text"})),
+            dry_step(
+                None,
+                "emit",
+                serde_json::json!({"x": "$later.value", "y": "$nope"}),
+            ),
+            dry_step(Some("later"), "emit", serde_json::json!({})),
+            dry_step(None, "missing_tool", serde_json::json!({})),
+        ];
+        let input = serde_json::json!({"url": "https://example.com"});
+        let issues = check(&steps, Some(&input));
+        assert_eq!(issues.len(), 5, "{:?}", issues);
+        assert!(issues[0].starts_with("Step 1: $input.link: 'link' isn't in"));
+        assert!(issues[1].contains("'text' isn't in the output of 'f' (available: body, status)"));
+        assert!(issues[2].contains("step 'later' hasn't run yet"));
+        assert!(issues[3].contains("unknown step 'nope'"));
+        assert_eq!(issues[4], "Step 5: unknown tool 'missing_tool'");
+    }
+
+    #[test]
+    fn test_dry_run_unknown_shapes_and_samples() {
+        let steps = vec![
+            dry_step(Some("e"), "emit", serde_json::json!({})),
+            dry_step(None, "emit", serde_json::json!({"v": "$e.anything.deep"})),
+        ];
+        // Nothing is known about emit's output or the input
+        assert!(check(&steps, None).is_empty());
+
+        // A sample output makes the reference checkable
+        let samples = HashMap::from([("e".to_string(), serde_json::json!({"other": 1}))]);
+        let issues = dry_run(&steps, &[], None, &samples, schemas);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].contains("'anything' isn't in the output of 'e'"));
+    }
+
+    #[test]
+    fn test_dry_run_conditions_foreach_and_on_error() {
+        let mut bad_condition = dry_step(None, "emit", serde_json::json!({}));
+        bad_condition.condition = Some("$prev.status == 1 == 2".to_string());
+        let mut unreachable = dry_step(None, "emit", serde_json::json!({}));
+        unreachable.condition = Some("1 == 2".to_string());
+        let mut missing_field_ok = dry_step(None, "emit", serde_json::json!({}));
+        missing_field_ok.condition = Some("$f.absent".to_string());
+        let mut not_array = dry_step(None, "emit", serde_json::json!({"v": "$item"}));
+        not_array.foreach = Some("$f.status".to_string());
+        let steps = vec![
+            dry_step(Some("f"), "fetch", serde_json::json!({})),
+            bad_condition,
+            unreachable,
+            missing_field_ok,
+            not_array,
+        ];
+        let on_error = vec![dry_step(
+            None,
+            "emit",
+            serde_json::json!({"m": "$error.message", "s": "$f.status", "bad": "$error.code"}),
+        )];
+
+        let issues = dry_run(&steps, &on_error, None, &HashMap::new(), schemas);
+        assert_eq!(issues.len(), 4, "{:?}", issues);
+        assert!(issues[0].starts_with("Step 2: invalid condition"));
+        assert!(issues[1].starts_with("Step 3: condition '1 == 2' is never true"));
+        assert!(issues[2].starts_with("Step 5: foreach $f.status is of type integer"));
+        assert!(issues[3].starts_with("on_error step 1: $error.code"));
+    }
+
+    #[test]
+    fn test_enter_pipeline_detects_cycles_and_depth() {
+        let chain = enter_pipeline(&[], "a").unwrap();
+        let chain = enter_pipeline(&chain, "b").unwrap();
+        assert_eq!(chain, ["a", "b"]);
+
+        let err = enter_pipeline(&chain, "a").unwrap_err();
+        assert_eq!(err.to_string(), "Pipeline cycle detected: a → b → a");
+
+        let deep: Vec<String> = (0..MAX_PIPELINE_DEPTH).map(|i| format!("p{}", i)).collect();
+        let err = enter_pipeline(&deep, "leaf").unwrap_err();
+        assert!(err.to_string().contains("maximum depth of 8"));
+    }
+
+    #[test]
+    fn test_run_into_result() {
+        let run = |success: bool, steps: Vec<StepResult>| PipelineRun {
+            pipeline: "child".to_string(),
+            success,
+            cancelled: false,
+            duration_ms: 0,
+            steps,
+            on_error: Vec::new(),
+            debug_run: None,
+        };
+
+        let ok = run(
+            true,
+            vec![step_result(0, serde_json::json!({"n": 2}), None)],
+        );
+        assert_eq!(ok.into_result().unwrap(), serde_json::json!({"n": 2}));
+        assert_eq!(
+            run(true, vec![]).into_result().unwrap(),
+            serde_json::Value::Null
+        );
+
+        let failed = run(
+            false,
+            vec![
+                step_result(0, serde_json::json!(1), None),
+                step_result(1, serde_json::json!(null), Some("boom")),
+            ],
+        );
+        assert_eq!(
+            failed.into_result().unwrap_err().to_string(),
+            "Pipeline 'child' failed at 't': boom"
+        );
+
+        let mut cancelled = run(false, vec![]);
+        cancelled.cancelled = true;
+        assert!(cancelled.into_result().unwrap_err().is::<Cancelled>());
+    }
+
+    fn transform(expr: &str, input: serde_json::Value) -> serde_json::Value {
+        run_transform(&serde_json::json!({"expr": expr, "input": input})).unwrap()
+    }
+
+    fn transform_err(expr: &str, input: serde_json::Value) -> String {
+        run_transform(&serde_json::json!({"expr": expr, "input": input}))
+            .unwrap_err()
+            .to_string()
+    }
+
+    fn shop() -> serde_json::Value {
+        serde_json::json!({
+            "user": {"name": "Ada", "id": 7},
+            "items": [
+                {"name": "pen", "price": 2},
+                {"name": "lamp", "price": 30},
+                {"name": "desk", "price": 120}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_transform_projection() {
+        assert_eq!(transform(".user.name", shop()), serde_json::json!("Ada"));
+        assert_eq!(
+            transform(".items[0].name", shop()),
+            serde_json::json!("pen")
+        );
+        assert_eq!(
+            transform(".items[-1].price", shop()),
+            serde_json::json!(120)
+        );
+        assert_eq!(
+            transform(".missing.deeper", shop()),
+            serde_json::Value::Null
+        );
+        assert_eq!(
+            transform(
+                "{name: .user.name, id: .user.id, count: (.items | length)}",
+                shop()
+            ),
+            serde_json::json!({"name": "Ada", "id": 7, "count": 3})
+        );
+        assert_eq!(
+            transform(".user | {name, \"user id\": .id}", shop()),
+            serde_json::json!({"name": "Ada", "user id": 7})
+        );
+        assert_eq!(
+            transform(".user | keys", shop()),
+            serde_json::json!(["id", "name"])
+        );
+    }
+
+    #[test]
+    fn test_transform_maps_over_arrays() {
+        assert_eq!(
+            transform(".items | map(.name)", shop()),
+            serde_json::json!(["pen", "lamp", "desk"])
+        );
+        assert_eq!(
+            transform("[.items[] | .price]", shop()),
+            serde_json::json!([2, 30, 120])
+        );
+        // Several results without [...] are collected into an array
+        assert_eq!(
+            transform(".items[].name", shop()),
+            serde_json::json!(["pen", "lamp", "desk"])
+        );
+        assert_eq!(
+            transform(".items | map({item: .name, cheap: (.price < 10)})", shop()),
+            serde_json::json!([
+                {"item": "pen", "cheap": true},
+                {"item": "lamp", "cheap": false},
+                {"item": "desk", "cheap": false}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_transform_filters() {
+        assert_eq!(
+            transform(".items | map(select(.price >= 30)) | map(.name)", shop()),
+            serde_json::json!(["lamp", "desk"])
+        );
+        assert_eq!(
+            transform(
+                "[.items[] | select(.name == \"pen\" or .price > 100) | .name]",
+                shop()
+            ),
+            serde_json::json!(["pen", "desk"])
+        );
+        assert_eq!(
+            transform(
+                "[.items[] | select(.price > 10 and (.name != \"desk\"))]",
+                shop()
+            ),
+            serde_json::json!([{"name": "lamp", "price": 30}])
+        );
+        assert_eq!(
+            transform(".items[] | select(.price > 1000)", shop()),
+            serde_json::Value::Null
+        );
+        assert_eq!(
+            transform("[.items[] | .price > 10 | not]", shop()),
+            serde_json::json!([true, false, false])
+        );
+    }
+
+    #[test]
+    fn test_transform_errors_point_at_the_problem() {
+        let err = transform_err(".items | map(.name", shop());
+        assert!(
+            err.contains("expected ')', reached the end at column 19"),
+            "{}",
+            err
+        );
+
+        let err = transform_err(".items | mapp(.name)", shop());
+        assert!(err.contains("unknown function 'mapp'"), "{}", err);
+        assert!(err.contains("column 10"), "{}", err);
+
+        let err = transform_err(".items.name", shop());
+        assert_eq!(
+            err,
+            "Transform failed at '.items.name': cannot index an array with \"name\""
+        );
+
+        let err = transform_err(".user | map(.id)", shop());
+        assert_eq!(
+            err,
+            "Transform failed at 'map(.id)': map needs an array, got an object"
+        );
+
+        assert_eq!(transform(".items.name?", shop()), serde_json::Value::Null);
+        assert!(run_transform(&serde_json::json!({"input": 1}))
+            .unwrap_err()
+            .to_string()
+            .contains("'expr'"));
+    }
+
+    #[test]
+    fn test_transform_steps_read_prev_by_default() {
+        let step = dry_step(None, TRANSFORM_TOOL, serde_json::json!({"expr": "."}));
+        assert_eq!(
+            PipelineExecutor::step_args(&step).into_owned(),
+            serde_json::json!({"expr": ".", "input": "$prev"})
+        );
+        let step = dry_step(
+            None,
+            TRANSFORM_TOOL,
+            serde_json::json!({"expr": ".", "input": "$input"}),
+        );
+        assert_eq!(
+            PipelineExecutor::step_args(&step)["input"],
+            serde_json::json!("$input")
+        );
+    }
+
+    #[test]
+    fn test_dry_run_checks_transform_expressions() {
+        let steps = vec![
+            dry_step(Some("fetch"), "fetch", serde_json::json!({})),
+            dry_step(
+                None,
+                TRANSFORM_TOOL,
+                serde_json::json!({"expr": ".items | map("}),
+            ),
+            dry_step(None, TRANSFORM_TOOL, serde_json::json!({"input": "$fetch"})),
+        ];
+        let issues = dry_run(&steps, &[], None, &HashMap::new(), |_| Ok(None));
+        assert_eq!(issues.len(), 2, "{:?}", issues);
+        assert!(issues[0].starts_with("Step 2: Invalid transform expression"));
+        assert_eq!(
+            issues[1],
+            "Step 3: transform needs an 'expr' string argument"
+        );
+    }
+
+    fn diagram_step(name: Option<&str>, tool: &str, args: serde_json::Value) -> PipelineStep {
+        serde_json::from_value(serde_json::json!({"tool": tool, "args": args}))
+            .map(|step: PipelineStep| PipelineStep {
+                name: name.map(String::from),
+                ..step
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        let fetch = diagram_step(
+            Some("fetch"),
+            "http_get",
+            serde_json::json!({"url": "$input.url"}),
+        );
+        let parse = PipelineStep {
+            condition: Some("$fetch.ok == true".to_string()),
+            ..diagram_step(None, "parse", serde_json::json!({"body": "$prev.body"}))
+        };
+        let save = PipelineStep {
+            continue_on_error: true,
+            ..diagram_step(
+                Some("save"),
+                "store",
+                serde_json::json!({"items": "$prev.items", "source": "$fetch.url", "key": "$input.key"}),
+            )
+        };
+
+        assert_eq!(
+            to_mermaid(&[fetch, parse, save], &[]),
+            r#"flowchart TD
+    input(["input"])
+    s1["fetch<br/>http_get"]
+    input --> s1
+    s2["step 2<br/>parse"]
+    s1 -.->|"if $fetch.ok == true"| s2
+    s3["save<br/>store"]
+    s2 --> s3
+    input -->|"$input"| s3
+    s1 -->|"$fetch"| s3
+    subgraph legend ["Legend"]
+        legend_may_fail["continue_on_error"]
+    end
+    classDef mayFail stroke-dasharray: 5 5
+    class s3,legend_may_fail mayFail
+"#
+        );
+    }
+
+    #[test]
+    fn test_to_mermaid_on_error_and_foreach() {
+        let each = PipelineStep {
+            foreach: Some("$input.urls".to_string()),
+            ..diagram_step(
+                Some("each"),
+                "http_get",
+                serde_json::json!({"url": "$item"}),
+            )
+        };
+        let notify = diagram_step(None, "notify", serde_json::json!({"text": "say \"hi\""}));
+
+        let diagram = to_mermaid(&[each], &[notify.clone(), notify]);
+        assert!(diagram.contains(r#"s1["each<br/>http_get<br/>foreach $input.urls"]"#));
+        assert!(diagram.contains("input --> s1\n"));
+        assert!(!diagram.contains("|\"$input\"|"));
+        assert!(diagram.contains(
+            r#"    subgraph on_error ["on_error"]
+        e1["step 1<br/>notify"]
+        e2["step 2<br/>notify"]
+        e1 --> e2
+    end"#
+        ));
+        assert!(!diagram.contains("Legend"));
+    }
 }
@@ -1,10 +1,12 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 
+use crate::builder::{BuildOptions, Builder};
+
 /// Tool execution type
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 #[serde(rename_all = "lowercase")]
@@ -111,6 +113,144 @@ pub struct PipelineStep {
     /// Condition to check before running (e.g., "$prev.success == true")
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub condition: Option<String>,
+    /// Abandon an attempt after this many seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Extra attempts after a failure or timeout
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retries: u32,
+    /// Pause between attempts, in milliseconds
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retry_delay_ms: u64,
+    /// Run the tool once per element of this array (e.g. "$fetch.items"),
+    /// with the element as `$item` and its position as `$index`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreach: Option<String>,
+    /// For foreach steps: how many elements run at once (default: 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<usize>,
+    /// Reuse this step's output when it runs again with the same tool and
+    /// resolved args within the TTL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<StepCache>,
+}
+
+/// Output caching for a pipeline step
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StepCache {
+    /// How long a cached output stays fresh
+    pub ttl_seconds: u64,
+}
+
+/// Most extra attempts a pipeline step may ask for
+pub const MAX_STEP_RETRIES: u32 = 10;
+
+/// Longest pause between attempts of a pipeline step (1 minute)
+pub const MAX_STEP_RETRY_DELAY_MS: u64 = 60_000;
+
+impl PipelineStep {
+    /// Check the timeout and retry settings are usable
+    pub fn validate(&self) -> Result<()> {
+        if self.timeout_secs == Some(0) {
+            anyhow::bail!("timeout_secs must be at least 1");
+        }
+        if self.retries > MAX_STEP_RETRIES {
+            anyhow::bail!("retries must be at most {}", MAX_STEP_RETRIES);
+        }
+        if self.retry_delay_ms > MAX_STEP_RETRY_DELAY_MS {
+            anyhow::bail!("retry_delay_ms must be at most {}", MAX_STEP_RETRY_DELAY_MS);
+        }
+        if let Some(foreach) = &self.foreach {
+            if !foreach.trim_start().starts_with('$') {
+                anyhow::bail!("foreach must be a variable reference like \"$prev.items\"");
+            }
+        }
+        match self.max_concurrency {
+            Some(_) if self.foreach.is_none() => {
+                anyhow::bail!("max_concurrency only applies to foreach steps")
+            }
+            Some(0) => anyhow::bail!("max_concurrency must be at least 1"),
+            _ => {}
+        }
+        if self.cache.is_some_and(|c| c.ttl_seconds == 0) {
+            anyhow::bail!("cache.ttl_seconds must be at least 1");
+        }
+        if let Some(name @ ("item" | "index" | "error")) = self.name.as_deref() {
+            anyhow::bail!("'{}' is a reserved variable and can't name a step", name);
+        }
+        Ok(())
+    }
+}
+
+/// Call rate limit for a tool: at most `max` calls per `per_secs` seconds
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max: u32,
+    pub per_secs: u64,
+}
+
+impl RateLimit {
+    /// Parse a `{"max": N, "per_secs": S}` object, rejecting zero values
+    pub fn from_value(value: serde_json::Value) -> Result<Self> {
+        let limit: Self = serde_json::from_value(value).map_err(|e| {
+            anyhow::anyhow!("Invalid rate_limit (expected {{max, per_secs}}): {}", e)
+        })?;
+        if limit.max == 0 || limit.per_secs == 0 {
+            anyhow::bail!("Invalid rate_limit: max and per_secs must be at least 1");
+        }
+        Ok(limit)
+    }
+}
+
+/// Where an imported tool came from, recorded at import so `update_tool`
+/// can fetch it again
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportOrigin {
+    /// The source as given to import_tool (git URL with optional #ref,
+    /// gist:ID, package, directory or raw file URL)
+    pub source: String,
+    /// Git commit the source was at, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// When the tool was imported or last updated (ISO 8601)
+    pub imported_at: String,
+    /// [`tool_content_hash`] of the tool as imported, to tell local edits apart
+    pub content_hash: String,
+}
+
+impl ImportOrigin {
+    /// Whether the source can still be fetched: a local directory or package
+    /// must exist, anything remote is assumed to
+    pub fn is_reachable(&self) -> bool {
+        let local = self.source.strip_prefix("file://").unwrap_or(&self.source);
+        !Path::new(local).is_absolute() || Path::new(local).exists()
+    }
+}
+
+/// How a script tool receives its arguments
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArgMode {
+    /// A JSON-RPC 2.0 request on stdin (supports memory, logging, progress, ...)
+    #[default]
+    Stdin,
+    /// `--key value` command-line flags; stdout is the result
+    Argv,
+}
+
+impl ArgMode {
+    /// Parse "stdin" or "argv" (case-insensitive)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "stdin" => Some(Self::Stdin),
+            "argv" => Some(Self::Argv),
+            _ => None,
+        }
+    }
+
+    fn is_stdin(&self) -> bool {
+        *self == Self::Stdin
+    }
 }
 
 /// Configuration for an external MCP server
@@ -152,15 +292,51 @@ pub struct ToolManifest {
     /// Behavior hints for clients
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub annotations: Option<ToolAnnotations>,
-    /// Script dependencies (pip/npm packages)
+    /// For script tools: how arguments are passed (stdin JSON-RPC or argv flags)
+    #[serde(default, skip_serializing_if = "ArgMode::is_stdin")]
+    pub arg_mode: ArgMode,
+    /// For script tools: collect every JSON-RPC result line into an array
+    /// instead of keeping only the last one
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub ndjson: bool,
+    /// For script tools: directory the script runs in, relative to the
+    /// first workspace root (or absolute). Unset runs in the server's
+    /// working directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    /// For script tools: environment variables set on top of the defaults,
+    /// e.g. {"PATH": "/opt/cli/bin:${PATH}"}; `${VAR}` expands from the
+    /// server's environment
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Script dependencies (pip/npm/gem packages)
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub dependencies: Vec<String>,
     /// WASM/Rust dependencies (crates) - format: "name@version" or "name@version[feat1,feat2]"
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub wasm_dependencies: Vec<String>,
+    /// WASM build settings (default features, release profile), reused on recompile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_options: Option<BuildOptions>,
+    /// For WASM tools: compute budget per call, in wasmtime fuel units
+    /// (roughly one per instruction). Unset means unlimited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm_fuel: Option<u64>,
     /// For pipeline tools: the steps to execute
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub pipeline_steps: Vec<PipelineStep>,
+    /// For pipeline tools: steps run when a step fails without continue_on_error
+    /// (the failure is available as `$error.step` and `$error.message`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_error: Vec<PipelineStep>,
+    /// For pipeline tools: don't send per-step progress notifications or
+    /// forward step logs to the client
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub quiet: bool,
+    /// For pipeline tools: run automatically while the server is up, on an
+    /// interval ("every 5m") or a cron expression (see [`crate::schedule`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
     /// For external MCP servers: connection configuration
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub mcp_server: Option<McpServerConfig>,
@@ -186,14 +362,31 @@ pub struct ToolManifest {
     #[serde(default, skip_serializing_if = "is_false")]
     pub disabled: bool,
     /// Docker services this tool requires to be running
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        alias = "required_services",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub requires_services: Vec<String>,
+    /// Maximum call rate (e.g., for tools hitting external APIs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    /// Where the tool was imported from (set by import_tool)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imported_from: Option<ImportOrigin>,
+    /// ID of the gist share_tool published the tool to, updated on the next share
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_gist: Option<String>,
 }
 
-fn is_false(b: &bool) -> bool {
+pub(crate) fn is_false(b: &bool) -> bool {
     !*b
 }
 
+fn is_zero<T: Default + PartialEq>(n: &T) -> bool {
+    *n == T::default()
+}
+
 fn default_version() -> String {
     "1.0.0".to_string()
 }
@@ -211,9 +404,18 @@ impl ToolManifest {
             input_schema: ToolSchema::any(),
             output_schema: None,
             annotations: None,
+            arg_mode: ArgMode::default(),
+            ndjson: false,
+            working_dir: None,
+            env: HashMap::new(),
             dependencies: vec![],
             wasm_dependencies: vec![],
+            build_options: None,
+            wasm_fuel: None,
             pipeline_steps: vec![],
+            on_error: vec![],
+            quiet: false,
+            schedule: None,
             mcp_server: None,
             author: None,
             license: None,
@@ -223,6 +425,9 @@ impl ToolManifest {
             updated_at: Some(now),
             disabled: false,
             requires_services: vec![],
+            rate_limit: None,
+            imported_from: None,
+            shared_gist: None,
         }
     }
 
@@ -232,6 +437,24 @@ impl ToolManifest {
         manifest.pipeline_steps = steps;
         manifest
     }
+
+    /// File name of a script tool's entry point: `entry_file`, or
+    /// `<name>.<ext>` inferred from the interpreter
+    pub fn script_file_name(&self) -> String {
+        if let Some(ref entry) = self.entry_file {
+            return entry.clone();
+        }
+        let ext = match self.interpreter.as_deref() {
+            Some("python3") | Some("python") => "py",
+            Some("node") | Some("nodejs") => "js",
+            Some("ruby") => "rb",
+            Some("bash") | Some("sh") => "sh",
+            Some("perl") => "pl",
+            Some("php") => "php",
+            _ => "script",
+        };
+        format!("{}.{}", self.name, ext)
+    }
 }
 
 /// Runtime tool configuration (includes paths resolved at load time)
@@ -295,34 +518,391 @@ impl ToolConfig {
     pub fn pipeline_steps(&self) -> &[PipelineStep] {
         &self.manifest.pipeline_steps
     }
+    pub fn on_error_steps(&self) -> &[PipelineStep] {
+        &self.manifest.on_error
+    }
     pub fn mcp_server(&self) -> Option<&McpServerConfig> {
         self.manifest.mcp_server.as_ref()
     }
+    pub fn tags(&self) -> &[String] {
+        &self.manifest.tags
+    }
+    /// Whether results may be memoized: WASM/script tools annotated
+    /// idempotentHint. Read-only alone isn't enough, as such a tool may
+    /// still read the clock, the network or changing files.
+    pub fn is_cacheable(&self) -> bool {
+        matches!(self.tool_type(), ToolType::Wasm | ToolType::Script)
+            && self
+                .annotations()
+                .is_some_and(|a| a.idempotent_hint == Some(true))
+    }
+
+    /// Annotated readOnlyHint: its sandbox mounts roots read-only and it
+    /// can't call tools annotated destructiveHint
+    pub fn is_read_only(&self) -> bool {
+        self.annotations()
+            .is_some_and(|a| a.read_only_hint == Some(true))
+    }
+
+    /// Annotated destructiveHint (and not readOnlyHint, which overrides it)
+    pub fn is_destructive(&self) -> bool {
+        !self.is_read_only()
+            && self
+                .annotations()
+                .is_some_and(|a| a.destructive_hint == Some(true))
+    }
+
+    /// Refuse a nested call from this tool to `callee` that would break this
+    /// tool's readOnlyHint
+    pub fn check_may_call(&self, callee: &ToolConfig) -> Result<()> {
+        if self.is_read_only() && callee.is_destructive() {
+            anyhow::bail!(
+                "Tool '{}' is annotated readOnlyHint, so it can't call '{}', which is annotated destructiveHint",
+                self.name(),
+                callee.name()
+            );
+        }
+        Ok(())
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.manifest.tags.iter().any(|t| t == tag)
+    }
+
+    /// Stored source file: `src.rs` of a WASM tool or a script tool's script.
+    /// `None` for other tools and WASM tools built without their source.
+    pub fn source_path(&self) -> Option<PathBuf> {
+        let path = match self.tool_type() {
+            ToolType::Wasm => self.tool_dir.join("src.rs"),
+            ToolType::Script => self.script_path.clone(),
+            ToolType::Pipeline | ToolType::Mcp => return None,
+        };
+        path.is_file().then_some(path)
+    }
+
+    /// MIME type of the tool's source, by language
+    pub fn source_mime_type(&self) -> &'static str {
+        if *self.tool_type() == ToolType::Wasm {
+            return "text/x-rust";
+        }
+        match self.script_path.extension().and_then(|e| e.to_str()) {
+            Some("py") => "text/x-python",
+            Some("js") => "text/javascript",
+            Some("rb") => "text/x-ruby",
+            Some("sh") => "text/x-shellscript",
+            Some("pl") => "text/x-perl",
+            Some("php") => "text/x-php",
+            _ => "text/plain",
+        }
+    }
+
+    /// Read the tool's source code
+    pub fn read_source(&self) -> Result<String> {
+        let path = self
+            .source_path()
+            .ok_or_else(|| anyhow::anyhow!("Tool '{}' has no stored source", self.name()))?;
+        Ok(String::from_utf8_lossy(&fs::read(path)?).into_owned())
+    }
+
+    /// Arguments that recreate this tool with the tool that makes its kind:
+    /// `build_tool` for WASM, `register_script` for scripts (both with
+    /// `overwrite`, and without `code`, which is [`Self::read_source`]) and
+    /// `pipeline` update for pipelines. None for external MCP tools.
+    pub fn rebuild_args(&self) -> Option<serde_json::Value> {
+        let m = &self.manifest;
+        let mut args = serde_json::Map::new();
+        let mut set = |key: &str, value: serde_json::Value| {
+            if !(value.is_null()
+                || value.as_array().is_some_and(|a| a.is_empty())
+                || value == serde_json::Value::Bool(false))
+            {
+                args.insert(key.to_string(), value);
+            }
+        };
+        fn json<T: Serialize>(value: &T) -> serde_json::Value {
+            serde_json::to_value(value).unwrap_or_default()
+        }
+
+        match m.tool_type {
+            ToolType::Mcp => return None,
+            ToolType::Pipeline => {
+                set("action", "update".into());
+                set("name", m.name.clone().into());
+                set("description", m.description.clone().into());
+                set("steps", json(&m.pipeline_steps));
+                set("on_error", json(&m.on_error));
+                set("tags", json(&m.tags));
+                set("quiet", m.quiet.into());
+                return Some(args.into());
+            }
+            ToolType::Wasm => {
+                set("name", m.name.clone().into());
+                set("description", m.description.clone().into());
+                set("dependencies", json(&m.wasm_dependencies));
+                set("build_options", json(&m.build_options));
+                if let Some(fuel) = m.wasm_fuel {
+                    set("wasm_fuel", fuel.into());
+                }
+            }
+            ToolType::Script => {
+                set("name", m.name.clone().into());
+                set("description", m.description.clone().into());
+                set("interpreter", json(&m.interpreter));
+                set(
+                    "extension",
+                    json(&self.script_path.extension().and_then(|e| e.to_str())),
+                );
+                set("dependencies", json(&m.dependencies));
+                set("requires_services", json(&m.requires_services));
+                if m.arg_mode == ArgMode::Argv {
+                    set("arg_mode", "argv".into());
+                }
+                set("ndjson", m.ndjson.into());
+                if let Some(dir) = &m.working_dir {
+                    set("working_dir", dir.clone().into());
+                }
+                if !m.env.is_empty() {
+                    set("env", json(&m.env));
+                }
+            }
+        }
+        set("input_schema", json(&m.input_schema));
+        set("output_schema", json(&m.output_schema));
+        set("annotations", json(&m.annotations));
+        set("tags", json(&m.tags));
+        set("rate_limit", json(&m.rate_limit));
+        set("overwrite", true.into());
+        Some(args.into())
+    }
+}
+
+/// Check the names in a script tool's `env`: letters, digits and
+/// underscores, not starting with a digit
+pub fn validate_env_names(env: &HashMap<String, String>) -> Result<()> {
+    for name in env.keys() {
+        let valid = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && name.chars().next().is_some_and(|c| !c.is_ascii_digit());
+        if !valid {
+            anyhow::bail!(
+                "Invalid environment variable name '{}' in env: use letters, digits and underscores",
+                name
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Trim tags, drop empty ones and remove duplicates (keeping first occurrence)
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !out.iter().any(|t| t == tag) {
+            out.push(tag.to_string());
+        }
+    }
+    out
 }
 
-/// Get current timestamp in ISO 8601 format
-fn chrono_now() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let duration = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default();
-    let secs = duration.as_secs();
-    // Simple ISO 8601 format without external dependency
-    format!(
-        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
-        1970 + secs / 31536000,
-        (secs % 31536000) / 2592000 + 1,
-        (secs % 2592000) / 86400 + 1,
-        (secs % 86400) / 3600,
-        (secs % 3600) / 60,
-        secs % 60
-    )
+/// Group tools by tag. A tool with several tags appears under each of them;
+/// untagged tools are returned separately.
+pub fn group_by_tag(
+    tools: &[ToolConfig],
+) -> (
+    std::collections::BTreeMap<String, Vec<&ToolConfig>>,
+    Vec<&ToolConfig>,
+) {
+    let mut groups: std::collections::BTreeMap<String, Vec<&ToolConfig>> = Default::default();
+    let mut untagged = Vec::new();
+
+    for tool in tools {
+        if tool.tags().is_empty() {
+            untagged.push(tool);
+        }
+        for tag in tool.tags() {
+            groups.entry(tag.clone()).or_default().push(tool);
+        }
+    }
+
+    for group in groups.values_mut() {
+        group.sort_by(|a, b| a.name().cmp(b.name()));
+    }
+    untagged.sort_by(|a, b| a.name().cmp(b.name()));
+
+    (groups, untagged)
+}
+
+/// Items per page of MCP list responses (resources, tools)
+pub const LIST_PAGE_SIZE: usize = 50;
+
+/// Slice one page out of `items`. The cursor is the offset of the page's
+/// first item, as returned in the previous page's next cursor; `None` starts
+/// at the beginning. Returns the page and the cursor of the next one, if any.
+pub fn paginate<T>(
+    items: Vec<T>,
+    cursor: Option<&str>,
+    page_size: usize,
+) -> Result<(Vec<T>, Option<String>)> {
+    let offset = match cursor {
+        None => 0,
+        Some(c) => c
+            .parse::<usize>()
+            .ok()
+            .filter(|&o| o <= items.len())
+            .ok_or_else(|| anyhow::anyhow!("Invalid cursor '{}'", c))?,
+    };
+    let page_size = page_size.max(1);
+    let next_cursor = (items.len() > offset + page_size).then(|| (offset + page_size).to_string());
+    let page = items.into_iter().skip(offset).take(page_size).collect();
+    Ok((page, next_cursor))
+}
+
+/// Changes for `ToolRegistry::update_pipeline`; `None` keeps the current value
+#[derive(Debug, Clone, Default)]
+pub struct PipelineUpdate {
+    pub steps: Option<Vec<PipelineStep>>,
+    pub on_error: Option<Vec<PipelineStep>>,
+    pub description: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub quiet: Option<bool>,
+}
+
+/// Which part of the semver version to bump when a tool is updated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VersionBump {
+    Major,
+    Minor,
+    #[default]
+    Patch,
+}
+
+impl VersionBump {
+    /// Parse "major", "minor" or "patch" (case-insensitive)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "major" => Some(Self::Major),
+            "minor" => Some(Self::Minor),
+            "patch" => Some(Self::Patch),
+            _ => None,
+        }
+    }
+}
+
+/// Compile a WASM tool's saved src.rs, returning the source and module
+fn compile_saved_source(tool: &ToolConfig) -> Result<(String, Vec<u8>)> {
+    let name = &tool.manifest.name;
+    let src_path = tool.tool_dir.join("src.rs");
+    if !src_path.exists() {
+        anyhow::bail!(
+            "Tool '{}' has no saved source (src.rs). Rebuild it with build_tool instead.",
+            name
+        );
+    }
+    let source = fs::read_to_string(&src_path)?;
+
+    let deps = Builder::parse_dependencies(tool.wasm_dependencies());
+    let options = tool.manifest.build_options.clone().unwrap_or_default();
+    let wasm_path = Builder::compile_tool_with_deps(name, &source, &deps, &options)?;
+    Builder::optimize_wasm(&wasm_path);
+    let wasm_bytes = fs::read(&wasm_path)?;
+    Ok((source, wasm_bytes))
+}
+
+/// Current UTC time in RFC 3339 format, e.g. "2024-05-01T12:30:00Z"
+pub(crate) fn chrono_now() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// Names of the server's built-in tools. User tools can't take these names:
+/// pipeline steps and `call_tool` would resolve them ambiguously.
+pub const RESERVED_TOOL_NAMES: &[&str] = &[
+    "build_tool",
+    "register_script",
+    "delete_tool",
+    "manage_tool",
+    "get_tool_source",
+    "version",
+    "call_tool",
+    "tool_stats",
+    "history",
+    "list_tools",
+    "execute_code",
+    "import_tool",
+    "confirm_import",
+    "search_index",
+    "update_tool",
+    "export_tool",
+    "share_tool",
+    "import_mcp",
+    "services",
+    "sandbox",
+    "pipeline",
+    "memory",
+    "transform",
+];
+
+/// Directories the server keeps next to the tool directories in
+/// TOOLS_DIR. A tool with one of these names would share its directory.
+pub const RESERVED_DIR_NAMES: &[&str] = &[
+    crate::services::SERVICES_DIR,
+    crate::services::BACKUPS_DIR,
+    crate::exporter::EXPORTS_DIR,
+    crate::builder::BUILD_CACHE_DIR,
+];
+
+/// Check that a tool name is safe to use as a directory name and as a
+/// Python/JS identifier (after `-` becomes `_`): only ASCII letters, digits,
+/// `_` and `-`, and not the name of a built-in tool or server directory.
+pub fn validate_tool_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        anyhow::bail!("Invalid tool name: the name can't be empty");
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '_' && *c != '-')
+    {
+        anyhow::bail!(
+            "Invalid tool name '{}': {:?} is not allowed (use letters, digits, '_' and '-')",
+            name,
+            c
+        );
+    }
+    if RESERVED_TOOL_NAMES.contains(&name) {
+        anyhow::bail!(
+            "Invalid tool name '{}': it is reserved for a built-in tool",
+            name
+        );
+    }
+    if RESERVED_DIR_NAMES.contains(&name) {
+        anyhow::bail!(
+            "Invalid tool name '{}': TOOLS_DIR/{} is used by the server",
+            name,
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Default number of version backups kept per tool
+pub const DEFAULT_MAX_VERSIONS: usize = 10;
+
+/// A tool directory that has a manifest.json the registry couldn't load
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadError {
+    /// Name of the tool directory
+    pub dir: String,
+    /// Why loading failed, including the serde error for a bad manifest
+    pub message: String,
 }
 
 #[derive(Clone)]
 pub struct ToolRegistry {
     tools: Arc<RwLock<HashMap<String, ToolConfig>>>,
+    /// Tool directories skipped by the last load
+    load_errors: Arc<RwLock<Vec<LoadError>>>,
     storage_dir: PathBuf,
+    /// Version backups kept per tool; 0 disables pruning
+    max_versions: usize,
 }
 
 impl ToolRegistry {
@@ -331,7 +911,9 @@ impl ToolRegistry {
 
         let registry = Self {
             tools: Arc::new(RwLock::new(HashMap::new())),
+            load_errors: Arc::new(RwLock::new(Vec::new())),
             storage_dir,
+            max_versions: DEFAULT_MAX_VERSIONS,
         };
 
         // Load tools from directory structure
@@ -343,15 +925,35 @@ impl ToolRegistry {
         registry
     }
 
+    /// Set how many version backups are kept per tool (0 = keep all)
+    pub fn with_max_versions(mut self, max_versions: usize) -> Self {
+        self.max_versions = max_versions;
+        self
+    }
+
+    /// Number of version backups kept per tool (0 = keep all)
+    pub fn max_versions(&self) -> usize {
+        self.max_versions
+    }
+
     /// Load/reload all tools from the directory structure
     pub fn reload(&self) {
         self.load_all_tools();
     }
 
-    /// Load all tools from the directory structure
+    /// Tool directories with a manifest that failed to load, by directory name
+    pub fn load_errors(&self) -> Vec<LoadError> {
+        self.load_errors.read().unwrap().clone()
+    }
+
+    /// Load all tools from the directory structure. Directories without a
+    /// manifest.json aren't tools and are passed over; ones whose manifest
+    /// fails to load, or names a different tool than the directory, are
+    /// logged and kept in `load_errors`.
     fn load_all_tools(&self) {
         let mut tools = self.tools.write().unwrap();
         tools.clear();
+        let mut load_errors = Vec::new();
 
         // Scan storage directory for tool directories
         if let Ok(entries) = fs::read_dir(&self.storage_dir) {
@@ -360,13 +962,28 @@ impl ToolRegistry {
                 if path.is_dir() {
                     let manifest_path = path.join("manifest.json");
                     if manifest_path.exists() {
-                        if let Ok(config) = self.load_tool_from_dir(&path) {
-                            tools.insert(config.manifest.name.clone(), config);
+                        match self.load_tool_from_dir(&path) {
+                            Ok(config) => {
+                                tools.insert(config.manifest.name.clone(), config);
+                            }
+                            Err(e) => {
+                                let error = LoadError {
+                                    dir: entry.file_name().to_string_lossy().to_string(),
+                                    message: format!("{:#}", e),
+                                };
+                                eprintln!(
+                                    "⚠️ Skipping tool directory '{}': {}",
+                                    error.dir, error.message
+                                );
+                                load_errors.push(error);
+                            }
                         }
                     }
                 }
             }
         }
+        load_errors.sort_by(|a, b| a.dir.cmp(&b.dir));
+        *self.load_errors.write().unwrap() = load_errors;
 
         let wasm_count = tools
             .values()
@@ -387,11 +1004,24 @@ impl ToolRegistry {
     /// Load a single tool from its directory
     fn load_tool_from_dir(&self, tool_dir: &Path) -> Result<ToolConfig> {
         let manifest_path = tool_dir.join("manifest.json");
-        let content = fs::read_to_string(&manifest_path)?;
-        let manifest: ToolManifest = serde_json::from_str(&content)?;
+        let content = fs::read_to_string(&manifest_path).context("Failed to read manifest.json")?;
+        let manifest: ToolManifest =
+            serde_json::from_str(&content).context("Invalid manifest.json")?;
 
         let tool_name = &manifest.name;
 
+        // The directory name is the registry key everywhere else (paths,
+        // renames, hot reload). Requiring the manifest to agree also means two
+        // directories can never load under the same name and clobber each other
+        let dir_name = tool_dir.file_name().unwrap_or_default().to_string_lossy();
+        if *tool_name != dir_name {
+            anyhow::bail!(
+                "manifest.json names the tool '{}' but its directory is '{}'",
+                tool_name,
+                dir_name
+            );
+        }
+
         // Determine file paths based on tool type
         let (wasm_path, script_path) = match manifest.tool_type {
             ToolType::Wasm => {
@@ -400,22 +1030,7 @@ impl ToolRegistry {
             }
             ToolType::Script => {
                 // Use entry_file if specified, otherwise infer from interpreter
-                let script = if let Some(ref entry) = manifest.entry_file {
-                    tool_dir.join(entry)
-                } else {
-                    // Fallback: infer from interpreter
-                    let ext = match manifest.interpreter.as_deref() {
-                        Some("python3") | Some("python") => "py",
-                        Some("node") | Some("nodejs") => "js",
-                        Some("ruby") => "rb",
-                        Some("bash") | Some("sh") => "sh",
-                        Some("perl") => "pl",
-                        Some("php") => "php",
-                        _ => "script",
-                    };
-                    tool_dir.join(format!("{}.{}", tool_name, ext))
-                };
-                (PathBuf::new(), script)
+                (PathBuf::new(), tool_dir.join(manifest.script_file_name()))
             }
             ToolType::Pipeline => {
                 // Pipelines don't have WASM or script files
@@ -515,9 +1130,18 @@ impl ToolRegistry {
                     .unwrap_or_else(ToolSchema::any),
                 output_schema: old.output_schema.map(ToolSchema::from_value),
                 annotations: old.annotations.map(ToolAnnotations::from_value),
+                arg_mode: ArgMode::default(),
+                ndjson: false,
+                working_dir: None,
+                env: HashMap::new(),
                 dependencies: old.dependencies,
                 wasm_dependencies: vec![],
+                build_options: None,
+                wasm_fuel: None,
                 pipeline_steps: vec![],
+                on_error: vec![],
+                quiet: false,
+                schedule: None,
                 mcp_server: None,
                 author: None,
                 license: None,
@@ -527,6 +1151,9 @@ impl ToolRegistry {
                 updated_at: Some(chrono_now()),
                 disabled: false,
                 requires_services: vec![],
+                rate_limit: None,
+                imported_from: None,
+                shared_gist: None,
             };
 
             // Save manifest
@@ -581,30 +1208,43 @@ impl ToolRegistry {
 
     /// Register a new tool or update existing one
     pub fn register_tool(&self, manifest: ToolManifest, code: &[u8]) -> Result<ToolConfig> {
+        self.register_tool_with_bump(manifest, code, VersionBump::Patch)
+    }
+
+    /// Register a tool, choosing how the version is bumped if it already exists
+    pub fn register_tool_with_bump(
+        &self,
+        manifest: ToolManifest,
+        code: &[u8],
+        bump: VersionBump,
+    ) -> Result<ToolConfig> {
         match manifest.tool_type {
-            ToolType::Wasm => self.register_wasm_tool(manifest, code, ""),
-            ToolType::Script => self.register_script_tool(manifest, code),
-            ToolType::Pipeline => self.register_pipeline_tool(manifest),
-            ToolType::Mcp => self.register_mcp_server_tool(manifest),
+            ToolType::Wasm => self.register_wasm_tool_with_bump(manifest, code, "", bump),
+            ToolType::Script => self.register_script_tool(manifest, code, bump),
+            ToolType::Pipeline => self.register_pipeline_tool(manifest, bump),
+            ToolType::Mcp => self.register_mcp_server_tool(manifest, bump),
         }
     }
 
     /// Register a WASM tool with optional source code preservation
     pub fn register_wasm_tool(
+        &self,
+        manifest: ToolManifest,
+        wasm_bytes: &[u8],
+        source_code: &str,
+    ) -> Result<ToolConfig> {
+        self.register_wasm_tool_with_bump(manifest, wasm_bytes, source_code, VersionBump::Patch)
+    }
+
+    /// Register a WASM tool, choosing how the version is bumped if it already exists
+    pub fn register_wasm_tool_with_bump(
         &self,
         mut manifest: ToolManifest,
         wasm_bytes: &[u8],
         source_code: &str,
+        bump: VersionBump,
     ) -> Result<ToolConfig> {
-        // Backup existing version if updating
-        if let Some(old_version) = self.backup_version(&manifest.name)? {
-            // Auto-increment version if same version
-            if manifest.version == old_version {
-                manifest.version = Self::increment_version(&manifest.version);
-                eprintln!("📈 Auto-incremented version to {}", manifest.version);
-            }
-        }
-        manifest.updated_at = Some(chrono_now());
+        self.prepare_update(&mut manifest, bump)?;
 
         let tool_dir = self.storage_dir.join(&manifest.name);
         fs::create_dir_all(&tool_dir)?;
@@ -642,36 +1282,18 @@ impl ToolRegistry {
     }
 
     /// Register a script tool
-    fn register_script_tool(&self, mut manifest: ToolManifest, code: &[u8]) -> Result<ToolConfig> {
-        // Backup existing version if updating
-        if let Some(old_version) = self.backup_version(&manifest.name)? {
-            // Auto-increment version if same version
-            if manifest.version == old_version {
-                manifest.version = Self::increment_version(&manifest.version);
-                eprintln!("📈 Auto-incremented version to {}", manifest.version);
-            }
-        }
-        manifest.updated_at = Some(chrono_now());
+    fn register_script_tool(
+        &self,
+        mut manifest: ToolManifest,
+        code: &[u8],
+        bump: VersionBump,
+    ) -> Result<ToolConfig> {
+        self.prepare_update(&mut manifest, bump)?;
 
         let tool_dir = self.storage_dir.join(&manifest.name);
         fs::create_dir_all(&tool_dir)?;
 
-        // Determine script filename
-        let script_filename = if let Some(ref entry) = manifest.entry_file {
-            entry.clone()
-        } else {
-            // Generate filename from interpreter
-            let ext = match manifest.interpreter.as_deref() {
-                Some("python3") | Some("python") => "py",
-                Some("node") | Some("nodejs") => "js",
-                Some("ruby") => "rb",
-                Some("bash") | Some("sh") => "sh",
-                Some("perl") => "pl",
-                Some("php") => "php",
-                _ => "script",
-            };
-            format!("{}.{}", manifest.name, ext)
-        };
+        let script_filename = manifest.script_file_name();
 
         // Update manifest with entry_file if not set
         if manifest.entry_file.is_none() {
@@ -715,16 +1337,12 @@ impl ToolRegistry {
     }
 
     /// Register a pipeline tool (no code, just manifest with steps)
-    fn register_pipeline_tool(&self, mut manifest: ToolManifest) -> Result<ToolConfig> {
-        // Backup existing version if updating
-        if let Some(old_version) = self.backup_version(&manifest.name)? {
-            // Auto-increment version if same version
-            if manifest.version == old_version {
-                manifest.version = Self::increment_version(&manifest.version);
-                eprintln!("📈 Auto-incremented version to {}", manifest.version);
-            }
-        }
-        manifest.updated_at = Some(chrono_now());
+    fn register_pipeline_tool(
+        &self,
+        mut manifest: ToolManifest,
+        bump: VersionBump,
+    ) -> Result<ToolConfig> {
+        self.prepare_update(&mut manifest, bump)?;
 
         let tool_dir = self.storage_dir.join(&manifest.name);
         fs::create_dir_all(&tool_dir)?;
@@ -753,16 +1371,12 @@ impl ToolRegistry {
     }
 
     /// Register an external MCP server tool (persisted)
-    fn register_mcp_server_tool(&self, mut manifest: ToolManifest) -> Result<ToolConfig> {
-        // Backup existing version if updating
-        if let Some(old_version) = self.backup_version(&manifest.name)? {
-            // Auto-increment version if same version
-            if manifest.version == old_version {
-                manifest.version = Self::increment_version(&manifest.version);
-                eprintln!("📈 Auto-incremented version to {}", manifest.version);
-            }
-        }
-        manifest.updated_at = Some(chrono_now());
+    fn register_mcp_server_tool(
+        &self,
+        mut manifest: ToolManifest,
+        bump: VersionBump,
+    ) -> Result<ToolConfig> {
+        self.prepare_update(&mut manifest, bump)?;
 
         let tool_dir = self.storage_dir.join(&manifest.name);
         fs::create_dir_all(&tool_dir)?;
@@ -873,6 +1487,14 @@ impl ToolRegistry {
         Ok(())
     }
 
+    /// Whether an imported tool was changed since it was imported or last
+    /// updated, or None for a tool that wasn't imported
+    pub fn modified_since_import(&self, name: &str) -> Option<bool> {
+        let tool = self.get_tool(name)?;
+        let origin = tool.manifest.imported_from.as_ref()?;
+        Some(tool_content_hash(&tool.tool_dir).map_or(true, |hash| hash != origin.content_hash))
+    }
+
     /// Delete a tool and its directory
     pub fn delete_tool(&self, name: &str) -> Result<bool> {
         let mut tools = self.tools.write().unwrap();
@@ -935,8 +1557,21 @@ impl ToolRegistry {
             anyhow::bail!("Tool directory does not exist: {}", tool_dir.display());
         }
 
-        // Load the tool from its directory
-        let config = self.load_tool_from_dir(&tool_dir)?;
+        // Load the tool from its directory, keeping load_errors current
+        let mut load_errors = self.load_errors.write().unwrap();
+        load_errors.retain(|error| error.dir != name);
+        let config = match self.load_tool_from_dir(&tool_dir) {
+            Ok(config) => config,
+            Err(e) => {
+                load_errors.push(LoadError {
+                    dir: name.to_string(),
+                    message: format!("{:#}", e),
+                });
+                load_errors.sort_by(|a, b| a.dir.cmp(&b.dir));
+                return Err(e);
+            }
+        };
+        drop(load_errors);
 
         // Update in-memory cache
         let mut tools = self.tools.write().unwrap();
@@ -953,6 +1588,267 @@ impl ToolRegistry {
         eprintln!("Unloaded tool: {}", name);
     }
 
+    /// Recompile a WASM tool from its saved `src.rs` and dependencies.
+    ///
+    /// Useful after a toolchain or dependency update; the build cache is
+    /// bypassed. The previous build is backed up like any other update.
+    pub fn recompile_wasm_tool(&self, name: &str) -> Result<ToolConfig> {
+        let tool = self
+            .get_tool(name)
+            .ok_or_else(|| anyhow::anyhow!("Tool not found: {}", name))?;
+
+        if *tool.tool_type() != ToolType::Wasm {
+            anyhow::bail!(
+                "Tool '{}' is a {:?} tool; only WASM tools can be recompiled",
+                name,
+                tool.tool_type()
+            );
+        }
+
+        let (source, wasm_bytes) = compile_saved_source(&tool)?;
+        self.register_wasm_tool(tool.manifest, &wasm_bytes, &source)
+    }
+
+    /// Compile a WASM tool from its saved src.rs and replace its module in
+    /// place, keeping its version (e.g. after restoring a backup)
+    pub fn rebuild_wasm_module(&self, name: &str) -> Result<()> {
+        let tool = self
+            .get_tool(name)
+            .ok_or_else(|| anyhow::anyhow!("Tool not found: {}", name))?;
+        let (_, wasm_bytes) = compile_saved_source(&tool)?;
+        fs::write(&tool.wasm_path, wasm_bytes)?;
+        Ok(())
+    }
+
+    // ==================== TAGS ====================
+
+    /// Add tags to a tool. Returns the tool's tags after the change.
+    pub fn add_tags(&self, name: &str, tags: &[String]) -> Result<Vec<String>> {
+        self.edit_tags(name, |current| {
+            current.extend(tags.iter().cloned());
+        })
+    }
+
+    /// Remove tags from a tool. Returns the tool's tags after the change.
+    pub fn remove_tags(&self, name: &str, tags: &[String]) -> Result<Vec<String>> {
+        self.edit_tags(name, |current| {
+            current.retain(|t| !tags.iter().any(|r| r.trim() == t));
+        })
+    }
+
+    fn edit_tags(&self, name: &str, edit: impl FnOnce(&mut Vec<String>)) -> Result<Vec<String>> {
+        let tool = self
+            .get_tool(name)
+            .ok_or_else(|| anyhow::anyhow!("Tool not found: {}", name))?;
+        if tool.server_id.is_some() {
+            anyhow::bail!(
+                "Tool '{}' is provided by an external MCP server and cannot be tagged",
+                name
+            );
+        }
+
+        let mut manifest = tool.manifest;
+        edit(&mut manifest.tags);
+        manifest.tags = normalize_tags(&manifest.tags);
+        let tags = manifest.tags.clone();
+
+        self.update_manifest(name, manifest)?;
+        Ok(tags)
+    }
+
+    // ==================== RENAME / COPY ====================
+
+    /// Rename a tool, moving its directory and preserving its version history.
+    /// Fails if the target name is already taken.
+    pub fn rename_tool(&self, old_name: &str, new_name: &str) -> Result<ToolConfig> {
+        let tool = self.check_rename_target(old_name, new_name)?;
+        let new_dir = self.storage_dir.join(new_name);
+
+        fs::rename(&tool.tool_dir, &new_dir)?;
+
+        retarget_tool_dir(&new_dir, old_name, new_name, |manifest| {
+            manifest.updated_at = Some(chrono_now());
+        })?;
+
+        // Keep rollback working: backed up versions carry the old name too
+        let versions_dir = new_dir.join("versions");
+        if versions_dir.exists() {
+            for entry in fs::read_dir(&versions_dir)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir() {
+                    retarget_tool_dir(&entry.path(), old_name, new_name, |_| {})?;
+                }
+            }
+        }
+
+        let config = self.load_tool_from_dir(&new_dir)?;
+
+        let mut tools = self.tools.write().unwrap();
+        tools.remove(old_name);
+        tools.insert(new_name.to_string(), config.clone());
+
+        eprintln!("Renamed tool: {} -> {}", old_name, new_name);
+        Ok(config)
+    }
+
+    /// Copy a tool under a new name. The copy starts a fresh version history.
+    pub fn copy_tool(&self, source_name: &str, new_name: &str) -> Result<ToolConfig> {
+        let tool = self.check_rename_target(source_name, new_name)?;
+        let new_dir = self.storage_dir.join(new_name);
+
+        copy_dir_filtered(&tool.tool_dir, &new_dir, &["versions"])?;
+
+        retarget_tool_dir(&new_dir, source_name, new_name, |manifest| {
+            let now = chrono_now();
+            manifest.version = default_version();
+            manifest.created_at = Some(now.clone());
+            manifest.updated_at = Some(now);
+        })?;
+
+        let config = self.load_tool_from_dir(&new_dir)?;
+
+        let mut tools = self.tools.write().unwrap();
+        tools.insert(new_name.to_string(), config.clone());
+
+        eprintln!("Copied tool: {} -> {}", source_name, new_name);
+        Ok(config)
+    }
+
+    /// Validate a rename/copy and return the source tool
+    fn check_rename_target(&self, source_name: &str, new_name: &str) -> Result<ToolConfig> {
+        let tool = self
+            .get_tool(source_name)
+            .ok_or_else(|| anyhow::anyhow!("Tool not found: {}", source_name))?;
+
+        if tool.server_id.is_some() {
+            anyhow::bail!(
+                "Tool '{}' is provided by an external MCP server and has no local files",
+                source_name
+            );
+        }
+        validate_tool_name(new_name)?;
+        if source_name == new_name {
+            anyhow::bail!("New name is the same as the current name");
+        }
+        if self.get_tool(new_name).is_some() || self.storage_dir.join(new_name).exists() {
+            anyhow::bail!("Tool '{}' already exists", new_name);
+        }
+
+        Ok(tool)
+    }
+
+    /// Names of pipelines that have a step calling `tool_name`
+    pub fn pipelines_referencing(&self, tool_name: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .tools
+            .read()
+            .unwrap()
+            .values()
+            .filter(|t| *t.tool_type() == ToolType::Pipeline)
+            .filter(|t| {
+                t.pipeline_steps()
+                    .iter()
+                    .chain(t.on_error_steps())
+                    .any(|s| s.tool == tool_name)
+            })
+            .map(|t| t.name().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Point every pipeline step calling `old_name` at `new_name`.
+    /// Returns the names of the pipelines that were updated.
+    pub fn replace_pipeline_references(
+        &self,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<Vec<String>> {
+        let pipelines = self.pipelines_referencing(old_name);
+
+        for pipeline_name in &pipelines {
+            if let Some(tool) = self.get_tool(pipeline_name) {
+                let mut manifest = tool.manifest;
+                let steps = manifest.pipeline_steps.iter_mut();
+                for step in steps.chain(manifest.on_error.iter_mut()) {
+                    if step.tool == old_name {
+                        step.tool = new_name.to_string();
+                    }
+                }
+                self.update_manifest(pipeline_name, manifest)?;
+            }
+        }
+
+        Ok(pipelines)
+    }
+
+    /// Replace parts of a pipeline's definition, keeping the rest (and
+    /// created_at). The previous definition is backed up as a version, so
+    /// the update can be rolled back.
+    pub fn update_pipeline(&self, name: &str, update: PipelineUpdate) -> Result<ToolConfig> {
+        let tool = self
+            .get_tool(name)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline not found: {}", name))?;
+        if *tool.tool_type() != ToolType::Pipeline {
+            anyhow::bail!("'{}' is not a pipeline", name);
+        }
+
+        let mut manifest = tool.manifest;
+        if let Some(steps) = update.steps {
+            manifest.pipeline_steps = steps;
+        }
+        if let Some(on_error) = update.on_error {
+            manifest.on_error = on_error;
+        }
+        if let Some(description) = update.description {
+            manifest.description = description;
+        }
+        if let Some(tags) = update.tags {
+            manifest.tags = normalize_tags(&tags);
+        }
+        if let Some(quiet) = update.quiet {
+            manifest.quiet = quiet;
+        }
+        for step in manifest.pipeline_steps.iter().chain(&manifest.on_error) {
+            step.validate()?;
+        }
+
+        self.register_tool(manifest, &[])
+    }
+
+    /// Set or clear a pipeline's schedule. Like tags, this doesn't create a
+    /// new version.
+    pub fn set_schedule(&self, name: &str, schedule: Option<String>) -> Result<ToolConfig> {
+        let tool = self
+            .get_tool(name)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline not found: {}", name))?;
+        if *tool.tool_type() != ToolType::Pipeline {
+            anyhow::bail!("'{}' is not a pipeline", name);
+        }
+        let schedule = schedule.map(|s| s.trim().to_string());
+        if let Some(ref expr) = schedule {
+            crate::schedule::Schedule::parse(expr)?;
+        }
+
+        let mut manifest = tool.manifest;
+        manifest.schedule = schedule;
+        self.update_manifest(name, manifest)?;
+        self.get_tool(name)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline not found: {}", name))
+    }
+
+    /// Enabled pipelines that have a schedule, as `(name, expression)`
+    pub fn scheduled_pipelines(&self) -> Vec<(String, String)> {
+        let mut scheduled: Vec<_> = self
+            .list_tools()
+            .into_iter()
+            .filter(|t| *t.tool_type() == ToolType::Pipeline && !t.manifest.disabled)
+            .filter_map(|t| Some((t.name().to_string(), t.manifest.schedule?)))
+            .collect();
+        scheduled.sort();
+        scheduled
+    }
+
     // ==================== VERSIONING ====================
 
     /// Get the versions directory for a tool
@@ -1078,9 +1974,7 @@ impl ToolRegistry {
                     let entry = entry?;
                     let path = entry.path();
                     if let Some(ext) = path.extension() {
-                        if ["py", "js", "rb", "sh", "pl", "php"]
-                            .contains(&ext.to_str().unwrap_or(""))
-                        {
+                        if SCRIPT_EXTENSIONS.contains(&ext.to_str().unwrap_or("")) {
                             let filename = path.file_name().unwrap();
                             fs::copy(&path, tool_dir.join(filename))?;
                         }
@@ -1117,12 +2011,129 @@ impl ToolRegistry {
         Ok(format!("Rolled back {} to version {}", name, version))
     }
 
+    /// Delete the oldest version backups so that at most `keep` remain.
+    /// The backup of the currently active version is never removed.
+    /// Returns the removed versions.
+    pub fn prune_versions(&self, name: &str, keep: usize) -> Result<Vec<String>> {
+        let current = self.get_tool(name).map(|t| t.manifest.version);
+        self.prune_backups(name, keep, current.as_deref())
+    }
+
+    /// Keep the `keep` newest backups, never touching the `protect` version
+    fn prune_backups(&self, name: &str, keep: usize, protect: Option<&str>) -> Result<Vec<String>> {
+        let versions_dir = self.versions_dir(name);
+        if !versions_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut backups = Vec::new();
+        for entry in fs::read_dir(&versions_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                if let Some(version) = entry.file_name().to_str() {
+                    if protect != Some(version) {
+                        backups.push(version.to_string());
+                    }
+                }
+            }
+        }
+        backups.sort_by(|a, b| version_compare(b, a)); // Newest first
+
+        let removed: Vec<String> = backups.into_iter().skip(keep).collect();
+        for version in &removed {
+            fs::remove_dir_all(versions_dir.join(version))?;
+        }
+        if !removed.is_empty() {
+            eprintln!("🧹 Pruned {} old version(s) of {}", removed.len(), name);
+        }
+        Ok(removed)
+    }
+
+    /// Load the manifest and source of a tool as stored for `version`.
+    /// `"current"` (or the live version number) reads the tool directory itself.
+    pub fn version_snapshot(&self, name: &str, version: &str) -> Result<VersionSnapshot> {
+        let current = self.get_tool(name).map(|t| t.manifest.version);
+        let dir = if version == "current" || current.as_deref() == Some(version) {
+            self.storage_dir.join(name)
+        } else {
+            self.versions_dir(name).join(version)
+        };
+
+        if !dir.join("manifest.json").exists() {
+            anyhow::bail!("Version {} not found for tool {}", version, name);
+        }
+        dir_snapshot(&dir, name, version)
+    }
+
+    /// Compare two stored versions of a tool: a unified diff of the source
+    /// plus the manifest fields that changed between them.
+    pub fn diff_versions(&self, name: &str, from: &str, to: &str) -> Result<VersionDiff> {
+        let old = self.version_snapshot(name, from)?;
+        let new = self.version_snapshot(name, to)?;
+        Ok(diff_snapshots(name, old, new))
+    }
+
+    /// Back up the existing version (if any) and work out the new version number.
+    ///
+    /// An explicitly newer version in the manifest is kept; otherwise the
+    /// current version is bumped according to `bump`. Old backups beyond the
+    /// retention limit are pruned.
+    fn prepare_update(&self, manifest: &mut ToolManifest, bump: VersionBump) -> Result<()> {
+        if let Some(old_version) = self.backup_version(&manifest.name)? {
+            if version_compare(&manifest.version, &old_version) != std::cmp::Ordering::Greater {
+                manifest.version = Self::bump_version(&old_version, bump);
+                eprintln!("📈 Auto-incremented version to {}", manifest.version);
+            }
+            // The outgoing version was just backed up and is the newest backup,
+            // so the active version is never at risk here
+            if self.max_versions > 0 {
+                self.prune_backups(&manifest.name, self.max_versions, None)?;
+            }
+        }
+        manifest.updated_at = Some(chrono_now());
+        Ok(())
+    }
+
+    /// Check that an explicitly requested version is `MAJOR.MINOR.PATCH` and
+    /// newer than the tool's current version, so it can't clobber a backup.
+    pub fn validate_explicit_version(&self, name: &str, version: &str) -> Result<()> {
+        let parts: Vec<&str> = version.split('.').collect();
+        if parts.len() != 3 || parts.iter().any(|p| p.parse::<u32>().is_err()) {
+            anyhow::bail!("Invalid version '{}': expected MAJOR.MINOR.PATCH", version);
+        }
+        if let Some(tool) = self.get_tool(name) {
+            let current = &tool.manifest.version;
+            if version_compare(version, current) != std::cmp::Ordering::Greater {
+                anyhow::bail!(
+                    "Version {} must be newer than the current version {}",
+                    version,
+                    current
+                );
+            }
+        }
+        Ok(())
+    }
+
     /// Increment version number (simple semver patch bump)
+    #[allow(dead_code)]
     pub fn increment_version(version: &str) -> String {
+        Self::bump_version(version, VersionBump::Patch)
+    }
+
+    /// Compute the next semver version for the given bump strategy
+    pub fn bump_version(version: &str, bump: VersionBump) -> String {
         let parts: Vec<&str> = version.split('.').collect();
         if parts.len() == 3 {
-            if let Ok(patch) = parts[2].parse::<u32>() {
-                return format!("{}.{}.{}", parts[0], parts[1], patch + 1);
+            if let (Ok(major), Ok(minor), Ok(patch)) = (
+                parts[0].parse::<u32>(),
+                parts[1].parse::<u32>(),
+                parts[2].parse::<u32>(),
+            ) {
+                return match bump {
+                    VersionBump::Major => format!("{}.0.0", major + 1),
+                    VersionBump::Minor => format!("{}.{}.0", major, minor + 1),
+                    VersionBump::Patch => format!("{}.{}.{}", major, minor, patch + 1),
+                };
             }
         }
         // Fallback: append .1
@@ -1130,8 +2141,349 @@ impl ToolRegistry {
     }
 }
 
+/// Script file extensions recognised in version backups and raw script imports
+pub const SCRIPT_EXTENSIONS: &[&str] = &["py", "js", "rb", "sh", "pl", "php"];
+
+/// Manifest fields that change on every update and carry no information in a diff
+const VOLATILE_MANIFEST_FIELDS: &[&str] = &["version", "updated_at", "imported_from"];
+
+/// A tool's manifest and source code as stored for a single version
+#[derive(Debug, Clone)]
+pub struct VersionSnapshot {
+    pub version: String,
+    pub manifest: serde_json::Value,
+    /// `src.rs` for WASM tools or the script file for script tools
+    pub source: Option<String>,
+    /// The compiled module of a WASM tool
+    pub module: Option<ModuleInfo>,
+}
+
+/// Size and content hash of a compiled WASM module
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleInfo {
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl ModuleInfo {
+    fn of(bytes: &[u8]) -> Self {
+        use sha2::{Digest, Sha256};
+        Self {
+            size: bytes.len() as u64,
+            sha256: Sha256::digest(bytes)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect(),
+        }
+    }
+}
+
+/// Manifest fields left out of [`tool_content_hash`]: the import record
+/// itself, and fields that change without the tool changing
+const UNHASHED_MANIFEST_FIELDS: &[&str] =
+    &["imported_from", "shared_gist", "updated_at", "disabled"];
+
+/// SHA-256 over a tool directory's manifest and files, leaving out env/,
+/// versions/ and hidden files. Used to notice local edits to an imported tool.
+pub fn tool_content_hash(dir: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+
+    fn collect(dir: &Path, rel: &Path, files: &mut Vec<(PathBuf, PathBuf)>) -> Result<()> {
+        for entry in fs::read_dir(dir)?.flatten() {
+            let name = entry.file_name();
+            let top_level = rel.as_os_str().is_empty();
+            if name.to_string_lossy().starts_with('.')
+                || (top_level && (name == "env" || name == "versions" || name == "manifest.json"))
+            {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                collect(&path, &rel.join(&name), files)?;
+            } else {
+                files.push((rel.join(&name), path));
+            }
+        }
+        Ok(())
+    }
+
+    let mut manifest: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(dir.join("manifest.json")).context("Failed to read manifest.json")?,
+    )?;
+    if let Some(obj) = manifest.as_object_mut() {
+        for field in UNHASHED_MANIFEST_FIELDS {
+            obj.remove(*field);
+        }
+    }
+
+    let mut files = Vec::new();
+    collect(dir, Path::new(""), &mut files)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(manifest.to_string().as_bytes());
+    for (rel, path) in files {
+        hasher.update([0]);
+        hasher.update(rel.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(fs::read(&path)?);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// A manifest value that differs between two versions. Nested objects are
+/// compared key by key, so `field` is a dotted path like `input_schema.properties.text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestChange {
+    pub field: String,
+    pub from: Option<serde_json::Value>,
+    pub to: Option<serde_json::Value>,
+}
+
+/// Differences between two versions of a tool
+#[derive(Debug, Clone)]
+pub struct VersionDiff {
+    pub from: String,
+    pub to: String,
+    /// Unified diff of the source; `None` when it can't be compared,
+    /// empty when the source is unchanged
+    pub source_diff: Option<String>,
+    /// Old and new module of a WASM tool whose source wasn't stored for both versions
+    pub module_change: Option<(ModuleInfo, ModuleInfo)>,
+    pub manifest_changes: Vec<ManifestChange>,
+}
+
+/// Compare two directories holding a version of a tool, such as the
+/// installed tool and a fresh copy of its import source
+pub fn diff_tool_dirs(name: &str, old_dir: &Path, new_dir: &Path) -> Result<VersionDiff> {
+    let old = dir_snapshot(old_dir, name, "old")?;
+    let new = dir_snapshot(new_dir, name, "new")?;
+    Ok(diff_snapshots(name, old, new))
+}
+
+/// The manifest and source in a tool or version directory; `version` is
+/// used when the manifest has none
+fn dir_snapshot(dir: &Path, name: &str, version: &str) -> Result<VersionSnapshot> {
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json"))?)?;
+    let version = manifest
+        .get("version")
+        .and_then(|v| v.as_str())
+        .unwrap_or(version)
+        .to_string();
+    let source = read_version_source(dir, &manifest);
+    let module = match manifest.get("tool_type").and_then(|t| t.as_str()) {
+        Some("wasm") => fs::read(dir.join(format!("{}.wasm", name)))
+            .ok()
+            .map(|bytes| ModuleInfo::of(&bytes)),
+        _ => None,
+    };
+
+    Ok(VersionSnapshot {
+        version,
+        manifest,
+        source,
+        module,
+    })
+}
+
+/// A unified diff of the source plus the manifest fields that changed
+/// between two snapshots of a tool
+fn diff_snapshots(name: &str, old: VersionSnapshot, new: VersionSnapshot) -> VersionDiff {
+    // WASM versions missing their source can only be compared by module
+    let module_change = match (&old.module, &new.module) {
+        (Some(a), Some(b)) if old.source.is_none() || new.source.is_none() => {
+            Some((a.clone(), b.clone()))
+        }
+        _ => None,
+    };
+    let source_diff = match (&old.source, &new.source) {
+        (None, None) => None,
+        _ if module_change.is_some() => None,
+        (a, b) => Some(unified_diff(
+            a.as_deref().unwrap_or(""),
+            b.as_deref().unwrap_or(""),
+            &format!("{}@{}", name, old.version),
+            &format!("{}@{}", name, new.version),
+        )),
+    };
+
+    let mut changes = Vec::new();
+    manifest_changes(&old.manifest, &new.manifest, "", &mut changes);
+
+    VersionDiff {
+        manifest_changes: changes,
+        from: old.version,
+        to: new.version,
+        source_diff,
+        module_change,
+    }
+}
+
+/// Read the source code stored alongside a manifest in a tool or version directory
+fn read_version_source(dir: &Path, manifest: &serde_json::Value) -> Option<String> {
+    match manifest.get("tool_type").and_then(|t| t.as_str()) {
+        Some("wasm") => fs::read_to_string(dir.join("src.rs")).ok(),
+        Some("script") => {
+            let entry = manifest
+                .get("entry_file")
+                .and_then(|e| e.as_str())
+                .map(|e| dir.join(e))
+                .filter(|p| p.exists());
+            let path = entry.or_else(|| {
+                fs::read_dir(dir)
+                    .ok()?
+                    .flatten()
+                    .map(|e| e.path())
+                    .find(|p| {
+                        p.extension()
+                            .and_then(|e| e.to_str())
+                            .map(|e| SCRIPT_EXTENSIONS.contains(&e))
+                            .unwrap_or(false)
+                    })
+            })?;
+            fs::read_to_string(path).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Line-based unified diff with three lines of context
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    similar::TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_label, new_label)
+        .to_string()
+}
+
+/// Compare two manifests field by field, ignoring bookkeeping fields and
+/// descending into nested objects
+fn manifest_changes(
+    old: &serde_json::Value,
+    new: &serde_json::Value,
+    prefix: &str,
+    changes: &mut Vec<ManifestChange>,
+) {
+    let empty = serde_json::Map::new();
+    let old = old.as_object().unwrap_or(&empty);
+    let new = new.as_object().unwrap_or(&empty);
+
+    let fields: std::collections::BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    for field in fields {
+        if prefix.is_empty() && VOLATILE_MANIFEST_FIELDS.contains(&field.as_str()) {
+            continue;
+        }
+        let path = if prefix.is_empty() {
+            field.clone()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+        match (old.get(field), new.get(field)) {
+            (a, b) if a == b => {}
+            (Some(a), Some(b)) if a.is_object() && b.is_object() => {
+                manifest_changes(a, b, &path, changes)
+            }
+            (a, b) => changes.push(ManifestChange {
+                field: path,
+                from: a.cloned(),
+                to: b.cloned(),
+            }),
+        }
+    }
+}
+
+/// Rewrite a tool directory (or a version backup) to use a new tool name.
+/// Renames files named after the tool (e.g. `old.wasm`, `old.py`) and updates
+/// the manifest's name and entry file, then applies `edit` to the manifest.
+fn retarget_tool_dir(
+    dir: &Path,
+    old_name: &str,
+    new_name: &str,
+    edit: impl FnOnce(&mut ToolManifest),
+) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        // Fixed-name files stay put even if the tool happens to share their stem
+        if matches!(
+            entry.file_name().to_str(),
+            Some("manifest.json") | Some("src.rs")
+        ) {
+            continue;
+        }
+        if path.file_stem().and_then(|s| s.to_str()) == Some(old_name) {
+            let new_file = match path.extension().and_then(|e| e.to_str()) {
+                Some(ext) => format!("{}.{}", new_name, ext),
+                None => new_name.to_string(),
+            };
+            fs::rename(&path, dir.join(new_file))?;
+        }
+    }
+
+    let manifest_path = dir.join("manifest.json");
+    if !manifest_path.exists() {
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&manifest_path)?;
+    let mut manifest: ToolManifest = serde_json::from_str(&content)?;
+    manifest.name = new_name.to_string();
+    if let Some(entry) = manifest.entry_file.clone() {
+        let entry_path = Path::new(&entry);
+        if entry_path.file_stem().and_then(|s| s.to_str()) == Some(old_name) {
+            manifest.entry_file = Some(match entry_path.extension().and_then(|e| e.to_str()) {
+                Some(ext) => format!("{}.{}", new_name, ext),
+                None => new_name.to_string(),
+            });
+        }
+    }
+    edit(&mut manifest);
+
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    Ok(())
+}
+
+/// Recursively copy a directory, skipping the given top-level entries
+fn copy_dir_filtered(src: &Path, dst: &Path, skip: &[&str]) -> Result<()> {
+    fs::create_dir_all(dst)?;
+
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        if skip.iter().any(|s| file_name == *s) {
+            continue;
+        }
+
+        let src_path = entry.path();
+        let dst_path = dst.join(&file_name);
+        let file_type = entry.file_type()?;
+
+        if file_type.is_symlink() {
+            // Preserve symlinks (e.g. the interpreter link inside a Python venv)
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(fs::read_link(&src_path)?, &dst_path)?;
+            #[cfg(not(unix))]
+            fs::copy(&src_path, &dst_path).map(|_| ())?;
+        } else if file_type.is_dir() {
+            copy_dir_filtered(&src_path, &dst_path, &[])?;
+        } else {
+            fs::copy(&src_path, &dst_path)?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Simple version comparison (for sorting)
-fn version_compare(a: &str, b: &str) -> std::cmp::Ordering {
+pub(crate) fn version_compare(a: &str, b: &str) -> std::cmp::Ordering {
     let a_parts: Vec<u32> = a.split('.').filter_map(|s| s.parse().ok()).collect();
     let b_parts: Vec<u32> = b.split('.').filter_map(|s| s.parse().ok()).collect();
 
@@ -1145,3 +2497,50 @@ fn version_compare(a: &str, b: &str) -> std::cmp::Ordering {
     }
     std::cmp::Ordering::Equal
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_tool_name() {
+        for ok in ["fetch", "json-to-csv", "step_2", "A1"] {
+            assert!(validate_tool_name(ok).is_ok(), "{} should be valid", ok);
+        }
+        for bad in [
+            "",
+            "../etc",
+            "a/b",
+            "has space",
+            "dot.name",
+            "naïve",
+            "call_tool",
+        ] {
+            assert!(
+                validate_tool_name(bad).is_err(),
+                "{:?} should be rejected",
+                bad
+            );
+        }
+        let err = validate_tool_name("memory").unwrap_err().to_string();
+        assert!(err.contains("reserved"), "{}", err);
+        for dir in ["exports", "backups"] {
+            let err = validate_tool_name(dir).unwrap_err().to_string();
+            assert!(err.contains("used by the server"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_chrono_now_is_current_rfc3339() {
+        let now = chrono_now();
+        assert!(now.ends_with('Z'), "{}", now);
+        let parsed = chrono::DateTime::parse_from_rfc3339(&now).unwrap();
+        let drift = chrono::Utc::now().signed_duration_since(parsed);
+        assert!(
+            drift.num_seconds().abs() <= 5,
+            "{} is off by {}",
+            now,
+            drift
+        );
+    }
+}
@@ -4,13 +4,15 @@ use serde_json::Value;
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::sync::Arc;
-use wasmtime::{Engine, Linker, Module, Store};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+use wasmtime::{Caller, Config, Engine, Linker, Module, Store, Trap, UpdateDeadline};
 use wasmtime_wasi::preview1::{self, WasiP1Ctx};
 use wasmtime_wasi::{pipe::MemoryOutputPipe, WasiCtxBuilder};
 
 use crate::client::McpClientManager;
-use crate::registry::{ToolConfig, ToolType};
+use crate::registry::{ArgMode, RateLimit, ToolConfig, ToolType};
 
 // ==================== Sandbox Configuration ====================
 
@@ -28,6 +30,33 @@ pub enum SandboxMode {
     Nsjail,
 }
 
+impl SandboxMode {
+    /// Parse a mode name as in SKILLZ_SANDBOX: "none", "bubblewrap" (or
+    /// "bwrap"), "firejail" or "nsjail"
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "none" | "" => Ok(Self::None),
+            "bubblewrap" | "bwrap" => Ok(Self::Bubblewrap),
+            "firejail" => Ok(Self::Firejail),
+            "nsjail" => Ok(Self::Nsjail),
+            other => anyhow::bail!(
+                "Unknown sandbox mode '{}': expected none, bubblewrap, firejail or nsjail",
+                other
+            ),
+        }
+    }
+
+    /// The sandbox program, or None when scripts run unsandboxed
+    pub fn program(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Bubblewrap => Some("bwrap"),
+            Self::Firejail => Some("firejail"),
+            Self::Nsjail => Some("nsjail"),
+        }
+    }
+}
+
 /// Configuration for script sandboxing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SandboxConfig {
@@ -39,6 +68,9 @@ pub struct SandboxConfig {
     pub read_paths: Vec<PathBuf>,
     /// Directories to allow write access
     pub write_paths: Vec<PathBuf>,
+    /// Mount workspace roots read-only (set for tools annotated readOnlyHint)
+    #[serde(default)]
+    pub read_only_roots: bool,
     /// Memory limit in MB (0 = unlimited)
     pub memory_limit_mb: u64,
     /// CPU time limit in seconds (0 = unlimited)
@@ -52,6 +84,7 @@ impl Default for SandboxConfig {
             allow_network: false,
             read_paths: vec![],
             write_paths: vec![],
+            read_only_roots: false,
             memory_limit_mb: 512,
             time_limit_secs: 30,
         }
@@ -60,21 +93,41 @@ impl Default for SandboxConfig {
 
 impl SandboxConfig {
     /// Check if the required sandbox tool is available
-    #[allow(dead_code)]
     pub fn check_available(&self) -> Result<bool> {
+        match self.mode.program() {
+            None => Ok(true),
+            Some(program) => Ok(Command::new(program).arg("--version").output().is_ok()),
+        }
+    }
+
+    /// The sandbox for one call of a tool: a tool annotated readOnlyHint
+    /// gets its roots mounted read-only and no extra write paths
+    pub fn for_call(&self, read_only: bool) -> SandboxConfig {
+        let mut config = self.clone();
+        if read_only {
+            config.write_paths.clear();
+            config.read_only_roots = true;
+        }
+        config
+    }
+
+    /// Arguments for the sandbox program (before `--` and the command)
+    #[cfg(unix)]
+    pub fn sandbox_args(&self, script_path: &Path, roots: &[String]) -> Vec<String> {
         match self.mode {
-            SandboxMode::None => Ok(true),
-            SandboxMode::Bubblewrap => Ok(Command::new("bwrap").arg("--version").output().is_ok()),
-            SandboxMode::Firejail => Ok(Command::new("firejail").arg("--version").output().is_ok()),
-            SandboxMode::Nsjail => Ok(Command::new("nsjail").arg("--version").output().is_ok()),
+            SandboxMode::None => vec![],
+            SandboxMode::Bubblewrap => self.build_bwrap_args(script_path, roots),
+            SandboxMode::Firejail => self.build_firejail_args(script_path, roots),
+            SandboxMode::Nsjail => self.build_nsjail_args(script_path, roots),
         }
     }
 
     /// Wrap a command with sandbox
+    #[cfg(unix)]
     pub fn wrap_command(&self, cmd: &mut Command, script_path: &Path, roots: &[String]) {
-        if self.mode == SandboxMode::None {
+        let Some(sandbox_cmd) = self.mode.program() else {
             return;
-        }
+        };
 
         let program = cmd.get_program().to_string_lossy().to_string();
         let args: Vec<String> = cmd
@@ -83,22 +136,9 @@ impl SandboxConfig {
             .collect();
 
         // Build sandbox command
-        let sandbox_args = match self.mode {
-            SandboxMode::None => return,
-            SandboxMode::Bubblewrap => self.build_bwrap_args(script_path, roots),
-            SandboxMode::Firejail => self.build_firejail_args(script_path, roots),
-            SandboxMode::Nsjail => self.build_nsjail_args(script_path, roots),
-        };
+        let sandbox_args = self.sandbox_args(script_path, roots);
 
         // Replace the command with sandboxed version
-        let sandbox_cmd = match self.mode {
-            SandboxMode::Bubblewrap => "bwrap",
-            SandboxMode::Firejail => "firejail",
-            SandboxMode::Nsjail => "nsjail",
-            SandboxMode::None => unreachable!(),
-        };
-
-        // Clear and rebuild command
         *cmd = Command::new(sandbox_cmd);
         for arg in sandbox_args {
             cmd.arg(arg);
@@ -109,6 +149,11 @@ impl SandboxConfig {
         }
     }
 
+    /// Sandboxing relies on Linux tools, so elsewhere commands run as-is
+    #[cfg(not(unix))]
+    pub fn wrap_command(&self, _cmd: &mut Command, _script_path: &Path, _roots: &[String]) {}
+
+    #[cfg(unix)]
     fn build_bwrap_args(&self, script_path: &Path, roots: &[String]) -> Vec<String> {
         let mut args = vec![
             "--unshare-all".to_string(),
@@ -139,8 +184,13 @@ impl SandboxConfig {
         }
 
         // Add workspace roots
+        let root_bind = if self.read_only_roots {
+            "--ro-bind"
+        } else {
+            "--bind"
+        };
         for root in roots {
-            args.push("--bind".to_string());
+            args.push(root_bind.to_string());
             args.push(root.clone());
             args.push(root.clone());
         }
@@ -167,6 +217,7 @@ impl SandboxConfig {
         args
     }
 
+    #[cfg(unix)]
     fn build_firejail_args(&self, _script_path: &Path, roots: &[String]) -> Vec<String> {
         let mut args = vec![
             "--quiet".to_string(),
@@ -185,6 +236,9 @@ impl SandboxConfig {
         // Add workspace roots as whitelist
         for root in roots {
             args.push(format!("--whitelist={}", root));
+            if self.read_only_roots {
+                args.push(format!("--read-only={}", root));
+            }
         }
 
         // Memory limit
@@ -203,6 +257,7 @@ impl SandboxConfig {
         args
     }
 
+    #[cfg(unix)]
     fn build_nsjail_args(&self, script_path: &Path, roots: &[String]) -> Vec<String> {
         let mut args = vec![
             "--mode".to_string(),
@@ -225,9 +280,9 @@ impl SandboxConfig {
             args.push(parent.to_string_lossy().to_string());
         }
 
-        // Add workspace roots with write access
+        // Add workspace roots, with write access unless read-only
         for root in roots {
-            args.push("-B".to_string());
+            args.push(if self.read_only_roots { "-R" } else { "-B" }.to_string());
             args.push(root.clone());
         }
 
@@ -344,9 +399,10 @@ impl Default for ExecutionContext {
             }
         }
 
-        // Forward all SKILLZ_* env vars (for secrets like SKILLZ_OPENAI_KEY)
+        // Forward all SKILLZ_* env vars (for secrets like SKILLZ_OPENAI_KEY),
+        // except the importer's git token
         for (key, val) in std::env::vars() {
-            if key.starts_with("SKILLZ_") {
+            if key.starts_with("SKILLZ_") && key != crate::importer::GIT_TOKEN_ENV {
                 env.insert(key, val);
             }
         }
@@ -451,10 +507,222 @@ pub struct StreamChunk {
 pub struct ScriptResult {
     pub output: Value,
     pub logs: Vec<LogEntry>,
+    /// Exit code of the script process (None if it was killed by a signal)
+    pub exit_code: Option<i32>,
     #[allow(dead_code)]
     pub progress: Vec<ProgressUpdate>,
 }
 
+/// Command-line flags for an argv-mode script call.
+///
+/// Each top-level argument becomes `--key value`: `true` is a bare `--key`,
+/// `false` and `null` are left out, arrays repeat the flag per item and
+/// objects are passed as JSON. The special key `_` holds positional
+/// arguments, which come after the flags.
+pub fn argv_flags(arguments: &Value) -> Result<Vec<String>> {
+    fn scalar(value: &Value) -> String {
+        match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }
+    }
+
+    let map = match arguments {
+        Value::Null => return Ok(Vec::new()),
+        Value::Object(map) => map,
+        _ => anyhow::bail!("Arguments of an argv-mode tool must be an object"),
+    };
+
+    let mut flags = Vec::new();
+    for (key, value) in map.iter().filter(|(key, _)| *key != "_") {
+        let flag = format!("--{}", key);
+        match value {
+            Value::Null | Value::Bool(false) => {}
+            Value::Bool(true) => flags.push(flag),
+            Value::Array(items) => {
+                for item in items {
+                    flags.push(flag.clone());
+                    flags.push(scalar(item));
+                }
+            }
+            other => {
+                flags.push(flag);
+                flags.push(scalar(other));
+            }
+        }
+    }
+
+    match map.get("_") {
+        None | Some(Value::Null) => {}
+        Some(Value::Array(items)) => flags.extend(items.iter().map(scalar)),
+        Some(other) => flags.push(scalar(other)),
+    }
+    Ok(flags)
+}
+
+// ==================== WASM Host Functions ====================
+
+/// Import module of the functions skillz provides to WASM tools
+pub const WASM_HOST_MODULE: &str = "skillz";
+
+/// Seconds before a `skillz_http_fetch` request is abandoned
+pub const HTTP_FETCH_TIMEOUT_SECS: u64 = 30;
+
+/// Largest response body handed to a WASM tool
+pub const HTTP_FETCH_MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// Store data of a WASM tool call: WASI plus host-function state
+struct WasmState {
+    wasi: WasiP1Ctx,
+    /// Whether `skillz_http_fetch` may reach the network
+    allow_network: bool,
+    /// Response of the last `skillz_http_fetch`, copied out by `skillz_http_response`
+    http_response: Vec<u8>,
+}
+
+/// A `skillz_http_fetch` request, passed as JSON in guest memory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpFetchRequest {
+    pub url: String,
+    /// HTTP method (default: GET)
+    #[serde(default = "default_http_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: std::collections::HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+fn default_http_method() -> String {
+    "GET".to_string()
+}
+
+/// Register the `skillz` host functions.
+///
+/// ABI (all pointers/lengths are i32 offsets into the guest's exported memory):
+/// - `skillz_http_fetch(req_ptr, req_len) -> i32` performs the request (JSON,
+///   see [`HttpFetchRequest`]) and returns the byte length of the response JSON:
+///   `{"status", "headers", "body"}` (`body_base64` for non-UTF-8 bodies) or `{"error"}`.
+/// - `skillz_http_response(buf_ptr, buf_len) -> i32` copies that response into
+///   the buffer and returns its length, or -1 if the buffer is too small.
+fn add_host_functions(linker: &mut Linker<WasmState>) -> Result<()> {
+    linker.func_wrap(
+        WASM_HOST_MODULE,
+        "skillz_http_fetch",
+        |mut caller: Caller<'_, WasmState>, ptr: i32, len: i32| -> Result<i32> {
+            let request = read_guest_bytes(&mut caller, ptr, len)?;
+            let response = if !caller.data().allow_network {
+                serde_json::json!({
+                    "error": "Network access is disabled for WASM tools (set SKILLZ_SANDBOX_NETWORK=1 to allow it)"
+                })
+            } else {
+                match serde_json::from_slice::<HttpFetchRequest>(&request) {
+                    Ok(request) => http_fetch_blocking(request)
+                        .unwrap_or_else(|e| serde_json::json!({"error": e.to_string()})),
+                    Err(e) => serde_json::json!({"error": format!("Invalid request: {}", e)}),
+                }
+            };
+            let bytes = serde_json::to_vec(&response)?;
+            let len = i32::try_from(bytes.len())?;
+            caller.data_mut().http_response = bytes;
+            Ok(len)
+        },
+    )?;
+
+    linker.func_wrap(
+        WASM_HOST_MODULE,
+        "skillz_http_response",
+        |mut caller: Caller<'_, WasmState>, ptr: i32, len: i32| -> Result<i32> {
+            let response = std::mem::take(&mut caller.data_mut().http_response);
+            if (len.max(0) as usize) < response.len() {
+                caller.data_mut().http_response = response;
+                return Ok(-1);
+            }
+            guest_memory(&mut caller)?.write(&mut caller, ptr as u32 as usize, &response)?;
+            Ok(response.len() as i32)
+        },
+    )?;
+
+    Ok(())
+}
+
+fn guest_memory(caller: &mut Caller<'_, WasmState>) -> Result<wasmtime::Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .context("WASM module does not export its memory")
+}
+
+fn read_guest_bytes(caller: &mut Caller<'_, WasmState>, ptr: i32, len: i32) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; len.max(0) as usize];
+    guest_memory(caller)?.read(&*caller, ptr as u32 as usize, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Run [`http_fetch`] from the (blocking) WASM call.
+///
+/// The request gets its own thread and current-thread runtime: blocking on the
+/// caller's runtime panics when the WASM call runs on an async worker thread.
+fn http_fetch_blocking(request: HttpFetchRequest) -> Result<Value> {
+    std::thread::spawn(move || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(http_fetch(request))
+    })
+    .join()
+    .map_err(|_| anyhow::anyhow!("HTTP fetch thread panicked"))?
+}
+
+/// Perform an HTTP request on behalf of a WASM tool
+pub async fn http_fetch(request: HttpFetchRequest) -> Result<Value> {
+    let method = reqwest::Method::from_bytes(request.method.to_uppercase().as_bytes())
+        .with_context(|| format!("Invalid HTTP method '{}'", request.method))?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(HTTP_FETCH_TIMEOUT_SECS))
+        .build()?;
+
+    let mut builder = client.request(method, &request.url);
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+    let mut response = builder.send().await?;
+
+    let status = response.status().as_u16();
+    let headers: serde_json::Map<String, Value> = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                Value::String(String::from_utf8_lossy(value.as_bytes()).to_string()),
+            )
+        })
+        .collect();
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > HTTP_FETCH_MAX_BODY_BYTES {
+            anyhow::bail!("Response body exceeds {} bytes", HTTP_FETCH_MAX_BODY_BYTES);
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let mut result = serde_json::json!({"status": status, "headers": headers});
+    match String::from_utf8(body) {
+        Ok(text) => result["body"] = Value::String(text),
+        Err(e) => {
+            use base64::Engine as _;
+            result["body_base64"] =
+                Value::String(base64::engine::general_purpose::STANDARD.encode(e.into_bytes()));
+        }
+    }
+    Ok(result)
+}
+
 // ==================== Tool Runtime ====================
 
 /// Type alias for elicitation handler callback
@@ -521,6 +789,203 @@ pub struct ResourceContent {
     pub blob: Option<String>, // base64 encoded
 }
 
+/// URI prefix of workspace files readable as resources:
+/// `skillz://files/{path}`, with `path` relative to a workspace root or absolute
+pub const FILE_RESOURCE_PREFIX: &str = "skillz://files/";
+
+/// Largest file served as a `skillz://files/` resource
+pub const MAX_FILE_RESOURCE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// MIME type by file extension; `None` for extensions not listed
+fn file_mime_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "xml" => "text/xml",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        "py" => "text/x-python",
+        "rs" => "text/x-rust",
+        "rb" => "text/x-ruby",
+        "sh" => "text/x-shellscript",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "wasm" => "application/wasm",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        _ => return None,
+    })
+}
+
+/// Whether content of this MIME type is returned as `text`
+fn is_text_mime(mime: &str) -> bool {
+    mime.starts_with("text/")
+        || matches!(
+            mime,
+            "application/json" | "application/yaml" | "application/toml" | "image/svg+xml"
+        )
+}
+
+/// The directory a script tool runs in: its manifest's `working_dir`,
+/// resolved against the first workspace root when relative
+pub fn script_working_dir(working_dir: &str, roots: &[String]) -> Result<PathBuf> {
+    let dir = match roots.first() {
+        Some(root) => Path::new(root).join(working_dir),
+        None => PathBuf::from(working_dir),
+    };
+    if !dir.is_dir() {
+        anyhow::bail!("working_dir {} is not a directory", dir.display());
+    }
+    Ok(dir)
+}
+
+/// Expand `${VAR}` in a manifest `env` value from the server's
+/// environment; unset variables expand to nothing
+pub fn expand_env(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let name = &rest[start + 2..start + 2 + len];
+        out.push_str(&std::env::var(name).unwrap_or_default());
+        rest = &rest[start + 3 + len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn absolute_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    std::env::current_dir()
+        .map(|cwd| cwd.join(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Whether `call_tool` output comes from a failed run: a script that
+/// exited non-zero or was killed reports its `exit_code` next to `result`
+pub fn is_failed_run(output: &Value) -> bool {
+    output
+        .get("exit_code")
+        .is_some_and(|code| code.as_i64() != Some(0))
+}
+
+/// `path` with `.` and `..` resolved lexically, without touching the
+/// filesystem
+fn lexical_normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Read a `skillz://files/{path}` resource. The file must lie inside one of
+/// `roots`, both as written and after resolving symlinks; a relative path is
+/// looked up in each root in turn. Paths outside the roots are refused
+/// before the filesystem is consulted, so whether they exist isn't
+/// revealed. Text files come back as `text`, anything else as a base64
+/// `blob`, with the MIME type guessed from the extension (or the content).
+pub fn read_file_resource(uri: &str, roots: &[String]) -> Result<ResourceContent> {
+    let requested = uri
+        .strip_prefix(FILE_RESOURCE_PREFIX)
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Invalid file resource URI: {}", uri))?;
+    let canonical_roots: Vec<PathBuf> = roots
+        .iter()
+        .filter_map(|r| std::fs::canonicalize(r).ok())
+        .collect();
+
+    let mut allowed_roots: Vec<PathBuf> = roots
+        .iter()
+        .map(|r| lexical_normalize(&absolute_path(Path::new(r))))
+        .collect();
+    allowed_roots.extend(canonical_roots.iter().cloned());
+
+    let candidates: Vec<PathBuf> = if Path::new(requested).is_absolute() {
+        vec![PathBuf::from(requested)]
+    } else {
+        canonical_roots.iter().map(|r| r.join(requested)).collect()
+    };
+    let candidates: Vec<PathBuf> = candidates
+        .iter()
+        .map(|c| lexical_normalize(c))
+        .filter(|c| allowed_roots.iter().any(|root| c.starts_with(root)))
+        .collect();
+    if candidates.is_empty() {
+        anyhow::bail!(
+            "Access denied: {} is outside the workspace roots",
+            requested
+        );
+    }
+    let path = candidates
+        .iter()
+        .find_map(|c| std::fs::canonicalize(c).ok())
+        .ok_or_else(|| anyhow::anyhow!("File not found: {}", requested))?;
+    if !canonical_roots.iter().any(|root| path.starts_with(root)) {
+        anyhow::bail!(
+            "Access denied: {} is outside the workspace roots",
+            requested
+        );
+    }
+
+    let metadata = std::fs::metadata(&path)?;
+    if !metadata.is_file() {
+        anyhow::bail!("Not a file: {}", requested);
+    }
+    if metadata.len() > MAX_FILE_RESOURCE_BYTES {
+        anyhow::bail!(
+            "File too large: {} bytes (limit {})",
+            metadata.len(),
+            MAX_FILE_RESOURCE_BYTES
+        );
+    }
+    let bytes = std::fs::read(&path)?;
+
+    let mime = match file_mime_type(&path) {
+        Some(mime) => mime,
+        None if !bytes.contains(&0) && std::str::from_utf8(&bytes).is_ok() => "text/plain",
+        None => "application/octet-stream",
+    };
+    let (text, blob) = match String::from_utf8(bytes) {
+        Ok(text) if is_text_mime(mime) => (Some(text), None),
+        Ok(text) => (None, Some(text.into_bytes())),
+        Err(e) => (None, Some(e.into_bytes())),
+    };
+    use base64::Engine as _;
+    Ok(ResourceContent {
+        uri: uri.to_string(),
+        mime_type: Some(mime.to_string()),
+        text,
+        blob: blob.map(|b| base64::engine::general_purpose::STANDARD.encode(b)),
+    })
+}
+
 /// Type alias for resource list handler callback
 pub type ResourceListHandler = std::sync::Arc<
     dyn Fn()
@@ -542,6 +1007,7 @@ pub type ResourceReadHandler = std::sync::Arc<
 /// Type alias for tools/call handler callback (tools calling other tools)
 pub type ToolCallHandler = std::sync::Arc<
     dyn Fn(
+            String,        // calling tool's name
             String,        // tool name
             Option<Value>, // arguments
         ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Value>> + Send>>
@@ -556,11 +1022,119 @@ pub type StreamHandler = std::sync::Arc<
         + Sync,
 >;
 
+/// Error returned when a tool call is cancelled while running
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Tool call cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Error returned when a WASM tool uses up its manifest's `wasm_fuel`
+#[derive(Debug)]
+pub struct FuelExhausted {
+    pub fuel: u64,
+}
+
+impl std::fmt::Display for FuelExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WASM fuel exhausted (budget: {} units)", self.fuel)
+    }
+}
+
+impl std::error::Error for FuelExhausted {}
+
+/// Error returned when a tool is called faster than its manifest's rate limit allows
+#[derive(Debug)]
+pub struct RateLimited {
+    pub tool: String,
+    pub limit: RateLimit,
+    /// Time until the next call is allowed
+    pub retry_after: std::time::Duration,
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Tool '{}' is rate limited ({} calls per {}s), retry in {}s",
+            self.tool,
+            self.limit.max,
+            self.limit.per_secs,
+            self.retry_after.as_secs_f64().ceil() as u64
+        )
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Token bucket: holds up to `max` tokens, refilled evenly over `per_secs`
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: std::time::Instant,
+}
+
+/// Per-tool token buckets, shared by all clones of a runtime
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<std::collections::HashMap<String, TokenBucket>>>,
+}
+
+impl RateLimiter {
+    /// Take one call from `tool`'s bucket, or report how long until one is available
+    pub fn try_acquire(&self, tool: &str, limit: RateLimit) -> Result<(), RateLimited> {
+        let now = std::time::Instant::now();
+        let capacity = limit.max as f64;
+        let per_sec = capacity / limit.per_secs as f64;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets.entry(tool.to_string()).or_insert(TokenBucket {
+            tokens: capacity,
+            refilled_at: now,
+        });
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_sec).min(capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(RateLimited {
+                tool: tool.to_string(),
+                limit,
+                retry_after: std::time::Duration::from_secs_f64((1.0 - bucket.tokens) / per_sec),
+            })
+        }
+    }
+}
+
+/// Default number of tools allowed to run at once: one per CPU
+pub fn default_max_concurrency() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+/// Create the WASM engine. Epoch interruption lets a cancelled call trap
+/// out of a running module; fuel enforces a manifest's `wasm_fuel`.
+fn new_engine() -> Result<Engine> {
+    let mut config = Config::new();
+    config.epoch_interruption(true);
+    config.consume_fuel(true);
+    Engine::new(&config)
+}
+
 #[derive(Clone)]
 pub struct ToolRuntime {
     engine: Engine,
     context: ExecutionContext,
-    sandbox_config: SandboxConfig,
+    /// Sandbox for script tools (shared by clones, changed with the sandbox tool)
+    sandbox_config: Arc<std::sync::RwLock<SandboxConfig>>,
     memory: Option<crate::memory::Memory>,
     /// Handler for elicitation requests (user input)
     elicitation_handler: Option<ElicitationHandler>,
@@ -582,18 +1156,29 @@ pub struct ToolRuntime {
     client_manager: Option<Arc<McpClientManager>>,
     /// Extra environment variables to inject (e.g., from services)
     extra_env: std::collections::HashMap<String, String>,
+    /// Cancels the running tool call (kills the script / traps the WASM module)
+    cancel_token: Option<CancellationToken>,
+    /// Bounds how many WASM/script tools execute at once (shared by clones)
+    concurrency: Option<Arc<Semaphore>>,
+    /// Enforces manifest `rate_limit`s (shared by clones)
+    rate_limiter: RateLimiter,
 }
 
 impl ToolRuntime {
     pub fn new() -> Result<Self> {
-        let engine = Engine::default();
+        let engine = new_engine()?;
 
         // Check for sandbox mode from environment
-        let sandbox_mode = match std::env::var("SKILLZ_SANDBOX").as_deref() {
-            Ok("bubblewrap") | Ok("bwrap") => SandboxMode::Bubblewrap,
-            Ok("firejail") => SandboxMode::Firejail,
-            Ok("nsjail") => SandboxMode::Nsjail,
-            _ => SandboxMode::None,
+        let sandbox_mode = std::env::var("SKILLZ_SANDBOX")
+            .ok()
+            .and_then(|mode| SandboxMode::parse(&mode).ok())
+            .unwrap_or_default();
+        #[cfg(not(unix))]
+        let sandbox_mode = {
+            if sandbox_mode != SandboxMode::None {
+                eprintln!("⚠️ SKILLZ_SANDBOX is only supported on Unix; scripts run unsandboxed");
+            }
+            SandboxMode::None
         };
 
         let sandbox_config = SandboxConfig {
@@ -605,7 +1190,7 @@ impl ToolRuntime {
         Ok(Self {
             engine,
             context: ExecutionContext::default(),
-            sandbox_config,
+            sandbox_config: Arc::new(std::sync::RwLock::new(sandbox_config)),
             memory: None,
             elicitation_handler: None,
             sampling_handler: None,
@@ -617,17 +1202,20 @@ impl ToolRuntime {
             stream_handler: None,
             client_manager: None,
             extra_env: std::collections::HashMap::new(),
+            cancel_token: None,
+            concurrency: Some(Arc::new(Semaphore::new(default_max_concurrency()))),
+            rate_limiter: RateLimiter::default(),
         })
     }
 
     /// Create runtime with custom sandbox configuration
     #[allow(dead_code)]
     pub fn with_sandbox(sandbox_config: SandboxConfig) -> Result<Self> {
-        let engine = Engine::default();
+        let engine = new_engine()?;
         Ok(Self {
             engine,
             context: ExecutionContext::default(),
-            sandbox_config,
+            sandbox_config: Arc::new(std::sync::RwLock::new(sandbox_config)),
             memory: None,
             elicitation_handler: None,
             sampling_handler: None,
@@ -639,6 +1227,9 @@ impl ToolRuntime {
             stream_handler: None,
             client_manager: None,
             extra_env: std::collections::HashMap::new(),
+            cancel_token: None,
+            concurrency: Some(Arc::new(Semaphore::new(default_max_concurrency()))),
+            rate_limiter: RateLimiter::default(),
         })
     }
 
@@ -649,8 +1240,63 @@ impl ToolRuntime {
         self
     }
 
-    /// Add an environment variable to be injected when running tools
+    /// Limit how many tools run at once; further calls queue for a slot.
+    /// 0 removes the limit.
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.concurrency = (max > 0).then(|| Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    /// Detach this runtime from the concurrency limit. Used for tools called
+    /// by a running tool, which already holds a slot and would otherwise
+    /// deadlock waiting for one.
+    pub fn without_concurrency_limit(mut self) -> Self {
+        self.concurrency = None;
+        self
+    }
+
+    /// Free execution slots, or `None` when concurrency is unlimited
+    #[allow(dead_code)]
+    pub fn available_slots(&self) -> Option<usize> {
+        self.concurrency.as_ref().map(|s| s.available_permits())
+    }
+
+    /// Abort tool calls made through this runtime when `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Whether the current call has been cancelled
+    fn is_cancelled(&self) -> bool {
+        self.cancel_token
+            .as_ref()
+            .map(|t| t.is_cancelled())
+            .unwrap_or(false)
+    }
+
+    /// Run `on_cancel` on the tokio runtime if the call is cancelled before
+    /// the returned guard is dropped.
+    fn on_cancel(
+        &self,
+        on_cancel: impl FnOnce() + Send + 'static,
+    ) -> Option<tokio_util::sync::DropGuard> {
+        let token = self.cancel_token.clone()?;
+        let done = CancellationToken::new();
+        let finished = done.clone();
+        tokio::runtime::Handle::current().spawn(async move {
+            tokio::select! {
+                _ = token.cancelled() => on_cancel(),
+                _ = finished.cancelled() => {}
+            }
+        });
+        Some(done.drop_guard())
+    }
+
+    /// Add an environment variable to be injected when running tools, both
+    /// into the process and the context's `environment`
     pub fn set_env_var(&mut self, key: String, value: String) {
+        self.context.environment.insert(key.clone(), value.clone());
         self.extra_env.insert(key, value);
     }
 
@@ -668,18 +1314,38 @@ impl ToolRuntime {
         self
     }
 
+    /// The sampling handler, if one is set
+    pub fn sampling_handler(&self) -> Option<SamplingHandler> {
+        self.sampling_handler.clone()
+    }
+
     /// Set logging handler (forwards script logs to MCP client)
     pub fn with_logging_handler(mut self, handler: LoggingHandler) -> Self {
         self.logging_handler = Some(handler);
         self
     }
 
+    /// Replace (or with None, drop) the logging handler
+    pub fn set_logging_handler(&mut self, handler: Option<LoggingHandler>) {
+        self.logging_handler = handler;
+    }
+
+    /// The logging handler, if one is set
+    pub fn logging_handler(&self) -> Option<LoggingHandler> {
+        self.logging_handler.clone()
+    }
+
     /// Set progress handler (forwards script progress to MCP client)
     pub fn with_progress_handler(mut self, handler: ProgressHandler) -> Self {
         self.progress_handler = Some(handler);
         self
     }
 
+    /// The progress handler, if one is set
+    pub fn progress_handler(&self) -> Option<ProgressHandler> {
+        self.progress_handler.clone()
+    }
+
     /// Set resource handlers (for listing and reading server resources)
     pub fn with_resource_handlers(
         mut self,
@@ -716,36 +1382,136 @@ impl ToolRuntime {
     }
 
     /// Get current sandbox configuration
-    #[allow(dead_code)]
-    pub fn sandbox_config(&self) -> &SandboxConfig {
-        &self.sandbox_config
+    pub fn sandbox_config(&self) -> SandboxConfig {
+        self.sandbox_config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Replace the sandbox configuration for every clone of this runtime,
+    /// from the next tool call on. Fails, leaving it unchanged, when the
+    /// mode's sandbox program isn't installed.
+    pub fn set_sandbox_config(&self, config: SandboxConfig) -> Result<SandboxConfig> {
+        if cfg!(not(unix)) && config.mode != SandboxMode::None {
+            anyhow::bail!("Sandboxing is only supported on Unix");
+        }
+        if !config.check_available()? {
+            anyhow::bail!(
+                "Sandbox mode {:?} isn't available: '{}' was not found on PATH",
+                config.mode,
+                config.mode.program().unwrap_or_default()
+            );
+        }
+        *self
+            .sandbox_config
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = config.clone();
+        Ok(config)
     }
 
     /// Check if sandbox is available
     #[allow(dead_code)]
     pub fn sandbox_available(&self) -> bool {
-        self.sandbox_config.check_available().unwrap_or(false)
+        self.sandbox_config().check_available().unwrap_or(false)
+    }
+
+    /// Install a script tool's dependencies, reporting to the client as it goes:
+    /// a progress notification at the start, whenever a declared dependency
+    /// shows up in the installer output ("installing pandas…"), and at the end.
+    /// Every installer line is also forwarded to the logging handler.
+    ///
+    /// Blocks on the handlers, so call it from a blocking context
+    /// (e.g. `spawn_blocking`) inside a tokio runtime.
+    pub fn install_deps(
+        &self,
+        env_path: &Path,
+        interpreter: Option<&str>,
+        dependencies: &[String],
+    ) -> Result<InstallResult> {
+        let handle = tokio::runtime::Handle::try_current().ok();
+        let total = dependencies.len() as u64 + 1;
+        let progress = |current: u64, message: String| {
+            if let (Some(handler), Some(handle)) = (&self.progress_handler, &handle) {
+                handle.block_on(handler(current, total, Some(message), None));
+            }
+        };
+
+        progress(
+            0,
+            format!(
+                "Installing {} dependencies: {}",
+                dependencies.len(),
+                dependencies.join(", ")
+            ),
+        );
+
+        let pending = Mutex::new(dependencies.to_vec());
+        let installed = std::sync::atomic::AtomicU64::new(0);
+        let on_output = |line: &str| {
+            if let (Some(handler), Some(handle)) = (&self.logging_handler, &handle) {
+                handle.block_on(handler("info".to_string(), line.to_string(), None));
+            }
+            let lower = line.to_lowercase();
+            let started = {
+                let mut pending = pending.lock().unwrap_or_else(|e| e.into_inner());
+                let position = pending
+                    .iter()
+                    .position(|dep| lower.contains(&dependency_name(dep).to_lowercase()));
+                position.map(|i| pending.remove(i))
+            };
+            if let Some(dep) = started {
+                let current = installed.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                progress(current, format!("installing {}…", dependency_name(&dep)));
+            }
+        };
+
+        let result = install_tool_deps_with_output(env_path, interpreter, dependencies, &on_output);
+        let message = match &result {
+            Ok(r) if r.success => "Dependencies installed".to_string(),
+            Ok(_) | Err(_) => "Dependency installation failed".to_string(),
+        };
+        progress(total, message);
+        result
     }
 
     /// Execute a tool based on its type
-    /// Note: Pipeline tools must be executed via call_pipeline, not call_tool
+    /// Note: Pipeline tools must be executed via call_pipeline, not call_tool.
+    /// This blocks until the tool exits, so async callers must run it on a
+    /// blocking thread (`tokio::task::spawn_blocking`); WASI panics on an async
+    /// worker thread
     pub fn call_tool(&self, config: &ToolConfig, args: Value) -> Result<Value> {
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        if let Some(limit) = config.manifest.rate_limit {
+            self.rate_limiter.try_acquire(config.name(), limit)?;
+        }
         match config.tool_type() {
-            ToolType::Wasm => self.call_wasm_tool(&config.wasm_path, args),
+            ToolType::Wasm => {
+                let _slot = self.acquire_slot(config.name())?;
+                self.call_wasm_tool(&config.wasm_path, config.manifest.wasm_fuel, args)
+            }
             ToolType::Script => {
+                let _slot = self.acquire_slot(config.name())?;
                 let result = self.call_script_tool(config, args)?;
-                // Format output with logs if present
-                if result.logs.is_empty() {
+                // Plain output for a clean run; otherwise include logs and exit code
+                if result.logs.is_empty() && result.exit_code == Some(0) {
                     Ok(result.output)
                 } else {
                     let mut output = serde_json::Map::new();
                     output.insert("result".to_string(), result.output);
-                    output.insert("logs".to_string(), serde_json::to_value(&result.logs)?);
+                    if !result.logs.is_empty() {
+                        output.insert("logs".to_string(), serde_json::to_value(&result.logs)?);
+                    }
+                    output.insert("exit_code".to_string(), result.exit_code.into());
                     Ok(Value::Object(output))
                 }
             }
             ToolType::Pipeline => {
-                anyhow::bail!("Pipeline tools must be executed via call_pipeline")
+                anyhow::bail!(
+                    "Pipeline tools run through the pipeline executor, not the tool runtime"
+                )
             }
             ToolType::Mcp => {
                 if let Some(manager) = &self.client_manager {
@@ -774,6 +1540,19 @@ impl ToolRuntime {
         }
     }
 
+    /// Wait for an execution slot; the slot is released when the permit drops
+    fn acquire_slot(&self, tool_name: &str) -> Result<Option<tokio::sync::OwnedSemaphorePermit>> {
+        let Some(semaphore) = self.concurrency.clone() else {
+            return Ok(None);
+        };
+        if let Ok(permit) = semaphore.clone().try_acquire_owned() {
+            return Ok(Some(permit));
+        }
+        eprintln!("⏳ {} waiting for a free execution slot", tool_name);
+        let permit = futures::executor::block_on(semaphore.acquire_owned())?;
+        Ok(Some(permit))
+    }
+
     /// Execute a tool by name (for pipelines)
     /// Returns the result as a JSON Value to preserve structure for pipeline variable resolution
     pub async fn call_tool_by_name(
@@ -799,10 +1578,11 @@ impl ToolRuntime {
         Ok(result)
     }
 
-    /// Execute a WASM tool
-    fn call_wasm_tool(&self, wasm_path: &Path, _args: Value) -> Result<Value> {
-        let mut linker: Linker<WasiP1Ctx> = Linker::new(&self.engine);
-        preview1::add_to_linker_sync(&mut linker, |t| t)?;
+    /// Execute a WASM tool (blocking; see [`Self::call_tool`])
+    fn call_wasm_tool(&self, wasm_path: &Path, fuel: Option<u64>, _args: Value) -> Result<Value> {
+        let mut linker: Linker<WasmState> = Linker::new(&self.engine);
+        preview1::add_to_linker_sync(&mut linker, |t| &mut t.wasi)?;
+        add_host_functions(&mut linker)?;
 
         let stdout = MemoryOutputPipe::new(4096);
 
@@ -811,7 +1591,28 @@ impl ToolRuntime {
             .stdout(stdout.clone())
             .build_p1();
 
-        let mut store = Store::new(&self.engine, wasi);
+        let mut store = Store::new(
+            &self.engine,
+            WasmState {
+                wasi,
+                allow_network: self.sandbox_config().allow_network,
+                http_response: Vec::new(),
+            },
+        );
+
+        // Fuel is always on in the engine, so no budget means all of it
+        store.set_fuel(fuel.unwrap_or(u64::MAX))?;
+
+        // Every epoch tick checks this call's token; only a cancelled call traps
+        let token = self.cancel_token.clone();
+        store.set_epoch_deadline(1);
+        store.epoch_deadline_callback(move |_| match &token {
+            Some(t) if t.is_cancelled() => Err(Cancelled.into()),
+            _ => Ok(UpdateDeadline::Continue(1)),
+        });
+        let engine = self.engine.clone();
+        let _cancel_guard = self.on_cancel(move || engine.increment_epoch());
+
         let module = Module::from_file(&self.engine, wasm_path)?;
 
         linker.module(&mut store, "", &module)?;
@@ -819,7 +1620,15 @@ impl ToolRuntime {
         let instance = linker.instantiate(&mut store, &module)?;
         let start = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
 
-        start.call(&mut store, ())?;
+        if let Err(e) = start.call(&mut store, ()) {
+            if self.is_cancelled() {
+                return Err(Cancelled.into());
+            }
+            if let (Some(fuel), Some(Trap::OutOfFuel)) = (fuel, e.downcast_ref::<Trap>()) {
+                return Err(FuelExhausted { fuel }.into());
+            }
+            return Err(e);
+        }
 
         let output = stdout.contents();
         let output_str = String::from_utf8_lossy(&output);
@@ -827,12 +1636,158 @@ impl ToolRuntime {
         Ok(Value::String(output_str.to_string()))
     }
 
+    /// Command that runs a script tool with `args`, inside its own
+    /// environment (venv, node_modules, gems) and the sandbox, if configured
+    fn script_command(
+        &self,
+        config: &ToolConfig,
+        args: &[String],
+        roots: &[String],
+    ) -> Result<Command> {
+        let working_dir = config
+            .manifest
+            .working_dir
+            .as_deref()
+            .map(|dir| script_working_dir(dir, roots))
+            .transpose()?;
+        // Paths into TOOLS_DIR may be relative to the server's directory
+        let (script_path, env_path) = match working_dir {
+            Some(_) => (
+                absolute_path(&config.script_path),
+                config.env_path.as_deref().map(absolute_path),
+            ),
+            None => (config.script_path.clone(), config.env_path.clone()),
+        };
+
+        let mut cmd = if let Some(interp) = config.interpreter() {
+            let mut c = match (&env_path, PackageManager::for_interpreter(interp)) {
+                (Some(env_path), Some(manager)) => manager.command(interp, env_path),
+                _ => Command::new(interp),
+            };
+            c.arg(&script_path);
+            c
+        } else if cfg!(windows) {
+            // No shebangs or exec bits: the interpreter must be named
+            anyhow::bail!(
+                "Script tool '{}' needs an explicit interpreter on Windows",
+                config.name()
+            );
+        } else {
+            Command::new(&script_path)
+        };
+        cmd.args(args);
+
+        // Apply sandbox wrapper if configured
+        self.sandbox_config()
+            .for_call(config.is_read_only())
+            .wrap_command(&mut cmd, &script_path, roots);
+
+        if let Some(dir) = working_dir {
+            cmd.current_dir(dir);
+        }
+        for (key, value) in &config.manifest.env {
+            cmd.env(key, expand_env(value));
+        }
+
+        // Inject extra environment variables (e.g., from services)
+        for (key, value) in &self.extra_env {
+            cmd.env(key, value);
+        }
+        Ok(cmd)
+    }
+
+    /// Execute a Script tool in argv mode: arguments become command-line
+    /// flags (see [`argv_flags`]) and stdout is the result
+    fn call_argv_script(
+        &self,
+        config: &ToolConfig,
+        arguments: &Value,
+        roots: &[String],
+    ) -> Result<ScriptResult> {
+        let flags = argv_flags(arguments)?;
+        eprintln!("Script argv: {:?}", flags);
+
+        let mut child = self
+            .script_command(config, &flags, roots)?
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context(format!("Failed to spawn script: {:?}", config.script_path))?;
+        let mut stdout = child.stdout.take().context("Failed to get stdout")?;
+        let mut stderr = child.stderr.take().context("Failed to get stderr")?;
+
+        // Kill the process on cancellation; its pipes close and the reads end
+        let child = Arc::new(Mutex::new(child));
+        let child_to_kill = child.clone();
+        let _cancel_guard = self.on_cancel(move || {
+            if let Ok(mut child) = child_to_kill.lock() {
+                eprintln!("⏹️ Killing cancelled script");
+                let _ = child.kill();
+            }
+        });
+
+        // Drain stderr alongside stdout so neither pipe fills up and blocks
+        let stderr_reader = std::thread::spawn(move || {
+            let mut content = Vec::new();
+            let _ = std::io::Read::read_to_end(&mut stderr, &mut content);
+            String::from_utf8_lossy(&content).into_owned()
+        });
+        let mut output = Vec::new();
+        let read = std::io::Read::read_to_end(&mut stdout, &mut output);
+        let stderr_content = stderr_reader.join().unwrap_or_default();
+        let status = child
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Script process lock poisoned"))?
+            .wait()?;
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        read?;
+
+        let mut logs = Vec::new();
+        if !stderr_content.is_empty() {
+            eprintln!("Script stderr: {}", stderr_content);
+            logs.push(LogEntry {
+                level: "error".to_string(),
+                message: stderr_content,
+                data: None,
+            });
+        }
+
+        let output = if !status.success() {
+            Value::String(format!("Script exited with status: {}", status))
+        } else {
+            Value::String(
+                String::from_utf8_lossy(&output)
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string(),
+            )
+        };
+
+        Ok(ScriptResult {
+            output,
+            logs,
+            exit_code: status.code(),
+            progress: Vec::new(),
+        })
+    }
+
     /// Execute a Script tool via JSON-RPC 2.0 with logging support
     fn call_script_tool(&self, config: &ToolConfig, args: Value) -> Result<ScriptResult> {
         // Build execution context
         let mut context = self.context.clone();
         context.tool_name = config.name().to_string();
 
+        if let Some(dir) = &config.manifest.working_dir {
+            context.working_directory = script_working_dir(dir, &context.roots)?
+                .to_string_lossy()
+                .to_string();
+        }
+        for (key, value) in &config.manifest.env {
+            context.environment.insert(key.clone(), expand_env(value));
+        }
+
         // Save values for use later (before context is moved)
         let sandbox_roots = context.roots.clone();
         let tool_name = context.tool_name.clone();
@@ -844,6 +1799,10 @@ impl ToolRuntime {
             _ => args,
         };
 
+        if config.manifest.arg_mode == ArgMode::Argv {
+            return self.call_argv_script(config, &arguments, &sandbox_roots);
+        }
+
         // Build the JSON-RPC request with context
         let request = JsonRpcRequest {
             jsonrpc: "2.0",
@@ -855,51 +1814,7 @@ impl ToolRuntime {
         let request_json = serde_json::to_string(&request)?;
         eprintln!("Script request: {}", request_json);
 
-        // Determine how to run the script (with virtual environment if configured)
-        let interpreter = config.interpreter();
-        let mut cmd = if let Some(interp) = interpreter {
-            // Check if we have a virtual environment
-            if let Some(ref env_path) = config.env_path {
-                let interpreter_in_env = match interp {
-                    "python3" | "python" => {
-                        let venv_python = env_path.join("bin").join("python");
-                        if venv_python.exists() {
-                            venv_python.to_string_lossy().to_string()
-                        } else {
-                            interp.to_string()
-                        }
-                    }
-                    "node" | "nodejs" => interp.to_string(), // Node uses NODE_PATH
-                    _ => interp.to_string(),
-                };
-                let mut c = Command::new(&interpreter_in_env);
-                c.arg(&config.script_path);
-
-                // For Node.js, set NODE_PATH to include local node_modules
-                if interp == "node" || interp == "nodejs" {
-                    let node_modules = env_path.join("node_modules");
-                    if node_modules.exists() {
-                        c.env("NODE_PATH", node_modules);
-                    }
-                }
-                c
-            } else {
-                let mut c = Command::new(interp);
-                c.arg(&config.script_path);
-                c
-            }
-        } else {
-            Command::new(&config.script_path)
-        };
-
-        // Apply sandbox wrapper if configured
-        self.sandbox_config
-            .wrap_command(&mut cmd, &config.script_path, &sandbox_roots);
-
-        // Inject extra environment variables (e.g., from services)
-        for (key, value) in &self.extra_env {
-            cmd.env(key, value);
-        }
+        let mut cmd = self.script_command(config, &[], &sandbox_roots)?;
 
         // Set up the process
         let mut child = cmd
@@ -911,6 +1826,17 @@ impl ToolRuntime {
 
         // Keep stdin open for bidirectional communication (memory requests)
         let mut stdin = child.stdin.take().context("Failed to get stdin")?;
+        let stdout = child.stdout.take().context("Failed to get stdout")?;
+
+        // Kill the process on cancellation; its stdout closes and the read loop ends
+        let child = Arc::new(Mutex::new(child));
+        let child_to_kill = child.clone();
+        let _cancel_guard = self.on_cancel(move || {
+            if let Ok(mut child) = child_to_kill.lock() {
+                eprintln!("⏹️ Killing cancelled script");
+                let _ = child.kill();
+            }
+        });
 
         // Write initial request
         stdin.write_all(request_json.as_bytes())?;
@@ -923,15 +1849,29 @@ impl ToolRuntime {
         let mut final_result: Option<Value> = None;
         let mut final_error: Option<String> = None;
 
-        // Read stdout line by line
-        let stdout = child.stdout.take().context("Failed to get stdout")?;
-        let reader = BufReader::new(stdout);
+        // In NDJSON mode every result line is kept, in order
+        let ndjson = config.manifest.ndjson;
+        let mut results = Vec::new();
+
+        // Read stdout line by line, decoding lossily so a stray binary byte
+        // doesn't abort the run
+        let mut reader = BufReader::new(stdout);
 
         // Clone memory for use in the loop
         let memory = self.memory.clone();
 
-        for line in reader.lines() {
-            let line = line?;
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(_) if self.is_cancelled() => break,
+                Err(e) => return Err(e.into()),
+            }
+            let line = String::from_utf8_lossy(&buf)
+                .trim_end_matches(['\r', '\n'])
+                .to_string();
             if line.trim().is_empty() {
                 continue;
             }
@@ -1058,8 +1998,11 @@ impl ToolRuntime {
                                     let key =
                                         params.get("key").and_then(|v| v.as_str()).unwrap_or("");
                                     let value = params.get("value").cloned().unwrap_or(Value::Null);
-                                    // Optional TTL in seconds (for caching)
-                                    let ttl = params.get("ttl").and_then(|v| v.as_u64());
+                                    // Optional TTL in seconds ("ttl" is the older spelling)
+                                    let ttl = params
+                                        .get("ttl_seconds")
+                                        .or_else(|| params.get("ttl"))
+                                        .and_then(|v| v.as_u64());
                                     let handle = tokio::runtime::Handle::current();
                                     match handle
                                         .block_on(mem.set_with_ttl(&tool_name, key, value, ttl))
@@ -1087,8 +2030,43 @@ impl ToolRuntime {
                         "memory/list" if is_request => {
                             let result = if let Some(ref mem) = memory {
                                 let handle = tokio::runtime::Handle::current();
-                                match handle.block_on(mem.list_keys(&tool_name)) {
-                                    Ok(keys) => serde_json::json!({"keys": keys}),
+                                let params = response.params.unwrap_or(Value::Null);
+                                let prefix = params.get("prefix").and_then(|v| v.as_str());
+                                let limit = params
+                                    .get("limit")
+                                    .and_then(|v| v.as_u64())
+                                    .map(|l| l.max(1) as usize);
+                                let offset = params
+                                    .get("cursor")
+                                    .and_then(|v| {
+                                        v.as_u64()
+                                            .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+                                    })
+                                    .unwrap_or(0)
+                                    as usize;
+                                let include_values = params
+                                    .get("include_values")
+                                    .and_then(|v| v.as_bool())
+                                    .unwrap_or(false);
+                                match handle.block_on(mem.list(
+                                    &tool_name,
+                                    prefix,
+                                    limit,
+                                    offset,
+                                    include_values,
+                                )) {
+                                    Ok(page) => {
+                                        let keys: Vec<&str> =
+                                            page.entries.iter().map(|e| e.key.as_str()).collect();
+                                        let mut result = serde_json::json!({
+                                            "keys": keys,
+                                            "next_cursor": page.next_offset.map(|o| o.to_string()),
+                                        });
+                                        if include_values {
+                                            result["entries"] = serde_json::json!(page.entries);
+                                        }
+                                        result
+                                    }
                                     Err(e) => serde_json::json!({"error": e.to_string()}),
                                 }
                             } else {
@@ -1132,6 +2110,45 @@ impl ToolRuntime {
                             stdin.flush()?;
                         }
 
+                        "memory/increment" | "memory/append" if is_request => {
+                            let result = if let Some(ref mem) = memory {
+                                let params = response.params.unwrap_or(Value::Null);
+                                let key = params.get("key").and_then(|v| v.as_str()).unwrap_or("");
+                                let handle = tokio::runtime::Handle::current();
+                                if method.as_str() == "memory/increment" {
+                                    let delta =
+                                        params.get("delta").and_then(|v| v.as_i64()).unwrap_or(1);
+                                    match handle.block_on(mem.increment(&tool_name, key, delta)) {
+                                        Ok(value) => serde_json::json!({"value": value}),
+                                        Err(e) => serde_json::json!({"error": e.to_string()}),
+                                    }
+                                } else {
+                                    let item = params.get("item").cloned().unwrap_or(Value::Null);
+                                    let max_length = params
+                                        .get("max_length")
+                                        .and_then(|v| v.as_u64())
+                                        .map(|l| l as usize);
+                                    match handle
+                                        .block_on(mem.append(&tool_name, key, item, max_length))
+                                    {
+                                        Ok(length) => serde_json::json!({"length": length}),
+                                        Err(e) => serde_json::json!({"error": e.to_string()}),
+                                    }
+                                }
+                            } else {
+                                serde_json::json!({"error": "Memory not available"})
+                            };
+
+                            let response_json = serde_json::json!({
+                                "jsonrpc": "2.0",
+                                "result": result,
+                                "id": response.id
+                            });
+                            stdin.write_all(serde_json::to_string(&response_json)?.as_bytes())?;
+                            stdin.write_all(b"\n")?;
+                            stdin.flush()?;
+                        }
+
                         // ===== Elicitation requests (user input via MCP) =====
                         "elicitation/create" if is_request => {
                             let result = if let Some(ref handler) = self.elicitation_handler {
@@ -1222,31 +2239,29 @@ impl ToolRuntime {
                         }
 
                         "resources/read" if is_request => {
-                            let result = if let Some(ref handler) = self.resource_read_handler {
-                                if let Some(params) = response.params {
-                                    let uri = params
-                                        .get("uri")
-                                        .and_then(|v| v.as_str())
-                                        .unwrap_or("")
-                                        .to_string();
-
-                                    if uri.is_empty() {
-                                        serde_json::json!({"error": "Missing uri parameter"})
-                                    } else {
-                                        let handle = tokio::runtime::Handle::current();
-                                        let handler = handler.clone();
-                                        match handle.block_on(handler(uri)) {
-                                            Ok(content) => {
-                                                serde_json::json!({"contents": [content]})
-                                            }
-                                            Err(e) => serde_json::json!({"error": e.to_string()}),
-                                        }
-                                    }
-                                } else {
-                                    serde_json::json!({"error": "Missing parameters"})
-                                }
+                            let uri = response
+                                .params
+                                .as_ref()
+                                .and_then(|p| p.get("uri"))
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("")
+                                .to_string();
+                            let content = if response.params.is_none() {
+                                Err(anyhow::anyhow!("Missing parameters"))
+                            } else if uri.is_empty() {
+                                Err(anyhow::anyhow!("Missing uri parameter"))
+                            } else if uri.starts_with(FILE_RESOURCE_PREFIX) {
+                                // Workspace files are read here, where the roots are known
+                                read_file_resource(&uri, &sandbox_roots)
+                            } else if let Some(ref handler) = self.resource_read_handler {
+                                let handle = tokio::runtime::Handle::current();
+                                handle.block_on(handler.clone()(uri))
                             } else {
-                                serde_json::json!({"error": "Resources not available"})
+                                Err(anyhow::anyhow!("Resources not available"))
+                            };
+                            let result = match content {
+                                Ok(content) => serde_json::json!({"contents": [content]}),
+                                Err(e) => serde_json::json!({"error": e.to_string()}),
                             };
 
                             let response_json = serde_json::json!({
@@ -1275,7 +2290,11 @@ impl ToolRuntime {
                                     } else {
                                         let handle = tokio::runtime::Handle::current();
                                         let handler = handler.clone();
-                                        match handle.block_on(handler(name.clone(), arguments)) {
+                                        match handle.block_on(handler(
+                                            config.name().to_string(),
+                                            name.clone(),
+                                            arguments,
+                                        )) {
                                             Ok(output) => serde_json::json!({"output": output}),
                                             Err(e) => {
                                                 serde_json::json!({"error": format!("Tool '{}' failed: {}", name, e)})
@@ -1323,7 +2342,11 @@ impl ToolRuntime {
                             final_error = Some(format!("{}\nData: {}", final_error.unwrap(), data));
                         }
                     } else if let Some(result) = response.result {
-                        final_result = Some(result);
+                        if ndjson {
+                            results.push(result);
+                        } else {
+                            final_result = Some(result);
+                        }
                     }
                 }
             } else {
@@ -1334,16 +2357,39 @@ impl ToolRuntime {
             }
         }
 
+        if ndjson && !results.is_empty() {
+            final_result = Some(Value::Array(results));
+        }
+
         // Close stdin to signal we're done
         drop(stdin);
 
-        // Wait for the process to finish
-        let status = child.wait()?;
+        // Wait for the process to finish, without holding the lock the
+        // cancellation watcher needs to kill it
+        let status = loop {
+            let mut child = child
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Script process lock poisoned"))?;
+            if self.is_cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Cancelled.into());
+            }
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            drop(child);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        let mut child = child
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Script process lock poisoned"))?;
 
         // Check stderr for any errors
         if let Some(mut stderr) = child.stderr.take() {
-            let mut stderr_content = String::new();
-            std::io::Read::read_to_string(&mut stderr, &mut stderr_content)?;
+            let mut stderr_bytes = Vec::new();
+            std::io::Read::read_to_end(&mut stderr, &mut stderr_bytes)?;
+            let stderr_content = String::from_utf8_lossy(&stderr_bytes).into_owned();
             if !stderr_content.is_empty() {
                 eprintln!("Script stderr: {}", stderr_content);
                 // Add stderr as a log entry
@@ -1369,6 +2415,7 @@ impl ToolRuntime {
         Ok(ScriptResult {
             output,
             logs,
+            exit_code: status.code(),
             progress,
         })
     }
@@ -1385,8 +2432,150 @@ pub struct InstallResult {
     pub env_path: Option<std::path::PathBuf>,
 }
 
+/// Package manager that installs a script tool's dependencies into its `env/` directory.
+/// Supporting a new interpreter means adding a variant and its match arms here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    /// pip in a virtual environment (python3)
+    Pip,
+    /// npm with a local node_modules (node)
+    Npm,
+    /// gem with a tool-local GEM_HOME (ruby)
+    Gem,
+}
+
+impl PackageManager {
+    /// The package manager for an interpreter, if dependency installation is supported
+    pub fn for_interpreter(interpreter: &str) -> Option<Self> {
+        match interpreter {
+            "python3" | "python" => Some(Self::Pip),
+            "node" | "nodejs" => Some(Self::Npm),
+            "ruby" => Some(Self::Gem),
+            _ => None,
+        }
+    }
+
+    /// Install dependencies into `env_path`, passing installer output lines to `on_output`
+    pub fn install(
+        self,
+        env_path: &Path,
+        dependencies: &[String],
+        on_output: OnOutputLine,
+    ) -> Result<InstallResult> {
+        match self {
+            Self::Pip => install_python_deps(env_path, dependencies, on_output),
+            Self::Npm => install_node_deps(env_path, dependencies, on_output),
+            Self::Gem => install_ruby_deps(env_path, dependencies, on_output),
+        }
+    }
+
+    /// Command running `interpreter` with the packages installed in `env_path`
+    pub fn command(self, interpreter: &str, env_path: &Path) -> Command {
+        match self {
+            Self::Pip => {
+                let venv_python = venv_executable(env_path, "python");
+                if venv_python.exists() {
+                    Command::new(venv_python)
+                } else {
+                    Command::new(interpreter)
+                }
+            }
+            Self::Npm => {
+                let mut cmd = Command::new(interpreter);
+                let node_modules = env_path.join("node_modules");
+                if node_modules.exists() {
+                    cmd.env("NODE_PATH", node_modules);
+                }
+                cmd
+            }
+            Self::Gem => {
+                let mut cmd = Command::new(interpreter);
+                let gem_home = env_path.join(GEM_HOME_DIR);
+                if gem_home.exists() {
+                    // Keep any system gem path so default gems still resolve
+                    let gem_path = match std::env::var_os("GEM_PATH") {
+                        Some(existing) if !existing.is_empty() => {
+                            let paths = std::iter::once(gem_home.clone())
+                                .chain(std::env::split_paths(&existing));
+                            std::env::join_paths(paths)
+                                .unwrap_or_else(|_| gem_home.clone().into_os_string())
+                        }
+                        _ => gem_home.clone().into_os_string(),
+                    };
+                    cmd.env("GEM_HOME", &gem_home).env("GEM_PATH", gem_path);
+                }
+                cmd
+            }
+        }
+    }
+}
+
+/// Callback receiving each line an installer prints (stdout and stderr)
+pub type OnOutputLine<'a> = &'a (dyn Fn(&str) + Sync);
+
+/// Run an installer to completion, passing each output line to `on_output` as it is printed
+fn run_installer(
+    cmd: &mut Command,
+    on_output: OnOutputLine,
+) -> std::io::Result<std::process::Output> {
+    fn forward_lines(pipe: impl std::io::Read, on_output: OnOutputLine) -> Vec<u8> {
+        let mut reader = BufReader::new(pipe);
+        let mut collected = Vec::new();
+        let mut line = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim();
+            if !text.is_empty() {
+                on_output(text);
+            }
+            collected.append(&mut line);
+        }
+        collected
+    }
+
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    let (stdout, stderr) = std::thread::scope(|scope| {
+        let stderr = scope.spawn(|| forward_lines(stderr, on_output));
+        let stdout = forward_lines(stdout, on_output);
+        (stdout, stderr.join().unwrap_or_default())
+    });
+
+    Ok(std::process::Output {
+        status: child.wait()?,
+        stdout,
+        stderr,
+    })
+}
+
+/// Directory under a tool's `env/` used as GEM_HOME for Ruby tools
+pub const GEM_HOME_DIR: &str = "gems";
+
+/// Directory of a Python venv holding its executables
+#[cfg(windows)]
+const VENV_BIN_DIR: &str = "Scripts";
+#[cfg(not(windows))]
+const VENV_BIN_DIR: &str = "bin";
+
+/// Path of executable `name` (e.g. "python", "pip") inside a Python venv
+pub fn venv_executable(env_path: &Path, name: &str) -> PathBuf {
+    env_path
+        .join(VENV_BIN_DIR)
+        .join(format!("{}{}", name, std::env::consts::EXE_SUFFIX))
+}
+
 /// Install Python dependencies using pip in a virtual environment
-pub fn install_python_deps(env_path: &Path, dependencies: &[String]) -> Result<InstallResult> {
+pub fn install_python_deps(
+    env_path: &Path,
+    dependencies: &[String],
+    on_output: OnOutputLine,
+) -> Result<InstallResult> {
     // Create virtual environment if it doesn't exist
     if !env_path.exists() {
         eprintln!("Creating Python venv at: {:?}", env_path);
@@ -1409,7 +2598,7 @@ pub fn install_python_deps(env_path: &Path, dependencies: &[String]) -> Result<I
     }
 
     // Install dependencies using pip
-    let pip_path = env_path.join("bin").join("pip");
+    let pip_path = venv_executable(env_path, "pip");
     if !pip_path.exists() {
         return Ok(InstallResult {
             success: false,
@@ -1426,14 +2615,32 @@ pub fn install_python_deps(env_path: &Path, dependencies: &[String]) -> Result<I
         });
     }
 
-    eprintln!("Installing Python deps: {:?}", dependencies);
-    let output = Command::new(&pip_path)
-        .arg("install")
-        .args(dependencies)
-        .output()
-        .context("Failed to run pip install")?;
+    // Reinstall the exact versions recorded last time if the dependency list is unchanged
+    let lockfile = lockfile_path(env_path, PYTHON_LOCKFILE);
+    let locked = lockfile
+        .as_deref()
+        .filter(|path| python_lock_matches(path, dependencies));
+
+    let mut cmd = Command::new(&pip_path);
+    cmd.arg("install");
+    match locked {
+        Some(path) => {
+            eprintln!("Installing Python deps from {:?}", path);
+            cmd.arg("-r").arg(path);
+        }
+        None => {
+            eprintln!("Installing Python deps: {:?}", dependencies);
+            cmd.args(dependencies);
+        }
+    }
+    let output = run_installer(&mut cmd, on_output).context("Failed to run pip install")?;
 
     if output.status.success() {
+        if let Some(path) = &lockfile {
+            if let Err(e) = write_python_lock(&pip_path, path, dependencies) {
+                eprintln!("Failed to write {}: {}", PYTHON_LOCKFILE, e);
+            }
+        }
         Ok(InstallResult {
             success: true,
             message: format!(
@@ -1456,7 +2663,11 @@ pub fn install_python_deps(env_path: &Path, dependencies: &[String]) -> Result<I
 }
 
 /// Install Node.js dependencies using npm
-pub fn install_node_deps(env_path: &Path, dependencies: &[String]) -> Result<InstallResult> {
+pub fn install_node_deps(
+    env_path: &Path,
+    dependencies: &[String],
+    on_output: OnOutputLine,
+) -> Result<InstallResult> {
     // Create directory if it doesn't exist
     std::fs::create_dir_all(env_path)?;
 
@@ -1488,14 +2699,39 @@ pub fn install_node_deps(env_path: &Path, dependencies: &[String]) -> Result<Ins
     let package_path = env_path.join("package.json");
     std::fs::write(&package_path, serde_json::to_string_pretty(&package_json)?)?;
 
-    eprintln!("Installing Node.js deps: {:?}", dependencies);
-    let output = Command::new("npm")
-        .current_dir(env_path)
-        .args(["install", "--production"])
-        .output()
-        .context("Failed to run npm install")?;
+    // `npm ci` reinstalls exactly the locked tree if the dependency list is unchanged
+    let lockfile = lockfile_path(env_path, NODE_LOCKFILE);
+    let env_lockfile = env_path.join(NODE_LOCKFILE);
+    let locked = lockfile
+        .as_deref()
+        .filter(|path| node_lock_matches(path, dependencies));
+    let npm_command = match locked {
+        Some(path) => {
+            std::fs::copy(path, &env_lockfile)?;
+            eprintln!("Installing Node.js deps from {:?}", path);
+            "ci"
+        }
+        None => {
+            eprintln!("Installing Node.js deps: {:?}", dependencies);
+            "install"
+        }
+    };
+    let output = run_installer(
+        Command::new("npm")
+            .current_dir(env_path)
+            .args([npm_command, "--production"]),
+        on_output,
+    )
+    .with_context(|| format!("Failed to run npm {}", npm_command))?;
 
     if output.status.success() {
+        if let Some(path) = &lockfile {
+            if env_lockfile.exists() {
+                if let Err(e) = std::fs::copy(&env_lockfile, path) {
+                    eprintln!("Failed to write {}: {}", NODE_LOCKFILE, e);
+                }
+            }
+        }
         Ok(InstallResult {
             success: true,
             message: format!(
@@ -1517,23 +2753,195 @@ pub fn install_node_deps(env_path: &Path, dependencies: &[String]) -> Result<Ins
     }
 }
 
-/// Install dependencies for a tool based on its interpreter
-pub fn install_tool_deps(
+/// Lockfile (in the tool directory) pinning a Python tool's resolved dependencies
+pub const PYTHON_LOCKFILE: &str = "requirements.lock";
+/// Lockfile (in the tool directory) pinning a Node.js tool's resolved dependencies
+pub const NODE_LOCKFILE: &str = "package-lock.json";
+
+/// Header line recording which declared dependencies a Python lockfile was resolved from
+const PYTHON_LOCK_HEADER: &str = "# skillz-dependencies: ";
+
+/// Lockfiles live next to the manifest (not in `env/`) so they are exported with the tool
+fn lockfile_path(env_path: &Path, name: &str) -> Option<PathBuf> {
+    env_path.parent().map(|tool_dir| tool_dir.join(name))
+}
+
+/// Declared dependencies in a stable order, for comparing against a lockfile
+fn dependency_set(dependencies: &[String]) -> Vec<String> {
+    let mut deps: Vec<String> = dependencies.iter().map(|d| d.trim().to_string()).collect();
+    deps.sort();
+    deps.dedup();
+    deps
+}
+
+/// Whether a Python lockfile was resolved from exactly these dependencies
+pub fn python_lock_matches(lockfile: &Path, dependencies: &[String]) -> bool {
+    let Ok(content) = std::fs::read_to_string(lockfile) else {
+        return false;
+    };
+    content
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix(PYTHON_LOCK_HEADER))
+        .is_some_and(|recorded| recorded == dependency_set(dependencies).join(","))
+}
+
+/// Write `pip freeze` output, headed by the declared dependencies, to the lockfile
+fn write_python_lock(pip_path: &Path, lockfile: &Path, dependencies: &[String]) -> Result<()> {
+    let output = Command::new(pip_path)
+        .args([
+            "freeze",
+            "--all",
+            "--exclude",
+            "pip",
+            "--exclude",
+            "setuptools",
+            "--exclude",
+            "wheel",
+        ])
+        .output()
+        .context("Failed to run pip freeze")?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr));
+    }
+    let content = format!(
+        "{}{}\n{}",
+        PYTHON_LOCK_HEADER,
+        dependency_set(dependencies).join(","),
+        String::from_utf8_lossy(&output.stdout)
+    );
+    std::fs::write(lockfile, content)?;
+    Ok(())
+}
+
+/// Whether an npm lockfile's root dependencies are exactly the declared packages
+pub fn node_lock_matches(lockfile: &Path, dependencies: &[String]) -> bool {
+    let Some(lock) = std::fs::read_to_string(lockfile)
+        .ok()
+        .and_then(|c| serde_json::from_str::<Value>(&c).ok())
+    else {
+        return false;
+    };
+    let Some(locked) = lock
+        .pointer("/packages/")
+        .and_then(|root| root.get("dependencies"))
+        .and_then(|d| d.as_object())
+    else {
+        return false;
+    };
+
+    let mut locked: Vec<&str> = locked.keys().map(|k| k.as_str()).collect();
+    locked.sort();
+    let mut declared: Vec<&str> = dependencies.iter().map(|d| npm_package_name(d)).collect();
+    declared.sort();
+    declared.dedup();
+    locked == declared
+}
+
+/// Package name of a dependency spec in any supported format
+/// ("pandas>=2.0", "requests[socks]", "lodash@4", "@scope/pkg@1" -> "@scope/pkg")
+fn dependency_name(spec: &str) -> &str {
+    let name = npm_package_name(spec.trim());
+    let end = name
+        .find(|c: char| "=<>!~[; ".contains(c))
+        .unwrap_or(name.len());
+    &name[..end]
+}
+
+/// Package name of an npm dependency spec ("lodash@4", "@scope/pkg@1" -> "@scope/pkg")
+fn npm_package_name(spec: &str) -> &str {
+    match spec.get(1..).and_then(|rest| rest.find('@')) {
+        Some(i) => &spec[..i + 1],
+        None => spec,
+    }
+}
+
+/// Install Ruby gems into a tool-local GEM_HOME (`env/gems`).
+/// Dependencies use the same "name@version" format as npm.
+pub fn install_ruby_deps(
     env_path: &Path,
-    interpreter: Option<&str>,
     dependencies: &[String],
+    on_output: OnOutputLine,
 ) -> Result<InstallResult> {
-    match interpreter {
-        Some("python3") | Some("python") => install_python_deps(env_path, dependencies),
-        Some("node") | Some("nodejs") => install_node_deps(env_path, dependencies),
-        Some(other) => Ok(InstallResult {
+    let gem_home = env_path.join(GEM_HOME_DIR);
+    std::fs::create_dir_all(&gem_home)?;
+
+    if dependencies.is_empty() {
+        return Ok(InstallResult {
+            success: true,
+            message: "No dependencies to install".to_string(),
+            env_path: Some(env_path.to_path_buf()),
+        });
+    }
+
+    // gem accepts "name:version"
+    let gems: Vec<String> = dependencies
+        .iter()
+        .map(|d| d.replacen('@', ":", 1))
+        .collect();
+
+    eprintln!("Installing Ruby gems: {:?}", dependencies);
+    let output = run_installer(
+        Command::new("gem")
+            .arg("install")
+            .arg("--no-document")
+            .arg("--install-dir")
+            .arg(&gem_home)
+            .args(&gems),
+        on_output,
+    )
+    .context("Failed to run gem install")?;
+
+    if output.status.success() {
+        Ok(InstallResult {
+            success: true,
+            message: format!(
+                "Installed {} dependencies:\n{}",
+                dependencies.len(),
+                String::from_utf8_lossy(&output.stdout)
+            ),
+            env_path: Some(env_path.to_path_buf()),
+        })
+    } else {
+        Ok(InstallResult {
             success: false,
             message: format!(
-                "Dependency installation not supported for interpreter: {}",
-                other
+                "gem install failed:\n{}",
+                String::from_utf8_lossy(&output.stderr)
             ),
             env_path: None,
-        }),
+        })
+    }
+}
+
+/// Install dependencies for a tool based on its interpreter
+pub fn install_tool_deps(
+    env_path: &Path,
+    interpreter: Option<&str>,
+    dependencies: &[String],
+) -> Result<InstallResult> {
+    install_tool_deps_with_output(env_path, interpreter, dependencies, &|_| {})
+}
+
+/// Install dependencies for a tool, passing installer output lines to `on_output`
+pub fn install_tool_deps_with_output(
+    env_path: &Path,
+    interpreter: Option<&str>,
+    dependencies: &[String],
+    on_output: OnOutputLine,
+) -> Result<InstallResult> {
+    match interpreter {
+        Some(interp) => match PackageManager::for_interpreter(interp) {
+            Some(manager) => manager.install(env_path, dependencies, on_output),
+            None => Ok(InstallResult {
+                success: false,
+                message: format!(
+                    "Dependency installation not supported for interpreter: {}",
+                    interp
+                ),
+                env_path: None,
+            }),
+        },
         None => Ok(InstallResult {
             success: false,
             message: "No interpreter specified for dependency installation".to_string(),
@@ -0,0 +1,254 @@
+//! Scheduled pipelines - run pipelines periodically while the server is up
+//!
+//! A pipeline's `schedule` is either a simple interval ("every 30s",
+//! "every 5m", "every 2h", "every 1d") or a cron expression: five fields
+//! (minute hour day month weekday) or six/seven with seconds first (and a
+//! year last). Times are UTC.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::pipeline::{truncate_chars, PipelineRun};
+
+/// Reserved memory namespace holding recent scheduled runs (one key per pipeline)
+pub const SCHEDULED_RUNS_NAMESPACE: &str = "_skillz_schedule";
+
+/// Scheduled runs kept per pipeline; older ones are dropped
+pub const MAX_SCHEDULED_RUNS: usize = 20;
+
+/// How often the scheduler checks for due pipelines
+pub const SCHEDULER_TICK: Duration = Duration::from_millis(250);
+
+/// Longest error text kept in a run summary
+const SUMMARY_ERROR_CHARS: usize = 200;
+
+/// When a scheduled pipeline runs
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    /// Every fixed interval, starting one interval after it is set
+    Every(Duration),
+    /// At the times matching a cron expression
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    /// Parse an interval ("every 30s", "every 5m", "every 2h", "every 1d")
+    /// or a cron expression
+    pub fn parse(expr: &str) -> Result<Self> {
+        let expr = expr.trim();
+        if let Some(interval) = expr.strip_prefix("every ") {
+            return parse_interval(interval.trim())
+                .map(Self::Every)
+                .with_context(|| format!("Invalid schedule '{}'", expr));
+        }
+
+        // The cron crate wants seconds first; standard 5-field crontab lines fire at :00
+        let fields = expr.split_whitespace().count();
+        let cron_expr = match fields {
+            5 => format!("0 {}", expr),
+            6 | 7 => expr.to_string(),
+            _ => anyhow::bail!(
+                "Invalid schedule '{}': use 'every <n>s|m|h|d' or a cron expression with 5-7 fields",
+                expr
+            ),
+        };
+        cron::Schedule::from_str(&cron_expr)
+            .map(|s| Self::Cron(Box::new(s)))
+            .map_err(|e| anyhow::anyhow!("Invalid schedule '{}': {}", expr, e))
+    }
+
+    /// The first run time strictly after `after`, if there is one
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Every(interval) => chrono::Duration::from_std(*interval)
+                .ok()
+                .and_then(|d| after.checked_add_signed(d)),
+            Self::Cron(schedule) => schedule.after(&after).next(),
+        }
+    }
+}
+
+/// "30s", "5m", "2h" or "1d" as a duration of at least one second
+fn parse_interval(interval: &str) -> Result<Duration> {
+    let split = interval
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(interval.len());
+    let (count, unit) = interval.split_at(split);
+    let count: u64 = count
+        .parse()
+        .map_err(|_| anyhow::anyhow!("expected a number of s, m, h or d"))?;
+    let unit_secs = match unit.trim() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 3600,
+        "d" | "day" | "days" => 86_400,
+        other => anyhow::bail!("unknown unit '{}' (use s, m, h or d)", other),
+    };
+    if count == 0 {
+        anyhow::bail!("the interval must be at least 1s");
+    }
+    count
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+        .context("the interval is too long")
+}
+
+/// Outcome of one scheduled run, kept in memory for `pipeline(action: "runs")`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledRun {
+    /// When the run started (RFC 3339, UTC)
+    pub started_at: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    /// "3 steps succeeded", or the step that failed and why
+    pub summary: String,
+}
+
+impl ScheduledRun {
+    pub fn new(started_at: DateTime<Utc>, run: &PipelineRun) -> Self {
+        let failed = run.steps.iter().rev().find(|s| !s.success);
+        let summary = match failed {
+            _ if run.cancelled => "cancelled".to_string(),
+            Some(failed) if !run.success => {
+                let name = failed
+                    .step_name
+                    .clone()
+                    .unwrap_or_else(|| format!("step {}", failed.step_index + 1));
+                let error = failed.error.as_deref().unwrap_or("unknown error");
+                let error = match truncate_chars(error, SUMMARY_ERROR_CHARS) {
+                    Some(cut) => format!("{}...", cut),
+                    None => error.to_string(),
+                };
+                format!("failed at {} ({}): {}", name, failed.tool, error)
+            }
+            _ => format!("{} step(s) succeeded", run.steps.len()),
+        };
+        Self {
+            started_at: started_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            success: run.success,
+            duration_ms: run.duration_ms,
+            summary,
+        }
+    }
+}
+
+/// Tracks when each scheduled pipeline is next due
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    /// Pipeline name -> (schedule expression, next run time)
+    next_runs: HashMap<String, (String, DateTime<Utc>)>,
+}
+
+impl Scheduler {
+    /// Given the currently scheduled pipelines as `(name, expression)`, return
+    /// those due at `now` and move them on to their next run time. A pipeline
+    /// seen for the first time, or whose schedule changed, is first due at the
+    /// next scheduled time after `now`. Invalid expressions are skipped.
+    pub fn due(&mut self, scheduled: &[(String, String)], now: DateTime<Utc>) -> Vec<String> {
+        self.next_runs
+            .retain(|name, _| scheduled.iter().any(|(n, _)| n == name));
+
+        let mut due = Vec::new();
+        for (name, expr) in scheduled {
+            let Ok(schedule) = Schedule::parse(expr) else {
+                continue;
+            };
+            match self.next_runs.get(name) {
+                Some((current, next)) if current == expr => {
+                    if *next > now {
+                        continue;
+                    }
+                    due.push(name.clone());
+                }
+                _ => {}
+            }
+            match schedule.next_after(now) {
+                Some(next) => {
+                    self.next_runs.insert(name.clone(), (expr.clone(), next));
+                }
+                None => {
+                    self.next_runs.remove(name);
+                }
+            }
+        }
+        due
+    }
+
+    /// When a pipeline is next due, if the scheduler is tracking it
+    #[allow(dead_code)]
+    pub fn next_run(&self, name: &str) -> Option<DateTime<Utc>> {
+        self.next_runs.get(name).map(|(_, next)| *next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(h: u32, m: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, h, m, s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_intervals() {
+        for (expr, secs) in [
+            ("every 30s", 30),
+            ("every 5m", 300),
+            ("every 2 hours", 7200),
+            ("every 1d", 86_400),
+        ] {
+            match Schedule::parse(expr).unwrap() {
+                Schedule::Every(d) => assert_eq!(d, Duration::from_secs(secs), "{}", expr),
+                other => panic!("{} parsed as {:?}", expr, other),
+            }
+        }
+        for bad in [
+            "every",
+            "every 0s",
+            "every 5w",
+            "every m",
+            "sometimes",
+            "* * *",
+        ] {
+            assert!(Schedule::parse(bad).is_err(), "{} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        // Five fields: minute 0 of every hour
+        let hourly = Schedule::parse("0 * * * *").unwrap();
+        assert_eq!(hourly.next_after(at(10, 15, 0)), Some(at(11, 0, 0)));
+
+        // Six fields: every 10 seconds
+        let often = Schedule::parse("*/10 * * * * *").unwrap();
+        assert_eq!(often.next_after(at(10, 15, 3)), Some(at(10, 15, 10)));
+
+        assert!(Schedule::parse("61 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_scheduler_runs_due_pipelines() {
+        let mut scheduler = Scheduler::default();
+        let scheduled = vec![("metrics".to_string(), "every 10s".to_string())];
+
+        // First sighting only starts the clock
+        assert!(scheduler.due(&scheduled, at(10, 0, 0)).is_empty());
+        assert_eq!(scheduler.next_run("metrics"), Some(at(10, 0, 10)));
+        assert!(scheduler.due(&scheduled, at(10, 0, 9)).is_empty());
+        assert_eq!(scheduler.due(&scheduled, at(10, 0, 10)), vec!["metrics"]);
+        assert_eq!(scheduler.next_run("metrics"), Some(at(10, 0, 20)));
+
+        // A changed schedule restarts the clock; an unscheduled pipeline is forgotten
+        let changed = vec![("metrics".to_string(), "every 1m".to_string())];
+        assert!(scheduler.due(&changed, at(10, 0, 20)).is_empty());
+        assert_eq!(scheduler.next_run("metrics"), Some(at(10, 1, 20)));
+        assert!(scheduler.due(&[], at(10, 5, 0)).is_empty());
+        assert_eq!(scheduler.next_run("metrics"), None);
+    }
+}
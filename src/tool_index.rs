@@ -0,0 +1,283 @@
+//! Community tool index - a JSON file listing shareable tools by name, so
+//! `import_tool(source: "index:<name>")` can install them without a URL.
+//!
+//! The index lives at `SKILLZ_TOOL_INDEX` (an https:// URL, a local path or
+//! a file:// URL), by default [`DEFAULT_TOOL_INDEX`]:
+//!
+//! ```json
+//! {
+//!   "format_version": 1,
+//!   "tools": [
+//!     {
+//!       "name": "weather_fetch",
+//!       "description": "Current weather for a city",
+//!       "source": "https://github.com/user/skillz-weather",
+//!       "checksum": "sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+//!       "tags": ["weather", "http"],
+//!       "version": "1.2.0"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `source` is anything `import_tool` accepts. `checksum` is the tool's
+//! content hash: what `import_tool(inspect: true)` reports, and what an
+//! import records as `imported_from.content_hash`. An index import is
+//! fetched into quarantine and only registered when the hash matches.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+
+use crate::importer::{self, ImportSource};
+use crate::registry::validate_tool_name;
+
+/// Environment variable with the location of the tool index
+pub const TOOL_INDEX_ENV: &str = "SKILLZ_TOOL_INDEX";
+/// Index used when SKILLZ_TOOL_INDEX isn't set
+pub const DEFAULT_TOOL_INDEX: &str =
+    "https://raw.githubusercontent.com/Algiras/skillz/main/tool-index.json";
+/// Version of the index format, bumped on incompatible changes
+pub const INDEX_FORMAT_VERSION: u32 = 1;
+/// Prefix of import sources naming an index entry
+pub const INDEX_PREFIX: &str = "index:";
+
+/// The tool index
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolIndex {
+    #[serde(default = "default_format_version")]
+    pub format_version: u32,
+    pub tools: Vec<IndexEntry>,
+}
+
+/// One tool in the index
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexEntry {
+    /// Tool name, as in its manifest.json
+    pub name: String,
+    pub description: String,
+    /// Where to import it from (git URL, gist, package, raw URL...)
+    pub source: String,
+    /// "sha256:<hex>" content hash of the tool
+    pub checksum: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Version the checksum was taken from
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+fn default_format_version() -> u32 {
+    INDEX_FORMAT_VERSION
+}
+
+/// Location of the tool index: SKILLZ_TOOL_INDEX or the default
+pub fn index_location() -> String {
+    std::env::var(TOOL_INDEX_ENV)
+        .ok()
+        .filter(|location| !location.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_TOOL_INDEX.to_string())
+}
+
+impl ToolIndex {
+    /// Fetch and parse the index at `location` (an http(s) URL, a path or a
+    /// file:// URL)
+    pub fn load(location: &str) -> Result<Self> {
+        let location = location.trim();
+        let bytes = if location.starts_with("http://") || location.starts_with("https://") {
+            importer::download(location)
+                .with_context(|| format!("Failed to fetch the tool index {}", location))?
+        } else {
+            let path = location.strip_prefix("file://").unwrap_or(location);
+            fs::read(path).with_context(|| format!("Failed to read the tool index {}", path))?
+        };
+        Self::parse(&bytes, location)
+    }
+
+    /// Parse and check an index; `location` labels errors
+    pub fn parse(bytes: &[u8], location: &str) -> Result<Self> {
+        let index: ToolIndex = serde_json::from_slice(bytes)
+            .with_context(|| format!("Failed to parse the tool index {}", location))?;
+        if index.format_version == 0 || index.format_version > INDEX_FORMAT_VERSION {
+            anyhow::bail!(
+                "Unsupported tool index format version {} in {} (this skillz supports up to {})",
+                index.format_version,
+                location,
+                INDEX_FORMAT_VERSION
+            );
+        }
+
+        let mut names = HashSet::new();
+        for (i, entry) in index.tools.iter().enumerate() {
+            entry.check().with_context(|| {
+                format!(
+                    "Invalid entry tools[{}] ('{}') in the tool index {}",
+                    i, entry.name, location
+                )
+            })?;
+            if !names.insert(entry.name.as_str()) {
+                anyhow::bail!(
+                    "Tool '{}' is listed more than once in the tool index {}",
+                    entry.name,
+                    location
+                );
+            }
+        }
+        Ok(index)
+    }
+
+    /// The entry named `name`
+    pub fn get(&self, name: &str) -> Option<&IndexEntry> {
+        self.tools.iter().find(|entry| entry.name == name)
+    }
+
+    /// Entries matching every word of `query` in their name, description or
+    /// tags (case-insensitive), name matches first. An empty query lists
+    /// every entry.
+    pub fn search(&self, query: &str) -> Vec<&IndexEntry> {
+        let query = query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+        let mut found: Vec<(u8, &IndexEntry)> = self
+            .tools
+            .iter()
+            .filter_map(|entry| {
+                let name = entry.name.to_lowercase();
+                let description = entry.description.to_lowercase();
+                let tags: Vec<String> = entry.tags.iter().map(|t| t.to_lowercase()).collect();
+                let matches = |word: &str| {
+                    name.contains(word)
+                        || description.contains(word)
+                        || tags.iter().any(|t| t.contains(word))
+                };
+                if !words.iter().all(|word| matches(word)) {
+                    return None;
+                }
+                let rank = if name == query {
+                    0
+                } else if words.iter().any(|word| name.contains(word)) {
+                    1
+                } else if words.iter().any(|word| tags.iter().any(|t| t == word)) {
+                    2
+                } else {
+                    3
+                };
+                Some((rank, entry))
+            })
+            .collect();
+        found.sort_by(|(a_rank, a), (b_rank, b)| (a_rank, &a.name).cmp(&(b_rank, &b.name)));
+        found.into_iter().map(|(_, entry)| entry).collect()
+    }
+}
+
+impl IndexEntry {
+    fn check(&self) -> Result<()> {
+        validate_tool_name(&self.name)?;
+        if self.source.trim().starts_with(INDEX_PREFIX) {
+            anyhow::bail!("source can't be another index entry");
+        }
+        ImportSource::parse(&self.source).context("source isn't an import source")?;
+        let hex = self.checksum.strip_prefix("sha256:").with_context(|| {
+            format!(
+                "checksum '{}' must look like \"sha256:<64 hex digits>\"",
+                self.checksum
+            )
+        })?;
+        if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!(
+                "checksum '{}' must look like \"sha256:<64 hex digits>\"",
+                self.checksum
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether a tool's content hash (as from `tool_content_hash`) is the
+    /// one listed
+    pub fn verifies(&self, content_hash: &str) -> bool {
+        self.checksum
+            .strip_prefix("sha256:")
+            .is_some_and(|hex| hex.eq_ignore_ascii_case(content_hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, description: &str, tags: &[&str]) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "description": description,
+            "source": format!("https://github.com/user/{}", name),
+            "checksum": format!("sha256:{}", "ab".repeat(32)),
+            "tags": tags,
+        })
+    }
+
+    fn parse(tools: Vec<serde_json::Value>) -> Result<ToolIndex> {
+        let index = serde_json::json!({ "format_version": 1, "tools": tools });
+        ToolIndex::parse(index.to_string().as_bytes(), "test-index.json")
+    }
+
+    #[test]
+    fn test_search_ranks_name_matches_first() {
+        let index = parse(vec![
+            entry("forecast", "Weather forecast for a week", &["weather"]),
+            entry("weather_fetch", "Current conditions", &["http"]),
+            entry("csv_stats", "Column statistics", &["data"]),
+        ])
+        .unwrap();
+
+        let names = |query: &str| -> Vec<String> {
+            index
+                .search(query)
+                .into_iter()
+                .map(|e| e.name.clone())
+                .collect()
+        };
+        assert_eq!(names("weather"), ["weather_fetch", "forecast"]);
+        assert_eq!(names("WEEK weather"), ["forecast"]);
+        assert_eq!(names("data"), ["csv_stats"]);
+        assert_eq!(names("").len(), 3);
+        assert!(names("nothing").is_empty());
+        assert_eq!(index.get("csv_stats").unwrap().tags, ["data"]);
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_entry() {
+        let mut bad = entry("weather_fetch", "Weather", &[]);
+        bad["checksum"] = "md5:abc".into();
+        let err = parse(vec![entry("ok", "Fine", &[]), bad]).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(
+            message.contains("tools[1] ('weather_fetch')"),
+            "{}",
+            message
+        );
+        assert!(message.contains("sha256:"), "{}", message);
+
+        let err = parse(vec![entry("dup", "One", &[]), entry("dup", "Two", &[])]).unwrap_err();
+        assert!(err.to_string().contains("more than once"), "{}", err);
+
+        let mut missing = entry("x", "No source", &[]);
+        missing.as_object_mut().unwrap().remove("source");
+        let err = parse(vec![missing]).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("missing field `source`"),
+            "{:#}",
+            err
+        );
+
+        let err = ToolIndex::parse(br#"{"format_version": 9, "tools": []}"#, "i.json").unwrap_err();
+        assert!(err.to_string().contains("Unsupported"), "{}", err);
+    }
+
+    #[test]
+    fn test_verifies_checksum() {
+        let index = parse(vec![entry("a", "A", &[])]).unwrap();
+        let entry = index.get("a").unwrap();
+        assert!(entry.verifies(&"AB".repeat(32)));
+        assert!(!entry.verifies(&"cd".repeat(32)));
+    }
+}
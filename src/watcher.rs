@@ -2,13 +2,28 @@
 //!
 //! Watches the tools directory for changes and automatically reloads
 //! modified tools without server restart.
+//!
+//! Events are coalesced per tool: a save touching several files, or a
+//! dependency install writing into `env/`, results in a single reload.
 
 use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebouncedEvent};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
+use crate::builder::BUILD_CACHE_DIR;
+use crate::memory::MEMORY_DB_FILE;
+use crate::services::SERVICES_DIR;
+
+/// Quiet period after a tool's last change before it is reloaded
+pub const DEFAULT_DEBOUNCE_MS: u64 = 300;
+
+/// Directories whose contents never trigger a reload (dependency
+/// environments, version control, build artifacts)
+const IGNORED_DIRS: &[&str] = &["env", ".git", BUILD_CACHE_DIR];
+
 /// Events emitted by the file watcher
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -19,6 +34,8 @@ pub enum WatchEvent {
     ToolAdded(String),
     /// A tool was removed
     ToolRemoved(String),
+    /// A service definition (services/<name>.json) was added, changed or removed
+    ServiceChanged(String),
     /// Watcher error
     Error(String),
 }
@@ -48,7 +65,8 @@ impl ToolWatcher {
         let (tx, rx) = std::sync::mpsc::channel();
 
         // Create debounced watcher (debounces rapid file changes)
-        let mut debouncer = new_debouncer(Duration::from_millis(500), tx)?;
+        let window = Duration::from_millis(DEFAULT_DEBOUNCE_MS);
+        let mut debouncer = new_debouncer(window, tx)?;
 
         // Watch the tools directory recursively
         debouncer
@@ -58,7 +76,8 @@ impl ToolWatcher {
         eprintln!("🔥 Hot reload enabled - watching {}", tools_dir.display());
 
         // Spawn a task to handle file events
-        let handle = tokio::task::spawn_blocking(move || Self::event_loop(rx, tools_dir, event_tx));
+        let handle =
+            tokio::task::spawn_blocking(move || Self::event_loop(rx, tools_dir, event_tx, window));
 
         Ok(WatchHandle {
             _debouncer: debouncer,
@@ -66,32 +85,42 @@ impl ToolWatcher {
         })
     }
 
-    /// Event loop that processes file system events
+    /// Event loop that processes file system events, holding each tool's
+    /// event back until it has been quiet for `window`
     fn event_loop(
         rx: std::sync::mpsc::Receiver<
             Result<Vec<DebouncedEvent>, notify_debouncer_mini::notify::Error>,
         >,
         tools_dir: PathBuf,
         event_tx: mpsc::Sender<WatchEvent>,
+        window: Duration,
     ) {
+        let mut coalescer = EventCoalescer::new(window);
         loop {
-            match rx.recv() {
+            let timeout = coalescer.next_deadline(Instant::now()).unwrap_or(window);
+            match rx.recv_timeout(timeout) {
                 Ok(Ok(events)) => {
+                    let now = Instant::now();
                     for event in events {
                         if let Some(watch_event) = Self::process_event(&event, &tools_dir) {
-                            // Use blocking send since we're in a sync context
-                            let _ = event_tx.blocking_send(watch_event);
+                            coalescer.push(watch_event, now);
                         }
                     }
                 }
                 Ok(Err(e)) => {
                     let _ = event_tx.blocking_send(WatchEvent::Error(e.to_string()));
                 }
-                Err(_) => {
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
                     // Channel closed, watcher stopped
                     break;
                 }
             }
+
+            for watch_event in coalescer.take_ready(Instant::now()) {
+                // Use blocking send since we're in a sync context
+                let _ = event_tx.blocking_send(watch_event);
+            }
         }
     }
 
@@ -120,6 +149,17 @@ impl ToolWatcher {
             return None;
         }
 
+        if is_ignored(rel_path) {
+            return None;
+        }
+
+        if tool_name == SERVICES_DIR {
+            return match (path.extension()?.to_str()?, path.file_stem()?.to_str()?) {
+                ("json", service) => Some(WatchEvent::ServiceChanged(service.to_string())),
+                _ => None,
+            };
+        }
+
         // Check what file changed
         let file_name = path.file_name()?.to_str()?;
 
@@ -152,6 +192,69 @@ impl ToolWatcher {
     }
 }
 
+/// Whether a path (relative to the tools directory) is inside an ignored
+/// directory or belongs to the memory database (including its -wal/-shm files)
+fn is_ignored(rel_path: &Path) -> bool {
+    rel_path.components().any(|component| match component {
+        Component::Normal(name) => name
+            .to_str()
+            .is_some_and(|name| IGNORED_DIRS.contains(&name) || name.starts_with(MEMORY_DB_FILE)),
+        _ => false,
+    })
+}
+
+/// Coalesces watch events per tool (or service): each one's latest event is released
+/// once no further event for it has arrived within the window
+pub struct EventCoalescer {
+    window: Duration,
+    pending: HashMap<String, (WatchEvent, Instant)>,
+}
+
+impl EventCoalescer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Record an event seen at `now`, replacing the tool's pending one
+    pub fn push(&mut self, event: WatchEvent, now: Instant) {
+        let key = match &event {
+            WatchEvent::ToolModified(name)
+            | WatchEvent::ToolAdded(name)
+            | WatchEvent::ToolRemoved(name) => name.clone(),
+            WatchEvent::ServiceChanged(name) => format!("{}/{}", SERVICES_DIR, name),
+            WatchEvent::Error(_) => String::new(),
+        };
+        self.pending.insert(key, (event, now));
+    }
+
+    /// Events whose tool has been quiet for the whole window
+    pub fn take_ready(&mut self, now: Instant) -> Vec<WatchEvent> {
+        let window = self.window;
+        let ready: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, seen))| now.duration_since(*seen) >= window)
+            .map(|(tool, _)| tool.clone())
+            .collect();
+        ready
+            .into_iter()
+            .filter_map(|tool| self.pending.remove(&tool))
+            .map(|(event, _)| event)
+            .collect()
+    }
+
+    /// Time until the next pending event is ready, if any
+    pub fn next_deadline(&self, now: Instant) -> Option<Duration> {
+        self.pending
+            .values()
+            .map(|(_, seen)| (*seen + self.window).saturating_duration_since(now))
+            .min()
+    }
+}
+
 /// Handle to the running watcher
 /// Dropping this handle will stop the watcher
 pub struct WatchHandle {
@@ -270,4 +373,75 @@ mod tests {
         };
         assert!(ToolWatcher::process_event(&txt_event, &tools_dir).is_none());
     }
+
+    #[test]
+    fn test_process_event_ignores_env_and_memory_db() {
+        let tools_dir = PathBuf::from("/tools");
+
+        for path in [
+            "/tools/my_tool/env/lib/python3.12/site-packages/six.py",
+            "/tools/my_tool/.git/hooks/pre-commit.sh",
+            "/tools/my_tool/.build_cache/out.wasm",
+            "/tools/.memory.db-wal",
+        ] {
+            let event = DebouncedEvent {
+                path: PathBuf::from(path),
+                kind: DebouncedEventKind::Any,
+            };
+            assert!(
+                ToolWatcher::process_event(&event, &tools_dir).is_none(),
+                "{} should be ignored",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_rapid_modifications_coalesce() {
+        let window = Duration::from_millis(DEFAULT_DEBOUNCE_MS);
+        let mut coalescer = EventCoalescer::new(window);
+        let start = Instant::now();
+
+        // manifest.json and the script saved in quick succession, twice
+        for i in 0..4 {
+            let now = start + Duration::from_millis(50 * i);
+            coalescer.push(WatchEvent::ToolModified("my_tool".to_string()), now);
+            assert!(coalescer.take_ready(now).is_empty());
+        }
+        coalescer.push(
+            WatchEvent::ToolModified("other".to_string()),
+            start + Duration::from_millis(250),
+        );
+
+        let last = start + Duration::from_millis(150);
+        assert_eq!(coalescer.next_deadline(last), Some(window));
+
+        let ready = coalescer.take_ready(last + window);
+        assert_eq!(ready.len(), 1);
+        assert!(matches!(&ready[0], WatchEvent::ToolModified(name) if name == "my_tool"));
+        assert!(coalescer.take_ready(last + window).is_empty());
+
+        let ready = coalescer.take_ready(start + Duration::from_millis(250) + window);
+        assert!(matches!(&ready[..], [WatchEvent::ToolModified(name)] if name == "other"));
+    }
+
+    #[test]
+    fn test_process_event_service_definitions() {
+        let tools_dir = PathBuf::from("/tools");
+
+        let event = DebouncedEvent {
+            path: PathBuf::from("/tools/services/postgres.json"),
+            kind: DebouncedEventKind::Any,
+        };
+        assert!(matches!(
+            ToolWatcher::process_event(&event, &tools_dir),
+            Some(WatchEvent::ServiceChanged(name)) if name == "postgres"
+        ));
+
+        let other = DebouncedEvent {
+            path: PathBuf::from("/tools/services/notes.txt"),
+            kind: DebouncedEventKind::Any,
+        };
+        assert!(ToolWatcher::process_event(&other, &tools_dir).is_none());
+    }
 }
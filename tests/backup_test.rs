@@ -0,0 +1,172 @@
+//! Tests for backing up and restoring the whole tools directory
+
+use flate2::read::GzDecoder;
+use skillz::backup::{Backup, BACKUP_METADATA_FILE};
+use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Entry paths in a backup
+fn entry_paths(archive: &Path) -> BTreeSet<String> {
+    let mut archive = tar::Archive::new(GzDecoder::new(fs::File::open(archive).unwrap()));
+    archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path().unwrap().to_string_lossy().to_string();
+            path.trim_end_matches('/').to_string()
+        })
+        .collect()
+}
+
+/// A registry with a script tool, a WASM tool, memory, a service and the
+/// directories backups leave out
+fn populated_registry(storage_dir: &Path) -> ToolRegistry {
+    let registry = ToolRegistry::new(storage_dir.to_path_buf());
+    let mut manifest = ToolManifest::new(
+        "shout".to_string(),
+        "Uppercases text".to_string(),
+        ToolType::Script,
+    );
+    manifest.interpreter = Some("bash".to_string());
+    registry
+        .register_tool(manifest, b"echo '{\"result\": \"HI\"}'\n")
+        .unwrap();
+    let manifest = ToolManifest::new(
+        "adder".to_string(),
+        "Adds numbers".to_string(),
+        ToolType::Wasm,
+    );
+    registry
+        .register_wasm_tool(manifest, b"\0asm-module", "fn main() {}")
+        .unwrap();
+
+    // Left open in WAL mode with the last write only in the log, like a
+    // running server's database
+    let db = rusqlite::Connection::open(storage_dir.join(".memory.db")).unwrap();
+    db.execute_batch(
+        "PRAGMA journal_mode=WAL;
+         PRAGMA wal_autocheckpoint=0;
+         CREATE TABLE notes (text TEXT);
+         INSERT INTO notes VALUES ('in the wal');",
+    )
+    .unwrap();
+    std::mem::forget(db);
+    fs::create_dir_all(storage_dir.join("services")).unwrap();
+    fs::write(storage_dir.join("services/redis.json"), b"{}").unwrap();
+    fs::create_dir_all(storage_dir.join("shout/env/bin")).unwrap();
+    fs::write(storage_dir.join("shout/env/bin/python"), b"").unwrap();
+    fs::create_dir_all(storage_dir.join(".build_cache/abc")).unwrap();
+    fs::create_dir_all(storage_dir.join("exports")).unwrap();
+    fs::write(storage_dir.join("exports/old.skillz"), b"").unwrap();
+    registry
+}
+
+/// Test that a backup holds tools, memory and services, but not envs,
+/// the build cache, exports or the database's WAL files
+#[test]
+fn test_backup_entries() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage_dir = temp_dir.path().join("tools");
+    let registry = populated_registry(&storage_dir);
+    assert!(storage_dir.join(".memory.db-wal").exists());
+
+    let out = temp_dir.path().join("backup.tar.gz");
+    let metadata = Backup::create(&registry, &out).unwrap();
+    assert_eq!(metadata.tools, ["adder", "shout"]);
+
+    let paths = entry_paths(&out);
+    for expected in [
+        BACKUP_METADATA_FILE,
+        ".memory.db",
+        "services/redis.json",
+        "shout/manifest.json",
+        "adder/manifest.json",
+        "adder/src.rs",
+    ] {
+        assert!(
+            paths.contains(expected),
+            "{} missing: {:?}",
+            expected,
+            paths
+        );
+    }
+    for path in &paths {
+        assert!(
+            !path.starts_with("shout/env")
+                && !path.starts_with(".build_cache")
+                && !path.starts_with("exports")
+                && path != ".memory.db-wal"
+                && path != ".memory.db-shm",
+            "{} shouldn't be backed up",
+            path
+        );
+    }
+    assert!(!out.with_extension("partial").exists());
+}
+
+/// Test that a restore brings back the backed-up state and keeps the
+/// replaced directory
+#[test]
+fn test_restore_replaces_tools_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage_dir = temp_dir.path().join("tools");
+    let registry = populated_registry(&storage_dir);
+    let out = temp_dir.path().join("backup.tar.gz");
+    Backup::create(&registry, &out).unwrap();
+
+    // Lose a tool and add another after the backup
+    registry.delete_tool("shout").unwrap();
+    let manifest = ToolManifest::new("later".to_string(), "Later".to_string(), ToolType::Script);
+    registry.register_tool(manifest, b"echo\n").unwrap();
+
+    let summary = Backup::restore(&registry, &out, false).unwrap();
+    let mut names: Vec<String> = registry
+        .list_tools()
+        .iter()
+        .map(|t| t.name().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["adder", "shout"]);
+    assert!(summary.warnings.is_empty(), "{:?}", summary.warnings);
+    assert_eq!(
+        fs::read(storage_dir.join("adder/adder.wasm")).unwrap(),
+        b"\0asm-module"
+    );
+    // The snapshot includes what was only in the write-ahead log
+    let db = rusqlite::Connection::open(storage_dir.join(".memory.db")).unwrap();
+    let text: String = db
+        .query_row("SELECT text FROM notes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(text, "in the wal");
+    assert!(!storage_dir.join("shout/env").exists());
+
+    let previous = summary.previous_dir.unwrap();
+    assert!(previous.join("later/manifest.json").exists());
+    assert_eq!(previous.parent(), storage_dir.parent());
+}
+
+/// Test that an archive that isn't a backup leaves the tools directory alone
+#[test]
+fn test_restore_rejects_other_archives() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage_dir = temp_dir.path().join("tools");
+    let registry = populated_registry(&storage_dir);
+
+    let package = skillz::exporter::Exporter::package(&registry, "shout").unwrap();
+    let not_backup = temp_dir.path().join("shout.skillz");
+    fs::write(&not_backup, package).unwrap();
+
+    let err = Backup::restore(&registry, &not_backup, false).unwrap_err();
+    assert!(err.to_string().contains("not a skillz backup"), "{:#}", err);
+    assert!(storage_dir.join("shout/manifest.json").exists());
+    let leftovers: Vec<_> = fs::read_dir(temp_dir.path())
+        .unwrap()
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| name.contains("restore"))
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+}
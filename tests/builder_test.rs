@@ -104,6 +104,28 @@ fn main() {
     let _ = fs::remove_file(wasm_path);
 }
 
+/// Test that compile failures carry structured rustc diagnostics
+#[test]
+fn test_compile_error_diagnostics() {
+    let code = r#"
+fn main() {
+    let y = undefined_value + 1;
+    println!("{}", y);
+}
+"#;
+
+    let err = skillz::builder::Builder::compile_tool("test_diagnostics", code)
+        .expect_err("Compilation should fail for undefined value");
+    let compile_err = err
+        .downcast_ref::<skillz::builder::CompileError>()
+        .expect("Error should be a CompileError");
+
+    let errors = compile_err.errors();
+    assert!(!errors.is_empty(), "Should report at least one error");
+    assert_eq!(errors[0].line, Some(3));
+    assert!(errors[0].message.contains("undefined_value"));
+}
+
 // ==================== WASM Dependency Tests ====================
 
 mod wasm_deps {
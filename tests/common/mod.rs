@@ -45,10 +45,11 @@ pub fn compile_test_tool(name: &str, code: &str) -> Result<PathBuf> {
         .output()?;
 
     if !output.status.success() {
-        anyhow::bail!(
-            "Cargo build failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if skillz::builder::is_missing_target_error(&stderr) {
+            anyhow::bail!(skillz::builder::missing_target_message());
+        }
+        anyhow::bail!("Cargo build failed: {}", stderr);
     }
 
     let wasm_path = project_path
@@ -0,0 +1,276 @@
+//! Tests for exporting tools as .skillz packages
+
+use flate2::read::GzDecoder;
+use skillz::exporter::{Exporter, PackageMetadata, METADATA_FILE, PACKAGE_FORMAT_VERSION};
+use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+use std::collections::BTreeMap;
+use std::io::Read;
+use tempfile::TempDir;
+
+/// Unpack a package into a map of entry path -> contents
+fn entries(package: &[u8]) -> BTreeMap<String, Vec<u8>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(package));
+    archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().to_string();
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents).unwrap();
+            (path, contents)
+        })
+        .collect()
+}
+
+fn register_script(registry: &ToolRegistry) {
+    let mut manifest = ToolManifest::new(
+        "shout".to_string(),
+        "Uppercases text".to_string(),
+        ToolType::Script,
+    );
+    manifest.interpreter = Some("python3".to_string());
+    manifest.dependencies = vec!["requests".to_string()];
+    registry
+        .register_tool(manifest, b"print('HELLO')\n")
+        .unwrap();
+}
+
+/// A script package holds the manifest, script and metadata only
+#[test]
+fn test_export_script_tool_entries() {
+    let temp_dir = TempDir::new().unwrap();
+    let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+    register_script(&registry);
+    // Update once so a versions/ backup exists, and fake an installed env
+    register_script(&registry);
+    std::fs::create_dir_all(temp_dir.path().join("shout/env/lib")).unwrap();
+    std::fs::write(temp_dir.path().join("shout/env/lib/site.py"), "x").unwrap();
+    std::fs::write(
+        temp_dir.path().join("shout/requirements.lock"),
+        "# skillz-dependencies: requests\nrequests==2.31.0\n",
+    )
+    .unwrap();
+
+    let package = Exporter::package(&registry, "shout").unwrap();
+    let entries = entries(&package);
+
+    let names: Vec<&str> = entries.keys().map(|k| k.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "shout/manifest.json",
+            "shout/requirements.lock",
+            "shout/shout.py",
+            "shout/skillz-package.json"
+        ]
+    );
+    assert_eq!(entries["shout/shout.py"], b"print('HELLO')\n");
+
+    let manifest: ToolManifest = serde_json::from_slice(&entries["shout/manifest.json"]).unwrap();
+    assert_eq!(manifest.name, "shout");
+    assert_eq!(manifest.version, "1.0.1");
+
+    let metadata: PackageMetadata =
+        serde_json::from_slice(&entries[&format!("shout/{}", METADATA_FILE)]).unwrap();
+    assert_eq!(metadata.format_version, PACKAGE_FORMAT_VERSION);
+    assert_eq!(metadata.tool_type, ToolType::Script);
+    assert_eq!(metadata.tool_version, "1.0.1");
+    assert_eq!(metadata.dependencies, vec!["requests"]);
+}
+
+/// Packages are written to the exports directory, named by version
+#[test]
+fn test_export_to_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+    register_script(&registry);
+
+    let exports = temp_dir.path().join("exports");
+    let path = Exporter::export_to_dir(&registry, "shout", &exports).unwrap();
+    assert_eq!(path, exports.join("shout-1.0.0.skillz"));
+    assert!(entries(&std::fs::read(&path).unwrap()).contains_key("shout/shout.py"));
+
+    assert!(Exporter::export_to_dir(&registry, "missing", &exports).is_err());
+}
+
+/// A request received by `mock_github`
+#[derive(Debug, Clone)]
+struct ApiRequest {
+    method: String,
+    path: String,
+    authorization: String,
+    body: serde_json::Value,
+}
+
+/// Serve a minimal GitHub gist API: creating gist "abc123", reading it (with
+/// a stale `old.txt` file) and updating it. Requests with a token other than
+/// "good-token" get 401. Returns the base URL and the requests received.
+fn mock_github() -> (String, std::sync::Arc<std::sync::Mutex<Vec<ApiRequest>>>) {
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = requests.clone();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut raw = Vec::new();
+            let mut buf = [0u8; 4096];
+            let header_end = loop {
+                let n = stream.read(&mut buf).unwrap();
+                raw.extend_from_slice(&buf[..n]);
+                if let Some(pos) = raw.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break pos + 4;
+                }
+                if n == 0 {
+                    break raw.len();
+                }
+            };
+            let head = String::from_utf8_lossy(&raw[..header_end]).to_string();
+            let header = |name: &str| {
+                head.lines()
+                    .find_map(|line| {
+                        let (key, value) = line.split_once(':')?;
+                        key.eq_ignore_ascii_case(name)
+                            .then(|| value.trim().to_string())
+                    })
+                    .unwrap_or_default()
+            };
+            let length: usize = header("content-length").parse().unwrap_or(0);
+            while raw.len() < header_end + length {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                raw.extend_from_slice(&buf[..n]);
+            }
+            let mut words = head.split_whitespace();
+            let request = ApiRequest {
+                method: words.next().unwrap_or("").to_string(),
+                path: words.next().unwrap_or("").to_string(),
+                authorization: header("authorization"),
+                body: serde_json::from_slice(&raw[header_end..]).unwrap_or_default(),
+            };
+            log.lock().unwrap().push(request.clone());
+
+            let gist = r#"{"id":"abc123","html_url":"https://gist.github.com/me/abc123","files":{"manifest.json":{},"old.txt":{}}}"#;
+            let (status, body) = if request.authorization != "Bearer good-token" {
+                ("401 Unauthorized", r#"{"message":"Bad credentials"}"#)
+            } else {
+                match (request.method.as_str(), request.path.as_str()) {
+                    ("POST", "/gists") => ("201 Created", gist),
+                    ("GET", "/gists/abc123") | ("PATCH", "/gists/abc123") => ("200 OK", gist),
+                    _ => ("404 Not Found", r#"{"message":"Not Found"}"#),
+                }
+            };
+            let _ = stream.write_all(
+                format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .as_bytes(),
+            );
+        }
+    });
+    (format!("http://127.0.0.1:{}", port), requests)
+}
+
+/// Sharing creates a gist with the manifest and script, then updates it
+#[tokio::test]
+async fn test_share_tool_as_gist() {
+    use skillz::exporter::GistSharer;
+
+    let temp_dir = TempDir::new().unwrap();
+    let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+    register_script(&registry);
+    let (api_url, requests) = mock_github();
+    let sharer = GistSharer::new("good-token".to_string()).with_api_url(&api_url);
+
+    let shared = sharer
+        .share(&registry, "shout", false, false)
+        .await
+        .unwrap();
+    assert_eq!(shared.id, "abc123");
+    assert_eq!(shared.url, "https://gist.github.com/me/abc123");
+    assert!(!shared.updated);
+    assert_eq!(shared.files, vec!["manifest.json", "shout.py"]);
+    {
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let create = &requests[0];
+        assert_eq!(
+            (create.method.as_str(), create.path.as_str()),
+            ("POST", "/gists")
+        );
+        assert_eq!(create.body["public"], false);
+        let files = create.body["files"].as_object().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files["shout.py"]["content"], "print('HELLO')\n");
+        let manifest: ToolManifest =
+            serde_json::from_str(files["manifest.json"]["content"].as_str().unwrap()).unwrap();
+        assert_eq!(manifest.name, "shout");
+        assert!(manifest.shared_gist.is_none());
+    }
+    // The gist is recorded locally, so sharing again updates it
+    let tool = registry.get_tool("shout").unwrap();
+    assert_eq!(tool.manifest.shared_gist.as_deref(), Some("abc123"));
+
+    let shared = sharer
+        .share(&registry, "shout", false, false)
+        .await
+        .unwrap();
+    assert!(shared.updated);
+    let requests = requests.lock().unwrap();
+    let methods: Vec<&str> = requests.iter().map(|r| r.method.as_str()).collect();
+    assert_eq!(methods, vec!["POST", "GET", "PATCH"]);
+    let update = &requests[2];
+    assert_eq!(update.path, "/gists/abc123");
+    // Files the tool doesn't have any more are deleted from the gist
+    assert_eq!(update.body["files"]["old.txt"], serde_json::Value::Null);
+    assert!(update.body["files"]["shout.py"]["content"].is_string());
+}
+
+/// Secrets in env declarations never leave the machine
+#[tokio::test]
+async fn test_share_strips_env_values() {
+    use skillz::exporter::GistSharer;
+    use skillz::registry::McpServerConfig;
+
+    let temp_dir = TempDir::new().unwrap();
+    let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+    let mut manifest = ToolManifest::new("remote".to_string(), "Remote".to_string(), ToolType::Mcp);
+    manifest.mcp_server = Some(McpServerConfig {
+        command: "npx".to_string(),
+        args: vec!["some-server".to_string()],
+        env: [("API_KEY".to_string(), "sk-very-secret".to_string())].into(),
+    });
+    registry.register_tool(manifest, &[]).unwrap();
+    let (api_url, requests) = mock_github();
+
+    let shared = GistSharer::new("good-token".to_string())
+        .with_api_url(&api_url)
+        .share(&registry, "remote", true, false)
+        .await
+        .unwrap();
+    assert_eq!(shared.required_env, vec!["API_KEY"]);
+    {
+        let requests = requests.lock().unwrap();
+        let body = requests[0].body.to_string();
+        assert!(!body.contains("sk-very-secret"), "{}", body);
+        assert!(body.contains("API_KEY"));
+        assert_eq!(requests[0].body["public"], true);
+    }
+
+    // A rejected token is reported without being echoed
+    let err = GistSharer::new("bad-token".to_string())
+        .with_api_url(&api_url)
+        .share(&registry, "remote", false, true)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Authentication failed"), "{}", err);
+    assert!(!err.to_string().contains("bad-token"));
+}
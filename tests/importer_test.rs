@@ -272,3 +272,898 @@ mod output_parsing {
         assert_eq!(step_output["status"], 200);
     }
 }
+
+/// Test importing .skillz packages produced by the exporter
+mod packages {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use skillz::exporter::{Exporter, METADATA_FILE};
+    use skillz::importer::Importer;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use tempfile::TempDir;
+
+    fn register_echo(registry: &ToolRegistry) {
+        let mut manifest =
+            ToolManifest::new("echo".to_string(), "Echoes".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        registry
+            .register_tool(
+                manifest,
+                b"read line\necho '{\"jsonrpc\":\"2.0\",\"result\":\"pong\",\"id\":1}'\n",
+            )
+            .unwrap();
+    }
+
+    /// Build a tar.gz with raw entry paths (bypassing tar's own path checks)
+    fn raw_package(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, contents) in entries {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_cksum();
+            archive.append(&header, *contents).unwrap();
+        }
+        archive.into_inner().unwrap().finish().unwrap()
+    }
+
+    /// Export from one TOOLS_DIR, import into another, and run the tool
+    #[test]
+    fn test_round_trip() {
+        let source_dir = TempDir::new().unwrap();
+        let source = ToolRegistry::new(source_dir.path().to_path_buf());
+        register_echo(&source);
+        let package =
+            Exporter::export_to_dir(&source, "echo", &source_dir.path().join("exports")).unwrap();
+
+        let target_dir = TempDir::new().unwrap();
+        let target = ToolRegistry::new(target_dir.path().to_path_buf());
+        let importer = Importer::new(target_dir.path().to_path_buf());
+        let result = importer
+            .import(package.to_str().unwrap(), &target, false)
+            .unwrap();
+        assert_eq!(result.tool_name, "echo");
+        assert_eq!(result.tool_type, ToolType::Script);
+        assert!(!target_dir.path().join("echo").join(METADATA_FILE).exists());
+
+        let tool = target.get_tool("echo").expect("imported tool is loaded");
+        let output = ToolRuntime::new()
+            .unwrap()
+            .call_tool(&tool, serde_json::json!({}))
+            .unwrap();
+        assert_eq!(output, serde_json::json!("pong"));
+
+        // Importing again needs overwrite, which keeps the old version as a backup
+        assert!(importer
+            .import(package.to_str().unwrap(), &target, false)
+            .unwrap_err()
+            .to_string()
+            .contains("already exists"));
+        importer
+            .import(package.to_str().unwrap(), &target, true)
+            .unwrap();
+        assert!(target_dir.path().join("echo/versions/1.0.0").is_dir());
+    }
+
+    #[test]
+    fn test_rejects_path_traversal() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let importer = Importer::new(temp_dir.path().join("tools"));
+
+        for path in [
+            "echo/../../evil.sh",
+            "/etc/evil",
+            "echo/nested/evil.sh",
+            "evil.sh",
+        ] {
+            let package = raw_package(&[(path, b"boom")]);
+            let err = importer
+                .import_package(&package, "test", &registry, false)
+                .unwrap_err();
+            assert!(
+                err.to_string().contains("outside the tool directory"),
+                "{}: {}",
+                path,
+                err
+            );
+        }
+        assert!(!temp_dir.path().join("evil.sh").exists());
+    }
+
+    #[test]
+    fn test_rejects_malformed_packages() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let importer = Importer::new(temp_dir.path().to_path_buf());
+        let import = |bytes: &[u8]| {
+            importer
+                .import_package(bytes, "test", &registry, false)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert!(import(b"definitely not gzip").contains("Malformed package"));
+        assert!(import(&raw_package(&[("echo/echo.sh", b"x")])).contains(METADATA_FILE));
+
+        let metadata = serde_json::json!({
+            "format_version": 99,
+            "skillz_version": "9.0.0",
+            "tool_name": "echo",
+            "tool_type": "script",
+            "tool_version": "1.0.0"
+        })
+        .to_string();
+        let path = format!("echo/{}", METADATA_FILE);
+        assert!(import(&raw_package(&[(&path, metadata.as_bytes())]))
+            .contains("Unsupported package format version 99"));
+    }
+}
+
+/// Test importing from local tool directories and raw file URLs
+mod local_and_raw_sources {
+    use skillz::importer::Importer;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use tempfile::TempDir;
+
+    const ECHO_SCRIPT: &str =
+        "read line\necho '{\"jsonrpc\":\"2.0\",\"result\":\"pong\",\"id\":1}'\n";
+
+    fn echo_manifest(entry_file: Option<&str>) -> String {
+        let mut manifest =
+            ToolManifest::new("echo".to_string(), "Echoes".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        manifest.entry_file = entry_file.map(str::to_string);
+        serde_json::to_string_pretty(&manifest).unwrap()
+    }
+
+    fn call_echo(registry: &ToolRegistry) -> serde_json::Value {
+        let tool = registry.get_tool("echo").expect("imported tool is loaded");
+        ToolRuntime::new()
+            .unwrap()
+            .call_tool(&tool, serde_json::json!({}))
+            .unwrap()
+    }
+
+    /// Serve `files` (path -> body) over HTTP; anything else is a 404.
+    /// Returns the server's base URL.
+    fn serve(files: Vec<(&'static str, String)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or("");
+                let response = match files.iter().find(|(p, _)| *p == path) {
+                    Some((_, body)) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    None => {
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_string()
+                    }
+                };
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        format!("http://127.0.0.1:{}", port)
+    }
+
+    #[test]
+    fn test_import_from_directory() {
+        let source_dir = TempDir::new().unwrap();
+        let tool_src = source_dir.path().join("my-echo");
+        std::fs::create_dir_all(tool_src.join("lib")).unwrap();
+        std::fs::create_dir_all(tool_src.join(".git")).unwrap();
+        std::fs::write(tool_src.join("manifest.json"), echo_manifest(None)).unwrap();
+        std::fs::write(tool_src.join("echo.sh"), ECHO_SCRIPT).unwrap();
+        std::fs::write(tool_src.join("lib/helper.txt"), "data").unwrap();
+
+        let target_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(target_dir.path().to_path_buf());
+        let importer = Importer::new(target_dir.path().to_path_buf());
+        let source = format!("file://{}", tool_src.display());
+        let result = importer.import(&source, &registry, false).unwrap();
+        assert_eq!(result.tool_name, "echo");
+        assert_eq!(call_echo(&registry), serde_json::json!("pong"));
+
+        // Copied, not moved; nested files come along but .git doesn't
+        assert!(tool_src.join("echo.sh").exists());
+        let tool_dir = target_dir.path().join("echo");
+        assert!(tool_dir.join("lib/helper.txt").exists());
+        assert!(!tool_dir.join(".git").exists());
+
+        // A second import needs overwrite, which backs up the current version
+        let err = importer.import(&source, &registry, false).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{}", err);
+        importer
+            .import(tool_src.to_str().unwrap(), &registry, true)
+            .unwrap();
+        assert!(tool_dir.join("versions/1.0.0").is_dir());
+    }
+
+    #[test]
+    fn test_import_from_directory_rejects_tools_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest =
+            ToolManifest::new("echo".to_string(), "Echoes".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        registry
+            .register_tool(manifest, ECHO_SCRIPT.as_bytes())
+            .unwrap();
+        let importer = Importer::new(temp_dir.path().to_path_buf());
+
+        for dir in [temp_dir.path().join("echo"), temp_dir.path().to_path_buf()] {
+            let err = importer
+                .import(dir.to_str().unwrap(), &registry, true)
+                .unwrap_err();
+            assert!(err.to_string().contains("overlaps TOOLS_DIR"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_import_from_raw_urls() {
+        let base = serve(vec![
+            ("/tools/echo/manifest.json", echo_manifest(None)),
+            ("/tools/echo/echo.sh", ECHO_SCRIPT.to_string()),
+            ("/paste/manifest.json", echo_manifest(None)),
+            ("/paste/run.sh", ECHO_SCRIPT.to_string()),
+        ]);
+
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let importer = Importer::new(temp_dir.path().to_path_buf());
+
+        // A manifest URL fetches the entry file inferred from the interpreter
+        let result = importer
+            .import(
+                &format!("{}/tools/echo/manifest.json", base),
+                &registry,
+                false,
+            )
+            .unwrap();
+        assert_eq!(result.tool_name, "echo");
+        assert_eq!(call_echo(&registry), serde_json::json!("pong"));
+
+        // A script URL becomes the entry file; its manifest sits next to it
+        importer
+            .import(&format!("{}/paste/run.sh", base), &registry, true)
+            .unwrap();
+        let tool = registry.get_tool("echo").unwrap();
+        assert_eq!(tool.manifest.entry_file.as_deref(), Some("run.sh"));
+        assert_eq!(call_echo(&registry), serde_json::json!("pong"));
+
+        // A missing script fails before anything is replaced
+        let err = importer
+            .import(&format!("{}/missing/x.py", base), &registry, true)
+            .unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("Download failed"),
+            "{:#}",
+            err
+        );
+        assert!(registry.get_tool("echo").is_some());
+    }
+}
+
+/// Test importing every tool in a repository layout
+mod repositories {
+    use skillz::importer::{ImportFilter, Importer};
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn write_tool(dir: &Path, name: &str, description: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        let mut manifest =
+            ToolManifest::new(name.to_string(), description.to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        std::fs::write(
+            dir.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(dir.join(format!("{}.sh", name)), "echo '{}'\n").unwrap();
+    }
+
+    /// tools/{alpha,beta,gamma}, where gamma already exists in TOOLS_DIR
+    fn fixture() -> (TempDir, TempDir, ToolRegistry) {
+        let repo = TempDir::new().unwrap();
+        std::fs::write(repo.path().join("README.md"), "three tools").unwrap();
+        for name in ["alpha", "beta", "gamma"] {
+            write_tool(&repo.path().join("tools").join(name), name, "From the repo");
+        }
+        std::fs::create_dir_all(repo.path().join(".git/tools/hidden")).unwrap();
+        write_tool(&repo.path().join(".git/tools/hidden"), "hidden", "Ignored");
+
+        let tools_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(tools_dir.path().to_path_buf());
+        let mut existing = ToolManifest::new(
+            "gamma".to_string(),
+            "Already here".to_string(),
+            ToolType::Script,
+        );
+        existing.interpreter = Some("bash".to_string());
+        registry.register_tool(existing, b"echo '{}'\n").unwrap();
+        (repo, tools_dir, registry)
+    }
+
+    #[test]
+    fn test_import_all_reports_collisions() {
+        let (repo, tools_dir, registry) = fixture();
+        let importer = Importer::new(tools_dir.path().to_path_buf());
+        let summary = importer
+            .import_all(
+                repo.path().to_str().unwrap(),
+                &registry,
+                &ImportFilter::default(),
+            )
+            .unwrap();
+
+        let imported: Vec<&str> = summary
+            .imported
+            .iter()
+            .map(|r| r.tool_name.as_str())
+            .collect();
+        assert_eq!(imported, vec!["alpha", "beta"]);
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(summary.skipped[0].name, "gamma");
+        assert!(summary.skipped[0].reason.contains("already exists"));
+        assert!(summary.failed.is_empty());
+        assert!(registry.get_tool("hidden").is_none());
+        assert_eq!(
+            registry.get_tool("gamma").unwrap().manifest.description,
+            "Already here"
+        );
+
+        // The collision can be overwritten on its own
+        let summary = importer
+            .import_all(
+                repo.path().to_str().unwrap(),
+                &registry,
+                &ImportFilter {
+                    select: Some(vec!["gamma".to_string(), "delta".to_string()]),
+                    overwrite_tools: vec!["gamma".to_string()],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(summary.imported.len(), 1);
+        assert_eq!(
+            registry.get_tool("gamma").unwrap().manifest.description,
+            "From the repo"
+        );
+        let skipped: Vec<&str> = summary.skipped.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(skipped, vec!["alpha", "beta"]);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].name, "delta");
+    }
+
+    #[test]
+    fn test_import_all_reports_bad_and_duplicate_manifests() {
+        let (repo, tools_dir, registry) = fixture();
+        write_tool(&repo.path().join("extra/alpha-copy"), "alpha", "Clash");
+        std::fs::create_dir_all(repo.path().join("tools/broken")).unwrap();
+        std::fs::write(repo.path().join("tools/broken/manifest.json"), "{").unwrap();
+
+        let importer = Importer::new(tools_dir.path().to_path_buf());
+        let summary = importer
+            .import_all(
+                repo.path().to_str().unwrap(),
+                &registry,
+                &ImportFilter::default(),
+            )
+            .unwrap();
+
+        let imported: Vec<&str> = summary
+            .imported
+            .iter()
+            .map(|r| r.tool_name.as_str())
+            .collect();
+        assert_eq!(imported, vec!["beta"]);
+        let failed: Vec<(&str, &str)> = summary
+            .failed
+            .iter()
+            .map(|t| (t.name.as_str(), t.reason.as_str()))
+            .collect();
+        assert_eq!(failed.len(), 2, "{:?}", failed);
+        assert_eq!(failed[0].0, "tools/broken");
+        assert!(failed[0].1.contains("Failed to parse manifest.json"));
+        assert_eq!(failed[1].0, "alpha");
+        assert!(
+            failed[1].1.contains("extra/alpha-copy, tools/alpha"),
+            "{}",
+            failed[1].1
+        );
+        assert!(registry.get_tool("alpha").is_none());
+    }
+
+    #[test]
+    fn test_single_import_refuses_several_tools() {
+        let (repo, tools_dir, registry) = fixture();
+        let importer = Importer::new(tools_dir.path().to_path_buf());
+        let err = importer
+            .import(repo.path().to_str().unwrap(), &registry, false)
+            .unwrap_err();
+        assert!(err.to_string().contains("Found 3 tools"), "{}", err);
+        assert!(registry.get_tool("alpha").is_none());
+    }
+}
+
+/// Updating imported tools from their recorded source
+mod updates {
+    use skillz::importer::Importer;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn write_upstream(dir: &Path, description: &str, version: &str, script: &str) {
+        let mut manifest = ToolManifest::new(
+            "greet".to_string(),
+            description.to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        manifest.version = version.to_string();
+        std::fs::write(
+            dir.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(dir.join("greet.sh"), script).unwrap();
+    }
+
+    /// An upstream tool directory imported into a fresh TOOLS_DIR
+    fn imported() -> (TempDir, TempDir, ToolRegistry, Importer) {
+        let upstream = TempDir::new().unwrap();
+        write_upstream(upstream.path(), "Says hi", "1.0.0", "echo '\"hi\"'\n");
+        let tools_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(tools_dir.path().to_path_buf());
+        let importer = Importer::new(tools_dir.path().to_path_buf());
+        importer
+            .import(upstream.path().to_str().unwrap(), &registry, false)
+            .unwrap();
+        (upstream, tools_dir, registry, importer)
+    }
+
+    #[test]
+    fn test_import_records_origin() {
+        let (upstream, _tools_dir, registry, importer) = imported();
+        let tool = registry.get_tool("greet").unwrap();
+        let origin = tool.manifest.imported_from.unwrap();
+        assert_eq!(origin.source, upstream.path().to_str().unwrap());
+        assert!(origin.commit.is_none());
+        assert!(origin.is_reachable());
+        assert_eq!(registry.modified_since_import("greet"), Some(false));
+
+        let result = importer.update("greet", &registry, false).unwrap();
+        assert!(result.diff.is_none(), "nothing changed upstream");
+        assert_eq!(result.to_version, "1.0.0");
+    }
+
+    #[test]
+    fn test_update_pulls_upstream_change() {
+        let (upstream, tools_dir, registry, importer) = imported();
+        write_upstream(upstream.path(), "Says hello", "1.0.0", "echo '\"hello\"'\n");
+
+        let result = importer.update("greet", &registry, false).unwrap();
+        // Upstream kept its version number, so the update bumps it
+        assert_eq!(
+            (result.from_version.as_str(), result.to_version.as_str()),
+            ("1.0.0", "1.0.1")
+        );
+        let diff = result.diff.unwrap();
+        assert!(diff.source_diff.unwrap().contains("+echo '\"hello\"'"));
+        assert!(diff
+            .manifest_changes
+            .iter()
+            .any(|c| c.field == "description"));
+
+        let tool = registry.get_tool("greet").unwrap();
+        assert_eq!(tool.manifest.description, "Says hello");
+        assert!(tools_dir
+            .path()
+            .join("greet/versions/1.0.0/greet.sh")
+            .exists());
+        assert_eq!(registry.modified_since_import("greet"), Some(false));
+
+        // A version raised upstream is kept
+        write_upstream(upstream.path(), "Says hey", "2.0.0", "echo '\"hey\"'\n");
+        let result = importer.update("greet", &registry, false).unwrap();
+        assert_eq!(result.to_version, "2.0.0");
+        assert!(importer
+            .update("greet", &registry, false)
+            .unwrap()
+            .diff
+            .is_none());
+    }
+
+    #[test]
+    fn test_update_refuses_local_changes_without_force() {
+        let (upstream, tools_dir, registry, importer) = imported();
+        let script = tools_dir.path().join("greet/greet.sh");
+        std::fs::write(&script, "echo '\"local\"'\n").unwrap();
+        assert_eq!(registry.modified_since_import("greet"), Some(true));
+        write_upstream(upstream.path(), "Says hello", "1.0.0", "echo '\"hello\"'\n");
+
+        let err = importer.update("greet", &registry, false).unwrap_err();
+        assert!(err.to_string().contains("modified since"), "{}", err);
+        assert_eq!(
+            std::fs::read_to_string(&script).unwrap(),
+            "echo '\"local\"'\n"
+        );
+
+        importer.update("greet", &registry, true).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&script).unwrap(),
+            "echo '\"hello\"'\n"
+        );
+        // The local edit is kept in the backup
+        assert_eq!(
+            std::fs::read_to_string(tools_dir.path().join("greet/versions/1.0.0/greet.sh"))
+                .unwrap(),
+            "echo '\"local\"'\n"
+        );
+    }
+
+    #[test]
+    fn test_update_needs_an_imported_tool() {
+        let tools_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(tools_dir.path().to_path_buf());
+        let mut manifest = ToolManifest::new(
+            "local".to_string(),
+            "Made here".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        registry.register_tool(manifest, b"echo '{}'\n").unwrap();
+        assert_eq!(registry.modified_since_import("local"), None);
+
+        let importer = Importer::new(tools_dir.path().to_path_buf());
+        let err = importer.update("local", &registry, false).unwrap_err();
+        assert!(err.to_string().contains("wasn't imported"), "{}", err);
+        assert!(importer.update("missing", &registry, false).is_err());
+    }
+}
+
+mod inspection {
+    use skillz::importer::{ImportFilter, Importer, PREVIEW_LINES};
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn write_tool(dir: &Path, interpreter: &str, dependencies: &[&str], script: &str) {
+        let mut manifest = ToolManifest::new(
+            "fetcher".to_string(),
+            "Fetches things".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some(interpreter.to_string());
+        manifest.dependencies = dependencies.iter().map(|d| d.to_string()).collect();
+        let entry_file = manifest.script_file_name();
+        std::fs::write(
+            dir.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(dir.join(entry_file), script).unwrap();
+    }
+
+    #[test]
+    fn test_inspect_reports_without_registering() {
+        let upstream = TempDir::new().unwrap();
+        let mut script = String::from(
+            "import requests\nimport subprocess\n\
+             open('/etc/hosts', 'w').write('x')\n\
+             subprocess.run(['ls'])\n",
+        );
+        for i in 0..150 {
+            script.push_str(&format!("print({})\n", i));
+        }
+        write_tool(
+            upstream.path(),
+            "python3",
+            &["requests>=2", "rich"],
+            &script,
+        );
+        let tools_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(tools_dir.path().to_path_buf());
+        let importer = Importer::new(tools_dir.path().to_path_buf());
+
+        let inspection = importer
+            .inspect(
+                upstream.path().to_str().unwrap(),
+                &registry,
+                &ImportFilter::default(),
+            )
+            .unwrap();
+        assert_eq!(inspection.tools.len(), 1);
+        let tool = &inspection.tools[0];
+        assert_eq!(tool.manifest.name, "fetcher");
+        assert!(!tool.exists);
+        assert_eq!(tool.code_file.as_deref(), Some("fetcher.py"));
+        assert_eq!(tool.code_lines, 154);
+        assert_eq!(tool.code_preview.lines().count(), PREVIEW_LINES);
+        assert!(tool.files.iter().any(|(name, _)| name == "manifest.json"));
+
+        let flagged = |category: &str, file: &str, line: usize| {
+            tool.red_flags
+                .iter()
+                .any(|f| f.category == category && f.file == file && f.line == line)
+        };
+        assert!(flagged("network", "fetcher.py", 1));
+        assert!(flagged("subprocess", "fetcher.py", 2));
+        assert!(flagged("filesystem write", "fetcher.py", 3));
+        assert!(flagged("outside roots", "fetcher.py", 3));
+        assert!(flagged("network", "manifest.json", 0));
+        assert!(!tool.red_flags.iter().any(|f| f.text.contains("rich")));
+        assert!(!tool.red_flags.iter().any(|f| f.text.starts_with("print(")));
+
+        // Nothing lands in TOOLS_DIR until confirmed
+        assert!(registry.get_tool("fetcher").is_none());
+        assert_eq!(std::fs::read_dir(tools_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_confirm_registers_what_was_inspected() {
+        let upstream = TempDir::new().unwrap();
+        write_tool(upstream.path(), "bash", &[], "echo '\"inspected\"'\n");
+        let tools_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(tools_dir.path().to_path_buf());
+        let importer = Importer::new(tools_dir.path().to_path_buf());
+        let source = upstream.path().to_str().unwrap();
+
+        let inspection = importer
+            .inspect(source, &registry, &ImportFilter::default())
+            .unwrap();
+        assert!(inspection.tools[0].red_flags.is_empty());
+        // A change after inspecting isn't what gets registered
+        write_tool(upstream.path(), "bash", &[], "echo '\"changed\"'\n");
+        assert!(registry.get_tool("fetcher").is_none());
+
+        let summary = importer.confirm(&inspection, &registry).unwrap();
+        assert_eq!(summary.imported.len(), 1);
+        let tool = registry.get_tool("fetcher").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(tools_dir.path().join("fetcher/fetcher.sh")).unwrap(),
+            "echo '\"inspected\"'\n"
+        );
+        assert_eq!(tool.manifest.imported_from.unwrap().source, source);
+        assert_eq!(registry.modified_since_import("fetcher"), Some(false));
+
+        // An existing tool is only replaced when the import asked for it
+        let inspection = importer
+            .inspect(source, &registry, &ImportFilter::default())
+            .unwrap();
+        assert!(inspection.tools[0].exists);
+        assert!(!inspection.overwrites("fetcher"));
+        let summary = importer.confirm(&inspection, &registry).unwrap();
+        assert!(summary.imported.is_empty());
+        assert_eq!(summary.skipped.len(), 1);
+    }
+}
+
+mod index_sources {
+    use skillz::importer::{ImportFilter, Importer};
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// An upstream tool directory and a tool index listing it with `checksum`
+    /// (its real content hash when None)
+    fn fixture(checksum: Option<&str>) -> (TempDir, TempDir, String) {
+        let upstream = TempDir::new().unwrap();
+        let mut manifest =
+            ToolManifest::new("greet".to_string(), "Says hi".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        std::fs::write(
+            upstream.path().join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(upstream.path().join("greet.sh"), "echo '\"hi\"'\n").unwrap();
+        let source = upstream.path().to_str().unwrap().to_string();
+
+        let checksum = checksum.map(str::to_string).unwrap_or_else(|| {
+            let scratch = TempDir::new().unwrap();
+            let registry = ToolRegistry::new(scratch.path().to_path_buf());
+            let inspection = Importer::new(scratch.path().to_path_buf())
+                .inspect(&source, &registry, &ImportFilter::default())
+                .unwrap();
+            format!("sha256:{}", inspection.tools[0].content_hash)
+        });
+        let index_dir = TempDir::new().unwrap();
+        let index_path = index_dir.path().join("index.json");
+        let index = serde_json::json!({
+            "format_version": 1,
+            "tools": [{
+                "name": "greet",
+                "description": "Says hi",
+                "source": source,
+                "checksum": checksum,
+                "tags": ["demo"]
+            }]
+        });
+        std::fs::write(&index_path, index.to_string()).unwrap();
+        (
+            upstream,
+            index_dir,
+            index_path.to_str().unwrap().to_string(),
+        )
+    }
+
+    fn tools(dir: &Path) -> (ToolRegistry, Importer) {
+        (
+            ToolRegistry::new(dir.to_path_buf()),
+            Importer::new(dir.to_path_buf()),
+        )
+    }
+
+    #[test]
+    fn test_import_from_index() {
+        let (upstream, _index_dir, index_path) = fixture(None);
+        let tools_dir = TempDir::new().unwrap();
+        let (registry, importer) = tools(tools_dir.path());
+        let importer = importer.with_index(&index_path);
+
+        let result = importer.import("index:greet", &registry, false).unwrap();
+        assert_eq!(result.tool_name, "greet");
+        let tool = registry.get_tool("greet").unwrap();
+        // Updates fetch from the entry's source, not the index
+        assert_eq!(
+            tool.manifest.imported_from.unwrap().source,
+            upstream.path().to_str().unwrap()
+        );
+
+        let err = importer
+            .import("index:greet", &registry, false)
+            .unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{}", err);
+        let err = importer
+            .import("index:missing", &registry, false)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("isn't in the tool index"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_import_from_index_verifies_checksum() {
+        let wrong = format!("sha256:{}", "0".repeat(64));
+        let (_upstream, _index_dir, index_path) = fixture(Some(&wrong));
+        let tools_dir = TempDir::new().unwrap();
+        let (registry, importer) = tools(tools_dir.path());
+
+        let err = importer
+            .with_index(&index_path)
+            .import_all("index:greet", &registry, &ImportFilter::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"), "{}", err);
+        assert!(registry.get_tool("greet").is_none());
+        assert_eq!(std::fs::read_dir(tools_dir.path()).unwrap().count(), 0);
+    }
+}
+
+/// Test importing git sources pinned to a branch, tag or commit
+mod git_refs {
+    use skillz::importer::Importer;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use std::path::Path;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    fn git(repo: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}", args);
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    fn commit_tool(repo: &Path, description: &str, version: &str) -> String {
+        let mut manifest = ToolManifest::new(
+            "greet".to_string(),
+            description.to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        manifest.version = version.to_string();
+        std::fs::write(
+            repo.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(repo.join("greet.sh"), "echo '\"hi\"'\n").unwrap();
+        git(repo, &["add", "."]);
+        git(repo, &["commit", "-q", "-m", version]);
+        git(repo, &["rev-parse", "HEAD"])
+    }
+
+    /// A repository at `<tmp>/fixture.git` with v1.0.0 tagged "v1" and
+    /// v2.0.0 on the "next" branch, and the two commits
+    fn fixture() -> (TempDir, String, String, String) {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("fixture.git");
+        std::fs::create_dir_all(&repo).unwrap();
+        git(&repo, &["init", "-q"]);
+        let first = commit_tool(&repo, "Says hi", "1.0.0");
+        git(&repo, &["tag", "v1"]);
+        git(&repo, &["checkout", "-q", "-b", "next"]);
+        let second = commit_tool(&repo, "Says hello", "2.0.0");
+        let url = repo.to_str().unwrap().to_string();
+        (dir, url, first, second)
+    }
+
+    fn import(source: &str) -> anyhow::Result<(TempDir, ToolManifest)> {
+        let tools_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(tools_dir.path().to_path_buf());
+        Importer::new(tools_dir.path().to_path_buf()).import(source, &registry, false)?;
+        let manifest = registry.get_tool("greet").unwrap().manifest;
+        Ok((tools_dir, manifest))
+    }
+
+    #[test]
+    fn test_import_pinned_refs() {
+        let (_dir, url, first, second) = fixture();
+
+        for (git_ref, version, commit) in [
+            ("next", "2.0.0", &second),
+            ("v1", "1.0.0", &first),
+            (first.as_str(), "1.0.0", &first),
+        ] {
+            let source = format!("{}#{}", url, git_ref);
+            let (_tools_dir, manifest) = import(&source).unwrap();
+            assert_eq!(manifest.version, version, "{}", source);
+            let origin = manifest.imported_from.unwrap();
+            assert_eq!(origin.source, source);
+            assert_eq!(origin.commit.as_ref(), Some(commit), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_invalid_refs_are_explained() {
+        let (_dir, url, first, _) = fixture();
+
+        let err = import(&format!("{}#nope", url)).unwrap_err().to_string();
+        assert!(err.contains("No branch or tag 'nope'"), "{}", err);
+
+        let err = import(&format!("{}#{}", url, &first[..8]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("full 40-character hash"), "{}", err);
+
+        let missing = "0123456789".repeat(4);
+        let err = import(&format!("{}#{}", url, missing))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains(&format!("Commit {} not found", missing)),
+            "{}",
+            err
+        );
+    }
+}
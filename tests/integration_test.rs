@@ -267,3 +267,890 @@ fn test_memory_tool() {
     let _ = child.kill();
     let _ = child.wait();
 }
+
+/// Call a tool and return the text of its result, skipping any
+/// notifications the server sends first
+fn call_tool_text(
+    stdin: &mut impl Write,
+    stdout: &mut BufReader<impl std::io::Read>,
+    name: &str,
+    arguments: serde_json::Value,
+    id: u64,
+) -> String {
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "tools/call",
+        "params": {"name": name, "arguments": arguments},
+        "id": id
+    });
+    writeln!(stdin, "{}", request).unwrap();
+    stdin.flush().unwrap();
+
+    loop {
+        let mut line = String::new();
+        assert!(stdout.read_line(&mut line).unwrap() > 0, "server exited");
+        let message: serde_json::Value = match serde_json::from_str(&line) {
+            Ok(m) => m,
+            Err(_) => continue,
+        };
+        if message["id"] == serde_json::json!(id) {
+            return message["result"]["content"][0]["text"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+        }
+    }
+}
+
+/// Start the server on an empty tools directory and complete the MCP handshake
+fn start_server(
+    tools_dir: &std::path::Path,
+) -> (
+    std::process::Child,
+    std::process::ChildStdin,
+    BufReader<std::process::ChildStdout>,
+) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_skillz"))
+        .env("TOOLS_DIR", tools_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to spawn server");
+
+    let mut stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let mut reader = BufReader::new(stdout);
+
+    let _ = send_request(
+        &mut stdin,
+        &mut reader,
+        "initialize",
+        serde_json::json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "test", "version": "1.0"}
+        }),
+        0,
+    );
+    writeln!(
+        stdin,
+        r#"{{"jsonrpc":"2.0","method":"notifications/initialized"}}"#
+    )
+    .unwrap();
+    stdin.flush().unwrap();
+    (child, stdin, reader)
+}
+
+/// Test pipelines calling pipelines, and that a cycle between them fails
+#[test]
+fn test_nested_pipelines() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let (mut child, mut stdin, mut reader) = start_server(tools_dir.path());
+
+    let mut call = |name: &str, arguments: serde_json::Value, id: u64| {
+        call_tool_text(&mut stdin, &mut reader, name, arguments, id)
+    };
+
+    let registered = call(
+        "register_script",
+        serde_json::json!({
+            "name": "double",
+            "description": "Doubles n",
+            "interpreter": "bash",
+            "code": "read line\nn=$(echo \"$line\" | sed -E 's/.*\"n\":([0-9]+).*/\\1/')\necho \"{\\\"jsonrpc\\\":\\\"2.0\\\",\\\"result\\\":{\\\"n\\\":$((n * 2))},\\\"id\\\":1}\"\n"
+        }),
+        1,
+    );
+    assert!(registered.contains("registered"), "{}", registered);
+
+    let pipeline = |name: &str, steps: serde_json::Value| serde_json::json!({"action": "create", "name": name, "steps": steps});
+    call(
+        "pipeline",
+        pipeline(
+            "double_it",
+            serde_json::json!([{"tool": "double", "args": {"n": "$input.n"}}]),
+        ),
+        2,
+    );
+    call(
+        "pipeline",
+        pipeline(
+            "quadruple",
+            serde_json::json!([
+                {"tool": "double_it", "args": {"n": "$input.n"}},
+                {"tool": "double", "args": {"n": "$prev.n"}}
+            ]),
+        ),
+        3,
+    );
+    let output = call(
+        "call_tool",
+        serde_json::json!({"tool_name": "quadruple", "arguments": {"n": 3}}),
+        4,
+    );
+    assert!(output.contains("Completed"), "{}", output);
+    assert!(output.contains("\"n\": 12"), "{}", output);
+
+    // a → b → a
+    call(
+        "pipeline",
+        pipeline("a", serde_json::json!([{"tool": "double_it", "args": {}}])),
+        5,
+    );
+    call(
+        "pipeline",
+        pipeline("b", serde_json::json!([{"tool": "a", "args": {}}])),
+        6,
+    );
+    let updated = call(
+        "pipeline",
+        serde_json::json!({"action": "update", "name": "a", "steps": [{"tool": "b", "args": {}}]}),
+        7,
+    );
+    assert!(updated.contains("Updated"), "{}", updated);
+    let output = call(
+        "call_tool",
+        serde_json::json!({"tool_name": "a", "arguments": {}}),
+        8,
+    );
+    assert!(output.contains("Failed"), "{}", output);
+    assert!(
+        output.contains("Pipeline cycle detected: a → b → a"),
+        "{}",
+        output
+    );
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Test transform steps reshaping data between steps, reading $prev by default
+#[test]
+fn test_transform_steps() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let (mut child, mut stdin, mut reader) = start_server(tools_dir.path());
+    let mut call = |name: &str, arguments: serde_json::Value, id: u64| {
+        call_tool_text(&mut stdin, &mut reader, name, arguments, id)
+    };
+
+    let created = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "create",
+            "name": "cheap_items",
+            "steps": [
+                {"name": "cheap", "tool": "transform", "args": {
+                    "input": "$input",
+                    "expr": ".items | map(select(.price < 10))"
+                }},
+                {"tool": "transform", "args": {"expr": "{names: map(.name), count: length}"}}
+            ]
+        }),
+        1,
+    );
+    assert!(created.contains("cheap_items"), "{}", created);
+
+    let output = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "run",
+            "name": "cheap_items",
+            "format": "json",
+            "input": {"items": [
+                {"name": "pen", "price": 2},
+                {"name": "lamp", "price": 30},
+                {"name": "cup", "price": 5}
+            ]}
+        }),
+        2,
+    );
+    let run: serde_json::Value = serde_json::from_str(&output).expect(&output);
+    assert_eq!(run["success"], serde_json::json!(true), "{}", output);
+    assert_eq!(
+        run["steps"][1]["output"],
+        serde_json::json!({"names": ["pen", "cup"], "count": 2})
+    );
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Test that pipeline steps can call built-in tools like memory
+#[test]
+fn test_pipeline_builtin_steps() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let (mut child, mut stdin, mut reader) = start_server(tools_dir.path());
+    let mut call = |name: &str, arguments: serde_json::Value, id: u64| {
+        call_tool_text(&mut stdin, &mut reader, name, arguments, id)
+    };
+
+    let created = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "create",
+            "name": "save_summary",
+            "steps": [
+                {"tool": "transform", "args": {"input": "$input", "expr": "{total: (.items | length)}"}},
+                {"tool": "memory", "args": {
+                    "action": "store",
+                    "tool_name": "reports",
+                    "key": "$input.key",
+                    "value": "$prev"
+                }}
+            ]
+        }),
+        1,
+    );
+    assert!(created.contains("save_summary"), "{}", created);
+
+    let output = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "run",
+            "name": "save_summary",
+            "format": "json",
+            "input": {"key": "latest", "items": [1, 2, 3]}
+        }),
+        2,
+    );
+    let run: serde_json::Value = serde_json::from_str(&output).expect(&output);
+    assert_eq!(run["success"], serde_json::json!(true), "{}", output);
+
+    let stored = call(
+        "memory",
+        serde_json::json!({"action": "get", "tool_name": "reports", "key": "latest"}),
+        3,
+    );
+    let stored: serde_json::Value = serde_json::from_str(&stored).expect(&stored);
+    assert_eq!(stored, serde_json::json!({"total": 3}));
+
+    let rejected = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "create",
+            "name": "indirect",
+            "steps": [{"tool": "call_tool", "args": {"tool_name": "save_summary"}}]
+        }),
+        4,
+    );
+    assert!(
+        rejected.contains("unknown tool 'call_tool'"),
+        "{}",
+        rejected
+    );
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Test that a failed built-in step (a build that doesn't compile, a
+/// missing tool to delete) stops the pipeline
+#[test]
+fn test_pipeline_stops_after_failed_builtin_step() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let (mut child, mut stdin, mut reader) = start_server(tools_dir.path());
+    let mut call = |name: &str, arguments: serde_json::Value, id: u64| {
+        call_tool_text(&mut stdin, &mut reader, name, arguments, id)
+    };
+
+    let created = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "create",
+            "name": "build_and_record",
+            "steps": [
+                {"tool": "build_tool", "args": {
+                    "name": "broken_tool",
+                    "description": "Doesn't compile",
+                    "code": "fn main() { let x: u32 = \"nope\"; }"
+                }},
+                {"tool": "memory", "args": {
+                    "action": "store",
+                    "tool_name": "builds",
+                    "key": "latest",
+                    "value": "built"
+                }}
+            ]
+        }),
+        1,
+    );
+    assert!(created.contains("build_and_record"), "{}", created);
+
+    let output = call(
+        "pipeline",
+        serde_json::json!({"action": "run", "name": "build_and_record", "format": "json"}),
+        2,
+    );
+    let run: serde_json::Value = serde_json::from_str(&output).expect(&output);
+    assert_eq!(run["success"], serde_json::json!(false), "{}", output);
+    assert_eq!(run["steps"].as_array().unwrap().len(), 1, "{}", output);
+    assert_eq!(run["steps"][0]["success"], serde_json::json!(false));
+
+    let stored = call(
+        "memory",
+        serde_json::json!({"action": "get", "tool_name": "builds", "key": "latest"}),
+        3,
+    );
+    assert_eq!(stored, "null");
+
+    // A reply that doesn't look like an error still fails the step
+    call(
+        "pipeline",
+        serde_json::json!({
+            "action": "create",
+            "name": "delete_and_record",
+            "steps": [
+                {"tool": "delete_tool", "args": {"tool_name": "missing_tool"}},
+                {"tool": "memory", "args": {
+                    "action": "store",
+                    "tool_name": "builds",
+                    "key": "deleted",
+                    "value": true
+                }}
+            ]
+        }),
+        4,
+    );
+    let output = call(
+        "pipeline",
+        serde_json::json!({"action": "run", "name": "delete_and_record", "format": "json"}),
+        5,
+    );
+    let run: serde_json::Value = serde_json::from_str(&output).expect(&output);
+    assert_eq!(run["success"], serde_json::json!(false), "{}", output);
+    assert_eq!(
+        run["steps"][0]["error"],
+        serde_json::json!("Tool 'missing_tool' not found")
+    );
+    let stored = call(
+        "memory",
+        serde_json::json!({"action": "get", "tool_name": "builds", "key": "deleted"}),
+        6,
+    );
+    assert_eq!(stored, "null");
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Test that on_error steps run only for the failure that ended a pipeline,
+/// not for failures it continued past
+#[test]
+fn test_on_error_only_after_fatal_failure() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let (mut child, mut stdin, mut reader) = start_server(tools_dir.path());
+    let mut call = |name: &str, arguments: serde_json::Value, id: u64| {
+        call_tool_text(&mut stdin, &mut reader, name, arguments, id)
+    };
+
+    let handler = serde_json::json!([{"tool": "memory", "args": {
+        "action": "store",
+        "tool_name": "handlers",
+        "key": "failed_step",
+        "value": "$error.step"
+    }}]);
+    // A memory get without a key fails
+    let failing = |continue_on_error: bool| {
+        serde_json::json!({
+            "name": "lookup",
+            "tool": "memory",
+            "args": {"action": "get", "tool_name": "handlers"},
+            "continue_on_error": continue_on_error
+        })
+    };
+    let last = serde_json::json!({"tool": "transform", "args": {"input": "$input", "expr": "."}});
+    let recorded =
+        serde_json::json!({"action": "get", "tool_name": "handlers", "key": "failed_step"});
+
+    call(
+        "pipeline",
+        serde_json::json!({
+            "action": "create",
+            "name": "tolerant",
+            "steps": [last.clone(), failing(true)],
+            "on_error": handler
+        }),
+        1,
+    );
+    let output = call(
+        "pipeline",
+        serde_json::json!({"action": "run", "name": "tolerant", "format": "json"}),
+        2,
+    );
+    let run: serde_json::Value = serde_json::from_str(&output).expect(&output);
+    assert_eq!(run["success"], serde_json::json!(true), "{}", output);
+    assert_eq!(run["steps"][1]["success"], serde_json::json!(false));
+    assert!(run.get("on_error").is_none(), "{}", output);
+    assert_eq!(call("memory", recorded.clone(), 3), "null");
+
+    call(
+        "pipeline",
+        serde_json::json!({
+            "action": "create",
+            "name": "fragile",
+            "steps": [last, failing(false)],
+            "on_error": handler
+        }),
+        4,
+    );
+    // Without stop_on_error every failure is continued past
+    let output = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "run",
+            "name": "fragile",
+            "format": "json",
+            "stop_on_error": false
+        }),
+        5,
+    );
+    let run: serde_json::Value = serde_json::from_str(&output).expect(&output);
+    assert_eq!(run["success"], serde_json::json!(true), "{}", output);
+    assert!(run.get("on_error").is_none(), "{}", output);
+    assert_eq!(call("memory", recorded.clone(), 6), "null");
+
+    let output = call(
+        "pipeline",
+        serde_json::json!({"action": "run", "name": "fragile", "format": "json"}),
+        7,
+    );
+    let run: serde_json::Value = serde_json::from_str(&output).expect(&output);
+    assert_eq!(run["success"], serde_json::json!(false), "{}", output);
+    assert_eq!(
+        run["on_error"][0]["success"],
+        serde_json::json!(true),
+        "{}",
+        output
+    );
+    assert_eq!(call("memory", recorded.clone(), 8), "\"lookup\"");
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Test that a scheduled pipeline runs on its own and records its runs
+#[test]
+fn test_scheduled_pipeline_runs() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let (mut child, mut stdin, mut reader) = start_server(tools_dir.path());
+    let mut call = |name: &str, arguments: serde_json::Value, id: u64| {
+        call_tool_text(&mut stdin, &mut reader, name, arguments, id)
+    };
+
+    let created = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "create",
+            "name": "tick",
+            "schedule": "every 1s",
+            "steps": [{"tool": "transform", "args": {"input": "$input", "expr": "{ok: true}"}}]
+        }),
+        1,
+    );
+    assert!(created.contains("tick"), "{}", created);
+
+    std::thread::sleep(std::time::Duration::from_millis(3500));
+
+    let runs = call(
+        "pipeline",
+        serde_json::json!({"action": "runs", "name": "tick"}),
+        2,
+    );
+    assert!(runs.contains("**Schedule:** every 1s"), "{}", runs);
+    assert!(runs.matches("✅").count() >= 2, "{}", runs);
+
+    let history = call("history", serde_json::json!({"tool_name": "tick"}), 3);
+    assert!(!history.contains("(0 entries)"), "{}", history);
+
+    let unscheduled = call(
+        "pipeline",
+        serde_json::json!({"action": "unschedule", "name": "tick"}),
+        4,
+    );
+    assert!(unscheduled.contains("unscheduled"), "{}", unscheduled);
+
+    let invalid = call(
+        "pipeline",
+        serde_json::json!({"action": "schedule", "name": "tick", "schedule": "every 0s"}),
+        5,
+    );
+    assert!(
+        invalid.starts_with("❌ Invalid schedule 'every 0s'"),
+        "{}",
+        invalid
+    );
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Test that a step with a cache block is skipped when it runs again
+#[test]
+fn test_cached_pipeline_steps() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let (mut child, mut stdin, mut reader) = start_server(tools_dir.path());
+    let mut call = |name: &str, arguments: serde_json::Value, id: u64| {
+        call_tool_text(&mut stdin, &mut reader, name, arguments, id)
+    };
+
+    let created = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "create",
+            "name": "count_once",
+            "steps": [
+                {"name": "count", "tool": "memory", "cache": {"ttl_seconds": 60}, "args": {
+                    "action": "increment", "tool_name": "counter", "key": "calls"
+                }},
+                {"tool": "transform", "args": {"expr": "{done: true}"}}
+            ]
+        }),
+        1,
+    );
+    assert!(created.contains("count_once"), "{}", created);
+
+    let run = serde_json::json!({"action": "run", "name": "count_once", "format": "json"});
+    let parse = |output: String| -> serde_json::Value {
+        let run: serde_json::Value = serde_json::from_str(&output).expect(&output);
+        assert_eq!(run["success"], serde_json::json!(true), "{}", output);
+        run
+    };
+    let first = parse(call("pipeline", run.clone(), 2));
+    assert_eq!(first["steps"][0].get("cached"), None);
+    let second = parse(call("pipeline", run, 3));
+    assert_eq!(second["steps"][0]["cached"], serde_json::json!(true));
+    assert_eq!(second["steps"][0]["output"], first["steps"][0]["output"]);
+
+    let calls = call(
+        "memory",
+        serde_json::json!({"action": "get", "tool_name": "counter", "key": "calls"}),
+        10,
+    );
+    assert_eq!(calls.trim(), "1");
+
+    let cleared = call(
+        "pipeline",
+        serde_json::json!({"action": "clear_cache", "name": "count_once"}),
+        11,
+    );
+    assert!(
+        cleared.contains("Cleared 1 cached step output"),
+        "{}",
+        cleared
+    );
+    call(
+        "pipeline",
+        serde_json::json!({"action": "run", "name": "count_once"}),
+        12,
+    );
+    let calls = call(
+        "memory",
+        serde_json::json!({"action": "get", "tool_name": "counter", "key": "calls"}),
+        13,
+    );
+    assert_eq!(calls.trim(), "2");
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Test that unsafe or reserved tool names are rejected before anything is written
+#[test]
+fn test_invalid_tool_names_rejected() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let (mut child, mut stdin, mut reader) = start_server(tools_dir.path());
+    let mut call = |name: &str, arguments: serde_json::Value, id: u64| {
+        call_tool_text(&mut stdin, &mut reader, name, arguments, id)
+    };
+
+    let traversal = call(
+        "register_script",
+        serde_json::json!({
+            "name": "../escape",
+            "description": "Should not be written",
+            "interpreter": "bash",
+            "code": "echo '{}'"
+        }),
+        1,
+    );
+    assert!(
+        traversal.starts_with("❌ Invalid tool name '../escape'"),
+        "{}",
+        traversal
+    );
+    assert!(!tools_dir.path().parent().unwrap().join("escape").exists());
+
+    let reserved = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "create",
+            "name": "call_tool",
+            "steps": [{"tool": "transform", "args": {"input": "$input", "expr": "."}}]
+        }),
+        2,
+    );
+    assert!(
+        reserved.contains("reserved for a built-in tool"),
+        "{}",
+        reserved
+    );
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Test that a debug run leaves every step's args and output in memory
+#[test]
+fn test_debug_pipeline_checkpoints() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let (mut child, mut stdin, mut reader) = start_server(tools_dir.path());
+    let mut call = |name: &str, arguments: serde_json::Value, id: u64| {
+        call_tool_text(&mut stdin, &mut reader, name, arguments, id)
+    };
+
+    let created = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "create",
+            "name": "breaks_late",
+            "steps": [
+                {"name": "cheap", "tool": "transform", "args": {
+                    "input": "$input",
+                    "expr": ".items | map(select(.price < 10))"
+                }},
+                {"name": "boom", "tool": "memory", "args": {"action": "explode"}}
+            ]
+        }),
+        1,
+    );
+    assert!(created.contains("breaks_late"), "{}", created);
+
+    let output = call(
+        "pipeline",
+        serde_json::json!({
+            "action": "run",
+            "name": "breaks_late",
+            "format": "json",
+            "debug": true,
+            "input": {"items": [{"price": 2}, {"price": 30}]}
+        }),
+        2,
+    );
+    let run: serde_json::Value = serde_json::from_str(&output).expect(&output);
+    assert_eq!(run["success"], serde_json::json!(false), "{}", output);
+    let run_id = run["debug_run"].as_str().expect("debug run id").to_string();
+    assert!(run_id.starts_with("breaks_late-"), "{}", run_id);
+
+    let get =
+        |key: String, id: u64, call: &mut dyn FnMut(&str, serde_json::Value, u64) -> String| {
+            let text = call(
+                "memory",
+                serde_json::json!({"action": "get", "tool_name": "__pipeline_debug__", "key": key}),
+                id,
+            );
+            serde_json::from_str::<serde_json::Value>(&text).expect(&text)
+        };
+    let first = get(format!("{}/step-001", run_id), 3, &mut call);
+    assert_eq!(
+        first["args"]["input"],
+        serde_json::json!({"items": [{"price": 2}, {"price": 30}]})
+    );
+    assert_eq!(first["output"], serde_json::json!([{"price": 2}]));
+    let second = get(format!("{}/step-002", run_id), 4, &mut call);
+    assert_eq!(second["step_name"], serde_json::json!("boom"));
+    assert_eq!(second["success"], serde_json::json!(false));
+    assert_eq!(second["args"], serde_json::json!({"action": "explode"}));
+
+    // Without debug nothing new is written
+    call(
+        "pipeline",
+        serde_json::json!({"action": "run", "name": "breaks_late", "input": {"items": []}}),
+        5,
+    );
+    let listed = call(
+        "memory",
+        serde_json::json!({"action": "list", "tool_name": "__pipeline_debug__"}),
+        6,
+    );
+    assert_eq!(listed.matches("step-00").count(), 2, "{}", listed);
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Test the list and info subcommands, which print and exit without serving
+#[test]
+fn test_list_and_info_subcommands() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let tool_dir = tools_dir.path().join("echo");
+    std::fs::create_dir_all(&tool_dir).unwrap();
+    std::fs::write(
+        tool_dir.join("manifest.json"),
+        r#"{"name": "echo", "description": "Echoes its input", "tool_type": "script", "interpreter": "bash", "tags": ["text"]}"#,
+    )
+    .unwrap();
+    std::fs::write(tool_dir.join("echo.sh"), "echo '{}'\n").unwrap();
+
+    let skillz = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_skillz"))
+            .env("TOOLS_DIR", tools_dir.path())
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .expect("Failed to run skillz");
+        (
+            output.status.success(),
+            String::from_utf8_lossy(&output.stdout).to_string(),
+        )
+    };
+
+    let (ok, listed) = skillz(&["list"]);
+    assert!(ok);
+    assert!(
+        listed.contains("**echo** [bash] - Echoes its input"),
+        "{}",
+        listed
+    );
+    let (ok, listed) = skillz(&["list", "--tag", "other"]);
+    assert!(ok);
+    assert!(listed.contains("No tools tagged 'other'"), "{}", listed);
+
+    let (ok, info) = skillz(&["info", "echo"]);
+    assert!(ok);
+    assert!(info.contains("Script Tool: echo"), "{}", info);
+    assert!(!tools_dir.path().join(".memory.db").exists());
+    assert!(!skillz(&["info", "missing"]).0);
+
+    // A broken manifest fails the listing, for CI checks
+    std::fs::create_dir_all(tools_dir.path().join("broken")).unwrap();
+    std::fs::write(tools_dir.path().join("broken/manifest.json"), "{").unwrap();
+    let (ok, listed) = skillz(&["list"]);
+    assert!(!ok);
+    assert!(listed.contains("`broken`"), "{}", listed);
+}
+
+/// Test running one tool from the command line, with arguments from the
+/// flag, a file or stdin
+#[test]
+fn test_run_subcommand() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let tool_dir = tools_dir.path().join("shout");
+    std::fs::create_dir_all(&tool_dir).unwrap();
+    std::fs::write(
+        tool_dir.join("manifest.json"),
+        r#"{"name": "shout", "description": "Echoes its arguments", "tool_type": "script", "interpreter": "bash"}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        tool_dir.join("shout.sh"),
+        r#"read line
+args=$(printf '%s' "$line" | sed 's/.*"arguments":\({[^}]*}\).*/\1/')
+echo "{\"jsonrpc\":\"2.0\",\"result\":{\"got\":$args},\"id\":1}"
+"#,
+    )
+    .unwrap();
+
+    let run = |args: &[&str], stdin: &str| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_skillz"))
+            .env("TOOLS_DIR", tools_dir.path())
+            .current_dir(tools_dir.path())
+            .arg("run")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to run skillz");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        (output.status.success(), stdout)
+    };
+    let result = |stdout: &str| serde_json::from_str::<serde_json::Value>(stdout).expect(stdout);
+
+    let (ok, stdout) = run(&["shout", "--args", r#"{"text":"hi"}"#], "");
+    assert!(ok, "{}", stdout);
+    assert_eq!(result(&stdout), serde_json::json!({"got": {"text": "hi"}}));
+
+    let file = tools_dir.path().join("args.json");
+    std::fs::write(&file, r#"{"text":"from file"}"#).unwrap();
+    let (ok, stdout) = run(&["shout", "--args-file", file.to_str().unwrap()], "");
+    assert!(ok, "{}", stdout);
+    assert_eq!(result(&stdout)["got"]["text"], "from file");
+
+    let (ok, stdout) = run(&["shout", "--args-file", "-"], r#"{"text":"piped"}"#);
+    assert!(ok, "{}", stdout);
+    assert_eq!(result(&stdout)["got"]["text"], "piped");
+
+    assert!(!run(&["shout", "--args", "[1, 2]"], "").0);
+    assert!(!run(&["missing"], "").0);
+}
+
+/// Test that a client sending logging/setLevel before its initialized
+/// notification keeps its session, and gets the setLevel answered after it
+#[test]
+fn test_early_set_level() {
+    let tools_dir = tempfile::TempDir::new().unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_skillz"))
+        .env("TOOLS_DIR", tools_dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to spawn server");
+    let mut stdin = child.stdin.take().unwrap();
+    let mut reader = BufReader::new(child.stdout.take().unwrap());
+
+    let response = send_request(
+        &mut stdin,
+        &mut reader,
+        "initialize",
+        serde_json::json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "early", "version": "1.0"}
+        }),
+        0,
+    );
+    assert!(
+        response["result"]["capabilities"]["logging"].is_object(),
+        "{}",
+        response
+    );
+
+    writeln!(
+        stdin,
+        r#"{{"jsonrpc":"2.0","id":1,"method":"logging/setLevel","params":{{"level":"error"}}}}"#
+    )
+    .unwrap();
+    writeln!(
+        stdin,
+        r#"{{"jsonrpc":"2.0","method":"notifications/initialized"}}"#
+    )
+    .unwrap();
+    stdin.flush().unwrap();
+
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(response["id"], 1, "{}", response);
+    assert!(response["result"].is_object(), "{}", response);
+
+    let response = send_request(
+        &mut stdin,
+        &mut reader,
+        "tools/list",
+        serde_json::json!({}),
+        2,
+    );
+    assert!(response["result"]["tools"].is_array(), "{}", response);
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
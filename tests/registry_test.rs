@@ -256,6 +256,160 @@ mod versioning {
         assert_eq!(ToolRegistry::increment_version("0.1.0"), "0.1.1");
     }
 
+    /// Test major/minor/patch bump strategies
+    #[test]
+    fn test_version_bump_strategies() {
+        use skillz::registry::VersionBump;
+
+        assert_eq!(
+            ToolRegistry::bump_version("1.2.3", VersionBump::Minor),
+            "1.3.0"
+        );
+        assert_eq!(
+            ToolRegistry::bump_version("1.2.3", VersionBump::Major),
+            "2.0.0"
+        );
+        assert_eq!(
+            ToolRegistry::bump_version("1.2.3", VersionBump::Patch),
+            "1.2.4"
+        );
+        assert_eq!(VersionBump::parse("MINOR"), Some(VersionBump::Minor));
+        assert_eq!(VersionBump::parse("huge"), None);
+    }
+
+    /// Test that a minor bump is applied when updating a tool
+    #[test]
+    fn test_register_with_minor_bump() {
+        use skillz::registry::VersionBump;
+
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let mut manifest =
+            ToolManifest::new("bumped".to_string(), "A tool".to_string(), ToolType::Script);
+        manifest.version = "1.2.3".to_string();
+        registry.register_tool(manifest, b"print(1)").unwrap();
+
+        let manifest =
+            ToolManifest::new("bumped".to_string(), "A tool".to_string(), ToolType::Script);
+        let config = registry
+            .register_tool_with_bump(manifest, b"print(2)", VersionBump::Minor)
+            .unwrap();
+        assert_eq!(config.manifest.version, "1.3.0");
+
+        // The previous version was backed up
+        let versions = registry.list_versions("bumped").unwrap();
+        assert!(versions.iter().any(|v| v == "1.2.3"));
+    }
+
+    /// Test diffing the source and manifest of two versions
+    #[test]
+    fn test_diff_versions() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let mut manifest =
+            ToolManifest::new("differ".to_string(), "First".to_string(), ToolType::Script);
+        manifest.interpreter = Some("python3".to_string());
+        registry
+            .register_tool(manifest, b"import sys\nprint(1)\n")
+            .unwrap();
+
+        let mut manifest =
+            ToolManifest::new("differ".to_string(), "Second".to_string(), ToolType::Script);
+        manifest.interpreter = Some("python3".to_string());
+        registry
+            .register_tool(manifest, b"import sys\nprint(2)\n")
+            .unwrap();
+
+        let diff = registry
+            .diff_versions("differ", "1.0.0", "current")
+            .unwrap();
+        assert_eq!(diff.from, "1.0.0");
+        assert_eq!(diff.to, "1.0.1");
+
+        let source = diff.source_diff.unwrap();
+        assert!(source.contains("-print(1)"));
+        assert!(source.contains("+print(2)"));
+        assert!(source.contains(" import sys"));
+
+        let fields: Vec<&str> = diff
+            .manifest_changes
+            .iter()
+            .map(|c| c.field.as_str())
+            .collect();
+        assert_eq!(fields, vec!["description"]);
+
+        assert!(registry
+            .diff_versions("differ", "9.9.9", "current")
+            .is_err());
+    }
+
+    /// A one-line change produces a single hunk with that line swapped
+    #[test]
+    fn test_diff_one_line_change_hunk() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let code = |greeting: &str| {
+            format!(
+                "import json\nimport sys\n\nname = 'world'\nprint('{}, ' + name)\nsys.stdout.flush()\n",
+                greeting
+            )
+        };
+        for (greeting, schema) in [("Hello", "string"), ("Howdy", "integer")] {
+            let mut manifest =
+                ToolManifest::new("hunk".to_string(), "Greets".to_string(), ToolType::Script);
+            manifest.interpreter = Some("python3".to_string());
+            manifest.input_schema = skillz::registry::ToolSchema::from_value(serde_json::json!({
+                "type": "object",
+                "properties": {"name": {"type": schema}}
+            }));
+            registry
+                .register_tool(manifest, code(greeting).as_bytes())
+                .unwrap();
+        }
+
+        let diff = registry.diff_versions("hunk", "1.0.0", "1.0.1").unwrap();
+        let source = diff.source_diff.unwrap();
+        assert!(source.contains("--- hunk@1.0.0"));
+        assert!(source.contains("+++ hunk@1.0.1"));
+        assert!(source.contains(
+            "@@ -2,5 +2,5 @@\n import sys\n \n name = 'world'\n-print('Hello, ' + name)\n+print('Howdy, ' + name)\n sys.stdout.flush()\n"
+        ));
+
+        // Schema changes are reported by path
+        assert_eq!(diff.manifest_changes.len(), 1);
+        let change = &diff.manifest_changes[0];
+        assert_eq!(change.field, "input_schema.properties.name.type");
+        assert_eq!(change.from, Some(serde_json::json!("string")));
+        assert_eq!(change.to, Some(serde_json::json!("integer")));
+    }
+
+    /// WASM versions without stored source are compared by size and hash
+    #[test]
+    fn test_diff_wasm_without_source() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        for module in [&b"\0asm-one"[..], &b"\0asm-second"[..]] {
+            let manifest =
+                ToolManifest::new("opaque".to_string(), "Opaque".to_string(), ToolType::Wasm);
+            registry.register_wasm_tool(manifest, module, "").unwrap();
+        }
+        // No source was registered, as for tools imported as bare modules
+        assert!(!temp_dir.path().join("opaque/src.rs").exists());
+
+        let diff = registry
+            .diff_versions("opaque", "1.0.0", "current")
+            .unwrap();
+        assert!(diff.source_diff.is_none());
+        let (old, new) = diff.module_change.unwrap();
+        assert_eq!((old.size, new.size), (8, 11));
+        assert_ne!(old.sha256, new.sha256);
+        assert_eq!(old.sha256.len(), 64);
+    }
+
     /// Test version backup and listing
     #[test]
     fn test_version_backup_and_list() {
@@ -351,6 +505,135 @@ mod versioning {
         assert_eq!(tool.manifest.description, "Original description");
     }
 
+    /// Overwrite a script three times, roll back to the first version and
+    /// check that executing the tool gives the original result again
+    #[test]
+    fn test_rollback_restores_execution() {
+        use skillz::runtime::ToolRuntime;
+
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let runtime = ToolRuntime::new().expect("Failed to create runtime");
+
+        let register = |n: u32| {
+            let mut manifest = ToolManifest::new(
+                "overwritten".to_string(),
+                format!("Revision {}", n),
+                ToolType::Script,
+            );
+            manifest.interpreter = Some("bash".to_string());
+            let script = format!(
+                "read line\necho '{{\"jsonrpc\":\"2.0\",\"result\":{{\"rev\":{}}},\"id\":1}}'\n",
+                n
+            );
+            registry
+                .register_tool(manifest, script.as_bytes())
+                .expect("Failed to register tool")
+        };
+        let run = || {
+            let tool = registry.get_tool("overwritten").unwrap();
+            runtime
+                .call_tool(&tool, serde_json::json!({}))
+                .expect("Failed to run tool")
+        };
+
+        for n in 1..=4 {
+            register(n);
+        }
+        assert_eq!(
+            registry.get_tool("overwritten").unwrap().manifest.version,
+            "1.0.3"
+        );
+        assert_eq!(run()["rev"], 4);
+
+        for version in ["1.0.0", "1.0.1", "1.0.2"] {
+            assert!(temp_dir
+                .path()
+                .join("overwritten/versions")
+                .join(version)
+                .join("overwritten.sh")
+                .exists());
+        }
+
+        registry.rollback("overwritten", "1.0.0").unwrap();
+        let tool = registry.get_tool("overwritten").unwrap();
+        assert_eq!(tool.manifest.version, "1.0.0");
+        assert_eq!(tool.manifest.description, "Revision 1");
+        assert_eq!(run()["rev"], 1);
+    }
+
+    /// Test that old backups are pruned beyond the retention limit
+    #[test]
+    fn test_version_retention_and_prune() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf()).with_max_versions(3);
+
+        for n in 0..6 {
+            let manifest =
+                ToolManifest::new("pruned".to_string(), format!("Rev {}", n), ToolType::Script);
+            registry
+                .register_tool(manifest, format!("echo {}", n).as_bytes())
+                .unwrap();
+        }
+
+        // Current is 1.0.5; only the three newest backups survive
+        let versions = registry.list_versions("pruned").unwrap();
+        assert_eq!(versions, vec!["1.0.5 (current)", "1.0.4", "1.0.3", "1.0.2"]);
+
+        // After rolling back, the active version's backup is never pruned
+        registry.rollback("pruned", "1.0.2").unwrap();
+        let removed = registry.prune_versions("pruned", 1).unwrap();
+        assert_eq!(removed, vec!["1.0.4", "1.0.3"]);
+        let versions = registry.list_versions("pruned").unwrap();
+        assert_eq!(versions, vec!["1.0.5", "1.0.2 (current)"]);
+        assert!(temp_dir.path().join("pruned/versions/1.0.2").exists());
+
+        assert!(registry.prune_versions("pruned", 5).unwrap().is_empty());
+    }
+
+    /// Test validation of explicitly requested versions
+    #[test]
+    fn test_validate_explicit_version() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        // Any well-formed version is fine for a new tool
+        assert!(registry.validate_explicit_version("fresh", "0.1.0").is_ok());
+        assert!(registry.validate_explicit_version("fresh", "v2").is_err());
+
+        let manifest = ToolManifest::new(
+            "explicit".to_string(),
+            "A tool".to_string(),
+            ToolType::Script,
+        );
+        registry.register_tool(manifest, b"echo hi").unwrap();
+
+        assert!(registry
+            .validate_explicit_version("explicit", "1.0.0")
+            .is_err());
+        assert!(registry
+            .validate_explicit_version("explicit", "0.9.0")
+            .is_err());
+        assert!(registry
+            .validate_explicit_version("explicit", "2.0.0")
+            .is_ok());
+
+        // An explicit newer version is kept rather than auto-incremented
+        let mut manifest = ToolManifest::new(
+            "explicit".to_string(),
+            "A tool".to_string(),
+            ToolType::Script,
+        );
+        manifest.version = "2.0.0".to_string();
+        let config = registry.register_tool(manifest, b"echo hello").unwrap();
+        assert_eq!(config.manifest.version, "2.0.0");
+        assert!(registry
+            .list_versions("explicit")
+            .unwrap()
+            .iter()
+            .any(|v| v == "1.0.0"));
+    }
+
     /// Test versions directory structure
     #[test]
     fn test_versions_directory_structure() {
@@ -383,3 +666,575 @@ mod versioning {
         assert!(v1_dir.join("manifest.json").exists());
     }
 }
+
+// ==================== Rename / Copy Tests ====================
+
+mod rename_copy {
+    use super::*;
+    use skillz::registry::{PipelineStep, ToolManifest, ToolRegistry, ToolType};
+
+    fn register_script(registry: &ToolRegistry, name: &str, code: &[u8]) {
+        let mut manifest = ToolManifest::new(
+            name.to_string(),
+            "A script tool".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("python3".to_string());
+        registry
+            .register_tool(manifest, code)
+            .expect("Failed to register tool");
+    }
+
+    /// Test that renaming moves the directory and keeps version history
+    #[test]
+    fn test_rename_moves_directory_and_versions() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        register_script(&registry, "bad_name", b"print('v1')");
+        register_script(&registry, "bad_name", b"print('v2')");
+
+        let config = registry
+            .rename_tool("bad_name", "good_name")
+            .expect("Failed to rename");
+
+        assert!(!temp_dir.path().join("bad_name").exists());
+        assert!(temp_dir.path().join("good_name/good_name.py").exists());
+        assert_eq!(config.manifest.name, "good_name");
+        assert_eq!(config.manifest.entry_file.as_deref(), Some("good_name.py"));
+        assert!(registry.get_tool("bad_name").is_none());
+
+        // Old version is still there and can be restored under the new name
+        registry
+            .rollback("good_name", "1.0.0")
+            .expect("Failed to rollback renamed tool");
+        let tool = registry.get_tool("good_name").unwrap();
+        assert_eq!(tool.manifest.name, "good_name");
+        assert_eq!(
+            std::fs::read_to_string(&tool.script_path).unwrap(),
+            "print('v1')"
+        );
+    }
+
+    /// Test that renaming onto an existing tool fails
+    #[test]
+    fn test_rename_fails_if_target_exists() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        register_script(&registry, "first", b"print(1)");
+        register_script(&registry, "second", b"print(2)");
+
+        assert!(registry.rename_tool("first", "second").is_err());
+        assert!(registry.get_tool("first").is_some());
+        assert!(temp_dir.path().join("first").exists());
+    }
+
+    /// Test that copying creates an independent tool with fresh history
+    #[test]
+    fn test_copy_tool() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        register_script(&registry, "original", b"print('a')");
+        register_script(&registry, "original", b"print('b')");
+
+        let copy = registry
+            .copy_tool("original", "duplicate")
+            .expect("Failed to copy");
+
+        assert_eq!(copy.manifest.version, "1.0.0");
+        assert!(registry.get_tool("original").is_some());
+        assert!(!temp_dir.path().join("duplicate/versions").exists());
+        assert_eq!(
+            std::fs::read_to_string(&copy.script_path).unwrap(),
+            "print('b')"
+        );
+    }
+
+    /// Test detection and rewriting of pipeline references
+    #[test]
+    fn test_pipeline_references() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        register_script(&registry, "step_tool", b"print(1)");
+        let steps = vec![PipelineStep {
+            name: None,
+            tool: "step_tool".to_string(),
+            args: serde_json::json!({}),
+            continue_on_error: false,
+            condition: None,
+            timeout_secs: None,
+            retries: 0,
+            retry_delay_ms: 0,
+            foreach: None,
+            max_concurrency: None,
+            cache: None,
+        }];
+        let manifest = ToolManifest::new_pipeline(
+            "my_pipeline".to_string(),
+            "Uses step_tool".to_string(),
+            steps,
+        );
+        registry
+            .register_tool(manifest, &[])
+            .expect("Failed to register pipeline");
+
+        assert_eq!(
+            registry.pipelines_referencing("step_tool"),
+            vec!["my_pipeline"]
+        );
+
+        registry.rename_tool("step_tool", "renamed_tool").unwrap();
+        let updated = registry
+            .replace_pipeline_references("step_tool", "renamed_tool")
+            .unwrap();
+        assert_eq!(updated, vec!["my_pipeline"]);
+
+        let pipeline = registry.get_tool("my_pipeline").unwrap();
+        assert_eq!(pipeline.manifest.pipeline_steps[0].tool, "renamed_tool");
+        assert!(registry.pipelines_referencing("step_tool").is_empty());
+    }
+}
+
+// ==================== Recompile Tests ====================
+
+mod recompile {
+    use super::*;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+
+    /// Test recompiling a WASM tool from its saved source
+    #[test]
+    fn test_recompile_from_saved_source() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let manifest = ToolManifest::new(
+            "recompiled".to_string(),
+            "A WASM tool".to_string(),
+            ToolType::Wasm,
+        );
+        let source = "fn main() { println!(\"hi\"); }";
+        registry
+            .register_wasm_tool(manifest, b"stale bytes", source)
+            .expect("Failed to register tool");
+
+        let config = registry
+            .recompile_wasm_tool("recompiled")
+            .expect("Recompile should succeed");
+
+        let bytes = std::fs::read(&config.wasm_path).unwrap();
+        assert_eq!(&bytes[0..4], &[0x00, 0x61, 0x73, 0x6D]);
+        assert_eq!(config.manifest.version, "1.0.1");
+    }
+
+    /// Test that recompile rejects non-WASM tools and missing source
+    #[test]
+    fn test_recompile_errors() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let script = ToolManifest::new(
+            "a_script".to_string(),
+            "Script".to_string(),
+            ToolType::Script,
+        );
+        registry.register_tool(script, b"print(1)").unwrap();
+        let err = registry.recompile_wasm_tool("a_script").unwrap_err();
+        assert!(err.to_string().contains("only WASM tools"));
+
+        let wasm = ToolManifest::new("no_src".to_string(), "WASM".to_string(), ToolType::Wasm);
+        registry.register_wasm_tool(wasm, b"\0asm", "").unwrap();
+        let err = registry.recompile_wasm_tool("no_src").unwrap_err();
+        assert!(err.to_string().contains("no saved source"));
+
+        assert!(registry.recompile_wasm_tool("missing").is_err());
+    }
+}
+
+// ==================== Tag Tests ====================
+
+mod tags {
+    use super::*;
+    use skillz::registry::{group_by_tag, ToolManifest, ToolRegistry, ToolType};
+
+    /// Test tagging a script tool, filtering by tag and manifest round-trip
+    #[test]
+    fn test_tag_filter_and_round_trip() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        for name in ["tagged_tool", "plain_tool"] {
+            let manifest =
+                ToolManifest::new(name.to_string(), "Script".to_string(), ToolType::Script);
+            registry.register_tool(manifest, b"print(1)").unwrap();
+        }
+
+        let tags = registry
+            .add_tags(
+                "tagged_tool",
+                &[
+                    "text".to_string(),
+                    " utils ".to_string(),
+                    "text".to_string(),
+                ],
+            )
+            .unwrap();
+        assert_eq!(tags, vec!["text", "utils"]);
+
+        let filtered: Vec<_> = registry
+            .list_tools()
+            .into_iter()
+            .filter(|t| t.has_tag("text"))
+            .collect();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].name(), "tagged_tool");
+
+        let tools = registry.list_tools();
+        let (groups, untagged) = group_by_tag(&tools);
+        assert_eq!(groups.keys().collect::<Vec<_>>(), vec!["text", "utils"]);
+        assert_eq!(untagged.len(), 1);
+        assert_eq!(untagged[0].name(), "plain_tool");
+
+        // Tags survive a reload from disk
+        let reloaded = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let tool = reloaded.get_tool("tagged_tool").unwrap();
+        assert_eq!(tool.tags(), ["text", "utils"]);
+
+        let tags = reloaded
+            .remove_tags("tagged_tool", &["text".to_string()])
+            .unwrap();
+        assert_eq!(tags, vec!["utils"]);
+    }
+}
+
+// ==================== Annotations ====================
+
+mod annotations {
+    use super::*;
+    use skillz::registry::{ToolAnnotations, ToolManifest, ToolRegistry, ToolType};
+
+    /// Only idempotent WASM/script tools have memoizable results
+    #[test]
+    fn test_is_cacheable() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let register = |name: &str, hints: serde_json::Value| {
+            let mut manifest =
+                ToolManifest::new(name.to_string(), "A tool".to_string(), ToolType::Script);
+            manifest.annotations = Some(ToolAnnotations::from_value(hints));
+            registry.register_tool(manifest, b"echo 1").unwrap()
+        };
+
+        assert!(register("idem", serde_json::json!({"idempotentHint": true})).is_cacheable());
+        // Read-only tools may still give a different answer each call
+        assert!(!register("reader", serde_json::json!({"readOnlyHint": true})).is_cacheable());
+        assert!(!register("writer", serde_json::json!({"readOnlyHint": false})).is_cacheable());
+
+        let plain = ToolManifest::new("plain".to_string(), "A tool".to_string(), ToolType::Script);
+        assert!(!registry
+            .register_tool(plain, b"echo 1")
+            .unwrap()
+            .is_cacheable());
+    }
+
+    /// A readOnlyHint tool may call anything but tools annotated destructiveHint
+    #[test]
+    fn test_read_only_tools_cant_call_destructive_tools() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let register = |name: &str, hints: serde_json::Value| {
+            let mut manifest =
+                ToolManifest::new(name.to_string(), "A tool".to_string(), ToolType::Script);
+            manifest.annotations = Some(ToolAnnotations::from_value(hints));
+            registry.register_tool(manifest, b"echo 1").unwrap()
+        };
+        let reader = register("reader", serde_json::json!({"readOnlyHint": true}));
+        let writer = register("writer", serde_json::json!({"readOnlyHint": false}));
+        let wiper = register("wiper", serde_json::json!({"destructiveHint": true}));
+        // readOnlyHint wins over a contradictory destructiveHint
+        let confused = register(
+            "confused",
+            serde_json::json!({"readOnlyHint": true, "destructiveHint": true}),
+        );
+
+        assert!(reader.is_read_only() && !reader.is_destructive());
+        assert!(wiper.is_destructive() && !confused.is_destructive());
+        let err = reader.check_may_call(&wiper).unwrap_err();
+        assert!(err.to_string().contains("readOnlyHint"), "{}", err);
+        assert!(reader.check_may_call(&writer).is_ok());
+        assert!(reader.check_may_call(&confused).is_ok());
+        assert!(writer.check_may_call(&wiper).is_ok());
+    }
+}
+
+// ==================== Pagination ====================
+
+mod pagination {
+    use super::*;
+    use skillz::registry::{paginate, ToolManifest, ToolRegistry, ToolType, LIST_PAGE_SIZE};
+
+    /// Following next cursors visits every tool resource exactly once
+    #[test]
+    fn test_pages_through_more_tools_than_one_page() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let count = LIST_PAGE_SIZE * 2 + 7;
+        for i in 0..count {
+            let manifest = ToolManifest::new(
+                format!("tool_{:03}", i),
+                "A tool".to_string(),
+                ToolType::Script,
+            );
+            registry.register_tool(manifest, b"echo 1").unwrap();
+        }
+
+        let mut uris: Vec<String> = registry
+            .list_tools()
+            .iter()
+            .map(|t| format!("skillz://tools/{}", t.name()))
+            .collect();
+        uris.sort();
+
+        let mut seen = Vec::new();
+        let mut cursor: Option<String> = None;
+        let mut pages = 0;
+        loop {
+            let (page, next) = paginate(uris.clone(), cursor.as_deref(), LIST_PAGE_SIZE).unwrap();
+            assert!(page.len() <= LIST_PAGE_SIZE);
+            pages += 1;
+            seen.extend(page);
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages, 3);
+        assert_eq!(seen, uris);
+    }
+
+    #[test]
+    fn test_paginate_edges() {
+        let (page, next) = paginate(vec![1, 2, 3, 4], None, 2).unwrap();
+        assert_eq!((page, next.as_deref()), (vec![1, 2], Some("2")));
+
+        // An exact fit has no next page
+        let (page, next) = paginate(vec![1, 2, 3, 4], Some("2"), 2).unwrap();
+        assert_eq!((page, next), (vec![3, 4], None));
+
+        let (page, next) = paginate(Vec::<i32>::new(), None, 2).unwrap();
+        assert!(page.is_empty() && next.is_none());
+
+        assert!(paginate(vec![1, 2], Some("abc"), 2).is_err());
+        assert!(paginate(vec![1, 2], Some("5"), 2).is_err());
+    }
+}
+
+// ==================== Tool Source ====================
+
+mod tool_source {
+    use super::*;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+
+    #[test]
+    fn test_script_source() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest =
+            ToolManifest::new("greet".to_string(), "Greets".to_string(), ToolType::Script);
+        manifest.interpreter = Some("python3".to_string());
+        let tool = registry
+            .register_tool(manifest, b"print('hello')\n")
+            .unwrap();
+
+        assert_eq!(tool.source_path(), Some(tool.script_path.clone()));
+        assert_eq!(tool.source_mime_type(), "text/x-python");
+        assert_eq!(tool.read_source().unwrap(), "print('hello')\n");
+    }
+
+    #[test]
+    fn test_wasm_source_only_when_stored() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let code = "fn main() { println!(\"hi\"); }";
+        let with_src = registry
+            .register_wasm_tool(
+                ToolManifest::new("built".to_string(), "Built".to_string(), ToolType::Wasm),
+                b"\0asm",
+                code,
+            )
+            .unwrap();
+        assert_eq!(with_src.source_mime_type(), "text/x-rust");
+        assert_eq!(with_src.read_source().unwrap(), code);
+
+        let without_src = registry
+            .register_wasm_tool(
+                ToolManifest::new("bare".to_string(), "Bare".to_string(), ToolType::Wasm),
+                b"\0asm",
+                "",
+            )
+            .unwrap();
+        assert!(without_src.source_path().is_none());
+        assert!(without_src.read_source().is_err());
+
+        let pipeline = registry
+            .register_tool(
+                ToolManifest::new_pipeline("p".to_string(), "P".to_string(), vec![]),
+                &[],
+            )
+            .unwrap();
+        assert!(pipeline.source_path().is_none());
+    }
+
+    #[test]
+    fn test_rebuild_args() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let mut manifest =
+            ToolManifest::new("greet".to_string(), "Greets".to_string(), ToolType::Script);
+        manifest.interpreter = Some("python3".to_string());
+        manifest.dependencies = vec!["requests".to_string()];
+        manifest.tags = vec!["text".to_string()];
+        let script = registry
+            .register_tool(manifest, b"print('hello')\n")
+            .unwrap();
+        let args = script.rebuild_args().unwrap();
+        assert_eq!(args["name"], "greet");
+        assert_eq!(args["interpreter"], "python3");
+        assert_eq!(args["extension"], "py");
+        assert_eq!(args["dependencies"], serde_json::json!(["requests"]));
+        assert_eq!(args["tags"], serde_json::json!(["text"]));
+        assert_eq!(args["overwrite"], true);
+        assert!(args.get("input_schema").is_some());
+        // Unset and default fields are left out
+        assert!(args.get("code").is_none());
+        assert!(args.get("ndjson").is_none());
+        assert!(args.get("output_schema").is_none());
+
+        let mut manifest =
+            ToolManifest::new("built".to_string(), "Built".to_string(), ToolType::Wasm);
+        manifest.wasm_dependencies = vec!["regex@1".to_string()];
+        let wasm = registry
+            .register_wasm_tool(manifest, b"\0asm", "fn main() {}")
+            .unwrap();
+        let args = wasm.rebuild_args().unwrap();
+        assert_eq!(args["dependencies"], serde_json::json!(["regex@1"]));
+        assert!(args.get("interpreter").is_none());
+
+        let steps: Vec<skillz::registry::PipelineStep> = serde_json::from_value(
+            serde_json::json!([{"tool": "greet", "args": {"name": "$input.name"}}]),
+        )
+        .unwrap();
+        let pipeline = registry
+            .register_tool(
+                ToolManifest::new_pipeline("p".to_string(), "P".to_string(), steps),
+                &[],
+            )
+            .unwrap();
+        let args = pipeline.rebuild_args().unwrap();
+        assert_eq!(args["action"], "update");
+        assert_eq!(args["steps"][0]["args"]["name"], "$input.name");
+        assert!(args.get("overwrite").is_none());
+    }
+}
+
+// ==================== Load Errors ====================
+
+mod load_errors {
+    use super::*;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+
+    #[test]
+    fn test_invalid_manifests_are_reported() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest =
+            ToolManifest::new("good".to_string(), "Works".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        registry.register_tool(manifest, b"echo hi\n").unwrap();
+
+        let broken = temp_dir.path().join("broken");
+        std::fs::create_dir_all(&broken).unwrap();
+        std::fs::write(broken.join("manifest.json"), "{ not json").unwrap();
+        let typo = temp_dir.path().join("typo");
+        std::fs::create_dir_all(&typo).unwrap();
+        std::fs::write(
+            typo.join("manifest.json"),
+            r#"{"name": "typo", "version": "1.0.0", "description": "", "tool_type": "scrpt"}"#,
+        )
+        .unwrap();
+        // Not a tool directory: no manifest at all
+        std::fs::create_dir_all(temp_dir.path().join("notes")).unwrap();
+
+        registry.reload();
+
+        assert!(registry.get_tool("good").is_some());
+        let errors = registry.load_errors();
+        let dirs: Vec<&str> = errors.iter().map(|e| e.dir.as_str()).collect();
+        assert_eq!(dirs, vec!["broken", "typo"]);
+        assert!(errors[0].message.starts_with("Invalid manifest.json: "));
+        assert!(errors[1].message.contains("unknown variant `scrpt`"));
+    }
+
+    #[test]
+    fn test_duplicate_manifest_names_are_reported() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest = ToolManifest::new(
+            "alpha".to_string(),
+            "Original".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        registry.register_tool(manifest, b"echo hi\n").unwrap();
+
+        // A hand-copied directory that still claims to be "alpha"
+        let copy = temp_dir.path().join("alpha-copy");
+        std::fs::create_dir_all(&copy).unwrap();
+        let original = std::fs::read_to_string(temp_dir.path().join("alpha/manifest.json"))
+            .unwrap()
+            .replace("Original", "Copy");
+        std::fs::write(copy.join("manifest.json"), original).unwrap();
+
+        registry.reload();
+
+        let alpha = registry.get_tool("alpha").unwrap();
+        assert_eq!(alpha.manifest.description, "Original");
+        assert_eq!(alpha.tool_dir, temp_dir.path().join("alpha"));
+        let errors = registry.load_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].dir, "alpha-copy");
+        assert!(
+            errors[0]
+                .message
+                .contains("names the tool 'alpha' but its directory is 'alpha-copy'"),
+            "{}",
+            errors[0].message
+        );
+    }
+
+    #[test]
+    fn test_reload_tool_clears_fixed_errors() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest =
+            ToolManifest::new("fixme".to_string(), "Fixed".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        registry.register_tool(manifest, b"echo hi\n").unwrap();
+        let manifest_path = temp_dir.path().join("fixme").join("manifest.json");
+        let good = std::fs::read_to_string(&manifest_path).unwrap();
+
+        std::fs::write(&manifest_path, "{").unwrap();
+        assert!(registry.reload_tool("fixme").is_err());
+        assert_eq!(registry.load_errors().len(), 1);
+
+        std::fs::write(&manifest_path, good).unwrap();
+        registry.reload_tool("fixme").unwrap();
+        assert!(registry.load_errors().is_empty());
+    }
+}
@@ -797,3 +797,1912 @@ fn test_memory_response_format() {
     assert!(list_response["result"]["keys"].is_array());
     assert_eq!(list_response["result"]["keys"].as_array().unwrap().len(), 3);
 }
+
+// ==================== Cancellation ====================
+
+mod cancellation {
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::{Cancelled, ToolRuntime};
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+    use tokio_util::sync::CancellationToken;
+
+    /// Run `tool_name` on a blocking thread, cancel it after 200ms and
+    /// return the result together with how long the call took
+    async fn call_and_cancel(
+        registry: &ToolRegistry,
+        tool_name: &str,
+    ) -> (anyhow::Result<serde_json::Value>, Duration) {
+        let token = CancellationToken::new();
+        let runtime = ToolRuntime::new().unwrap().with_cancellation(token.clone());
+        let tool = registry.get_tool(tool_name).unwrap();
+
+        let start = Instant::now();
+        let call =
+            tokio::task::spawn_blocking(move || runtime.call_tool(&tool, serde_json::json!({})));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        token.cancel();
+
+        let result = tokio::time::timeout(Duration::from_secs(10), call)
+            .await
+            .expect("cancelled call did not return")
+            .unwrap();
+        (result, start.elapsed())
+    }
+
+    /// A long-running script is killed when its call is cancelled
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cancel_script_tool() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let mut manifest =
+            ToolManifest::new("sleepy".to_string(), "Sleeps".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        // `read -t` waits in-process on the still-open stdin
+        registry
+            .register_tool(manifest, b"read line\nread -t 30 never\necho done\n")
+            .unwrap();
+
+        let (result, elapsed) = call_and_cancel(&registry, "sleepy").await;
+        assert!(result.unwrap_err().is::<Cancelled>());
+        assert!(elapsed < Duration::from_secs(10));
+    }
+
+    /// A WASM module stuck in a loop traps when its call is cancelled
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cancel_wasm_tool() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let manifest = ToolManifest::new("spin".to_string(), "Spins".to_string(), ToolType::Wasm);
+        let module = r#"(module (func (export "_start") (loop (br 0))))"#;
+        registry
+            .register_wasm_tool(manifest, module.as_bytes(), "")
+            .unwrap();
+
+        let (result, elapsed) = call_and_cancel(&registry, "spin").await;
+        assert!(result.unwrap_err().is::<Cancelled>());
+        assert!(elapsed < Duration::from_secs(10));
+    }
+
+    /// Epoch checks don't interrupt a WASM call that is never cancelled
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_uncancelled_wasm_completes() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let manifest = ToolManifest::new("count".to_string(), "Counts".to_string(), ToolType::Wasm);
+        // Count down from 1,000,000 before returning
+        let module = r#"(module (func (export "_start") (local i32)
+            (local.set 0 (i32.const 1000000))
+            (loop (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                  (br_if 0 (local.get 0)))))"#;
+        let tool = registry
+            .register_wasm_tool(manifest, module.as_bytes(), "")
+            .unwrap();
+
+        let token = CancellationToken::new();
+        let runtime = ToolRuntime::new().unwrap().with_cancellation(token);
+        let result =
+            tokio::task::spawn_blocking(move || runtime.call_tool(&tool, serde_json::json!({})))
+                .await
+                .unwrap();
+        assert_eq!(result.unwrap(), serde_json::json!(""));
+    }
+
+    /// A call whose token is already cancelled never starts
+    #[test]
+    fn test_cancelled_before_start() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let manifest = ToolManifest::new("noop".to_string(), "Noop".to_string(), ToolType::Script);
+        let tool = registry.register_tool(manifest, b"echo hi").unwrap();
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let runtime = ToolRuntime::new().unwrap().with_cancellation(token);
+        let err = runtime.call_tool(&tool, serde_json::json!({})).unwrap_err();
+        assert!(err.is::<Cancelled>());
+    }
+}
+
+// ==================== Concurrency Limit ====================
+
+mod concurrency {
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    /// With two slots, a third concurrent call waits until one frees up
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_extra_call_waits_for_slot() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+
+        let mut manifest =
+            ToolManifest::new("slow".to_string(), "Slow".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        let tool = registry
+            .register_tool(
+                manifest,
+                b"read line\nread -t 1 never\necho '{\"jsonrpc\":\"2.0\",\"result\":\"ok\",\"id\":1}'\n",
+            )
+            .unwrap();
+
+        let runtime = ToolRuntime::new().unwrap().with_max_concurrency(2);
+        let spawn = || {
+            let runtime = runtime.clone();
+            let tool = tool.clone();
+            tokio::task::spawn_blocking(move || runtime.call_tool(&tool, serde_json::json!({})))
+        };
+
+        let first = spawn();
+        let second = spawn();
+        while runtime.available_slots() != Some(0) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let third = spawn();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!third.is_finished(), "third call should be queued");
+
+        for call in [first, second, third] {
+            assert_eq!(call.await.unwrap().unwrap(), serde_json::json!("ok"));
+        }
+        assert_eq!(runtime.available_slots(), Some(2));
+    }
+
+    /// A limit of 0 means unlimited
+    #[test]
+    fn test_zero_means_unlimited() {
+        let runtime = ToolRuntime::new().unwrap().with_max_concurrency(0);
+        assert_eq!(runtime.available_slots(), None);
+        let runtime = ToolRuntime::new().unwrap().with_max_concurrency(3);
+        assert_eq!(runtime.available_slots(), Some(3));
+        assert_eq!(runtime.without_concurrency_limit().available_slots(), None);
+    }
+}
+
+mod rate_limiting {
+    use skillz::registry::{RateLimit, ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::{RateLimited, ToolRuntime};
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn limited_tool(
+        registry: &ToolRegistry,
+        max: u32,
+        per_secs: u64,
+    ) -> skillz::registry::ToolConfig {
+        let mut manifest = ToolManifest::new(
+            "limited".to_string(),
+            "Limited".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        manifest.rate_limit = Some(RateLimit { max, per_secs });
+        registry
+            .register_tool(
+                manifest,
+                b"read line\necho '{\"jsonrpc\":\"2.0\",\"result\":\"ok\",\"id\":1}'\n",
+            )
+            .unwrap()
+    }
+
+    /// Calls beyond the limit fail with a retry hint; clones share the bucket
+    #[test]
+    fn test_calls_over_limit_are_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let tool = limited_tool(&registry, 2, 60);
+
+        let runtime = ToolRuntime::new().unwrap();
+        let args = serde_json::json!({});
+        assert!(runtime.call_tool(&tool, args.clone()).is_ok());
+        assert!(runtime.clone().call_tool(&tool, args.clone()).is_ok());
+
+        let err = runtime.call_tool(&tool, args).unwrap_err();
+        let limited = err
+            .downcast_ref::<RateLimited>()
+            .expect("RateLimited error");
+        assert_eq!(limited.tool, "limited");
+        assert!(limited.retry_after > Duration::from_secs(25));
+        assert!(err.to_string().contains("retry in 30s"), "{}", err);
+    }
+
+    /// Tokens refill over the window
+    #[test]
+    fn test_limit_recovers_after_window() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let tool = limited_tool(&registry, 1, 1);
+
+        let runtime = ToolRuntime::new().unwrap();
+        let args = serde_json::json!({});
+        assert!(runtime.call_tool(&tool, args.clone()).is_ok());
+        assert!(runtime.call_tool(&tool, args.clone()).is_err());
+
+        std::thread::sleep(Duration::from_millis(1100));
+        assert!(runtime.call_tool(&tool, args).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit_from_value() {
+        let limit = RateLimit::from_value(serde_json::json!({"max": 10, "per_secs": 60})).unwrap();
+        assert_eq!(
+            limit,
+            RateLimit {
+                max: 10,
+                per_secs: 60
+            }
+        );
+        assert!(RateLimit::from_value(serde_json::json!({"max": 0, "per_secs": 60})).is_err());
+        assert!(RateLimit::from_value(serde_json::json!({"max": 5})).is_err());
+    }
+}
+
+mod memory_ttl {
+    use skillz::memory::Memory;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use tempfile::TempDir;
+
+    /// Scripts can set a TTL through memory/set's ttl_seconds param
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_script_memory_set_with_ttl_seconds() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let memory = Memory::new(temp_dir.path()).await.unwrap();
+
+        let mut manifest =
+            ToolManifest::new("cacher".to_string(), "Caches".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        let tool = registry
+            .register_tool(
+                manifest,
+                br#"read line
+echo '{"jsonrpc":"2.0","method":"memory/set","params":{"key":"k","value":1,"ttl_seconds":1},"id":2}'
+read reply
+echo '{"jsonrpc":"2.0","result":"stored","id":1}'
+"#,
+            )
+            .unwrap();
+
+        let runtime = ToolRuntime::new().unwrap().with_memory(memory.clone());
+        let result =
+            tokio::task::spawn_blocking(move || runtime.call_tool(&tool, serde_json::json!({})))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(result, serde_json::json!("stored"));
+
+        assert_eq!(
+            memory.get("cacher", "k").await.unwrap(),
+            Some(serde_json::json!(1))
+        );
+        assert_eq!(memory.stats().await.unwrap().expiring_soon, 1);
+
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        assert_eq!(memory.get("cacher", "k").await.unwrap(), None);
+        assert!(memory.list_keys("cacher").await.unwrap().is_empty());
+    }
+
+    /// A memory/set that doesn't match the tool's schema gets an error reply
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_script_memory_set_checks_schema() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let memory = Memory::new(temp_dir.path()).await.unwrap();
+        memory
+            .set(
+                "strict",
+                skillz::memory::SCHEMA_KEY,
+                serde_json::json!({"type": "integer"}),
+            )
+            .await
+            .unwrap();
+
+        let mut manifest =
+            ToolManifest::new("strict".to_string(), "Strict".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        let tool = registry
+            .register_tool(
+                manifest,
+                br#"read line
+echo '{"jsonrpc":"2.0","method":"memory/set","params":{"key":"k","value":"one"},"id":2}'
+read -r reply
+printf '{"jsonrpc":"2.0","result":%s,"id":1}\n' "$reply"
+"#,
+            )
+            .unwrap();
+
+        let runtime = ToolRuntime::new().unwrap().with_memory(memory.clone());
+        let result =
+            tokio::task::spawn_blocking(move || runtime.call_tool(&tool, serde_json::json!({})))
+                .await
+                .unwrap()
+                .unwrap();
+        let error = result["result"]["error"].as_str().unwrap_or_default();
+        assert!(
+            error.starts_with("Value for 'k' doesn't match the memory schema of 'strict'"),
+            "{}",
+            result
+        );
+        assert_eq!(memory.get("strict", "k").await.unwrap(), None);
+    }
+}
+
+mod ruby_deps {
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::{install_tool_deps, PackageManager, ToolRuntime, GEM_HOME_DIR};
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_package_manager_for_interpreter() {
+        assert_eq!(
+            PackageManager::for_interpreter("python3"),
+            Some(PackageManager::Pip)
+        );
+        assert_eq!(
+            PackageManager::for_interpreter("nodejs"),
+            Some(PackageManager::Npm)
+        );
+        assert_eq!(
+            PackageManager::for_interpreter("ruby"),
+            Some(PackageManager::Gem)
+        );
+        assert_eq!(PackageManager::for_interpreter("perl"), None);
+    }
+
+    /// Gems install into env/gems and the script sees them through GEM_HOME
+    #[test]
+    fn test_ruby_gem_install() {
+        let gem_check = Command::new("gem").arg("--version").output();
+        if gem_check.is_err() || !gem_check.unwrap().status.success() {
+            eprintln!("Skipping Ruby gem test: gem not available");
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest =
+            ToolManifest::new("rubytool".to_string(), "Ruby".to_string(), ToolType::Script);
+        manifest.interpreter = Some("ruby".to_string());
+        manifest.dependencies = vec!["rainbow".to_string()];
+        registry
+            .register_tool(
+                manifest,
+                br#"require 'json'
+require 'rainbow'
+request = JSON.parse($stdin.gets)
+puts({jsonrpc: "2.0", result: ENV["GEM_HOME"], id: request["id"]}.to_json)
+$stdout.flush
+"#,
+            )
+            .unwrap();
+
+        let env_path = registry.tool_env_path("rubytool");
+        let result = install_tool_deps(&env_path, Some("ruby"), &["rainbow".to_string()]).unwrap();
+        assert!(result.success, "{}", result.message);
+        registry.mark_deps_installed("rubytool").unwrap();
+        registry.reload();
+
+        let tool = registry.get_tool("rubytool").unwrap();
+        let output = ToolRuntime::new()
+            .unwrap()
+            .call_tool(&tool, serde_json::json!({}))
+            .unwrap();
+        assert_eq!(
+            output,
+            serde_json::json!(env_path.join(GEM_HOME_DIR).display().to_string())
+        );
+    }
+}
+
+mod lockfiles {
+    use skillz::runtime::{node_lock_matches, python_lock_matches};
+    use tempfile::TempDir;
+
+    fn deps(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_python_lock_matches_declared_dependencies() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock = temp_dir.path().join("requirements.lock");
+        assert!(!python_lock_matches(&lock, &deps(&["requests"])));
+
+        std::fs::write(
+            &lock,
+            "# skillz-dependencies: pandas,requests\ncertifi==2024.2.2\nrequests==2.31.0\n",
+        )
+        .unwrap();
+        // Order and duplicates don't matter, the set does
+        assert!(python_lock_matches(
+            &lock,
+            &deps(&["requests", "pandas", "requests"])
+        ));
+        assert!(!python_lock_matches(&lock, &deps(&["requests"])));
+        assert!(!python_lock_matches(
+            &lock,
+            &deps(&["requests", "pandas", "numpy"])
+        ));
+
+        // A plain pip freeze without the header is never trusted
+        std::fs::write(&lock, "requests==2.31.0\n").unwrap();
+        assert!(!python_lock_matches(&lock, &deps(&["requests"])));
+    }
+
+    #[test]
+    fn test_node_lock_matches_declared_dependencies() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock = temp_dir.path().join("package-lock.json");
+        std::fs::write(
+            &lock,
+            serde_json::json!({
+                "lockfileVersion": 3,
+                "packages": {
+                    "": {"dependencies": {"lodash": "^4.17.21", "@scope/pkg": "1.0.0"}},
+                    "node_modules/lodash": {"version": "4.17.21"}
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        assert!(node_lock_matches(
+            &lock,
+            &deps(&["lodash@4", "@scope/pkg@1.0.0"])
+        ));
+        assert!(node_lock_matches(&lock, &deps(&["@scope/pkg", "lodash"])));
+        assert!(!node_lock_matches(&lock, &deps(&["lodash"])));
+        assert!(!node_lock_matches(
+            &lock,
+            &deps(&["lodash", "@scope/pkg", "axios"])
+        ));
+
+        std::fs::write(&lock, "not json").unwrap();
+        assert!(!node_lock_matches(&lock, &deps(&["lodash"])));
+    }
+}
+
+mod install_progress {
+    use skillz::runtime::{ProgressHandler, ToolRuntime};
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    type Events = Arc<Mutex<Vec<(u64, u64, Option<String>)>>>;
+
+    /// Installs report a start and a finish notification even when they fail
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_install_reports_start_and_finish() {
+        let events: Events = Arc::default();
+        let recorder = events.clone();
+        let handler: ProgressHandler = Arc::new(move |current, total, message, _token| {
+            recorder.lock().unwrap().push((current, total, message));
+            Box::pin(async {})
+        });
+        let runtime = ToolRuntime::new().unwrap().with_progress_handler(handler);
+
+        let temp_dir = TempDir::new().unwrap();
+        let env_path = temp_dir.path().join("env");
+        let result = tokio::task::spawn_blocking(move || {
+            runtime.install_deps(&env_path, Some("perl"), &["JSON::XS".to_string()])
+        })
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(!result.success);
+
+        let events = events.lock().unwrap();
+        assert_eq!(
+            *events,
+            vec![
+                (
+                    0,
+                    2,
+                    Some("Installing 1 dependencies: JSON::XS".to_string())
+                ),
+                (2, 2, Some("Dependency installation failed".to_string())),
+            ]
+        );
+    }
+}
+
+// ==================== Read-only Sandbox ====================
+
+#[cfg(unix)]
+mod read_only_sandbox {
+    use skillz::runtime::{SandboxConfig, SandboxMode};
+    use std::path::{Path, PathBuf};
+
+    fn config(mode: SandboxMode) -> SandboxConfig {
+        SandboxConfig {
+            mode,
+            write_paths: vec![PathBuf::from("/data/out")],
+            ..Default::default()
+        }
+    }
+
+    /// Whether `flag` is directly followed by `path` in `args`
+    fn binds(args: &[String], flag: &str, path: &str) -> bool {
+        args.windows(2).any(|w| w[0] == flag && w[1] == path)
+    }
+
+    #[test]
+    fn test_read_only_tools_get_read_only_roots() {
+        let script = Path::new("/tools/reader/reader.py");
+        let roots = vec!["/work".to_string()];
+
+        let sandbox = config(SandboxMode::Bubblewrap);
+        let args = sandbox.for_call(false).sandbox_args(script, &roots);
+        assert!(binds(&args, "--bind", "/work"));
+        assert!(binds(&args, "--bind", "/data/out"));
+
+        let read_only = sandbox.for_call(true);
+        assert!(read_only.write_paths.is_empty());
+        let args = read_only.sandbox_args(script, &roots);
+        assert!(binds(&args, "--ro-bind", "/work"));
+        assert!(!args.iter().any(|a| a == "--bind"));
+
+        let args = config(SandboxMode::Nsjail)
+            .for_call(true)
+            .sandbox_args(script, &roots);
+        assert!(binds(&args, "-R", "/work"));
+        assert!(!args.iter().any(|a| a == "-B"));
+
+        let args = config(SandboxMode::Firejail)
+            .for_call(true)
+            .sandbox_args(script, &roots);
+        assert!(args.iter().any(|a| a == "--read-only=/work"));
+    }
+}
+
+// ==================== Runtime Sandbox Changes ====================
+
+mod sandbox_settings {
+    use skillz::runtime::{SandboxConfig, SandboxMode, ToolRuntime};
+
+    #[test]
+    fn test_set_sandbox_config_applies_to_clones() {
+        let runtime = ToolRuntime::with_sandbox(SandboxConfig::default()).unwrap();
+        let clone = runtime.clone();
+
+        let config = SandboxConfig {
+            allow_network: true,
+            time_limit_secs: 5,
+            ..runtime.sandbox_config()
+        };
+        let effective = runtime.set_sandbox_config(config).unwrap();
+        assert!(effective.allow_network);
+        assert!(clone.sandbox_config().allow_network);
+        assert_eq!(clone.sandbox_config().time_limit_secs, 5);
+    }
+
+    #[test]
+    fn test_unavailable_mode_is_rejected() {
+        let runtime = ToolRuntime::with_sandbox(SandboxConfig::default()).unwrap();
+        let nsjail = SandboxConfig {
+            mode: SandboxMode::Nsjail,
+            ..Default::default()
+        };
+        if nsjail.check_available().unwrap() && cfg!(unix) {
+            return; // Can't simulate a missing program here
+        }
+
+        assert!(runtime.set_sandbox_config(nsjail).is_err());
+        assert_eq!(runtime.sandbox_config().mode, SandboxMode::None);
+    }
+
+    #[test]
+    fn test_parse_sandbox_mode() {
+        assert_eq!(
+            SandboxMode::parse("bwrap").unwrap(),
+            SandboxMode::Bubblewrap
+        );
+        assert_eq!(SandboxMode::parse("NsJail").unwrap(), SandboxMode::Nsjail);
+        assert_eq!(SandboxMode::parse("none").unwrap(), SandboxMode::None);
+        assert!(SandboxMode::parse("docker").is_err());
+    }
+}
+
+// ==================== WASM HTTP Fetch ====================
+
+mod http_fetch {
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::{SandboxConfig, ToolRuntime};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use tempfile::TempDir;
+
+    /// A module that sends `request` through skillz_http_fetch and prints the response
+    fn fetch_module(request: &serde_json::Value) -> String {
+        let request = request.to_string();
+        format!(
+            r#"(module
+                (import "skillz" "skillz_http_fetch" (func $fetch (param i32 i32) (result i32)))
+                (import "skillz" "skillz_http_response" (func $response (param i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "fd_write"
+                    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 1024) "{}")
+                (func (export "_start") (local $len i32)
+                    (local.set $len (call $fetch (i32.const 1024) (i32.const {})))
+                    (drop (call $response (i32.const 4096) (local.get $len)))
+                    (i32.store (i32.const 0) (i32.const 4096))
+                    (i32.store (i32.const 4) (local.get $len))
+                    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))"#,
+            request.replace('"', "\\22"),
+            request.len()
+        )
+    }
+
+    fn register(registry: &ToolRegistry, module: &str) -> skillz::registry::ToolConfig {
+        let manifest =
+            ToolManifest::new("fetcher".to_string(), "Fetches".to_string(), ToolType::Wasm);
+        registry
+            .register_wasm_tool(manifest, module.as_bytes(), "")
+            .unwrap()
+    }
+
+    fn response_of(output: serde_json::Value) -> serde_json::Value {
+        serde_json::from_str(output.as_str().unwrap()).unwrap()
+    }
+
+    /// Without allow_network the host refuses the request
+    #[test]
+    fn test_http_fetch_requires_network_permission() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let tool = register(
+            &registry,
+            &fetch_module(&serde_json::json!({"url": "http://127.0.0.1:9/"})),
+        );
+
+        let runtime = ToolRuntime::with_sandbox(SandboxConfig::default()).unwrap();
+        let response = response_of(runtime.call_tool(&tool, serde_json::json!({})).unwrap());
+        assert!(response["error"]
+            .as_str()
+            .unwrap()
+            .contains("Network access is disabled"));
+    }
+
+    /// With allow_network the host performs the request and returns status, headers and body
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_http_fetch_returns_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello")
+                .unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let tool = register(
+            &registry,
+            &fetch_module(&serde_json::json!({
+                "url": format!("http://127.0.0.1:{}/greeting", port),
+                "headers": {"x-skillz": "yes"}
+            })),
+        );
+
+        let runtime = ToolRuntime::with_sandbox(SandboxConfig {
+            allow_network: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let output =
+            tokio::task::spawn_blocking(move || runtime.call_tool(&tool, serde_json::json!({})))
+                .await
+                .unwrap()
+                .unwrap();
+
+        let response = response_of(output);
+        assert_eq!(response["status"], 200);
+        assert_eq!(response["body"], "hello");
+        assert_eq!(response["headers"]["content-type"], "text/plain");
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /greeting HTTP/1.1"));
+        assert!(request.contains("x-skillz: yes"));
+    }
+}
+
+// ==================== WASM Fuel ====================
+
+mod wasm_fuel {
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::{FuelExhausted, ToolRuntime};
+    use tempfile::TempDir;
+
+    /// A module that counts down from `iterations` and then prints "done"
+    fn counting_module(iterations: u32) -> String {
+        format!(
+            r#"(module
+                (import "wasi_snapshot_preview1" "fd_write"
+                    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 16) "done")
+                (func (export "_start") (local $i i32)
+                    (local.set $i (i32.const {}))
+                    (block $exit
+                        (loop $count
+                            (br_if $exit (i32.eqz (local.get $i)))
+                            (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+                            (br $count)))
+                    (i32.store (i32.const 0) (i32.const 16))
+                    (i32.store (i32.const 4) (i32.const 4))
+                    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))"#,
+            iterations
+        )
+    }
+
+    fn call(iterations: u32, fuel: Option<u64>) -> anyhow::Result<serde_json::Value> {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest =
+            ToolManifest::new("counter".to_string(), "Counts".to_string(), ToolType::Wasm);
+        manifest.wasm_fuel = fuel;
+        let tool = registry
+            .register_wasm_tool(manifest, counting_module(iterations).as_bytes(), "")
+            .unwrap();
+        ToolRuntime::new()
+            .unwrap()
+            .call_tool(&tool, serde_json::json!({}))
+    }
+
+    /// Without a budget, and within one, the module runs to completion
+    #[test]
+    fn test_runs_within_budget() {
+        assert_eq!(call(1_000_000, None).unwrap(), "done");
+        assert_eq!(call(100, Some(1_000_000)).unwrap(), "done");
+    }
+
+    /// A module that needs more than its budget is cut off
+    #[test]
+    fn test_fuel_exhausted() {
+        let err = call(1_000_000, Some(10_000)).unwrap_err();
+        let exhausted = err.downcast_ref::<FuelExhausted>().expect("fuel error");
+        assert_eq!(exhausted.fuel, 10_000);
+        assert!(err.to_string().contains("WASM fuel exhausted"), "{}", err);
+    }
+}
+
+// ==================== Argv Arg Mode ====================
+
+mod arg_mode {
+    use serde_json::json;
+    use skillz::registry::{ArgMode, ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::{argv_flags, ToolRuntime};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_argv_flags() {
+        let flags = argv_flags(&json!({
+            "name": "World",
+            "count": 2,
+            "verbose": true,
+            "quiet": false,
+            "skip": null,
+            "tag": ["a", "b"],
+            "_": ["in.txt"]
+        }))
+        .unwrap();
+        assert_eq!(
+            flags,
+            [
+                "--count",
+                "2",
+                "--name",
+                "World",
+                "--tag",
+                "a",
+                "--tag",
+                "b",
+                "--verbose",
+                "in.txt"
+            ]
+        );
+        assert!(argv_flags(&json!(null)).unwrap().is_empty());
+        assert!(argv_flags(&json!("text")).is_err());
+    }
+
+    /// Argv tools get flags on the command line and return raw stdout
+    #[test]
+    fn test_argv_script_returns_stdout() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest =
+            ToolManifest::new("echoer".to_string(), "Echoes".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        manifest.arg_mode = ArgMode::Argv;
+        let tool = registry.register_tool(manifest, b"echo \"$@\"\n").unwrap();
+
+        let runtime = ToolRuntime::new().unwrap();
+        let output = runtime
+            .call_tool(&tool, json!({"name": "World", "loud": true}))
+            .unwrap();
+        assert_eq!(output, json!("--loud --name World"));
+    }
+}
+
+// ==================== Script Exit Code ====================
+
+mod exit_code {
+    use serde_json::json;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use tempfile::TempDir;
+
+    fn run(code: &[u8]) -> serde_json::Value {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest =
+            ToolManifest::new("exiter".to_string(), "Exits".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        let tool = registry.register_tool(manifest, code).unwrap();
+        ToolRuntime::new()
+            .unwrap()
+            .call_tool(&tool, json!({}))
+            .unwrap()
+    }
+
+    /// A clean run returns the plain result
+    #[test]
+    fn test_clean_exit_returns_plain_result() {
+        let output = run(b"read line\necho '{\"jsonrpc\":\"2.0\",\"result\":false,\"id\":1}'\n");
+        assert_eq!(output, json!(false));
+    }
+
+    /// A crash is distinguishable from a falsy result
+    #[test]
+    fn test_failed_exit_reports_exit_code() {
+        let output = run(b"read line\nexit 3\n");
+        assert_eq!(output["exit_code"], json!(3));
+        assert_eq!(
+            output["result"],
+            json!("Script exited with status: exit status: 3")
+        );
+        assert!(skillz::runtime::is_failed_run(&output));
+    }
+
+    /// Only a non-zero exit code marks a run as failed, not a falsy result
+    #[test]
+    fn test_is_failed_run() {
+        assert!(!skillz::runtime::is_failed_run(&json!(false)));
+        assert!(!skillz::runtime::is_failed_run(
+            &json!({"result": 1, "logs": [], "exit_code": 0})
+        ));
+        // Killed by a signal: no exit code
+        assert!(skillz::runtime::is_failed_run(
+            &json!({"result": "Script exited with status: signal: 9", "exit_code": null})
+        ));
+    }
+}
+
+// ==================== Service Environment ====================
+
+mod service_env {
+    use serde_json::json;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use tempfile::TempDir;
+
+    /// Service env vars reach both the process and context.environment
+    #[test]
+    fn test_service_env_vars_are_injected() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest = ToolManifest::new(
+            "db_user".to_string(),
+            "Uses db".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        manifest.requires_services = vec!["postgres".to_string()];
+        let tool = registry
+            .register_tool(
+                manifest,
+                br#"read line
+case "$line" in *'"POSTGRES_PORT":"49153"'*) ctx=true ;; *) ctx=false ;; esac
+echo "{\"jsonrpc\":\"2.0\",\"result\":{\"port\":\"$POSTGRES_PORT\",\"in_context\":$ctx},\"id\":1}"
+"#,
+            )
+            .unwrap();
+
+        let mut runtime = ToolRuntime::new().unwrap();
+        runtime.set_env_var("POSTGRES_PORT".to_string(), "49153".to_string());
+        let output = runtime.call_tool(&tool, json!({})).unwrap();
+        assert_eq!(output, json!({"port": "49153", "in_context": true}));
+    }
+}
+
+// ==================== Working Directory & Env ====================
+
+mod working_dir_env {
+    use serde_json::json;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::{expand_env, script_working_dir, ToolRuntime};
+    use tempfile::TempDir;
+
+    /// Scripts run in their working_dir with their env added, and see both
+    /// in the context
+    #[test]
+    fn test_script_runs_in_working_dir_with_env() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest = ToolManifest::new(
+            "project_cli".to_string(),
+            "Wraps a project CLI".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        manifest.working_dir = Some(project.path().to_string_lossy().to_string());
+        manifest.env = [
+            ("GREETING".to_string(), "hi".to_string()),
+            ("TOOL_PATH".to_string(), "/opt/cli/bin:${PATH}".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let tool = registry
+            .register_tool(
+                manifest,
+                br#"read line
+case "$line" in *'"GREETING":"hi"'*) ctx=true ;; *) ctx=false ;; esac
+echo "{\"jsonrpc\":\"2.0\",\"result\":{\"pwd\":\"$(pwd -P)\",\"greeting\":\"$GREETING\",\"path\":\"$TOOL_PATH\",\"in_context\":$ctx},\"id\":1}"
+"#,
+            )
+            .unwrap();
+
+        let output = ToolRuntime::new()
+            .unwrap()
+            .call_tool(&tool, json!({}))
+            .unwrap();
+        let project_dir = project.path().canonicalize().unwrap();
+        assert_eq!(output["pwd"], json!(project_dir.to_string_lossy()));
+        assert_eq!(output["greeting"], json!("hi"));
+        assert_eq!(
+            output["path"],
+            json!(format!("/opt/cli/bin:{}", std::env::var("PATH").unwrap()))
+        );
+        assert_eq!(output["in_context"], json!(true));
+    }
+
+    /// A relative working_dir is resolved against the first root
+    #[test]
+    fn test_working_dir_resolution() {
+        let root = TempDir::new().unwrap();
+        std::fs::create_dir(root.path().join("app")).unwrap();
+        let roots = vec![root.path().to_string_lossy().to_string()];
+
+        assert_eq!(
+            script_working_dir("app", &roots).unwrap(),
+            root.path().join("app")
+        );
+        let err = script_working_dir("missing", &roots).unwrap_err();
+        assert!(err.to_string().contains("not a directory"), "{}", err);
+    }
+
+    #[test]
+    fn test_expand_env() {
+        let home = std::env::var("HOME").unwrap_or_default();
+        assert_eq!(expand_env("${HOME}/bin"), format!("{}/bin", home));
+        assert_eq!(expand_env("a${SKILLZ_SURELY_UNSET_VAR}b"), "ab");
+        assert_eq!(expand_env("$HOME ${unclosed"), "$HOME ${unclosed");
+    }
+}
+
+// ==================== NDJSON Output ====================
+
+mod ndjson {
+    use serde_json::json;
+    use skillz::registry::{ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use tempfile::TempDir;
+
+    const THREE_RESULTS: &[u8] = b"read line
+for i in 1 2 3; do echo \"{\\\"jsonrpc\\\":\\\"2.0\\\",\\\"result\\\":{\\\"n\\\":$i},\\\"id\\\":1}\"; done
+";
+
+    fn run(code: &[u8], ndjson: bool) -> serde_json::Value {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest = ToolManifest::new(
+            "streamer".to_string(),
+            "Streams".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        manifest.ndjson = ndjson;
+        let tool = registry.register_tool(manifest, code).unwrap();
+        ToolRuntime::new()
+            .unwrap()
+            .call_tool(&tool, json!({}))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_ndjson_collects_every_result() {
+        assert_eq!(
+            run(THREE_RESULTS, true),
+            json!([{"n": 1}, {"n": 2}, {"n": 3}])
+        );
+        assert_eq!(run(THREE_RESULTS, false), json!({"n": 3}));
+    }
+
+    /// Invalid UTF-8 is replaced rather than failing the call
+    #[test]
+    fn test_invalid_utf8_is_decoded_lossily() {
+        let output = run(b"read line\nprintf 'bad \\xff byte\\n'\n", false);
+        assert_eq!(output, json!("bad \u{fffd} byte"));
+    }
+}
+
+// ==================== Pipeline Step Retries ====================
+
+mod step_retries {
+    use serde_json::json;
+    use skillz::memory::Memory;
+    use skillz::pipeline::run_with_retries;
+    use skillz::registry::{PipelineStep, ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use tempfile::TempDir;
+    use tokio_util::sync::CancellationToken;
+
+    /// Fails (exit 1) on its first two calls and succeeds on the third
+    const FLAKY: &[u8] = br#"read line
+echo '{"jsonrpc":"2.0","method":"memory/increment","params":{"key":"calls"},"id":2}'
+read reply
+case "$reply" in *'"value":1'*|*'"value":2'*) exit 1;; esac
+echo '{"jsonrpc":"2.0","result":"ok","id":1}'
+"#;
+
+    fn step(tool: &str, retries: u32, timeout_secs: Option<u64>) -> PipelineStep {
+        PipelineStep {
+            name: None,
+            tool: tool.to_string(),
+            args: json!({}),
+            continue_on_error: false,
+            condition: None,
+            timeout_secs,
+            retries,
+            retry_delay_ms: 10,
+            foreach: None,
+            max_concurrency: None,
+            cache: None,
+        }
+    }
+
+    async fn setup(code: &[u8]) -> (ToolRuntime, ToolRegistry, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest =
+            ToolManifest::new("flaky".to_string(), "Flaky".to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        registry.register_tool(manifest, code).unwrap();
+        let memory = Memory::new(temp_dir.path()).await.unwrap();
+        let runtime = ToolRuntime::new().unwrap().with_memory(memory);
+        (runtime, registry, temp_dir)
+    }
+
+    async fn run(
+        runtime: &ToolRuntime,
+        registry: &ToolRegistry,
+        step: &PipelineStep,
+    ) -> skillz::pipeline::StepAttempts {
+        run_with_retries(step, &CancellationToken::new(), |token| {
+            let runtime = runtime.clone().with_cancellation(token);
+            async move {
+                runtime
+                    .call_tool_by_name(&step.tool, Some(json!({})), registry)
+                    .await
+            }
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_step_succeeds_on_third_attempt() {
+        let (runtime, registry, _dir) = setup(FLAKY).await;
+
+        let outcome = run(&runtime, &registry, &step("flaky", 2, None)).await;
+        assert_eq!(outcome.result.unwrap(), json!("ok"));
+        assert_eq!(outcome.attempts, 3);
+        assert_eq!(outcome.attempt_errors.len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_step_gives_up_after_retries() {
+        let (runtime, registry, _dir) = setup(FLAKY).await;
+
+        let outcome = run(&runtime, &registry, &step("flaky", 1, None)).await;
+        assert_eq!(outcome.result.unwrap()["exit_code"], json!(1));
+        assert_eq!(outcome.attempts, 2);
+        assert_eq!(outcome.attempt_errors, ["Script exited with code 1"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_step_times_out() {
+        let (runtime, registry, _dir) = setup(b"read line\nexec sleep 10\n").await;
+
+        let start = std::time::Instant::now();
+        let outcome = run(&runtime, &registry, &step("flaky", 0, Some(1))).await;
+        assert_eq!(
+            outcome.result.unwrap_err().to_string(),
+            "Timed out after 1s"
+        );
+        assert_eq!(outcome.attempts, 1);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_validate_step() {
+        assert!(step("flaky", 2, Some(5)).validate().is_ok());
+        assert!(step("flaky", 0, Some(0)).validate().is_err());
+        assert!(step("flaky", 11, None).validate().is_err());
+    }
+}
+
+// ==================== Platform Paths ====================
+
+mod platform {
+    use skillz::runtime::venv_executable;
+    use std::path::Path;
+
+    #[test]
+    fn test_venv_executable() {
+        let env = Path::new("env");
+        let expected = if cfg!(windows) {
+            env.join("Scripts").join("python.exe")
+        } else {
+            env.join("bin").join("python")
+        };
+        assert_eq!(venv_executable(env, "python"), expected);
+    }
+}
+
+// ==================== Pipeline Foreach ====================
+
+mod foreach {
+    use serde_json::json;
+    use skillz::pipeline::{collect_foreach, run_foreach, run_with_retries, PipelineExecutor};
+    use skillz::registry::{PipelineStep, ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+    use tokio_util::sync::CancellationToken;
+
+    /// Maps a doubling tool over [1, 2, 3] and collects the outputs in order
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_foreach_collects_outputs() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest = ToolManifest::new(
+            "double".to_string(),
+            "Doubles n".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        registry
+            .register_tool(
+                manifest,
+                br#"read line
+n=$(echo "$line" | sed -E 's/.*"n":([0-9]+).*/\1/')
+echo "{\"jsonrpc\":\"2.0\",\"result\":$((n * 2)),\"id\":1}"
+"#,
+            )
+            .unwrap();
+        let runtime = ToolRuntime::new().unwrap();
+
+        let step = PipelineStep {
+            name: Some("doubled".to_string()),
+            tool: "double".to_string(),
+            args: json!({"n": "$item"}),
+            continue_on_error: false,
+            condition: None,
+            timeout_secs: None,
+            retries: 0,
+            retry_delay_ms: 0,
+            foreach: Some("$prev".to_string()),
+            max_concurrency: Some(2),
+            cache: None,
+        };
+        step.validate().unwrap();
+
+        let step_results = HashMap::new();
+        let prev = json!([1, 2, 3]);
+        let items = PipelineExecutor::resolve_foreach(
+            step.foreach.as_deref().unwrap(),
+            &json!({}),
+            &step_results,
+            Some(&prev),
+        )
+        .unwrap();
+
+        let ct = CancellationToken::new();
+        let outcomes = run_foreach(items, 2, |(index, item)| {
+            let scope = PipelineExecutor::item_scope(&step_results, index, item);
+            let args =
+                PipelineExecutor::resolve_args(&step.args, &json!({}), &scope, None).unwrap();
+            let (runtime, registry, step, ct) = (&runtime, &registry, &step, &ct);
+            async move {
+                run_with_retries(step, ct, |token| {
+                    let runtime = runtime.clone().with_cancellation(token);
+                    let args = args.clone();
+                    async move {
+                        runtime
+                            .call_tool_by_name(&step.tool, Some(args), registry)
+                            .await
+                    }
+                })
+                .await
+            }
+        })
+        .await;
+
+        let collected = collect_foreach(outcomes);
+        assert_eq!(collected.result.unwrap(), json!([2, 4, 6]));
+        assert_eq!(collected.attempts, 3);
+    }
+}
+
+// ==================== On Error Steps ====================
+
+mod on_error {
+    use serde_json::json;
+    use skillz::memory::Memory;
+    use skillz::pipeline::{PipelineExecutor, StepResult};
+    use skillz::registry::{PipelineStep, ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn script(registry: &ToolRegistry, name: &str, code: &[u8]) {
+        let mut manifest = ToolManifest::new(name.to_string(), name.to_string(), ToolType::Script);
+        manifest.interpreter = Some("bash".to_string());
+        registry.register_tool(manifest, code).unwrap();
+    }
+
+    fn step(name: &str, tool: &str, args: serde_json::Value) -> PipelineStep {
+        PipelineStep {
+            name: Some(name.to_string()),
+            tool: tool.to_string(),
+            args,
+            continue_on_error: false,
+            condition: None,
+            timeout_secs: None,
+            retries: 0,
+            retry_delay_ms: 0,
+            foreach: None,
+            max_concurrency: None,
+            cache: None,
+        }
+    }
+
+    /// A failing step's error reaches the on_error step through $error, which
+    /// stores it in memory
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_on_error_step_stores_error_message() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let memory = Memory::new(temp_dir.path()).await.unwrap();
+        // A script whose interpreter is missing fails to start
+        let mut broken =
+            ToolManifest::new("broken".to_string(), "Broken".to_string(), ToolType::Script);
+        broken.interpreter = Some("skillz-missing-interpreter".to_string());
+        registry.register_tool(broken, b"").unwrap();
+        script(
+            &registry,
+            "record_failure",
+            br#"read -r line
+msg=$(echo "$line" | sed -E 's/.*"message":"(([^"\\]|\\.)*)".*/\1/')
+echo "{\"jsonrpc\":\"2.0\",\"method\":\"memory/set\",\"params\":{\"key\":\"last_error\",\"value\":\"$msg\"},\"id\":2}"
+read reply
+echo '{"jsonrpc":"2.0","result":"recorded","id":1}'
+"#,
+        );
+
+        let mut manifest = ToolManifest::new_pipeline(
+            "guarded".to_string(),
+            "Records its failures".to_string(),
+            vec![step("write", "broken", json!({}))],
+        );
+        manifest.on_error = vec![step(
+            "record",
+            "record_failure",
+            json!({"step": "$error.step", "message": "$error.message"}),
+        )];
+        let pipeline = registry.register_tool(manifest, &[]).unwrap();
+        assert_eq!(pipeline.on_error_steps().len(), 1);
+
+        let runtime = ToolRuntime::new().unwrap().with_memory(memory.clone());
+        let main_step = &pipeline.pipeline_steps()[0];
+        let error = runtime
+            .call_tool_by_name(&main_step.tool, Some(json!({})), &registry)
+            .await
+            .unwrap_err();
+        let failed = StepResult {
+            step_index: 0,
+            step_name: main_step.name.clone(),
+            tool: main_step.tool.clone(),
+            success: false,
+            output: json!(null),
+            error: Some(error.to_string()),
+            duration_ms: 0,
+            attempts: 1,
+            attempt_errors: Vec::new(),
+            cached: false,
+        };
+
+        let mut step_results = HashMap::new();
+        step_results.insert("error".to_string(), PipelineExecutor::error_info(&failed));
+        let handler = &pipeline.on_error_steps()[0];
+        let args =
+            PipelineExecutor::resolve_args(&handler.args, &json!({}), &step_results, None).unwrap();
+        assert_eq!(args["step"], json!("write"));
+
+        let result = runtime
+            .call_tool_by_name(&handler.tool, Some(args), &registry)
+            .await
+            .unwrap();
+        assert_eq!(result, json!("recorded"));
+
+        let stored = memory.get("record_failure", "last_error").await.unwrap();
+        assert_eq!(stored, Some(json!(failed.error.unwrap())));
+    }
+
+    #[test]
+    fn test_error_is_reserved_step_name() {
+        assert!(step("error", "broken", json!({})).validate().is_err());
+    }
+}
+
+// ==================== Pipeline Updates ====================
+
+mod pipeline_update {
+    use serde_json::json;
+    use skillz::pipeline::PipelineExecutor;
+    use skillz::registry::{PipelineStep, PipelineUpdate, ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn greet_step(greeting: &str) -> PipelineStep {
+        PipelineStep {
+            name: Some("greet".to_string()),
+            tool: "echo_args".to_string(),
+            args: json!({"greeting": greeting, "who": "$input.who"}),
+            continue_on_error: false,
+            condition: None,
+            timeout_secs: None,
+            retries: 0,
+            retry_delay_ms: 0,
+            foreach: None,
+            max_concurrency: None,
+            cache: None,
+        }
+    }
+
+    /// Runs the pipeline's single step the way the executor does
+    async fn run(registry: &ToolRegistry, name: &str, input: serde_json::Value) -> String {
+        let pipeline = registry.get_tool(name).unwrap();
+        let step = &pipeline.pipeline_steps()[0];
+        let args =
+            PipelineExecutor::resolve_args(&step.args, &input, &HashMap::new(), None).unwrap();
+        let output = ToolRuntime::new()
+            .unwrap()
+            .call_tool_by_name(&step.tool, Some(args), registry)
+            .await
+            .unwrap();
+        output.as_str().unwrap().to_string()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_updated_step_args_are_used_by_next_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest = ToolManifest::new(
+            "echo_args".to_string(),
+            "Greets".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        registry
+            .register_tool(
+                manifest,
+                br#"read -r line
+g=$(echo "$line" | sed -E 's/.*"greeting":"([^"]*)".*/\1/')
+w=$(echo "$line" | sed -E 's/.*"who":"([^"]*)".*/\1/')
+echo "{\"jsonrpc\":\"2.0\",\"result\":\"$g $w\",\"id\":1}"
+"#,
+            )
+            .unwrap();
+
+        let mut manifest = ToolManifest::new_pipeline(
+            "welcome".to_string(),
+            "Says hello".to_string(),
+            vec![greet_step("Helo")],
+        );
+        manifest.tags = vec!["greetings".to_string()];
+        let created = registry.register_tool(manifest, &[]).unwrap();
+        let input = json!({"who": "Ada"});
+        assert_eq!(run(&registry, "welcome", input.clone()).await, "Helo Ada");
+
+        let updated = registry
+            .update_pipeline(
+                "welcome",
+                PipelineUpdate {
+                    steps: Some(vec![greet_step("Hello")]),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(run(&registry, "welcome", input).await, "Hello Ada");
+
+        // Untouched fields are kept, the version is bumped and the old
+        // definition can be rolled back to
+        assert_eq!(updated.tags(), ["greetings".to_string()]);
+        assert_eq!(updated.description(), "Says hello");
+        assert_eq!(updated.manifest.created_at, created.manifest.created_at);
+        assert_eq!(updated.manifest.version, "1.0.1");
+        assert!(registry
+            .list_versions("welcome")
+            .unwrap()
+            .contains(&created.manifest.version));
+        registry
+            .rollback("welcome", &created.manifest.version)
+            .unwrap();
+        assert_eq!(
+            registry.get_tool("welcome").unwrap().pipeline_steps()[0].args["greeting"],
+            json!("Helo")
+        );
+    }
+
+    #[test]
+    fn test_update_rejects_non_pipelines_and_invalid_steps() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let script = ToolManifest::new("plain".to_string(), "A".to_string(), ToolType::Script);
+        registry.register_tool(script, b"echo 1").unwrap();
+        assert!(registry
+            .update_pipeline("plain", PipelineUpdate::default())
+            .is_err());
+        assert!(registry
+            .update_pipeline("missing", PipelineUpdate::default())
+            .is_err());
+
+        let pipeline =
+            ToolManifest::new_pipeline("p".to_string(), "P".to_string(), vec![greet_step("Hi")]);
+        registry.register_tool(pipeline, &[]).unwrap();
+        let mut bad = greet_step("Hi");
+        bad.retries = 1000;
+        assert!(registry
+            .update_pipeline(
+                "p",
+                PipelineUpdate {
+                    steps: Some(vec![bad]),
+                    ..Default::default()
+                },
+            )
+            .is_err());
+        assert_eq!(registry.get_tool("p").unwrap().manifest.version, "1.0.0");
+    }
+}
+
+// ==================== Pipeline Run Results ====================
+
+mod pipeline_run {
+    use serde_json::json;
+    use skillz::pipeline::{PipelineExecutor, PipelineRun, StepResult};
+    use skillz::registry::{PipelineStep, ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::ToolRuntime;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn double_step(name: &str, n: &str) -> PipelineStep {
+        PipelineStep {
+            name: Some(name.to_string()),
+            tool: "double".to_string(),
+            args: json!({"n": n}),
+            continue_on_error: false,
+            condition: None,
+            timeout_secs: None,
+            retries: 0,
+            retry_delay_ms: 0,
+            foreach: None,
+            max_concurrency: None,
+            cache: None,
+        }
+    }
+
+    /// Runs a two-step pipeline and checks the structured (format: "json") result
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_two_step_run_json_result() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest = ToolManifest::new(
+            "double".to_string(),
+            "Doubles n".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        registry
+            .register_tool(
+                manifest,
+                br#"read line
+n=$(echo "$line" | sed -E 's/.*"n":([0-9]+).*/\1/')
+echo "{\"jsonrpc\":\"2.0\",\"result\":$((n * 2)),\"id\":1}"
+"#,
+            )
+            .unwrap();
+        let pipeline = registry
+            .register_tool(
+                ToolManifest::new_pipeline(
+                    "quadruple".to_string(),
+                    "Doubles twice".to_string(),
+                    vec![
+                        double_step("first", "$input.n"),
+                        double_step("second", "$prev"),
+                    ],
+                ),
+                &[],
+            )
+            .unwrap();
+
+        let runtime = ToolRuntime::new().unwrap();
+        let input = json!({"n": 3});
+        let mut step_results = HashMap::new();
+        let mut prev = None;
+        let mut steps = Vec::new();
+        for (i, step) in pipeline.pipeline_steps().iter().enumerate() {
+            let args =
+                PipelineExecutor::resolve_args(&step.args, &input, &step_results, prev.as_ref())
+                    .unwrap();
+            let output = runtime
+                .call_tool_by_name(&step.tool, Some(args), &registry)
+                .await
+                .unwrap();
+            step_results.insert(step.name.clone().unwrap(), output.clone());
+            prev = Some(output.clone());
+            steps.push(StepResult {
+                step_index: i,
+                step_name: step.name.clone(),
+                tool: step.tool.clone(),
+                success: true,
+                output,
+                error: None,
+                duration_ms: 0,
+                attempts: 1,
+                attempt_errors: Vec::new(),
+                cached: false,
+            });
+        }
+        let run = PipelineRun {
+            pipeline: pipeline.name().to_string(),
+            success: true,
+            cancelled: false,
+            duration_ms: 0,
+            steps,
+            on_error: Vec::new(),
+            debug_run: None,
+        };
+        assert_eq!(run.final_output(), Some(&json!(12)));
+
+        let structured: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string_pretty(&run).unwrap()).unwrap();
+        assert_eq!(structured["pipeline"], json!("quadruple"));
+        assert_eq!(structured["success"], json!(true));
+        assert_eq!(structured["steps"][0]["step_name"], json!("first"));
+        assert_eq!(structured["steps"][0]["output"], json!(6));
+        assert_eq!(structured["steps"][1]["output"], json!(12));
+        assert!(structured.get("on_error").is_none());
+
+        let markdown = run.to_markdown(false);
+        assert!(markdown.contains("## ✅ Pipeline 'quadruple' Completed"));
+        assert!(markdown.contains("**✅ second** (double)"));
+    }
+}
+
+// ==================== File Resources ====================
+
+mod file_resources {
+    use base64::Engine as _;
+    use skillz::runtime::read_file_resource;
+    use tempfile::TempDir;
+
+    const PNG_HEADER: &[u8] = &[
+        0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0, 0, 0x0d,
+    ];
+
+    fn workspace() -> (TempDir, Vec<String>) {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("assets")).unwrap();
+        std::fs::write(dir.path().join("notes.md"), "# Notes\n").unwrap();
+        std::fs::write(dir.path().join("assets/logo.png"), PNG_HEADER).unwrap();
+        std::fs::write(dir.path().join("data.bin"), [0u8, 1, 2, 255]).unwrap();
+        let roots = vec![dir.path().to_string_lossy().into_owned()];
+        (dir, roots)
+    }
+
+    #[test]
+    fn test_text_file_is_returned_as_text() {
+        let (_dir, roots) = workspace();
+        let content = read_file_resource("skillz://files/notes.md", &roots).unwrap();
+        assert_eq!(content.mime_type.as_deref(), Some("text/markdown"));
+        assert_eq!(content.text.as_deref(), Some("# Notes\n"));
+        assert!(content.blob.is_none());
+    }
+
+    #[test]
+    fn test_binary_file_is_returned_as_base64_blob() {
+        let (dir, roots) = workspace();
+        let content = read_file_resource("skillz://files/assets/logo.png", &roots).unwrap();
+        assert_eq!(content.mime_type.as_deref(), Some("image/png"));
+        assert!(content.text.is_none());
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(content.blob.unwrap())
+            .unwrap();
+        assert_eq!(decoded, PNG_HEADER);
+
+        // Unknown extensions are sniffed; absolute paths inside a root work too
+        let abs = format!("skillz://files/{}", dir.path().join("data.bin").display());
+        let content = read_file_resource(&abs, &roots).unwrap();
+        assert_eq!(
+            content.mime_type.as_deref(),
+            Some("application/octet-stream")
+        );
+        assert!(content.blob.is_some());
+    }
+
+    #[test]
+    fn test_paths_outside_roots_are_refused() {
+        let (dir, roots) = workspace();
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+
+        let absolute = format!(
+            "skillz://files/{}",
+            outside.path().join("secret.txt").display()
+        );
+        let err = read_file_resource(&absolute, &roots).unwrap_err();
+        assert!(err.to_string().contains("outside the workspace roots"));
+        // A missing file outside the roots gets the same answer
+        let missing = format!(
+            "skillz://files/{}",
+            outside.path().join("missing.txt").display()
+        );
+        let err = read_file_resource(&missing, &roots).unwrap_err();
+        assert!(err.to_string().contains("outside the workspace roots"));
+        let err = read_file_resource("skillz://files/../nowhere/missing.txt", &roots).unwrap_err();
+        assert!(err.to_string().contains("outside the workspace roots"));
+
+        let relative = format!(
+            "skillz://files/../{}/secret.txt",
+            outside.path().file_name().unwrap().to_string_lossy()
+        );
+        assert!(read_file_resource(&relative, &roots).is_err());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(
+                outside.path().join("secret.txt"),
+                dir.path().join("link.txt"),
+            )
+            .unwrap();
+            let err = read_file_resource("skillz://files/link.txt", &roots).unwrap_err();
+            assert!(err.to_string().contains("outside the workspace roots"));
+        }
+
+        assert!(read_file_resource("skillz://files/missing.txt", &roots).is_err());
+        assert!(read_file_resource("skillz://files/assets", &roots).is_err());
+    }
+}
+
+// ==================== Pipeline Step Progress ====================
+
+mod step_progress {
+    use serde_json::json;
+    use skillz::pipeline::{StepProgress, StepResult};
+    use skillz::registry::{PipelineStep, PipelineUpdate, ToolManifest, ToolRegistry, ToolType};
+    use skillz::runtime::{LoggingHandler, ProgressHandler, ToolRuntime};
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    type Update = (u64, u64, Option<String>, Option<String>);
+
+    fn step(name: Option<&str>, tool: &str) -> PipelineStep {
+        PipelineStep {
+            name: name.map(str::to_string),
+            tool: tool.to_string(),
+            args: json!({}),
+            continue_on_error: false,
+            condition: None,
+            timeout_secs: None,
+            retries: 0,
+            retry_delay_ms: 0,
+            foreach: None,
+            max_concurrency: None,
+            cache: None,
+        }
+    }
+
+    fn result(index: usize, step: &PipelineStep, success: bool, attempts: u32) -> StepResult {
+        StepResult {
+            step_index: index,
+            step_name: step.name.clone(),
+            tool: step.tool.clone(),
+            success,
+            output: json!(null),
+            error: None,
+            duration_ms: 0,
+            attempts,
+            attempt_errors: Vec::new(),
+            cached: false,
+        }
+    }
+
+    fn capture_progress() -> (ProgressHandler, Arc<Mutex<Vec<Update>>>) {
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let sink = updates.clone();
+        let handler: ProgressHandler = Arc::new(move |current, total, message, token| {
+            sink.lock().unwrap().push((current, total, message, token));
+            Box::pin(async {})
+        });
+        (handler, updates)
+    }
+
+    fn capture_logs() -> (LoggingHandler, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let sink = logs.clone();
+        let handler: LoggingHandler = Arc::new(move |_level, _message, data| {
+            sink.lock().unwrap().push(data.unwrap_or(json!(null)));
+            Box::pin(async {})
+        });
+        (handler, logs)
+    }
+
+    /// N steps produce 2N progress updates, in order, under the caller's token
+    #[tokio::test]
+    async fn test_steps_report_before_and_after() {
+        let (handler, updates) = capture_progress();
+        let steps = [
+            step(Some("fetch"), "http_get"),
+            step(None, "json_parse"),
+            step(Some("report"), "format_report"),
+        ];
+        let progress = StepProgress::new(Some(handler), None, Some("tok-1".to_string()), 3);
+
+        for (i, s) in steps.iter().enumerate() {
+            progress.step_started(i, s).await;
+            let (success, attempts) = match i {
+                1 => (true, 0),
+                2 => (false, 1),
+                _ => (true, 1),
+            };
+            progress
+                .step_finished(&result(i, s, success, attempts))
+                .await;
+        }
+
+        let updates = updates.lock().unwrap().clone();
+        let token = Some("tok-1".to_string());
+        let expected: Vec<Update> = vec![
+            (0, 3, Some("running fetch (http_get)".into()), token.clone()),
+            (1, 3, Some("fetch (http_get) done".into()), token.clone()),
+            (
+                1,
+                3,
+                Some("running step 2 (json_parse)".into()),
+                token.clone(),
+            ),
+            (
+                2,
+                3,
+                Some("step 2 (json_parse) skipped".into()),
+                token.clone(),
+            ),
+            (
+                2,
+                3,
+                Some("running report (format_report)".into()),
+                token.clone(),
+            ),
+            (3, 3, Some("report (format_report) failed".into()), token),
+        ];
+        assert_eq!(updates, expected);
+    }
+
+    /// Script logs reach the client tagged with the step that wrote them
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_step_logs_are_tagged() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        let mut manifest = ToolManifest::new(
+            "chatty".to_string(),
+            "Logs and returns".to_string(),
+            ToolType::Script,
+        );
+        manifest.interpreter = Some("bash".to_string());
+        registry
+            .register_tool(
+                manifest,
+                br#"read line
+echo '{"jsonrpc":"2.0","method":"log","params":{"level":"info","message":"fetching"}}'
+echo '{"jsonrpc":"2.0","result":"ok","id":1}'
+"#,
+            )
+            .unwrap();
+
+        let (handler, logs) = capture_logs();
+        let progress = StepProgress::new(None, Some(handler), None, 1);
+        let fetch = step(Some("fetch"), "chatty");
+        let mut runtime = ToolRuntime::new().unwrap();
+        runtime.set_logging_handler(progress.step_logger(0, &fetch));
+
+        let output = runtime
+            .call_tool_by_name("chatty", None, &registry)
+            .await
+            .unwrap();
+        assert_eq!(output["result"], json!("ok"));
+        assert_eq!(
+            logs.lock().unwrap().clone(),
+            vec![json!({"message": "fetching", "step": "fetch (chatty)"})]
+        );
+    }
+
+    /// A reporter without handlers (a quiet pipeline) forwards nothing
+    #[tokio::test]
+    async fn test_quiet_reports_nothing() {
+        let progress = StepProgress::default();
+        let fetch = step(Some("fetch"), "http_get");
+        assert!(progress.step_logger(0, &fetch).is_none());
+        progress.step_started(0, &fetch).await;
+        progress.step_finished(&result(0, &fetch, true, 1)).await;
+    }
+
+    #[test]
+    fn test_quiet_is_saved_with_the_pipeline() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ToolRegistry::new(temp_dir.path().to_path_buf());
+        registry
+            .register_tool(
+                ToolManifest::new_pipeline(
+                    "steps".to_string(),
+                    "Runs a step".to_string(),
+                    vec![step(Some("fetch"), "http_get")],
+                ),
+                &[],
+            )
+            .unwrap();
+        assert!(!registry.get_tool("steps").unwrap().manifest.quiet);
+
+        let updated = registry
+            .update_pipeline(
+                "steps",
+                PipelineUpdate {
+                    quiet: Some(true),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(updated.manifest.quiet);
+
+        let reloaded = ToolRegistry::new(temp_dir.path().to_path_buf());
+        assert!(reloaded.get_tool("steps").unwrap().manifest.quiet);
+    }
+}